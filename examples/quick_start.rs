@@ -158,12 +158,32 @@ async fn handle_message_types() -> claude_code_sdk::Result<()> {
                                 println!("    Is Error: {}", is_error);
                             }
                         }
+                        ContentBlock::Image(_) => {
+                            println!("  Block {}: Image", i);
+                        }
+                        ContentBlock::Thinking(thinking_block) => {
+                            println!("  Block {}: Thinking - {}", i, thinking_block.thinking);
+                        }
+                        ContentBlock::RedactedThinking(_) => {
+                            println!("  Block {}: Redacted Thinking", i);
+                        }
                     }
                 }
             }
             Message::System(msg) => {
                 println!("System message: {}", msg.content);
             }
+            Message::SdkInfo(info) => {
+                println!(
+                    "SDK {} on {} (CLI version: {})",
+                    info.sdk_version,
+                    info.platform,
+                    info.cli_version.as_deref().unwrap_or("unknown")
+                );
+            }
+            Message::StreamEvent(event) => {
+                println!("Stream event: {}", event.event_type().unwrap_or("unknown"));
+            }
             Message::Result(result) => {
                 println!("Result message (ID: {})", result.id);
                 if let Some(exit_code) = result.exit_code {
@@ -221,7 +241,7 @@ async fn error_handling_example() {
                             eprintln!("Process failed (exit code {}): {}", exit_code, stderr);
                             break;
                         }
-                        ClaudeSDKError::CLIJSONDecode { message } => {
+                        ClaudeSDKError::CLIJSONDecode { message, .. } => {
                             eprintln!("JSON decode error: {}", message);
                             break;
                         }
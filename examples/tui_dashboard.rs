@@ -0,0 +1,76 @@
+//! An interactive terminal dashboard for a single query.
+//!
+//! Run with `cargo run --example tui_dashboard --features tui -- "your prompt"`.
+//! Press `q`, `Esc` or Ctrl-C to stop the query and quit.
+
+use claude_code_sdk::progress::progress_channel;
+use claude_code_sdk::tui::{Dashboard, TerminalGuard};
+use claude_code_sdk::{query_with_handle, ClaudeCodeOptions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let prompt = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Summarize the files in this directory".to_string());
+
+    let (progress, mut progress_events) = progress_channel();
+    let options = ClaudeCodeOptions::new()
+        .with_max_turns(5)
+        .with_include_partial_messages(true)
+        .with_progress_callback(progress);
+
+    let mut dashboard = Dashboard::new(prompt.clone());
+    let mut handle = query_with_handle(&prompt, Some(options)).await?;
+
+    // Restores the terminal on every exit path, including panics
+    let mut terminal = TerminalGuard::new();
+    let mut ticks = tokio::time::interval(Duration::from_millis(50));
+
+    loop {
+        terminal.draw(|frame| dashboard.render(frame))?;
+
+        tokio::select! {
+            item = handle.next(), if !dashboard.is_finished() => match item {
+                Some(item) => dashboard.push_result(&item),
+                None => break,
+            },
+            Some(event) = progress_events.next() => dashboard.push_progress(&event),
+            _ = ticks.tick() => {
+                if quit_requested()? {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Stop the CLI if the user quit early, and wait for it to exit
+    handle.close().await?;
+    drop(terminal);
+
+    let usage = handle.usage();
+    println!(
+        "{} tokens in, {} tokens out, ${:.4}",
+        usage.tokens_input, usage.tokens_output, usage.cost_usd
+    );
+    Ok(())
+}
+
+/// Drain pending key events, returning whether the user asked to quit.
+fn quit_requested() -> std::io::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
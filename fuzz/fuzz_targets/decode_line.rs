@@ -0,0 +1,11 @@
+//! One raw line of CLI output, as the transport decodes it.
+
+#![no_main]
+
+use claude_code_sdk::protocol::{decode_line, decode_utf8_lossy};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (line, _) = decode_utf8_lossy(data);
+    let _ = decode_line(&line, None, None);
+});
@@ -0,0 +1,15 @@
+//! `Message` and `ContentBlock` deserialization on their own.
+
+#![no_main]
+
+use claude_code_sdk::{ContentBlock, Message};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = serde_json::from_slice::<Message>(data) {
+        // Whatever decodes must encode again
+        serde_json::to_vec(&message).unwrap();
+    }
+    let _ = serde_json::from_slice::<ContentBlock>(data);
+    let _ = serde_json::from_slice::<Vec<ContentBlock>>(data);
+});
@@ -0,0 +1,24 @@
+//! Whole CLI output split into lines and decoded in each output format.
+
+#![no_main]
+
+use claude_code_sdk::output::OutputFormat;
+use claude_code_sdk::protocol::decode_lines;
+use claude_code_sdk::ClaudeCodeOptions;
+use futures::io::Cursor;
+use futures::stream::StreamExt;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, output)) = data.split_first() else {
+        return;
+    };
+    let format = match selector % 3 {
+        0 => OutputFormat::StreamJson,
+        1 => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    let options = ClaudeCodeOptions::new().with_output_format(format);
+    let messages = decode_lines(Cursor::new(output), &options);
+    let _ = futures::executor::block_on(messages.collect::<Vec<_>>());
+});
@@ -0,0 +1,309 @@
+//! Usage analytics: a JSONL log of completed queries and aggregates over it.
+
+use crate::error::Result;
+use crate::run_id::RunId;
+use crate::types::ResultMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// One completed query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Completion time in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub run_id: Option<RunId>,
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    pub cost_usd: f64,
+    pub tokens_input: i64,
+    pub tokens_output: i64,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_first_token_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_second: Option<f64>,
+}
+
+impl UsageRecord {
+    pub fn from_result(
+        result: &ResultMessage,
+        run_id: Option<RunId>,
+        model: Option<String>,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            run_id,
+            session_id: result.session_id.clone(),
+            model,
+            cost_usd: result.cost_usd.unwrap_or(0.0),
+            tokens_input: result.tokens_input.unwrap_or(0).into(),
+            tokens_output: result.tokens_output.unwrap_or(0).into(),
+            duration_ms: duration.as_millis() as u64,
+            exit_code: result.exit_code,
+            success: result.exit_code.unwrap_or(0) == 0 && result.canceled != Some(true),
+            time_to_first_token_ms: None,
+            tokens_per_second: None,
+        }
+    }
+
+    /// Add the latency figures of `stats`.
+    pub fn with_stats(mut self, stats: &StreamStats) -> Self {
+        self.time_to_first_token_ms = stats
+            .time_to_first_token
+            .map(|ttft| ttft.as_millis() as u64);
+        self.tokens_per_second = stats.tokens_per_second();
+        self
+    }
+
+    /// The UTC date of the record as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_from_days((self.timestamp / 86_400) as i64);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Totals over a set of usage records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageAggregate {
+    pub queries: u64,
+    pub failures: u64,
+    pub cost_usd: f64,
+    pub tokens_input: i64,
+    pub tokens_output: i64,
+    pub duration_ms: u64,
+    /// Queries with a recorded time to first token.
+    #[serde(default)]
+    pub timed_queries: u64,
+    #[serde(default)]
+    pub time_to_first_token_ms: u64,
+    /// Queries with a recorded token rate.
+    #[serde(default)]
+    pub rated_queries: u64,
+    #[serde(default)]
+    pub tokens_per_second: f64,
+}
+
+impl UsageAggregate {
+    /// The mean time to first token over the timed queries.
+    pub fn mean_time_to_first_token(&self) -> Option<Duration> {
+        (self.timed_queries > 0)
+            .then(|| Duration::from_millis(self.time_to_first_token_ms / self.timed_queries))
+    }
+
+    /// The mean output token rate over the rated queries.
+    pub fn mean_tokens_per_second(&self) -> Option<f64> {
+        (self.rated_queries > 0).then(|| self.tokens_per_second / self.rated_queries as f64)
+    }
+
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.queries += 1;
+        if !record.success {
+            self.failures += 1;
+        }
+        self.cost_usd += record.cost_usd;
+        self.tokens_input += record.tokens_input;
+        self.tokens_output += record.tokens_output;
+        self.duration_ms += record.duration_ms;
+        if let Some(ttft) = record.time_to_first_token_ms {
+            self.timed_queries += 1;
+            self.time_to_first_token_ms += ttft;
+        }
+        if let Some(rate) = record.tokens_per_second {
+            self.rated_queries += 1;
+            self.tokens_per_second += rate;
+        }
+    }
+}
+
+/// Latency of one run, for monitoring model performance.
+///
+/// The CLI reports whole messages, so the first token is taken to arrive
+/// with the first assistant message.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamStats {
+    /// From the start of the run to the first assistant message.
+    pub time_to_first_token: Option<Duration>,
+    /// From the start of the run to its result; zero until it completes.
+    pub duration: Duration,
+    pub tokens_output: i64,
+}
+
+impl StreamStats {
+    /// Output tokens per second of generation, i.e. after the first token.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let generation = self
+            .duration
+            .checked_sub(self.time_to_first_token.unwrap_or_default())?;
+        (self.tokens_output > 0 && !generation.is_zero())
+            .then(|| self.tokens_output as f64 / generation.as_secs_f64())
+    }
+}
+
+/// Aggregate records by UTC date.
+pub fn daily(records: &[UsageRecord]) -> BTreeMap<String, UsageAggregate> {
+    aggregate_by(records, UsageRecord::date)
+}
+
+/// Aggregate records by model, with `default` for records without one.
+pub fn by_model(records: &[UsageRecord]) -> BTreeMap<String, UsageAggregate> {
+    aggregate_by(records, |record| {
+        record
+            .model
+            .clone()
+            .unwrap_or_else(|| "default".to_string())
+    })
+}
+
+fn aggregate_by<F>(records: &[UsageRecord], key: F) -> BTreeMap<String, UsageAggregate>
+where
+    F: Fn(&UsageRecord) -> String,
+{
+    let mut aggregates: BTreeMap<String, UsageAggregate> = BTreeMap::new();
+    for record in records {
+        aggregates.entry(key(record)).or_default().add(record);
+    }
+    aggregates
+}
+
+/// An append-only JSONL log of [`UsageRecord`]s with size based rotation.
+///
+/// When the log grows past the size limit it is renamed to `<path>.1`,
+/// shifting older files up to `<path>.<max_files>`, which is deleted.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::analytics::{self, UsageLog};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// # fn main() -> claude_code_sdk::Result<()> {
+/// let log = UsageLog::new("usage.jsonl");
+/// let options = ClaudeCodeOptions::new().with_usage_log(log.clone());
+/// // ... run queries ...
+/// for (date, usage) in analytics::daily(&log.records()?) {
+///     println!("{}: {} queries, ${:.2}", date, usage.queries, usage.cost_usd);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UsageLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    lock: Arc<Mutex<()>>,
+}
+
+impl UsageLog {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Rotate after `max_bytes`, keeping at most `max_files` rotated files.
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &UsageRecord) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size >= self.max_bytes {
+            self.rotate()?;
+        }
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// All records, oldest rotated file first.
+    ///
+    /// Lines that cannot be parsed, such as one truncated by a crash, are
+    /// skipped.
+    pub fn records(&self) -> Result<Vec<UsageRecord>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut records = Vec::new();
+        for index in (0..=self.max_files).rev() {
+            let file = match fs::File::open(self.rotated_path(index)) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for line in BufReader::new(file).lines() {
+                if let Ok(record) = serde_json::from_str(&line?) {
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&self) -> Result<()> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        let _ = fs::remove_file(self.rotated_path(self.max_files));
+        for index in (0..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,69 @@
+//! Notes attached to the messages of a run.
+//!
+//! Reviewers and graders mark up a run through
+//! [`QueryHandle::annotate`](crate::QueryHandle::annotate): each
+//! [`Annotation`] is a key and a JSON value on one message of the
+//! transcript. A [`SessionArchive`](crate::archive::SessionArchive) stores
+//! the annotations next to the transcript, and exported
+//! [dataset](crate::dataset) examples carry them along.
+
+use crate::compression;
+use crate::error::{ClaudeSDKError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A key and value attached to one message of a transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// The position of the message in the transcript, counting from 0.
+    /// Notices the SDK adds to the stream itself are not counted.
+    pub message_seq: usize,
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+impl Annotation {
+    pub fn new<K: Into<String>, V: Into<serde_json::Value>>(
+        message_seq: usize,
+        key: K,
+        value: V,
+    ) -> Self {
+        Self {
+            message_seq,
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Write `annotations` as NDJSON, one annotation per line.
+pub fn to_ndjson(annotations: &[Annotation]) -> Result<String> {
+    let mut ndjson = String::new();
+    for annotation in annotations {
+        ndjson.push_str(&serde_json::to_string(annotation)?);
+        ndjson.push('\n');
+    }
+    Ok(ndjson)
+}
+
+/// Parse annotations written by [`to_ndjson`]. Blank lines are skipped.
+pub fn from_ndjson(ndjson: &str) -> Result<Vec<Annotation>> {
+    ndjson
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| ClaudeSDKError::cli_json_decode(format!("Line {}: {}", number + 1, e)))
+        })
+        .collect()
+}
+
+/// Read an annotations file, decompressing it if it is gzip or zstd
+/// compressed, see [`crate::compression`].
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Annotation>> {
+    let ndjson = String::from_utf8(compression::decompress(fs::read(path)?)?)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    from_ndjson(&ndjson)
+}
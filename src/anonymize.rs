@@ -0,0 +1,217 @@
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::{ContentBlock, Message};
+use regex::Regex;
+use std::env;
+use std::path::PathBuf;
+
+/// Rewrites sensitive text before a transcript leaves the machine.
+pub trait Scrubber: Send + Sync {
+    fn scrub(&self, text: &str) -> String;
+}
+
+impl<F> Scrubber for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn scrub(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Replaces every match of a regular expression.
+#[derive(Debug, Clone)]
+pub struct RegexScrubber {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RegexScrubber {
+    /// `replacement` may refer to capture groups, e.g. `${1}=<redacted>`.
+    pub fn new<S: Into<String>>(pattern: &str, replacement: S) -> Result<Self> {
+        let pattern = Regex::new(pattern).map_err(|e| {
+            ClaudeSDKError::invalid_options(format!("Invalid scrubber pattern: {}", e))
+        })?;
+        Ok(Self {
+            pattern,
+            replacement: replacement.into(),
+        })
+    }
+}
+
+impl Scrubber for RegexScrubber {
+    fn scrub(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, self.replacement.as_str())
+            .into_owned()
+    }
+}
+
+/// Patterns for credentials that commonly end up in tool output.
+const SECRET_PATTERNS: &[(&str, &str)] = &[
+    (
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+        "<redacted:private-key>",
+    ),
+    (r"sk-ant-[A-Za-z0-9_-]{10,}", "<redacted:anthropic-key>"),
+    (r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", "<redacted:aws-key>"),
+    (r"\bgh[pousr]_[A-Za-z0-9]{36,}\b", "<redacted:github-token>"),
+    (r"\bxox[abprs]-[A-Za-z0-9-]{10,}", "<redacted:slack-token>"),
+    (
+        r"(?i)\b(bearer)\s+[A-Za-z0-9._~+/-]{8,}=*",
+        "${1} <redacted>",
+    ),
+    (
+        r#"(?i)\b([a-z0-9_]*(?:api[_-]?key|secret|token|password|passwd)[a-z0-9_]*)(\s*[:=]\s*["']?)[^\s"']{8,}"#,
+        "${1}${2}<redacted>",
+    ),
+];
+
+/// Strips file paths, usernames and secrets from transcripts so agent runs
+/// can be shared for debugging or submitted as telemetry.
+///
+/// ```rust
+/// use claude_code_sdk::anonymize::Anonymizer;
+///
+/// let anonymizer = Anonymizer::new()
+///     .with_path("/srv/acme-checkout", "<workspace>")
+///     .with_scrubber(|text: &str| text.replace("Acme Corp", "<company>"));
+///
+/// assert_eq!(
+///     anonymizer.scrub_text("Acme Corp build in /srv/acme-checkout/src failed"),
+///     "<company> build in <workspace>/src failed"
+/// );
+/// ```
+pub struct Anonymizer {
+    scrubbers: Vec<Box<dyn Scrubber>>,
+}
+
+impl Default for Anonymizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Anonymizer {
+    /// An anonymizer with the built-in secret, home directory and username
+    /// scrubbers.
+    pub fn new() -> Self {
+        let mut anonymizer = Self::empty();
+        for (pattern, replacement) in SECRET_PATTERNS {
+            let scrubber = RegexScrubber::new(pattern, *replacement)
+                .expect("built-in secret patterns are valid");
+            anonymizer = anonymizer.with_scrubber(scrubber);
+        }
+
+        if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+            let home = home.to_string_lossy().into_owned();
+            if home.len() > 1 {
+                anonymizer = anonymizer.with_path(home, "~");
+            }
+        }
+
+        // Other users' home directories
+        anonymizer = anonymizer.with_scrubber(
+            RegexScrubber::new(
+                r"(/home/|/Users/|[A-Za-z]:\\Users\\)[^/\\\s]+",
+                "${1}<user>",
+            )
+            .expect("built-in home directory pattern is valid"),
+        );
+
+        if let Ok(user) = env::var("USER").or_else(|_| env::var("USERNAME")) {
+            if user.len() > 2 {
+                let pattern = format!(r"\b{}\b", regex::escape(&user));
+                if let Ok(scrubber) = RegexScrubber::new(&pattern, "<user>") {
+                    anonymizer = anonymizer.with_scrubber(scrubber);
+                }
+            }
+        }
+
+        anonymizer
+    }
+
+    /// An anonymizer without any scrubbers.
+    pub fn empty() -> Self {
+        Self {
+            scrubbers: Vec::new(),
+        }
+    }
+
+    pub fn with_scrubber<S: Scrubber + 'static>(mut self, scrubber: S) -> Self {
+        self.scrubbers.push(Box::new(scrubber));
+        self
+    }
+
+    /// Replace a path prefix, e.g. a checkout directory, with a placeholder.
+    pub fn with_path<P: Into<String>, R: Into<String>>(self, path: P, replacement: R) -> Self {
+        let path = path.into();
+        let replacement = replacement.into();
+        self.with_scrubber(move |text: &str| text.replace(&path, &replacement))
+    }
+
+    pub fn scrub_text(&self, text: &str) -> String {
+        self.scrubbers
+            .iter()
+            .fold(text.to_string(), |text, scrubber| scrubber.scrub(&text))
+    }
+
+    pub fn scrub_message(&self, message: &Message) -> Message {
+        let mut message = message.clone();
+        match &mut message {
+            Message::User(msg) => self.scrub_blocks(&mut msg.content),
+            Message::Assistant(msg) => self.scrub_blocks(&mut msg.content),
+            Message::System(msg) => msg.content = self.scrub_text(&msg.content),
+            Message::Result(msg) => {
+                if let Some(content) = &msg.content {
+                    msg.content = Some(self.scrub_text(content));
+                }
+            }
+            Message::SdkInfo(info) => {
+                let scrub_path =
+                    |path: &PathBuf| PathBuf::from(self.scrub_text(&path.to_string_lossy()));
+                info.cli_path = info.cli_path.as_ref().map(scrub_path);
+                info.options.cwd = info.options.cwd.as_ref().map(scrub_path);
+                info.options.add_dirs = info.options.add_dirs.iter().map(scrub_path).collect();
+            }
+            Message::StreamEvent(event) => self.scrub_value(&mut event.event),
+        }
+        message
+    }
+
+    pub fn scrub_transcript(&self, messages: &[Message]) -> Vec<Message> {
+        messages.iter().map(|m| self.scrub_message(m)).collect()
+    }
+
+    fn scrub_blocks(&self, blocks: &mut [ContentBlock]) {
+        for block in blocks {
+            match block {
+                ContentBlock::Text(text) => text.text = self.scrub_text(&text.text),
+                ContentBlock::ToolUse(tool_use) => self.scrub_value(&mut tool_use.input),
+                ContentBlock::ToolResult(result) => {
+                    if let Some(content) = &result.content {
+                        result.content = Some(self.scrub_text(content));
+                    }
+                }
+                ContentBlock::Thinking(thinking) => {
+                    thinking.thinking = self.scrub_text(&thinking.thinking)
+                }
+                // Encrypted by the API, or not text
+                ContentBlock::RedactedThinking(_) | ContentBlock::Image(_) => {}
+            }
+        }
+    }
+
+    pub(crate) fn scrub_value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => *s = self.scrub_text(s),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.scrub_value(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.scrub_value(v)),
+            _ => {}
+        }
+    }
+}
+
+/// Anonymize a transcript with the default scrubbers.
+pub fn anonymize(messages: &[Message]) -> Vec<Message> {
+    Anonymizer::new().scrub_transcript(messages)
+}
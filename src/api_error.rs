@@ -0,0 +1,163 @@
+use crate::error::ClaudeSDKError;
+use crate::types::{ContentBlock, Message};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The type of an Anthropic API error surfaced by the CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorKind {
+    Overloaded,
+    RateLimit,
+    Authentication,
+    Permission,
+    InvalidRequest,
+    Server,
+    Other(String),
+}
+
+impl ApiErrorKind {
+    fn from_type(error_type: &str) -> Self {
+        match error_type {
+            "overloaded_error" => Self::Overloaded,
+            "rate_limit_error" => Self::RateLimit,
+            "authentication_error" => Self::Authentication,
+            "permission_error" => Self::Permission,
+            "invalid_request_error" => Self::InvalidRequest,
+            "api_error" => Self::Server,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    fn from_status(status: u16) -> Self {
+        match status {
+            529 => Self::Overloaded,
+            429 => Self::RateLimit,
+            401 => Self::Authentication,
+            403 => Self::Permission,
+            400 => Self::InvalidRequest,
+            500..=599 => Self::Server,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// The API's name for this error type, e.g. `overloaded_error`.
+    pub fn error_type(&self) -> &str {
+        match self {
+            Self::Overloaded => "overloaded_error",
+            Self::RateLimit => "rate_limit_error",
+            Self::Authentication => "authentication_error",
+            Self::Permission => "permission_error",
+            Self::InvalidRequest => "invalid_request_error",
+            Self::Server => "api_error",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Whether a request failing with this error may succeed when retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Overloaded | Self::RateLimit | Self::Server)
+    }
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overloaded => f.write_str("overloaded"),
+            Self::RateLimit => f.write_str("rate limited"),
+            Self::Authentication => f.write_str("authentication failed"),
+            Self::Permission => f.write_str("permission denied"),
+            Self::InvalidRequest => f.write_str("invalid request"),
+            Self::Server => f.write_str("server error"),
+            Self::Other(other) => f.write_str(other),
+        }
+    }
+}
+
+/// Parse an API error from an `{"type": "error", "error": {...}}` value.
+pub fn from_error_value(value: &serde_json::Value) -> Option<ClaudeSDKError> {
+    if value.get("type")?.as_str()? != "error" {
+        return None;
+    }
+    let error = value.get("error")?;
+    let kind = ApiErrorKind::from_type(error.get("type")?.as_str()?);
+    let message = error
+        .get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let retry_after = value
+        .get("retry_after")
+        .or_else(|| error.get("retry_after"))
+        .and_then(|r| r.as_f64())
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .or_else(|| retry_after_from_text(&message));
+
+    Some(ClaudeSDKError::Api {
+        kind,
+        message,
+        retry_after,
+    })
+}
+
+/// Parse an error the CLI reports as text, e.g.
+/// `API Error: 529 {"type":"error","error":{"type":"overloaded_error",...}}`.
+pub fn from_text(text: &str) -> Option<ClaudeSDKError> {
+    let rest = text.trim_start().strip_prefix("API Error")?;
+    let rest = rest.trim_start_matches(':').trim_start();
+
+    let status: Option<u16> = rest
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|digits| digits.parse().ok());
+
+    if let Some(start) = rest.find('{') {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&rest[start..]) {
+            if let Some(ClaudeSDKError::Api {
+                kind,
+                message,
+                retry_after,
+            }) = from_error_value(&value)
+            {
+                return Some(ClaudeSDKError::Api {
+                    kind,
+                    message,
+                    retry_after: retry_after.or_else(|| retry_after_from_text(text)),
+                });
+            }
+        }
+    }
+
+    Some(ClaudeSDKError::Api {
+        kind: status
+            .map(ApiErrorKind::from_status)
+            .unwrap_or_else(|| ApiErrorKind::Other("unknown".to_string())),
+        message: rest.to_string(),
+        retry_after: retry_after_from_text(text),
+    })
+}
+
+/// Detect an assistant message that only reports an API error.
+pub fn from_message(message: &Message) -> Option<ClaudeSDKError> {
+    match message {
+        Message::Assistant(msg) => match msg.content.as_slice() {
+            [ContentBlock::Text(block)] => from_text(&block.text),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub(crate) fn retry_after_from_text(text: &str) -> Option<Duration> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)retry[-_ ]after\W{0,3}(\d+(?:\.\d+)?)")
+            .expect("retry-after pattern is valid")
+    });
+    let secs: f64 = pattern.captures(text)?.get(1)?.as_str().parse().ok()?;
+    // Out-of-range values are ignored rather than panicking
+    Duration::try_from_secs_f64(secs).ok()
+}
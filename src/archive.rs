@@ -0,0 +1,304 @@
+//! Ending long-lived sessions and archiving their transcripts.
+
+use crate::annotations::{self, Annotation};
+use crate::compression::Compression;
+use crate::error::Result;
+use crate::run_id::RunId;
+use crate::types::{Message, Shared};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Storage for the transcripts of finished sessions.
+#[async_trait]
+pub trait TranscriptStore: Send + Sync {
+    /// Store the messages of the run `run_id`, returning where they went.
+    async fn store(
+        &self,
+        run_id: RunId,
+        session_id: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String>;
+
+    /// Store the annotations of the run `run_id` next to its transcript at
+    /// `location`, replacing any stored before: annotations added after
+    /// archiving are stored again with the earlier ones. Stores that keep
+    /// only transcripts ignore them.
+    async fn store_annotations(
+        &self,
+        _run_id: RunId,
+        _location: &str,
+        _annotations: &[Annotation],
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Stores each transcript as `<run id>.ndjson` in a directory, readable with
+/// [`Debugger::load`](crate::debugger::Debugger::load), and its annotations
+/// as `<run id>.annotations.ndjson`, readable with [`annotations::load`].
+#[derive(Debug, Clone)]
+pub struct FileTranscriptStore {
+    dir: PathBuf,
+    compression: Compression,
+}
+
+impl FileTranscriptStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            compression: Compression::None,
+        }
+    }
+
+    /// Compress transcripts, adding `.gz` or `.zst` to their file names.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+#[async_trait]
+impl TranscriptStore for FileTranscriptStore {
+    async fn store(
+        &self,
+        run_id: RunId,
+        _session_id: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String> {
+        let mut ndjson = Vec::new();
+        for message in messages {
+            serde_json::to_writer(&mut ndjson, message)?;
+            ndjson.push(b'\n');
+        }
+        let path = self.write(&format!("{}.ndjson", run_id), ndjson).await?;
+        Ok(path.display().to_string())
+    }
+
+    async fn store_annotations(
+        &self,
+        run_id: RunId,
+        _location: &str,
+        annotations: &[Annotation],
+    ) -> Result<()> {
+        let ndjson = annotations::to_ndjson(annotations)?;
+        self.write(
+            &format!("{}.annotations.ndjson", run_id),
+            ndjson.into_bytes(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl FileTranscriptStore {
+    async fn write(&self, file_name: &str, contents: Vec<u8>) -> Result<PathBuf> {
+        let mut file_name = file_name.to_string();
+        if let Some(extension) = self.compression.extension() {
+            file_name = format!("{}.{}", file_name, extension);
+        }
+        #[cfg(feature = "compression")]
+        let contents = self.compression.compress(&contents)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(file_name);
+        tokio::fs::write(&path, contents).await?;
+        Ok(path)
+    }
+}
+
+/// Keeps transcripts in memory, under the location `memory:<run id>`.
+#[derive(Debug, Default)]
+pub struct InMemoryTranscriptStore {
+    transcripts: Mutex<HashMap<String, Vec<Message>>>,
+    annotations: Mutex<HashMap<String, Vec<Annotation>>>,
+}
+
+impl InMemoryTranscriptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The transcript stored at `location`.
+    pub fn get(&self, location: &str) -> Option<Vec<Message>> {
+        self.transcripts.lock().unwrap().get(location).cloned()
+    }
+
+    /// The annotations of the transcript stored at `location`.
+    pub fn annotations(&self, location: &str) -> Vec<Annotation> {
+        self.annotations
+            .lock()
+            .unwrap()
+            .get(location)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl TranscriptStore for InMemoryTranscriptStore {
+    async fn store(
+        &self,
+        run_id: RunId,
+        _session_id: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String> {
+        let location = format!("memory:{}", run_id);
+        self.transcripts
+            .lock()
+            .unwrap()
+            .insert(location.clone(), messages.to_vec());
+        Ok(location)
+    }
+
+    async fn store_annotations(
+        &self,
+        _run_id: RunId,
+        location: &str,
+        annotations: &[Annotation],
+    ) -> Result<()> {
+        self.annotations
+            .lock()
+            .unwrap()
+            .insert(location.to_string(), annotations.to_vec());
+        Ok(())
+    }
+}
+
+/// A session whose transcript was archived.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Archived {
+    pub run_id: RunId,
+    pub session_id: Option<String>,
+    /// Where the store put the transcript.
+    pub location: String,
+    pub messages: usize,
+    /// How many annotations were stored with the transcript.
+    #[serde(default)]
+    pub annotations: usize,
+    /// Whether the session was ended for exceeding its maximum lifetime.
+    pub expired: bool,
+}
+
+pub type ArchivedCallback = Shared<dyn Fn(&Archived) + Send + Sync>;
+
+/// Housekeeping for services running many sessions: each session's
+/// transcript is flushed to a [`TranscriptStore`] when it ends, and a
+/// session running longer than the maximum lifetime is ended early.
+///
+/// Once stored, the stream delivers a system message naming the location,
+/// and the [`Archived`] event is passed to the callback and available from
+/// [`QueryHandle::archived`](crate::QueryHandle::archived). The lifetime
+/// covers fix-it turns and turn retries.
+///
+/// ```rust
+/// use claude_code_sdk::archive::{FileTranscriptStore, SessionArchive};
+/// use claude_code_sdk::ClaudeCodeOptions;
+/// use std::time::Duration;
+///
+/// let archive = SessionArchive::new(FileTranscriptStore::new("transcripts"))
+///     .with_max_lifetime(Duration::from_secs(30 * 60))
+///     .on_archived(|archived| println!("archived to {}", archived.location));
+/// let options = ClaudeCodeOptions::new().with_session_archive(archive);
+/// ```
+#[derive(Clone)]
+pub struct SessionArchive {
+    store: Shared<dyn TranscriptStore>,
+    max_lifetime: Option<Duration>,
+    on_archived: Option<ArchivedCallback>,
+}
+
+impl SessionArchive {
+    pub fn new<S: TranscriptStore + 'static>(store: S) -> Self {
+        Self::from_shared(Arc::new(store))
+    }
+
+    /// Archive to a store that is also used elsewhere.
+    pub fn from_shared(store: Arc<dyn TranscriptStore>) -> Self {
+        Self {
+            store: Shared(store),
+            max_lifetime: None,
+            on_archived: None,
+        }
+    }
+
+    /// End sessions that are still running after `lifetime`.
+    pub fn with_max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_lifetime = Some(lifetime);
+        self
+    }
+
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    /// Call `callback` with each archived session.
+    pub fn on_archived<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Archived) + Send + Sync + 'static,
+    {
+        self.on_archived = Some(Shared(Arc::new(callback)));
+        self
+    }
+
+    pub(crate) async fn archive(
+        &self,
+        run_id: RunId,
+        session_id: Option<String>,
+        messages: Vec<Message>,
+        annotations: Vec<Annotation>,
+        expired: bool,
+    ) -> Result<Archived> {
+        let location = self
+            .store
+            .store(run_id, session_id.as_deref(), &messages)
+            .await?;
+        if !annotations.is_empty() {
+            self.store
+                .store_annotations(run_id, &location, &annotations)
+                .await?;
+        }
+        let archived = Archived {
+            run_id,
+            session_id,
+            location,
+            messages: messages.len(),
+            annotations: annotations.len(),
+            expired,
+        };
+        tracing::info!(run_id = %run_id, location = %archived.location, "archived transcript");
+        if let Some(callback) = &self.on_archived {
+            callback(&archived);
+        }
+        Ok(archived)
+    }
+}
+
+impl SessionArchive {
+    /// Store `annotations` with the transcript `archived` describes,
+    /// replacing the ones stored with it before.
+    pub(crate) async fn store_annotations(
+        &self,
+        archived: &mut Archived,
+        annotations: &[Annotation],
+    ) -> Result<()> {
+        self.store
+            .store_annotations(archived.run_id, &archived.location, annotations)
+            .await?;
+        archived.annotations = annotations.len();
+        tracing::info!(run_id = %archived.run_id, annotations = annotations.len(), "stored annotations");
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SessionArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionArchive")
+            .field("max_lifetime", &self.max_lifetime)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,108 @@
+//! Consuming message streams without `futures::Stream`.
+//!
+//! [`MessageIter`] wraps any message stream with an inherent async
+//! [`next`](MessageIter::next), so no extension trait needs to be in scope.
+//! Built with `RUSTFLAGS="--cfg claude_sdk_nightly"` on a nightly toolchain,
+//! it also implements the standard library's unstable
+//! `core::async_iter::AsyncIterator`.
+//!
+//! [`into_channel`] forwards a stream to a Tokio channel instead, for
+//! consumers that hand messages to other tasks or to synchronous code.
+
+use futures::stream::{Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// How many messages a channel from [`into_channel`] buffers before the
+/// query waits for the receiver.
+pub const CHANNEL_CAPACITY: usize = 32;
+
+/// A message stream as an async iterator.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::async_iter::MessageIter;
+/// use claude_code_sdk::query;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut messages = MessageIter::new(query("Hello Claude", None).await?);
+/// while let Some(message) = messages.next().await {
+///     println!("{:?}", message?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MessageIter<S> {
+    stream: S,
+}
+
+impl<S: Stream + Unpin> MessageIter<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// The next item, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<S::Item> {
+        self.stream.next().await
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Stream + Unpin> Stream for MessageIter<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
+
+#[cfg(claude_sdk_nightly)]
+impl<S: Stream + Unpin> core::async_iter::AsyncIterator for MessageIter<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
+
+/// Forward `stream` to a channel from a spawned task.
+///
+/// The channel holds up to [`CHANNEL_CAPACITY`] messages. Dropping the
+/// receiver drops the stream, which ends a running query.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::async_iter::into_channel;
+/// use claude_code_sdk::query;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut messages = into_channel(query("Hello Claude", None).await?);
+/// while let Some(message) = messages.recv().await {
+///     println!("{:?}", message?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "subprocess")]
+pub fn into_channel<S>(mut stream: S) -> tokio::sync::mpsc::Receiver<S::Item>
+where
+    S: Stream + Send + Unpin + 'static,
+    S::Item: Send,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        loop {
+            let item = tokio::select! {
+                item = stream.next() => item,
+                _ = tx.closed() => break,
+            };
+            let Some(item) = item else { break };
+            if tx.send(item).await.is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
@@ -0,0 +1,17 @@
+//! Serves the SDK over NDJSON on stdin and stdout, see
+//! [`claude_code_sdk::serve`].
+
+use std::process::ExitCode;
+use tokio::io::BufReader;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let stdin = BufReader::new(tokio::io::stdin());
+    match claude_code_sdk::serve::serve(stdin, tokio::io::stdout()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("claude-sdk-serve: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
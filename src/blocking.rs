@@ -0,0 +1,74 @@
+//! Blocking queries, for programs without an async runtime.
+//!
+//! Each query runs on its own single-threaded Tokio runtime, so these
+//! functions must not be called from async code: use [`crate::query`]
+//! there.
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::{blocking, Message};
+//!
+//! # fn main() -> claude_code_sdk::Result<()> {
+//! for message in blocking::query_iter("Explain src/main.rs", None)? {
+//!     if let Message::Result(result) = message? {
+//!         println!("{}", result.content.unwrap_or_default());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Result;
+use crate::handle::QueryHandle;
+use crate::prompt::PromptInput;
+use crate::types::{ClaudeCodeOptions, Message};
+use futures::StreamExt;
+use tokio::runtime::{Builder, Runtime};
+
+/// Run a query to its end and return all of its messages, or the first
+/// error.
+pub fn query<P: Into<PromptInput>>(
+    prompt: P,
+    options: Option<ClaudeCodeOptions>,
+) -> Result<Vec<Message>> {
+    query_iter(prompt, options)?.collect()
+}
+
+/// Start a query and iterate over its messages as they arrive.
+pub fn query_iter<P: Into<PromptInput>>(
+    prompt: P,
+    options: Option<ClaudeCodeOptions>,
+) -> Result<MessageIter> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let handle = runtime.block_on(crate::query_with_handle(prompt, options))?;
+    Ok(MessageIter { runtime, handle })
+}
+
+/// The messages of a blocking query. Dropping it stops the query and waits
+/// until the CLI process has been reaped.
+pub struct MessageIter {
+    runtime: Runtime,
+    handle: QueryHandle,
+}
+
+impl MessageIter {
+    /// The query's handle, for its session state.
+    pub fn handle(&self) -> &QueryHandle {
+        &self.handle
+    }
+}
+
+impl Iterator for MessageIter {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.handle.next())
+    }
+}
+
+impl Drop for MessageIter {
+    fn drop(&mut self) {
+        if let Err(e) = self.runtime.block_on(self.handle.close()) {
+            tracing::warn!(error = %e, "failed to close a blocking query");
+        }
+    }
+}
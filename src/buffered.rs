@@ -0,0 +1,210 @@
+//! Several independent readers of one conversation.
+//!
+//! A message stream can only be consumed once. [`BufferedConversation`]
+//! keeps every message as it arrives and hands out any number of
+//! [`ConversationReader`]s, each reading from the start at its own pace, so
+//! a UI and a persistence task can follow the same query without racing on
+//! one stream.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::Message;
+use futures::stream::Stream;
+use futures::task::ArcWake;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// A buffered message, with errors shared between the readers.
+pub type BufferedItem = std::result::Result<Message, Arc<ClaudeSDKError>>;
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Message>> + Send>>;
+
+/// A conversation whose messages are kept for every reader.
+///
+/// The underlying stream is read as fast as the fastest reader; slower
+/// readers catch up from the buffer. Nothing is ever dropped from the
+/// buffer, so it grows with the conversation.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::buffered::BufferedConversation;
+/// use claude_code_sdk::query;
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let conversation = BufferedConversation::new(query("Hello Claude", None).await?);
+///
+/// let mut persisted = conversation.reader();
+/// tokio::spawn(async move {
+///     while let Some(message) = persisted.next().await {
+///         // write to storage
+///     }
+/// });
+///
+/// let mut ui = conversation.reader();
+/// while let Some(message) = ui.next().await {
+///     println!("{:?}", message);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BufferedConversation {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    wakers: Arc<WakeAll>,
+}
+
+struct State {
+    items: Vec<BufferedItem>,
+    /// `None` once the stream has ended.
+    source: Option<MessageStream>,
+}
+
+/// Wakes every reader waiting for the source, whichever of them polled it
+/// last.
+#[derive(Default)]
+struct WakeAll {
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl ArcWake for WakeAll {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *arc_self.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl BufferedConversation {
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Message>> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    items: Vec::new(),
+                    source: Some(Box::pin(stream)),
+                }),
+                wakers: Arc::new(WakeAll::default()),
+            }),
+        }
+    }
+
+    /// A reader starting at the first message.
+    pub fn reader(&self) -> ConversationReader {
+        ConversationReader {
+            inner: self.inner.clone(),
+            position: 0,
+        }
+    }
+
+    /// The messages received so far, without errors.
+    pub fn messages(&self) -> Vec<Message> {
+        let state = self.inner.state.lock().unwrap();
+        state
+            .items
+            .iter()
+            .filter_map(|item| item.as_ref().ok().cloned())
+            .collect()
+    }
+
+    /// How many items, messages and errors, have been received so far.
+    pub fn len(&self) -> usize {
+        self.inner.state.lock().unwrap().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the underlying stream has ended.
+    pub fn is_complete(&self) -> bool {
+        self.inner.state.lock().unwrap().source.is_none()
+    }
+}
+
+impl std::fmt::Debug for BufferedConversation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferedConversation")
+            .field("len", &self.len())
+            .field("complete", &self.is_complete())
+            .finish()
+    }
+}
+
+impl Inner {
+    fn poll_item(&self, position: usize, cx: &mut Context<'_>) -> Poll<Option<BufferedItem>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(item) = state.items.get(position) {
+            return Poll::Ready(Some(item.clone()));
+        }
+        let Some(source) = state.source.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        {
+            let mut wakers = self.wakers.wakers.lock().unwrap();
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        let waker = futures::task::waker(self.wakers.clone());
+        let item = match source.as_mut().poll_next(&mut Context::from_waker(&waker)) {
+            Poll::Ready(Some(item)) => {
+                let item = item.map_err(Arc::new);
+                state.items.push(item.clone());
+                Some(item)
+            }
+            Poll::Ready(None) => {
+                state.source = None;
+                None
+            }
+            Poll::Pending => return Poll::Pending,
+        };
+        drop(state);
+        // Other readers waiting at the end of the buffer can move on now
+        WakeAll::wake_by_ref(&self.wakers);
+        Poll::Ready(item)
+    }
+}
+
+/// One reader of a [`BufferedConversation`].
+///
+/// Cloning a reader gives a second one at the same position.
+#[derive(Clone)]
+pub struct ConversationReader {
+    inner: Arc<Inner>,
+    position: usize,
+}
+
+impl ConversationReader {
+    /// How many items this reader has consumed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl std::fmt::Debug for ConversationReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConversationReader")
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl Stream for ConversationReader {
+    type Item = BufferedItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = self.inner.poll_item(self.position, cx);
+        if let Poll::Ready(Some(_)) = item {
+            self.position += 1;
+        }
+        item
+    }
+}
@@ -0,0 +1,126 @@
+//! Features of the installed CLI, detected from its `--version` and
+//! `--help` output.
+
+use crate::compat::CliVersion;
+#[cfg(feature = "subprocess")]
+use crate::error::Result;
+#[cfg(feature = "subprocess")]
+use crate::{protocol, sdk_info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+#[cfg(feature = "subprocess")]
+use std::collections::HashMap;
+#[cfg(feature = "subprocess")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "subprocess")]
+use std::sync::Mutex;
+use std::sync::OnceLock;
+#[cfg(feature = "subprocess")]
+use std::time::Duration;
+#[cfg(feature = "subprocess")]
+use tokio::process::Command;
+
+#[cfg(feature = "subprocess")]
+const HELP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the installed CLI supports.
+///
+/// `flags` holds every long flag listed by `--help`. It is empty when the
+/// help output could not be obtained or listed no flags, in which case
+/// [`supports_flag`](Self::supports_flag) assumes every flag is supported.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: Option<CliVersion>,
+    pub flags: BTreeSet<String>,
+    /// Accepts streamed JSON messages on stdin (`--input-format stream-json`).
+    pub stream_json_input: bool,
+    /// Accepts custom subagent definitions (`--agents`).
+    pub agents: bool,
+    /// Accepts a settings JSON file or string (`--settings`).
+    pub settings_json: bool,
+}
+
+impl Capabilities {
+    /// Derive the capabilities from the CLI's version and `--help` output.
+    pub fn from_help(version: Option<CliVersion>, help: &str) -> Self {
+        static FLAG: OnceLock<Regex> = OnceLock::new();
+        let flag =
+            FLAG.get_or_init(|| Regex::new(r"(?:^|[\s,\[])(--[a-zA-Z][a-zA-Z0-9-]*)").unwrap());
+        let flags: BTreeSet<String> = flag
+            .captures_iter(help)
+            .map(|captures| captures[1].to_string())
+            .collect();
+
+        Self {
+            version,
+            stream_json_input: flags.contains("--input-format") && help.contains("stream-json"),
+            agents: flags.contains("--agents"),
+            settings_json: flags.contains("--settings"),
+            flags,
+        }
+    }
+
+    /// Whether the CLI accepts `flag`, e.g. `--add-dir`.
+    ///
+    /// True if the help output was unavailable.
+    pub fn supports_flag(&self, flag: &str) -> bool {
+        self.flags.is_empty() || self.flags.contains(flag)
+    }
+}
+
+/// Probe the installed CLI's capabilities.
+///
+/// Runs `--version` and `--help` once per binary; later calls return the
+/// cached result.
+///
+/// ```rust,no_run
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let capabilities = claude_code_sdk::capabilities::probe_capabilities().await?;
+/// if !capabilities.stream_json_input {
+///     println!("streaming input needs a newer Claude Code CLI");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "subprocess")]
+pub async fn probe_capabilities() -> Result<Capabilities> {
+    let binary = protocol::find_cli_binary()?;
+    Ok(probe(&binary).await)
+}
+
+/// Probe the CLI at `cli_path`, caching the result per binary.
+#[cfg(feature = "subprocess")]
+pub(crate) async fn probe(cli_path: &Path) -> Capabilities {
+    static PROBED: OnceLock<Mutex<HashMap<PathBuf, Capabilities>>> = OnceLock::new();
+    let probed = PROBED.get_or_init(Default::default);
+    if let Some(capabilities) = probed.lock().unwrap().get(cli_path) {
+        return capabilities.clone();
+    }
+
+    let version = sdk_info::cli_version(cli_path)
+        .await
+        .as_deref()
+        .and_then(CliVersion::parse);
+    let output = tokio::time::timeout(
+        HELP_TIMEOUT,
+        Command::new(cli_path)
+            .arg("--help")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    let help = match output {
+        Ok(Ok(output)) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => String::new(),
+    };
+
+    let capabilities = Capabilities::from_help(version, &help);
+    probed
+        .lock()
+        .unwrap()
+        .insert(cli_path.to_path_buf(), capabilities.clone());
+    capabilities
+}
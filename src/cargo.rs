@@ -0,0 +1,204 @@
+//! Running the agent against a Cargo workspace.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::tool_policy::ToolCategory;
+use crate::types::ClaudeCodeOptions;
+use serde::Deserialize;
+#[cfg(feature = "subprocess")]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Cargo commands the agent may run without asking.
+const CARGO_COMMANDS: &[&str] = &["build", "check", "clippy", "fmt", "test"];
+
+/// How much of the `cargo check` output is packed into the prompt.
+const CHECK_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// A workspace member, as reported by `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CargoPackage {
+    pub name: String,
+    pub version: String,
+    pub edition: String,
+    pub manifest_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<CargoPackage>,
+    workspace_root: PathBuf,
+}
+
+/// The Cargo workspace a query operates on.
+///
+/// [`cargo_context`] detects the workspace around the current directory and
+/// runs `cargo check`; [`prompt`](Self::prompt) packs the package metadata
+/// and any compiler errors into the prompt, and [`apply`](Self::apply) runs
+/// the query from the workspace root with the file tools and the usual Cargo
+/// commands allowed.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::cargo::cargo_context;
+/// use claude_code_sdk::{query, ClaudeCodeOptions};
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let context = cargo_context().await?;
+/// if !context.check_passed() {
+///     let options = context.apply(ClaudeCodeOptions::new());
+///     let stream = query(&context.prompt("Fix the build."), Some(options)).await?;
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoContext {
+    pub workspace_root: PathBuf,
+    pub packages: Vec<CargoPackage>,
+    /// The output of a failing `cargo check`; `None` if it passed or was not
+    /// run.
+    pub check_output: Option<String>,
+}
+
+impl CargoContext {
+    /// Build the context from the JSON output of
+    /// `cargo metadata --no-deps --format-version 1`.
+    pub fn from_metadata(json: &str, check_output: Option<String>) -> Result<Self> {
+        let metadata: Metadata = serde_json::from_str(json)
+            .map_err(|e| ClaudeSDKError::cargo(format!("Invalid cargo metadata: {}", e)))?;
+        Ok(Self {
+            workspace_root: metadata.workspace_root,
+            packages: metadata.packages,
+            check_output,
+        })
+    }
+
+    /// Detect the workspace containing `dir` and run `cargo check` in it.
+    #[cfg(feature = "subprocess")]
+    pub async fn detect<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let metadata = cargo(dir, &["metadata", "--no-deps", "--format-version", "1"]).await?;
+        if !metadata.status.success() {
+            return Err(ClaudeSDKError::cargo(format!(
+                "No Cargo workspace found at {}: {}",
+                dir.display(),
+                String::from_utf8_lossy(&metadata.stderr).trim()
+            )));
+        }
+        let mut context = Self::from_metadata(&String::from_utf8_lossy(&metadata.stdout), None)?;
+
+        let check = cargo(
+            &context.workspace_root,
+            &[
+                "check",
+                "--workspace",
+                "--all-targets",
+                "--message-format",
+                "short",
+            ],
+        )
+        .await?;
+        if !check.status.success() {
+            context.check_output = Some(String::from_utf8_lossy(&check.stderr).into_owned());
+        }
+        Ok(context)
+    }
+
+    pub fn check_passed(&self) -> bool {
+        self.check_output.is_none()
+    }
+
+    /// `task` preceded by a description of the workspace and, if
+    /// `cargo check` failed, its output.
+    pub fn prompt(&self, task: &str) -> String {
+        let mut prompt = format!(
+            "This is a Rust project. The Cargo workspace at {} contains these packages:\n",
+            self.workspace_root.display()
+        );
+        for package in &self.packages {
+            let manifest = package
+                .manifest_path
+                .strip_prefix(&self.workspace_root)
+                .unwrap_or(&package.manifest_path);
+            prompt.push_str(&format!(
+                "- {} {} (edition {}, {})\n",
+                package.name,
+                package.version,
+                package.edition,
+                manifest.display()
+            ));
+        }
+        if let Some(output) = &self.check_output {
+            prompt.push_str(&format!(
+                "\n`cargo check` currently fails:\n\n```\n{}\n```\n",
+                tail(output.trim_end(), CHECK_OUTPUT_BYTES)
+            ));
+        }
+        prompt.push('\n');
+        prompt.push_str(task);
+        prompt
+    }
+
+    /// Tool rules for the file tools and the Cargo commands.
+    pub fn allowed_tools(&self) -> Vec<String> {
+        ToolCategory::FileRead
+            .tools()
+            .iter()
+            .chain(ToolCategory::FileWrite.tools())
+            .map(|tool| tool.to_string())
+            .chain(
+                CARGO_COMMANDS
+                    .iter()
+                    .map(|command| format!("Bash(cargo {}:*)", command)),
+            )
+            .collect()
+    }
+
+    /// Run from the workspace root with the file tools and Cargo commands
+    /// allowed.
+    pub fn apply(&self, mut options: ClaudeCodeOptions) -> ClaudeCodeOptions {
+        options.cwd = Some(self.workspace_root.clone());
+        let allowed = options.allowed_tools.get_or_insert_with(Vec::new);
+        for tool in self.allowed_tools() {
+            if !allowed.contains(&tool) {
+                allowed.push(tool);
+            }
+        }
+        options.push_append_system_prompt(
+            "Use `cargo check` to confirm the workspace compiles and `cargo test` to run the \
+             tests after making changes.",
+        );
+        options
+    }
+}
+
+/// Detect the Cargo workspace around the current directory, see
+/// [`CargoContext`].
+#[cfg(feature = "subprocess")]
+pub async fn cargo_context() -> Result<CargoContext> {
+    CargoContext::detect(std::env::current_dir()?).await
+}
+
+#[cfg(feature = "subprocess")]
+async fn cargo(dir: &Path, args: &[&str]) -> Result<std::process::Output> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    tokio::process::Command::new(cargo)
+        .args(args)
+        .current_dir(dir)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| ClaudeSDKError::cargo(format!("Failed to run cargo: {}", e)))
+}
+
+/// The last `max` bytes of `text`, on a character boundary.
+pub(crate) fn tail(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut cut = text.len() - max;
+    while !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    &text[cut..]
+}
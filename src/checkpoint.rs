@@ -0,0 +1,82 @@
+#[cfg(feature = "subprocess")]
+use crate::error::ClaudeSDKError;
+use crate::error::Result;
+use crate::types::Usage;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "subprocess")]
+use tokio::process::Command;
+
+/// A serializable snapshot of a query that can be resumed later, possibly on
+/// another machine that shares the same workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub session_id: String,
+    pub turn_count: i32,
+    pub usage: Usage,
+    pub workspace: Option<WorkspaceSnapshot>,
+}
+
+impl Checkpoint {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Reference to the workspace a checkpoint was taken in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub root: PathBuf,
+    /// Git revision checked out in `root`, if it is a git repository.
+    pub revision: Option<String>,
+}
+
+impl WorkspaceSnapshot {
+    #[cfg(feature = "subprocess")]
+    pub async fn capture<P: Into<PathBuf>>(root: P) -> Self {
+        let root = root.into();
+        let revision = revision(&root).await;
+        Self { root, revision }
+    }
+
+    /// Check that the workspace at `root` is still at the revision of this
+    /// snapshot, so a resumed run does not pick up on a different tree.
+    ///
+    /// Nothing is checked if the snapshot has no revision.
+    #[cfg(feature = "subprocess")]
+    pub async fn verify(&self, root: &Path) -> Result<()> {
+        let Some(expected) = &self.revision else {
+            return Ok(());
+        };
+        match revision(root).await {
+            Some(current) if current == *expected => Ok(()),
+            current => Err(ClaudeSDKError::checkpoint(format!(
+                "workspace {} is at {}, but the checkpoint was taken at {}",
+                root.display(),
+                current.as_deref().unwrap_or("no git revision"),
+                expected
+            ))),
+        }
+    }
+}
+
+/// The git revision checked out in `root`, if it is a git repository.
+#[cfg(feature = "subprocess")]
+async fn revision(root: &Path) -> Option<String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|revision| !revision.is_empty())
+}
@@ -1,31 +1,646 @@
-use crate::error::Result;
-use crate::transport::{SubprocessCLITransport, Transport};
-use crate::types::{ClaudeCodeOptions, Message};
-use futures::stream::Stream;
+use crate::api_error::ApiErrorKind;
+use crate::capabilities::{self, Capabilities};
+use crate::compat::{self, OptionWarning};
+use crate::context_files::ContextFiles;
+use crate::danger;
+use crate::error::{ClaudeSDKError, Result};
+use crate::external_tools::{self, ToolResultSender};
+use crate::file_lock;
+#[cfg(feature = "file-patch")]
+use crate::file_patch;
+use crate::handle::QueryHandle;
+use crate::idempotency::{self, Begin, Claim};
+use crate::key_router;
+use crate::middleware::{self, Middleware};
+use crate::output_budget;
+use crate::overlay;
+use crate::prompt::PromptInput;
+use crate::protocol;
+use crate::run_id::RunId;
+use crate::sdk_info::{OptionsSummary, SdkInfo};
+use crate::speech;
+use crate::transport::{DisposeGuard, ProcessExit, SubprocessCLITransport, Transport};
+use crate::types::{ClaudeCodeOptions, Message, PermissionMode};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What [`ClaudeSDKClient::connect`] established before the first query.
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    /// Empty with a [custom transport](ClaudeCodeOptions::with_transport_factory).
+    pub cli_path: PathBuf,
+    pub capabilities: Capabilities,
+    /// The environment the queries will report, see [`SdkInfo`].
+    pub sdk_info: SdkInfo,
+    /// Options the installed CLI will ignore, see
+    /// [`CompatMode`](crate::CompatMode).
+    pub option_warnings: Vec<OptionWarning>,
+    /// How long the checks took.
+    pub warmup: Duration,
+}
+
+/// A client checked against the installed CLI ahead of its first query.
+///
+/// Each [`query`](Self::query) spawns its own CLI process, while
+/// [`send_message`](Self::send_message) holds a conversation with a single
+/// process kept alive across turns. Everything else a first query would
+/// discover is done up front by [`connect`](Self::connect): finding and
+/// probing the CLI, validating the options against it, checking the
+/// workspace guard, reading context files and fetching the first rotated
+/// credential. A service can fail at startup on a missing CLI or bad
+/// configuration instead of on its first user request, and the probes are
+/// cached for the queries that follow.
+///
+/// Conversations set up and adapt their messages like queries do: tenant
+/// overlays, middleware, context files, key routing, danger detection and
+/// the other stream adapters apply to both.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKClient};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let client = ClaudeSDKClient::connect(ClaudeCodeOptions::new().with_max_turns(3)).await?;
+/// println!("ready in {:?}", client.readiness().warmup);
+///
+/// let mut handle = client.query("Summarize README.md").await?;
+/// while let Some(message) = handle.next().await {
+///     println!("{:?}", message?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A multi-turn conversation in one process:
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKClient};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut client = ClaudeSDKClient::connect(ClaudeCodeOptions::new()).await?;
+/// for prompt in ["What does src/lib.rs export?", "Which of those are async?"] {
+///     client.send_message(prompt).await?;
+///     let mut response = client.receive_response();
+///     while let Some(message) = response.next().await {
+///         println!("{:?}", message?);
+///     }
+/// }
+/// client.disconnect().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ClaudeSDKClient {
+    options: ClaudeCodeOptions,
+    readiness: Readiness,
+    conversation: Option<Conversation>,
+}
+
+/// A clone shares the checks but not the conversation; it starts its own
+/// with its first message.
+impl Clone for ClaudeSDKClient {
+    fn clone(&self) -> Self {
+        Self {
+            options: self.options.clone(),
+            readiness: self.readiness.clone(),
+            conversation: None,
+        }
+    }
+}
+
+/// The CLI process of a [`ClaudeSDKClient`] conversation.
+struct Conversation {
+    transport: Box<dyn Transport>,
+    // Only touched through `&mut self`; the mutex makes the client `Sync`.
+    messages: std::sync::Mutex<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>>,
+    /// Messages read while waiting for the answer to a control request,
+    /// not yet handed out.
+    buffered: VecDeque<Result<Message>>,
+    /// Whether `messages` has ended.
+    ended: bool,
+}
+
+impl Conversation {
+    /// The next message of the conversation, if it has not ended.
+    async fn next(&mut self) -> Option<Result<Message>> {
+        if let Some(item) = self.buffered.pop_front() {
+            return Some(item);
+        }
+        if self.ended {
+            return None;
+        }
+        let messages = self
+            .messages
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let item = messages.next().await;
+        self.ended = item.is_none();
+        item
+    }
+
+    /// Send `request` and wait for the CLI's answer, reading the messages
+    /// it arrives with meanwhile; they are kept for
+    /// [`receive_response`](ClaudeSDKClient::receive_response).
+    async fn control(&mut self, request: serde_json::Value) -> Result<()> {
+        let messages = self
+            .messages
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let answer = self.transport.send_control(request);
+        tokio::pin!(answer);
+        loop {
+            tokio::select! {
+                biased;
+                answered = &mut answer => return answered,
+                item = messages.next(), if !self.ended => match item {
+                    Some(item) => self.buffered.push_back(item),
+                    None => self.ended = true,
+                },
+                else => {
+                    return Err(ClaudeSDKError::cli_connection(
+                        "the conversation ended before the CLI answered",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Conversation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conversation").finish_non_exhaustive()
+    }
+}
+
+impl ClaudeSDKClient {
+    /// Run the checks of a first query without sending a prompt.
+    pub async fn connect(options: ClaudeCodeOptions) -> Result<Self> {
+        let started = Instant::now();
+        options.validate()?;
+
+        // A custom transport does not run the installed CLI
+        let (cli_path, capabilities) = match &options.transport_factory {
+            Some(_) => (PathBuf::new(), Capabilities::default()),
+            None => {
+                let cli_path = protocol::cli_binary(&options)?;
+                let capabilities = capabilities::probe(&cli_path).await;
+                if capabilities.version.is_none() {
+                    return Err(ClaudeSDKError::cli_connection(format!(
+                        "{} did not report a version",
+                        cli_path.display()
+                    )));
+                }
+                (cli_path, capabilities)
+            }
+        };
+        let option_warnings = compat::check_capabilities(&mut options.clone(), &capabilities)?;
+
+        options
+            .workspace_guard
+            .unwrap_or_default()
+            .check(&options)
+            .await?;
+        if let Some(paths) = &options.context_files {
+            ContextFiles::read(paths.iter().cloned(), options.cwd.as_deref())?;
+        }
+        if let Some(rotation) = &options.key_rotation {
+            rotation.current().await?;
+        }
+
+        let sdk_info = match &options.transport_factory {
+            Some(_) => SdkInfo::new(None, None, &options),
+            None => SdkInfo::collect(&cli_path, &options).await,
+        };
+        let readiness = Readiness {
+            cli_path,
+            capabilities,
+            sdk_info,
+            option_warnings,
+            warmup: started.elapsed(),
+        };
+        tracing::info!(
+            cli = %readiness.cli_path.display(),
+            warmup = ?readiness.warmup,
+            "Claude Code client ready"
+        );
+        Ok(Self {
+            options,
+            readiness,
+            conversation: None,
+        })
+    }
+
+    /// A client reusing the checks of another one, e.g. for several
+    /// tenants' clients backed by the same CLI.
+    ///
+    /// Only the options are checked again, against the capabilities in
+    /// `readiness`.
+    pub fn from_readiness(options: ClaudeCodeOptions, mut readiness: Readiness) -> Result<Self> {
+        options.validate()?;
+        readiness.option_warnings =
+            compat::check_capabilities(&mut options.clone(), &readiness.capabilities)?;
+        readiness.sdk_info.options = OptionsSummary::new(&options);
+        Ok(Self {
+            options,
+            readiness,
+            conversation: None,
+        })
+    }
+
+    /// Wrap the client's queries in `layer`, see [`crate::middleware`].
+    pub fn with_middleware<M: Middleware + 'static>(mut self, layer: Arc<M>) -> Self {
+        self.options = self.options.with_middleware(layer);
+        self
+    }
+
+    pub fn readiness(&self) -> &Readiness {
+        &self.readiness
+    }
+
+    pub fn options(&self) -> &ClaudeCodeOptions {
+        &self.options
+    }
+
+    /// Switch the permission mode.
+    ///
+    /// A live [conversation](Self::send_message) is asked to switch with a
+    /// `set_permission_mode` control request, from its next tool call on;
+    /// later queries and conversations start with the new mode. Fails with
+    /// [`UnsupportedOption`](ClaudeSDKError::UnsupportedOption) if the
+    /// installed CLI has no flag for `mode`, the conversation's transport
+    /// does not accept control requests or the CLI refuses the request.
+    /// Messages that arrive while waiting for the CLI's answer are kept for
+    /// [`receive_response`](Self::receive_response).
+    pub async fn set_permission_mode(&mut self, mode: PermissionMode) -> Result<()> {
+        let flag = match mode {
+            PermissionMode::Default => None,
+            PermissionMode::AcceptEdits => Some("--accept-edits"),
+            PermissionMode::BypassPermissions => Some("--bypass-permissions"),
+        };
+        let request = serde_json::json!({
+            "subtype": "set_permission_mode",
+            "mode": match mode {
+                PermissionMode::Default => "default",
+                PermissionMode::AcceptEdits => "acceptEdits",
+                PermissionMode::BypassPermissions => "bypassPermissions",
+            },
+        });
+        self.update("permission_mode", flag, request, |options| {
+            options.permission_mode = Some(mode)
+        })
+        .await
+    }
+
+    /// Switch the model, see
+    /// [`set_permission_mode`](Self::set_permission_mode). A live
+    /// conversation uses it from its next turn on.
+    pub async fn set_model<S: Into<String>>(&mut self, model: S) -> Result<()> {
+        let model = model.into();
+        if model.trim().is_empty() {
+            return Err(ClaudeSDKError::invalid_options("model must not be empty"));
+        }
+        let request = serde_json::json!({ "subtype": "set_model", "model": model });
+        self.update("claude_model", Some("--model"), request, |options| {
+            options.claude_model = Some(model)
+        })
+        .await
+    }
+
+    /// Apply `change` to the options if the CLI accepts `flag` and the
+    /// changed options are still valid, sending `request` to a live
+    /// conversation first. The options stay untouched if either fails.
+    async fn update(
+        &mut self,
+        option: &str,
+        flag: Option<&str>,
+        request: serde_json::Value,
+        change: impl FnOnce(&mut ClaudeCodeOptions),
+    ) -> Result<()> {
+        if let Some(flag) = flag {
+            if !self.readiness.capabilities.supports_flag(flag) {
+                return Err(ClaudeSDKError::unsupported_option(
+                    option,
+                    format!("the installed CLI does not accept {}", flag),
+                ));
+            }
+        }
+        let mut options = self.options.clone();
+        change(&mut options);
+        options.validate()?;
+        if let Some(conversation) = &mut self.conversation {
+            conversation.control(request).await.map_err(|e| match e {
+                ClaudeSDKError::UnsupportedOption { message, .. } => {
+                    ClaudeSDKError::unsupported_option(option, message)
+                }
+                e => e,
+            })?;
+            tracing::info!(option, "changed client option of the live conversation");
+        } else {
+            tracing::info!(option, "changed client option for the next turn");
+        }
+        self.readiness.sdk_info.options = OptionsSummary::new(&options);
+        self.options = options;
+        Ok(())
+    }
+
+    /// Query with the client's current options, see
+    /// [`query_with_handle`](crate::query_with_handle).
+    pub async fn query<P: Into<PromptInput>>(&self, prompt: P) -> Result<QueryHandle> {
+        crate::query_with_handle(prompt, Some(self.options.clone())).await
+    }
+
+    /// Send the next user turn of the conversation, starting the CLI
+    /// process with the first one. Read Claude's answer with
+    /// [`receive_response`](Self::receive_response).
+    ///
+    /// The process reads its messages from stdin, which needs a CLI that
+    /// supports `--input-format stream-json`.
+    pub async fn send_message<P: Into<PromptInput>>(&mut self, prompt: P) -> Result<()> {
+        let mut prompt = prompt.into();
+        let conversation = match &mut self.conversation {
+            Some(conversation) => conversation,
+            None => {
+                let (conversation, first) = self.start_conversation(prompt).await?;
+                prompt = first;
+                self.conversation.insert(conversation)
+            }
+        };
+        conversation.transport.send_message(prompt).await
+    }
+
+    /// The messages of the current turn, up to and including its result
+    /// message.
+    ///
+    /// Ends right away if no message has been sent. Messages not read yet
+    /// stay for the next call.
+    ///
+    /// The [message filter](ClaudeCodeOptions::with_message_filter) applies
+    /// here; a turn still ends with its result message even if the filter
+    /// drops it.
+    pub fn receive_response(&mut self) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send + '_>> {
+        let filter = self.options.message_filter.clone();
+        Box::pin(stream::unfold(
+            (self.conversation.as_mut(), false),
+            move |(conversation, done)| {
+                let filter = filter.clone();
+                async move {
+                    let conversation = conversation.filter(|_| !done)?;
+                    loop {
+                        let item = conversation.next().await?;
+                        let done = matches!(item, Ok(Message::Result(_)));
+                        let item = match (item, &filter) {
+                            (Ok(message), Some(filter)) => filter.apply(message).map(Ok),
+                            (item, _) => Some(item),
+                        };
+                        match item {
+                            Some(item) => return Some((item, (Some(conversation), done))),
+                            None if done => return None,
+                            None => {}
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Whether a conversation process is running.
+    pub fn is_conversing(&self) -> bool {
+        self.conversation.is_some()
+    }
+
+    /// End the conversation: close the CLI's input and wait until its
+    /// process has exited, stopping it once the
+    /// [shutdown timeout](ClaudeCodeOptions::with_shutdown_timeout) passes.
+    /// The next message starts a new one.
+    ///
+    /// Returns how the process ended, if a conversation was running.
+    pub async fn disconnect(&mut self) -> Result<Option<ProcessExit>> {
+        let Some(mut conversation) = self.conversation.take() else {
+            return Ok(None);
+        };
+        conversation.transport.end_input().await;
+        conversation.transport.disconnect().await
+    }
+
+    /// Start the conversation's process, returning it with the first
+    /// message as set up by the middleware and context files.
+    async fn start_conversation(&self, prompt: PromptInput) -> Result<(Conversation, PromptInput)> {
+        for (option, set) in [
+            ("external_tools", self.options.external_tools.is_some()),
+            ("idempotency_key", self.options.idempotency_key.is_some()),
+        ] {
+            if set {
+                return Err(ClaudeSDKError::unsupported_option(
+                    option,
+                    format!(
+                        "{} are only supported by one-shot queries",
+                        option.replace('_', " ")
+                    ),
+                ));
+            }
+        }
+        let (prompt, mut options) = resolve(prompt, self.options.clone()).await?;
+        // Shared by the transport's hooks and the adapters
+        options.run_id.get_or_insert_with(RunId::new);
+        let (prompt, context_summary) = prepare(prompt, &options).await?;
+        let mut spawn_options = options.clone();
+        if let Some(rotation) = &options.key_rotation {
+            rotation.current().await?.apply(&mut spawn_options);
+        }
+        let routed_key = route(&options, &mut spawn_options)?;
+        let mut transport: Box<dyn Transport> = match &options.transport_factory {
+            Some(factory) => factory(PromptInput::default(), spawn_options),
+            None => Box::new(SubprocessCLITransport::interactive(spawn_options)),
+        };
+        transport.connect().await?;
+        let messages = transport.receive_messages().await?;
+        let messages = adapt(
+            messages,
+            transport.as_ref(),
+            &options,
+            context_summary,
+            routed_key,
+            None,
+        );
+        tracing::info!(cli = %self.readiness.cli_path.display(), "started Claude Code conversation");
+        Ok((
+            Conversation {
+                transport,
+                messages: std::sync::Mutex::new(messages),
+                buffered: VecDeque::new(),
+                ended: false,
+            },
+            prompt,
+        ))
+    }
+}
 
 pub struct InternalClient {
     transport: Option<Box<dyn Transport>>,
 }
 
+impl Default for InternalClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InternalClient {
     pub fn new() -> Self {
         Self { transport: None }
     }
 
-    pub async fn process_query(
+    pub async fn process_query<P: Into<PromptInput>>(
         &mut self,
-        prompt: String,
+        prompt: P,
         options: ClaudeCodeOptions,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
-        // Create and configure transport
-        let mut transport = Box::new(SubprocessCLITransport::new(prompt, options));
+        let prompt = prompt.into();
+        let idempotency = match &options.idempotency_key {
+            Some(key) => {
+                let store = options
+                    .idempotency_store
+                    .clone()
+                    .unwrap_or_else(idempotency::default_store);
+                match store.begin(key).await? {
+                    // Already completed: replay the stored result without spawning the CLI
+                    Begin::Done(result) => {
+                        return Ok(Box::pin(stream::once(future::ready(Ok(Message::Result(
+                            *result,
+                        ))))));
+                    }
+                    Begin::InFlight => {
+                        return Err(ClaudeSDKError::RunInProgress {
+                            idempotency_key: key.clone(),
+                        });
+                    }
+                    // Released again if the run fails before its result
+                    Begin::Started => Some(Claim::new(store, key.clone())),
+                }
+            }
+            None => None,
+        };
 
-        // Connect to the transport
-        transport.connect().await?;
+        let (prompt, context_summary) = prepare(prompt, &options).await?;
+
+        let max_retries = options.retry_policy.as_ref().map_or(0, |p| p.max_retries);
+        let mut attempt = 0;
+        let mut reauthenticated = false;
+        let mut model = options.claude_model.clone();
+        let mut fallback_models = options
+            .fallback_models
+            .clone()
+            .unwrap_or_default()
+            .into_iter();
+        let (transport, message_stream, routed_key) = loop {
+            let mut spawn_options = options.clone();
+            spawn_options.claude_model = model.clone();
+            if let Some(rotation) = &options.key_rotation {
+                rotation.current().await?.apply(&mut spawn_options);
+            }
+            let routed_key = route(&options, &mut spawn_options)?;
+
+            // Create and configure transport
+            let mut transport: Box<dyn Transport> = match &options.transport_factory {
+                Some(factory) => factory(prompt.clone(), spawn_options),
+                None => Box::new(SubprocessCLITransport::new(prompt.clone(), spawn_options)),
+            };
+
+            // Connect to the transport
+            transport.connect().await?;
 
-        // Get the message stream
-        let message_stream = transport.receive_messages().await?;
+            // Get the message stream
+            let message_stream = transport.receive_messages().await?;
+
+            if options.retry_policy.is_none()
+                && options.key_rotation.is_none()
+                && options.key_router.is_none()
+                && options.first_token_deadline.is_none()
+            {
+                break (transport, message_stream, routed_key);
+            }
+
+            // Retrying is only safe before Claude has produced any output
+            let read = read_until_output(message_stream);
+            let (buffered, message_stream) = match options.first_token_deadline {
+                Some(deadline) => match tokio::time::timeout(deadline, read).await {
+                    Ok(read) => read,
+                    Err(_) => {
+                        let _ = stop(transport.as_mut()).await;
+                        let Some(next) = fallback_models.next() else {
+                            return Err(ClaudeSDKError::SlowStart { deadline, model });
+                        };
+                        tracing::warn!(
+                            ?deadline,
+                            model = model.as_deref().unwrap_or("default"),
+                            fallback = %next,
+                            "no output before the first-token deadline, switching models"
+                        );
+                        model = Some(next);
+                        continue;
+                    }
+                },
+                None => read.await,
+            };
+            if let (Some(Err(e)), Some(router), Some(name)) =
+                (buffered.last(), &options.key_router, &routed_key)
+            {
+                if key_router::is_rate_limit_error(e) {
+                    router.quarantine(name, e.retry_after());
+                }
+            }
+            match (buffered.last(), &options.key_rotation) {
+                (Some(Err(e)), Some(rotation))
+                    if is_authentication_error(e) && !reauthenticated =>
+                {
+                    tracing::warn!(error = %e, "authentication failed, fetching a new credential");
+                    rotation.invalidate();
+                    reauthenticated = true;
+                    let _ = stop(transport.as_mut()).await;
+                }
+                (Some(Err(e)), _)
+                    if key_router::is_rate_limit_error(e)
+                        && options
+                            .key_router
+                            .as_ref()
+                            .is_some_and(|router| router.has_available_key()) =>
+                {
+                    tracing::warn!(error = %e, key = ?routed_key, "key rate limited, switching keys");
+                    let _ = stop(transport.as_mut()).await;
+                }
+                (Some(Err(e)), _) if e.is_retryable() && attempt < max_retries => {
+                    let delay = options
+                        .retry_policy
+                        .as_ref()
+                        .map(|policy| policy.delay(attempt, e.retry_after()))
+                        .unwrap_or_default();
+                    tracing::warn!(attempt, ?delay, error = %e, "retrying query");
+                    let _ = stop(transport.as_mut()).await;
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => {
+                    let replay: Pin<Box<dyn Stream<Item = Result<Message>> + Send>> =
+                        Box::pin(stream::iter(buffered).chain(message_stream));
+                    break (transport, replay, routed_key);
+                }
+            }
+        };
+
+        let message_stream = adapt(
+            message_stream,
+            transport.as_ref(),
+            &options,
+            context_summary,
+            routed_key,
+            idempotency,
+        );
 
         // Store the transport for cleanup
         self.transport = Some(transport);
@@ -33,13 +648,190 @@ impl InternalClient {
         // Return the stream of messages
         Ok(message_stream)
     }
+
+    /// Stop the transport's process and wait for it to be reaped.
+    pub async fn close(&mut self) -> Result<()> {
+        if let Some(mut transport) = self.transport.take() {
+            stop(transport.as_mut()).await?;
+        }
+        Ok(())
+    }
+
+    pub fn dispose_guard(&self) -> Option<DisposeGuard> {
+        self.transport
+            .as_ref()
+            .and_then(|transport| transport.dispose_guard())
+    }
+
+    pub fn tool_results(&self) -> Option<ToolResultSender> {
+        self.transport
+            .as_ref()
+            .and_then(|transport| transport.tool_results())
+    }
+
+    pub fn option_warnings(&self) -> Vec<OptionWarning> {
+        self.transport
+            .as_ref()
+            .map(|transport| transport.option_warnings())
+            .unwrap_or_default()
+    }
+}
+
+/// Apply the overlay of the options' tenant and the middleware's
+/// [`before_query`](Middleware::before_query) to a query or conversation.
+pub(crate) async fn resolve(
+    prompt: PromptInput,
+    options: ClaudeCodeOptions,
+) -> Result<(PromptInput, ClaudeCodeOptions)> {
+    let options = overlay::resolve(options).await?;
+    middleware::before_query(prompt, options).await
+}
+
+/// Check the workspace and embed the context files in `prompt`, returning
+/// it with the message announcing the files.
+async fn prepare(
+    prompt: PromptInput,
+    options: &ClaudeCodeOptions,
+) -> Result<(PromptInput, Option<Message>)> {
+    options
+        .workspace_guard
+        .unwrap_or_default()
+        .check(options)
+        .await?;
+
+    let Some(paths) = &options.context_files else {
+        return Ok((prompt, None));
+    };
+    let files = ContextFiles::read(paths.iter().cloned(), options.cwd.as_deref())?;
+    tracing::info!(
+        files = files.files.len(),
+        estimated_tokens = files.estimated_tokens(),
+        "embedding context files"
+    );
+    let summary = files.summary().into();
+    Ok((files.apply(prompt), Some(summary)))
+}
+
+/// Take a key from the options' key router for a spawn, if they have one.
+fn route(
+    options: &ClaudeCodeOptions,
+    spawn_options: &mut ClaudeCodeOptions,
+) -> Result<Option<String>> {
+    let Some(router) = &options.key_router else {
+        return Ok(None);
+    };
+    let key = router.select()?;
+    key.credential.apply(spawn_options);
+    Ok(Some(key.name))
+}
+
+/// Pass the messages of a query or conversation through the adapters its
+/// options ask for.
+fn adapt(
+    mut messages: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    transport: &dyn Transport,
+    options: &ClaudeCodeOptions,
+    context_summary: Option<Message>,
+    routed_key: Option<String>,
+    idempotency: Option<Claim>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    if let Some(summary) = context_summary {
+        messages = Box::pin(stream::once(future::ready(Ok(summary))).chain(messages));
+    }
+    if let (Some(router), Some(name)) = (&options.key_router, routed_key) {
+        messages = key_router::track_key(messages, router.clone(), name);
+    }
+    if options.dedupe_system_messages == Some(true) {
+        messages = crate::filter::dedupe_system_messages(messages);
+    }
+    if let Some(detector) = &options.danger_detector {
+        messages =
+            danger::interrupt_on_danger(messages, detector.clone(), transport.dispose_guard());
+    }
+    if let Some(budget) = options.max_output_tokens_per_turn {
+        messages = output_budget::enforce(messages, budget, transport.dispose_guard());
+    }
+    if let Some(locks) = &options.file_locks {
+        let run_id = options.run_id.unwrap_or_default();
+        messages = if transport.guards_tool_calls() {
+            file_lock::release_on_result(messages, locks.clone(), run_id)
+        } else {
+            file_lock::lock_edited_files(
+                messages,
+                locks.clone(),
+                run_id,
+                workspace_dir(options),
+                transport.dispose_guard(),
+            )
+        };
+    }
+    if let Some(sender) = transport.tool_results() {
+        messages = external_tools::close_on_result(messages, sender);
+    }
+    #[cfg(feature = "analysis-only")]
+    {
+        messages =
+            crate::tool_policy::interrupt_on_disabled_tool(messages, transport.dispose_guard());
+    }
+    if !options.middleware.is_empty() {
+        messages = middleware::apply(messages, options.middleware.clone());
+    }
+    if let Some(callback) = &options.text_chunk_callback {
+        messages = speech::speak_text(messages, callback.clone());
+    }
+    #[cfg(feature = "file-patch")]
+    if let Some(callback) = &options.file_patch_callback {
+        messages = file_patch::emit_patches(messages, callback.clone(), workspace_dir(options));
+    }
+    if let Some(claim) = idempotency {
+        messages = idempotency::record_result(messages, claim);
+    }
+    messages
+}
+
+/// The directory relative tool paths of a run are resolved against.
+fn workspace_dir(options: &ClaudeCodeOptions) -> PathBuf {
+    options
+        .cwd
+        .clone()
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+fn is_authentication_error(error: &ClaudeSDKError) -> bool {
+    matches!(
+        error.root(),
+        ClaudeSDKError::Api {
+            kind: ApiErrorKind::Authentication,
+            ..
+        }
+    )
+}
+
+/// Stop `transport`'s process right away instead of waiting for it to exit,
+/// then disconnect.
+async fn stop(transport: &mut dyn Transport) -> Result<Option<ProcessExit>> {
+    if let Some(guard) = transport.dispose_guard() {
+        guard.dispose().await?;
+    }
+    transport.disconnect().await
 }
 
-impl Drop for InternalClient {
-    fn drop(&mut self) {
-        if let Some(_transport) = self.transport.take() {
-            // Best effort cleanup - we can't await in Drop
-            // The transport's Drop implementation should handle cleanup
+/// Read messages up to and including the first item that is not a system
+/// message, returning them along with the rest of the stream.
+async fn read_until_output(
+    mut message_stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+) -> (
+    Vec<Result<Message>>,
+    Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+) {
+    let mut buffered = Vec::new();
+    while let Some(item) = message_stream.next().await {
+        let is_system = matches!(item, Ok(Message::System(_) | Message::SdkInfo(_)));
+        buffered.push(item);
+        if !is_system {
+            break;
         }
     }
+    (buffered, message_stream)
 }
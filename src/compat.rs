@@ -0,0 +1,255 @@
+//! Compatibility of options with the installed CLI version.
+
+use crate::capabilities::Capabilities;
+use crate::error::{ClaudeSDKError, Result};
+use crate::tool_policy::ANALYSIS_ONLY;
+use crate::types::ClaudeCodeOptions;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A Claude Code CLI version, as reported by `claude-code --version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CliVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl CliVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse the leading `major.minor.patch` of a version string such as
+    /// `1.0.3 (Claude Code)`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.split_whitespace().next()?.trim_start_matches('v');
+        let version = version.split(['-', '+']).next()?;
+        let mut parts = version.split('.').map(|part| part.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// How options the installed CLI does not support are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompatMode {
+    /// Fail the query before the CLI is started.
+    Strict,
+    /// Drop the option and report an [`OptionWarning`]. Options whose
+    /// absence would change what the query operates on, like
+    /// `fork_session`, or lift a restriction, like `disallowed_tools`,
+    /// still fail.
+    #[default]
+    Lax,
+}
+
+/// Why the CLI was found not to support an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedReason {
+    /// The CLI is older than the version that introduced the flag.
+    #[default]
+    Version,
+    /// The CLI is new enough, but its `--help` output does not list the
+    /// flag.
+    MissingFromHelp,
+}
+
+/// An option that was dropped because the CLI does not support it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionWarning {
+    /// The `ClaudeCodeOptions` field name.
+    pub option: String,
+    /// The CLI flag the option maps to.
+    pub flag: String,
+    pub required: CliVersion,
+    pub detected: CliVersion,
+    #[serde(default)]
+    pub reason: UnsupportedReason,
+}
+
+impl fmt::Display for OptionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            UnsupportedReason::Version => write!(
+                f,
+                "option `{}` ({}) requires Claude Code CLI {} or newer, found {}",
+                self.option, self.flag, self.required, self.detected
+            ),
+            UnsupportedReason::MissingFromHelp => write!(
+                f,
+                "option `{}` ({}) is not listed by `--help` of Claude Code CLI {}",
+                self.option, self.flag, self.detected
+            ),
+        }
+    }
+}
+
+struct FlagSupport {
+    option: &'static str,
+    flag: &'static str,
+    since: CliVersion,
+    is_set: fn(&ClaudeCodeOptions) -> bool,
+    clear: fn(&mut ClaudeCodeOptions),
+    /// Whether the query can run without the option in
+    /// [`CompatMode::Lax`], or must fail.
+    droppable: bool,
+}
+
+/// The CLI version each version-dependent flag first appeared in.
+///
+/// Flags available in every supported CLI version are not listed.
+const FLAG_SUPPORT: &[FlagSupport] = &[
+    FlagSupport {
+        option: "resume",
+        flag: "--resume",
+        since: CliVersion::new(0, 2, 74),
+        is_set: |options| options.resume.is_some(),
+        clear: |options| options.resume = None,
+        droppable: true,
+    },
+    FlagSupport {
+        option: "continue_conversation",
+        flag: "--continue",
+        since: CliVersion::new(0, 2, 74),
+        is_set: |options| options.continue_conversation == Some(true),
+        clear: |options| options.continue_conversation = None,
+        droppable: true,
+    },
+    FlagSupport {
+        option: "disallowed_tools",
+        flag: "--disallowedTools",
+        since: CliVersion::new(0, 2, 100),
+        // Analysis-only builds always pass the tools they disable
+        is_set: |options| ANALYSIS_ONLY || options.disallowed_tools.is_some(),
+        clear: |options| options.disallowed_tools = None,
+        // Dropping the flag would run the query without the restriction
+        droppable: false,
+    },
+    FlagSupport {
+        option: "append_system_prompt",
+        flag: "--append-system-prompt",
+        since: CliVersion::new(1, 0, 0),
+        is_set: |options| options.effective_append_system_prompt().is_some(),
+        clear: |options| {
+            options.append_system_prompt = None;
+            options.response_language = None;
+        },
+        droppable: true,
+    },
+    FlagSupport {
+        option: "max_thinking_tokens",
+        flag: "--max-thinking-tokens",
+        since: CliVersion::new(1, 0, 0),
+        is_set: |options| options.max_thinking_tokens.is_some(),
+        clear: |options| options.max_thinking_tokens = None,
+        droppable: true,
+    },
+    FlagSupport {
+        option: "add_dirs",
+        flag: "--add-dir",
+        since: CliVersion::new(1, 0, 18),
+        is_set: |options| options.add_dirs.is_some(),
+        clear: |options| options.add_dirs = None,
+        droppable: true,
+    },
+    FlagSupport {
+        option: "include_partial_messages",
+        flag: "--include-partial-messages",
+        since: CliVersion::new(1, 0, 86),
+        is_set: |options| options.include_partial_messages == Some(true),
+        clear: |options| options.include_partial_messages = None,
+        droppable: true,
+    },
+    FlagSupport {
+        option: "fork_session",
+        flag: "--fork-session",
+        since: CliVersion::new(2, 0, 0),
+        is_set: |options| options.fork_session == Some(true),
+        clear: |options| options.fork_session = None,
+        // Dropping the flag would continue the original session instead
+        droppable: false,
+    },
+];
+
+/// Check `options` against the flags supported by the CLI `version`.
+///
+/// In [`CompatMode::Strict`] the first unsupported option is an error. In
+/// [`CompatMode::Lax`] unsupported options are removed from `options` and
+/// returned as warnings, except those that cannot be dropped, like
+/// `disallowed_tools`, which are an error in either mode.
+pub fn check(options: &mut ClaudeCodeOptions, version: CliVersion) -> Result<Vec<OptionWarning>> {
+    check_with(options, version, |support| {
+        (version < support.since).then_some(UnsupportedReason::Version)
+    })
+}
+
+/// Like [`check`], but also treats flags missing from the CLI's `--help`
+/// output as unsupported.
+///
+/// Nothing is checked if the CLI version is unknown.
+pub fn check_capabilities(
+    options: &mut ClaudeCodeOptions,
+    capabilities: &Capabilities,
+) -> Result<Vec<OptionWarning>> {
+    let Some(version) = capabilities.version else {
+        return Ok(Vec::new());
+    };
+    check_with(options, version, |support| {
+        if version < support.since {
+            Some(UnsupportedReason::Version)
+        } else if !capabilities.supports_flag(support.flag) {
+            Some(UnsupportedReason::MissingFromHelp)
+        } else {
+            None
+        }
+    })
+}
+
+fn check_with(
+    options: &mut ClaudeCodeOptions,
+    version: CliVersion,
+    unsupported: impl Fn(&FlagSupport) -> Option<UnsupportedReason>,
+) -> Result<Vec<OptionWarning>> {
+    let mode = options.compat_mode.unwrap_or_default();
+    let mut warnings = Vec::new();
+    for support in FLAG_SUPPORT {
+        if !(support.is_set)(options) {
+            continue;
+        }
+        let Some(reason) = unsupported(support) else {
+            continue;
+        };
+        let warning = OptionWarning {
+            option: support.option.to_string(),
+            flag: support.flag.to_string(),
+            required: support.since,
+            detected: version,
+            reason,
+        };
+        if mode == CompatMode::Strict || !support.droppable {
+            return Err(ClaudeSDKError::unsupported_option(
+                support.option,
+                warning.to_string(),
+            ));
+        }
+        tracing::warn!("dropping unsupported {}", warning);
+        (support.clear)(options);
+        warnings.push(warning);
+    }
+    Ok(warnings)
+}
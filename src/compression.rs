@@ -0,0 +1,126 @@
+//! Compressed transcripts.
+//!
+//! Raw taps and archived transcripts of long runs grow to hundreds of
+//! megabytes of NDJSON. With the `compression` feature they can be written
+//! gzip or zstd compressed, see [`RawTap::compressed`](crate::tap::RawTap::compressed)
+//! and [`FileTranscriptStore::with_compression`](crate::archive::FileTranscriptStore::with_compression).
+//! [`Debugger::load`](crate::debugger::Debugger::load) recognizes compressed
+//! files by their content and decompresses them transparently.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a transcript is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    /// Zstandard, faster and denser than gzip.
+    Zstd,
+}
+
+impl Compression {
+    /// Recognize compressed data by its magic number.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// The file extension added for this compression, e.g. `gz`.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use std::io::Write;
+
+        Ok(match self {
+            Self::None => data.to_vec(),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Self::Zstd => zstd::encode_all(data, 0)?,
+        })
+    }
+
+    /// Wrap `writer` in a streaming encoder.
+    #[cfg(feature = "compression")]
+    pub(crate) fn encoder<W>(
+        &self,
+        writer: W,
+    ) -> std::pin::Pin<Box<dyn tokio::io::AsyncWrite + Send>>
+    where
+        W: tokio::io::AsyncWrite + Send + 'static,
+    {
+        use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+
+        match self {
+            Self::None => Box::pin(writer),
+            Self::Gzip => Box::pin(GzipEncoder::new(writer)),
+            Self::Zstd => Box::pin(ZstdEncoder::new(writer)),
+        }
+    }
+}
+
+/// Decompress `data` if it is compressed, otherwise return it unchanged.
+///
+/// A stream that was cut off, e.g. the raw tap of a killed run, yields
+/// everything up to the cut. Without the `compression` feature compressed
+/// data is an error.
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
+    let compression = Compression::detect(&data);
+    if compression == Compression::None {
+        return Ok(data);
+    }
+    decode(compression, &data)
+}
+
+#[cfg(feature = "compression")]
+fn decode(compression: Compression, data: &[u8]) -> Result<Vec<u8>> {
+    use std::io::{ErrorKind, Read};
+
+    let mut decoded = Vec::new();
+    let read = match compression {
+        Compression::None => return Ok(data.to_vec()),
+        Compression::Gzip => flate2::read::MultiGzDecoder::new(data).read_to_end(&mut decoded),
+        Compression::Zstd => zstd::stream::read::Decoder::new(data)?.read_to_end(&mut decoded),
+    };
+    match read {
+        Ok(_) => Ok(decoded),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            tracing::debug!(?compression, "reading an unfinished compressed stream");
+            Ok(decoded)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode(compression: Compression, _data: &[u8]) -> Result<Vec<u8>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "reading {:?} compressed data requires the `compression` feature",
+            compression
+        ),
+    )
+    .into())
+}
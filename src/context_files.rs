@@ -0,0 +1,132 @@
+//! Embedding explicitly chosen files in the prompt.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::prompt::{InputBlock, PromptInput};
+use crate::types::SystemMessage;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Characters per token assumed by the token estimates.
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+
+/// A file embedded in the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFile {
+    /// The path as given, shown in the file's header.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl ContextFile {
+    /// Read `path`, resolving a relative path against `cwd` if given.
+    pub fn read<P: Into<PathBuf>>(path: P, cwd: Option<&Path>) -> Result<Self> {
+        let path = path.into();
+        let full_path = match cwd {
+            Some(cwd) if path.is_relative() => cwd.join(&path),
+            _ => path.clone(),
+        };
+        let bytes = fs::read(&full_path).map_err(|e| {
+            ClaudeSDKError::invalid_options(format!(
+                "Cannot read context file {}: {}",
+                full_path.display(),
+                e
+            ))
+        })?;
+        let content = String::from_utf8(bytes).map_err(|_| {
+            ClaudeSDKError::invalid_options(format!(
+                "Context file {} is not UTF-8 text",
+                full_path.display()
+            ))
+        })?;
+        Ok(Self { path, content })
+    }
+
+    /// A rough estimate of the tokens the file adds to the prompt.
+    pub fn estimated_tokens(&self) -> usize {
+        (self.content.chars().count() + CHARS_PER_TOKEN - 1) / CHARS_PER_TOKEN
+    }
+
+    /// The file wrapped in a tag naming its path.
+    pub fn render(&self) -> String {
+        let content = self.content.strip_suffix('\n').unwrap_or(&self.content);
+        format!(
+            "<file path=\"{}\">\n{}\n</file>",
+            self.path.display(),
+            content
+        )
+    }
+}
+
+/// The files set with
+/// [`with_context_files`](crate::ClaudeCodeOptions::with_context_files),
+/// read and ready to be placed ahead of the prompt.
+///
+/// The agent sees the files without having to find and read them first,
+/// which suits "review these three files" tasks.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::context_files::ContextFiles;
+///
+/// # fn main() -> claude_code_sdk::Result<()> {
+/// let files = ContextFiles::read(["src/parser.rs", "src/lexer.rs"], None)?;
+/// println!("~{} tokens of context", files.estimated_tokens());
+/// let prompt = files.apply("Review these files for panics.".into());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextFiles {
+    pub files: Vec<ContextFile>,
+}
+
+impl ContextFiles {
+    /// Read each of `paths`, see [`ContextFile::read`].
+    pub fn read<I, P>(paths: I, cwd: Option<&Path>) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        let files = paths
+            .into_iter()
+            .map(|path| ContextFile::read(path, cwd))
+            .collect::<Result<_>>()?;
+        Ok(Self { files })
+    }
+
+    pub fn estimated_tokens(&self) -> usize {
+        self.files.iter().map(ContextFile::estimated_tokens).sum()
+    }
+
+    /// The files, each with a header, as one block of text.
+    pub fn render(&self) -> String {
+        let files: Vec<String> = self.files.iter().map(ContextFile::render).collect();
+        format!(
+            "The following files are provided as context:\n\n{}",
+            files.join("\n\n")
+        )
+    }
+
+    /// `prompt` preceded by the files.
+    pub fn apply(&self, prompt: PromptInput) -> PromptInput {
+        if self.files.is_empty() {
+            return prompt;
+        }
+        match prompt {
+            PromptInput::Text(text) => PromptInput::Text(format!("{}\n\n{}", self.render(), text)),
+            PromptInput::Blocks(blocks) => PromptInput::Blocks(
+                std::iter::once(InputBlock::text(self.render()))
+                    .chain(blocks)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// A system message reporting what was embedded.
+    pub fn summary(&self) -> SystemMessage {
+        SystemMessage::new(format!(
+            "Embedded {} context file(s) in the prompt (~{} tokens)",
+            self.files.len(),
+            self.estimated_tokens()
+        ))
+    }
+}
@@ -0,0 +1,328 @@
+//! The CLI's control protocol.
+//!
+//! When the CLI reads stream-json from stdin, the SDK and the CLI can send
+//! each other control requests next to the conversation's messages. Each
+//! is answered with a control response carrying the same `request_id`:
+//!
+//! ```text
+//! -> {"type":"control_request","request_id":"req_0","request":{"subtype":"initialize","hooks":{...}}}
+//! <- {"type":"control_response","response":{"subtype":"success","request_id":"req_0","response":{...}}}
+//! <- {"type":"control_request","request_id":"cli_4","request":{"subtype":"hook_callback","callback_id":"hook_0","input":{...}}}
+//! -> {"type":"control_response","response":{"subtype":"success","request_id":"cli_4","response":{...}}}
+//! ```
+//!
+//! The SDK uses it to register [lifecycle hooks](crate::hooks::HookRegistration)
+//! before the first prompt and to run them when the CLI calls back, to
+//! carry the MCP messages of [SDK MCP servers](crate::sdk_mcp), and to
+//! change the permission mode or model of a live
+//! [conversation](crate::ClaudeSDKClient::send_message). Control messages
+//! are answered by the transport and never appear in the message stream.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::hooks::{HookInput, HookRegistration};
+use crate::sdk_mcp::SdkMcpServer;
+use crate::transport::CliInput;
+use crate::types::Message;
+use futures::stream::{Stream, StreamExt};
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How long the SDK waits for the CLI to answer one of its requests.
+pub const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A control request, sent by either side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlRequest {
+    pub request_id: String,
+    /// The request's `subtype` and its arguments.
+    pub request: serde_json::Value,
+}
+
+impl ControlRequest {
+    pub fn subtype(&self) -> Option<&str> {
+        self.request.get("subtype")?.as_str()
+    }
+}
+
+/// The answer to a [`ControlRequest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "subtype", rename_all = "snake_case")]
+pub enum ControlResponse {
+    Success {
+        request_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        response: Option<serde_json::Value>,
+    },
+    Error {
+        request_id: String,
+        error: String,
+    },
+}
+
+/// A line of the control protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    ControlRequest(ControlRequest),
+    ControlResponse { response: ControlResponse },
+}
+
+impl ControlMessage {
+    /// Parse `line` if it is a control message rather than a conversation
+    /// message.
+    pub fn parse(line: &[u8]) -> Option<Self> {
+        // Cheap test first: almost every line is a conversation message
+        if !line.windows(8).any(|window| window == b"control_") {
+            return None;
+        }
+        serde_json::from_slice(line).ok()
+    }
+}
+
+/// The `initialize` request registering `hooks`, and the hooks by the ids
+/// the CLI will call them with.
+pub(crate) fn initialize(
+    hooks: &[HookRegistration],
+) -> (ControlRequest, HashMap<String, HookRegistration>) {
+    let mut callbacks = HashMap::new();
+    let mut events: BTreeMap<&str, Vec<serde_json::Value>> = BTreeMap::new();
+    for (index, hook) in hooks.iter().enumerate() {
+        let id = format!("hook_{}", index);
+        events
+            .entry(hook.event.as_str())
+            .or_default()
+            .push(serde_json::json!({
+                "matcher": hook.matcher,
+                "hookCallbackIds": [id],
+            }));
+        callbacks.insert(id, hook.clone());
+    }
+    let request = ControlRequest {
+        request_id: "req_0".to_string(),
+        request: serde_json::json!({ "subtype": "initialize", "hooks": events }),
+    };
+    (request, callbacks)
+}
+
+/// Answers the control requests of one CLI process.
+#[derive(Clone)]
+pub(crate) struct ControlChannel {
+    input: CliInput,
+    hooks: Arc<HashMap<String, HookRegistration>>,
+    servers: Arc<Vec<SdkMcpServer>>,
+    /// Numbers the SDK's own requests; `req_0` is `initialize`.
+    next_request: Arc<AtomicUsize>,
+    /// The SDK's requests waiting for their answer, by request id.
+    answers: Arc<Mutex<HashMap<String, oneshot::Sender<ControlResponse>>>>,
+}
+
+impl ControlChannel {
+    pub(crate) fn new(
+        input: CliInput,
+        hooks: HashMap<String, HookRegistration>,
+        servers: Vec<SdkMcpServer>,
+    ) -> Self {
+        Self {
+            input,
+            hooks: Arc::new(hooks),
+            servers: Arc::new(servers),
+            next_request: Arc::new(AtomicUsize::new(1)),
+            answers: Arc::default(),
+        }
+    }
+
+    /// Send `request` to the CLI and wait for its answer, which arrives
+    /// with the messages, so they must be read meanwhile.
+    ///
+    /// A refusal fails with [`ClaudeSDKError::UnsupportedOption`], no answer
+    /// within [`ANSWER_TIMEOUT`] with [`ClaudeSDKError::CLIConnection`].
+    pub(crate) async fn request(&self, request: serde_json::Value) -> Result<()> {
+        let request_id = format!("req_{}", self.next_request.fetch_add(1, Ordering::Relaxed));
+        let subtype = request["subtype"]
+            .as_str()
+            .unwrap_or("control request")
+            .to_string();
+        tracing::debug!(request_id, ?request, "sending control request");
+        let line = serde_json::to_string(&ControlMessage::ControlRequest(ControlRequest {
+            request_id: request_id.clone(),
+            request,
+        }))?;
+        let (answer, answered) = oneshot::channel();
+        self.answers
+            .lock()
+            .unwrap()
+            .insert(request_id.clone(), answer);
+        if let Err(e) = self.input.send_line(&line).await {
+            self.answers.lock().unwrap().remove(&request_id);
+            return Err(e);
+        }
+        match tokio::time::timeout(ANSWER_TIMEOUT, answered).await {
+            Ok(Ok(ControlResponse::Success { .. })) => Ok(()),
+            Ok(Ok(ControlResponse::Error { error, .. })) => {
+                Err(ClaudeSDKError::unsupported_option(subtype, error))
+            }
+            Ok(Err(_)) | Err(_) => {
+                self.answers.lock().unwrap().remove(&request_id);
+                Err(ClaudeSDKError::cli_connection(format!(
+                    "the CLI did not answer the {} request within {:?}",
+                    subtype, ANSWER_TIMEOUT
+                )))
+            }
+        }
+    }
+
+    /// Handle `line` if it is a control message, returning whether it was.
+    pub(crate) async fn handle_line(&self, line: &[u8]) -> bool {
+        match ControlMessage::parse(line) {
+            Some(ControlMessage::ControlRequest(request)) => {
+                // Answered on a task of its own, so a hook waiting for
+                // something later in the output, such as a free tool slot,
+                // does not stop the output from being read
+                let channel = self.clone();
+                tokio::spawn(async move {
+                    // A panicking hook still gets the CLI an answer, which
+                    // would otherwise wait for one forever
+                    let answered = AssertUnwindSafe(channel.answer(&request))
+                        .catch_unwind()
+                        .await;
+                    let response = answered.unwrap_or_else(|panic| {
+                        let error = panic_message(&*panic);
+                        tracing::error!(request_id = %request.request_id, %error, "hook panicked");
+                        ControlResponse::Error {
+                            request_id: request.request_id.clone(),
+                            error: format!("The SDK panicked answering: {}", error),
+                        }
+                    });
+                    if let Err(e) = channel.send(response).await {
+                        tracing::warn!(error = %e, "failed to answer a control request");
+                    }
+                });
+                true
+            }
+            Some(ControlMessage::ControlResponse { response }) => {
+                let request_id = match &response {
+                    ControlResponse::Success { request_id, .. }
+                    | ControlResponse::Error { request_id, .. } => request_id,
+                };
+                let waiting = self.answers.lock().unwrap().remove(request_id);
+                match (waiting, &response) {
+                    (Some(answer), _) => {
+                        let _ = answer.send(response);
+                    }
+                    (None, ControlResponse::Error { request_id, error }) => {
+                        tracing::warn!(request_id, error, "the CLI refused a control request");
+                    }
+                    (None, ControlResponse::Success { .. }) => {}
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn answer(&self, request: &ControlRequest) -> ControlResponse {
+        let request_id = request.request_id.clone();
+        let result = match request.subtype() {
+            Some("hook_callback") => self.run_hook(&request.request).await,
+            Some("mcp_message") => self.forward_mcp(&request.request).await,
+            subtype => Err(format!(
+                "Unsupported control request: {}",
+                subtype.unwrap_or("(none)")
+            )),
+        };
+        match result {
+            Ok(response) => ControlResponse::Success {
+                request_id,
+                response: Some(response),
+            },
+            Err(error) => ControlResponse::Error { request_id, error },
+        }
+    }
+
+    async fn run_hook(
+        &self,
+        request: &serde_json::Value,
+    ) -> std::result::Result<serde_json::Value, String> {
+        let callback_id = request
+            .get("callback_id")
+            .and_then(|id| id.as_str())
+            .unwrap_or_default();
+        let hook = self
+            .hooks
+            .get(callback_id)
+            .ok_or_else(|| format!("No hook registered as {:?}", callback_id))?;
+        let mut input: HookInput =
+            serde_json::from_value(request.get("input").cloned().unwrap_or_default())
+                .map_err(|e| format!("Invalid hook input: {}", e))?;
+        if input.tool_use_id.is_none() {
+            input.tool_use_id = request
+                .get("tool_use_id")
+                .and_then(|id| id.as_str())
+                .map(str::to_string);
+        }
+        let event = input.hook_event_name;
+        if !hook.applies_to(&input) {
+            tracing::trace!(%event, callback_id, "hook filter did not match");
+            return Ok(serde_json::json!({}));
+        }
+        let output = (hook.callback)(input).await;
+        tracing::debug!(%event, callback_id, ?output, "ran hook");
+        serde_json::to_value(output).map_err(|e| e.to_string())
+    }
+
+    async fn forward_mcp(
+        &self,
+        request: &serde_json::Value,
+    ) -> std::result::Result<serde_json::Value, String> {
+        let name = request
+            .get("server_name")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default();
+        let server = self
+            .servers
+            .iter()
+            .find(|server| server.name == name)
+            .ok_or_else(|| format!("No SDK MCP server named {:?}", name))?;
+        let message = request.get("message").cloned().unwrap_or_default();
+        let response = server.handle(&message).await;
+        Ok(serde_json::json!({ "mcp_response": response }))
+    }
+
+    async fn send(&self, response: ControlResponse) -> Result<()> {
+        let line = serde_json::to_string(&ControlMessage::ControlResponse { response })?;
+        self.input.send_line(&line).await
+    }
+}
+
+/// The message a panic was raised with.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Close the CLI's stdin once the result message arrives. Hook callbacks
+/// are answered on stdin, so it stays open until then.
+pub(crate) fn close_input_on_result(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    input: CliInput,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(stream.then(move |item| {
+        let input = input.clone();
+        async move {
+            if let Ok(Message::Result(_)) = &item {
+                input.close().await;
+            }
+            item
+        }
+    }))
+}
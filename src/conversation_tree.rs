@@ -0,0 +1,278 @@
+//! Parent/child relationships between forked sessions.
+
+use crate::checkpoint::Checkpoint;
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::{ClaudeCodeOptions, Usage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A session in a [`ConversationTree`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationNode {
+    pub session_id: String,
+    pub parent: Option<String>,
+    pub children: Vec<String>,
+    /// The prompt that started the session, or the branch.
+    pub prompt: Option<String>,
+    pub label: Option<String>,
+    pub turn_count: i32,
+    pub usage: Usage,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+/// Sessions and the forks made from them.
+///
+/// Each branch is a session resumed from its parent with
+/// [`fork_session`](ClaudeCodeOptions::with_fork_session), so the parent is
+/// left unchanged and alternatives can be explored side by side. The tree
+/// serializes to JSON and can back a UI that lets users pick a branch to
+/// continue.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::conversation_tree::ConversationTree;
+/// use claude_code_sdk::query_with_handle;
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut tree = ConversationTree::new();
+/// let mut root = query_with_handle("Sketch a caching layer", None).await?;
+/// while let Some(message) = root.next().await {
+///     message?;
+/// }
+/// let root_id = tree.record(None, &root.checkpoint().await?, "Sketch a caching layer")?;
+///
+/// for alternative in ["Use an LRU", "Use a TTL cache"] {
+///     let options = tree.fork_options(&root_id, None)?;
+///     let mut branch = query_with_handle(alternative, Some(options)).await?;
+///     while let Some(message) = branch.next().await {
+///         message?;
+///     }
+///     tree.record(Some(&root_id), &branch.checkpoint().await?, alternative)?;
+/// }
+/// tree.save("conversations.json")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationTree {
+    nodes: BTreeMap<String, ConversationNode>,
+}
+
+impl ConversationTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the session captured in `checkpoint` below `parent`, or as a root.
+    ///
+    /// Returns the session id. Recording a session again updates its turn
+    /// count and usage.
+    pub fn record(
+        &mut self,
+        parent: Option<&str>,
+        checkpoint: &Checkpoint,
+        prompt: &str,
+    ) -> Result<String> {
+        let session_id = checkpoint.session_id.clone();
+        if let Some(node) = self.nodes.get_mut(&session_id) {
+            if node.parent.as_deref() != parent {
+                return Err(ClaudeSDKError::checkpoint(format!(
+                    "Session {} is already recorded with another parent",
+                    session_id
+                )));
+            }
+            node.turn_count = checkpoint.turn_count;
+            node.usage = checkpoint.usage.clone();
+            return Ok(session_id);
+        }
+        if let Some(parent) = parent {
+            if parent == session_id {
+                return Err(ClaudeSDKError::checkpoint(format!(
+                    "Session {} cannot be its own parent",
+                    session_id
+                )));
+            }
+            self.node_mut(parent)?.children.push(session_id.clone());
+        }
+
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        self.nodes.insert(
+            session_id.clone(),
+            ConversationNode {
+                session_id: session_id.clone(),
+                parent: parent.map(str::to_string),
+                children: Vec::new(),
+                prompt: Some(prompt.to_string()),
+                label: None,
+                turn_count: checkpoint.turn_count,
+                usage: checkpoint.usage.clone(),
+                created_at,
+            },
+        );
+        Ok(session_id)
+    }
+
+    pub fn set_label<S: Into<String>>(&mut self, session_id: &str, label: S) -> Result<()> {
+        self.node_mut(session_id)?.label = Some(label.into());
+        Ok(())
+    }
+
+    /// Options that fork a new branch from `session_id`.
+    pub fn fork_options(
+        &self,
+        session_id: &str,
+        options: Option<ClaudeCodeOptions>,
+    ) -> Result<ClaudeCodeOptions> {
+        self.node(session_id)?;
+        Ok(options
+            .unwrap_or_default()
+            .with_resume(session_id)
+            .with_fork_session())
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<&ConversationNode> {
+        self.nodes.get(session_id)
+    }
+
+    pub fn contains(&self, session_id: &str) -> bool {
+        self.nodes.contains_key(session_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Sessions without a parent, oldest first.
+    pub fn roots(&self) -> Vec<&ConversationNode> {
+        let mut roots: Vec<_> = self
+            .nodes
+            .values()
+            .filter(|node| node.parent.is_none())
+            .collect();
+        roots.sort_by_key(|node| node.created_at);
+        roots
+    }
+
+    /// The direct forks of `session_id`, in the order they were recorded.
+    pub fn children(&self, session_id: &str) -> Vec<&ConversationNode> {
+        self.nodes
+            .get(session_id)
+            .map(|node| {
+                node.children
+                    .iter()
+                    .filter_map(|child| self.nodes.get(child))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The sessions from the root down to `session_id`, inclusive.
+    ///
+    /// A tree loaded from edited JSON may have parents forming a cycle; the
+    /// path then starts where the cycle closes.
+    pub fn path(&self, session_id: &str) -> Vec<&ConversationNode> {
+        let mut path = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = self.nodes.get(session_id);
+        while let Some(node) = current {
+            if !seen.insert(node.session_id.as_str()) {
+                break;
+            }
+            path.push(node);
+            current = node
+                .parent
+                .as_deref()
+                .and_then(|parent| self.nodes.get(parent));
+        }
+        path.reverse();
+        path
+    }
+
+    /// Every session below `session_id`, depth first, each once.
+    pub fn descendants(&self, session_id: &str) -> Vec<&ConversationNode> {
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::from([session_id]);
+        let mut stack: Vec<_> = self.children(session_id).into_iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            if !seen.insert(node.session_id.as_str()) {
+                continue;
+            }
+            descendants.push(node);
+            stack.extend(self.children(&node.session_id).into_iter().rev());
+        }
+        descendants
+    }
+
+    /// Sessions that have not been forked.
+    pub fn leaves(&self) -> Vec<&ConversationNode> {
+        self.nodes
+            .values()
+            .filter(|node| node.children.is_empty())
+            .collect()
+    }
+
+    /// Remove `session_id` and everything below it, returning the removed
+    /// nodes.
+    ///
+    /// Only the tree is changed; the CLI keeps the sessions' transcripts.
+    pub fn prune(&mut self, session_id: &str) -> Result<Vec<ConversationNode>> {
+        let node = self.node(session_id)?;
+        let parent = node.parent.clone();
+        let ids: Vec<String> = std::iter::once(session_id.to_string())
+            .chain(
+                self.descendants(session_id)
+                    .into_iter()
+                    .map(|node| node.session_id.clone()),
+            )
+            .collect();
+
+        if let Some(parent) = parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            parent.children.retain(|child| child != session_id);
+        }
+        Ok(ids.iter().filter_map(|id| self.nodes.remove(id)).collect())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    fn node(&self, session_id: &str) -> Result<&ConversationNode> {
+        self.nodes
+            .get(session_id)
+            .ok_or_else(|| ClaudeSDKError::checkpoint(format!("Unknown session {}", session_id)))
+    }
+
+    fn node_mut(&mut self, session_id: &str) -> Result<&mut ConversationNode> {
+        self.nodes
+            .get_mut(session_id)
+            .ok_or_else(|| ClaudeSDKError::checkpoint(format!("Unknown session {}", session_id)))
+    }
+}
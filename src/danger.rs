@@ -0,0 +1,256 @@
+//! Detection of destructive or exfiltrating tool calls.
+
+#[cfg(feature = "subprocess")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::hooks::{HookEvent, HookOutput, HookRegistration};
+#[cfg(feature = "subprocess")]
+use crate::transport::DisposeGuard;
+use crate::types::{ContentBlock, Message, ToolUseBlock};
+#[cfg(feature = "subprocess")]
+use futures::stream::{self, Stream, StreamExt};
+use regex::Regex;
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+
+/// A named pattern matched against the string inputs of tool calls.
+#[derive(Debug, Clone)]
+pub struct DangerPattern {
+    pub name: String,
+    /// The tools the pattern applies to; empty for every tool.
+    pub tools: Vec<String>,
+    pub regex: Regex,
+}
+
+impl DangerPattern {
+    pub fn new(
+        name: &str,
+        tools: &[&str],
+        pattern: &str,
+    ) -> std::result::Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.to_string(),
+            tools: tools.iter().map(|tool| tool.to_string()).collect(),
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    fn applies_to(&self, tool_name: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|tool| tool == tool_name)
+    }
+}
+
+/// A tool call matched by a [`DangerPattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub pattern: String,
+    pub tool_name: String,
+    pub tool_use_id: String,
+    /// The text that matched.
+    pub matched: String,
+}
+
+/// What happens when a dangerous tool call is detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DangerAction {
+    /// Deny the call, kill the CLI process and end the stream with
+    /// [`ClaudeSDKError::DangerousToolUse`](crate::ClaudeSDKError::DangerousToolUse).
+    #[default]
+    Interrupt,
+    /// Log a warning and let the run continue.
+    Warn,
+}
+
+/// A seatbelt on top of permission modes: watches the tool calls Claude
+/// makes and stops the run when one matches a dangerous pattern.
+///
+/// The built-in patterns catch recursive forced deletes, git force pushes
+/// and commands sending credentials over the network. The subprocess
+/// transport checks each call in a `PreToolUse` [hook](crate::hooks) and
+/// denies a dangerous one before the CLI runs it, then interrupts the run.
+/// A CLI that cannot call back into the SDK, or a transport other than the
+/// subprocess one, only reports the call once made; the run is interrupted
+/// as soon as it is, so combine the detector with a restrictive permission
+/// mode there to keep the call from executing at all.
+///
+/// ```rust
+/// use claude_code_sdk::danger::{DangerPattern, DangerousCommandDetector};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let detector = DangerousCommandDetector::new()
+///     .with_pattern(DangerPattern::new("drop_table", &["Bash"], r"(?i)drop\s+table").unwrap());
+/// let options = ClaudeCodeOptions::new().with_danger_detector(detector);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DangerousCommandDetector {
+    patterns: Vec<DangerPattern>,
+    action: DangerAction,
+}
+
+impl Default for DangerousCommandDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DangerousCommandDetector {
+    /// A detector with the built-in patterns.
+    pub fn new() -> Self {
+        let builtin = [
+            (
+                "recursive_delete",
+                &["Bash"][..],
+                r"\brm\s+(?:-\w+\s+)*(?:-[a-zA-Z]*[rR][a-zA-Z]*f|-[a-zA-Z]*f[a-zA-Z]*[rR]|-[rR]\s+-f|-f\s+-[rR]|--recursive\s+--force|--force\s+--recursive)\b",
+            ),
+            (
+                "force_push",
+                &["Bash"][..],
+                r"\bgit\s+push\b[^\n;&|]*(?:\s--force(?:-with-lease)?\b|\s-f\b|\s\+\S)",
+            ),
+            (
+                "credential_exfiltration",
+                &["Bash", "Edit", "MultiEdit", "Write"][..],
+                r"(?i)(?:\b(?:curl|wget|nc|ncat|scp)\b[^\n]*(?:\.ssh/|\.aws/credentials|\.netrc|id_rsa|id_ed25519|\$\{?\w*(?:token|secret|api_key|password)\w*)|(?:\.ssh/|\.aws/credentials|\.netrc|id_rsa|id_ed25519|\bprintenv\b|\benv\b)[^\n]*\|\s*(?:curl|wget|nc|ncat)\b)",
+            ),
+        ];
+        let patterns = builtin
+            .iter()
+            .map(|(name, tools, pattern)| {
+                DangerPattern::new(name, tools, pattern).expect("built-in pattern is valid")
+            })
+            .collect();
+        Self {
+            patterns,
+            action: DangerAction::default(),
+        }
+    }
+
+    /// A detector without any patterns.
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+            action: DangerAction::default(),
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: DangerPattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    pub fn with_action(mut self, action: DangerAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn action(&self) -> DangerAction {
+        self.action
+    }
+
+    pub fn patterns(&self) -> &[DangerPattern] {
+        &self.patterns
+    }
+
+    /// Check a single tool call against the patterns.
+    pub fn check(&self, tool_use: &ToolUseBlock) -> Option<Detection> {
+        let mut inputs = Vec::new();
+        collect_strings(&tool_use.input, &mut inputs);
+
+        self.patterns
+            .iter()
+            .filter(|pattern| pattern.applies_to(&tool_use.name))
+            .find_map(|pattern| {
+                let matched = inputs.iter().find_map(|input| pattern.regex.find(input))?;
+                Some(Detection {
+                    pattern: pattern.name.clone(),
+                    tool_name: tool_use.name.clone(),
+                    tool_use_id: tool_use.id.clone(),
+                    matched: matched.as_str().to_string(),
+                })
+            })
+    }
+
+    /// The first dangerous tool call in a message.
+    pub fn scan(&self, message: &Message) -> Option<Detection> {
+        let content = match message {
+            Message::User(msg) => &msg.content,
+            Message::Assistant(msg) => &msg.content,
+            _ => return None,
+        };
+        content.iter().find_map(|block| match block {
+            ContentBlock::ToolUse(tool_use) => self.check(tool_use),
+            _ => None,
+        })
+    }
+}
+
+fn collect_strings<'a>(value: &'a serde_json::Value, out: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s),
+        serde_json::Value::Array(values) => values.iter().for_each(|v| collect_strings(v, out)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// A `PreToolUse` hook denying the tool calls `detector` finds dangerous,
+/// or `None` if it only warns.
+#[cfg(feature = "subprocess")]
+pub(crate) fn deny_dangerous_calls(detector: DangerousCommandDetector) -> Option<HookRegistration> {
+    if detector.action != DangerAction::Interrupt {
+        return None;
+    }
+    Some(HookRegistration::new(HookEvent::PreToolUse, move |input| {
+        let tool_use = ToolUseBlock::new(
+            input.tool_use_id.unwrap_or_default(),
+            input.tool_name.unwrap_or_default(),
+            input.tool_input.unwrap_or_default(),
+        );
+        let output = match detector.check(&tool_use) {
+            Some(detection) => {
+                tracing::warn!(?detection, "denying dangerous tool call");
+                HookOutput::deny(format!(
+                    "The call matches the dangerous pattern {:?} and is not allowed",
+                    detection.pattern
+                ))
+            }
+            None => HookOutput::allow(),
+        };
+        async move { output }
+    }))
+}
+
+/// Stop the stream, and the process behind `guard`, at the first dangerous
+/// tool call.
+#[cfg(feature = "subprocess")]
+pub(crate) fn interrupt_on_danger(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    detector: DangerousCommandDetector,
+    guard: Option<DisposeGuard>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(stream::unfold(
+        Some((stream, detector, guard)),
+        |state| async move {
+            let (mut stream, detector, guard) = state?;
+            let item = stream.next().await?;
+            let detection = match &item {
+                Ok(message) => detector.scan(message),
+                Err(_) => None,
+            };
+            match (detection, detector.action) {
+                (Some(detection), DangerAction::Interrupt) => {
+                    tracing::warn!(?detection, "interrupting run on dangerous tool call");
+                    if let Some(guard) = &guard {
+                        let _ = guard.dispose().await;
+                    }
+                    Some((Err(ClaudeSDKError::DangerousToolUse { detection }), None))
+                }
+                (Some(detection), DangerAction::Warn) => {
+                    tracing::warn!(?detection, "dangerous tool call");
+                    Some((item, Some((stream, detector, guard))))
+                }
+                (None, _) => Some((item, Some((stream, detector, guard)))),
+            }
+        },
+    ))
+}
@@ -0,0 +1,329 @@
+//! Exporting transcripts as fine-tuning and eval datasets.
+
+use crate::annotations::Annotation;
+use crate::debugger::Debugger;
+use crate::error::Result;
+use crate::types::{ContentBlock, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// The messages-array layout of each exported line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetFormat {
+    /// `{"system": ..., "messages": [...]}` with Messages API content
+    /// blocks; tool calls are `tool_use` and `tool_result` blocks.
+    #[default]
+    Anthropic,
+    /// `{"messages": [...]}` in the chat completions layout; tool calls are
+    /// `tool_calls` on assistant messages answered by `tool` messages.
+    OpenAi,
+}
+
+/// A transcript to export: the messages of a run and the prompt and system
+/// prompt that started it, which the CLI does not echo.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    pub system: Option<String>,
+    pub prompt: Option<String>,
+    pub messages: Vec<Message>,
+    /// Exported as an `annotations` array on the example. Their
+    /// `message_seq` counts the messages above, not the exported ones.
+    pub annotations: Vec<Annotation>,
+}
+
+impl Conversation {
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self {
+            messages,
+            ..Self::default()
+        }
+    }
+
+    /// Load an NDJSON transcript, see [`Debugger::load`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Debugger::load(path)?.messages().to_vec()))
+    }
+
+    pub fn with_system<S: Into<String>>(mut self, system: S) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    pub fn with_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    ToolCall {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        id: String,
+        content: String,
+        is_error: bool,
+    },
+}
+
+impl DatasetFormat {
+    /// One dataset example for `conversation`.
+    ///
+    /// Tool calls are normalized: calls without a result and results without
+    /// a call are dropped, ids are renumbered in call order so identical runs
+    /// export identically, and non-object inputs are wrapped as
+    /// `{"input": ...}`. System, result and SDK messages are left out, and
+    /// consecutive messages of the same role are merged.
+    pub fn example(&self, conversation: &Conversation) -> Value {
+        let prefix = match self {
+            Self::Anthropic => "toolu",
+            Self::OpenAi => "call",
+        };
+        let turns = normalize(conversation, prefix);
+        let mut example = match self {
+            Self::Anthropic => anthropic_example(conversation.system.as_deref(), &turns),
+            Self::OpenAi => openai_example(conversation.system.as_deref(), &turns),
+        };
+        if !conversation.annotations.is_empty() {
+            example["annotations"] = json!(conversation.annotations);
+        }
+        example
+    }
+
+    /// The examples for `conversations`, one per line.
+    pub fn to_jsonl<'a, I>(&self, conversations: I) -> Result<String>
+    where
+        I: IntoIterator<Item = &'a Conversation>,
+    {
+        let mut jsonl = String::new();
+        for conversation in conversations {
+            jsonl.push_str(&serde_json::to_string(&self.example(conversation))?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Append the examples for `conversations` to the file at `path`.
+    pub fn append_jsonl<'a, P, I>(&self, path: P, conversations: I) -> Result<()>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = &'a Conversation>,
+    {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(self.to_jsonl(conversations)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn normalize(conversation: &Conversation, prefix: &str) -> Vec<(Role, Vec<Part>)> {
+    let mut raw: Vec<(Role, Vec<Part>)> = Vec::new();
+    if let Some(prompt) = &conversation.prompt {
+        raw.push((Role::User, vec![Part::Text(prompt.clone())]));
+    }
+    for message in &conversation.messages {
+        let (role, content) = match message {
+            Message::User(msg) => (Role::User, &msg.content),
+            Message::Assistant(msg) => (Role::Assistant, &msg.content),
+            _ => continue,
+        };
+        let parts = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(block) if block.text.trim().is_empty() => None,
+                ContentBlock::Text(block) => Some(Part::Text(block.text.clone())),
+                ContentBlock::ToolUse(block) => Some(Part::ToolCall {
+                    id: block.id.clone(),
+                    name: block.name.clone(),
+                    input: match &block.input {
+                        Value::Object(_) => block.input.clone(),
+                        other => json!({ "input": other }),
+                    },
+                }),
+                ContentBlock::ToolResult(block) => Some(Part::ToolResult {
+                    id: block.tool_use_id.clone(),
+                    content: block.content.clone().unwrap_or_default(),
+                    is_error: block.is_error == Some(true),
+                }),
+                ContentBlock::Image(_)
+                | ContentBlock::Thinking(_)
+                | ContentBlock::RedactedThinking(_) => None,
+            })
+            .collect();
+        raw.push((role, parts));
+    }
+
+    let called: HashSet<&str> = parts_of(&raw)
+        .filter_map(|part| match part {
+            Part::ToolCall { id, .. } => Some(id.as_str()),
+            _ => None,
+        })
+        .collect();
+    let answered: HashSet<&str> = parts_of(&raw)
+        .filter_map(|part| match part {
+            Part::ToolResult { id, .. } => Some(id.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut ids = HashMap::new();
+    for part in parts_of(&raw) {
+        if let Part::ToolCall { id, .. } = part {
+            if answered.contains(id.as_str()) && !ids.contains_key(id) {
+                ids.insert(id.clone(), format!("{}_{}", prefix, ids.len() + 1));
+            }
+        }
+    }
+
+    let mut turns: Vec<(Role, Vec<Part>)> = Vec::new();
+    for (role, parts) in &raw {
+        let parts: Vec<Part> = parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::ToolCall { id, name, input } => ids.get(id).map(|id| Part::ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                }),
+                Part::ToolResult {
+                    id,
+                    content,
+                    is_error,
+                } if called.contains(id.as_str()) => ids.get(id).map(|id| Part::ToolResult {
+                    id: id.clone(),
+                    content: content.clone(),
+                    is_error: *is_error,
+                }),
+                Part::ToolResult { .. } => None,
+                Part::Text(text) => Some(Part::Text(text.clone())),
+            })
+            .collect();
+        if parts.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last, existing)) if last == role => existing.extend(parts),
+            _ => turns.push((*role, parts)),
+        }
+    }
+    turns
+}
+
+fn parts_of(turns: &[(Role, Vec<Part>)]) -> impl Iterator<Item = &Part> {
+    turns.iter().flat_map(|(_, parts)| parts)
+}
+
+fn anthropic_example(system: Option<&str>, turns: &[(Role, Vec<Part>)]) -> Value {
+    let messages: Vec<Value> = turns
+        .iter()
+        .map(|(role, parts)| {
+            let content: Vec<Value> = parts
+                .iter()
+                .map(|part| match part {
+                    Part::Text(text) => json!({ "type": "text", "text": text }),
+                    Part::ToolCall { id, name, input } => {
+                        json!({ "type": "tool_use", "id": id, "name": name, "input": input })
+                    }
+                    Part::ToolResult {
+                        id,
+                        content,
+                        is_error,
+                    } => json!({
+                        "type": "tool_result",
+                        "tool_use_id": id,
+                        "content": content,
+                        "is_error": is_error,
+                    }),
+                })
+                .collect();
+            let role = match role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            json!({ "role": role, "content": content })
+        })
+        .collect();
+    match system {
+        Some(system) => json!({ "system": system, "messages": messages }),
+        None => json!({ "messages": messages }),
+    }
+}
+
+fn openai_example(system: Option<&str>, turns: &[(Role, Vec<Part>)]) -> Value {
+    let mut messages: Vec<Value> = system
+        .map(|system| json!({ "role": "system", "content": system }))
+        .into_iter()
+        .collect();
+    for (role, parts) in turns {
+        let text: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        let text = (!text.is_empty()).then(|| text.join("\n"));
+        match role {
+            Role::Assistant => {
+                let tool_calls: Vec<Value> = parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        Part::ToolCall { id, name, input } => Some(json!({
+                            "id": id,
+                            "type": "function",
+                            "function": { "name": name, "arguments": input.to_string() },
+                        })),
+                        _ => None,
+                    })
+                    .collect();
+                let mut message = json!({ "role": "assistant", "content": text });
+                if !tool_calls.is_empty() {
+                    message["tool_calls"] = Value::Array(tool_calls);
+                }
+                messages.push(message);
+            }
+            Role::User => {
+                // Tool results become `tool` messages, which must directly
+                // follow the assistant message making the calls
+                for part in parts {
+                    if let Part::ToolResult { id, content, .. } = part {
+                        messages.push(json!({
+                            "role": "tool",
+                            "tool_call_id": id,
+                            "content": content,
+                        }));
+                    }
+                }
+                if let Some(text) = text {
+                    messages.push(json!({ "role": "user", "content": text }));
+                }
+            }
+        }
+    }
+    json!({ "messages": messages })
+}
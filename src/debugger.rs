@@ -0,0 +1,373 @@
+//! Stepping through a recorded run.
+
+use crate::compression;
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::handle::QueryHandle;
+use crate::progress::ToolProgressEvent;
+use crate::types::{
+    ClaudeCodeOptions, ContentBlock, Message, ToolResultBlock, ToolUseBlock, Usage,
+};
+use std::fs;
+use std::path::Path;
+
+/// How much of a tool result is included when replaying history.
+const REPLAY_RESULT_CHARS: usize = 2000;
+
+/// Where [`Debugger::continue_to_breakpoint`] stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// A call to the named tool.
+    Tool(String),
+    /// A tool result reported as an error.
+    ToolError,
+    /// The message at this index.
+    Index(usize),
+}
+
+impl Breakpoint {
+    pub fn tool<S: Into<String>>(name: S) -> Self {
+        Self::Tool(name.into())
+    }
+
+    fn hit(&self, index: usize, message: &Message) -> bool {
+        match self {
+            Self::Tool(name) => tool_uses(message).any(|tool_use| &tool_use.name == name),
+            Self::ToolError => tool_results(message).any(|result| result.is_error == Some(true)),
+            Self::Index(at) => *at == index,
+        }
+    }
+}
+
+/// A tool call seen up to the current position.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    /// The index of the message making the call.
+    pub index: usize,
+    pub tool_use: ToolUseBlock,
+    /// The result, if it was reported by the current position.
+    pub result: Option<ToolResultBlock>,
+}
+
+/// The state of the run after the message at `index`.
+#[derive(Debug, Clone, Default)]
+pub struct DebugState {
+    pub index: usize,
+    pub turn_count: i32,
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Usage,
+    pub session_id: Option<String>,
+    /// The most recent assistant text.
+    pub last_text: Option<String>,
+}
+
+impl DebugState {
+    /// Tool calls still waiting for their result.
+    pub fn pending_tools(&self) -> impl Iterator<Item = &ToolCall> {
+        self.tool_calls.iter().filter(|call| call.result.is_none())
+    }
+}
+
+/// Time-travel navigation over the messages of a recorded run.
+///
+/// Load a transcript written by a raw tap (`with_raw_tap`) or any NDJSON
+/// file of messages, then step through it, jump between tool
+/// calls or run to a breakpoint, inspecting the [`DebugState`] at each point.
+/// [`rerun`](Self::rerun) continues from the current position against a live
+/// session.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::debugger::{Breakpoint, Debugger};
+///
+/// # fn main() -> claude_code_sdk::Result<()> {
+/// let mut debugger = Debugger::load("wire.ndjson")?;
+/// debugger.add_breakpoint(Breakpoint::tool("Bash"));
+/// while let Some(index) = debugger.continue_to_breakpoint() {
+///     let state = debugger.state();
+///     println!("#{}: {} tool calls so far", index, state.tool_calls.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    messages: Vec<Message>,
+    position: Option<usize>,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    /// A debugger positioned before the first message.
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self {
+            messages,
+            position: None,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    /// Parse an NDJSON transcript. Blank lines and tool progress
+    /// notifications are skipped.
+    pub fn from_ndjson(ndjson: &str) -> Result<Self> {
+        let mut messages = Vec::new();
+        for (number, line) in ndjson.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |e: serde_json::Error| {
+                ClaudeSDKError::cli_json_decode(format!("Line {}: {}", number + 1, e))
+            };
+            let value: serde_json::Value = serde_json::from_str(line).map_err(invalid)?;
+            if ToolProgressEvent::parse(&value).is_some() {
+                continue;
+            }
+            messages.push(serde_json::from_value(value).map_err(invalid)?);
+        }
+        Ok(Self::new(messages))
+    }
+
+    /// Read an NDJSON transcript, decompressing it if it is gzip or zstd
+    /// compressed, see [`crate::compression`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let ndjson = String::from_utf8(compression::decompress(fs::read(path)?)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Self::from_ndjson(&ndjson)
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// The index of the current message; `None` before the first step.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.messages.get(self.position?)
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.messages.is_empty() || self.position == Some(self.messages.len() - 1)
+    }
+
+    /// Move to the next message.
+    pub fn step(&mut self) -> Option<&Message> {
+        let next = self.position.map_or(0, |position| position + 1);
+        self.seek(next)
+    }
+
+    /// Move to the previous message.
+    pub fn step_back(&mut self) -> Option<&Message> {
+        match self.position {
+            Some(0) | None => {
+                self.position = None;
+                None
+            }
+            Some(position) => self.seek(position - 1),
+        }
+    }
+
+    /// Move to the message at `index`; stays put if it is out of range.
+    pub fn seek(&mut self, index: usize) -> Option<&Message> {
+        if index >= self.messages.len() {
+            return None;
+        }
+        self.position = Some(index);
+        self.messages.get(index)
+    }
+
+    /// Move back before the first message.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Move to the next message calling a tool, returning the first call.
+    pub fn next_tool(&mut self) -> Option<&ToolUseBlock> {
+        let index = self.find_next(|_, message| tool_uses(message).next().is_some())?;
+        self.position = Some(index);
+        tool_uses(&self.messages[index]).next()
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Move to the next message hitting a breakpoint, returning its index.
+    ///
+    /// Without a hit the debugger stays put and `None` is returned.
+    pub fn continue_to_breakpoint(&mut self) -> Option<usize> {
+        let breakpoints = &self.breakpoints;
+        let index = self.find_next(|index, message| {
+            breakpoints
+                .iter()
+                .any(|breakpoint| breakpoint.hit(index, message))
+        })?;
+        self.position = Some(index);
+        Some(index)
+    }
+
+    /// The state of the run after the current message.
+    pub fn state(&self) -> DebugState {
+        let mut state = DebugState::default();
+        let Some(position) = self.position else {
+            return state;
+        };
+        state.index = position;
+
+        for (index, message) in self.messages[..=position].iter().enumerate() {
+            for tool_use in tool_uses(message) {
+                state.tool_calls.push(ToolCall {
+                    index,
+                    tool_use: tool_use.clone(),
+                    result: None,
+                });
+            }
+            for result in tool_results(message) {
+                if let Some(call) = state
+                    .tool_calls
+                    .iter_mut()
+                    .find(|call| call.tool_use.id == result.tool_use_id)
+                {
+                    call.result = Some(result.clone());
+                }
+            }
+            match message {
+                Message::Assistant(msg) => {
+                    state.turn_count += 1;
+                    state.last_text = text(&msg.content).or(state.last_text);
+                }
+                Message::Result(result) => {
+                    state.usage.add_result(result);
+                    if let Some(session_id) = &result.session_id {
+                        state.session_id = Some(session_id.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    /// The conversation up to the current message as plain text, for
+    /// replaying it to a new session.
+    pub fn history(&self) -> String {
+        let Some(position) = self.position else {
+            return String::new();
+        };
+        let mut history = Vec::new();
+        for message in &self.messages[..=position] {
+            let (role, content) = match message {
+                Message::User(msg) => ("user", &msg.content),
+                Message::Assistant(msg) => ("assistant", &msg.content),
+                _ => continue,
+            };
+            for block in content {
+                history.push(match block {
+                    ContentBlock::Text(block) => format!("[{}] {}", role, block.text),
+                    ContentBlock::ToolUse(block) => {
+                        format!("[tool call {}] {}", block.name, block.input)
+                    }
+                    ContentBlock::ToolResult(block) => {
+                        let content = block.content.as_deref().unwrap_or_default();
+                        let content: String = content.chars().take(REPLAY_RESULT_CHARS).collect();
+                        match block.is_error {
+                            Some(true) => format!("[tool error] {}", content),
+                            _ => format!("[tool result] {}", content),
+                        }
+                    }
+                    ContentBlock::Image(_) => format!("[{}] (image)", role),
+                    ContentBlock::Thinking(_) | ContentBlock::RedactedThinking(_) => continue,
+                });
+            }
+        }
+        history.join("\n")
+    }
+
+    /// Options and prompt for continuing from the current position.
+    ///
+    /// At the end of a run with a known session id, the session is forked
+    /// so the recorded one stays unchanged. The CLI cannot resume a session
+    /// part way through, so from any other position a new session is started
+    /// with the history up to the current message replayed in the prompt.
+    pub fn rerun_options(
+        &self,
+        prompt: &str,
+        options: Option<ClaudeCodeOptions>,
+    ) -> (String, ClaudeCodeOptions) {
+        let options = options.unwrap_or_default();
+        match self.state().session_id {
+            Some(session_id) if self.is_at_end() => (
+                prompt.to_string(),
+                options.with_resume(session_id).with_fork_session(),
+            ),
+            _ => (
+                format!(
+                    "This is the transcript of an earlier attempt at the task, up to the point \
+                     where it should be continued:\n\n{}\n\nContinue from this point: {}",
+                    self.history(),
+                    prompt
+                ),
+                options,
+            ),
+        }
+    }
+
+    /// Continue from the current position against a live session, see
+    /// [`rerun_options`](Self::rerun_options).
+    #[cfg(feature = "subprocess")]
+    pub async fn rerun(
+        &self,
+        prompt: &str,
+        options: Option<ClaudeCodeOptions>,
+    ) -> Result<QueryHandle> {
+        let (prompt, options) = self.rerun_options(prompt, options);
+        crate::query_with_handle(&prompt, Some(options)).await
+    }
+
+    fn find_next(&self, matches: impl Fn(usize, &Message) -> bool) -> Option<usize> {
+        let start = self.position.map_or(0, |position| position + 1);
+        (start..self.messages.len()).find(|&index| matches(index, &self.messages[index]))
+    }
+}
+
+fn content(message: &Message) -> &[ContentBlock] {
+    match message {
+        Message::User(msg) => &msg.content,
+        Message::Assistant(msg) => &msg.content,
+        _ => &[],
+    }
+}
+
+fn tool_uses(message: &Message) -> impl Iterator<Item = &ToolUseBlock> {
+    content(message).iter().filter_map(|block| match block {
+        ContentBlock::ToolUse(tool_use) => Some(tool_use),
+        _ => None,
+    })
+}
+
+fn tool_results(message: &Message) -> impl Iterator<Item = &ToolResultBlock> {
+    content(message).iter().filter_map(|block| match block {
+        ContentBlock::ToolResult(result) => Some(result),
+        _ => None,
+    })
+}
+
+fn text(content: &[ContentBlock]) -> Option<String> {
+    let text: Vec<&str> = content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(block) => Some(block.text.as_str()),
+            _ => None,
+        })
+        .collect();
+    (!text.is_empty()).then(|| text.join("\n"))
+}
@@ -0,0 +1,167 @@
+//! Capturing the CLI output that fails to decode.
+//!
+//! When a line of output cannot be decoded, the
+//! [`CLIJSONDecode`](crate::ClaudeSDKError::CLIJSONDecode) error carries the
+//! offending line, truncated, and the path inside the message where decoding
+//! failed, so protocol drift between the SDK and the CLI can be diagnosed
+//! from the error alone. [`DecodeDiagnostics`] redacts the captured line and
+//! saves failing lines in full to a directory.
+
+use crate::anonymize::Anonymizer;
+use crate::error::ClaudeSDKError;
+use crate::sdk_info::SdkInfo;
+use crate::types::{AssistantMessage, ResultMessage, StreamEvent, SystemMessage, UserMessage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Lines longer than this are truncated in decode errors by default.
+pub const MAX_PAYLOAD_LEN: usize = 512;
+
+/// How lines of CLI output that fail to decode are captured.
+///
+/// ```rust
+/// use claude_code_sdk::diagnostics::DecodeDiagnostics;
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new().with_decode_diagnostics(
+///     DecodeDiagnostics::new()
+///         .redacted()
+///         .with_dump_dir("target/claude-diagnostics"),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeDiagnostics {
+    /// Scrub secrets, home directories and user names from captured lines
+    /// with the default [`Anonymizer`].
+    #[serde(default)]
+    pub redact: bool,
+    /// Save each failing line, untruncated, as a JSON file in this
+    /// directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dump_dir: Option<PathBuf>,
+    /// Truncate lines in errors to this many characters, [`MAX_PAYLOAD_LEN`]
+    /// by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_payload_len: Option<usize>,
+}
+
+impl DecodeDiagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn redacted(mut self) -> Self {
+        self.redact = true;
+        self
+    }
+
+    pub fn with_dump_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.dump_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_max_payload_len(mut self, len: usize) -> Self {
+        self.max_payload_len = Some(len);
+        self
+    }
+
+    /// Replace the line captured in the decode error `error` with `line`,
+    /// redacted and truncated as configured, and save it to the dump
+    /// directory. Other errors are returned unchanged.
+    pub fn capture(&self, line: &str, error: ClaudeSDKError) -> ClaudeSDKError {
+        let ClaudeSDKError::CLIJSONDecode { message, path, .. } = error else {
+            return error;
+        };
+        let line = if self.redact {
+            Anonymizer::new().scrub_text(line)
+        } else {
+            line.to_string()
+        };
+        let dump = self.dump_dir.as_deref().and_then(|dir| {
+            dump(dir, &message, path.as_deref(), &line)
+                .map_err(|e| {
+                    tracing::warn!(dir = %dir.display(), error = %e, "failed to save undecodable CLI output");
+                })
+                .ok()
+        });
+        let max_len = self.max_payload_len.unwrap_or(MAX_PAYLOAD_LEN);
+        ClaudeSDKError::CLIJSONDecode {
+            message,
+            path,
+            payload: Some(truncate_payload(&line, max_len)),
+            dump,
+        }
+    }
+}
+
+fn dump(dir: &Path, message: &str, path: Option<&str>, line: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let file = dir.join(format!("decode-{}.json", uuid::Uuid::new_v4()));
+    let document = serde_json::json!({
+        "error": message,
+        "path": path,
+        "payload": line,
+    });
+    std::fs::write(&file, serde_json::to_vec_pretty(&document)?)?;
+    tracing::info!(file = %file.display(), "saved undecodable CLI output");
+    Ok(file)
+}
+
+/// The decode error for `line`, which failed to decode with `error`.
+pub(crate) fn decode_error(line: &str, error: &serde_json::Error) -> ClaudeSDKError {
+    decode_failure(line, format!("Failed to parse JSON: {}", error), None)
+}
+
+/// The decode error for the message `value` read from `line`, locating the
+/// failure inside the message by its `type`.
+pub(crate) fn message_decode_error(
+    line: &str,
+    value: &serde_json::Value,
+    error: &serde_json::Error,
+) -> ClaudeSDKError {
+    fn locate<T: serde::de::DeserializeOwned>(
+        value: &serde_json::Value,
+    ) -> Option<(String, String)> {
+        let error = serde_path_to_error::deserialize::<_, T>(value).err()?;
+        Some((error.path().to_string(), error.inner().to_string()))
+    }
+
+    let located = match value["type"].as_str() {
+        Some("user") => locate::<UserMessage>(value),
+        Some("assistant") => locate::<AssistantMessage>(value),
+        Some("system") => locate::<SystemMessage>(value),
+        Some("result") => locate::<ResultMessage>(value),
+        Some("sdk_info") => locate::<SdkInfo>(value),
+        Some("stream_event") => locate::<StreamEvent>(value),
+        Some(other) => Some((".".to_string(), format!("unknown message type `{}`", other))),
+        None => Some((".".to_string(), "missing field `type`".to_string())),
+    };
+    match located {
+        Some((path, message)) => decode_failure(
+            line,
+            format!("Failed to parse JSON: {}", message),
+            Some(path),
+        ),
+        None => decode_failure(line, format!("Failed to parse JSON: {}", error), None),
+    }
+}
+
+fn decode_failure(line: &str, message: String, path: Option<String>) -> ClaudeSDKError {
+    let payload = truncate_payload(line, MAX_PAYLOAD_LEN);
+    tracing::debug!(path = path.as_deref(), payload = %payload, "{}", message);
+    ClaudeSDKError::CLIJSONDecode {
+        message,
+        path,
+        payload: Some(payload),
+        dump: None,
+    }
+}
+
+fn truncate_payload(line: &str, max_len: usize) -> String {
+    let len = line.chars().count();
+    if len <= max_len {
+        return line.to_string();
+    }
+    let head: String = line.chars().take(max_len).collect();
+    format!("{}... ({} chars)", head, len)
+}
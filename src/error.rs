@@ -1,5 +1,19 @@
+use crate::api_error::ApiErrorKind;
+use crate::danger::Detection;
+use crate::file_lock::LockConflict;
+use crate::interaction::InteractionKind;
+use crate::workspace_guard::UnsafeWorkspaceReason;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Arguments longer than this are truncated in an `ErrorContext`.
+const MAX_ARG_LEN: usize = 80;
+
 #[derive(Error, Debug)]
 pub enum ClaudeSDKError {
     #[error("CLI connection error: {message}")]
@@ -11,8 +25,122 @@ pub enum ClaudeSDKError {
     #[error("Process failed with exit code {exit_code}: {stderr}")]
     Process { exit_code: i32, stderr: String },
 
-    #[error("Failed to decode JSON response: {message}")]
-    CLIJSONDecode { message: String },
+    #[error("Failed to decode JSON response: {message}{}", decode_details(.path, .payload, .dump))]
+    CLIJSONDecode {
+        message: String,
+        /// Where in the message decoding failed, e.g. `content[0].text`.
+        path: Option<String>,
+        /// The offending line of CLI output, truncated and possibly redacted,
+        /// see [`DecodeDiagnostics`](crate::diagnostics::DecodeDiagnostics).
+        payload: Option<String>,
+        /// Where the offending line was saved in full.
+        dump: Option<PathBuf>,
+    },
+
+    #[error("Claude API error ({kind}): {message}")]
+    Api {
+        kind: ApiErrorKind,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Invalid options: {message}")]
+    InvalidOptions { message: String },
+
+    #[error("Unsupported option {option}: {message}")]
+    UnsupportedOption { option: String, message: String },
+
+    #[error("Tool {tool} is disabled in this analysis-only build")]
+    ToolDisabled { tool: String },
+
+    #[error("No external tool call {tool_use_id} is waiting for a result")]
+    UnknownToolUse { tool_use_id: String },
+
+    #[error("No message {message_seq} has been received yet")]
+    UnknownMessage { message_seq: usize },
+
+    #[error(
+        "The Claude Code CLI requires SDK protocol {required}, but claude-code-sdk {sdk_version} \
+         supports protocol {supported}. Upgrade claude-code-sdk (`cargo update -p claude-code-sdk`) \
+         or install an older Claude Code CLI"
+    )]
+    IncompatibleProtocol {
+        required: u32,
+        supported: u32,
+        sdk_version: String,
+    },
+
+    #[error("Query was cancelled before it started")]
+    Cancelled,
+
+    #[error(
+        "No output from {} within the first-token deadline of {deadline:?}",
+        model.as_deref().unwrap_or("the default model")
+    )]
+    SlowStart {
+        deadline: Duration,
+        model: Option<String>,
+    },
+
+    #[error("Checkpoint error: {message}")]
+    Checkpoint { message: String },
+
+    #[error("Unsafe workspace {}: {reason}", path.display())]
+    UnsafeWorkspace {
+        path: PathBuf,
+        reason: UnsafeWorkspaceReason,
+    },
+
+    #[error(
+        "Run interrupted: dangerous {} call matched `{}`: {}",
+        detection.tool_name,
+        detection.pattern,
+        detection.matched
+    )]
+    DangerousToolUse { detection: Detection },
+
+    #[error(
+        "Run interrupted: {} of {} is locked by run {}",
+        conflict.tool_name,
+        conflict.path.display(),
+        conflict.holder
+    )]
+    FileLockConflict { conflict: LockConflict },
+
+    #[error("A run with idempotency key {idempotency_key} is still in progress")]
+    RunInProgress { idempotency_key: String },
+
+    #[error(
+        "Run interrupted: {output_tokens} output tokens in one turn exceed the budget of {budget}"
+    )]
+    OutputBudgetExceeded { budget: i32, output_tokens: i32 },
+
+    #[error(
+        "Claude Code is waiting for interactive input ({kind}): {}",
+        kind.remediation()
+    )]
+    InteractionRequired {
+        kind: InteractionKind,
+        /// The line the CLI asked with.
+        prompt: String,
+    },
+
+    #[error("Sandbox error: {message}")]
+    Sandbox { message: String },
+
+    #[error("Cargo error: {message}")]
+    Cargo { message: String },
+
+    #[error("Webhook delivery to {url} failed: {message}")]
+    Webhook { url: String, message: String },
+
+    #[error("GitHub request to {url} failed: {message}")]
+    GitHub {
+        url: String,
+        /// The HTTP status GitHub answered with, if it answered.
+        status: Option<u16>,
+        message: String,
+    },
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -20,11 +148,228 @@ pub enum ClaudeSDKError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[cfg(feature = "subprocess")]
     #[error("Timeout error: {0}")]
     Timeout(#[from] tokio::time::error::Elapsed),
 
     #[error("Binary discovery error: {0}")]
     Which(#[from] which::Error),
+
+    #[error("{source} [{context}]")]
+    WithContext {
+        source: Box<ClaudeSDKError>,
+        context: ErrorContext,
+    },
+}
+
+/// A stable, machine-readable identifier for each kind of
+/// [`ClaudeSDKError`], for services that map failures to HTTP or gRPC
+/// errors. Codes are never renamed; new kinds of errors get new codes, so
+/// matches need a wildcard arm, and codes from a newer SDK deserialize as
+/// [`Unknown`](Self::Unknown).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorCode {
+    CliConnection,
+    CliNotFound,
+    ProcessFailed,
+    DecodeFailed,
+    ApiOverloaded,
+    ApiRateLimited,
+    ApiAuthentication,
+    ApiPermission,
+    ApiInvalidRequest,
+    ApiServer,
+    ApiOther,
+    InvalidOptions,
+    UnsupportedOption,
+    ToolDisabled,
+    UnknownToolUse,
+    UnknownMessage,
+    IncompatibleProtocol,
+    Cancelled,
+    SlowStart,
+    Checkpoint,
+    UnsafeWorkspace,
+    DangerousToolUse,
+    FileLockConflict,
+    RunInProgress,
+    InteractionRequired,
+    OutputBudgetExceeded,
+    Sandbox,
+    Cargo,
+    Webhook,
+    #[serde(rename = "github")]
+    GitHub,
+    Io,
+    Json,
+    Timeout,
+    BinaryDiscovery,
+    /// A code this version does not know. Never produced by
+    /// [`ClaudeSDKError::code`].
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// The code as it appears in [`ClaudeSDKError::to_json`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CliConnection => "cli_connection",
+            Self::CliNotFound => "cli_not_found",
+            Self::ProcessFailed => "process_failed",
+            Self::DecodeFailed => "decode_failed",
+            Self::ApiOverloaded => "api_overloaded",
+            Self::ApiRateLimited => "api_rate_limited",
+            Self::ApiAuthentication => "api_authentication",
+            Self::ApiPermission => "api_permission",
+            Self::ApiInvalidRequest => "api_invalid_request",
+            Self::ApiServer => "api_server",
+            Self::ApiOther => "api_other",
+            Self::InvalidOptions => "invalid_options",
+            Self::UnsupportedOption => "unsupported_option",
+            Self::ToolDisabled => "tool_disabled",
+            Self::UnknownToolUse => "unknown_tool_use",
+            Self::UnknownMessage => "unknown_message",
+            Self::IncompatibleProtocol => "incompatible_protocol",
+            Self::Cancelled => "cancelled",
+            Self::SlowStart => "slow_start",
+            Self::Checkpoint => "checkpoint",
+            Self::UnsafeWorkspace => "unsafe_workspace",
+            Self::DangerousToolUse => "dangerous_tool_use",
+            Self::FileLockConflict => "file_lock_conflict",
+            Self::RunInProgress => "run_in_progress",
+            Self::InteractionRequired => "interaction_required",
+            Self::OutputBudgetExceeded => "output_budget_exceeded",
+            Self::Sandbox => "sandbox",
+            Self::Cargo => "cargo",
+            Self::Webhook => "webhook",
+            Self::GitHub => "github",
+            Self::Io => "io",
+            Self::Json => "json",
+            Self::Timeout => "timeout",
+            Self::BinaryDiscovery => "binary_discovery",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// The HTTP status a service would usually answer with: 4xx for
+    /// problems with the request, 5xx for failures of the CLI or the API.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::InvalidOptions
+            | Self::UnsupportedOption
+            | Self::UnknownToolUse
+            | Self::UnknownMessage
+            | Self::ApiInvalidRequest => 400,
+            Self::ApiAuthentication => 401,
+            Self::ApiPermission | Self::ToolDisabled | Self::DangerousToolUse => 403,
+            Self::UnsafeWorkspace | Self::FileLockConflict | Self::RunInProgress => 409,
+            Self::ApiRateLimited => 429,
+            Self::Cancelled => 499,
+            Self::ApiOverloaded
+            | Self::CliNotFound
+            | Self::IncompatibleProtocol
+            | Self::InteractionRequired => 503,
+            Self::SlowStart | Self::Timeout => 504,
+            _ => 500,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The JSON form of an error, see [`ClaudeSDKError::to_json`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorJson {
+    pub code: ErrorCode,
+    /// The error's `Display` output, for people rather than programs.
+    pub message: String,
+    pub retryable: bool,
+    /// Fields of the error, such as `exit_code` or `retry_after_ms`. Which
+    /// keys appear depends on the code.
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Details of the CLI invocation an error happened in.
+///
+/// Only the names of explicitly set environment variables are kept, never
+/// their values, and long arguments such as the prompt are truncated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    pub binary: Option<PathBuf>,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env_keys: Vec<String>,
+}
+
+impl ErrorContext {
+    pub fn from_command(cmd: &Command) -> Self {
+        let args = cmd
+            .get_args()
+            .map(|arg| truncate_arg(&arg.to_string_lossy()))
+            .collect();
+        let mut env_keys: Vec<String> = cmd
+            .get_envs()
+            .map(|(key, _)| key.to_string_lossy().into_owned())
+            .collect();
+        env_keys.sort();
+
+        Self {
+            binary: Some(PathBuf::from(cmd.get_program())),
+            args,
+            cwd: cmd.get_current_dir().map(PathBuf::from),
+            env_keys,
+        }
+    }
+}
+
+fn truncate_arg(arg: &str) -> String {
+    if arg.chars().count() <= MAX_ARG_LEN {
+        return arg.to_string();
+    }
+    let head: String = arg.chars().take(MAX_ARG_LEN).collect();
+    format!("{}... ({} chars)", head, arg.chars().count())
+}
+
+fn decode_details(
+    path: &Option<String>,
+    payload: &Option<String>,
+    dump: &Option<PathBuf>,
+) -> String {
+    let mut details = String::new();
+    if let Some(path) = path {
+        details.push_str(&format!(" at `{}`", path));
+    }
+    if let Some(payload) = payload {
+        details.push_str(&format!(" in line: {}", payload));
+    }
+    if let Some(dump) = dump {
+        details.push_str(&format!(" (saved to {})", dump.display()));
+    }
+    details
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.binary {
+            Some(binary) => write!(f, "binary: {}", binary.display())?,
+            None => write!(f, "binary: <unknown>")?,
+        }
+        write!(f, ", args: {:?}", self.args)?;
+        if let Some(cwd) = &self.cwd {
+            write!(f, ", cwd: {}", cwd.display())?;
+        }
+        if !self.env_keys.is_empty() {
+            write!(f, ", env: {:?}", self.env_keys)?;
+        }
+        Ok(())
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ClaudeSDKError>;
@@ -46,6 +391,249 @@ impl ClaudeSDKError {
     pub fn cli_json_decode<S: Into<String>>(message: S) -> Self {
         Self::CLIJSONDecode {
             message: message.into(),
+            path: None,
+            payload: None,
+            dump: None,
+        }
+    }
+
+    pub fn invalid_options<S: Into<String>>(message: S) -> Self {
+        Self::InvalidOptions {
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported_option<O: Into<String>, S: Into<String>>(option: O, message: S) -> Self {
+        Self::UnsupportedOption {
+            option: option.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn tool_disabled<S: Into<String>>(tool: S) -> Self {
+        Self::ToolDisabled { tool: tool.into() }
+    }
+
+    pub fn incompatible_protocol(required: u32, supported: u32) -> Self {
+        Self::IncompatibleProtocol {
+            required,
+            supported,
+            sdk_version: crate::negotiation::SDK_VERSION.to_string(),
+        }
+    }
+
+    pub fn checkpoint<S: Into<String>>(message: S) -> Self {
+        Self::Checkpoint {
+            message: message.into(),
+        }
+    }
+
+    pub fn interaction_required<S: Into<String>>(kind: InteractionKind, prompt: S) -> Self {
+        Self::InteractionRequired {
+            kind,
+            prompt: prompt.into(),
+        }
+    }
+
+    pub fn output_budget_exceeded(budget: i32, output_tokens: i32) -> Self {
+        Self::OutputBudgetExceeded {
+            budget,
+            output_tokens,
+        }
+    }
+
+    pub fn sandbox<S: Into<String>>(message: S) -> Self {
+        Self::Sandbox {
+            message: message.into(),
+        }
+    }
+
+    pub fn cargo<S: Into<String>>(message: S) -> Self {
+        Self::Cargo {
+            message: message.into(),
+        }
+    }
+
+    pub fn webhook<U: Into<String>, S: Into<String>>(url: U, message: S) -> Self {
+        Self::Webhook {
+            url: url.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn github<U: Into<String>, S: Into<String>>(
+        url: U,
+        status: Option<u16>,
+        message: S,
+    ) -> Self {
+        Self::GitHub {
+            url: url.into(),
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Whether the operation may succeed if retried.
+    pub fn is_retryable(&self) -> bool {
+        match self.root() {
+            Self::Api { kind, .. } => kind.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// How long the API asked to wait before retrying, if it said.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.root() {
+            Self::Api { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// The stable code of this error, see [`ErrorCode`].
+    pub fn code(&self) -> ErrorCode {
+        match self.root() {
+            Self::CLIConnection { .. } => ErrorCode::CliConnection,
+            Self::CLINotFound => ErrorCode::CliNotFound,
+            Self::Process { .. } => ErrorCode::ProcessFailed,
+            Self::CLIJSONDecode { .. } => ErrorCode::DecodeFailed,
+            Self::Api { kind, .. } => match kind {
+                ApiErrorKind::Overloaded => ErrorCode::ApiOverloaded,
+                ApiErrorKind::RateLimit => ErrorCode::ApiRateLimited,
+                ApiErrorKind::Authentication => ErrorCode::ApiAuthentication,
+                ApiErrorKind::Permission => ErrorCode::ApiPermission,
+                ApiErrorKind::InvalidRequest => ErrorCode::ApiInvalidRequest,
+                ApiErrorKind::Server => ErrorCode::ApiServer,
+                ApiErrorKind::Other(_) => ErrorCode::ApiOther,
+            },
+            Self::InvalidOptions { .. } => ErrorCode::InvalidOptions,
+            Self::UnsupportedOption { .. } => ErrorCode::UnsupportedOption,
+            Self::ToolDisabled { .. } => ErrorCode::ToolDisabled,
+            Self::UnknownToolUse { .. } => ErrorCode::UnknownToolUse,
+            Self::UnknownMessage { .. } => ErrorCode::UnknownMessage,
+            Self::IncompatibleProtocol { .. } => ErrorCode::IncompatibleProtocol,
+            Self::Cancelled => ErrorCode::Cancelled,
+            Self::SlowStart { .. } => ErrorCode::SlowStart,
+            Self::Checkpoint { .. } => ErrorCode::Checkpoint,
+            Self::UnsafeWorkspace { .. } => ErrorCode::UnsafeWorkspace,
+            Self::DangerousToolUse { .. } => ErrorCode::DangerousToolUse,
+            Self::FileLockConflict { .. } => ErrorCode::FileLockConflict,
+            Self::RunInProgress { .. } => ErrorCode::RunInProgress,
+            Self::InteractionRequired { .. } => ErrorCode::InteractionRequired,
+            Self::OutputBudgetExceeded { .. } => ErrorCode::OutputBudgetExceeded,
+            Self::Sandbox { .. } => ErrorCode::Sandbox,
+            Self::Cargo { .. } => ErrorCode::Cargo,
+            Self::Webhook { .. } => ErrorCode::Webhook,
+            Self::GitHub { .. } => ErrorCode::GitHub,
+            Self::Io(_) => ErrorCode::Io,
+            Self::Json(_) => ErrorCode::Json,
+            #[cfg(feature = "subprocess")]
+            Self::Timeout(_) => ErrorCode::Timeout,
+            Self::Which(_) => ErrorCode::BinaryDiscovery,
+            Self::WithContext { source, .. } => source.code(),
+        }
+    }
+
+    /// This error in a stable JSON schema: `code`, `message`, `retryable`
+    /// and `details`, see [`ErrorJson`].
+    ///
+    /// The invocation context is left out, as its arguments may hold parts
+    /// of the prompt.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.to_error_json()).expect("error JSON is serializable")
+    }
+
+    /// This error as an [`ErrorJson`].
+    pub fn to_error_json(&self) -> ErrorJson {
+        let root = self.root();
+        let details = match root {
+            Self::Process { exit_code, .. } => json!({ "exit_code": exit_code }),
+            Self::CLIJSONDecode { path, dump, .. } => json!({
+                "path": path,
+                "dump": dump,
+            }),
+            Self::Api {
+                kind, retry_after, ..
+            } => json!({
+                "kind": kind,
+                "retry_after_ms": retry_after.map(|delay| delay.as_millis() as u64),
+            }),
+            Self::UnsupportedOption { option, .. } => json!({ "option": option }),
+            Self::ToolDisabled { tool } => json!({ "tool": tool }),
+            Self::UnknownToolUse { tool_use_id } => json!({ "tool_use_id": tool_use_id }),
+            Self::UnknownMessage { message_seq } => json!({ "message_seq": message_seq }),
+            Self::IncompatibleProtocol {
+                required,
+                supported,
+                sdk_version,
+            } => json!({
+                "required": required,
+                "supported": supported,
+                "sdk_version": sdk_version,
+            }),
+            Self::SlowStart { deadline, model } => json!({
+                "deadline_ms": deadline.as_millis() as u64,
+                "model": model,
+            }),
+            Self::UnsafeWorkspace { path, .. } => json!({ "path": path }),
+            Self::DangerousToolUse { detection } => json!({
+                "tool_name": detection.tool_name,
+                "tool_use_id": detection.tool_use_id,
+                "pattern": detection.pattern,
+            }),
+            Self::FileLockConflict { conflict } => json!({
+                "path": conflict.path,
+                "tool_name": conflict.tool_name,
+                "tool_use_id": conflict.tool_use_id,
+                "holder": conflict.holder,
+            }),
+            Self::RunInProgress { idempotency_key } => {
+                json!({ "idempotency_key": idempotency_key })
+            }
+            Self::InteractionRequired { kind, .. } => json!({ "kind": kind }),
+            Self::OutputBudgetExceeded {
+                budget,
+                output_tokens,
+            } => json!({ "budget": budget, "output_tokens": output_tokens }),
+            Self::Webhook { url, .. } => json!({ "url": url }),
+            Self::GitHub { url, status, .. } => json!({ "url": url, "status": status }),
+            _ => json!({}),
+        };
+        let details = match details {
+            serde_json::Value::Object(mut map) => {
+                map.retain(|_, value| !value.is_null());
+                map
+            }
+            _ => serde_json::Map::new(),
+        };
+        ErrorJson {
+            code: self.code(),
+            message: root.to_string(),
+            retryable: self.is_retryable(),
+            details,
+        }
+    }
+
+    /// Attach the details of the CLI invocation to this error.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        Self::WithContext {
+            source: Box::new(self),
+            context,
+        }
+    }
+
+    /// The invocation details attached to this error, if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, with any attached context removed.
+    pub fn root(&self) -> &ClaudeSDKError {
+        match self {
+            Self::WithContext { source, .. } => source.root(),
+            other => other,
         }
     }
 }
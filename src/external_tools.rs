@@ -0,0 +1,382 @@
+//! Tools executed by the application instead of the CLI.
+//!
+//! Tools given to [`with_external_tools`](crate::ClaudeCodeOptions::with_external_tools)
+//! are offered to Claude as the tools of an [SDK MCP server](crate::sdk_mcp)
+//! named `external`, so Claude calls them as `mcp__external__<name>`, see
+//! [`qualified_name`]. Their calls arrive as tool uses in the message
+//! stream like any other; the application runs them however it likes, e.g.
+//! in its own sandbox or against mocks, and answers with
+//! [`QueryHandle::submit_tool_result`](crate::QueryHandle::submit_tool_result).
+//! The CLI waits for the server's answer, which the SDK holds back until
+//! the result is submitted.
+//!
+//! A `PreToolUse` hook the SDK registers for these tools approves each call
+//! and learns its tool use id, which the MCP call itself does not carry. So
+//! this needs a CLI that supports `--input-format stream-json` and
+//! `--mcp-config`.
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::external_tools::{qualified_name, ExternalTool};
+//! use claude_code_sdk::{query_with_handle, ClaudeCodeOptions, ContentBlock, Message};
+//! use serde_json::json;
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn run() -> claude_code_sdk::Result<()> {
+//! let deploy = ExternalTool::new("deploy")
+//!     .with_description("Deploy a branch")
+//!     .with_input_schema(json!({
+//!         "type": "object",
+//!         "properties": {"branch": {"type": "string"}},
+//!         "required": ["branch"],
+//!     }));
+//! let options = ClaudeCodeOptions::new().with_external_tools([deploy]);
+//! let mut handle = query_with_handle("Deploy the staging branch", Some(options)).await?;
+//! while let Some(message) = handle.next().await {
+//!     if let Message::Assistant(message) = message? {
+//!         for block in &message.content {
+//!             if let ContentBlock::ToolUse(tool_use) = block {
+//!                 if tool_use.name == qualified_name("deploy") {
+//!                     handle.submit_tool_result(&tool_use.id, "deployed").await?;
+//!                 }
+//!             }
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "subprocess")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::hooks::{HookEvent, HookOutput, HookRegistration};
+#[cfg(feature = "subprocess")]
+use crate::sdk_mcp::{self, SdkMcpServer, ToolOutput};
+#[cfg(feature = "subprocess")]
+use crate::types::{ContentBlock, Message};
+#[cfg(feature = "subprocess")]
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "subprocess")]
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+#[cfg(feature = "subprocess")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "subprocess")]
+use tokio::sync::oneshot;
+
+/// The SDK MCP server external tools are offered through.
+pub const SERVER_NAME: &str = "external";
+
+/// The name Claude calls the external tool `name` by,
+/// `mcp__external__<name>`.
+pub fn qualified_name(name: &str) -> String {
+    format!("mcp__{}__{}", SERVER_NAME, name)
+}
+
+/// A tool the application runs, see [`crate::external_tools`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalTool {
+    pub name: String,
+    /// Tells Claude what the tool does.
+    #[serde(default)]
+    pub description: String,
+    /// The JSON schema of the tool's arguments.
+    #[serde(default = "any_object")]
+    pub input_schema: Value,
+}
+
+fn any_object() -> Value {
+    serde_json::json!({ "type": "object" })
+}
+
+impl ExternalTool {
+    /// A tool taking any object as its arguments.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            description: String::new(),
+            input_schema: any_object(),
+        }
+    }
+
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_input_schema(mut self, input_schema: Value) -> Self {
+        self.input_schema = input_schema;
+        self
+    }
+}
+
+impl From<&str> for ExternalTool {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for ExternalTool {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+/// A call of an external tool waiting for its result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingToolUse {
+    pub id: String,
+    /// The tool's name without the `mcp__external__` prefix.
+    pub name: String,
+    pub input: Value,
+}
+
+/// Hands the results of external tool calls to the waiting CLI.
+///
+/// Cheap to clone, so results can be submitted from other tasks while the
+/// query's messages are still being read.
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Clone, Default)]
+pub struct ToolResultSender {
+    inner: Arc<Inner>,
+}
+
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Default)]
+struct Inner {
+    /// The ids of calls the `PreToolUse` hook approved, by tool name and
+    /// input, until their MCP call arrives.
+    announced: Mutex<HashMap<(String, String), VecDeque<String>>>,
+    calls: Mutex<HashMap<String, Call>>,
+}
+
+#[cfg(feature = "subprocess")]
+#[derive(Debug)]
+struct Call {
+    tool_use: PendingToolUse,
+    state: CallState,
+}
+
+#[cfg(feature = "subprocess")]
+#[derive(Debug)]
+enum CallState {
+    /// Seen in the message stream, the MCP call has not arrived yet.
+    Seen,
+    /// The MCP call is waiting for the result.
+    Waiting(oneshot::Sender<ToolOutput>),
+    /// Submitted before the MCP call arrived.
+    Answered(ToolOutput),
+}
+
+#[cfg(feature = "subprocess")]
+impl ToolResultSender {
+    /// The external tool calls still waiting for their result.
+    pub fn pending(&self) -> Vec<PendingToolUse> {
+        let mut pending: Vec<PendingToolUse> = self
+            .inner
+            .calls
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|call| !matches!(call.state, CallState::Answered(_)))
+            .map(|call| call.tool_use.clone())
+            .collect();
+        pending.sort_by(|a, b| a.id.cmp(&b.id));
+        pending
+    }
+
+    /// Send the result of the external tool call `tool_use_id`.
+    ///
+    /// The call may be answered as soon as its tool use is in the message
+    /// stream. Fails with [`UnknownToolUse`](ClaudeSDKError::UnknownToolUse)
+    /// for any other id, or one already answered.
+    pub async fn submit<S: Into<String>>(
+        &self,
+        tool_use_id: &str,
+        content: S,
+        is_error: bool,
+    ) -> Result<()> {
+        let unknown = || ClaudeSDKError::UnknownToolUse {
+            tool_use_id: tool_use_id.to_string(),
+        };
+        let output = if is_error {
+            ToolOutput::error(content)
+        } else {
+            ToolOutput::text(content)
+        };
+        let mut calls = self.inner.calls.lock().unwrap();
+        let call = calls.get_mut(tool_use_id).ok_or_else(unknown)?;
+        match std::mem::replace(&mut call.state, CallState::Seen) {
+            CallState::Seen => call.state = CallState::Answered(output),
+            CallState::Waiting(result) => {
+                calls.remove(tool_use_id);
+                result.send(output).map_err(|_| unknown())?;
+            }
+            CallState::Answered(output) => {
+                call.state = CallState::Answered(output);
+                return Err(unknown());
+            }
+        }
+        tracing::debug!(tool_use_id, is_error, "submitted external tool result");
+        Ok(())
+    }
+
+    /// Note the external tool uses of an assistant message, so their results
+    /// can be submitted before the CLI calls the tool.
+    fn record(&self, message: &Message) {
+        let Message::Assistant(message) = message else {
+            return;
+        };
+        let prefix = qualified_name("");
+        let mut calls = self.inner.calls.lock().unwrap();
+        for block in &message.content {
+            let ContentBlock::ToolUse(tool_use) = block else {
+                continue;
+            };
+            let Some(name) = tool_use.name.strip_prefix(&prefix) else {
+                continue;
+            };
+            calls.entry(tool_use.id.clone()).or_insert_with(|| Call {
+                tool_use: PendingToolUse {
+                    id: tool_use.id.clone(),
+                    name: name.to_string(),
+                    input: tool_use.input.clone(),
+                },
+                state: CallState::Seen,
+            });
+        }
+    }
+
+    /// The SDK MCP server offering `tools` to Claude, whose calls wait for
+    /// the submitted results.
+    pub(crate) fn server(&self, tools: &[ExternalTool]) -> SdkMcpServer {
+        tools
+            .iter()
+            .fold(SdkMcpServer::new(SERVER_NAME), |server, tool| {
+                let sender = self.clone();
+                let name = tool.name.clone();
+                server.with_tool(sdk_mcp::tool(
+                    tool.name.clone(),
+                    tool.description.clone(),
+                    tool.input_schema.clone(),
+                    move |input| {
+                        let sender = sender.clone();
+                        let name = name.clone();
+                        async move { sender.wait_for_result(name, input).await }
+                    },
+                ))
+            })
+    }
+
+    /// The `PreToolUse` hook approving external tool calls and noting their
+    /// ids.
+    pub(crate) fn hook(&self) -> HookRegistration {
+        let sender = self.clone();
+        let prefix = qualified_name("");
+        HookRegistration::new(HookEvent::PreToolUse, move |input| {
+            let name = input
+                .tool_name
+                .as_deref()
+                .and_then(|name| name.strip_prefix(&prefix));
+            let output = match (name, input.tool_use_id) {
+                (Some(name), Some(id)) => {
+                    let key = (name.to_string(), input_key(input.tool_input.as_ref()));
+                    let mut announced = sender.inner.announced.lock().unwrap();
+                    announced.entry(key).or_default().push_back(id);
+                    HookOutput {
+                        hook_specific_output: Some(serde_json::json!({
+                            "hookEventName": HookEvent::PreToolUse.as_str(),
+                            "permissionDecision": "allow",
+                        })),
+                        ..HookOutput::default()
+                    }
+                }
+                _ => HookOutput::allow(),
+            };
+            async move { output }
+        })
+        .with_matcher(format!("{}.*", qualified_name("")))
+    }
+
+    async fn wait_for_result(&self, name: String, input: Value) -> ToolOutput {
+        let key = (name.clone(), input_key(Some(&input)));
+        let id = {
+            let mut announced = self.inner.announced.lock().unwrap();
+            let id = announced.get_mut(&key).and_then(VecDeque::pop_front);
+            if announced.get(&key).is_some_and(VecDeque::is_empty) {
+                announced.remove(&key);
+            }
+            id
+        };
+        let Some(id) = id else {
+            tracing::warn!(tool = %name, "external tool called without a PreToolUse hook call");
+            return ToolOutput::error("the SDK could not match this call to a tool use");
+        };
+        let (result, received) = oneshot::channel();
+        {
+            let mut calls = self.inner.calls.lock().unwrap();
+            let answered = calls
+                .get(&id)
+                .is_some_and(|call| matches!(call.state, CallState::Answered(_)));
+            if answered {
+                if let Some(Call {
+                    state: CallState::Answered(output),
+                    ..
+                }) = calls.remove(&id)
+                {
+                    return output;
+                }
+            }
+            let call = calls.entry(id.clone()).or_insert_with(|| Call {
+                tool_use: PendingToolUse {
+                    id: id.clone(),
+                    name,
+                    input,
+                },
+                state: CallState::Seen,
+            });
+            call.state = CallState::Waiting(result);
+        }
+        received
+            .await
+            .unwrap_or_else(|_| ToolOutput::error("the application submitted no result"))
+    }
+
+    /// Give up on the calls still waiting, e.g. when the query ends.
+    pub(crate) fn close(&self) {
+        self.inner.announced.lock().unwrap().clear();
+        let unanswered = std::mem::take(&mut *self.inner.calls.lock().unwrap());
+        if !unanswered.is_empty() {
+            tracing::warn!(
+                unanswered = unanswered.len(),
+                "query finished with external tool calls unanswered"
+            );
+        }
+    }
+}
+
+/// The form tool inputs are matched by between the hook and the MCP call.
+#[cfg(feature = "subprocess")]
+fn input_key(input: Option<&Value>) -> String {
+    input
+        .cloned()
+        .unwrap_or_else(|| serde_json::json!({}))
+        .to_string()
+}
+
+/// Note the external tool uses in `stream`, and give up on unanswered
+/// calls once the result message arrives.
+#[cfg(feature = "subprocess")]
+pub(crate) fn close_on_result(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    sender: ToolResultSender,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(stream.inspect(move |item| match item {
+        Ok(Message::Result(_)) => sender.close(),
+        Ok(message) => sender.record(message),
+        Err(_) => {}
+    }))
+}
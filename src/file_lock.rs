@@ -0,0 +1,381 @@
+//! Advisory locks on the files edited by concurrent sessions.
+//!
+//! Sessions working in the same directory can share one [`WorkspaceLocks`]
+//! through [`ClaudeCodeOptions::with_file_locks`](crate::ClaudeCodeOptions::with_file_locks).
+//! When Claude calls an editing tool, the file it edits is locked for the
+//! run until the run ends. An editing tool call on a file locked by another
+//! run is refused, so two agents never edit the same file at once.
+//!
+//! Locks are taken in a `PreToolUse` [hook](crate::hooks) before the CLI
+//! runs the tool, and a conflicting call is denied: Claude is told the file
+//! is being edited by another session and the run goes on. A CLI that
+//! cannot call back into the SDK, or a transport other than the subprocess
+//! one, only reports tool calls once it made them. There locks are taken as
+//! the calls appear in the stream, and a conflicting call is stopped by
+//! interrupting its run with
+//! [`FileLockConflict`](crate::ClaudeSDKError::FileLockConflict). Locks are
+//! only seen by sessions sharing the same `WorkspaceLocks`; nothing is
+//! written to disk.
+//!
+//! ```rust
+//! use claude_code_sdk::file_lock::{LockEvent, LockScope, WorkspaceLocks};
+//! use claude_code_sdk::ClaudeCodeOptions;
+//!
+//! let locks = WorkspaceLocks::new()
+//!     .with_scope(LockScope::Directory)
+//!     .on_event(|event| {
+//!         if let LockEvent::Conflict(conflict) = event {
+//!             eprintln!("{} is being edited by run {}", conflict.path.display(), conflict.holder);
+//!         }
+//!     });
+//! let frontend = ClaudeCodeOptions::new().with_file_locks(locks.clone());
+//! let backend = ClaudeCodeOptions::new().with_file_locks(locks);
+//! ```
+
+#[cfg(feature = "subprocess")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::hooks::{HookEvent, HookOutput, HookRegistration};
+use crate::run_id::RunId;
+#[cfg(feature = "subprocess")]
+use crate::transport::DisposeGuard;
+use crate::types::{ContentBlock, Message, Shared, ToolUseBlock};
+#[cfg(feature = "subprocess")]
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// The tools that edit files, with the input naming the file.
+const EDITING_TOOLS: &[(&str, &str)] = &[
+    ("Edit", "file_path"),
+    ("MultiEdit", "file_path"),
+    ("Write", "file_path"),
+    ("NotebookEdit", "notebook_path"),
+];
+
+/// What a lock covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockScope {
+    /// The edited file only.
+    #[default]
+    File,
+    /// The directory containing the edited file, so runs stay out of each
+    /// other's modules.
+    Directory,
+}
+
+/// An editing tool call refused because another run holds the lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockConflict {
+    /// The locked file or directory.
+    pub path: PathBuf,
+    pub tool_name: String,
+    pub tool_use_id: String,
+    /// The run whose call was refused.
+    pub run_id: RunId,
+    /// The run holding the lock.
+    pub holder: RunId,
+}
+
+/// A change to the locks of a [`WorkspaceLocks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockEvent {
+    Acquired {
+        run_id: RunId,
+        path: PathBuf,
+    },
+    Conflict(LockConflict),
+    /// The run ended and gave up its locks.
+    Released {
+        run_id: RunId,
+        paths: Vec<PathBuf>,
+    },
+}
+
+pub type LockEventCallback = Shared<dyn Fn(&LockEvent) + Send + Sync>;
+
+/// Locks shared by the sessions of one workspace.
+///
+/// Cheap to clone; clones share the same locks.
+#[derive(Clone, Default)]
+pub struct WorkspaceLocks {
+    scope: LockScope,
+    held: Arc<Mutex<HashMap<PathBuf, RunId>>>,
+    on_event: Option<LockEventCallback>,
+}
+
+impl WorkspaceLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_scope(mut self, scope: LockScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Call `callback` whenever a lock is taken, refused or released.
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&LockEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Shared(Arc::new(callback)));
+        self
+    }
+
+    pub fn scope(&self) -> LockScope {
+        self.scope
+    }
+
+    /// The run holding the lock covering `path`, if any. Relative paths are
+    /// resolved against the current directory.
+    pub fn holder<P: AsRef<Path>>(&self, path: P) -> Option<RunId> {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let key = self.key(&cwd, path.as_ref());
+        self.held.lock().unwrap().get(&key).copied()
+    }
+
+    /// Every lock held, sorted by path.
+    pub fn held(&self) -> Vec<(PathBuf, RunId)> {
+        let mut held: Vec<(PathBuf, RunId)> = self
+            .held
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, run_id)| (path.clone(), *run_id))
+            .collect();
+        held.sort_by(|a, b| a.0.cmp(&b.0));
+        held
+    }
+
+    /// Take the lock for the file edited by `tool_use`, run by `run_id` in
+    /// `cwd`. Returns the conflict if another run holds it; calls of tools
+    /// that do not edit files always succeed.
+    pub fn check(
+        &self,
+        run_id: RunId,
+        cwd: &Path,
+        tool_use: &ToolUseBlock,
+    ) -> Option<LockConflict> {
+        let path = edited_path(tool_use)?;
+        let key = self.key(cwd, Path::new(path));
+        let holder = {
+            let mut held = self.held.lock().unwrap();
+            match held.get(&key) {
+                Some(holder) if *holder == run_id => return None,
+                Some(holder) => Some(*holder),
+                None => {
+                    held.insert(key.clone(), run_id);
+                    None
+                }
+            }
+        };
+        match holder {
+            Some(holder) => {
+                let conflict = LockConflict {
+                    path: key,
+                    tool_name: tool_use.name.clone(),
+                    tool_use_id: tool_use.id.clone(),
+                    run_id,
+                    holder,
+                };
+                self.notify(&LockEvent::Conflict(conflict.clone()));
+                Some(conflict)
+            }
+            None => {
+                tracing::debug!(%run_id, path = %key.display(), "locked file for editing");
+                self.notify(&LockEvent::Acquired { run_id, path: key });
+                None
+            }
+        }
+    }
+
+    /// Check every editing tool call in `message`, stopping at the first
+    /// conflict.
+    pub fn scan(&self, run_id: RunId, cwd: &Path, message: &Message) -> Option<LockConflict> {
+        let content = match message {
+            Message::User(msg) => &msg.content,
+            Message::Assistant(msg) => &msg.content,
+            _ => return None,
+        };
+        content.iter().find_map(|block| match block {
+            ContentBlock::ToolUse(tool_use) => self.check(run_id, cwd, tool_use),
+            _ => None,
+        })
+    }
+
+    /// Give up every lock held by `run_id`, returning the paths.
+    pub fn release(&self, run_id: RunId) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = {
+            let mut held = self.held.lock().unwrap();
+            let paths = held
+                .iter()
+                .filter(|(_, holder)| **holder == run_id)
+                .map(|(path, _)| path.clone())
+                .collect();
+            held.retain(|_, holder| *holder != run_id);
+            paths
+        };
+        if !paths.is_empty() {
+            paths.sort();
+            self.notify(&LockEvent::Released {
+                run_id,
+                paths: paths.clone(),
+            });
+        }
+        paths
+    }
+
+    fn key(&self, cwd: &Path, path: &Path) -> PathBuf {
+        let path = normalize(&cwd.join(path));
+        match self.scope {
+            LockScope::File => path,
+            LockScope::Directory => path.parent().map(Path::to_path_buf).unwrap_or(path),
+        }
+    }
+
+    fn notify(&self, event: &LockEvent) {
+        if let Some(callback) = &self.on_event {
+            callback(event);
+        }
+    }
+}
+
+impl std::fmt::Debug for WorkspaceLocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkspaceLocks")
+            .field("scope", &self.scope)
+            .field("held", &self.held.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// The file an editing tool call writes to.
+pub(crate) fn edited_path(tool_use: &ToolUseBlock) -> Option<&str> {
+    let (_, field) = EDITING_TOOLS
+        .iter()
+        .find(|(tool, _)| *tool == tool_use.name)?;
+    tool_use.input.get(field)?.as_str()
+}
+
+/// `path` with `.` and `..` resolved, without touching the filesystem: the
+/// file may not exist yet.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Releases the locks of a run when its stream ends or is dropped.
+#[cfg(feature = "subprocess")]
+struct ReleaseOnDrop {
+    locks: WorkspaceLocks,
+    run_id: RunId,
+}
+
+#[cfg(feature = "subprocess")]
+impl Drop for ReleaseOnDrop {
+    fn drop(&mut self) {
+        self.locks.release(self.run_id);
+    }
+}
+
+/// A `PreToolUse` hook taking the lock for each editing tool call of
+/// `run_id` in `cwd` before it runs, and denying the call on a conflict.
+#[cfg(feature = "subprocess")]
+pub(crate) fn deny_locked_edits(
+    locks: WorkspaceLocks,
+    run_id: RunId,
+    cwd: PathBuf,
+) -> HookRegistration {
+    let tools: Vec<&str> = EDITING_TOOLS.iter().map(|(tool, _)| *tool).collect();
+    HookRegistration::new(HookEvent::PreToolUse, move |input| {
+        let tool_use = ToolUseBlock::new(
+            input.tool_use_id.unwrap_or_default(),
+            input.tool_name.unwrap_or_default(),
+            input.tool_input.unwrap_or_default(),
+        );
+        let output = match locks.check(run_id, &cwd, &tool_use) {
+            Some(conflict) => {
+                tracing::warn!(?conflict, "denying edit of a file locked by another run");
+                HookOutput::deny(format!(
+                    "{} is being edited by another session; leave it alone or come back to it later",
+                    conflict.path.display()
+                ))
+            }
+            None => HookOutput::allow(),
+        };
+        async move { output }
+    })
+    .with_matcher(tools.join("|"))
+}
+
+/// Release the locks of `run_id` once the result arrives or the stream is
+/// dropped, for runs whose locks are taken by [`deny_locked_edits`].
+#[cfg(feature = "subprocess")]
+pub(crate) fn release_on_result(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    locks: WorkspaceLocks,
+    run_id: RunId,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let release = ReleaseOnDrop { locks, run_id };
+    Box::pin(stream.inspect(move |item| {
+        if let Ok(Message::Result(_)) = item {
+            release.locks.release(release.run_id);
+        }
+    }))
+}
+
+/// Lock the files edited in the stream, interrupting the run on a
+/// conflict and releasing its locks once the result arrives.
+#[cfg(feature = "subprocess")]
+pub(crate) fn lock_edited_files(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    locks: WorkspaceLocks,
+    run_id: RunId,
+    cwd: PathBuf,
+    guard: Option<DisposeGuard>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let release = ReleaseOnDrop { locks, run_id };
+    Box::pin(stream::unfold(
+        Some((stream, release, cwd, guard)),
+        |state| async move {
+            let (mut stream, release, cwd, guard) = state?;
+            let item = stream.next().await?;
+            let conflict = match &item {
+                Ok(Message::Result(_)) => {
+                    release.locks.release(release.run_id);
+                    None
+                }
+                Ok(message) => release.locks.scan(release.run_id, &cwd, message),
+                Err(_) => None,
+            };
+            match conflict {
+                Some(conflict) => {
+                    tracing::warn!(
+                        ?conflict,
+                        "interrupting run editing a file locked by another run"
+                    );
+                    if let Some(guard) = &guard {
+                        let _ = guard.dispose().await;
+                    }
+                    Some((Err(ClaudeSDKError::FileLockConflict { conflict }), None))
+                }
+                None => Some((item, Some((stream, release, cwd, guard)))),
+            }
+        },
+    ))
+}
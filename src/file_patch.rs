@@ -0,0 +1,245 @@
+//! File edits as a stream of patches.
+//!
+//! IDE plugins and review bots that mirror an agent's work keep their own
+//! model of the files it edits. [`on_file_patch`](crate::ClaudeCodeOptions::on_file_patch)
+//! reports each successful `Edit`, `MultiEdit`, `Write` and `NotebookEdit`
+//! call as a [`FilePatch`] once its result arrives: the file, whether it was
+//! added or replaced, the change itself, and hashes of the file's content
+//! before and after, so the mirror can check that it applies the patch to
+//! the same content and ends up with the same file.
+//!
+//! The CLI runs tools on its own, so the content before is read when the
+//! call appears in the stream, just before the CLI runs it, and the content
+//! after when its result arrives. A file changed by something else in
+//! between shows up as a `before_hash` the mirror does not have. Failed
+//! calls are not reported.
+//!
+//! ```rust
+//! use claude_code_sdk::file_patch::{FilePatch, PatchChange};
+//! use claude_code_sdk::ClaudeCodeOptions;
+//!
+//! # fn apply(_patch: &FilePatch) {}
+//! let options = ClaudeCodeOptions::new().on_file_patch(|patch| {
+//!     if let PatchChange::Edits { edits } = &patch.change {
+//!         eprintln!("{} edits to {}", edits.len(), patch.path.display());
+//!     }
+//!     apply(patch);
+//! });
+//! ```
+
+#[cfg(feature = "subprocess")]
+use crate::error::Result;
+#[cfg(feature = "subprocess")]
+use crate::file_lock::{edited_path, normalize};
+#[cfg(feature = "subprocess")]
+use crate::types::{ContentBlock, Message};
+use crate::types::{Shared, ToolUseBlock};
+#[cfg(feature = "subprocess")]
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::collections::HashMap;
+#[cfg(feature = "subprocess")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+
+/// What an edit did to its file, after the operations of JSON Patch
+/// (RFC 6902).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchOp {
+    /// The file did not exist before.
+    Add,
+    /// The file existed and was changed.
+    Replace,
+}
+
+/// One string replacement of an `Edit` or `MultiEdit` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub old: String,
+    pub new: String,
+    /// Whether every occurrence of `old` was replaced, not just one.
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+/// The change an editing tool call made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatchChange {
+    /// The whole new content, written by `Write`.
+    Content { content: String },
+    /// Replacements made by `Edit` or `MultiEdit`, in order.
+    Edits { edits: Vec<TextEdit> },
+    /// A change to a notebook cell: the input of the `NotebookEdit` call.
+    Notebook { input: serde_json::Value },
+}
+
+impl PatchChange {
+    /// The change made by `tool_use`, or `None` if it does not edit a file.
+    pub fn from_tool_use(tool_use: &ToolUseBlock) -> Option<Self> {
+        let input = &tool_use.input;
+        let text = |value: &serde_json::Value, key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let edit = |value: &serde_json::Value| TextEdit {
+            old: text(value, "old_string"),
+            new: text(value, "new_string"),
+            replace_all: value
+                .get("replace_all")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        };
+        match tool_use.name.as_str() {
+            "Write" => Some(Self::Content {
+                content: text(input, "content"),
+            }),
+            "Edit" => Some(Self::Edits {
+                edits: vec![edit(input)],
+            }),
+            "MultiEdit" => Some(Self::Edits {
+                edits: input
+                    .get("edits")
+                    .and_then(|v| v.as_array())
+                    .map(|edits| edits.iter().map(edit).collect())
+                    .unwrap_or_default(),
+            }),
+            "NotebookEdit" => Some(Self::Notebook {
+                input: input.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A successful editing tool call, see [`crate::file_patch`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilePatch {
+    pub op: PatchOp,
+    /// The edited file, absolute.
+    pub path: PathBuf,
+    pub tool_name: String,
+    pub tool_use_id: String,
+    /// The [`content_hash`] of the file before the edit; `None` if it did
+    /// not exist.
+    pub before_hash: Option<String>,
+    /// The [`content_hash`] of the file after the edit; `None` if it could
+    /// not be read.
+    pub after_hash: Option<String>,
+    pub change: PatchChange,
+}
+
+pub type FilePatchCallback = Shared<dyn Fn(&FilePatch) + Send + Sync>;
+
+/// The hash of a file's content used in [`FilePatch`]es: `sha256:` and the
+/// SHA-256 digest in lowercase hex.
+pub fn content_hash(content: &[u8]) -> String {
+    let digest = hmac_sha256::Hash::hash(content);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256:{}", hex)
+}
+
+#[cfg(feature = "subprocess")]
+async fn file_hash(path: &Path) -> Option<String> {
+    tokio::fs::read(path)
+        .await
+        .ok()
+        .map(|content| content_hash(&content))
+}
+
+/// The editing tool calls of a stream waiting for their results.
+#[cfg(feature = "subprocess")]
+struct Patcher {
+    callback: FilePatchCallback,
+    cwd: PathBuf,
+    pending: HashMap<String, FilePatch>,
+}
+
+#[cfg(feature = "subprocess")]
+impl Patcher {
+    async fn observe(&mut self, message: &Message) {
+        match message {
+            Message::Assistant(message) => {
+                for block in &message.content {
+                    if let ContentBlock::ToolUse(tool_use) = block {
+                        self.start(tool_use).await;
+                    }
+                }
+            }
+            Message::User(message) => {
+                for block in &message.content {
+                    let ContentBlock::ToolResult(result) = block else {
+                        continue;
+                    };
+                    let Some(mut patch) = self.pending.remove(&result.tool_use_id) else {
+                        continue;
+                    };
+                    if result.is_error == Some(true) {
+                        continue;
+                    }
+                    patch.after_hash = file_hash(&patch.path).await;
+                    (self.callback)(&patch);
+                }
+            }
+            // Calls without a result by now were never run
+            Message::Result(_) => self.pending.clear(),
+            _ => {}
+        }
+    }
+
+    async fn start(&mut self, tool_use: &ToolUseBlock) {
+        let (Some(path), Some(change)) =
+            (edited_path(tool_use), PatchChange::from_tool_use(tool_use))
+        else {
+            return;
+        };
+        let path = normalize(&self.cwd.join(path));
+        let before_hash = file_hash(&path).await;
+        let patch = FilePatch {
+            op: if before_hash.is_some() {
+                PatchOp::Replace
+            } else {
+                PatchOp::Add
+            },
+            path,
+            tool_name: tool_use.name.clone(),
+            tool_use_id: tool_use.id.clone(),
+            before_hash,
+            after_hash: None,
+            change,
+        };
+        self.pending.insert(tool_use.id.clone(), patch);
+    }
+}
+
+/// Report the stream's successful editing tool calls to `callback` as
+/// [`FilePatch`]es, resolving relative paths against `cwd`.
+#[cfg(feature = "subprocess")]
+pub(crate) fn emit_patches(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    callback: FilePatchCallback,
+    cwd: PathBuf,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let patcher = Patcher {
+        callback,
+        cwd,
+        pending: HashMap::new(),
+    };
+    Box::pin(stream::unfold(
+        (stream, patcher),
+        |(mut stream, mut patcher)| async move {
+            let item = stream.next().await?;
+            if let Ok(message) = &item {
+                patcher.observe(message).await;
+            }
+            Some((item, (stream, patcher)))
+        },
+    ))
+}
@@ -0,0 +1,197 @@
+use crate::error::Result;
+use crate::types::{ContentBlock, Message, SystemMessage};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+
+/// Selects which messages are handed to the caller.
+///
+/// The filter applies last, to the messages a query's stream or
+/// [`ClaudeSDKClient::receive_response`](crate::ClaudeSDKClient::receive_response)
+/// yields. The SDK itself still sees every message, so dropping system or
+/// result messages does not lose the session id, stored idempotent results
+/// or the end of a turn.
+///
+/// ```rust
+/// use claude_code_sdk::{ClaudeCodeOptions, MessageFilter};
+///
+/// let options = ClaudeCodeOptions::new()
+///     .with_message_filter(MessageFilter::assistant_and_result().strip_tool_results());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageFilter {
+    pub include_user: bool,
+    pub include_assistant: bool,
+    pub include_system: bool,
+    pub include_result: bool,
+    /// Replace the content of tool results with `None`.
+    pub strip_tool_result_content: bool,
+}
+
+impl Default for MessageFilter {
+    fn default() -> Self {
+        Self {
+            include_user: true,
+            include_assistant: true,
+            include_system: true,
+            include_result: true,
+            strip_tool_result_content: false,
+        }
+    }
+}
+
+impl MessageFilter {
+    /// A filter that lets every message through unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only deliver assistant and result messages.
+    pub fn assistant_and_result() -> Self {
+        Self {
+            include_user: false,
+            include_system: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn without_user_messages(mut self) -> Self {
+        self.include_user = false;
+        self
+    }
+
+    pub fn without_system_messages(mut self) -> Self {
+        self.include_system = false;
+        self
+    }
+
+    pub fn strip_tool_results(mut self) -> Self {
+        self.strip_tool_result_content = true;
+        self
+    }
+
+    /// Apply the filter, returning `None` if the message should be dropped.
+    pub fn apply(&self, mut message: Message) -> Option<Message> {
+        let included = match &message {
+            Message::User(_) => self.include_user,
+            // Parts of assistant messages
+            Message::Assistant(_) | Message::StreamEvent(_) => self.include_assistant,
+            Message::System(_) | Message::SdkInfo(_) => self.include_system,
+            Message::Result(_) => self.include_result,
+        };
+        if !included {
+            return None;
+        }
+
+        if self.strip_tool_result_content {
+            let content = match &mut message {
+                Message::User(msg) => Some(&mut msg.content),
+                Message::Assistant(msg) => Some(&mut msg.content),
+                _ => None,
+            };
+            for block in content.into_iter().flatten() {
+                if let ContentBlock::ToolResult(result) = block {
+                    result.content = None;
+                }
+            }
+        }
+
+        Some(message)
+    }
+}
+
+/// Collapses consecutive identical system messages into one.
+///
+/// Long sessions repeat the same notices, such as compaction or MCP
+/// reconnects. A run of messages with the same content is delivered as its
+/// first message with [`repeat_count`](SystemMessage::repeat_count) set to
+/// the length of the run; a lone message is delivered unchanged. A system
+/// message is therefore held back until the next message shows whether it
+/// repeats.
+///
+/// ```rust
+/// use claude_code_sdk::filter::SystemDedupe;
+/// use claude_code_sdk::Message;
+///
+/// let messages = SystemDedupe::dedupe(vec![
+///     Message::system("Reconnected to MCP server"),
+///     Message::system("Reconnected to MCP server"),
+///     Message::assistant_text("Done."),
+/// ]);
+/// assert_eq!(messages.len(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct SystemDedupe {
+    pending: Option<SystemMessage>,
+}
+
+impl SystemDedupe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deduplicate a recorded transcript.
+    pub fn dedupe<I: IntoIterator<Item = Message>>(messages: I) -> Vec<Message> {
+        let mut dedupe = Self::new();
+        let mut deduped: Vec<Message> = messages
+            .into_iter()
+            .flat_map(|message| dedupe.push(message))
+            .collect();
+        deduped.extend(dedupe.finish());
+        deduped
+    }
+
+    /// Feed the next message, returning the messages now ready.
+    pub fn push(&mut self, message: Message) -> Vec<Message> {
+        let Message::System(system) = message else {
+            return self.finish().into_iter().chain([message]).collect();
+        };
+        match &mut self.pending {
+            Some(pending)
+                if pending.subtype == system.subtype && pending.content == system.content =>
+            {
+                let count = pending.repeat_count.unwrap_or(1) + system.repeat_count.unwrap_or(1);
+                pending.repeat_count = Some(count);
+                Vec::new()
+            }
+            _ => self
+                .pending
+                .replace(system)
+                .map(Message::System)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// The held back message, once the messages have ended.
+    pub fn finish(&mut self) -> Option<Message> {
+        self.pending.take().map(Message::System)
+    }
+}
+
+/// Apply [`SystemDedupe`] to a message stream. An error flushes the held
+/// back message ahead of it.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) fn dedupe_system_messages(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let mut dedupe = SystemDedupe::new();
+    Box::pin(
+        stream
+            .map(Some)
+            .chain(stream::once(async { None }))
+            .flat_map(move |item| {
+                let ready: Vec<Result<Message>> = match item {
+                    Some(Ok(message)) => dedupe.push(message).into_iter().map(Ok).collect(),
+                    Some(Err(e)) => dedupe
+                        .finish()
+                        .map(Ok)
+                        .into_iter()
+                        .chain([Err(e)])
+                        .collect(),
+                    None => dedupe.finish().map(Ok).into_iter().collect(),
+                };
+                stream::iter(ready)
+            }),
+    )
+}
@@ -0,0 +1,498 @@
+//! Pull request reviews on GitHub.
+//!
+//! [`review_pull_request`] runs the whole workflow: it fetches the diff of a
+//! pull request, asks Claude to review it with read-only tools, and turns
+//! the findings into the payload of GitHub's "create a review" endpoint,
+//! which [`GitHubClient::submit_review`] posts. The steps are public too, for
+//! workflows that fetch diffs or post reviews their own way.
+//!
+//! GitHub only accepts review comments on lines inside the diff's hunks.
+//! Findings elsewhere are listed in the review body instead.
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::github::{review_pull_request, GitHubClient, PullRequest};
+//! use claude_code_sdk::ClaudeCodeOptions;
+//!
+//! # async fn example() -> claude_code_sdk::Result<()> {
+//! let github = GitHubClient::from_env();
+//! let pr = PullRequest::parse("octocat/hello-world#42")?;
+//! let options = ClaudeCodeOptions::new().with_cwd("/src/hello-world");
+//! let review = review_pull_request(&github, &pr, options).await?;
+//! github.submit_review(&pr, &review).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::tool_policy::{ToolCategory, ToolPolicy};
+use crate::types::{ClaudeCodeOptions, ContentBlock, Message};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+/// The REST API of github.com; GitHub Enterprise Server has its own, see
+/// [`GitHubClient::with_api_url`].
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// A pull request, such as `octocat/hello-world#42`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl PullRequest {
+    pub fn new<O: Into<String>, R: Into<String>>(owner: O, repo: R, number: u64) -> Self {
+        Self {
+            owner: owner.into(),
+            repo: repo.into(),
+            number,
+        }
+    }
+
+    /// Parse `owner/repo#42` or a pull request URL such as
+    /// `https://github.com/owner/repo/pull/42`.
+    pub fn parse(reference: &str) -> Result<Self> {
+        let invalid = || {
+            ClaudeSDKError::invalid_options(format!(
+                "Not a pull request: {:?}, expected owner/repo#number or a pull request URL",
+                reference
+            ))
+        };
+        let reference = reference.trim();
+        let (repository, number) = match reference.split_once('#') {
+            Some(parts) => parts,
+            None => {
+                let path = reference
+                    .split_once("://")
+                    .map_or(reference, |(_, rest)| rest);
+                let mut parts = path.trim_end_matches('/').rsplitn(3, '/');
+                let (Some(number), Some("pull"), Some(rest)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid());
+                };
+                // Drop the host
+                let repository = rest.split_once('/').map_or("", |(_, repo)| repo);
+                (repository, number)
+            }
+        };
+        let (owner, repo) = repository.split_once('/').ok_or_else(invalid)?;
+        let number = number.parse().map_err(|_| invalid())?;
+        if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+            return Err(invalid());
+        }
+        Ok(Self::new(owner, repo, number))
+    }
+}
+
+impl fmt::Display for PullRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+/// How serious a [`ReviewFinding`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// A bug or vulnerability; the review requests changes.
+    Error,
+    #[default]
+    Warning,
+    Suggestion,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Suggestion => "suggestion",
+        }
+    }
+}
+
+/// A problem found by the review, in the JSON form [`review_prompt`] asks
+/// for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewFinding {
+    /// The file, relative to the repository root.
+    pub path: String,
+    /// The line in the new version of the file, counting from 1.
+    pub line: u32,
+    /// The last line, for findings spanning several lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+    #[serde(default)]
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// The lines of one file a diff shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffFile {
+    /// The file after the change, relative to the repository root.
+    pub path: String,
+    /// The lines of the new version each hunk covers, context included.
+    pub hunks: Vec<Range<u32>>,
+}
+
+impl DiffFile {
+    /// The hunk containing `line` of the new version, if any.
+    fn hunk(&self, line: u32) -> Option<&Range<u32>> {
+        self.hunks.iter().find(|hunk| hunk.contains(&line))
+    }
+}
+
+/// The files of a unified diff as GitHub serves it, with the lines of each
+/// file that review comments can be left on. Deleted files are left out.
+pub fn parse_diff(diff: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    // Whether the lines are a file's header, where `+++` names the file
+    // rather than adding a line starting with `++`
+    let mut in_header = false;
+    let mut in_file = false;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            in_file = false;
+        } else if let (true, Some(path)) = (in_header, line.strip_prefix("+++ ")) {
+            in_file = path != "/dev/null";
+            if in_file {
+                let path = path.strip_prefix("b/").unwrap_or(path);
+                files.push(DiffFile {
+                    path: path.to_string(),
+                    hunks: Vec::new(),
+                });
+            }
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            in_header = false;
+            if !in_file {
+                continue;
+            }
+            // @@ -old_start,old_count +new_start,new_count @@
+            let new = header
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'));
+            let Some(new) = new else { continue };
+            let (start, count) = new.split_once(',').unwrap_or((new, "1"));
+            if let (Ok(start), Ok(count), Some(file)) =
+                (start.parse::<u32>(), count.parse::<u32>(), files.last_mut())
+            {
+                if count > 0 {
+                    file.hunks.push(start..start + count);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// The prompt asking Claude to review `diff`, answering with findings as
+/// JSON that [`parse_findings`] reads.
+pub fn review_prompt(pr: &PullRequest, diff: &str) -> String {
+    format!(
+        "Review the changes of pull request {pr} for bugs, security problems and \
+         maintainability issues. The repository is checked out in the working \
+         directory; read the surrounding code where the diff alone is not enough. \
+         Only report problems in the changed code, not style preferences.\n\n\
+         Answer with nothing but a JSON object of this form:\n\n\
+         {{\"findings\": [{{\"path\": \"src/lib.rs\", \"line\": 12, \"end_line\": 14, \
+         \"severity\": \"error\", \"message\": \"...\"}}]}}\n\n\
+         `path` is relative to the repository root, `line` and the optional \
+         `end_line` are lines of the new version of the file, and `severity` is \
+         one of \"error\", \"warning\" and \"suggestion\". Answer with an empty \
+         list if there is nothing to report.\n\n\
+         ```diff\n{diff}\n```",
+        pr = pr,
+        diff = diff.trim_end(),
+    )
+}
+
+/// `options` restricted to reading files: reviews never edit the checkout,
+/// run commands, browse or start sub-agents.
+pub fn review_options(options: ClaudeCodeOptions) -> ClaudeCodeOptions {
+    options.with_tool_policy(ToolPolicy::disable_categories(&[
+        ToolCategory::FileWrite,
+        ToolCategory::Execute,
+        ToolCategory::Network,
+        ToolCategory::Agent,
+    ]))
+}
+
+#[derive(Deserialize)]
+struct Findings {
+    findings: Vec<ReviewFinding>,
+}
+
+/// The findings of a review reply: the JSON object [`review_prompt`] asks
+/// for, also when wrapped in a code fence or prose.
+pub fn parse_findings(reply: &str) -> Result<Vec<ReviewFinding>> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => reply,
+    };
+    Ok(serde_json::from_str::<Findings>(json)?.findings)
+}
+
+/// What a review tells GitHub about the pull request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewEvent {
+    Comment,
+    RequestChanges,
+}
+
+/// A comment on lines of the diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewComment {
+    pub path: String,
+    /// The last line commented on.
+    pub line: u32,
+    /// `RIGHT`: comments are on the new version of the file.
+    pub side: String,
+    /// The first line, for comments on several lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_side: Option<String>,
+    pub body: String,
+}
+
+/// The body of GitHub's "create a review for a pull request" request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewPayload {
+    pub body: String,
+    pub event: ReviewEvent,
+    pub comments: Vec<ReviewComment>,
+}
+
+/// The review of `findings` on `diff`: a comment for each finding on lines
+/// of the diff, the others listed in the body. The review requests changes
+/// if any finding is an [`Severity::Error`].
+pub fn review_payload(findings: &[ReviewFinding], diff: &str) -> ReviewPayload {
+    let files = parse_diff(diff);
+    let mut comments = Vec::new();
+    let mut outside = Vec::new();
+    for finding in findings {
+        let body = format!("**{}**: {}", finding.severity.as_str(), finding.message);
+        let hunk = files
+            .iter()
+            .find(|file| file.path == finding.path)
+            .and_then(|file| file.hunk(finding.line));
+        let Some(hunk) = hunk else {
+            outside.push(format!("- `{}:{}` {}", finding.path, finding.line, body));
+            continue;
+        };
+        // Multi-line comments cannot leave the hunk they start in
+        let end = finding
+            .end_line
+            .filter(|end| *end > finding.line)
+            .map(|end| end.min(hunk.end - 1));
+        comments.push(match end {
+            Some(end) if end > finding.line => ReviewComment {
+                path: finding.path.clone(),
+                line: end,
+                side: "RIGHT".to_string(),
+                start_line: Some(finding.line),
+                start_side: Some("RIGHT".to_string()),
+                body,
+            },
+            _ => ReviewComment {
+                path: finding.path.clone(),
+                line: finding.line,
+                side: "RIGHT".to_string(),
+                start_line: None,
+                start_side: None,
+                body,
+            },
+        });
+    }
+
+    let mut body = match findings.len() {
+        0 => "No issues found.".to_string(),
+        1 => "1 finding.".to_string(),
+        n => format!("{} findings.", n),
+    };
+    if !outside.is_empty() {
+        body.push_str("\n\nOutside the diff:\n\n");
+        body.push_str(&outside.join("\n"));
+    }
+    let event = if findings.iter().any(|f| f.severity == Severity::Error) {
+        ReviewEvent::RequestChanges
+    } else {
+        ReviewEvent::Comment
+    };
+    ReviewPayload {
+        body,
+        event,
+        comments,
+    }
+}
+
+/// The GitHub REST API calls of the review workflow.
+#[derive(Clone)]
+pub struct GitHubClient {
+    api_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl fmt::Debug for GitHubClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubClient")
+            .field("api_url", &self.api_url)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl Default for GitHubClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitHubClient {
+    /// A client without a token, which can only read public repositories.
+    pub fn new() -> Self {
+        Self {
+            api_url: DEFAULT_API_URL.to_string(),
+            token: None,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// A client using the `GITHUB_TOKEN` environment variable, as set in
+    /// GitHub Actions, if it is set.
+    pub fn from_env() -> Self {
+        match std::env::var("GITHUB_TOKEN") {
+            Ok(token) if !token.is_empty() => Self::new().with_token(token),
+            _ => Self::new(),
+        }
+    }
+
+    pub fn with_token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Use another API, such as `https://github.example.com/api/v3` for
+    /// GitHub Enterprise Server.
+    pub fn with_api_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.api_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    fn pull_url(&self, pr: &PullRequest) -> String {
+        format!(
+            "{}/repos/{}/{}/pulls/{}",
+            self.api_url, pr.owner, pr.repo, pr.number
+        )
+    }
+
+    async fn send(&self, url: &str, request: reqwest::RequestBuilder) -> Result<String> {
+        let mut request = request
+            .header(
+                "User-Agent",
+                concat!("claude-code-sdk-rust/", env!("CARGO_PKG_VERSION")),
+            )
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ClaudeSDKError::github(url, None, e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ClaudeSDKError::github(url, Some(status.as_u16()), e.to_string()))?;
+        if !status.is_success() {
+            // GitHub explains errors in a JSON `message`
+            let message = serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return Err(ClaudeSDKError::github(url, Some(status.as_u16()), message));
+        }
+        Ok(text)
+    }
+
+    /// The unified diff of `pr`.
+    pub async fn pull_request_diff(&self, pr: &PullRequest) -> Result<String> {
+        let url = self.pull_url(pr);
+        let request = self
+            .client
+            .get(&url)
+            .header("Accept", "application/vnd.github.diff");
+        self.send(&url, request).await
+    }
+
+    /// Post `review` on `pr`.
+    pub async fn submit_review(&self, pr: &PullRequest, review: &ReviewPayload) -> Result<()> {
+        let url = format!("{}/reviews", self.pull_url(pr));
+        let request = self
+            .client
+            .post(&url)
+            .header("Accept", "application/vnd.github+json")
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(review)?);
+        self.send(&url, request).await.map(|_| ())
+    }
+}
+
+/// Review `pr`: fetch its diff, ask Claude for findings with
+/// [`review_options`], and map them to a [`ReviewPayload`]. The review is
+/// not posted; see [`GitHubClient::submit_review`].
+///
+/// `options` should run Claude in a checkout of the pull request's head, so
+/// it can read the code around the diff.
+pub async fn review_pull_request(
+    github: &GitHubClient,
+    pr: &PullRequest,
+    options: ClaudeCodeOptions,
+) -> Result<ReviewPayload> {
+    let diff = github.pull_request_diff(pr).await?;
+    let mut stream =
+        crate::query_with_handle(review_prompt(pr, &diff), Some(review_options(options))).await?;
+    // The findings are in the final answer, not in text between tool calls
+    let mut last_text = String::new();
+    let mut reply = None;
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Assistant(message) if message.parent_tool_use_id.is_none() => {
+                let text: Vec<String> = message
+                    .content
+                    .into_iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(block) => Some(block.text),
+                        _ => None,
+                    })
+                    .collect();
+                if !text.is_empty() {
+                    last_text = text.join("\n");
+                }
+            }
+            Message::Result(result) => {
+                reply = result.content.filter(|content| !content.trim().is_empty());
+            }
+            _ => {}
+        }
+    }
+    let reply = reply.unwrap_or(last_text);
+    Ok(review_payload(&parse_findings(&reply)?, &diff))
+}
@@ -0,0 +1,848 @@
+use crate::analytics::{StreamStats, UsageRecord};
+use crate::annotations::Annotation;
+use crate::archive::Archived;
+use crate::async_iter::{self, MessageIter};
+use crate::buffered::BufferedConversation;
+use crate::checkpoint::{Checkpoint, WorkspaceSnapshot};
+use crate::client::InternalClient;
+use crate::compat::OptionWarning;
+use crate::error::{ClaudeSDKError, Result};
+use crate::external_tools::ToolResultSender;
+use crate::negotiation::NegotiatedProtocol;
+use crate::prompt::PromptInput;
+use crate::run_id::RunId;
+use crate::transport::DisposeGuard;
+use crate::turn_retry::TurnOutcome;
+use crate::types::{ClaudeCodeOptions, Message, SystemMessage, Usage};
+use crate::verify::{self, Verification, VerificationReport, DEFAULT_MAX_FIX_ATTEMPTS};
+use futures::future::BoxFuture;
+use futures::stream::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{Instrument, Span};
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Message>> + Send>>;
+
+/// A newly started turn: its client, messages and start time.
+type TurnStart = (InternalClient, MessageStream, Instant);
+
+/// A finished verification pass and, if it failed, the fix-it turn.
+struct VerifyStep {
+    verification: Result<Verification>,
+    fix_turn: Option<Result<TurnStart>>,
+}
+
+/// A running query.
+///
+/// The handle is itself a stream of messages and keeps the underlying CLI
+/// process alive until it is dropped. While messages flow through it, it
+/// records the session state needed to checkpoint and later resume the run.
+pub struct QueryHandle {
+    client: InternalClient,
+    // Only ever accessed through `&mut self`; the mutex just makes the
+    // handle `Sync` without requiring a `Sync` stream.
+    stream: Mutex<MessageStream>,
+    options: ClaudeCodeOptions,
+    span: Span,
+    run_id: RunId,
+    session_id: Option<String>,
+    base_turns: i32,
+    turn_count: i32,
+    usage: Usage,
+    option_warnings: Vec<OptionWarning>,
+    protocol: NegotiatedProtocol,
+    started: Instant,
+    /// When the CLI process of the current turn was started.
+    turn_started: Instant,
+    stats: StreamStats,
+    run_failed: bool,
+    /// The current process's stream has ended.
+    exhausted: bool,
+    verification: Option<VerificationReport>,
+    verifying: Option<Mutex<BoxFuture<'static, VerifyStep>>>,
+    /// The prompt of the current attempt, for turn retries.
+    prompt: Option<PromptInput>,
+    turn: TurnOutcome,
+    turn_attempt: u32,
+    /// Errors of the current attempt, delivered unless it is retried.
+    held_errors: Vec<ClaudeSDKError>,
+    retrying: Option<Mutex<BoxFuture<'static, Result<TurnStart>>>>,
+    /// The messages so far, kept when a session archive is configured.
+    transcript: Vec<Message>,
+    /// How many messages of the transcript have been received.
+    received: usize,
+    annotations: Vec<Annotation>,
+    /// When the session reaches its maximum lifetime.
+    deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+    expired: bool,
+    archiving: Option<Mutex<BoxFuture<'static, Result<Archived>>>>,
+    archive_started: bool,
+    archived: Option<Archived>,
+    pending: VecDeque<Result<Message>>,
+}
+
+impl QueryHandle {
+    pub(crate) fn new(
+        client: InternalClient,
+        stream: MessageStream,
+        options: ClaudeCodeOptions,
+        span: Span,
+    ) -> Self {
+        Self {
+            option_warnings: client.option_warnings(),
+            client,
+            stream: Mutex::new(stream),
+            run_id: options.run_id.unwrap_or_default(),
+            span,
+            session_id: None,
+            base_turns: 0,
+            turn_count: 0,
+            usage: Usage::default(),
+            protocol: NegotiatedProtocol::default(),
+            started: Instant::now(),
+            turn_started: Instant::now(),
+            stats: StreamStats::default(),
+            run_failed: false,
+            exhausted: false,
+            verification: options
+                .verifier
+                .as_ref()
+                .map(|_| VerificationReport::default()),
+            verifying: None,
+            prompt: None,
+            turn: TurnOutcome::default(),
+            turn_attempt: 1,
+            held_errors: Vec::new(),
+            retrying: None,
+            transcript: Vec::new(),
+            received: 0,
+            annotations: Vec::new(),
+            deadline: options
+                .session_archive
+                .as_ref()
+                .and_then(|archive| archive.max_lifetime())
+                .map(|lifetime| Box::pin(tokio::time::sleep(lifetime))),
+            expired: false,
+            archiving: None,
+            archive_started: false,
+            archived: None,
+            pending: VecDeque::new(),
+            options,
+        }
+    }
+
+    /// Keep the prompt, so a failed turn can be retried with an adapted one.
+    pub(crate) fn with_prompt(mut self, prompt: PromptInput) -> Self {
+        if self.options.turn_retry.is_some() {
+            self.prompt = Some(prompt);
+        }
+        self
+    }
+
+    /// Measure the run from `started`, e.g. from before the CLI was spawned.
+    pub(crate) fn started_at(mut self, started: Instant) -> Self {
+        self.started = started;
+        self.turn_started = started;
+        if let (Some(deadline), Some(lifetime)) = (
+            &mut self.deadline,
+            self.options
+                .session_archive
+                .as_ref()
+                .and_then(|archive| archive.max_lifetime()),
+        ) {
+            deadline
+                .as_mut()
+                .reset(tokio::time::Instant::from_std(started + lifetime));
+        }
+        self
+    }
+
+    /// Carry the totals of a previous run forward into this one.
+    pub(crate) fn resumed_from(mut self, checkpoint: &Checkpoint) -> Self {
+        self.session_id = Some(checkpoint.session_id.clone());
+        self.base_turns = checkpoint.turn_count;
+        self.turn_count = checkpoint.turn_count;
+        self.usage = checkpoint.usage.clone();
+        self
+    }
+
+    /// The unique id of this query, also passed to the CLI in
+    /// [`RUN_ID_ENV`](crate::run_id::RUN_ID_ENV).
+    pub fn run_id(&self) -> RunId {
+        self.run_id
+    }
+
+    /// The CLI's session id, from the run's `init` message or its result.
+    /// Persist it to continue the session with
+    /// [`with_resume`](crate::ClaudeCodeOptions::with_resume).
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    pub fn turn_count(&self) -> i32 {
+        self.turn_count
+    }
+
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    pub fn options(&self) -> &ClaudeCodeOptions {
+        &self.options
+    }
+
+    /// Options that were dropped because the installed CLI does not support
+    /// them, see [`CompatMode::Lax`](crate::CompatMode::Lax).
+    pub fn option_warnings(&self) -> &[OptionWarning] {
+        &self.option_warnings
+    }
+
+    /// The protocol version agreed with the CLI, see
+    /// [`crate::negotiation`].
+    pub fn negotiated_protocol(&self) -> NegotiatedProtocol {
+        self.protocol
+    }
+
+    /// Time to first token and token rate of the run, or of the latest
+    /// fix-it turn when a verifier is configured.
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+
+    /// The archived transcript, once the session has ended and a
+    /// [`SessionArchive`](crate::archive::SessionArchive) stored it.
+    pub fn archived(&self) -> Option<&Archived> {
+        self.archived.as_ref()
+    }
+
+    /// How many messages of the transcript have been received. The latest
+    /// one is at `message_count() - 1` for [`annotate`](Self::annotate).
+    pub fn message_count(&self) -> usize {
+        self.received
+    }
+
+    /// Attach `key` = `value` to the received message at `message_seq`, see
+    /// [`crate::annotations`].
+    ///
+    /// With a [`SessionArchive`](crate::archive::SessionArchive), the
+    /// annotations are stored with the transcript when the session ends.
+    /// Store later ones with
+    /// [`store_annotations`](Self::store_annotations).
+    pub fn annotate<K: Into<String>, V: Into<serde_json::Value>>(
+        &mut self,
+        message_seq: usize,
+        key: K,
+        value: V,
+    ) -> Result<()> {
+        if message_seq >= self.received {
+            return Err(ClaudeSDKError::UnknownMessage { message_seq });
+        }
+        if self.archive_started {
+            tracing::debug!(
+                message_seq,
+                "annotation added after the transcript was archived"
+            );
+        }
+        self.annotations
+            .push(Annotation::new(message_seq, key, value));
+        Ok(())
+    }
+
+    /// Store all annotations with the archived transcript again, including
+    /// the ones added after it was archived. Returns `false` if the
+    /// transcript has not been archived, e.g. because the stream has not
+    /// ended yet; it then takes the annotations along when it is.
+    pub async fn store_annotations(&mut self) -> Result<bool> {
+        let (Some(archive), Some(archived)) =
+            (self.options.session_archive.clone(), self.archived.as_mut())
+        else {
+            return Ok(false);
+        };
+        archive
+            .store_annotations(archived, &self.annotations)
+            .await?;
+        Ok(true)
+    }
+
+    /// The annotations added so far, in the order they were added.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// The state of verification, when a
+    /// [`Verifier`](crate::verify::Verifier) is configured.
+    ///
+    /// Final once the stream has ended.
+    pub fn verification(&self) -> Option<&VerificationReport> {
+        self.verification.as_ref()
+    }
+
+    /// The file the CLI's stderr is written to, when a
+    /// [`StderrLog`](crate::stderr_log::StderrLog) is configured.
+    pub fn logs_path(&self) -> Option<PathBuf> {
+        self.options
+            .stderr_log
+            .as_ref()
+            .map(|log| log.path_for(self.run_id))
+    }
+
+    /// Stop the query and wait until the CLI process has been reaped.
+    ///
+    /// Dropping the handle also stops the process, but can only reap it in the
+    /// background.
+    pub async fn close(&mut self) -> Result<()> {
+        self.client.close().await
+    }
+
+    /// A guard that can reap the CLI process after the handle has been moved
+    /// elsewhere, e.g. into a consumer task.
+    ///
+    /// Fix-it turns sent by a verifier run in a new process, which the guard
+    /// does not cover.
+    pub fn dispose_guard(&self) -> Option<DisposeGuard> {
+        self.client.dispose_guard()
+    }
+
+    /// Answer the external tool call `tool_use_id`, see
+    /// [`crate::external_tools`].
+    pub async fn submit_tool_result<S: Into<String>>(
+        &self,
+        tool_use_id: &str,
+        content: S,
+    ) -> Result<()> {
+        self.tool_results()?
+            .submit(tool_use_id, content, false)
+            .await
+    }
+
+    /// Answer the external tool call `tool_use_id` with a failure.
+    pub async fn submit_tool_error<S: Into<String>>(
+        &self,
+        tool_use_id: &str,
+        message: S,
+    ) -> Result<()> {
+        self.tool_results()?
+            .submit(tool_use_id, message, true)
+            .await
+    }
+
+    /// Where the results of external tool calls go, for answering them
+    /// from another task.
+    pub fn tool_results(&self) -> Result<ToolResultSender> {
+        self.client.tool_results().ok_or_else(|| {
+            ClaudeSDKError::invalid_options("the query was started without external tools")
+        })
+    }
+
+    /// The messages as an async iterator, see [`MessageIter`].
+    pub fn into_async_iter(self) -> MessageIter<Self> {
+        MessageIter::new(self)
+    }
+
+    /// The messages buffered for several readers, see
+    /// [`BufferedConversation`]. As with [`into_channel`](Self::into_channel),
+    /// the session state is no longer accessible.
+    pub fn into_buffered(self) -> BufferedConversation {
+        BufferedConversation::new(self)
+    }
+
+    /// Forward the messages to a channel from a spawned task, see
+    /// [`async_iter::into_channel`]. The session state is no longer
+    /// accessible; take a [`dispose_guard`](Self::dispose_guard) first to
+    /// reap the process from elsewhere.
+    pub fn into_channel(self) -> mpsc::Receiver<Result<Message>> {
+        async_iter::into_channel(self)
+    }
+
+    /// Capture the current session so it can be continued with
+    /// [`resume_from_checkpoint`](crate::resume_from_checkpoint).
+    ///
+    /// Fails if the CLI has not reported a session id yet.
+    pub async fn checkpoint(&self) -> Result<Checkpoint> {
+        let session_id = self
+            .session_id
+            .clone()
+            .ok_or_else(|| ClaudeSDKError::checkpoint("No session id has been reported yet"))?;
+
+        let root = match &self.options.cwd {
+            Some(cwd) => Some(cwd.clone()),
+            None => std::env::current_dir().ok(),
+        };
+        let workspace = match root {
+            Some(root) => Some(WorkspaceSnapshot::capture(root).await),
+            None => None,
+        };
+
+        Ok(Checkpoint {
+            session_id,
+            turn_count: self.turn_count,
+            usage: self.usage.clone(),
+            workspace,
+        })
+    }
+
+    fn observe(&mut self, message: &Message) {
+        #[cfg(feature = "webhooks")]
+        self.notify_webhook(message);
+        if self.options.session_archive.is_some() {
+            self.transcript.push(message.clone());
+        }
+        self.received += 1;
+
+        if matches!(message, Message::Assistant(_)) && self.stats.time_to_first_token.is_none() {
+            let elapsed = self.turn_started.elapsed();
+            self.stats.time_to_first_token = Some(elapsed);
+            self.options.stats().record_first_token(elapsed);
+        }
+
+        match message {
+            Message::System(msg) => {
+                if let Some(Ok(protocol)) = NegotiatedProtocol::from_ack(msg) {
+                    self.protocol = protocol;
+                }
+                if let Some(session_id) = &msg.session_id {
+                    self.session_id = Some(session_id.clone());
+                }
+            }
+            Message::Assistant(_) => self.turn_count += 1,
+            Message::Result(result) => {
+                self.stats.duration = self.turn_started.elapsed();
+                self.stats.tokens_output = result.tokens_output.unwrap_or(0).into();
+                self.options.stats().record_turn(self.stats.duration);
+                if let Some(cost_usd) = result.cost_usd {
+                    self.options.stats().record_cost(cost_usd);
+                }
+                tracing::info!(
+                    target: "claude_code_sdk::metrics",
+                    run_id = %self.run_id,
+                    model = self.options.claude_model.as_deref().unwrap_or("default"),
+                    time_to_first_token_ms = self.stats.time_to_first_token.map(|ttft| ttft.as_millis() as u64),
+                    tokens_per_second = self.stats.tokens_per_second(),
+                    duration_ms = self.stats.duration.as_millis() as u64,
+                    tokens_output = self.stats.tokens_output,
+                    "run completed"
+                );
+                if let Some(session_id) = &result.session_id {
+                    self.session_id = Some(session_id.clone());
+                }
+                if let Some(num_turns) = result.num_turns {
+                    self.turn_count = self.base_turns + num_turns;
+                }
+                self.usage.add_result(result);
+                if result.exit_code.is_some_and(|code| code != 0) || result.canceled == Some(true) {
+                    self.run_failed = true;
+                }
+                if let Some(log) = &self.options.usage_log {
+                    let record = UsageRecord::from_result(
+                        result,
+                        Some(self.run_id),
+                        self.options.claude_model.clone(),
+                        self.started.elapsed(),
+                    )
+                    .with_stats(&self.stats);
+                    if let Err(e) = log.append(&record) {
+                        tracing::warn!(error = %e, "failed to append usage record");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+impl QueryHandle {
+    fn notify_webhook(&self, message: &Message) {
+        use crate::types::ContentBlock;
+        use crate::webhook::WebhookEvent;
+
+        let Some(notifier) = &self.options.webhook_notifier else {
+            return;
+        };
+        match message {
+            Message::Assistant(msg) => {
+                for block in &msg.content {
+                    if let ContentBlock::ToolUse(tool_use) = block {
+                        notifier.notify_in_background(WebhookEvent::ToolUse {
+                            run_id: self.run_id,
+                            tool_use_id: tool_use.id.clone(),
+                            tool_name: tool_use.name.clone(),
+                            input: tool_use.input.clone(),
+                        });
+                    }
+                }
+            }
+            Message::Result(result) => notifier.notify_in_background(WebhookEvent::RunCompleted {
+                run_id: self.run_id,
+                session_id: result.session_id.clone(),
+                exit_code: result.exit_code,
+                num_turns: result.num_turns,
+                cost_usd: result.cost_usd,
+                duration_ms: self.started.elapsed().as_millis() as u64,
+            }),
+            _ => {}
+        }
+    }
+
+    fn notify_webhook_error(&self, error: &ClaudeSDKError) {
+        if let Some(notifier) = &self.options.webhook_notifier {
+            notifier.notify_in_background(crate::webhook::WebhookEvent::RunFailed {
+                run_id: self.run_id,
+                error: error.to_string(),
+            });
+        }
+    }
+}
+
+impl QueryHandle {
+    /// End a session that reached its maximum lifetime.
+    fn expire(&mut self) {
+        tracing::warn!(run_id = %self.run_id, "session reached its maximum lifetime");
+        if let Some(guard) = self.client.dispose_guard() {
+            tokio::spawn(async move {
+                let _ = guard.dispose().await;
+            });
+        }
+        self.expired = true;
+        self.exhausted = true;
+        self.retrying = None;
+        self.verifying = None;
+        let lifetime = self
+            .options
+            .session_archive
+            .as_ref()
+            .and_then(|archive| archive.max_lifetime())
+            .unwrap_or_default();
+        self.pending
+            .push_back(Ok(Message::System(SystemMessage::new(format!(
+                "Session ended after reaching its maximum lifetime of {:?}",
+                lifetime
+            )))));
+        self.pending.extend(self.held_errors.drain(..).map(Err));
+    }
+
+    fn start_archive(&mut self) -> Option<BoxFuture<'static, Result<Archived>>> {
+        let archive = self.options.session_archive.clone()?;
+        if self.archive_started {
+            return None;
+        }
+        self.archive_started = true;
+        let transcript = std::mem::take(&mut self.transcript);
+        let annotations = self.annotations.clone();
+        let (run_id, session_id, expired) = (self.run_id, self.session_id.clone(), self.expired);
+        Some(Box::pin(
+            async move {
+                archive
+                    .archive(run_id, session_id, transcript, annotations, expired)
+                    .await
+            }
+            .instrument(self.span.clone()),
+        ))
+    }
+
+    fn finish_archive(&mut self, archived: Result<Archived>) {
+        match archived {
+            Ok(archived) => {
+                self.pending
+                    .push_back(Ok(Message::System(SystemMessage::new(format!(
+                        "Archived transcript to {}",
+                        archived.location
+                    )))));
+                self.archived = Some(archived);
+            }
+            Err(e) => self.pending.push_back(Err(e)),
+        }
+    }
+
+    /// Whether an error of the current attempt might be retried, and should
+    /// be held back until the attempt ends.
+    fn may_retry_errors(&self) -> bool {
+        self.prompt.is_some()
+            && self.options.turn_retry.as_ref().is_some_and(|retry| {
+                retry.policy.retry_errors && self.turn_attempt <= retry.policy.max_retries
+            })
+    }
+
+    /// Start the next attempt if the finished one was unsuccessful and the
+    /// turn retry allows another.
+    fn start_turn_retry(&mut self) -> Option<BoxFuture<'static, Result<TurnStart>>> {
+        let retry = self.options.turn_retry.as_ref()?;
+        let failure = self.turn.failure(self.turn_attempt)?;
+        let prompt = retry.adapt(&failure, self.prompt.as_ref()?)?;
+
+        let detail = failure
+            .detail
+            .as_deref()
+            .map(|detail| format!(" ({})", detail))
+            .unwrap_or_default();
+        tracing::info!(attempt = failure.attempt, kind = ?failure.kind, "retrying turn");
+        self.pending
+            .push_back(Ok(Message::System(SystemMessage::new(format!(
+                "Turn {}{}, retrying with an adapted prompt ({} of {})",
+                failure.kind, detail, failure.attempt, retry.policy.max_retries
+            )))));
+        self.turn = TurnOutcome::default();
+        self.turn_attempt += 1;
+        self.held_errors.clear();
+        self.run_failed = false;
+        self.prompt = Some(prompt.clone());
+
+        let mut options = self.options.clone();
+        options.idempotency_key = None;
+        let span = self.span.clone();
+        Some(Box::pin(
+            async move {
+                let started = Instant::now();
+                let mut client = InternalClient::new();
+                let stream = client.process_query(prompt, options).await?;
+                Ok((client, stream, started))
+            }
+            .instrument(span),
+        ))
+    }
+
+    fn finish_turn_retry(&mut self, started: Result<TurnStart>) {
+        match started {
+            Ok((client, stream, started)) => {
+                self.client = client;
+                self.stream = Mutex::new(stream);
+                self.exhausted = false;
+                self.base_turns = self.turn_count;
+                self.turn_started = started;
+                self.stats = StreamStats::default();
+                self.options.stats().record_spawn(started.elapsed());
+            }
+            Err(e) => {
+                self.run_failed = true;
+                self.pending.push_back(Err(e));
+            }
+        }
+    }
+
+    /// Whether the stream should be followed by a verification pass.
+    fn should_verify(&self) -> bool {
+        !self.run_failed && self.options.verifier.is_some()
+    }
+
+    fn start_verification(&mut self) -> BoxFuture<'static, VerifyStep> {
+        let verifier = self.options.verifier.clone().expect("verifier is set");
+        let workspace = self
+            .options
+            .cwd
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let fix_turns = self.verification.as_ref().map_or(0, |r| r.fix_turns);
+        let max_fix_attempts = self
+            .options
+            .max_fix_attempts
+            .unwrap_or(DEFAULT_MAX_FIX_ATTEMPTS);
+        let session_id = self.session_id.clone();
+        // The follow-up resumes the session; a cached result for the key
+        // would otherwise replace the fix-it turn, and the session already
+        // has the context files.
+        let mut options = self.options.clone();
+        options.idempotency_key = None;
+        options.context_files = None;
+        let span = self.span.clone();
+
+        Box::pin(
+            async move {
+                let verification = verifier.verify(&workspace).await;
+                let fix_turn = match (&verification, session_id) {
+                    (Ok(Verification::Failed { feedback }), Some(session_id))
+                        if fix_turns < max_fix_attempts =>
+                    {
+                        tracing::info!(
+                            attempt = fix_turns + 1,
+                            "verification failed, sending fix-it turn"
+                        );
+                        let started = Instant::now();
+                        let mut client = InternalClient::new();
+                        let stream = client
+                            .process_query(
+                                verify::fix_prompt(feedback),
+                                options.with_resume(session_id),
+                            )
+                            .await;
+                        Some(stream.map(|stream| (client, stream, started)))
+                    }
+                    _ => None,
+                };
+                VerifyStep {
+                    verification,
+                    fix_turn,
+                }
+            }
+            .instrument(span),
+        )
+    }
+
+    fn finish_verification(&mut self, step: VerifyStep) {
+        let report = self.verification.get_or_insert_with(Default::default);
+        let verification = match step.verification {
+            Ok(verification) => verification,
+            Err(e) => {
+                self.run_failed = true;
+                self.pending.push_back(Err(e));
+                return;
+            }
+        };
+        report.passes += 1;
+        match verification {
+            Verification::Passed => {
+                report.verified = true;
+                report.last_feedback = None;
+                self.pending
+                    .push_back(Ok(Message::System(SystemMessage::new(
+                        "Verification passed",
+                    ))));
+            }
+            Verification::Failed { feedback } => {
+                self.pending
+                    .push_back(Ok(Message::System(SystemMessage::new(format!(
+                        "Verification failed: {}",
+                        feedback
+                    )))));
+                report.last_feedback = Some(feedback);
+                match step.fix_turn {
+                    Some(Ok((client, stream, started))) => {
+                        report.fix_turns += 1;
+                        self.client = client;
+                        self.stream = Mutex::new(stream);
+                        self.exhausted = false;
+                        self.base_turns = self.turn_count;
+                        self.turn_started = started;
+                        self.stats = StreamStats::default();
+                    }
+                    Some(Err(e)) => self.pending.push_back(Err(e)),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+impl Stream for QueryHandle {
+    type Item = Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let span = this.span.clone();
+        let _entered = span.enter();
+        loop {
+            if let Some(item) = this.pending.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+            if let Some(archiving) = &mut this.archiving {
+                let archiving = archiving
+                    .get_mut()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let archived = match archiving.as_mut().poll(cx) {
+                    Poll::Ready(archived) => archived,
+                    Poll::Pending => return Poll::Pending,
+                };
+                this.archiving = None;
+                this.finish_archive(archived);
+                continue;
+            }
+            if let Some(deadline) = &mut this.deadline {
+                if !this.exhausted && deadline.as_mut().poll(cx).is_ready() {
+                    this.deadline = None;
+                    this.expire();
+                    continue;
+                }
+            }
+            if let Some(retrying) = &mut this.retrying {
+                let retrying = retrying
+                    .get_mut()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let started = match retrying.as_mut().poll(cx) {
+                    Poll::Ready(started) => started,
+                    Poll::Pending => return Poll::Pending,
+                };
+                this.retrying = None;
+                this.finish_turn_retry(started);
+                continue;
+            }
+            if let Some(verifying) = &mut this.verifying {
+                let verifying = verifying
+                    .get_mut()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let step = match verifying.as_mut().poll(cx) {
+                    Poll::Ready(step) => step,
+                    Poll::Pending => return Poll::Pending,
+                };
+                this.verifying = None;
+                this.finish_verification(step);
+                continue;
+            }
+            if this.exhausted {
+                if let Some(archiving) = this.start_archive() {
+                    this.archiving = Some(Mutex::new(archiving));
+                    continue;
+                }
+                return Poll::Ready(None);
+            }
+
+            let stream = this
+                .stream
+                .get_mut()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let poll = stream.as_mut().poll_next(cx);
+            match &poll {
+                Poll::Ready(Some(Ok(message))) => {
+                    this.observe(message);
+                    this.turn.observe(message);
+                    // Only what reaches the caller is filtered, the handle
+                    // itself sees every message
+                    if let Some(filter) = &this.options.message_filter {
+                        if let Poll::Ready(Some(Ok(message))) = poll {
+                            match filter.apply(message) {
+                                Some(message) => return Poll::Ready(Some(Ok(message))),
+                                None => continue,
+                            }
+                        }
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    this.run_failed = true;
+                    this.turn.fail(e);
+                    if this.may_retry_errors() {
+                        if let Poll::Ready(Some(Err(e))) = poll {
+                            this.held_errors.push(e);
+                        }
+                        continue;
+                    }
+                    #[cfg(feature = "webhooks")]
+                    this.notify_webhook_error(e);
+                }
+                Poll::Ready(None) => {
+                    this.exhausted = true;
+                    if let Some(retrying) = this.start_turn_retry() {
+                        this.retrying = Some(Mutex::new(retrying));
+                        continue;
+                    }
+                    if !this.held_errors.is_empty() {
+                        #[cfg(feature = "webhooks")]
+                        this.notify_webhook_error(&this.held_errors[0]);
+                        this.pending.extend(this.held_errors.drain(..).map(Err));
+                        continue;
+                    }
+                    if this.should_verify() {
+                        this.verifying = Some(Mutex::new(this.start_verification()));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            return poll;
+        }
+    }
+}
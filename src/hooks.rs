@@ -0,0 +1,715 @@
+//! Hooks into the CLI's agent loop and the SDK's message stream.
+//!
+//! [Lifecycle hooks](HookRegistration) are Rust callbacks the CLI runs at
+//! the same points as the hook commands of its settings: before and after
+//! each tool call, when a prompt is submitted, when Claude stops, and so
+//! on. They are registered with
+//! [`ClaudeCodeOptions::on_hook`](crate::ClaudeCodeOptions::on_hook) and
+//! reach the CLI through its control protocol, see [`crate::control`].
+//! Their [`HookOutput`] can block a tool call before it runs or stop the
+//! turn, so no separate hook scripts need to be shipped.
+//!
+//! ```rust
+//! use claude_code_sdk::hooks::{HookEvent, HookOutput, HookRegistration};
+//! use claude_code_sdk::ClaudeCodeOptions;
+//!
+//! let options = ClaudeCodeOptions::new()
+//!     .with_hook(
+//!         HookRegistration::new(HookEvent::PreToolUse, |input| async move {
+//!             let command = input.tool_input.as_ref().and_then(|i| i["command"].as_str());
+//!             match command {
+//!                 Some(command) if command.contains("rm -rf") => {
+//!                     HookOutput::deny("recursive deletes are not allowed")
+//!                 }
+//!                 _ => HookOutput::allow(),
+//!             }
+//!         })
+//!         .with_matcher("Bash"),
+//!     )
+//!     .on_hook(HookEvent::PostToolUse, |input| async move {
+//!         println!("{} finished", input.tool_name.unwrap_or_default());
+//!         HookOutput::allow()
+//!     });
+//! ```
+//!
+//! [Tool result hooks](ToolResultHook) rewrite the output of MCP tools
+//! before Claude sees it, e.g. to cut noisy logs down, in a `PostToolUse`
+//! hook of their own.
+
+#[cfg(feature = "subprocess")]
+use crate::error::Result;
+use crate::types::Shared;
+#[cfg(feature = "subprocess")]
+use crate::types::{ContentBlock, Message};
+use futures::future::BoxFuture;
+#[cfg(feature = "subprocess")]
+use futures::stream::{Stream, StreamExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::collections::HashMap;
+use std::future::Future;
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+use std::sync::Arc;
+#[cfg(feature = "subprocess")]
+use std::sync::Mutex;
+
+/// The points of the CLI's agent loop a hook can run at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HookEvent {
+    /// Before a tool call runs; the hook can deny it.
+    PreToolUse,
+    /// After a tool call returned.
+    PostToolUse,
+    /// When a prompt is submitted, before Claude sees it.
+    UserPromptSubmit,
+    /// When Claude is about to end its turn; blocking makes it go on.
+    Stop,
+    /// When a subagent is about to end its turn.
+    SubagentStop,
+    /// Before the conversation is compacted.
+    PreCompact,
+    /// When the CLI sends a notification, e.g. that it waits for input.
+    Notification,
+}
+
+impl HookEvent {
+    /// The event's name in the CLI's settings and protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreToolUse => "PreToolUse",
+            Self::PostToolUse => "PostToolUse",
+            Self::UserPromptSubmit => "UserPromptSubmit",
+            Self::Stop => "Stop",
+            Self::SubagentStop => "SubagentStop",
+            Self::PreCompact => "PreCompact",
+            Self::Notification => "Notification",
+        }
+    }
+}
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What the CLI passes to a hook. Fields an event does not have are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookInput {
+    pub hook_event_name: HookEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// The tool call, for `PreToolUse` and `PostToolUse`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<serde_json::Value>,
+    /// What the tool returned, for `PostToolUse`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_response: Option<serde_json::Value>,
+    /// The submitted prompt, for `UserPromptSubmit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Whether Claude already goes on because of a stop hook, for `Stop`
+    /// and `SubagentStop`. Hooks should not block again then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_hook_active: Option<bool>,
+    /// The notification text, for `Notification`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// `manual` or `auto`, for `PreCompact`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+}
+
+impl HookInput {
+    pub fn new(hook_event_name: HookEvent) -> Self {
+        Self {
+            hook_event_name,
+            session_id: None,
+            transcript_path: None,
+            cwd: None,
+            tool_use_id: None,
+            tool_name: None,
+            tool_input: None,
+            tool_response: None,
+            prompt: None,
+            stop_hook_active: None,
+            message: None,
+            trigger: None,
+        }
+    }
+}
+
+/// How a hook's `decision` affects the CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookDecision {
+    Approve,
+    Block,
+}
+
+/// A hook's answer, in the JSON form hook commands print. The default lets
+/// the CLI go on as if there was no hook.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    /// `false` stops the turn after the hook.
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub continue_: Option<bool>,
+    /// Shown to the user when `continue_` is `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress_output: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<HookDecision>,
+    /// Why the hook blocked, shown to Claude.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// A warning shown to the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    /// Output only some events understand, e.g. the `permissionDecision`
+    /// of `PreToolUse`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<serde_json::Value>,
+}
+
+impl HookOutput {
+    /// Let the CLI go on.
+    pub fn allow() -> Self {
+        Self::default()
+    }
+
+    /// Refuse a `PreToolUse` tool call; `reason` tells Claude why.
+    pub fn deny<S: Into<String>>(reason: S) -> Self {
+        Self {
+            hook_specific_output: Some(serde_json::json!({
+                "hookEventName": HookEvent::PreToolUse.as_str(),
+                "permissionDecision": "deny",
+                "permissionDecisionReason": reason.into(),
+            })),
+            ..Self::default()
+        }
+    }
+
+    /// Block what the event is about: a `PostToolUse` result is flagged to
+    /// Claude, a `UserPromptSubmit` prompt is dropped and a `Stop` makes
+    /// Claude go on, with `reason` as the explanation.
+    pub fn block<S: Into<String>>(reason: S) -> Self {
+        Self {
+            decision: Some(HookDecision::Block),
+            reason: Some(reason.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Stop the turn, showing `reason` to the user.
+    pub fn stop<S: Into<String>>(reason: S) -> Self {
+        Self {
+            continue_: Some(false),
+            stop_reason: Some(reason.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_system_message<S: Into<String>>(mut self, message: S) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+}
+
+/// An async lifecycle hook.
+pub type HookCallback = Shared<dyn Fn(HookInput) -> BoxFuture<'static, HookOutput> + Send + Sync>;
+
+/// A lifecycle hook and the event it runs at.
+#[derive(Debug, Clone)]
+pub struct HookRegistration {
+    pub event: HookEvent,
+    /// Which tools a `PreToolUse` or `PostToolUse` hook runs for, as a
+    /// pattern of the CLI's settings, e.g. `Bash` or `Edit|Write`. `None`
+    /// runs it for every tool.
+    pub matcher: Option<String>,
+    /// Checked by the SDK before the callback runs, see [`HookMatcher`].
+    pub filter: Option<HookMatcher>,
+    pub callback: HookCallback,
+}
+
+impl HookRegistration {
+    pub fn new<F, Fut>(event: HookEvent, callback: F) -> Self
+    where
+        F: Fn(HookInput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HookOutput> + Send + 'static,
+    {
+        Self {
+            event,
+            matcher: None,
+            filter: None,
+            callback: Shared(Arc::new(move |input| Box::pin(callback(input)))),
+        }
+    }
+
+    pub fn with_matcher<S: Into<String>>(mut self, matcher: S) -> Self {
+        self.matcher = Some(matcher.into());
+        self
+    }
+
+    /// Only run the callback for the calls `filter` matches. Its tool
+    /// patterns are also passed to the CLI, so it does not call back for
+    /// other tools at all.
+    pub fn with_filter(mut self, filter: HookMatcher) -> Self {
+        self.matcher = filter.cli_matcher();
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Whether the callback should run for `input`.
+    pub fn applies_to(&self, input: &HookInput) -> bool {
+        self.filter
+            .as_ref()
+            .map_or(true, |filter| filter.matches(input))
+    }
+}
+
+type ContentPredicate = Shared<dyn Fn(&str) -> bool + Send + Sync>;
+type InputPredicate = Shared<dyn Fn(&HookInput) -> bool + Send + Sync>;
+
+/// The tool inputs naming the file or directory a tool works on.
+const PATH_FIELDS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// A declarative condition on hook inputs, checked by the SDK before a
+/// hook's callback runs.
+///
+/// Tool patterns are tool names in which `*` matches any characters, e.g.
+/// `mcp__github__*`. Path globs are matched against the file a tool works
+/// on, relative to the session's directory: `*` matches within one path
+/// segment, `**` across segments, and a glob without `/` matches the file
+/// name in any directory. Content predicates see every string of the tool
+/// input, e.g. a Bash command or the text written by Write, and the
+/// submitted prompt.
+///
+/// A call matches when it matches one of the tool patterns, one of the path
+/// globs and every predicate; conditions not given are not checked.
+///
+/// ```rust
+/// use claude_code_sdk::hooks::{HookEvent, HookMatcher, HookOutput, HookRegistration};
+///
+/// let hook = HookRegistration::new(HookEvent::PreToolUse, |_| async {
+///     HookOutput::deny("migrations are applied by the release pipeline")
+/// })
+/// .with_filter(
+///     HookMatcher::new()
+///         .tool("Edit")
+///         .tool("Write")
+///         .path("migrations/**")
+///         .content(|text| text.contains("DROP")),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HookMatcher {
+    tools: Vec<String>,
+    tool_patterns: Vec<Regex>,
+    path_patterns: Vec<Regex>,
+    content: Vec<ContentPredicate>,
+    predicates: Vec<InputPredicate>,
+}
+
+impl HookMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match calls of the tools named by `pattern`.
+    pub fn tool<S: Into<String>>(mut self, pattern: S) -> Self {
+        let pattern = pattern.into();
+        self.tool_patterns.push(wildcard_regex(&pattern));
+        self.tools.push(pattern);
+        self
+    }
+
+    /// Match calls working on a file matched by `glob`.
+    pub fn path(mut self, glob: &str) -> Self {
+        self.path_patterns.push(glob_regex(glob));
+        self
+    }
+
+    /// Match calls whose input contains a string `predicate` accepts.
+    pub fn content<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.content.push(Shared(Arc::new(predicate)));
+        self
+    }
+
+    /// Match inputs `predicate` accepts.
+    pub fn when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&HookInput) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Shared(Arc::new(predicate)));
+        self
+    }
+
+    pub fn matches(&self, input: &HookInput) -> bool {
+        let tool_name = input.tool_name.as_deref().unwrap_or_default();
+        if !self.tool_patterns.is_empty()
+            && !self
+                .tool_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(tool_name))
+        {
+            return false;
+        }
+        if !self.path_patterns.is_empty() {
+            let Some(path) = tool_path(input) else {
+                return false;
+            };
+            let relative = relative_path(&path, input.cwd.as_deref());
+            let name = relative.rsplit('/').next().unwrap_or_default();
+            if !self
+                .path_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&relative) || pattern.is_match(name))
+            {
+                return false;
+            }
+        }
+        if !self.content.is_empty() {
+            let mut texts = Vec::new();
+            if let Some(tool_input) = &input.tool_input {
+                collect_strings(tool_input, &mut texts);
+            }
+            texts.extend(input.prompt.as_deref());
+            if !self
+                .content
+                .iter()
+                .all(|predicate| texts.iter().any(|text| predicate(text)))
+            {
+                return false;
+            }
+        }
+        self.predicates.iter().all(|predicate| predicate(input))
+    }
+
+    /// The tool patterns as a matcher for the CLI: a regular expression
+    /// alternating between them.
+    pub fn cli_matcher(&self) -> Option<String> {
+        if self.tools.is_empty() {
+            return None;
+        }
+        let alternatives: Vec<String> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                tool.split('*')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(".*")
+            })
+            .collect();
+        Some(alternatives.join("|"))
+    }
+}
+
+/// `pattern` with `*` matching any characters, anchored at both ends.
+fn wildcard_regex(pattern: &str) -> Regex {
+    let escaped: Vec<String> = pattern.split('*').map(regex::escape).collect();
+    Regex::new(&format!("^{}$", escaped.join(".*"))).expect("escaped pattern is valid")
+}
+
+/// `glob` as a regular expression over `/`-separated paths.
+fn glob_regex(glob: &str) -> Regex {
+    let glob = glob.trim_start_matches("./");
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // `**/` also matches no directory at all
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).expect("escaped glob is valid")
+}
+
+/// The file or directory the tool of `input` works on.
+fn tool_path(input: &HookInput) -> Option<String> {
+    let tool_input = input.tool_input.as_ref()?;
+    PATH_FIELDS
+        .iter()
+        .find_map(|field| tool_input.get(field)?.as_str())
+        .map(|path| path.replace('\\', "/"))
+}
+
+/// `path` relative to `cwd` if it lies inside it.
+fn relative_path(path: &str, cwd: Option<&str>) -> String {
+    let relative = cwd.map(|cwd| cwd.replace('\\', "/")).and_then(|cwd| {
+        path.strip_prefix(cwd.trim_end_matches('/'))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .map(str::to_string)
+    });
+    relative.unwrap_or_else(|| path.trim_start_matches("./").to_string())
+}
+
+fn collect_strings<'a>(value: &'a serde_json::Value, texts: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(text) => texts.push(text),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_strings(item, texts);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values() {
+                collect_strings(field, texts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The tool call a tool result belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolResultContext<'a> {
+    pub tool_use_id: &'a str,
+    pub tool_name: Option<&'a str>,
+    pub input: Option<&'a serde_json::Value>,
+    pub is_error: bool,
+}
+
+/// Post-processes the output of an MCP tool call before Claude sees it.
+/// Returning `Some` replaces the output.
+///
+/// These hooks are registered with
+/// [`with_tool_result_hook`](crate::ClaudeCodeOptions::with_tool_result_hook)
+/// and run in a `PostToolUse` hook over the control protocol, which hands
+/// the rewritten output back to the CLI. The CLI only lets hooks replace
+/// the output of MCP tools (`mcp__<server>__<tool>`), so only those are
+/// affected: the hooks are not called for built-in tools such as Bash or
+/// Read, whose output reaches Claude unchanged. A rewritten error result
+/// stays an error.
+pub type ToolResultHook =
+    Shared<dyn Fn(&ToolResultContext<'_>, &str) -> Option<String> + Send + Sync>;
+
+/// Keep the first `head` and last `tail` lines of long MCP tool output.
+///
+/// Only the output of `mcp__*` tools is truncated, see [`ToolResultHook`];
+/// the logs of built-in tools such as Bash are left as they are.
+pub fn truncate_lines(head: usize, tail: usize) -> ToolResultHook {
+    Shared(Arc::new(move |_: &ToolResultContext<'_>, content: &str| {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.len() <= head + tail {
+            return None;
+        }
+        let omitted = lines.len() - head - tail;
+        let mut truncated = lines[..head].join("\n");
+        truncated.push_str(&format!("\n... [{} lines omitted] ...\n", omitted));
+        truncated.push_str(&lines[lines.len() - tail..].join("\n"));
+        Some(truncated)
+    }))
+}
+
+/// Prefix each line of output from the given MCP tools, e.g.
+/// `mcp__files__read`, with its line number.
+///
+/// Only `mcp__*` tools can be numbered, see [`ToolResultHook`]; naming a
+/// built-in tool such as Read has no effect.
+pub fn number_lines(tools: &[&str]) -> ToolResultHook {
+    let tools: Vec<String> = tools.iter().map(|t| t.to_string()).collect();
+    Shared(Arc::new(
+        move |context: &ToolResultContext<'_>, content: &str| {
+            let tool_name = context.tool_name?;
+            if !tools.iter().any(|t| t == tool_name) {
+                return None;
+            }
+            let width = content.lines().count().to_string().len();
+            Some(
+                content
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| format!("{:>width$}\t{}", i + 1, line, width = width))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        },
+    ))
+}
+
+/// The tools whose output the CLI lets a `PostToolUse` hook replace.
+const REPLACEABLE_OUTPUT: &str = "mcp__.*";
+
+/// A `PostToolUse` hook running `hooks` over the output of MCP tool calls
+/// and handing the rewritten output back to the CLI, which passes it on to
+/// Claude in place of the original.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) fn rewrite_tool_output(hooks: Vec<ToolResultHook>) -> HookRegistration {
+    HookRegistration::new(HookEvent::PostToolUse, move |input| {
+        let output = match rewritten(&hooks, &input) {
+            Some(response) => HookOutput {
+                hook_specific_output: Some(serde_json::json!({
+                    "hookEventName": HookEvent::PostToolUse.as_str(),
+                    "updatedMCPToolOutput": response,
+                })),
+                ..HookOutput::default()
+            },
+            None => HookOutput::allow(),
+        };
+        async move { output }
+    })
+    .with_matcher(REPLACEABLE_OUTPUT)
+}
+
+/// The tool response of `input` after `hooks`, in the shape it came in, or
+/// `None` if no hook changed it or the tool is not an MCP tool.
+fn rewritten(hooks: &[ToolResultHook], input: &HookInput) -> Option<serde_json::Value> {
+    let tool_name = input.tool_name.as_deref()?;
+    if !tool_name.starts_with("mcp__") {
+        return None;
+    }
+    let response = input.tool_response.as_ref()?;
+    let mut text = response_text(response)?;
+    let context = ToolResultContext {
+        tool_use_id: input.tool_use_id.as_deref().unwrap_or_default(),
+        tool_name: Some(tool_name),
+        input: input.tool_input.as_ref(),
+        is_error: response
+            .get("isError")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false),
+    };
+    let mut changed = false;
+    for hook in hooks {
+        if let Some(edited) = hook(&context, &text) {
+            text = edited;
+            changed = true;
+        }
+    }
+    let content = serde_json::json!([{ "type": "text", "text": text }]);
+    changed.then(|| match response {
+        serde_json::Value::String(_) => text.into(),
+        // Keep `isError` and anything else besides the content
+        serde_json::Value::Object(fields) => {
+            let mut fields = fields.clone();
+            fields.insert("content".to_string(), content);
+            fields.into()
+        }
+        _ => content,
+    })
+}
+
+/// The text of an MCP tool response: a string, or the text blocks of its
+/// content.
+fn response_text(response: &serde_json::Value) -> Option<String> {
+    match response {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(blocks) => {
+            let texts: Vec<&str> = blocks
+                .iter()
+                .filter_map(|block| block.get("text")?.as_str())
+                .collect();
+            (!texts.is_empty()).then(|| texts.join("\n"))
+        }
+        serde_json::Value::Object(fields) => response_text(fields.get("content")?),
+        _ => None,
+    }
+}
+
+/// Caps how many tool calls run at once, see
+/// [`with_max_parallel_tools`](crate::ClaudeCodeOptions::with_max_parallel_tools).
+///
+/// Its `PreToolUse` hook takes one of `max` slots before a call runs and
+/// waits while none is free; the call's `PostToolUse` hook, or its result
+/// in the stream for calls that never ran, gives the slot back. Calls the
+/// CLI reports without a tool use id cannot be matched to their release,
+/// so they run without taking a slot.
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Clone)]
+pub(crate) struct ToolSlots {
+    free: Arc<tokio::sync::Semaphore>,
+    taken: Arc<Mutex<HashMap<String, tokio::sync::OwnedSemaphorePermit>>>,
+}
+
+#[cfg(feature = "subprocess")]
+impl ToolSlots {
+    pub(crate) fn new(max: u32) -> Self {
+        Self {
+            free: Arc::new(tokio::sync::Semaphore::new(max as usize)),
+            taken: Arc::default(),
+        }
+    }
+
+    /// The hooks taking and returning the slots.
+    pub(crate) fn hooks(&self) -> [HookRegistration; 2] {
+        let slots = self.clone();
+        let take = HookRegistration::new(HookEvent::PreToolUse, move |input| {
+            let slots = slots.clone();
+            async move {
+                let Some(id) = input.tool_use_id else {
+                    tracing::warn!(
+                        tool = input.tool_name.as_deref().unwrap_or_default(),
+                        "tool call without a tool use id runs outside max_parallel_tools"
+                    );
+                    return HookOutput::allow();
+                };
+                let free = slots.free.clone();
+                if let Ok(slot) = free.acquire_owned().await {
+                    slots.taken.lock().unwrap().insert(id, slot);
+                }
+                HookOutput::allow()
+            }
+        });
+        let slots = self.clone();
+        let give_back = HookRegistration::new(HookEvent::PostToolUse, move |input| {
+            if let Some(id) = &input.tool_use_id {
+                slots.release(id);
+            }
+            async { HookOutput::allow() }
+        });
+        [take, give_back]
+    }
+
+    fn release(&self, tool_use_id: &str) {
+        self.taken.lock().unwrap().remove(tool_use_id);
+    }
+
+    /// Give back the slots of calls whose results arrive in `stream`, such
+    /// as calls another hook denied, and every slot at the end of a turn.
+    pub(crate) fn release_on_results(
+        &self,
+        stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+        let slots = self.clone();
+        Box::pin(stream.inspect(move |item| match item {
+            Ok(Message::User(message)) => {
+                for block in &message.content {
+                    if let ContentBlock::ToolResult(result) = block {
+                        slots.release(&result.tool_use_id);
+                    }
+                }
+            }
+            Ok(Message::Result(_)) => slots.taken.lock().unwrap().clear(),
+            _ => {}
+        }))
+    }
+}
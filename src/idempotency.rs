@@ -0,0 +1,218 @@
+use crate::error::Result;
+#[cfg(feature = "subprocess")]
+use crate::types::Message;
+use crate::types::{ResultMessage, Shared};
+use async_trait::async_trait;
+#[cfg(feature = "subprocess")]
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+#[cfg(feature = "subprocess")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// How many results the process-wide default store keeps before dropping
+/// the oldest.
+pub const DEFAULT_STORE_CAPACITY: usize = 1024;
+
+/// What [`IdempotencyStore::begin`] found under a key.
+#[derive(Debug, Clone)]
+pub enum Begin {
+    /// The key was free and now belongs to the caller's run.
+    Started,
+    /// Another run holds the key and has not finished yet.
+    InFlight,
+    /// A run under the key already succeeded with this result.
+    Done(Box<ResultMessage>),
+}
+
+/// Storage for the results of queries submitted with an idempotency key.
+///
+/// Implement this to share results across processes (e.g. in Redis or a
+/// database); [`InMemoryIdempotencyStore`] is used when none is configured.
+/// `begin` must claim a key atomically, so that of two runs submitted at
+/// the same time only one starts.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Claim `key` for a new run, unless another run holds it or already
+    /// succeeded under it.
+    async fn begin(&self, key: &str) -> Result<Begin>;
+    async fn get(&self, key: &str) -> Result<Option<ResultMessage>>;
+    /// Save the successful result of the run holding `key`.
+    async fn put(&self, key: &str, result: ResultMessage) -> Result<()>;
+    /// Release `key` after its run failed, so it can be retried.
+    async fn abandon(&self, key: &str) -> Result<()>;
+}
+
+pub type IdempotencyStoreRef = Shared<dyn IdempotencyStore>;
+
+/// Keeps results in memory, by default without a limit.
+#[derive(Debug)]
+pub struct InMemoryIdempotencyStore {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    in_flight: HashSet<String>,
+    results: HashMap<String, ResultMessage>,
+    /// The keys of `results`, oldest first.
+    order: VecDeque<String>,
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::with_capacity(usize::MAX)
+    }
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store keeping at most `capacity` results, dropping the oldest
+    /// first.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn begin(&self, key: &str) -> Result<Begin> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(result) = entries.results.get(key) {
+            return Ok(Begin::Done(Box::new(result.clone())));
+        }
+        if !entries.in_flight.insert(key.to_string()) {
+            return Ok(Begin::InFlight);
+        }
+        Ok(Begin::Started)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<ResultMessage>> {
+        Ok(self.entries.lock().unwrap().results.get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, result: ResultMessage) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.in_flight.remove(key);
+        if entries.results.insert(key.to_string(), result).is_none() {
+            entries.order.push_back(key.to_string());
+        }
+        while entries.results.len() > self.capacity {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+            entries.results.remove(&oldest);
+        }
+        Ok(())
+    }
+
+    async fn abandon(&self, key: &str) -> Result<()> {
+        self.entries.lock().unwrap().in_flight.remove(key);
+        Ok(())
+    }
+}
+
+/// The process-wide store used when a key is set without an explicit store,
+/// keeping the last [`DEFAULT_STORE_CAPACITY`] results.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) fn default_store() -> IdempotencyStoreRef {
+    static STORE: OnceLock<Arc<InMemoryIdempotencyStore>> = OnceLock::new();
+    Shared(
+        STORE
+            .get_or_init(|| {
+                Arc::new(InMemoryIdempotencyStore::with_capacity(
+                    DEFAULT_STORE_CAPACITY,
+                ))
+            })
+            .clone(),
+    )
+}
+
+/// A run's claim on its idempotency key, released when dropped unless the
+/// run succeeded.
+#[cfg(feature = "subprocess")]
+pub(crate) struct Claim {
+    store: IdempotencyStoreRef,
+    key: String,
+    settled: AtomicBool,
+}
+
+#[cfg(feature = "subprocess")]
+impl Claim {
+    pub(crate) fn new(store: IdempotencyStoreRef, key: String) -> Self {
+        Self {
+            store,
+            key,
+            settled: AtomicBool::new(false),
+        }
+    }
+
+    /// Save `result` if the run succeeded, or release the key for a retry.
+    async fn settle(&self, result: &ResultMessage) {
+        if self.settled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let failed = result.is_error == Some(true) || result.canceled == Some(true);
+        if !failed {
+            match self.store.put(&self.key, result.clone()).await {
+                Ok(()) => return,
+                Err(e) => {
+                    tracing::warn!(key = %self.key, error = %e, "failed to store idempotent result")
+                }
+            }
+        }
+        if let Err(e) = self.store.abandon(&self.key).await {
+            tracing::warn!(key = %self.key, error = %e, "failed to release idempotency key");
+        }
+    }
+}
+
+#[cfg(feature = "subprocess")]
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if *self.settled.get_mut() {
+            return;
+        }
+        let store = self.store.clone();
+        let key = std::mem::take(&mut self.key);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = store.abandon(&key).await {
+                        tracing::warn!(%key, error = %e, "failed to release idempotency key");
+                    }
+                });
+            }
+            Err(_) => tracing::warn!(%key, "idempotency key left claimed outside a runtime"),
+        }
+    }
+}
+
+/// Save the `ResultMessage` of `stream` under the claimed key as it passes
+/// through. The key is released for a retry if the run fails, or the
+/// stream ends or is dropped before its result.
+#[cfg(feature = "subprocess")]
+pub(crate) fn record_result(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    claim: Claim,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let claim = Arc::new(claim);
+    Box::pin(stream.then(move |item| {
+        let claim = claim.clone();
+        async move {
+            if let Ok(Message::Result(result)) = &item {
+                claim.settle(result).await;
+            }
+            item
+        }
+    }))
+}
@@ -0,0 +1,312 @@
+//! Interactive prompts of the CLI in headless runs.
+//!
+//! In some states the CLI asks a question instead of answering the prompt:
+//! when it is not logged in, or has not been told to trust the working
+//! directory. Without a terminal nobody answers, and the run would hang.
+//! The subprocess transport watches the CLI's output for such prompts,
+//! stops the process and fails with
+//! [`ClaudeSDKError::InteractionRequired`](crate::ClaudeSDKError::InteractionRequired),
+//! whose [`InteractionKind`] says how to fix the setup.
+//!
+//! The watch recognizes the CLI's own prompts on whole lines of stdout or
+//! stderr, and on a line left unfinished for half a second, as prompts
+//! waiting for an answer often are.
+//!
+//! The prompt to trust the working directory can be avoided with
+//! [`with_trusted_folder`](crate::ClaudeCodeOptions::with_trusted_folder),
+//! which marks the directory as trusted in the CLI's configuration before
+//! each run. That file is shared by every run of the user; with an
+//! [isolated home](crate::ClaudeCodeOptions::with_isolated_home) the run's
+//! own copy is changed instead.
+
+#[cfg(feature = "subprocess")]
+use crate::types::ClaudeCodeOptions;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "subprocess")]
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "subprocess")]
+use std::time::Duration;
+#[cfg(feature = "subprocess")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// What the CLI is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionKind {
+    /// Someone to log in.
+    Login,
+    /// Confirmation that the files in the working directory can be trusted.
+    TrustFolder,
+    /// Any other answer on its interactive terminal UI.
+    Terminal,
+}
+
+impl InteractionKind {
+    /// The kind of prompt the CLI wrote in `line`, or `None` if `line` is
+    /// not one.
+    ///
+    /// Only a line that is one of the CLI's own prompts counts, once its
+    /// colors and frame are removed: output that merely mentions logging
+    /// in, e.g. a tool's stderr, does not. JSON lines are never prompts.
+    pub fn from_line(line: &str) -> Option<Self> {
+        const PROMPTS: &[(&str, InteractionKind)] = &[
+            (
+                "do you trust the files in this folder",
+                InteractionKind::TrustFolder,
+            ),
+            ("select login method", InteractionKind::Login),
+            ("please run /login", InteractionKind::Login),
+            (
+                "invalid api key · please run /login",
+                InteractionKind::Login,
+            ),
+            ("not logged in · please run /login", InteractionKind::Login),
+            ("press enter to log in", InteractionKind::Login),
+            ("raw mode is not supported", InteractionKind::Terminal),
+            (
+                "error: raw mode is not supported",
+                InteractionKind::Terminal,
+            ),
+            ("press enter to continue", InteractionKind::Terminal),
+        ];
+        /// Longer lines are output, not a prompt.
+        const MAX_PROMPT_CHARS: usize = 200;
+
+        let line = line.trim_start();
+        if line.starts_with('{') {
+            return None;
+        }
+        static ANSI: OnceLock<Regex> = OnceLock::new();
+        let ansi = ANSI.get_or_init(|| {
+            Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]").expect("ANSI escape pattern is valid")
+        });
+        let text = ansi.replace_all(line, "").to_lowercase();
+        // The terminal UI frames its prompts in boxes
+        let text = text
+            .trim_matches(|c: char| c.is_whitespace() || "│╭╮╰╯─>❯⚠✗*".contains(c))
+            .to_string();
+        if text.chars().count() > MAX_PROMPT_CHARS {
+            return None;
+        }
+        PROMPTS
+            .iter()
+            .find(|(prompt, _)| text.starts_with(prompt))
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::TrustFolder => "trust_folder",
+            Self::Terminal => "terminal",
+        }
+    }
+
+    /// How to set the CLI up so that it does not ask.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::Login => {
+                "log in once by running `claude` in a terminal as the same user, \
+                 or pass an API key or provider in the options"
+            }
+            Self::TrustFolder => {
+                "run `claude` in the working directory once and trust it, \
+                 or set `with_trusted_folder()` in the options"
+            }
+            Self::Terminal => {
+                "check that no setting or option of the CLI asks for confirmation, \
+                 e.g. disable the auto-updater"
+            }
+        }
+    }
+}
+
+impl fmt::Display for InteractionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The CLI's global configuration file for a run with `options`, or `None`
+/// if there is no home directory. `home` is the run's isolated home, if it
+/// has one.
+#[cfg(feature = "subprocess")]
+pub(crate) fn config_path(options: &ClaudeCodeOptions, home: Option<&Path>) -> Option<PathBuf> {
+    if let Some(home) = home {
+        return Some(home.join(".claude").join(".claude.json"));
+    }
+    let var = |key: &str| {
+        options
+            .env
+            .as_ref()
+            .and_then(|env| env.get(key))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os(key).map(PathBuf::from))
+    };
+    var("CLAUDE_CONFIG_DIR")
+        .or_else(|| var("HOME"))
+        .or_else(|| var("USERPROFILE"))
+        .map(|dir| dir.join(".claude.json"))
+}
+
+/// How long an unfinished line of the CLI's output must stay unfinished
+/// before it is checked for a prompt, which need not end in a newline.
+#[cfg(feature = "subprocess")]
+pub(crate) const PROMPT_WAIT: Duration = Duration::from_millis(500);
+
+/// Splits output into lines, noticing a line that stays unfinished.
+#[cfg(feature = "subprocess")]
+pub(crate) struct Lines<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// The unfinished line in `buffer` was already reported.
+    stalled: bool,
+    ended: bool,
+}
+
+#[cfg(feature = "subprocess")]
+impl<R: AsyncRead + Unpin> Lines<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            stalled: false,
+            ended: false,
+        }
+    }
+
+    /// The next line without its newline, or `None` once the output ends.
+    /// A line left unfinished for [`PROMPT_WAIT`] is passed to `on_stall`
+    /// once, before it ends. Cancel safe.
+    pub(crate) async fn next_line(
+        &mut self,
+        on_stall: Option<&(dyn Fn(&str) + Sync)>,
+    ) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+                line.pop();
+                self.stalled = false;
+                return Ok(Some(line));
+            }
+            if self.ended {
+                return Ok(Some(std::mem::take(&mut self.buffer)).filter(|line| !line.is_empty()));
+            }
+            let waiting = on_stall.is_some() && !self.buffer.is_empty() && !self.stalled;
+            let stall = async move {
+                if waiting {
+                    tokio::time::sleep(PROMPT_WAIT).await
+                } else {
+                    std::future::pending().await
+                }
+            };
+            let mut chunk = [0; 8192];
+            tokio::select! {
+                read = self.reader.read(&mut chunk) => match read? {
+                    0 => self.ended = true,
+                    n => self.buffer.extend_from_slice(&chunk[..n]),
+                },
+                () = stall => {
+                    self.stalled = true;
+                    if let Some(on_stall) = on_stall {
+                        on_stall(&String::from_utf8_lossy(&self.buffer));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How often [`trust_folder`] starts over when the configuration changes
+/// while it writes.
+const TRUST_ATTEMPTS: usize = 5;
+
+/// Mark `dir` as trusted in the CLI configuration at `config`, returning
+/// whether the file had to be changed.
+///
+/// The file is shared with the CLI and other runs, so the new file only
+/// replaces it if the old one is still unchanged, starting over otherwise.
+/// Runs in this process take turns.
+pub fn trust_folder(config: &Path, dir: &Path) -> io::Result<bool> {
+    static WRITING: Mutex<()> = Mutex::new(());
+    let _turn = WRITING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    for _ in 0..TRUST_ATTEMPTS {
+        let original = match fs::read(config) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let Some(settings) = trusted(original.as_deref(), &dir)? else {
+            return Ok(false);
+        };
+
+        // Replace the file in one step, so the CLI never reads half of it
+        if let Some(parent) = config.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = config.with_extension(format!("sdk-{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&temporary, serde_json::to_vec_pretty(&settings)?)?;
+        let current = match fs::read(config) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                let _ = fs::remove_file(&temporary);
+                return Err(e);
+            }
+        };
+        if current != original {
+            let _ = fs::remove_file(&temporary);
+            tracing::debug!(config = %config.display(), "configuration changed while marking folder as trusted, retrying");
+            continue;
+        }
+        fs::rename(&temporary, config).map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            e
+        })?;
+        tracing::info!(dir = %dir.display(), config = %config.display(), "marked folder as trusted");
+        return Ok(true);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "the configuration kept changing while marking the folder as trusted",
+    ))
+}
+
+/// The settings in `config` with `dir` trusted, or `None` if it already is.
+fn trusted(config: Option<&[u8]>, dir: &Path) -> io::Result<Option<serde_json::Value>> {
+    let mut settings: serde_json::Value = match config {
+        Some(bytes) => serde_json::from_slice(bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        None => serde_json::json!({}),
+    };
+    let key = dir.to_string_lossy().into_owned();
+    let project = settings
+        .as_object_mut()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a JSON object"))?
+        .entry("projects")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "`projects` is not an object"))?
+        .entry(key)
+        .or_insert_with(|| serde_json::json!({}));
+    if !project.is_object() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the project's settings are not an object",
+        ));
+    }
+    if project["hasTrustDialogAccepted"] == serde_json::Value::Bool(true) {
+        return Ok(None);
+    }
+    project["hasTrustDialogAccepted"] = true.into();
+    Ok(Some(settings))
+}
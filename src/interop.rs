@@ -0,0 +1,234 @@
+//! Conversions between this crate's message types and the Anthropic
+//! Messages API wire format.
+//!
+//! [`ApiMessage`] and [`ApiContentBlock`] serialize exactly like the
+//! `messages` entries of a Messages API request, which is also the format used
+//! by API client crates such as `anthropic-sdk` and `async-anthropic`. Use
+//! [`ApiMessage::to_wire`] and [`ApiMessage::from_wire`] to move between them
+//! and any such crate's message type:
+//!
+//! ```rust,ignore
+//! let api: ApiMessage = claude_message.try_into()?;
+//! let message: async_anthropic::types::Message = api.to_wire()?;
+//! ```
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::prompt::MediaSource;
+use crate::types::{
+    AssistantMessage, ContentBlock, ImageBlock, Message, RedactedThinkingBlock, TextBlock,
+    ThinkingBlock, ToolResultBlock, ToolUseBlock, UserMessage,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiRole {
+    User,
+    Assistant,
+}
+
+/// A content block as sent to and returned by the Messages API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<ToolResultContent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+    Image {
+        source: MediaSource,
+    },
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+}
+
+/// Tool result content, which the API accepts as a string or as blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ApiContentBlock>),
+}
+
+impl ToolResultContent {
+    /// The text of the content, with text blocks joined by newlines.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ApiContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// A message as sent to and returned by the Messages API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiMessage {
+    pub role: ApiRole,
+    pub content: Vec<ApiContentBlock>,
+}
+
+impl ApiMessage {
+    /// Convert into another type with the same wire format, such as an API
+    /// client crate's message type.
+    pub fn to_wire<T: DeserializeOwned>(&self) -> Result<T> {
+        let value = serde_json::to_value(self)?;
+        serde_json::from_value(value).map_err(ClaudeSDKError::from)
+    }
+
+    /// Convert from another type with the same wire format.
+    pub fn from_wire<T: Serialize>(message: &T) -> Result<Self> {
+        let value = serde_json::to_value(message)?;
+        serde_json::from_value(value).map_err(ClaudeSDKError::from)
+    }
+}
+
+impl From<ContentBlock> for ApiContentBlock {
+    fn from(block: ContentBlock) -> Self {
+        match block {
+            ContentBlock::Text(block) => Self::Text { text: block.text },
+            ContentBlock::ToolUse(block) => Self::ToolUse {
+                id: block.id,
+                name: block.name,
+                input: block.input,
+            },
+            ContentBlock::ToolResult(block) => {
+                let content = if block.images.is_empty() {
+                    block.content.map(ToolResultContent::Text)
+                } else {
+                    let text = block
+                        .content
+                        .filter(|text| !text.is_empty())
+                        .map(|text| Self::Text { text });
+                    let images = block.images.into_iter().map(|image| Self::Image {
+                        source: image.source,
+                    });
+                    Some(ToolResultContent::Blocks(
+                        text.into_iter().chain(images).collect(),
+                    ))
+                };
+                Self::ToolResult {
+                    tool_use_id: block.tool_use_id,
+                    content,
+                    is_error: block.is_error,
+                }
+            }
+            ContentBlock::Image(block) => Self::Image {
+                source: block.source,
+            },
+            ContentBlock::Thinking(block) => Self::Thinking {
+                thinking: block.thinking,
+                signature: block.signature,
+            },
+            ContentBlock::RedactedThinking(block) => Self::RedactedThinking { data: block.data },
+        }
+    }
+}
+
+impl From<ApiContentBlock> for ContentBlock {
+    fn from(block: ApiContentBlock) -> Self {
+        match block {
+            ApiContentBlock::Text { text } => TextBlock::new(text).into(),
+            ApiContentBlock::ToolUse { id, name, input } => {
+                ToolUseBlock::new(id, name, input).into()
+            }
+            ApiContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                let images = match &content {
+                    Some(ToolResultContent::Blocks(blocks)) => blocks
+                        .iter()
+                        .filter_map(|block| match block {
+                            ApiContentBlock::Image { source } => Some(ImageBlock {
+                                source: source.clone(),
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                let mut block =
+                    ToolResultBlock::new(tool_use_id, content.map(|c| c.text()), is_error);
+                block.images = images;
+                block.into()
+            }
+            ApiContentBlock::Image { source } => ImageBlock { source }.into(),
+            ApiContentBlock::Thinking {
+                thinking,
+                signature,
+            } => ThinkingBlock {
+                thinking,
+                signature,
+            }
+            .into(),
+            ApiContentBlock::RedactedThinking { data } => RedactedThinkingBlock { data }.into(),
+        }
+    }
+}
+
+impl From<UserMessage> for ApiMessage {
+    fn from(message: UserMessage) -> Self {
+        Self {
+            role: ApiRole::User,
+            content: message.content.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<AssistantMessage> for ApiMessage {
+    fn from(message: AssistantMessage) -> Self {
+        Self {
+            role: ApiRole::Assistant,
+            content: message.content.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Only user and assistant messages have an API equivalent; other messages
+/// are returned unchanged as the error.
+impl TryFrom<Message> for ApiMessage {
+    type Error = Message;
+
+    fn try_from(message: Message) -> std::result::Result<Self, Message> {
+        match message {
+            Message::User(message) => Ok(message.into()),
+            Message::Assistant(message) => Ok(message.into()),
+            other => Err(other),
+        }
+    }
+}
+
+impl From<ApiMessage> for Message {
+    fn from(message: ApiMessage) -> Self {
+        let content = message.content.into_iter().map(Into::into).collect();
+        match message.role {
+            ApiRole::User => Message::User(UserMessage::new(content)),
+            ApiRole::Assistant => Message::Assistant(AssistantMessage::new(content)),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Running the CLI with a temporary home directory.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::ClaudeCodeOptions;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs the CLI with `HOME` and the XDG base directories pointing into a
+/// fresh temporary directory, so SDK runs neither read nor change the
+/// developer's own Claude configuration, sessions and history.
+///
+/// The directory holds `.claude/settings.json`, generated from the allowed
+/// and disallowed tools of the options and overlaid with
+/// [`with_settings`](Self::with_settings). It is removed once the process
+/// is done with it.
+///
+/// Logins stored in the real home directory are not visible to the CLI.
+/// Pass an API key or provider in the options, or copy the stored login
+/// with [`with_credentials`](Self::with_credentials).
+///
+/// ```rust
+/// use claude_code_sdk::isolation::IsolatedHome;
+/// use claude_code_sdk::ClaudeCodeOptions;
+/// use serde_json::json;
+///
+/// let options = ClaudeCodeOptions::new().with_isolated_home(
+///     IsolatedHome::new()
+///         .with_credentials()
+///         .with_settings(json!({ "cleanupPeriodDays": 1 })),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IsolatedHome {
+    /// Settings merged over the generated ones, key by key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<serde_json::Value>,
+    /// Copy `~/.claude/.credentials.json` into the temporary home.
+    #[serde(default)]
+    pub copy_credentials: bool,
+}
+
+impl IsolatedHome {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_settings(mut self, settings: serde_json::Value) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Let the CLI use the login stored in the real home directory.
+    pub fn with_credentials(mut self) -> Self {
+        self.copy_credentials = true;
+        self
+    }
+
+    /// The `.claude/settings.json` written for `options`.
+    pub fn settings_for(&self, options: &ClaudeCodeOptions) -> serde_json::Value {
+        let mut permissions = serde_json::Map::new();
+        if let Some(tools) = &options.allowed_tools {
+            permissions.insert("allow".to_string(), tools.clone().into());
+        }
+        if let Some(tools) = &options.disallowed_tools {
+            permissions.insert("deny".to_string(), tools.clone().into());
+        }
+        let mut settings = serde_json::Map::new();
+        if !permissions.is_empty() {
+            settings.insert("permissions".to_string(), permissions.into());
+        }
+        if let Some(serde_json::Value::Object(overlay)) = &self.settings {
+            settings.extend(overlay.clone());
+        }
+        settings.into()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self
+            .settings
+            .as_ref()
+            .is_some_and(|settings| !settings.is_object())
+        {
+            return Err(ClaudeSDKError::invalid_options(
+                "isolated home settings must be a JSON object",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Create the temporary home for a run with `options`.
+    pub fn create(&self, options: &ClaudeCodeOptions) -> Result<HomeDir> {
+        let home = HomeDir::create()?;
+        let claude_dir = home.path.join(".claude");
+        for dir in [
+            &claude_dir,
+            &home.path.join(".config"),
+            &home.path.join(".cache"),
+            &home.path.join(".local/share"),
+            &home.path.join(".local/state"),
+        ] {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(
+            claude_dir.join("settings.json"),
+            serde_json::to_vec_pretty(&self.settings_for(options))?,
+        )?;
+        if self.copy_credentials {
+            if let Some(credentials) =
+                real_home().map(|home| home.join(".claude/.credentials.json"))
+            {
+                if credentials.is_file() {
+                    std::fs::copy(&credentials, claude_dir.join(".credentials.json"))?;
+                }
+            }
+        }
+        tracing::debug!(home = %home.path.display(), "created isolated home");
+        Ok(home)
+    }
+}
+
+fn real_home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// A temporary home directory, removed when dropped.
+#[derive(Debug)]
+pub struct HomeDir {
+    path: PathBuf,
+}
+
+impl HomeDir {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("claude-sdk-home-{}", uuid::Uuid::new_v4()));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&path)?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The environment variables pointing the CLI at this home.
+    pub fn env_vars(&self) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("HOME", self.path.clone()),
+            ("USERPROFILE", self.path.clone()),
+            ("CLAUDE_CONFIG_DIR", self.path.join(".claude")),
+            ("XDG_CONFIG_HOME", self.path.join(".config")),
+            ("XDG_CACHE_HOME", self.path.join(".cache")),
+            ("XDG_DATA_HOME", self.path.join(".local/share")),
+            ("XDG_STATE_HOME", self.path.join(".local/state")),
+        ]
+    }
+
+    /// Point `cmd` at this home, overriding the variables set in the
+    /// options' environment.
+    pub fn apply(&self, cmd: &mut Command) {
+        for (key, value) in self.env_vars() {
+            cmd.env(key, value);
+        }
+    }
+}
+
+impl Drop for HomeDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            tracing::warn!(home = %self.path.display(), error = %e, "failed to remove isolated home");
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Running several conversations at once.
+//!
+//! [`join_all_conversations`] waits for every query to finish, e.g. to fan
+//! one task out over several repositories. [`select_first_success`] races
+//! queries against each other, e.g. the same prompt on two models, keeps
+//! the first to succeed and stops the others. Both report the combined
+//! usage of every query, including what stopped and failed ones used
+//! before they ended.
+
+use crate::error::ClaudeSDKError;
+use crate::handle::QueryHandle;
+use crate::run_id::RunId;
+use crate::types::{Message, ResultMessage, Usage};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::HashSet;
+
+/// A query that ran to its end, successfully or not.
+#[derive(Debug)]
+pub struct FinishedConversation {
+    /// The position of the query among those passed in.
+    pub index: usize,
+    pub run_id: RunId,
+    pub session_id: Option<String>,
+    pub messages: Vec<Message>,
+    /// From the result message, or else the token counts of the messages
+    /// received before the query failed.
+    pub usage: Usage,
+    /// The error that ended the query early, if any.
+    pub error: Option<ClaudeSDKError>,
+}
+
+impl FinishedConversation {
+    /// The query's result message, if it got that far.
+    pub fn result(&self) -> Option<&ResultMessage> {
+        last_result(&self.messages)
+    }
+
+    /// Whether the query ended in a result that is neither an error exit
+    /// nor a cancellation.
+    pub fn is_success(&self) -> bool {
+        succeeded(&self.messages, self.error.as_ref())
+    }
+}
+
+/// Every query of a [`join_all_conversations`].
+#[derive(Debug)]
+pub struct JoinedConversations {
+    /// In the order the handles were passed in.
+    pub conversations: Vec<FinishedConversation>,
+    /// The usage of all of them together.
+    pub usage: Usage,
+}
+
+impl JoinedConversations {
+    pub fn all_succeeded(&self) -> bool {
+        self.conversations
+            .iter()
+            .all(FinishedConversation::is_success)
+    }
+}
+
+/// The outcome of a [`select_first_success`].
+#[derive(Debug)]
+pub struct Race {
+    /// The first query to succeed, or `None` if every one failed.
+    pub winner: Option<FinishedConversation>,
+    /// The queries that failed before a winner was found, in the order they
+    /// ended.
+    pub failed: Vec<FinishedConversation>,
+    /// The positions of the queries stopped once the winner was found.
+    pub cancelled: Vec<usize>,
+    /// The usage of all queries: the winner's and failed ones', plus the
+    /// tokens stopped queries reported before they were stopped. Only
+    /// result messages carry a cost, so stopped queries add none.
+    pub usage: Usage,
+}
+
+/// Read every query in `handles` to its end, concurrently.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{join_all_conversations, query_with_handle};
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let handles = vec![
+///     query_with_handle("Summarize crates/core", None).await?,
+///     query_with_handle("Summarize crates/cli", None).await?,
+/// ];
+/// let joined = join_all_conversations(handles).await;
+/// println!("cost ${:.4}", joined.usage.cost_usd);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn join_all_conversations<I>(handles: I) -> JoinedConversations
+where
+    I: IntoIterator<Item = QueryHandle>,
+{
+    let mut handles: Vec<QueryHandle> = handles.into_iter().collect();
+    let mut received: Vec<Vec<Message>> = handles.iter().map(|_| Vec::new()).collect();
+    let drained = futures::future::join_all(
+        handles
+            .iter_mut()
+            .zip(received.iter_mut())
+            .enumerate()
+            .map(|(index, (handle, messages))| drain(index, handle, messages)),
+    )
+    .await;
+
+    let mut usage = Usage::default();
+    let mut conversations = Vec::with_capacity(drained.len());
+    for (index, error) in drained {
+        let messages = std::mem::take(&mut received[index]);
+        let conversation = finish(index, &mut handles[index], messages, error).await;
+        usage.add(&conversation.usage);
+        conversations.push(conversation);
+    }
+    JoinedConversations {
+        conversations,
+        usage,
+    }
+}
+
+/// Read the queries in `handles` concurrently until one succeeds, then stop
+/// the rest and wait until their processes have been reaped.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{query_with_handle, select_first_success, ClaudeCodeOptions};
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut fast = ClaudeCodeOptions::new();
+/// fast.claude_model = Some("claude-haiku-4".to_string());
+/// let handles = vec![
+///     query_with_handle("Explain the build failure", None).await?,
+///     query_with_handle("Explain the build failure", Some(fast)).await?,
+/// ];
+/// let race = select_first_success(handles).await;
+/// if let Some(winner) = &race.winner {
+///     println!("query {} answered first", winner.index);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn select_first_success<I>(handles: I) -> Race
+where
+    I: IntoIterator<Item = QueryHandle>,
+{
+    let mut handles: Vec<QueryHandle> = handles.into_iter().collect();
+    // Outside the drains, so stopped queries keep what they received
+    let mut received: Vec<Vec<Message>> = handles.iter().map(|_| Vec::new()).collect();
+    let mut running: FuturesUnordered<_> = handles
+        .iter_mut()
+        .zip(received.iter_mut())
+        .enumerate()
+        .map(|(index, (handle, messages))| async move {
+            let (index, error) = drain(index, handle, messages).await;
+            let success = succeeded(messages, error.as_ref());
+            (index, error, success)
+        })
+        .collect();
+
+    let mut ended = Vec::new();
+    let mut winner = None;
+    while let Some((index, error, success)) = running.next().await {
+        ended.push((index, error));
+        if success {
+            winner = Some(ended.len() - 1);
+            break;
+        }
+    }
+    drop(running);
+
+    let mut usage = Usage::default();
+    let cancelled: Vec<usize> = (0..handles.len())
+        .filter(|index| !ended.iter().any(|drained| drained.0 == *index))
+        .collect();
+    for index in &cancelled {
+        if let Err(e) = handles[*index].close().await {
+            tracing::warn!(index, error = %e, "failed to stop a losing query");
+        }
+        usage.add(&partial_usage(&received[*index]));
+    }
+
+    let mut failed = Vec::new();
+    let mut won = None;
+    for (position, (index, error)) in ended.into_iter().enumerate() {
+        let messages = std::mem::take(&mut received[index]);
+        let conversation = finish(index, &mut handles[index], messages, error).await;
+        usage.add(&conversation.usage);
+        if winner == Some(position) {
+            won = Some(conversation);
+        } else {
+            failed.push(conversation);
+        }
+    }
+    if let Some(winner) = &won {
+        tracing::info!(
+            winner = winner.index,
+            cancelled = cancelled.len(),
+            "first query succeeded, stopped the others"
+        );
+    }
+    Race {
+        winner: won,
+        failed,
+        cancelled,
+        usage,
+    }
+}
+
+/// Read `handle` into `messages` until its stream ends or fails.
+async fn drain(
+    index: usize,
+    handle: &mut QueryHandle,
+    messages: &mut Vec<Message>,
+) -> (usize, Option<ClaudeSDKError>) {
+    while let Some(item) = handle.next().await {
+        match item {
+            Ok(message) => messages.push(message),
+            Err(e) => return (index, Some(e)),
+        }
+    }
+    (index, None)
+}
+
+/// The tokens the assistant messages in `messages` reported, for a query
+/// that ended without a result. The CLI repeats a message's usage with
+/// each of its content blocks, so each message id counts once.
+fn partial_usage(messages: &[Message]) -> Usage {
+    let mut usage = Usage::default();
+    let mut counted = HashSet::new();
+    for message in messages {
+        let Message::Assistant(assistant) = message else {
+            continue;
+        };
+        let Some(reported) = &assistant.usage else {
+            continue;
+        };
+        if let Some(id) = &assistant.id {
+            if !counted.insert(id.as_str()) {
+                continue;
+            }
+        }
+        usage.tokens_input += reported.input_tokens.unwrap_or(0);
+        usage.tokens_output += reported.output_tokens.unwrap_or(0);
+        usage.cache_read_tokens += reported.cache_read_input_tokens.unwrap_or(0);
+        usage.cache_creation_tokens += reported.cache_creation_input_tokens.unwrap_or(0);
+    }
+    usage
+}
+
+/// Whether a query with `messages` ended in a result that is neither an
+/// error exit nor a cancellation.
+fn succeeded(messages: &[Message], error: Option<&ClaudeSDKError>) -> bool {
+    error.is_none()
+        && last_result(messages).is_some_and(|result| {
+            result.exit_code.unwrap_or(0) == 0 && result.canceled != Some(true)
+        })
+}
+
+fn last_result(messages: &[Message]) -> Option<&ResultMessage> {
+    messages.iter().rev().find_map(|message| match message {
+        Message::Result(result) => Some(result),
+        _ => None,
+    })
+}
+
+async fn finish(
+    index: usize,
+    handle: &mut QueryHandle,
+    messages: Vec<Message>,
+    error: Option<ClaudeSDKError>,
+) -> FinishedConversation {
+    // Reap the process; a failed query may not have exited on its own
+    if let Err(e) = handle.close().await {
+        tracing::warn!(index, error = %e, "failed to close a finished query");
+    }
+    let usage = match last_result(&messages) {
+        Some(_) => handle.usage().clone(),
+        None => partial_usage(&messages),
+    };
+    FinishedConversation {
+        index,
+        run_id: handle.run_id(),
+        session_id: handle.session_id().map(str::to_string),
+        messages,
+        usage,
+        error,
+    }
+}
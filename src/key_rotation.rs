@@ -0,0 +1,163 @@
+//! Fetching rotating credentials before each CLI spawn.
+
+use crate::error::Result;
+use crate::types::{ClaudeCodeOptions, Shared};
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a fetched credential is reused by default.
+pub const DEFAULT_KEY_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A credential handed to the CLI.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credential {
+    /// An API key, passed in `ANTHROPIC_API_KEY`.
+    ApiKey(String),
+    /// A bearer token, passed in `ANTHROPIC_AUTH_TOKEN`.
+    AuthToken(String),
+}
+
+impl Credential {
+    /// The environment variable the CLI reads the credential from.
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            Self::ApiKey(_) => "ANTHROPIC_API_KEY",
+            Self::AuthToken(_) => "ANTHROPIC_AUTH_TOKEN",
+        }
+    }
+
+    pub fn secret(&self) -> &str {
+        match self {
+            Self::ApiKey(secret) | Self::AuthToken(secret) => secret,
+        }
+    }
+
+    /// Pass the credential to the CLI started with `options`, overriding
+    /// [`claude_api_key`](ClaudeCodeOptions::claude_api_key).
+    pub fn apply(&self, options: &mut ClaudeCodeOptions) {
+        options
+            .env
+            .get_or_insert_with(Default::default)
+            .insert(self.env_var().to_string(), self.secret().to_string());
+    }
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Self::ApiKey(_) => "ApiKey",
+            Self::AuthToken(_) => "AuthToken",
+        };
+        write!(f, "{}(<redacted>)", kind)
+    }
+}
+
+/// Where a [`KeyRotation`] fetches credentials from, e.g. Vault or a cloud
+/// secret manager.
+#[async_trait]
+pub trait KeySource: Send + Sync {
+    async fn fetch(&self) -> Result<Credential>;
+}
+
+/// A [`KeySource`] backed by an async closure, see [`KeyRotation::from_fn`].
+pub struct FnKeySource<F>(F);
+
+#[async_trait]
+impl<F, Fut> KeySource for FnKeySource<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Credential>> + Send,
+{
+    async fn fetch(&self) -> Result<Credential> {
+        (self.0)().await
+    }
+}
+
+/// Keeps the CLI supplied with a current credential.
+///
+/// The client consults the rotation before each spawn, including retries
+/// and fix-it turns. A fetched credential is reused until its TTL runs out;
+/// an authentication error from the API discards it and the query is
+/// started once more with a freshly fetched one. Clones share the cached
+/// credential.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::key_rotation::{Credential, KeyRotation};
+/// use claude_code_sdk::ClaudeCodeOptions;
+/// use std::time::Duration;
+///
+/// # async fn read_from_vault() -> claude_code_sdk::Result<String> { unimplemented!() }
+/// let rotation = KeyRotation::from_fn(|| async {
+///     Ok(Credential::ApiKey(read_from_vault().await?))
+/// })
+/// .with_ttl(Duration::from_secs(60));
+/// let options = ClaudeCodeOptions::new().with_key_rotation(rotation);
+/// ```
+#[derive(Clone)]
+pub struct KeyRotation {
+    source: Shared<dyn KeySource>,
+    ttl: Duration,
+    cached: Arc<Mutex<Option<(Credential, Instant)>>>,
+}
+
+impl KeyRotation {
+    pub fn new<S: KeySource + 'static>(source: S) -> Self {
+        Self {
+            source: Shared(Arc::new(source)),
+            ttl: DEFAULT_KEY_TTL,
+            cached: Arc::default(),
+        }
+    }
+
+    /// Fetch credentials with an async closure.
+    pub fn from_fn<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Credential>> + Send + 'static,
+    {
+        Self::new(FnKeySource(fetch))
+    }
+
+    /// Reuse a fetched credential for `ttl`, [`DEFAULT_KEY_TTL`] by default.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The cached credential if it is still fresh, otherwise a newly
+    /// fetched one.
+    pub async fn current(&self) -> Result<Credential> {
+        if let Some((credential, fetched_at)) = &*self.cached.lock().unwrap() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(credential.clone());
+            }
+        }
+        self.refresh().await
+    }
+
+    /// Fetch a new credential regardless of the cached one.
+    pub async fn refresh(&self) -> Result<Credential> {
+        let credential = self.source.fetch().await?;
+        *self.cached.lock().unwrap() = Some((credential.clone(), Instant::now()));
+        Ok(credential)
+    }
+
+    /// Discard the cached credential, so the next spawn fetches a new one.
+    pub fn invalidate(&self) {
+        self.cached.lock().unwrap().take();
+    }
+}
+
+impl std::fmt::Debug for KeyRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRotation")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
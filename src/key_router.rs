@@ -0,0 +1,276 @@
+//! Spreading queries across several API keys or organizations.
+
+use crate::api_error::ApiErrorKind;
+use crate::error::{ClaudeSDKError, Result};
+use crate::key_rotation::Credential;
+use crate::types::{Message, ResultMessage};
+use futures::stream::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a rate limited key is left out by default, when the API does
+/// not say when to retry.
+pub const DEFAULT_QUARANTINE: Duration = Duration::from_secs(60);
+
+/// The longest a key is left out, however far off the API says to retry.
+const MAX_QUARANTINE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// How a [`KeyRouter`] picks the key for the next query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoutingStrategy {
+    /// Hand out keys in proportion to their weights, interleaved rather than
+    /// in bursts.
+    #[default]
+    Weighted,
+    /// Pick the key that has gone unused the longest, ignoring weights.
+    LeastRecentlyUsed,
+}
+
+/// What went through a key so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyUsage {
+    pub queries: u64,
+    pub tokens_input: u64,
+    pub tokens_output: u64,
+    pub cost_usd: f64,
+    /// How many times the key was quarantined after hitting a rate limit.
+    pub rate_limits: u64,
+}
+
+/// The key chosen for a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutedKey {
+    pub name: String,
+    pub credential: Credential,
+}
+
+#[derive(Debug)]
+struct RouterKey {
+    name: String,
+    credential: Credential,
+    weight: u32,
+    // Smooth weighted round robin, as in nginx
+    current_weight: i64,
+    last_used: Option<Instant>,
+    quarantined_until: Option<Instant>,
+    usage: KeyUsage,
+}
+
+impl RouterKey {
+    fn is_available(&self, now: Instant) -> bool {
+        self.weight > 0 && self.quarantined_until.map_or(true, |until| until <= now)
+    }
+}
+
+/// Distributes queries over several credentials, for teams pooling
+/// capacity across organizations.
+///
+/// Each spawn of the CLI takes a key from the router. A key that hits a
+/// rate limit is quarantined for as long as the API asked, or
+/// [`DEFAULT_QUARANTINE`], and a query rate limited before producing output
+/// is started again on another key. Results are added to the usage of the
+/// key that produced them. Clones share keys and usage.
+///
+/// ```rust
+/// use claude_code_sdk::key_rotation::Credential;
+/// use claude_code_sdk::key_router::{KeyRouter, RoutingStrategy};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let router = KeyRouter::new(RoutingStrategy::Weighted)
+///     .with_key("team-a", Credential::ApiKey("sk-ant-a".into()), 3)
+///     .with_key("team-b", Credential::ApiKey("sk-ant-b".into()), 1);
+/// let options = ClaudeCodeOptions::new().with_key_router(router.clone());
+/// ```
+#[derive(Clone)]
+pub struct KeyRouter {
+    strategy: RoutingStrategy,
+    quarantine: Duration,
+    keys: Arc<Mutex<Vec<RouterKey>>>,
+}
+
+impl KeyRouter {
+    pub fn new(strategy: RoutingStrategy) -> Self {
+        Self {
+            strategy,
+            quarantine: DEFAULT_QUARANTINE,
+            keys: Arc::default(),
+        }
+    }
+
+    /// Add a key. `weight` only matters to [`RoutingStrategy::Weighted`];
+    /// a key with weight 0 is never picked.
+    pub fn with_key<S: Into<String>>(self, name: S, credential: Credential, weight: u32) -> Self {
+        self.keys.lock().unwrap().push(RouterKey {
+            name: name.into(),
+            credential,
+            weight,
+            current_weight: 0,
+            last_used: None,
+            quarantined_until: None,
+            usage: KeyUsage::default(),
+        });
+        self
+    }
+
+    /// Quarantine rate limited keys for `quarantine` when the API gives no
+    /// retry delay, [`DEFAULT_QUARANTINE`] by default.
+    pub fn with_quarantine(mut self, quarantine: Duration) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    pub fn strategy(&self) -> RoutingStrategy {
+        self.strategy
+    }
+
+    /// The names of the keys, in the order they were added.
+    pub fn key_names(&self) -> Vec<String> {
+        let keys = self.keys.lock().unwrap();
+        keys.iter().map(|key| key.name.clone()).collect()
+    }
+
+    /// Pick the key for the next query and count the query against it.
+    ///
+    /// Fails with a rate limit error, retryable once the first quarantine
+    /// ends, when every key is quarantined.
+    pub fn select(&self) -> Result<RoutedKey> {
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap();
+        if keys.iter().all(|key| key.weight == 0) {
+            return Err(ClaudeSDKError::invalid_options(
+                "the key router has no key with a weight above 0",
+            ));
+        }
+
+        let index = match self.strategy {
+            RoutingStrategy::Weighted => {
+                let total: i64 = keys
+                    .iter()
+                    .filter(|key| key.is_available(now))
+                    .map(|key| i64::from(key.weight))
+                    .sum();
+                let mut chosen = None;
+                for (index, key) in keys.iter_mut().enumerate() {
+                    if key.is_available(now) {
+                        key.current_weight += i64::from(key.weight);
+                        if chosen.map_or(true, |(_, weight)| key.current_weight > weight) {
+                            chosen = Some((index, key.current_weight));
+                        }
+                    }
+                }
+                chosen.map(|(index, _)| {
+                    keys[index].current_weight -= total;
+                    index
+                })
+            }
+            RoutingStrategy::LeastRecentlyUsed => keys
+                .iter()
+                .enumerate()
+                .filter(|(_, key)| key.is_available(now))
+                .min_by_key(|(_, key)| key.last_used)
+                .map(|(index, _)| index),
+        };
+
+        let Some(index) = index else {
+            let retry_after = keys
+                .iter()
+                .filter_map(|key| key.quarantined_until)
+                .min()
+                .map(|until| until.saturating_duration_since(now));
+            return Err(ClaudeSDKError::Api {
+                kind: ApiErrorKind::RateLimit,
+                message: "every key of the key router is quarantined".to_string(),
+                retry_after,
+            });
+        };
+        let key = &mut keys[index];
+        key.last_used = Some(now);
+        key.usage.queries += 1;
+        Ok(RoutedKey {
+            name: key.name.clone(),
+            credential: key.credential.clone(),
+        })
+    }
+
+    /// Leave `name` out for `retry_after`, or the router's quarantine if
+    /// `None`, but no longer than a year.
+    pub fn quarantine(&self, name: &str, retry_after: Option<Duration>) {
+        let now = Instant::now();
+        let until = now
+            .checked_add(retry_after.unwrap_or(self.quarantine).min(MAX_QUARANTINE))
+            .unwrap_or(now);
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(key) = keys.iter_mut().find(|key| key.name == name) {
+            key.quarantined_until = Some(until);
+            key.usage.rate_limits += 1;
+        }
+    }
+
+    pub fn is_quarantined(&self, name: &str) -> bool {
+        let now = Instant::now();
+        let keys = self.keys.lock().unwrap();
+        keys.iter()
+            .any(|key| key.name == name && key.quarantined_until.is_some_and(|until| until > now))
+    }
+
+    /// Whether any key can currently be picked.
+    pub fn has_available_key(&self) -> bool {
+        let now = Instant::now();
+        let keys = self.keys.lock().unwrap();
+        keys.iter().any(|key| key.is_available(now))
+    }
+
+    /// Add the tokens and cost of `result` to the usage of `name`.
+    pub fn record_usage(&self, name: &str, result: &ResultMessage) {
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(key) = keys.iter_mut().find(|key| key.name == name) {
+            let tokens = |count: Option<i32>| u64::try_from(count.unwrap_or(0)).unwrap_or(0);
+            key.usage.tokens_input += tokens(result.tokens_input);
+            key.usage.tokens_output += tokens(result.tokens_output);
+            key.usage.cost_usd += result.cost_usd.unwrap_or(0.0);
+        }
+    }
+
+    pub fn usage(&self, name: &str) -> Option<KeyUsage> {
+        let keys = self.keys.lock().unwrap();
+        keys.iter()
+            .find(|key| key.name == name)
+            .map(|key| key.usage.clone())
+    }
+}
+
+impl std::fmt::Debug for KeyRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRouter")
+            .field("strategy", &self.strategy)
+            .field("quarantine", &self.quarantine)
+            .field("keys", &self.key_names())
+            .finish()
+    }
+}
+
+pub(crate) fn is_rate_limit_error(error: &ClaudeSDKError) -> bool {
+    matches!(
+        error.root(),
+        ClaudeSDKError::Api {
+            kind: ApiErrorKind::RateLimit,
+            ..
+        }
+    )
+}
+
+/// Record the results of a query routed to `name`, quarantining the key if
+/// the query hits a rate limit.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) fn track_key(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    router: KeyRouter,
+    name: String,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(stream.inspect(move |item| match item {
+        Ok(Message::Result(result)) => router.record_usage(&name, result),
+        Err(e) if is_rate_limit_error(e) => router.quarantine(&name, e.retry_after()),
+        _ => {}
+    }))
+}
@@ -0,0 +1,183 @@
+use crate::error::{ClaudeSDKError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A validated BCP 47 language tag such as `en`, `fr-CA` or `zh-Hant-TW`.
+///
+/// Besides regular language tags this accepts private use tags such as
+/// `x-klingon` and the irregular grandfathered tags such as `i-default`
+/// (RFC 5646 section 2.1).
+///
+/// Parsing normalizes the case of each subtag: the language is lowercase,
+/// the script titlecase and the region uppercase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LanguageTag(String);
+
+impl LanguageTag {
+    pub fn parse(tag: &str) -> Result<Self> {
+        let invalid =
+            || ClaudeSDKError::invalid_options(format!("Invalid language tag: {:?}", tag));
+
+        let subtags: Vec<&str> = tag.split('-').collect();
+        if subtags
+            .iter()
+            .any(|s| s.is_empty() || s.len() > 8 || !s.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            return Err(invalid());
+        }
+
+        if let Some(grandfathered) = IRREGULAR
+            .iter()
+            .find(|irregular| irregular.eq_ignore_ascii_case(tag))
+        {
+            return Ok(Self(grandfathered.to_string()));
+        }
+
+        let mut normalized = Vec::with_capacity(subtags.len());
+        let mut rest = subtags.as_slice();
+
+        // A private use tag is only a private use sequence
+        if !rest[0].eq_ignore_ascii_case("x") {
+            // language, with optional extended language subtags
+            let language = rest[0];
+            let valid_length =
+                (2..=3).contains(&language.len()) || (5..=8).contains(&language.len());
+            if !is_alpha(language) || !valid_length {
+                return Err(invalid());
+            }
+            normalized.push(language.to_ascii_lowercase());
+            rest = &rest[1..];
+            if language.len() <= 3 {
+                let mut extlangs = 0;
+                while let Some(subtag) = rest.first().filter(|s| s.len() == 3 && is_alpha(s)) {
+                    extlangs += 1;
+                    if extlangs > 3 {
+                        return Err(invalid());
+                    }
+                    normalized.push(subtag.to_ascii_lowercase());
+                    rest = &rest[1..];
+                }
+            }
+
+            // script
+            if let Some(subtag) = rest.first().filter(|s| s.len() == 4 && is_alpha(s)) {
+                let mut script = subtag.to_ascii_lowercase();
+                script[..1].make_ascii_uppercase();
+                normalized.push(script);
+                rest = &rest[1..];
+            }
+
+            // region
+            if let Some(subtag) = rest
+                .first()
+                .filter(|s| (s.len() == 2 && is_alpha(s)) || (s.len() == 3 && is_digit(s)))
+            {
+                normalized.push(subtag.to_ascii_uppercase());
+                rest = &rest[1..];
+            }
+
+            // variants
+            while let Some(subtag) = rest.first().filter(|s| {
+                (5..=8).contains(&s.len()) || (s.len() == 4 && s.as_bytes()[0].is_ascii_digit())
+            }) {
+                normalized.push(subtag.to_ascii_lowercase());
+                rest = &rest[1..];
+            }
+        }
+
+        // extensions and private use
+        while let Some(singleton) = rest.first() {
+            if singleton.len() != 1 {
+                return Err(invalid());
+            }
+            let private_use = singleton.eq_ignore_ascii_case("x");
+            normalized.push(singleton.to_ascii_lowercase());
+            rest = &rest[1..];
+
+            let min_len = if private_use { 1 } else { 2 };
+            let count = rest
+                .iter()
+                .take_while(|s| private_use || s.len() >= min_len)
+                .count();
+            if count == 0 {
+                return Err(invalid());
+            }
+            normalized.extend(rest[..count].iter().map(|s| s.to_ascii_lowercase()));
+            rest = &rest[count..];
+        }
+
+        Ok(Self(normalized.join("-")))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The system prompt instruction asking for responses in this language.
+    pub fn instruction(&self) -> String {
+        format!(
+            "Always respond in the language identified by the BCP 47 language tag \"{}\", \
+             regardless of the language used in the prompt, unless explicitly asked otherwise.",
+            self.0
+        )
+    }
+}
+
+/// The grandfathered tags that do not match the regular tag syntax.
+const IRREGULAR: &[&str] = &[
+    "en-GB-oed",
+    "i-ami",
+    "i-bnn",
+    "i-default",
+    "i-enochian",
+    "i-hak",
+    "i-klingon",
+    "i-lux",
+    "i-mingo",
+    "i-navajo",
+    "i-pwn",
+    "i-tao",
+    "i-tay",
+    "i-tsu",
+    "sgn-BE-FR",
+    "sgn-BE-NL",
+    "sgn-CH-DE",
+];
+
+fn is_alpha(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn is_digit(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_digit())
+}
+
+impl FromStr for LanguageTag {
+    type Err = ClaudeSDKError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for LanguageTag {
+    type Error = ClaudeSDKError;
+
+    fn try_from(tag: String) -> Result<Self> {
+        Self::parse(&tag)
+    }
+}
+
+impl From<LanguageTag> for String {
+    fn from(tag: LanguageTag) -> Self {
+        tag.0
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
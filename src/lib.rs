@@ -43,18 +43,140 @@
 //! }
 //! ```
 
+// `--cfg claude_sdk_nightly` implements `AsyncIterator`, see `async_iter`.
+// Allowed here rather than declared in `[lints]`, which needs Cargo 1.74
+#![allow(unexpected_cfgs)]
+#![cfg_attr(claude_sdk_nightly, feature(async_iterator))]
+
+pub mod analytics;
+pub mod annotations;
+pub mod anonymize;
+pub mod api_error;
+#[cfg(feature = "subprocess")]
+pub mod archive;
+pub mod async_iter;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod buffered;
+pub mod capabilities;
+pub mod cargo;
+pub mod checkpoint;
+#[cfg(feature = "subprocess")]
 pub mod client;
+pub mod compat;
+pub mod compression;
+pub mod context_files;
+#[cfg(feature = "subprocess")]
+pub mod control;
+pub mod conversation_tree;
+pub mod danger;
+pub mod dataset;
+pub mod debugger;
+pub mod diagnostics;
 pub mod error;
+pub mod external_tools;
+pub mod file_lock;
+#[cfg(feature = "file-patch")]
+pub mod file_patch;
+pub mod filter;
+#[cfg(feature = "github")]
+pub mod github;
+#[cfg(feature = "subprocess")]
+pub mod handle;
+pub mod hooks;
+pub mod idempotency;
+pub mod interaction;
+#[cfg(feature = "anthropic-interop")]
+pub mod interop;
+pub mod isolation;
+#[cfg(feature = "subprocess")]
+pub mod join;
+pub mod key_rotation;
+pub mod key_router;
+pub mod language;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+#[cfg(feature = "subprocess")]
+pub mod memory;
+pub mod middleware;
+pub mod monorepo;
+pub mod negotiation;
+pub mod output;
+pub mod output_budget;
+pub mod overlay;
+#[cfg(feature = "subprocess")]
+pub mod pool;
+pub mod progress;
+pub mod prompt;
+pub mod protocol;
+pub mod provider;
+pub mod proxy;
+pub mod quota;
+pub mod refusal;
+pub mod repro;
+pub mod retry;
+pub mod run_id;
+#[cfg(feature = "sandbox-linux")]
+pub mod sandbox;
+#[cfg(feature = "subprocess")]
+pub mod script;
+pub mod sdk_info;
+pub mod sdk_mcp;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod speech;
+pub mod sse;
+pub mod stats;
+pub mod stderr_log;
+#[cfg(feature = "subprocess")]
+pub mod tap;
+pub mod tool_policy;
 pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod turn_retry;
 pub mod types;
+pub mod update;
+pub mod verify;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+pub mod workspace_guard;
 
+#[cfg(feature = "subprocess")]
+pub use capabilities::probe_capabilities;
+pub use capabilities::Capabilities;
+pub use checkpoint::{Checkpoint, WorkspaceSnapshot};
+#[cfg(feature = "subprocess")]
 use client::InternalClient;
-pub use error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+pub use client::{ClaudeSDKClient, Readiness};
+pub use compat::{CompatMode, OptionWarning, UnsupportedReason};
+pub use error::{ClaudeSDKError, ErrorCode, ErrorContext, ErrorJson, Result};
+pub use filter::MessageFilter;
+#[cfg(feature = "subprocess")]
 use futures::stream::Stream;
+#[cfg(feature = "subprocess")]
+pub use handle::QueryHandle;
+#[cfg(feature = "subprocess")]
+pub use join::{join_all_conversations, select_first_success};
+pub use language::LanguageTag;
+pub use progress::ToolProgressEvent;
+pub use prompt::{MediaSource, PromptInput};
+pub use provider::Provider;
+pub use proxy::ProxyConfig;
+pub use refusal::{Refusal, RefusalCategory};
+pub use retry::RetryPolicy;
+pub use run_id::RunId;
+pub use sdk_info::SdkInfo;
+#[cfg(feature = "subprocess")]
 use std::env;
+#[cfg(feature = "subprocess")]
 use std::pin::Pin;
+#[cfg(feature = "subprocess")]
+use tracing::Instrument;
 pub use types::*;
 
+#[cfg(feature = "subprocess")]
 /// Query Claude Code with a prompt and optional configuration.
 ///
 /// This is the main entry point for the SDK. It creates a client, connects to
@@ -95,19 +217,128 @@ pub use types::*;
 ///     Ok(())
 /// }
 /// ```
-pub async fn query(
-    prompt: &str,
+pub async fn query<P: Into<PromptInput>>(
+    prompt: P,
     options: Option<ClaudeCodeOptions>,
 ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+    Ok(Box::pin(query_with_handle(prompt, options).await?))
+}
+
+#[cfg(feature = "subprocess")]
+/// Like [`query`], but returning a stream without a `Send` bound.
+///
+/// For single-threaded executors such as a Tokio `LocalSet`, where the
+/// stream is stored in types that are not `Send` either. [`query`] works in
+/// those too; this variant only saves callers from spelling out the `Send`
+/// bound in their own types.
+pub async fn query_local<P: Into<PromptInput>>(
+    prompt: P,
+    options: Option<ClaudeCodeOptions>,
+) -> Result<Pin<Box<dyn Stream<Item = Result<Message>>>>> {
+    Ok(Box::pin(query_with_handle(prompt, options).await?))
+}
+
+#[cfg(feature = "subprocess")]
+/// Query Claude Code and return a [`QueryHandle`].
+///
+/// The handle streams the same messages as [`query`], and additionally
+/// exposes the session state observed so far (session id, turn count,
+/// usage) and can capture a [`Checkpoint`] of the run.
+pub async fn query_with_handle<P: Into<PromptInput>>(
+    prompt: P,
+    options: Option<ClaudeCodeOptions>,
+) -> Result<QueryHandle> {
+    let prompt = prompt.into();
     // Set environment variable to identify SDK usage
     env::set_var("CLAUDE_CODE_ENTRYPOINT", "sdk-rust");
 
-    let options = options.unwrap_or_default();
+    let (prompt, mut options) = client::resolve(prompt, options.unwrap_or_default()).await?;
+    let run_id = *options.run_id.get_or_insert_with(RunId::new);
+    let span = tracing::info_span!("claude_code_query", run_id = %run_id);
+
+    let started = std::time::Instant::now();
     let mut client = InternalClient::new();
+    let stream = client
+        .process_query(prompt.clone(), options.clone())
+        .instrument(span.clone())
+        .await;
+    if stream.is_ok() {
+        options.stats().record_spawn(started.elapsed());
+    }
+
+    #[cfg(feature = "webhooks")]
+    if let Some(notifier) = &options.webhook_notifier {
+        notifier.notify_in_background(match &stream {
+            Ok(_) => webhook::WebhookEvent::RunStarted {
+                run_id,
+                prompt: prompt.text_content(),
+            },
+            Err(e) => webhook::WebhookEvent::RunFailed {
+                run_id,
+                error: e.to_string(),
+            },
+        });
+    }
+
+    Ok(QueryHandle::new(client, stream?, options, span)
+        .started_at(started)
+        .with_prompt(prompt))
+}
+
+#[cfg(feature = "subprocess")]
+/// Continue a run captured with [`QueryHandle::checkpoint`].
+///
+/// The session is resumed with `prompt` as the next user turn. When no `cwd`
+/// is set in `options`, the checkpoint's workspace root is used, so a run can
+/// be picked up on another machine as long as the workspace is shared. If
+/// the checkpoint recorded a git revision, the workspace must still be at
+/// it, or resuming fails with a [`Checkpoint`](ClaudeSDKError::Checkpoint)
+/// error.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{query_with_handle, resume_from_checkpoint};
+/// use tokio_stream::StreamExt;
+///
+/// #[tokio::main]
+/// async fn main() -> claude_code_sdk::Result<()> {
+///     let mut handle = query_with_handle("Start refactoring src/", None).await?;
+///     while let Some(message) = handle.next().await {
+///         message?;
+///     }
+///
+///     let json = handle.checkpoint().await?.to_json()?;
+///
+///     // ... later, possibly elsewhere
+///     let checkpoint = claude_code_sdk::Checkpoint::from_json(&json)?;
+///     let mut resumed = resume_from_checkpoint(&checkpoint, "Continue", None).await?;
+///     while let Some(message) = resumed.next().await {
+///         println!("{:?}", message?);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn resume_from_checkpoint<P: Into<PromptInput>>(
+    checkpoint: &Checkpoint,
+    prompt: P,
+    options: Option<ClaudeCodeOptions>,
+) -> Result<QueryHandle> {
+    let mut options = options
+        .unwrap_or_default()
+        .with_resume(checkpoint.session_id.clone());
+
+    if let Some(workspace) = &checkpoint.workspace {
+        let root = options.cwd.get_or_insert_with(|| workspace.root.clone());
+        workspace.verify(root).await?;
+    }
 
-    client.process_query(prompt.to_string(), options).await
+    let handle = query_with_handle(prompt, Some(options)).await?;
+    Ok(handle.resumed_from(checkpoint))
 }
 
 // Re-export commonly used types at the crate root
 pub use error::ClaudeSDKError as Error;
-pub use transport::Transport;
+#[cfg(feature = "subprocess")]
+pub use transport::{DisposeGuard, ProcessExit, Termination, Transport};
@@ -0,0 +1,627 @@
+//! A language server bridging editors to the agent.
+//!
+//! [`serve`] speaks the Language Server Protocol over any reader and
+//! writer, so an editor plugin can start it on stdio, next to the
+//! language's own server, and offer Claude on the code being edited:
+//!
+//! - `textDocument/codeAction` offers "Ask Claude about this code" and
+//!   "Fix with Claude" on the selection; the fix carries the diagnostics
+//!   reported in it.
+//! - `workspace/executeCommand` runs the commands behind them.
+//!   [`ASK_COMMAND`] returns `{"answer": ...}` with Claude's reply.
+//!   [`FIX_COMMAND`] asks for a replacement of the selected lines, applies
+//!   it with a `workspace/applyEdit` request and returns `{"edit": ...}`
+//!   with the text edit it sent.
+//!
+//! Commands query through one [`ClaudeSDKClient`] connected at
+//! `initialize`, in the workspace root unless the options set a working
+//! directory, and their queries run concurrently. A failed connection fails
+//! `initialize`; a failed query fails its command, with the error's
+//! [JSON form](ClaudeSDKError::to_json) as the error data. Documents are
+//! synced in full.
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::ClaudeCodeOptions;
+//! use tokio::io::BufReader;
+//!
+//! # async fn run() -> claude_code_sdk::Result<()> {
+//! let options = ClaudeCodeOptions::new().with_max_turns(3);
+//! claude_code_sdk::lsp::serve(options, BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ClaudeSDKClient;
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::{ClaudeCodeOptions, ContentBlock, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// The command answering a question about a selection.
+pub const ASK_COMMAND: &str = "claude.ask";
+/// The command replacing a selection with Claude's fix.
+pub const FIX_COMMAND: &str = "claude.fix";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// `TextDocumentSyncKind.Full`
+const FULL_SYNC: u8 = 1;
+
+/// The largest message body read, so a bad `Content-Length` cannot
+/// allocate without bound.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// A position in a document; `character` counts UTF-16 code units, as in
+/// LSP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// The range extended to the start of its first line and the start of
+    /// the line after its last one.
+    fn whole_lines(&self) -> Self {
+        let end_line = if self.end.character == 0 && self.end.line > self.start.line {
+            self.end.line
+        } else {
+            self.end.line.saturating_add(1)
+        };
+        Self {
+            start: Position {
+                line: self.start.line,
+                character: 0,
+            },
+            end: Position {
+                line: end_line,
+                character: 0,
+            },
+        }
+    }
+}
+
+/// The argument of [`ASK_COMMAND`] and [`FIX_COMMAND`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionArgs {
+    pub uri: String,
+    pub range: Range,
+    /// The question to ask, or what to fix. Asking defaults to an
+    /// explanation, fixing to the diagnostics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction: Option<String>,
+    /// Messages of the diagnostics in the range.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<String>,
+}
+
+/// Read the body of the next message: headers up to an empty line, then
+/// `Content-Length` bytes. `None` at the end of the input.
+pub async fn read_message<R>(reader: &mut R) -> Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            match length {
+                Some(_) => break,
+                None => continue,
+            }
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim().parse::<usize>().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid Content-Length: {}", value.trim()),
+                    )
+                })?;
+                if value > MAX_MESSAGE_BYTES {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Content-Length {} exceeds the limit of {} bytes",
+                            value, MAX_MESSAGE_BYTES
+                        ),
+                    )
+                    .into());
+                }
+                length = Some(value);
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// Write `message` with its `Content-Length` header.
+pub async fn write_message<W>(writer: &mut W, message: &Value) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let body = serde_json::to_vec(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Serve the editor on `reader` and `writer` until it sends `exit` or the
+/// input ends.
+pub async fn serve<R, W>(options: ClaudeCodeOptions, mut reader: R, writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    let writer_task = tokio::spawn(write_messages(rx, writer));
+    let mut server = Server {
+        options,
+        client: None,
+        documents: HashMap::new(),
+        running: Vec::new(),
+        tx,
+        edits_sent: 0,
+        shutting_down: false,
+    };
+
+    let mut exited = false;
+    while let Some(body) = read_message(&mut reader).await? {
+        server.running.retain(|task| !task.is_finished());
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(message) => {
+                if server.handle(message).await {
+                    exited = true;
+                    break;
+                }
+            }
+            Err(e) => server.send(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                format!("Invalid message: {}", e),
+                None,
+            )),
+        }
+    }
+
+    let Server { running, tx, .. } = server;
+    for task in running {
+        if exited {
+            task.abort();
+        } else {
+            let _ = task.await;
+        }
+    }
+    drop(tx);
+    writer_task
+        .await
+        .map_err(|e| ClaudeSDKError::cli_connection(format!("Message writer failed: {}", e)))?
+}
+
+struct Server {
+    options: ClaudeCodeOptions,
+    /// Shared by the command tasks, which only hold it to start their
+    /// query.
+    client: Option<Arc<Mutex<ClaudeSDKClient>>>,
+    /// The text of each open document, by URI.
+    documents: HashMap<String, String>,
+    running: Vec<JoinHandle<()>>,
+    tx: mpsc::UnboundedSender<Value>,
+    edits_sent: u64,
+    shutting_down: bool,
+}
+
+impl Server {
+    fn send(&self, message: Value) {
+        let _ = self.tx.send(message);
+    }
+
+    /// Handle one message, returning whether the server should exit.
+    async fn handle(&mut self, message: Value) -> bool {
+        // Responses to our `workspace/applyEdit` requests need no handling
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return false;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let Some(id) = message.get("id").cloned() else {
+            match method {
+                "exit" => return true,
+                "textDocument/didOpen" => {
+                    if let (Some(uri), Some(text)) = (
+                        params["textDocument"]["uri"].as_str(),
+                        params["textDocument"]["text"].as_str(),
+                    ) {
+                        self.documents.insert(uri.to_string(), text.to_string());
+                    }
+                }
+                "textDocument/didChange" => {
+                    let text = params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str());
+                    if let (Some(uri), Some(text)) = (params["textDocument"]["uri"].as_str(), text)
+                    {
+                        self.documents.insert(uri.to_string(), text.to_string());
+                    }
+                }
+                "textDocument/didClose" => {
+                    if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                        self.documents.remove(uri);
+                    }
+                }
+                _ => {}
+            }
+            return false;
+        };
+
+        let response = match method {
+            "initialize" => self.initialize(id, params).await,
+            _ if self.client.is_none() => {
+                error_response(id, SERVER_NOT_INITIALIZED, "Server not initialized", None)
+            }
+            "shutdown" => {
+                self.shutting_down = true;
+                response(id, Value::Null)
+            }
+            _ if self.shutting_down => {
+                error_response(id, INVALID_REQUEST, "Server is shutting down", None)
+            }
+            "textDocument/codeAction" => response(id, self.code_actions(&params)),
+            "workspace/executeCommand" => match self.execute_command(id.clone(), params) {
+                Ok(()) => return false,
+                Err(message) => error_response(id, INVALID_PARAMS, message, None),
+            },
+            method => error_response(
+                id,
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+                None,
+            ),
+        };
+        self.send(response);
+        false
+    }
+
+    async fn initialize(&mut self, id: Value, params: Value) -> Value {
+        let mut options = self.options.clone();
+        if options.cwd.is_none() {
+            options.cwd = params["rootUri"]
+                .as_str()
+                .or_else(|| params["workspaceFolders"][0]["uri"].as_str())
+                .and_then(uri_to_path)
+                .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        }
+        match ClaudeSDKClient::connect(options).await {
+            Ok(client) => {
+                self.client = Some(Arc::new(Mutex::new(client)));
+                response(
+                    id,
+                    json!({
+                        "capabilities": {
+                            "textDocumentSync": FULL_SYNC,
+                            "codeActionProvider": {
+                                "codeActionKinds": ["quickfix", "refactor"],
+                            },
+                            "executeCommandProvider": {
+                                "commands": [ASK_COMMAND, FIX_COMMAND],
+                            },
+                        },
+                        "serverInfo": {
+                            "name": "claude-code-sdk",
+                            "version": env!("CARGO_PKG_VERSION"),
+                        },
+                    }),
+                )
+            }
+            Err(e) => error_response(id, INTERNAL_ERROR, e.to_string(), Some(e.to_json())),
+        }
+    }
+
+    fn code_actions(&self, params: &Value) -> Value {
+        let Some(uri) = params["textDocument"]["uri"].as_str() else {
+            return json!([]);
+        };
+        let Ok(range) = serde_json::from_value::<Range>(params["range"].clone()) else {
+            return json!([]);
+        };
+        if !self.documents.contains_key(uri) {
+            return json!([]);
+        }
+        let diagnostics: Vec<String> = params["context"]["diagnostics"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|diagnostic| diagnostic["message"].as_str().map(String::from))
+            .collect();
+        let action = |title: &str, kind: &str, command: &str, args: SelectionArgs| {
+            json!({
+                "title": title,
+                "kind": kind,
+                "command": { "title": title, "command": command, "arguments": [args] },
+            })
+        };
+        json!([
+            action(
+                "Ask Claude about this code",
+                "refactor",
+                ASK_COMMAND,
+                SelectionArgs {
+                    uri: uri.to_string(),
+                    range: if range.is_empty() {
+                        range.whole_lines()
+                    } else {
+                        range
+                    },
+                    instruction: None,
+                    diagnostics: Vec::new(),
+                },
+            ),
+            action(
+                "Fix with Claude",
+                "quickfix",
+                FIX_COMMAND,
+                SelectionArgs {
+                    uri: uri.to_string(),
+                    range: range.whole_lines(),
+                    instruction: None,
+                    diagnostics,
+                },
+            ),
+        ])
+    }
+
+    /// Start the command in `params`, which responds to `id` once done.
+    fn execute_command(&mut self, id: Value, params: Value) -> std::result::Result<(), String> {
+        let command = params["command"].as_str().unwrap_or_default().to_string();
+        if command != ASK_COMMAND && command != FIX_COMMAND {
+            return Err(format!("Unknown command: {}", command));
+        }
+        let args: SelectionArgs = serde_json::from_value(params["arguments"][0].clone())
+            .map_err(|e| format!("Invalid arguments: {}", e))?;
+        let text = self
+            .documents
+            .get(&args.uri)
+            .ok_or_else(|| format!("{} is not open", args.uri))?;
+        let start = offset(text, args.range.start);
+        let end = offset(text, args.range.end).max(start);
+        let selection = text[start..end].to_string();
+
+        let client = self.client.clone().expect("initialized before commands");
+        let tx = self.tx.clone();
+        let task = if command == ASK_COMMAND {
+            let prompt = ask_prompt(&args, &selection);
+            tokio::spawn(async move {
+                let message = match reply(&client, prompt).await {
+                    Ok(answer) => response(id, json!({ "answer": answer })),
+                    Err(e) => failure(id, &e),
+                };
+                let _ = tx.send(message);
+            })
+        } else {
+            self.edits_sent += 1;
+            let edit_id = format!("claude-fix-{}", self.edits_sent);
+            let prompt = fix_prompt(&args, &selection);
+            tokio::spawn(async move {
+                let code = match reply(&client, prompt).await {
+                    Ok(code) => code,
+                    Err(e) => {
+                        let _ = tx.send(failure(id, &e));
+                        return;
+                    }
+                };
+                let mut new_text = strip_code_fence(&code).to_string();
+                if selection.ends_with('\n') && !new_text.ends_with('\n') {
+                    new_text.push('\n');
+                }
+                let edit = json!({ "range": args.range, "newText": new_text });
+                let _ = tx.send(json!({
+                    "jsonrpc": "2.0",
+                    "id": edit_id,
+                    "method": "workspace/applyEdit",
+                    "params": {
+                        "label": "Fix with Claude",
+                        "edit": { "changes": { args.uri: [edit.clone()] } },
+                    },
+                }));
+                let _ = tx.send(response(id, json!({ "edit": edit })));
+            })
+        };
+        self.running.push(task);
+        Ok(())
+    }
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: impl ToString, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message.to_string() });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn failure(id: Value, error: &ClaudeSDKError) -> Value {
+    error_response(id, INTERNAL_ERROR, error, Some(error.to_json()))
+}
+
+/// Claude's reply to `prompt`: the result's text, or else the text of its
+/// messages.
+async fn reply(client: &Mutex<ClaudeSDKClient>, prompt: String) -> Result<String> {
+    let mut handle = client.lock().await.query(prompt).await?;
+    let mut text = Vec::new();
+    while let Some(message) = handle.next().await {
+        match message? {
+            Message::Assistant(message) if message.parent_tool_use_id.is_none() => {
+                text.extend(message.content.into_iter().filter_map(|block| match block {
+                    ContentBlock::Text(block) => Some(block.text),
+                    _ => None,
+                }));
+            }
+            Message::Result(result) => {
+                if let Some(content) = result.content.filter(|c| !c.trim().is_empty()) {
+                    return Ok(content);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(text.join("\n"))
+}
+
+/// Where the selection is, for the prompt: the file and its lines.
+fn location(args: &SelectionArgs) -> String {
+    let file = uri_to_path(&args.uri)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| args.uri.clone());
+    let first = args.range.start.line + 1;
+    let last = match args.range.end {
+        Position { character: 0, line } if line > args.range.start.line => line,
+        Position { line, .. } => line.saturating_add(1),
+    };
+    format!("{}, lines {}-{}", file, first, last)
+}
+
+fn ask_prompt(args: &SelectionArgs, selection: &str) -> String {
+    let question = args.instruction.as_deref().unwrap_or("Explain this code.");
+    format!(
+        "{}\n\nThe code is from {}:\n\n```\n{}\n```",
+        question,
+        location(args),
+        selection.trim_end()
+    )
+}
+
+fn fix_prompt(args: &SelectionArgs, selection: &str) -> String {
+    let problem = match (&args.instruction, args.diagnostics.is_empty()) {
+        (Some(instruction), _) => instruction.clone(),
+        (None, false) => format!("Problems reported:\n- {}", args.diagnostics.join("\n- ")),
+        (None, true) => "Fix any bugs in it.".to_string(),
+    };
+    format!(
+        "Fix the following code from {}.\n{}\n\n```\n{}\n```\n\n\
+         Reply with only the corrected code replacing these lines, \
+         without explanations or code fences.",
+        location(args),
+        problem,
+        selection.trim_end()
+    )
+}
+
+/// `code` without the Markdown code fence Claude may wrap it in anyway.
+fn strip_code_fence(code: &str) -> &str {
+    let trimmed = code.trim();
+    let Some(inner) = trimmed.strip_prefix("```") else {
+        return code;
+    };
+    let Some(inner) = inner.strip_suffix("```") else {
+        return code;
+    };
+    // Drop the info string, e.g. `rust`
+    match inner.split_once('\n') {
+        Some((_, body)) => body.trim_end_matches('\n'),
+        None => inner,
+    }
+}
+
+/// The byte offset of `position` in `text`, clamped to its line and to the
+/// end of the text.
+fn offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |newline| line_start + newline);
+    let mut units = 0;
+    for (index, c) in text[line_start..line_end].char_indices() {
+        if units >= position.character as usize {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    line_end
+}
+
+/// The path of a `file:` URI.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = match (byte, tail) {
+            (b'%', [high, low, ..]) => std::str::from_utf8(&[*high, *low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/src` names `C:/src` on Windows
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+async fn write_messages<W>(mut rx: mpsc::UnboundedReceiver<Value>, mut writer: W) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(message) = rx.recv().await {
+        write_message(&mut writer, &message).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::{ClaudeCodeOptions, ContentBlock, Message};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio_stream::StreamExt;
+
+const SUMMARY_PROMPT: &str = "Write a compact memory of this session for your future self working \
+on the same project: key facts about the codebase, decisions made, conventions learned and open \
+tasks. Use terse bullet points, at most 300 words. Output only the memory.";
+
+/// Storage for per-project memory summaries.
+#[async_trait]
+pub trait MemoryStore: Send + Sync {
+    async fn load(&self, project: &str) -> Result<Option<String>>;
+    async fn save(&self, project: &str, summary: &str) -> Result<()>;
+}
+
+/// Stores each project's memory as a markdown file in a directory.
+#[derive(Debug, Clone)]
+pub struct FileMemoryStore {
+    dir: PathBuf,
+}
+
+impl FileMemoryStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, project: &str) -> PathBuf {
+        let name: String = project
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.md", name))
+    }
+}
+
+#[async_trait]
+impl MemoryStore for FileMemoryStore {
+    async fn load(&self, project: &str) -> Result<Option<String>> {
+        match tokio::fs::read_to_string(self.path(project)).await {
+            Ok(summary) => Ok(Some(summary)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, project: &str, summary: &str) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.path(project), summary).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryMemoryStore {
+    summaries: Mutex<HashMap<String, String>>,
+}
+
+impl InMemoryMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MemoryStore for InMemoryMemoryStore {
+    async fn load(&self, project: &str) -> Result<Option<String>> {
+        Ok(self.summaries.lock().unwrap().get(project).cloned())
+    }
+
+    async fn save(&self, project: &str, summary: &str) -> Result<()> {
+        self.summaries
+            .lock()
+            .unwrap()
+            .insert(project.to_string(), summary.to_string());
+        Ok(())
+    }
+}
+
+/// Persistent memory across sessions of a project.
+///
+/// At the end of a session, [`MemoryManager::remember`] asks Claude for a
+/// compact summary of what it learned, using a cheap follow-up query on the
+/// same session, and stores it. [`MemoryManager::prepare`] injects the stored
+/// summary into the next session's system prompt.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::memory::{FileMemoryStore, MemoryManager};
+/// use claude_code_sdk::{query_with_handle, ClaudeCodeOptions};
+/// use std::sync::Arc;
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let memory = MemoryManager::new("my-service", Arc::new(FileMemoryStore::new(".memory")))
+///     .with_summary_model("claude-3-5-haiku-latest");
+///
+/// let options = memory.prepare(ClaudeCodeOptions::new()).await?;
+/// let mut handle = query_with_handle("Fix the flaky test", Some(options)).await?;
+/// while let Some(message) = handle.next().await {
+///     message?;
+/// }
+///
+/// if let Some(session_id) = handle.session_id() {
+///     memory.remember(session_id, handle.options()).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MemoryManager {
+    project: String,
+    store: Arc<dyn MemoryStore>,
+    summary_model: Option<String>,
+}
+
+impl MemoryManager {
+    pub fn new<S: Into<String>>(project: S, store: Arc<dyn MemoryStore>) -> Self {
+        Self {
+            project: project.into(),
+            store,
+            summary_model: None,
+        }
+    }
+
+    /// Use a cheaper model for the summary query.
+    pub fn with_summary_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.summary_model = Some(model.into());
+        self
+    }
+
+    pub async fn load(&self) -> Result<Option<String>> {
+        self.store.load(&self.project).await
+    }
+
+    /// Add the stored memory, if any, to the system prompt of `options`.
+    pub async fn prepare(&self, mut options: ClaudeCodeOptions) -> Result<ClaudeCodeOptions> {
+        if let Some(summary) = self.load().await? {
+            options.push_append_system_prompt(format!(
+                "Memory from previous sessions on this project:\n{}",
+                summary.trim()
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Summarize the session and store the summary, returning it.
+    ///
+    /// `options` should be the options the session ran with, so the summary
+    /// query resumes it in the same workspace.
+    pub async fn remember(&self, session_id: &str, options: &ClaudeCodeOptions) -> Result<String> {
+        let mut options = ClaudeCodeOptions {
+            cwd: options.cwd.clone(),
+            env: options.env.clone(),
+            provider: options.provider.clone(),
+            proxy: options.proxy.clone(),
+            claude_api_key: options.claude_api_key.clone(),
+            ..ClaudeCodeOptions::default()
+        }
+        .with_resume(session_id)
+        .with_max_turns(1);
+        options.claude_model = self
+            .summary_model
+            .clone()
+            .or_else(|| options.claude_model.clone());
+
+        // Fold the previous memory into the new summary so it accumulates
+        // across sessions instead of only describing the last one.
+        let prompt = match self.load().await? {
+            Some(previous) => format!(
+                "{}\n\nMerge it with the memory from earlier sessions, dropping anything \
+                 that is no longer true:\n{}",
+                SUMMARY_PROMPT,
+                previous.trim()
+            ),
+            None => SUMMARY_PROMPT.to_string(),
+        };
+
+        let mut stream = crate::query(&prompt, Some(options)).await?;
+        let mut summary = String::new();
+        while let Some(message) = stream.next().await {
+            if let Message::Assistant(msg) = message? {
+                for block in msg.content {
+                    if let ContentBlock::Text(text) = block {
+                        summary.push_str(&text.text);
+                    }
+                }
+            }
+        }
+
+        let summary = summary.trim().to_string();
+        if summary.is_empty() {
+            return Err(ClaudeSDKError::cli_connection(
+                "Memory summary query returned no text",
+            ));
+        }
+        self.store.save(&self.project, &summary).await?;
+        Ok(summary)
+    }
+}
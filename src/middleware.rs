@@ -0,0 +1,110 @@
+//! Layers wrapped around every query.
+//!
+//! A [`Middleware`] sees each query before it starts, every message it
+//! produces and its result. Cross-cutting concerns such as refreshing
+//! credentials, logging, redaction or metrics can each be written once as a
+//! layer and registered with
+//! [`ClaudeCodeOptions::with_middleware`](crate::ClaudeCodeOptions::with_middleware)
+//! or [`ClaudeSDKClient::with_middleware`](crate::ClaudeSDKClient::with_middleware).
+//!
+//! Layers run in registration order: the first registered sees the query
+//! first and each message first.
+
+use crate::error::Result;
+use crate::prompt::PromptInput;
+use crate::types::{ClaudeCodeOptions, Message, ResultMessage, Shared};
+use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use std::pin::Pin;
+
+/// A layer around queries. Every method has a default that does nothing.
+///
+/// ```rust
+/// use async_trait::async_trait;
+/// use claude_code_sdk::middleware::Middleware;
+/// use claude_code_sdk::prompt::PromptInput;
+/// use claude_code_sdk::{ClaudeCodeOptions, ResultMessage};
+/// use std::sync::Arc;
+///
+/// struct CostLog;
+///
+/// #[async_trait]
+/// impl Middleware for CostLog {
+///     async fn after_result(&self, result: &ResultMessage) {
+///         println!("run {} cost ${:.4}", result.id, result.cost_usd.unwrap_or_default());
+///     }
+/// }
+///
+/// let options = ClaudeCodeOptions::new().with_middleware(Arc::new(CostLog));
+/// ```
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the CLI is started, with the prompt and options of the
+    /// query. Changes are used for the query; an error aborts it.
+    async fn before_query(
+        &self,
+        _prompt: &mut PromptInput,
+        _options: &mut ClaudeCodeOptions,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with each message before it is delivered. Return the message,
+    /// possibly changed, or `None` to drop it.
+    fn on_message(&self, message: Message) -> Option<Message> {
+        Some(message)
+    }
+
+    /// Called with the result message once the run has finished.
+    async fn after_result(&self, _result: &ResultMessage) {}
+}
+
+pub type MiddlewareRef = Shared<dyn Middleware>;
+
+/// Run the `before_query` of each layer in `options`.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) async fn before_query(
+    mut prompt: PromptInput,
+    mut options: ClaudeCodeOptions,
+) -> Result<(PromptInput, ClaudeCodeOptions)> {
+    let layers = options.middleware.clone();
+    for layer in &layers {
+        layer.before_query(&mut prompt, &mut options).await?;
+    }
+    Ok((prompt, options))
+}
+
+/// Pass every message of the stream through `layers`.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) fn apply(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    layers: Vec<MiddlewareRef>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(
+        stream
+            .filter_map({
+                let layers = layers.clone();
+                move |item| {
+                    let item = match item {
+                        Ok(message) => layers
+                            .iter()
+                            .try_fold(message, |message, layer| layer.on_message(message))
+                            .map(Ok),
+                        Err(e) => Some(Err(e)),
+                    };
+                    futures::future::ready(item)
+                }
+            })
+            .then(move |item| {
+                let layers = layers.clone();
+                async move {
+                    if let Ok(Message::Result(result)) = &item {
+                        for layer in &layers {
+                            layer.after_result(result).await;
+                        }
+                    }
+                    item
+                }
+            }),
+    )
+}
@@ -0,0 +1,169 @@
+use crate::error::{ClaudeSDKError, Result};
+use crate::tool_policy::ToolCategory;
+use crate::types::ClaudeCodeOptions;
+use std::path::{Component, Path, PathBuf};
+
+/// Scopes a query to selected packages of a large monorepo.
+///
+/// The query runs from the repository root, file edits are only allowed in
+/// the target packages, and a system prompt tells the agent which parts of
+/// the tree it is responsible for.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{monorepo::MonorepoContext, ClaudeCodeOptions};
+///
+/// # fn main() -> claude_code_sdk::Result<()> {
+/// let options = MonorepoContext::new("/work/monorepo")
+///     .with_package("services/billing")
+///     .with_read_only("libs/proto")
+///     .apply(ClaudeCodeOptions::new())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonorepoContext {
+    root: PathBuf,
+    packages: Vec<PathBuf>,
+    read_only: Vec<PathBuf>,
+    external_dirs: Vec<PathBuf>,
+}
+
+impl MonorepoContext {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            packages: Vec::new(),
+            read_only: Vec::new(),
+            external_dirs: Vec::new(),
+        }
+    }
+
+    /// Add a package, relative to the root, that the agent may modify.
+    pub fn with_package<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.packages.push(path.into());
+        self
+    }
+
+    pub fn with_packages<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.packages.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a directory, relative to the root, the agent should consult but
+    /// not modify.
+    pub fn with_read_only<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.read_only.push(path.into());
+        self
+    }
+
+    /// Give the agent access to a directory outside the repository root.
+    pub fn with_external_dir<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.external_dirs.push(path.into());
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn packages(&self) -> &[PathBuf] {
+        &self.packages
+    }
+
+    /// Check that every package is a directory inside the root.
+    pub fn validate(&self) -> Result<()> {
+        if self.packages.is_empty() {
+            return Err(ClaudeSDKError::invalid_options(
+                "MonorepoContext requires at least one package",
+            ));
+        }
+        for path in self.packages.iter().chain(&self.read_only) {
+            let escapes_root = path
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            if escapes_root {
+                return Err(ClaudeSDKError::invalid_options(format!(
+                    "Package path must be relative to the monorepo root: {}",
+                    path.display()
+                )));
+            }
+            if !self.root.join(path).is_dir() {
+                return Err(ClaudeSDKError::invalid_options(format!(
+                    "Package directory does not exist: {}",
+                    self.root.join(path).display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Permission rules allowing edits inside the target packages.
+    pub fn allowed_path_patterns(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .flat_map(|package| {
+                let package = package.to_string_lossy().trim_end_matches('/').to_string();
+                ToolCategory::FileWrite
+                    .tools()
+                    .iter()
+                    .map(move |tool| format!("{}({}/**)", tool, package))
+            })
+            .collect()
+    }
+
+    /// The system prompt describing the agent's scope.
+    pub fn system_prompt(&self) -> String {
+        let list = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| format!("- {}", p.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let mut prompt = format!(
+            "You are working in a monorepo rooted at {}. Only create, modify or delete files \
+             inside these packages:\n{}",
+            self.root.display(),
+            list(&self.packages)
+        );
+        if !self.read_only.is_empty() {
+            prompt.push_str(&format!(
+                "\n\nYou may read these directories for context, but do not modify them:\n{}",
+                list(&self.read_only)
+            ));
+        }
+        prompt.push_str(
+            "\n\nIf a change outside these packages seems necessary, describe it instead of \
+             making it.",
+        );
+        prompt
+    }
+
+    /// Configure `options` to operate only on the target packages.
+    pub fn apply(&self, mut options: ClaudeCodeOptions) -> Result<ClaudeCodeOptions> {
+        self.validate()?;
+
+        options.cwd = Some(self.root.clone());
+
+        if !self.external_dirs.is_empty() {
+            options
+                .add_dirs
+                .get_or_insert_with(Vec::new)
+                .extend(self.external_dirs.iter().cloned());
+        }
+
+        options
+            .allowed_tools
+            .get_or_insert_with(Vec::new)
+            .extend(self.allowed_path_patterns());
+
+        options.push_append_system_prompt(self.system_prompt());
+
+        Ok(options)
+    }
+}
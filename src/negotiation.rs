@@ -0,0 +1,79 @@
+//! Announcing the SDK to the CLI and agreeing on a protocol version.
+//!
+//! Every CLI process is started with the SDK's name, version and protocol
+//! version in its environment. A CLI that takes part in the negotiation
+//! answers with a system message carrying its own protocol version and the
+//! oldest SDK protocol it still accepts:
+//!
+//! ```text
+//! {"type":"system","content":"protocol","protocol_version":2,"min_sdk_protocol_version":1}
+//! ```
+//!
+//! The query then speaks the lower of the two protocol versions, see
+//! [`QueryHandle::negotiated_protocol`](crate::QueryHandle::negotiated_protocol).
+//! A CLI that needs a newer SDK fails the query with
+//! [`ClaudeSDKError::IncompatibleProtocol`].
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::SystemMessage;
+use serde::{Deserialize, Serialize};
+
+pub const SDK_NAME: &str = "claude-code-sdk-rust";
+pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The newest protocol version this SDK speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub const SDK_NAME_ENV: &str = "CLAUDE_CODE_SDK_NAME";
+pub const SDK_VERSION_ENV: &str = "CLAUDE_CODE_SDK_VERSION";
+pub const SDK_PROTOCOL_ENV: &str = "CLAUDE_CODE_SDK_PROTOCOL_VERSION";
+
+/// The protocol version a query runs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedProtocol {
+    pub version: u32,
+    /// The protocol version the CLI reported, `None` until it answers or if
+    /// it does not take part in the negotiation.
+    pub cli_version: Option<u32>,
+}
+
+impl Default for NegotiatedProtocol {
+    /// Protocol 1, which every CLI speaks, until the CLI answers.
+    fn default() -> Self {
+        Self {
+            version: 1,
+            cli_version: None,
+        }
+    }
+}
+
+impl NegotiatedProtocol {
+    /// The protocol agreed on by the CLI's answer `message`, or `None` if
+    /// the message is not an answer.
+    ///
+    /// Fails if the CLI only accepts SDK protocols newer than
+    /// [`PROTOCOL_VERSION`].
+    pub fn from_ack(message: &SystemMessage) -> Option<Result<Self>> {
+        let cli_version = message.protocol_version?;
+        let required = message.min_sdk_protocol_version.unwrap_or(1);
+        if required > PROTOCOL_VERSION {
+            return Some(Err(ClaudeSDKError::incompatible_protocol(
+                required,
+                PROTOCOL_VERSION,
+            )));
+        }
+        Some(Ok(Self {
+            version: cli_version.min(PROTOCOL_VERSION),
+            cli_version: Some(cli_version),
+        }))
+    }
+}
+
+/// The environment variables announcing the SDK to the CLI.
+pub fn env_vars() -> [(&'static str, String); 3] {
+    [
+        (SDK_NAME_ENV, SDK_NAME.to_string()),
+        (SDK_VERSION_ENV, SDK_VERSION.to_string()),
+        (SDK_PROTOCOL_ENV, PROTOCOL_VERSION.to_string()),
+    ]
+}
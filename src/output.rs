@@ -0,0 +1,277 @@
+//! Decoding the CLI's output in each of its output formats.
+
+use crate::diagnostics::{self, DecodeDiagnostics};
+use crate::error::Result;
+use crate::progress::ProgressCallback;
+use crate::protocol;
+use crate::refusal::RefusalCallback;
+use crate::types::{AssistantMessage, ClaudeCodeOptions, Message, ResultMessage, TextBlock};
+use crate::update::{self, UpdateCallback, UpdateEvent};
+use serde::{Deserialize, Serialize};
+
+/// The output format requested with `--output-format`.
+///
+/// Without one the CLI is started with `--format json` and its NDJSON
+/// output decoded, falling back to plain text if the CLI rejects the flag.
+/// Pin a format for CLIs that only support one of the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// One JSON message per line, as they happen.
+    #[default]
+    StreamJson,
+    /// A single JSON document once the run is over.
+    Json,
+    /// The final response as plain text; no tool use, usage or cost details.
+    Text,
+}
+
+impl OutputFormat {
+    /// The value of the `--output-format` flag.
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            Self::StreamJson => "stream-json",
+            Self::Json => "json",
+            Self::Text => "text",
+        }
+    }
+
+    /// A parser for this format, using the callbacks and run id of `options`.
+    pub fn parser(&self, options: &ClaudeCodeOptions) -> Box<dyn OutputParser> {
+        let id = options.run_id.map(|id| id.to_string()).unwrap_or_default();
+        match self {
+            Self::StreamJson => Box::new(StreamJsonParser::new(options)),
+            Self::Json => Box::new(JsonParser::new(options, id)),
+            Self::Text => Box::new(TextParser::new(id)),
+        }
+    }
+}
+
+/// Turns the lines the CLI writes to stdout into messages.
+///
+/// Lines are fed in order, with the newline (and any `\r`) removed;
+/// [`finish`](Self::finish) is called once stdout is closed. Parsers that
+/// need the whole output return nothing until then.
+pub trait OutputParser: Send {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<Message>>;
+
+    fn finish(&mut self) -> Result<Vec<Message>> {
+        Ok(Vec::new())
+    }
+}
+
+/// The parser for the format selected in `options`.
+pub fn parser(options: &ClaudeCodeOptions) -> Box<dyn OutputParser> {
+    options.output_format.unwrap_or_default().parser(options)
+}
+
+/// Decodes NDJSON output one message per line, see [`protocol::decode_line`].
+///
+/// Update notifications are handed to the options' update callback, see
+/// [`crate::update`]. Lines that fail to decode are captured as set in the
+/// options' [`DecodeDiagnostics`].
+pub struct StreamJsonParser {
+    progress_callback: Option<ProgressCallback>,
+    refusal_callback: Option<RefusalCallback>,
+    update_callback: Option<UpdateCallback>,
+    diagnostics: Option<DecodeDiagnostics>,
+}
+
+impl StreamJsonParser {
+    pub fn new(options: &ClaudeCodeOptions) -> Self {
+        Self {
+            progress_callback: options.progress_callback.clone(),
+            refusal_callback: options.refusal_callback.clone(),
+            update_callback: options.update_callback.clone(),
+            diagnostics: options.decode_diagnostics.clone(),
+        }
+    }
+
+    /// Whether `line` is an update notification, reporting it if so.
+    fn update_notice(&self, line: &str) -> bool {
+        match UpdateEvent::from_line(line) {
+            Some(event) => {
+                update::notify(&event, self.update_callback.as_ref());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl OutputParser for StreamJsonParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<Message>> {
+        if self.update_notice(line) {
+            return Ok(Vec::new());
+        }
+        let message = protocol::decode_line(
+            line,
+            self.progress_callback.as_ref(),
+            self.refusal_callback.as_ref(),
+        )
+        .map_err(|e| match &self.diagnostics {
+            Some(diagnostics) => diagnostics.capture(line, e),
+            None => e,
+        })?;
+        Ok(message.into_iter().collect())
+    }
+}
+
+/// Decodes the single JSON document written by `--output-format json`.
+///
+/// The document is either an array of messages or a summary of the run
+/// whose `result` is the final response. A summary is turned into an
+/// assistant message with that response followed by a result message.
+pub struct JsonParser {
+    stream: StreamJsonParser,
+    id: String,
+    buffer: String,
+    /// Where the buffered JSON stands, to tell its lines from notices.
+    scan: JsonScan,
+}
+
+/// How far into its JSON text a document is, counting brackets outside
+/// strings.
+#[derive(Debug, Default)]
+struct JsonScan {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonScan {
+    fn feed(&mut self, line: &str) {
+        for c in line.chars() {
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the next line starts outside any JSON value.
+    fn between_values(&self) -> bool {
+        self.depth == 0 && !self.in_string
+    }
+}
+
+impl JsonParser {
+    /// `id` becomes the id of the result message built from a summary.
+    pub fn new<S: Into<String>>(options: &ClaudeCodeOptions, id: S) -> Self {
+        Self {
+            stream: StreamJsonParser::new(options),
+            id: id.into(),
+            buffer: String::new(),
+            scan: JsonScan::default(),
+        }
+    }
+
+    fn summary(&self, value: &serde_json::Value) -> Result<Vec<Message>> {
+        let mut result = ResultMessage::deserialize(value)
+            .map_err(|e| diagnostics::message_decode_error(&value.to_string(), value, &e))?;
+        if result.id.is_empty() {
+            result.id = self.id.clone();
+        }
+        // The response becomes an assistant message of its own
+        let text = result.content.take().unwrap_or_default();
+
+        let mut messages = Vec::new();
+        if !text.is_empty() {
+            messages.push(AssistantMessage::new(vec![TextBlock::new(text).into()]).into());
+        }
+        messages.push(result.into());
+        Ok(messages)
+    }
+}
+
+impl OutputParser for JsonParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<Message>> {
+        // Inside the document a line is JSON, whatever it says
+        if self.scan.between_values() && self.stream.update_notice(line) {
+            return Ok(Vec::new());
+        }
+        self.scan.feed(line);
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        Ok(Vec::new())
+    }
+
+    fn finish(&mut self) -> Result<Vec<Message>> {
+        let json = std::mem::take(&mut self.buffer);
+        self.scan = JsonScan::default();
+        if json.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let value: serde_json::Value = serde_json::from_str(&json).map_err(|e| {
+            let error = diagnostics::decode_error(&json, &e);
+            match &self.stream.diagnostics {
+                Some(diagnostics) => diagnostics.capture(&json, error),
+                None => error,
+            }
+        })?;
+        match &value {
+            serde_json::Value::Array(items) => {
+                let mut messages = Vec::new();
+                for item in items {
+                    messages.extend(self.stream.parse_line(&item.to_string())?);
+                }
+                Ok(messages)
+            }
+            _ if value["type"] == "result" && value["result"].is_string() => self.summary(&value),
+            _ => self.stream.parse_line(&value.to_string()),
+        }
+    }
+}
+
+/// Collects the plain-text output of `--output-format text`.
+///
+/// The text becomes a single assistant message followed by a result message
+/// carrying no usage details. Nothing is produced if the CLI wrote nothing.
+pub struct TextParser {
+    id: String,
+    lines: Vec<String>,
+}
+
+impl TextParser {
+    /// `id` becomes the id of the result message.
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self {
+            id: id.into(),
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl OutputParser for TextParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<Message>> {
+        self.lines.push(line.to_string());
+        Ok(Vec::new())
+    }
+
+    fn finish(&mut self) -> Result<Vec<Message>> {
+        if self.lines.is_empty() {
+            return Ok(Vec::new());
+        }
+        let text = std::mem::take(&mut self.lines)
+            .join("\n")
+            .trim_end()
+            .to_string();
+        let mut messages = Vec::new();
+        if !text.is_empty() {
+            messages.push(AssistantMessage::new(vec![TextBlock::new(text).into()]).into());
+        }
+        messages.push(ResultMessage::new(self.id.clone()).into());
+        Ok(messages)
+    }
+}
@@ -0,0 +1,219 @@
+//! A budget of output tokens per turn, enforced by the SDK.
+//!
+//! [`with_max_output_tokens_per_turn`](crate::ClaudeCodeOptions::with_max_output_tokens_per_turn)
+//! caps how much Claude may write between a prompt and its result,
+//! including tool calls and the output of subagents. Once a turn's output
+//! goes over the budget, the CLI is stopped and the stream ends with
+//! [`OutputBudgetExceeded`](crate::ClaudeSDKError::OutputBudgetExceeded),
+//! protecting cost-sensitive automations from runaway generations.
+//!
+//! Output is counted from the usage the CLI reports with each assistant
+//! message, or estimated from the message's content where it reports
+//! none. The CLI sends a message with several content blocks as one
+//! assistant message per block, each repeating the usage of the whole
+//! message, so messages with the same id are counted once. With partial
+//! messages enabled, the text, thinking and tool input deltas of a message
+//! are estimated as they stream and the usage of its `message_delta`
+//! replaces the estimate, so a runaway generation is stopped while it is
+//! still being written. Otherwise the budget is checked after each
+//! message, and a turn can exceed it by at most the message that crossed
+//! it.
+
+use crate::context_files::CHARS_PER_TOKEN;
+#[cfg(feature = "subprocess")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::transport::DisposeGuard;
+#[cfg(feature = "subprocess")]
+use crate::types::Message;
+#[cfg(feature = "subprocess")]
+use crate::types::StreamEvent;
+use crate::types::{AssistantMessage, ContentBlock};
+#[cfg(feature = "subprocess")]
+use futures::stream::{self, Stream, StreamExt};
+#[cfg(feature = "subprocess")]
+use std::collections::HashMap;
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+
+/// The output tokens of `message`: as reported in its usage, or estimated
+/// from its text, thinking and tool calls.
+pub fn output_tokens(message: &AssistantMessage) -> i32 {
+    if let Some(tokens) = message.usage.as_ref().and_then(|usage| usage.output_tokens) {
+        return tokens;
+    }
+    let chars: usize = message
+        .content
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text(text) => text.text.chars().count(),
+            ContentBlock::ToolUse(tool_use) => {
+                tool_use.name.len() + tool_use.input.to_string().chars().count()
+            }
+            ContentBlock::Thinking(thinking) => thinking.thinking.chars().count(),
+            ContentBlock::ToolResult(_)
+            | ContentBlock::Image(_)
+            | ContentBlock::RedactedThinking(_) => 0,
+        })
+        .sum();
+    estimate(chars)
+}
+
+/// The tokens of `chars` characters of output.
+fn estimate(chars: usize) -> i32 {
+    i32::try_from((chars + CHARS_PER_TOKEN - 1) / CHARS_PER_TOKEN).unwrap_or(i32::MAX)
+}
+
+/// The output of one API message of a turn.
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Default)]
+struct MessageOutput {
+    /// The largest output the CLI reported for the message.
+    reported: Option<i32>,
+    /// Estimated from the message's deltas, or its content when it did not
+    /// stream.
+    estimated: i32,
+    streamed: bool,
+}
+
+#[cfg(feature = "subprocess")]
+impl MessageOutput {
+    fn tokens(&self) -> i32 {
+        self.reported.unwrap_or(self.estimated)
+    }
+}
+
+/// The output of a turn so far.
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Default)]
+struct TurnOutput {
+    /// Messages by id; those without one are counted as they come.
+    messages: HashMap<String, MessageOutput>,
+    /// The id of the message streaming for the main agent and each
+    /// subagent.
+    streaming: HashMap<Option<String>, String>,
+    spent: i32,
+}
+
+#[cfg(feature = "subprocess")]
+impl TurnOutput {
+    /// Count `message`, returning the output of the turn so far.
+    fn observe(&mut self, message: &Message) -> i32 {
+        match message {
+            Message::StreamEvent(event) => self.observe_event(event),
+            Message::Assistant(message) => match &message.id {
+                Some(id) => {
+                    let tokens = message.usage.as_ref().and_then(|usage| usage.output_tokens);
+                    self.update(id, |output| match tokens {
+                        Some(tokens) => {
+                            output.reported =
+                                Some(output.reported.map_or(tokens, |r| r.max(tokens)))
+                        }
+                        // Its deltas were counted already
+                        None if output.streamed => {}
+                        None => {
+                            output.estimated =
+                                output.estimated.saturating_add(output_tokens(message))
+                        }
+                    })
+                }
+                None => self.spent = self.spent.saturating_add(output_tokens(message)),
+            },
+            _ => {}
+        }
+        self.spent
+    }
+
+    fn observe_event(&mut self, event: &StreamEvent) {
+        let parent = event.parent_tool_use_id.clone();
+        match event.event_type() {
+            Some("message_start") => {
+                if let Some(id) = event
+                    .event
+                    .pointer("/message/id")
+                    .and_then(|id| id.as_str())
+                {
+                    self.streaming.insert(parent, id.to_string());
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = event
+                    .text_delta()
+                    .or_else(|| event.thinking_delta())
+                    .or_else(|| event.event.pointer("/delta/partial_json")?.as_str());
+                if let (Some(delta), Some(id)) = (delta, self.streaming.get(&parent).cloned()) {
+                    let tokens = estimate(delta.chars().count());
+                    self.update(&id, |output| {
+                        output.streamed = true;
+                        output.estimated = output.estimated.saturating_add(tokens);
+                    });
+                }
+            }
+            Some("message_delta") => {
+                let tokens = event
+                    .event
+                    .pointer("/usage/output_tokens")
+                    .and_then(|tokens| tokens.as_i64())
+                    .map(|tokens| i32::try_from(tokens).unwrap_or(i32::MAX));
+                if let (Some(tokens), Some(id)) = (tokens, self.streaming.get(&parent).cloned()) {
+                    self.update(&id, |output| {
+                        output.reported = Some(output.reported.map_or(tokens, |r| r.max(tokens)))
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Change the output of message `id`, keeping the turn's total.
+    fn update(&mut self, id: &str, change: impl FnOnce(&mut MessageOutput)) {
+        let output = self.messages.entry(id.to_string()).or_default();
+        let before = output.tokens();
+        change(output);
+        self.spent = self
+            .spent
+            .saturating_sub(before)
+            .saturating_add(output.tokens());
+    }
+}
+
+/// Stop the stream, and the process behind `guard`, once the output of a
+/// turn exceeds `budget` tokens.
+#[cfg(feature = "subprocess")]
+pub(crate) fn enforce(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    budget: i32,
+    guard: Option<DisposeGuard>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    Box::pin(stream::unfold(
+        Some((stream, TurnOutput::default(), guard)),
+        move |state| async move {
+            let (mut stream, mut turn, guard) = state?;
+            let item = stream.next().await?;
+            let spent = match &item {
+                // The next turn starts with a fresh budget
+                Ok(Message::Result(_)) => {
+                    turn = TurnOutput::default();
+                    0
+                }
+                Ok(message) => turn.observe(message),
+                Err(_) => turn.spent,
+            };
+            if spent <= budget {
+                return Some((item, Some((stream, turn, guard))));
+            }
+            tracing::warn!(
+                budget,
+                output_tokens = spent,
+                "interrupting turn over its output token budget"
+            );
+            if let Some(guard) = &guard {
+                let _ = guard.dispose().await;
+            }
+            Some((
+                Err(ClaudeSDKError::output_budget_exceeded(budget, spent)),
+                None,
+            ))
+        },
+    ))
+}
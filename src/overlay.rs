@@ -0,0 +1,164 @@
+//! Tenant-specific configuration layered over shared base options.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::tool_policy::ToolPolicy;
+use crate::types::{ClaudeCodeOptions, PermissionMode, Shared};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Overrides for one tenant, applied over the base options when a query
+/// for that tenant starts.
+///
+/// Set fields replace those of the base options, except the tool policy,
+/// which only ever disables more tools, and the environment, which is
+/// merged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigOverlay {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<i32>,
+    /// Passed on as [`claude_max_tokens`](ClaudeCodeOptions::claude_max_tokens).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_policy: Option<ToolPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<PermissionMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub append_system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl ConfigOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_max_turns(mut self, turns: i32) -> Self {
+        self.max_turns = Some(turns);
+        self
+    }
+
+    pub fn with_max_output_tokens(mut self, tokens: i32) -> Self {
+        self.max_output_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tool_policy = Some(policy);
+        self
+    }
+
+    pub fn with_permission_mode(mut self, mode: PermissionMode) -> Self {
+        self.permission_mode = Some(mode);
+        self
+    }
+
+    pub fn with_append_system_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.append_system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// `options` with this overlay applied.
+    pub fn apply(&self, mut options: ClaudeCodeOptions) -> ClaudeCodeOptions {
+        if let Some(model) = &self.model {
+            options.claude_model = Some(model.clone());
+        }
+        if let Some(turns) = self.max_turns {
+            options.max_turns = Some(turns);
+        }
+        if let Some(tokens) = self.max_output_tokens {
+            options.claude_max_tokens = Some(tokens);
+        }
+        if let Some(mode) = &self.permission_mode {
+            options.permission_mode = Some(mode.clone());
+        }
+        if let Some(prompt) = &self.append_system_prompt {
+            options.append_system_prompt = Some(match options.append_system_prompt.take() {
+                Some(base) => format!("{}\n\n{}", base, prompt),
+                None => prompt.clone(),
+            });
+        }
+        if !self.env.is_empty() {
+            options
+                .env
+                .get_or_insert_with(HashMap::new)
+                .extend(self.env.clone());
+        }
+        match &self.tool_policy {
+            Some(policy) => policy.apply(options),
+            None => options,
+        }
+    }
+}
+
+/// Looks up the overlay of a tenant when one of its queries starts, e.g.
+/// from a database of per-organization plans.
+#[async_trait]
+pub trait OverlayProvider: Send + Sync {
+    /// The overlay for `tenant`, or `None` to run with the base options.
+    async fn overlay(&self, tenant: &str) -> Result<Option<ConfigOverlay>>;
+}
+
+pub type OverlayProviderRef = Shared<dyn OverlayProvider>;
+
+/// Overlays kept in memory, keyed by tenant.
+#[derive(Debug, Default)]
+pub struct InMemoryOverlayProvider {
+    overlays: Mutex<HashMap<String, ConfigOverlay>>,
+}
+
+impl InMemoryOverlayProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_overlay<S: Into<String>>(self, tenant: S, overlay: ConfigOverlay) -> Self {
+        self.set(tenant, overlay);
+        self
+    }
+
+    /// Add or replace the overlay of `tenant`, effective for its next query.
+    pub fn set<S: Into<String>>(&self, tenant: S, overlay: ConfigOverlay) {
+        self.overlays.lock().unwrap().insert(tenant.into(), overlay);
+    }
+}
+
+#[async_trait]
+impl OverlayProvider for InMemoryOverlayProvider {
+    async fn overlay(&self, tenant: &str) -> Result<Option<ConfigOverlay>> {
+        Ok(self.overlays.lock().unwrap().get(tenant).cloned())
+    }
+}
+
+/// `options` with the overlay of their tenant applied, if they name one.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+pub(crate) async fn resolve(options: ClaudeCodeOptions) -> Result<ClaudeCodeOptions> {
+    let Some(tenant) = options.tenant.clone() else {
+        return Ok(options);
+    };
+    let provider = options.overlay_provider.clone().ok_or_else(|| {
+        ClaudeSDKError::invalid_options("a tenant is set but no overlay provider")
+    })?;
+    match provider.overlay(&tenant).await? {
+        Some(overlay) => {
+            tracing::debug!(tenant = %tenant, "applying tenant overlay");
+            Ok(overlay.apply(options))
+        }
+        None => Ok(options),
+    }
+}
@@ -0,0 +1,506 @@
+use crate::error::{ClaudeSDKError, Result};
+use crate::handle::QueryHandle;
+use crate::quota::QuotaState;
+use crate::types::{ClaudeCodeOptions, Message};
+use futures::stream::Stream;
+use std::collections::{HashMap, VecDeque};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::oneshot;
+
+/// How many affinity keys a pool remembers by default.
+pub const DEFAULT_AFFINITY_CAPACITY: usize = 1024;
+
+/// Scheduling priority of a pooled query. Higher priorities are always
+/// started first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Normal,
+    Background,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Background];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct Waiter {
+    id: u64,
+    tx: oneshot::Sender<PoolPermit>,
+}
+
+struct AffinityEntry {
+    session_id: Option<String>,
+    last_used: u64,
+    in_use: usize,
+}
+
+struct PoolState {
+    max_concurrency: usize,
+    shares: [usize; 3],
+    running: [usize; 3],
+    queues: [VecDeque<Waiter>; 3],
+    next_id: u64,
+    affinity_capacity: usize,
+    affinity: HashMap<String, AffinityEntry>,
+    clock: u64,
+    quota: Option<QuotaState>,
+}
+
+impl PoolState {
+    /// Mark `key` as used by a starting query, returning its session and
+    /// whether another query is using it.
+    fn claim_affinity(&mut self, key: &str) -> (Option<String>, bool) {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self
+            .affinity
+            .entry(key.to_string())
+            .or_insert(AffinityEntry {
+                session_id: None,
+                last_used: clock,
+                in_use: 0,
+            });
+        let busy = entry.in_use > 0;
+        entry.in_use += 1;
+        entry.last_used = clock;
+        let session_id = entry.session_id.clone();
+        self.evict_affinity();
+        (session_id, busy)
+    }
+
+    /// Drop the least recently used idle keys until within capacity.
+    fn evict_affinity(&mut self) {
+        while self.affinity.len() > self.affinity_capacity {
+            let oldest = self
+                .affinity
+                .iter()
+                .filter(|(_, entry)| entry.in_use == 0)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            let Some(key) = oldest else { break };
+            self.affinity.remove(&key);
+        }
+    }
+
+    /// Reserve slots for as many waiters as the limits allow, highest
+    /// priority first.
+    fn take_ready(&mut self) -> Vec<(Priority, oneshot::Sender<PoolPermit>)> {
+        let mut ready = Vec::new();
+        while self.running.iter().sum::<usize>() < self.max_concurrency {
+            let next = Priority::ALL.into_iter().find(|p| {
+                self.running[p.index()] < self.shares[p.index()]
+                    && !self.queues[p.index()].is_empty()
+            });
+            let Some(priority) = next else { break };
+            let waiter = self.queues[priority.index()].pop_front().unwrap();
+            self.running[priority.index()] += 1;
+            ready.push((priority, waiter.tx));
+        }
+        ready
+    }
+}
+
+/// Runs queries with bounded concurrency and priority queueing, so
+/// interactive queries are not starved by background batch jobs.
+///
+/// Each priority can be given a share: the maximum number of slots its
+/// queries may occupy at once. Queries that have not started yet can be
+/// cancelled. Queries submitted with an affinity key continue the session of
+/// the previous query with that key, see [`QueuedQuery::with_affinity_key`].
+/// A pool with a [quota](Self::with_quota) also paces the start of queries
+/// to the organization's rate limits.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::pool::{Priority, SessionPool};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// // Four concurrent queries, at most one of them a background job
+/// let pool = SessionPool::new(4).with_share(Priority::Background, 1);
+///
+/// let batch = pool.submit("Summarize the changelog", None, Priority::Background);
+/// let mut interactive = pool.submit("What does main.rs do?", None, Priority::High).start().await?;
+///
+/// while let Some(message) = interactive.next().await {
+///     println!("{:?}", message?);
+/// }
+/// batch.cancel();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SessionPool {
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl SessionPool {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                max_concurrency,
+                shares: [max_concurrency; 3],
+                running: [0; 3],
+                queues: Default::default(),
+                next_id: 0,
+                affinity_capacity: DEFAULT_AFFINITY_CAPACITY,
+                affinity: HashMap::new(),
+                clock: 0,
+                quota: None,
+            })),
+        }
+    }
+
+    /// Limit how many slots queries of `priority` may occupy at once.
+    pub fn with_share(self, priority: Priority, max_running: usize) -> Self {
+        self.state.lock().unwrap().shares[priority.index()] = max_running;
+        self
+    }
+
+    /// Start queries only as fast as `quota` allows, and keep it up to date
+    /// with the rate limits they run into. Queries wait for the quota once
+    /// they have a slot, so higher priorities still start first.
+    pub fn with_quota(self, quota: QuotaState) -> Self {
+        self.state.lock().unwrap().quota = Some(quota);
+        self
+    }
+
+    /// The quota the pool paces queries to.
+    pub fn quota(&self) -> Option<QuotaState> {
+        self.state.lock().unwrap().quota.clone()
+    }
+
+    /// Remember the sessions of at most `capacity` affinity keys, evicting
+    /// the least recently used ones, see [`QueuedQuery::with_affinity_key`].
+    pub fn with_affinity_capacity(self, capacity: usize) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.affinity_capacity = capacity;
+            state.evict_affinity();
+        }
+        self
+    }
+
+    /// Queue a query. It starts once [`QueuedQuery::start`] obtains a slot.
+    pub fn submit(
+        &self,
+        prompt: &str,
+        options: Option<ClaudeCodeOptions>,
+        priority: Priority,
+    ) -> QueuedQuery {
+        QueuedQuery {
+            slot: self.acquire(priority),
+            prompt: prompt.to_string(),
+            options,
+            affinity_key: None,
+        }
+    }
+
+    /// The session queries with `key` resume.
+    pub fn affinity_session(&self, key: &str) -> Option<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .affinity
+            .get(key)
+            .and_then(|entry| entry.session_id.clone())
+    }
+
+    /// Pin `key` to `session_id`, e.g. to restore affinities after a restart.
+    pub fn set_affinity_session<K: Into<String>, S: Into<String>>(&self, key: K, session_id: S) {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.affinity.entry(key.into()).or_insert(AffinityEntry {
+            session_id: None,
+            last_used: clock,
+            in_use: 0,
+        });
+        entry.session_id = Some(session_id.into());
+        entry.last_used = clock;
+        state.evict_affinity();
+    }
+
+    /// Forget the session of `key`, so its next query starts a new one.
+    pub fn forget_affinity(&self, key: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.affinity.get_mut(key) {
+            Some(entry) if entry.in_use > 0 => entry.session_id.take().is_some(),
+            Some(_) => state.affinity.remove(key).is_some(),
+            None => false,
+        }
+    }
+
+    /// The number of affinity keys remembered.
+    pub fn affinity_keys(&self) -> usize {
+        self.state.lock().unwrap().affinity.len()
+    }
+
+    /// Request a slot for work of the given priority.
+    pub fn acquire(&self, priority: Priority) -> SlotRequest {
+        let (tx, rx) = oneshot::channel();
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.queues[priority.index()].push_back(Waiter { id, tx });
+            id
+        };
+        self.dispatch();
+
+        SlotRequest {
+            id,
+            priority,
+            rx,
+            pool: self.clone(),
+        }
+    }
+
+    pub fn running(&self, priority: Priority) -> usize {
+        self.state.lock().unwrap().running[priority.index()]
+    }
+
+    pub fn queued(&self, priority: Priority) -> usize {
+        self.state.lock().unwrap().queues[priority.index()].len()
+    }
+
+    fn dispatch(&self) {
+        let ready = self.state.lock().unwrap().take_ready();
+        for (priority, tx) in ready {
+            // If the requester is gone the returned permit is dropped, which
+            // frees the slot and dispatches again.
+            let _ = tx.send(PoolPermit {
+                pool: self.clone(),
+                priority,
+            });
+        }
+    }
+
+    fn release(&self, priority: Priority) {
+        self.state.lock().unwrap().running[priority.index()] -= 1;
+        self.dispatch();
+    }
+
+    fn record_affinity(&self, key: &str, session_id: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        if let Some(entry) = state.affinity.get_mut(key) {
+            entry.session_id = session_id;
+            entry.last_used = clock;
+        }
+    }
+
+    fn release_affinity(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.affinity.get_mut(key) {
+            entry.in_use -= 1;
+        }
+        state.evict_affinity();
+    }
+}
+
+/// A slot in a [`SessionPool`], held until dropped.
+pub struct PoolPermit {
+    pool: SessionPool,
+    priority: Priority,
+}
+
+impl PoolPermit {
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+impl Drop for PoolPermit {
+    fn drop(&mut self) {
+        self.pool.release(self.priority);
+    }
+}
+
+/// A pending request for a pool slot.
+pub struct SlotRequest {
+    id: u64,
+    priority: Priority,
+    rx: oneshot::Receiver<PoolPermit>,
+    pool: SessionPool,
+}
+
+impl SlotRequest {
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Remove the request from the queue.
+    ///
+    /// Returns `false` if a slot was already granted.
+    pub fn cancel(&self) -> bool {
+        let mut state = self.pool.state.lock().unwrap();
+        let queue = &mut state.queues[self.priority.index()];
+        match queue.iter().position(|w| w.id == self.id) {
+            Some(position) => {
+                queue.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wait until a slot is granted.
+    pub async fn wait(self) -> Result<PoolPermit> {
+        self.rx.await.map_err(|_| ClaudeSDKError::Cancelled)
+    }
+}
+
+/// A query waiting in a [`SessionPool`].
+pub struct QueuedQuery {
+    slot: SlotRequest,
+    prompt: String,
+    options: Option<ClaudeCodeOptions>,
+    affinity_key: Option<String>,
+}
+
+impl QueuedQuery {
+    pub fn priority(&self) -> Priority {
+        self.slot.priority()
+    }
+
+    /// Route the query to the session of earlier queries with the same key.
+    ///
+    /// The query resumes the session the last query with `key` ended in, so
+    /// a chat backend can keep each user's context and prompt cache warm.
+    /// If another query with the key is still running, the session is forked
+    /// instead so the two do not interleave, and the key follows whichever
+    /// finishes last. A run that fails before reporting its result drops the
+    /// session, so the next query with the key starts afresh.
+    pub fn with_affinity_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.affinity_key = Some(key.into());
+        self
+    }
+
+    /// Cancel the query if it has not started yet.
+    pub fn cancel(&self) -> bool {
+        self.slot.cancel()
+    }
+
+    /// Wait for a slot, and the pool's quota if it has one, and start the
+    /// query.
+    ///
+    /// Fails with [`ClaudeSDKError::Cancelled`] if the query was cancelled
+    /// while queued.
+    pub async fn start(self) -> Result<PooledQuery> {
+        let permit = self.slot.wait().await?;
+        let pool = permit.pool.clone();
+        let quota = pool.quota();
+        if let Some(quota) = &quota {
+            quota.acquire().await;
+        }
+        let mut options = self.options;
+        let affinity = self.affinity_key.map(|key| {
+            let (session_id, busy) = pool.state.lock().unwrap().claim_affinity(&key);
+            let guard = AffinityGuard {
+                pool: pool.clone(),
+                key,
+                saw_result: false,
+            };
+            let explicit = options.as_ref().is_some_and(|o| o.resume.is_some());
+            if let (Some(session_id), false) = (session_id, explicit) {
+                let mut resumed = options.take().unwrap_or_default().with_resume(session_id);
+                if busy {
+                    resumed = resumed.with_fork_session();
+                }
+                options = Some(resumed);
+            }
+            guard
+        });
+        let handle = crate::query_with_handle(&self.prompt, options).await?;
+        Ok(PooledQuery {
+            handle,
+            affinity,
+            quota,
+            _permit: permit,
+        })
+    }
+}
+
+/// Keeps an affinity key marked as in use while its query runs.
+struct AffinityGuard {
+    pool: SessionPool,
+    key: String,
+    saw_result: bool,
+}
+
+impl AffinityGuard {
+    fn observe(&mut self, item: &Result<Message>) {
+        match item {
+            Ok(Message::Result(result)) => {
+                self.saw_result = true;
+                if result.session_id.is_some() {
+                    self.pool
+                        .record_affinity(&self.key, result.session_id.clone());
+                }
+            }
+            Err(_) if !self.saw_result => self.pool.record_affinity(&self.key, None),
+            _ => {}
+        }
+    }
+}
+
+impl Drop for AffinityGuard {
+    fn drop(&mut self) {
+        self.pool.release_affinity(&self.key);
+    }
+}
+
+/// A running pooled query. Its slot is released when it is dropped.
+pub struct PooledQuery {
+    handle: QueryHandle,
+    affinity: Option<AffinityGuard>,
+    quota: Option<QuotaState>,
+    _permit: PoolPermit,
+}
+
+impl PooledQuery {
+    /// The affinity key the query was started with.
+    pub fn affinity_key(&self) -> Option<&str> {
+        self.affinity.as_ref().map(|guard| guard.key.as_str())
+    }
+}
+
+impl Deref for PooledQuery {
+    type Target = QueryHandle;
+
+    fn deref(&self) -> &QueryHandle {
+        &self.handle
+    }
+}
+
+impl DerefMut for PooledQuery {
+    fn deref_mut(&mut self) -> &mut QueryHandle {
+        &mut self.handle
+    }
+}
+
+impl Stream for PooledQuery {
+    type Item = Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.handle).poll_next(cx);
+        if let Poll::Ready(Some(item)) = &poll {
+            if let Some(affinity) = &mut this.affinity {
+                affinity.observe(item);
+            }
+            if let Some(quota) = &this.quota {
+                quota.observe(item);
+            }
+        }
+        poll
+    }
+}
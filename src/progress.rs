@@ -0,0 +1,73 @@
+use crate::types::Shared;
+#[cfg(feature = "subprocess")]
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::sync::Arc;
+#[cfg(feature = "subprocess")]
+use tokio::sync::mpsc;
+#[cfg(feature = "subprocess")]
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// Live progress reported by the CLI while a tool is still running, such as
+/// streamed Bash output or task status updates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolProgressEvent {
+    pub tool_use_id: String,
+    pub tool_name: Option<String>,
+    /// Incremental output produced since the previous event.
+    pub output: Option<String>,
+    /// Human readable status text.
+    pub message: Option<String>,
+    /// Completion fraction between 0.0 and 1.0, when known.
+    pub progress: Option<f64>,
+}
+
+impl ToolProgressEvent {
+    /// Parse a progress notification from a raw CLI output line.
+    ///
+    /// Returns `None` if the value is not a progress notification.
+    pub fn parse(value: &serde_json::Value) -> Option<Self> {
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some("tool_progress") | Some("progress") => serde_json::from_value(value.clone()).ok(),
+            _ => None,
+        }
+    }
+}
+
+pub type ProgressCallback = Shared<dyn Fn(&ToolProgressEvent) + Send + Sync>;
+
+/// Create a progress callback paired with a stream of the events it receives.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{progress::progress_channel, query, ClaudeCodeOptions};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let (callback, mut events) = progress_channel();
+/// let options = ClaudeCodeOptions::new().with_progress_callback(callback);
+///
+/// tokio::spawn(async move {
+///     while let Some(event) = events.next().await {
+///         print!("{}", event.output.unwrap_or_default());
+///     }
+/// });
+///
+/// let mut stream = query("Run the test suite", Some(options)).await?;
+/// while let Some(message) = stream.next().await {
+///     message?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "subprocess")]
+pub fn progress_channel() -> (
+    ProgressCallback,
+    impl Stream<Item = ToolProgressEvent> + Send + Unpin,
+) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let callback: ProgressCallback = Shared(Arc::new(move |event: &ToolProgressEvent| {
+        let _ = tx.send(event.clone());
+    }));
+    (callback, UnboundedReceiverStream::new(rx))
+}
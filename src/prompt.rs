@@ -0,0 +1,253 @@
+//! Prompts made of text, images and files.
+
+use crate::error::{ClaudeSDKError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the data of an image or document block comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    Text { media_type: String, data: String },
+    Url { url: String },
+}
+
+/// The media type of the image file at `path`, judged by its extension, or
+/// `None` if it is not a PNG, JPEG, GIF or WebP file.
+pub(crate) fn image_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// A content block of a prompt, in the Messages API format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: MediaSource,
+    },
+    Document {
+        source: MediaSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+}
+
+impl InputBlock {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    /// An image from base64-encoded `data`.
+    pub fn image_base64<M: Into<String>, D: Into<String>>(media_type: M, data: D) -> Self {
+        Self::Image {
+            source: MediaSource::Base64 {
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+
+    /// Attach the file at `path`.
+    ///
+    /// PNG, JPEG, GIF and WebP files become image blocks and PDFs document
+    /// blocks, both base64 encoded. Any other file must be UTF-8 text and is
+    /// attached as a plain text document titled with its path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| {
+            ClaudeSDKError::invalid_options(format!(
+                "Cannot read attachment {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        if let Some(media_type) = image_media_type(path) {
+            return Ok(Self::image_base64(media_type, STANDARD.encode(&bytes)));
+        }
+        let title = Some(path.display().to_string());
+        if extension.as_deref() == Some("pdf") {
+            return Ok(Self::Document {
+                source: MediaSource::Base64 {
+                    media_type: "application/pdf".to_string(),
+                    data: STANDARD.encode(&bytes),
+                },
+                title,
+            });
+        }
+        let text = String::from_utf8(bytes).map_err(|_| {
+            ClaudeSDKError::invalid_options(format!(
+                "Attachment {} is neither an image, a PDF nor UTF-8 text",
+                path.display()
+            ))
+        })?;
+        Ok(Self::Document {
+            source: MediaSource::Text {
+                media_type: "text/plain".to_string(),
+                data: text,
+            },
+            title,
+        })
+    }
+}
+
+/// What is sent to Claude as the first user turn.
+///
+/// Plain text is passed to the CLI as an argument. Content blocks are sent
+/// as a user message on stdin with `--input-format stream-json`, which needs
+/// a CLI that supports it.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::prompt::PromptInput;
+/// use claude_code_sdk::query;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let prompt = PromptInput::text("Why does this page render blank?")
+///     .with_file("screenshots/home.png")?
+///     .with_file("src/home.tsx")?;
+/// let stream = query(prompt, None).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PromptInput {
+    Text(String),
+    Blocks(Vec<InputBlock>),
+}
+
+impl PromptInput {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// Attach each file in `paths`, see [`InputBlock::from_file`].
+    pub fn files<I, P>(paths: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let blocks = paths
+            .into_iter()
+            .map(InputBlock::from_file)
+            .collect::<Result<_>>()?;
+        Ok(Self::Blocks(blocks))
+    }
+
+    pub fn with_block(self, block: InputBlock) -> Self {
+        let mut blocks = self.into_blocks();
+        blocks.push(block);
+        Self::Blocks(blocks)
+    }
+
+    /// Attach the file at `path`, see [`InputBlock::from_file`].
+    pub fn with_file<P: Into<PathBuf>>(self, path: P) -> Result<Self> {
+        Ok(self.with_block(InputBlock::from_file(path.into())?))
+    }
+
+    /// The prompt as content blocks.
+    pub fn into_blocks(self) -> Vec<InputBlock> {
+        match self {
+            Self::Text(text) if text.is_empty() => Vec::new(),
+            Self::Text(text) => vec![InputBlock::Text { text }],
+            Self::Blocks(blocks) => blocks,
+        }
+    }
+
+    /// Whether the prompt has neither text nor attachments.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Blocks(blocks) => blocks.is_empty(),
+        }
+    }
+
+    /// The prompt if it is plain text.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Blocks(_) => None,
+        }
+    }
+
+    /// The text of the prompt, without attachments.
+    pub fn text_content(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    InputBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// The stream-json user message carrying the prompt.
+    pub fn to_stream_json(&self) -> Result<String> {
+        let message = serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": self.clone().into_blocks(),
+            },
+        });
+        Ok(serde_json::to_string(&message)?)
+    }
+}
+
+impl Default for PromptInput {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<&str> for PromptInput {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<String> for PromptInput {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&String> for PromptInput {
+    fn from(text: &String) -> Self {
+        Self::Text(text.clone())
+    }
+}
+
+impl From<Vec<InputBlock>> for PromptInput {
+    fn from(blocks: Vec<InputBlock>) -> Self {
+        Self::Blocks(blocks)
+    }
+}
+
+impl From<InputBlock> for PromptInput {
+    fn from(block: InputBlock) -> Self {
+        Self::Blocks(vec![block])
+    }
+}
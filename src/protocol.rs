@@ -0,0 +1,438 @@
+//! The runtime-independent parts of the CLI protocol: building the CLI
+//! command line and decoding its output.
+//!
+//! The Tokio based [`SubprocessCLITransport`](crate::transport::SubprocessCLITransport)
+//! is built on these. Other runtimes can spawn the command returned by
+//! [`cli_command`] with their own process API, e.g. `async_process::Command::from`,
+//! and decode its stdout with [`decode_lines`].
+
+use crate::api_error;
+use crate::diagnostics;
+use crate::error::{ClaudeSDKError, Result};
+use crate::external_tools;
+use crate::negotiation::{self, NegotiatedProtocol};
+use crate::output;
+use crate::progress::{ProgressCallback, ToolProgressEvent};
+use crate::prompt::PromptInput;
+use crate::refusal::{Refusal, RefusalCallback};
+use crate::run_id::RUN_ID_ENV;
+use crate::sdk_mcp::SdkMcpServer;
+#[cfg(feature = "analysis-only")]
+use crate::tool_policy::ToolPolicy;
+use crate::types::{ClaudeCodeOptions, Message, PermissionMode, SystemMessage};
+use crate::update;
+use futures::io::{AsyncBufRead, AsyncBufReadExt};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use which::which;
+
+/// Environment variable capping the tokens the CLI requests per response.
+pub const MAX_OUTPUT_TOKENS_ENV: &str = "CLAUDE_CODE_MAX_OUTPUT_TOKENS";
+
+pub fn find_cli_binary() -> Result<PathBuf> {
+    // Common installation paths for Claude Code CLI
+    let paths = [
+        "claude-code",
+        "/usr/local/bin/claude-code",
+        "/opt/homebrew/bin/claude-code",
+        // Add more paths as needed based on common installation locations
+    ];
+
+    for path in &paths {
+        if let Ok(binary_path) = which(path) {
+            return Ok(binary_path);
+        }
+    }
+
+    Err(ClaudeSDKError::CLINotFound)
+}
+
+/// The CLI the options run: their [`cli_path`](ClaudeCodeOptions::cli_path),
+/// or the installed one.
+pub fn cli_binary(options: &ClaudeCodeOptions) -> Result<PathBuf> {
+    match &options.cli_path {
+        Some(path) => Ok(path.clone()),
+        None => find_cli_binary(),
+    }
+}
+
+/// Build the CLI command for a query, with stdout and stderr piped.
+///
+/// With `json_output` the CLI is asked for NDJSON output (`--format json`),
+/// or for the [`output_format`](ClaudeCodeOptions::output_format) if one is
+/// set.
+pub fn cli_command(
+    options: &ClaudeCodeOptions,
+    prompt: &str,
+    json_output: bool,
+) -> Result<Command> {
+    build_command(options, Some(prompt), json_output)
+}
+
+/// Like [`cli_command`], for any [`PromptInput`].
+///
+/// Content blocks are not passed as an argument: the CLI is started with
+/// `--input-format stream-json` and stdin piped, and the caller writes
+/// [`PromptInput::to_stream_json`] to it. So is any prompt when the options
+/// have [hooks](ClaudeCodeOptions::on_hook),
+/// [SDK MCP servers](ClaudeCodeOptions::with_sdk_mcp_server) or
+/// [external tools](ClaudeCodeOptions::with_external_tools), which are
+/// answered on it.
+pub fn cli_command_for_input(
+    options: &ClaudeCodeOptions,
+    prompt: &PromptInput,
+    json_output: bool,
+) -> Result<Command> {
+    match prompt.as_text() {
+        Some(text) if !prompt_on_stdin(options, prompt) => {
+            build_command(options, Some(text), json_output)
+        }
+        _ => build_command(options, None, json_output),
+    }
+}
+
+/// Whether the CLI reads `prompt` as stream-json from stdin rather than
+/// from its arguments.
+pub(crate) fn prompt_on_stdin(options: &ClaudeCodeOptions, prompt: &PromptInput) -> bool {
+    prompt.as_text().is_none() || options.uses_control_protocol()
+}
+
+fn build_command(
+    options: &ClaudeCodeOptions,
+    prompt: Option<&str>,
+    json_output: bool,
+) -> Result<Command> {
+    options.validate()?;
+    let binary_path = cli_binary(options)?;
+    let mut cmd = Command::new(binary_path);
+
+    // Set working directory
+    if let Some(cwd) = &options.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    // Build CLI arguments based on options
+    if json_output {
+        match options.output_format {
+            Some(format) => cmd.arg("--output-format").arg(format.as_arg()),
+            None => cmd.arg("--format").arg("json"),
+        };
+    }
+
+    if let Some(system_prompt) = &options.system_prompt {
+        cmd.arg("--system").arg(system_prompt);
+    }
+
+    if let Some(append_system_prompt) = options.effective_append_system_prompt() {
+        cmd.arg("--append-system-prompt").arg(append_system_prompt);
+    }
+
+    if let Some(max_turns) = options.max_turns {
+        cmd.arg("--max-turns").arg(max_turns.to_string());
+    }
+
+    if let Some(tokens) = options.max_thinking_tokens {
+        cmd.arg("--max-thinking-tokens").arg(tokens.to_string());
+    }
+
+    if options.include_partial_messages.unwrap_or(false) {
+        cmd.arg("--include-partial-messages");
+    }
+
+    if let Some(permission_mode) = &options.permission_mode {
+        match permission_mode {
+            PermissionMode::AcceptEdits => {
+                cmd.arg("--accept-edits");
+            }
+            PermissionMode::BypassPermissions => {
+                cmd.arg("--bypass-permissions");
+            }
+            PermissionMode::Default => {
+                // No additional flags needed for default mode
+            }
+        }
+    }
+
+    if let Some(add_dirs) = &options.add_dirs {
+        for dir in add_dirs {
+            cmd.arg("--add-dir").arg(dir);
+        }
+    }
+
+    if let Some(allowed_tools) = &options.allowed_tools {
+        for tool in allowed_tools {
+            cmd.arg("--tool").arg(tool);
+        }
+    }
+
+    let disallowed_tools = disallowed_tools(options);
+    if !disallowed_tools.is_empty() {
+        cmd.arg("--disallowedTools").arg(disallowed_tools.join(","));
+    }
+
+    let mut servers: serde_json::Map<String, serde_json::Value> = options
+        .sdk_mcp_servers
+        .iter()
+        .map(|server| (server.name.clone(), server.config()))
+        .collect();
+    if options.external_tools.is_some() {
+        let server = SdkMcpServer::new(external_tools::SERVER_NAME);
+        servers.insert(server.name.clone(), server.config());
+    }
+    if !servers.is_empty() {
+        cmd.arg("--mcp-config")
+            .arg(serde_json::json!({ "mcpServers": servers }).to_string());
+    }
+
+    if options.disable_safety_suggestions.unwrap_or(false) {
+        cmd.arg("--disable-safety-suggestions");
+    }
+
+    if options.disable_telemetry.unwrap_or(false) {
+        cmd.arg("--disable-telemetry");
+    }
+
+    if options.disable_stream.unwrap_or(false) {
+        cmd.arg("--disable-stream");
+    }
+
+    if options.disable_vision.unwrap_or(false) {
+        cmd.arg("--disable-vision");
+    }
+
+    if options.disable_search.unwrap_or(false) {
+        cmd.arg("--disable-search");
+    }
+
+    if let Some(claude_model) = &options.claude_model {
+        cmd.arg("--model").arg(claude_model);
+    }
+
+    if let Some(session_id) = &options.resume {
+        cmd.arg("--resume").arg(session_id);
+    }
+
+    if options.continue_conversation.unwrap_or(false) {
+        cmd.arg("--continue");
+    }
+
+    if options.fork_session.unwrap_or(false) {
+        cmd.arg("--fork-session");
+    }
+
+    if let Some(claude_api_key) = &options.claude_api_key {
+        cmd.env("ANTHROPIC_API_KEY", claude_api_key);
+    }
+
+    if let Some(provider) = &options.provider {
+        for (key, value) in provider.env_vars() {
+            cmd.env(key, value);
+        }
+    }
+
+    if let Some(proxy) = &options.proxy {
+        for (key, value) in proxy.env_vars() {
+            cmd.env(key, value);
+        }
+    }
+
+    for (key, value) in negotiation::env_vars() {
+        cmd.env(key, value);
+    }
+
+    if let Some(run_id) = &options.run_id {
+        cmd.env(RUN_ID_ENV, run_id.to_string());
+    }
+
+    if let Some(max_tokens) = options.claude_max_tokens {
+        cmd.env(MAX_OUTPUT_TOKENS_ENV, max_tokens.to_string());
+    }
+
+    if options.disable_auto_update.unwrap_or(false) {
+        cmd.env(update::DISABLE_AUTOUPDATER_ENV, "1");
+    }
+
+    if let Some(env_vars) = &options.env {
+        for (key, value) in env_vars {
+            cmd.env(key, value);
+        }
+    }
+
+    // Add the prompt as the final argument, or read it from stdin
+    match prompt {
+        Some(prompt) => {
+            cmd.arg(prompt);
+            cmd.stdin(Stdio::null());
+        }
+        None => {
+            cmd.arg("--input-format").arg("stream-json");
+            cmd.stdin(Stdio::piped());
+        }
+    }
+
+    // Configure stdio
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    #[cfg(feature = "sandbox-linux")]
+    if let Some(sandbox) = &options.sandbox {
+        sandbox.apply(&mut cmd, options)?;
+    }
+
+    Ok(cmd)
+}
+
+/// The tools passed to `--disallowedTools`: those of the options and, in
+/// `analysis-only` builds, every tool such builds disable.
+fn disallowed_tools(options: &ClaudeCodeOptions) -> Vec<String> {
+    let tools = options.disallowed_tools.iter().flatten().cloned();
+    #[cfg(feature = "analysis-only")]
+    let tools = tools.chain(ToolPolicy::analysis_only().disallowed_tools());
+    let mut unique = Vec::new();
+    for tool in tools {
+        if !unique.contains(&tool) {
+            unique.push(tool);
+        }
+    }
+    unique
+}
+
+/// Decode one line of CLI output.
+///
+/// Tool progress notifications are handed to the progress callback and do
+/// not produce a message. Refusals are reported to the refusal callback and
+/// the message is still returned.
+pub fn decode_line(
+    line: &str,
+    progress_callback: Option<&ProgressCallback>,
+    refusal_callback: Option<&RefusalCallback>,
+) -> Result<Option<Message>> {
+    if line.trim().is_empty() {
+        return Err(ClaudeSDKError::cli_json_decode("Empty line received"));
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| diagnostics::decode_error(line, &e))?;
+
+    if let Some(error) = api_error::from_error_value(&value) {
+        return Err(error);
+    }
+
+    if let Some(event) = ToolProgressEvent::parse(&value) {
+        if let Some(callback) = progress_callback {
+            callback(&event);
+        }
+        return Ok(None);
+    }
+
+    if let Some(callback) = refusal_callback {
+        if let Some(refusal) = Refusal::from_value(&value) {
+            callback(&refusal);
+        }
+    }
+
+    let message = Message::deserialize(&value)
+        .map_err(|e| diagnostics::message_decode_error(line, &value, &e))?;
+
+    if let Some(error) = api_error::from_message(&message) {
+        return Err(error);
+    }
+    if let Message::System(system) = &message {
+        if let Some(Err(e)) = NegotiatedProtocol::from_ack(system) {
+            return Err(e);
+        }
+    }
+
+    Ok(Some(message))
+}
+
+/// Decode a raw line of CLI output as UTF-8, replacing invalid sequences.
+///
+/// Tool output containing binary data can reach the CLI's output unescaped.
+/// Rather than failing the stream, each invalid sequence is replaced with
+/// U+FFFD and a system message reporting the replacement is returned along
+/// with the line. A trailing `\r` is stripped.
+pub fn decode_utf8_lossy(bytes: &[u8]) -> (String, Option<Message>) {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(line) => (line.to_string(), None),
+        Err(_) => {
+            let replaced = count_invalid_sequences(bytes);
+            tracing::warn!(replaced, "replaced invalid UTF-8 in Claude Code CLI output");
+            let notice = SystemMessage::new(format!(
+                "Replaced {} invalid UTF-8 sequence(s) in a line of CLI output",
+                replaced
+            ));
+            (
+                String::from_utf8_lossy(bytes).into_owned(),
+                Some(notice.into()),
+            )
+        }
+    }
+}
+
+fn count_invalid_sequences(mut bytes: &[u8]) -> usize {
+    let mut count = 0;
+    while let Err(e) = std::str::from_utf8(bytes) {
+        count += 1;
+        let invalid = e.error_len().unwrap_or(bytes.len() - e.valid_up_to());
+        bytes = &bytes[e.valid_up_to() + invalid..];
+    }
+    count
+}
+
+/// Decode the output of the CLI into messages.
+///
+/// Uses the [`OutputParser`](crate::output::OutputParser) for the output
+/// format of `options` and applies its message filter, as the SDK does to
+/// the messages it hands to callers. Invalid UTF-8 is tolerated as described for
+/// [`decode_utf8_lossy`].
+pub fn decode_lines<R>(
+    reader: R,
+    options: &ClaudeCodeOptions,
+) -> impl Stream<Item = Result<Message>> + Send
+where
+    R: AsyncBufRead + Send + Unpin,
+{
+    let message_filter = options.message_filter.clone();
+    let parser = output::parser(options);
+    let batches = stream::unfold(Some((reader, parser)), |state| async move {
+        let (mut reader, mut parser) = state?;
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => Some((batch(None, parser.finish()), None)),
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                let (line, notice) = decode_utf8_lossy(&line);
+                let items = batch(notice, parser.parse_line(&line));
+                Some((items, Some((reader, parser))))
+            }
+            Err(e) => Some((vec![Err(ClaudeSDKError::Io(e))], None)),
+        }
+    });
+    batches.flat_map(move |items| {
+        stream::iter(
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Ok(message) => match &message_filter {
+                        Some(filter) => filter.apply(message).map(Ok),
+                        None => Some(Ok(message)),
+                    },
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Vec<_>>(),
+        )
+    })
+}
+
+pub(crate) fn batch(notice: Option<Message>, parsed: Result<Vec<Message>>) -> Vec<Result<Message>> {
+    let parsed = match parsed {
+        Ok(messages) => messages.into_iter().map(Ok).collect(),
+        Err(e) => vec![Err(e)],
+    };
+    notice.map(Ok).into_iter().chain(parsed).collect()
+}
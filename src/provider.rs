@@ -0,0 +1,115 @@
+use crate::error::{ClaudeSDKError, Result};
+use serde::{Deserialize, Serialize};
+
+/// The API backend the CLI talks to.
+///
+/// Selecting a provider sets the environment variables the CLI reads to route
+/// requests, e.g. `CLAUDE_CODE_USE_BEDROCK` and `AWS_REGION` for Bedrock.
+/// Credentials themselves are resolved by the CLI through the provider's usual
+/// chain (AWS profiles, instance roles, gcloud application default
+/// credentials, ...).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Provider {
+    Anthropic,
+    Bedrock {
+        region: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base_url: Option<String>,
+    },
+    Vertex {
+        project: String,
+        region: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base_url: Option<String>,
+    },
+}
+
+impl Provider {
+    pub fn bedrock<S: Into<String>>(region: S) -> Self {
+        Self::Bedrock {
+            region: region.into(),
+            profile: None,
+            base_url: None,
+        }
+    }
+
+    pub fn vertex<S: Into<String>>(project: S, region: S) -> Self {
+        Self::Vertex {
+            project: project.into(),
+            region: region.into(),
+            base_url: None,
+        }
+    }
+
+    /// Check that the settings the provider requires are present.
+    pub fn validate(&self) -> Result<()> {
+        let require = |name: &str, value: &str| {
+            if value.trim().is_empty() {
+                Err(ClaudeSDKError::invalid_options(format!(
+                    "{} is required for the {} provider",
+                    name,
+                    self.name()
+                )))
+            } else {
+                Ok(())
+            }
+        };
+
+        match self {
+            Self::Anthropic => Ok(()),
+            Self::Bedrock { region, .. } => require("region", region),
+            Self::Vertex {
+                project, region, ..
+            } => {
+                require("project", project)?;
+                require("region", region)
+            }
+        }
+    }
+
+    /// The environment variables that select this provider in the CLI.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        match self {
+            Self::Anthropic => {}
+            Self::Bedrock {
+                region,
+                profile,
+                base_url,
+            } => {
+                vars.push(("CLAUDE_CODE_USE_BEDROCK", "1".to_string()));
+                vars.push(("AWS_REGION", region.clone()));
+                if let Some(profile) = profile {
+                    vars.push(("AWS_PROFILE", profile.clone()));
+                }
+                if let Some(base_url) = base_url {
+                    vars.push(("ANTHROPIC_BEDROCK_BASE_URL", base_url.clone()));
+                }
+            }
+            Self::Vertex {
+                project,
+                region,
+                base_url,
+            } => {
+                vars.push(("CLAUDE_CODE_USE_VERTEX", "1".to_string()));
+                vars.push(("ANTHROPIC_VERTEX_PROJECT_ID", project.clone()));
+                vars.push(("CLOUD_ML_REGION", region.clone()));
+                if let Some(base_url) = base_url {
+                    vars.push(("ANTHROPIC_VERTEX_BASE_URL", base_url.clone()));
+                }
+            }
+        }
+        vars
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Anthropic => "Anthropic",
+            Self::Bedrock { .. } => "Bedrock",
+            Self::Vertex { .. } => "Vertex",
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::{ClaudeSDKError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Proxy settings applied to the CLI process only, leaving the environment
+/// of the calling process untouched.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(url) = &self.http_proxy {
+            validate_proxy_url("HTTP proxy", url)?;
+        }
+        if let Some(url) = &self.https_proxy {
+            validate_proxy_url("HTTPS proxy", url)?;
+        }
+        for host in &self.no_proxy {
+            if host.is_empty() || host.contains(|c: char| c == ',' || c.is_whitespace()) {
+                return Err(ClaudeSDKError::invalid_options(format!(
+                    "Invalid no_proxy entry: {:?}",
+                    host
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The environment variables that configure the proxy in the CLI.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        if let Some(url) = &self.http_proxy {
+            vars.push(("HTTP_PROXY", url.clone()));
+        }
+        if let Some(url) = &self.https_proxy {
+            vars.push(("HTTPS_PROXY", url.clone()));
+        }
+        if !self.no_proxy.is_empty() {
+            vars.push(("NO_PROXY", self.no_proxy.join(",")));
+        }
+        vars
+    }
+}
+
+fn validate_proxy_url(name: &str, url: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(ClaudeSDKError::invalid_options(format!(
+            "Invalid {} URL {:?}: {}",
+            name,
+            redacted(url),
+            reason
+        )))
+    };
+
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return invalid("missing scheme");
+    };
+    if scheme != "http" && scheme != "https" {
+        return invalid("scheme must be http or https");
+    }
+
+    let authority = rest.split('/').next().unwrap_or_default();
+    // Strip optional user:password@ credentials
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        match bracketed.split_once(']') {
+            Some((host, rest)) => (host, rest.strip_prefix(':')),
+            None => return invalid("unterminated IPv6 address"),
+        }
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+
+    if host.is_empty() {
+        return invalid("missing host");
+    }
+    if let Some(port) = port {
+        if port.parse::<u16>().is_err() {
+            return invalid("port must be a number between 0 and 65535");
+        }
+    }
+    Ok(())
+}
+
+/// `url` with any `user:password@` credentials masked, for error messages.
+fn redacted(url: &str) -> String {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let Some(at) = rest[..authority_end].rfind('@') else {
+        return url.to_string();
+    };
+    let host = &rest[at + 1..];
+    match scheme {
+        Some(scheme) => format!("{}://***@{}", scheme, host),
+        None => format!("***@{}", host),
+    }
+}
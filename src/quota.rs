@@ -0,0 +1,299 @@
+//! Pacing queries to an organization's rate limits.
+//!
+//! The CLI passes the API's rate limit errors on, sometimes quoting the
+//! `anthropic-ratelimit-*` and `retry-after` headers of the response. A
+//! [`QuotaState`] collects what they say into a token bucket shared by
+//! every query of a batch, so that one 429 slows the others down instead
+//! of each of them running into the limit in turn.
+
+use crate::api_error::{self, ApiErrorKind};
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::Message;
+use regex::Regex;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How long queries are held back after a rate limit when the API does not
+/// say when to retry.
+pub const DEFAULT_QUOTA_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The longest queries are held back after a rate limit, however long the
+/// API asks for.
+const MAX_PAUSE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The window the API's request limits apply to.
+const LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Rate limits reported by the API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Requests allowed per minute.
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    /// Tokens allowed per minute, the tightest of the total, input and
+    /// output token limits.
+    pub tokens_limit: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimits {
+    /// Parse the `anthropic-ratelimit-*` and `retry-after` headers quoted in
+    /// `text`, either as `name: value` lines or as JSON. `None` if there are
+    /// none.
+    pub fn parse(text: &str) -> Option<Self> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(
+                r"(?i)anthropic-ratelimit-(requests|tokens|input-tokens|output-tokens)-(limit|remaining)\W{0,4}(\d+)",
+            )
+            .expect("rate limit pattern is valid")
+        });
+        let mut limits = Self {
+            retry_after: api_error::retry_after_from_text(text),
+            ..Self::default()
+        };
+        for captures in pattern.captures_iter(text) {
+            let Ok(value) = captures[3].parse::<u64>() else {
+                continue;
+            };
+            let field = match (captures[1].to_lowercase() == "requests", &captures[2]) {
+                (true, kind) if kind.eq_ignore_ascii_case("limit") => &mut limits.requests_limit,
+                (true, _) => &mut limits.requests_remaining,
+                (false, kind) if kind.eq_ignore_ascii_case("limit") => &mut limits.tokens_limit,
+                (false, _) => &mut limits.tokens_remaining,
+            };
+            *field = Some(field.map_or(value, |current| current.min(value)));
+        }
+        (limits != Self::default()).then_some(limits)
+    }
+
+    /// What an API error says about the limits. A rate limit error without
+    /// details still means nothing remains.
+    pub fn from_error(error: &ClaudeSDKError) -> Option<Self> {
+        let ClaudeSDKError::Api { kind, message, .. } = error.root() else {
+            return None;
+        };
+        let mut limits = Self::parse(message);
+        if *kind == ApiErrorKind::RateLimit {
+            let limits = limits.get_or_insert_with(Self::default);
+            if !limits.is_exhausted() {
+                limits.requests_remaining = Some(0);
+            }
+        }
+        if let Some(limits) = &mut limits {
+            limits.retry_after = limits.retry_after.or(error.retry_after());
+        }
+        limits
+    }
+
+    /// Whether no requests or no tokens remain.
+    pub fn is_exhausted(&self) -> bool {
+        self.requests_remaining == Some(0) || self.tokens_remaining == Some(0)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Requests per [`LIMIT_WINDOW`]; queries are not paced while unknown.
+    capacity: Option<f64>,
+    available: f64,
+    refreshed: Instant,
+    paused_until: Option<Instant>,
+    limits: RateLimits,
+    rate_limits: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        if let Some(capacity) = self.capacity {
+            let elapsed = now.saturating_duration_since(self.refreshed).as_secs_f64();
+            self.available =
+                (self.available + elapsed * capacity / LIMIT_WINDOW.as_secs_f64()).min(capacity);
+        }
+        self.refreshed = now;
+    }
+
+    /// How long until a query may start, zero if one may now.
+    fn delay(&mut self, now: Instant) -> Duration {
+        if let Some(until) = self.paused_until.filter(|until| *until > now) {
+            return until - now;
+        }
+        self.refill(now);
+        match self.capacity {
+            Some(capacity) if self.available < 1.0 => Duration::from_secs_f64(
+                (1.0 - self.available) * LIMIT_WINDOW.as_secs_f64() / capacity.max(1.0),
+            ),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Rate limits shared by the queries of a batch, see [`crate::quota`].
+///
+/// Queries take one request from a token bucket holding the organization's
+/// requests per minute, refilled continuously. The bucket's size and level
+/// follow the limits the API reports. Until it has reported any, queries
+/// are only held back after a rate limit: for as long as the API asked, up
+/// to a day, or the backoff. Clones share the state.
+///
+/// The bucket paces the start of queries, not the API requests they make:
+/// a query is charged one request however many turns it runs. Limits the
+/// API reports afterwards correct the level, but a batch of long agentic
+/// runs should start with a lower
+/// [`with_requests_per_minute`](Self::with_requests_per_minute) than the
+/// organization's limit.
+///
+/// ```rust
+/// use claude_code_sdk::pool::SessionPool;
+/// use claude_code_sdk::quota::QuotaState;
+///
+/// // Start at 50 requests per minute until the API says otherwise
+/// let quota = QuotaState::new().with_requests_per_minute(50);
+/// let pool = SessionPool::new(8).with_quota(quota.clone());
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuotaState {
+    backoff: Duration,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl Default for QuotaState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuotaState {
+    pub fn new() -> Self {
+        Self {
+            backoff: DEFAULT_QUOTA_BACKOFF,
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity: None,
+                available: 0.0,
+                refreshed: Instant::now(),
+                paused_until: None,
+                limits: RateLimits::default(),
+                rate_limits: 0,
+            })),
+        }
+    }
+
+    /// Pace queries to `requests` per minute before the API reports its
+    /// limit.
+    pub fn with_requests_per_minute(self, requests: u32) -> Self {
+        {
+            let mut bucket = self.bucket.lock().unwrap();
+            bucket.capacity = Some(f64::from(requests));
+            bucket.available = f64::from(requests);
+        }
+        self
+    }
+
+    /// Hold queries back for `backoff` after a rate limit that does not say
+    /// when to retry, instead of [`DEFAULT_QUOTA_BACKOFF`].
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Take what `limits` say into account.
+    pub fn observe_limits(&self, limits: &RateLimits) {
+        let now = Instant::now();
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(now);
+        if let Some(limit) = limits.requests_limit {
+            bucket.capacity = Some(limit as f64);
+        }
+        if let Some(remaining) = limits.requests_remaining {
+            bucket.available = remaining as f64;
+        }
+        if let Some(capacity) = bucket.capacity {
+            bucket.available = bucket.available.min(capacity);
+        }
+        // Without a request limit the bucket cannot tell when requests
+        // return, and it never tracks tokens
+        let unknown_refill = bucket.capacity.is_none() || limits.tokens_remaining == Some(0);
+        let pause = match limits.retry_after {
+            Some(retry_after) => Some(retry_after),
+            None if limits.is_exhausted() && unknown_refill => Some(self.backoff),
+            None => None,
+        };
+        if let Some(until) = pause.and_then(|pause| now.checked_add(pause.min(MAX_PAUSE))) {
+            bucket.paused_until = Some(
+                bucket
+                    .paused_until
+                    .map_or(until, |paused| paused.max(until)),
+            );
+        }
+        let merged = RateLimits {
+            requests_limit: limits.requests_limit.or(bucket.limits.requests_limit),
+            tokens_limit: limits.tokens_limit.or(bucket.limits.tokens_limit),
+            ..limits.clone()
+        };
+        bucket.limits = merged;
+    }
+
+    /// Take an error into account, counting rate limits.
+    pub fn observe_error(&self, error: &ClaudeSDKError) {
+        let Some(limits) = RateLimits::from_error(error) else {
+            return;
+        };
+        if crate::key_router::is_rate_limit_error(error) {
+            self.bucket.lock().unwrap().rate_limits += 1;
+        }
+        self.observe_limits(&limits);
+    }
+
+    /// Take an item of a query's stream into account, including API errors
+    /// the CLI reports as assistant messages.
+    pub fn observe(&self, item: &Result<Message>) {
+        match item {
+            Ok(message) => {
+                if let Some(error) = api_error::from_message(message) {
+                    self.observe_error(&error);
+                }
+            }
+            Err(error) => self.observe_error(error),
+        }
+    }
+
+    /// The limits the API reported last.
+    pub fn limits(&self) -> RateLimits {
+        self.bucket.lock().unwrap().limits.clone()
+    }
+
+    /// How many rate limit errors were observed.
+    pub fn rate_limits(&self) -> u64 {
+        self.bucket.lock().unwrap().rate_limits
+    }
+
+    /// How long until the next query may start, zero if it may now.
+    pub fn delay(&self) -> Duration {
+        self.bucket.lock().unwrap().delay(Instant::now())
+    }
+
+    /// Take a request from the bucket if one is available, or return how
+    /// long until one is.
+    pub fn try_acquire(&self) -> std::result::Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        match bucket.delay(Instant::now()) {
+            Duration::ZERO => {
+                if bucket.capacity.is_some() {
+                    bucket.available -= 1.0;
+                }
+                Ok(())
+            }
+            delay => Err(delay),
+        }
+    }
+
+    /// Wait until a query may start and charge it one request.
+    #[cfg(feature = "subprocess")]
+    pub async fn acquire(&self) {
+        while let Err(delay) = self.try_acquire() {
+            tracing::debug!(?delay, "waiting for the rate limit quota");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
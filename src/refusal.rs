@@ -0,0 +1,135 @@
+use crate::types::{ContentBlock, Message, Shared};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Why Claude declined a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalCategory {
+    /// The request could cause harm, e.g. malware or weapons.
+    Safety,
+    /// The request conflicts with a usage or content policy.
+    Policy,
+    /// The request involves personal data or privacy violations.
+    Privacy,
+    /// A refusal whose reason could not be classified.
+    Other,
+}
+
+/// A refusal detected in the assistant's output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Refusal {
+    pub category: RefusalCategory,
+    /// The stop reason reported by the API, when the refusal came from one.
+    pub stop_reason: Option<String>,
+    /// The assistant text containing the refusal.
+    pub text: String,
+}
+
+impl Refusal {
+    /// Detect a refusal from a raw CLI output line.
+    ///
+    /// Assistant messages with a `refusal` stop reason are always reported.
+    /// Otherwise the text content is matched against common refusal phrasing.
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        if value.get("type")?.as_str()? != "assistant" {
+            return None;
+        }
+        // The CLI may wrap the API message in a `message` field.
+        let message = value.get("message").unwrap_or(value);
+        let text = message
+            .get("content")
+            .and_then(|c| c.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+                    .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+
+        let stop_reason = message.get("stop_reason").and_then(|s| s.as_str());
+        if stop_reason == Some("refusal") {
+            return Some(Self {
+                category: categorize(&text),
+                stop_reason: stop_reason.map(String::from),
+                text,
+            });
+        }
+        Self::from_text(&text)
+    }
+
+    /// Detect a refusal in an assistant message by its text content.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        let Message::Assistant(assistant) = message else {
+            return None;
+        };
+        let text = assistant
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::from_text(&text)
+    }
+
+    /// Detect a refusal by its phrasing.
+    ///
+    /// Only the opening of the text is considered, so an answer that merely
+    /// mentions it cannot do something is not treated as a refusal.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let opening: String = text.trim_start().chars().take(200).collect();
+        if !refusal_pattern().is_match(&opening) {
+            return None;
+        }
+        Some(Self {
+            category: categorize(text),
+            stop_reason: None,
+            text: text.to_string(),
+        })
+    }
+}
+
+fn refusal_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?i)^(?:i'?m sorry,?\s+(?:but\s+)?|i apologi[sz]e,?\s+(?:but\s+)?)?i\s+(?:can(?:'|no)t|can not|won'?t|will not|must decline|(?:am|'m)\s+(?:not able|unable)\s+to)\s+(?:help|assist|provide|create|write|do|comply|fulfil|generate|support)",
+        )
+        .unwrap()
+    })
+}
+
+fn categorize(text: &str) -> RefusalCategory {
+    static PATTERNS: OnceLock<Vec<(RefusalCategory, Regex)>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        vec![
+            (
+                RefusalCategory::Privacy,
+                Regex::new(r"(?i)\b(privacy|personal (data|information)|doxx|surveil)").unwrap(),
+            ),
+            (
+                RefusalCategory::Safety,
+                Regex::new(r"(?i)\b(harm|dangerous|malware|malicious|weapon|exploit|illegal)")
+                    .unwrap(),
+            ),
+            (
+                RefusalCategory::Policy,
+                Regex::new(r"(?i)\b(polic(y|ies)|guidelines|terms of (use|service))").unwrap(),
+            ),
+        ]
+    });
+    patterns
+        .iter()
+        .find(|(_, pattern)| pattern.is_match(text))
+        .map(|(category, _)| *category)
+        .unwrap_or(RefusalCategory::Other)
+}
+
+pub type RefusalCallback = Shared<dyn Fn(&Refusal) + Send + Sync>;
@@ -0,0 +1,210 @@
+//! Sanitized bundles of a conversation for bug reports.
+//!
+//! A [`ReproBundle`] holds everything needed to report a problem with a
+//! run: its transcript, the options it ran with, and the SDK and CLI
+//! versions and platform it ran on, with paths, usernames and secrets
+//! scrubbed by an [`Anonymizer`]. Attach the file [`ReproBundle::save`]
+//! writes to an issue; maintainers [`load`](ReproBundle::load) it and
+//! replay the transcript through a [`MockTransport`] without the CLI.
+//!
+//! ```rust
+//! use claude_code_sdk::repro::{make_repro_bundle, ReproBundle};
+//! use claude_code_sdk::{query, Message};
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn run(transcript: Vec<Message>) -> claude_code_sdk::Result<()> {
+//! // The reporter
+//! let bundle = make_repro_bundle(&transcript);
+//! bundle.save("repro.json")?;
+//!
+//! // The maintainer
+//! let bundle = ReproBundle::load("repro.json")?;
+//! let mut stream = query("replay", Some(bundle.replay_options())).await?;
+//! while let Some(message) = stream.next().await {
+//!     println!("{:?}", message?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::anonymize::Anonymizer;
+use crate::error::Result;
+use crate::transport::MockTransport;
+use crate::types::{ClaudeCodeOptions, Message};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The version of the bundle format, raised on incompatible changes.
+pub const REPRO_BUNDLE_VERSION: u32 = 1;
+
+/// Options that only hold credentials, left out of bundles.
+const SECRET_OPTIONS: &[&str] = &["claude_api_key"];
+
+/// Options mapping names to values that may be credentials, such as
+/// environment variables and headers. Their names are kept.
+const SECRET_MAPS: &[&str] = &["env", "claude_extra_headers", "claude_default_headers"];
+
+/// Words marking a command line flag, such as an MCP server's
+/// `--api-key`, whose value is a credential.
+const SECRET_FLAG_WORDS: &[&str] = &["token", "key", "secret", "password", "auth", "credential"];
+
+const REDACTED: &str = "<redacted>";
+
+/// A conversation packaged for a bug report, see [`crate::repro`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproBundle {
+    /// The [`REPRO_BUNDLE_VERSION`] the bundle was written with.
+    pub bundle_version: u32,
+    /// The SDK that made the bundle.
+    pub sdk_version: String,
+    /// The CLI the conversation ran with, if its [`SdkInfo`](crate::SdkInfo)
+    /// message said.
+    pub cli_version: Option<String>,
+    /// The operating system and architecture the conversation ran on,
+    /// e.g. `linux-x86_64`.
+    pub platform: String,
+    /// The options of the run with credentials removed, if they were given.
+    /// Transcripts carry a summary of them in their `SdkInfo` message
+    /// either way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+    /// The messages of the conversation, anonymized.
+    pub transcript: Vec<Message>,
+}
+
+/// Bundle `conversation` with the default [`Anonymizer`].
+pub fn make_repro_bundle(conversation: &[Message]) -> ReproBundle {
+    ReproBundle::new(conversation, None, &Anonymizer::new())
+}
+
+impl ReproBundle {
+    /// Bundle `conversation` and the `options` it ran with, scrubbing both
+    /// with `anonymizer`.
+    ///
+    /// The CLI version and platform are taken from the transcript's
+    /// [`SdkInfo`](crate::SdkInfo) message, so the bundle describes the
+    /// machine the conversation ran on rather than the one bundling it.
+    pub fn new(
+        conversation: &[Message],
+        options: Option<&ClaudeCodeOptions>,
+        anonymizer: &Anonymizer,
+    ) -> Self {
+        let info = conversation.iter().find_map(|message| match message {
+            Message::SdkInfo(info) => Some(info),
+            _ => None,
+        });
+        let options = options
+            .and_then(|options| serde_json::to_value(options).ok())
+            .map(|mut options| {
+                let user_info = Regex::new(r"://[^/@\s]+@").expect("valid pattern");
+                redact_options(&mut options, &user_info);
+                anonymizer.scrub_value(&mut options);
+                options
+            });
+        Self {
+            bundle_version: REPRO_BUNDLE_VERSION,
+            sdk_version: env!("CARGO_PKG_VERSION").to_string(),
+            cli_version: info.and_then(|info| info.cli_version.clone()),
+            platform: info.map_or_else(
+                || format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+                |info| info.platform.clone(),
+            ),
+            options,
+            transcript: anonymizer.scrub_transcript(conversation),
+        }
+    }
+
+    /// Write the bundle to `path` as pretty-printed JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read a bundle written by [`save`](Self::save).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// A transport replaying the transcript.
+    pub fn transport(&self) -> MockTransport {
+        MockTransport::new().with_messages(self.transcript.iter().cloned())
+    }
+
+    /// Options running queries against the replayed transcript instead of
+    /// the CLI; the prompt is ignored.
+    pub fn replay_options(&self) -> ClaudeCodeOptions {
+        ClaudeCodeOptions::new().with_transport_factory(self.transport().factory())
+    }
+}
+
+/// Remove credentials from serialized options: the [`SECRET_OPTIONS`], the
+/// values of the [`SECRET_MAPS`] wherever they appear (MCP servers have an
+/// `env` too), the values of secret flags in MCP server `args`, and user
+/// info in URLs such as proxies.
+fn redact_options(value: &mut serde_json::Value, user_info: &Regex) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for key in SECRET_OPTIONS {
+                map.remove(*key);
+            }
+            for (key, value) in map.iter_mut() {
+                match value {
+                    serde_json::Value::Object(secrets) if SECRET_MAPS.contains(&key.as_str()) => {
+                        for secret in secrets.values_mut() {
+                            *secret = REDACTED.into();
+                        }
+                    }
+                    serde_json::Value::Array(args) if key == "args" => {
+                        redact_args(args);
+                        redact_options(value, user_info);
+                    }
+                    _ => redact_options(value, user_info),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_options(item, user_info);
+            }
+        }
+        serde_json::Value::String(text) if user_info.is_match(text) => {
+            *text = user_info
+                .replace_all(text, format!("://{}@", REDACTED).as_str())
+                .into_owned();
+        }
+        _ => {}
+    }
+}
+
+/// Redact the values of flags named like credentials, given either as the
+/// next argument (`--api-key sk-...`) or inline (`--api-key=sk-...`).
+fn redact_args(args: &mut [serde_json::Value]) {
+    let mut secret_next = false;
+    for arg in args {
+        let serde_json::Value::String(text) = arg else {
+            secret_next = false;
+            continue;
+        };
+        if std::mem::take(&mut secret_next) && !text.starts_with('-') {
+            *text = REDACTED.to_string();
+            continue;
+        }
+        let Some(flag) = text.strip_prefix('-') else {
+            continue;
+        };
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (flag, false),
+        };
+        let lower = name.to_ascii_lowercase();
+        if !SECRET_FLAG_WORDS.iter().any(|word| lower.contains(word)) {
+            continue;
+        }
+        if inline {
+            *text = format!("-{}={}", name, REDACTED);
+        } else {
+            secret_next = true;
+        }
+    }
+}
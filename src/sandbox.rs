@@ -0,0 +1,231 @@
+//! Defense-in-depth restrictions for the CLI process on Linux.
+//!
+//! With a [`SandboxPolicy`] set, the spawned CLI process restricts itself
+//! before it starts: Landlock limits filesystem writes to the workspace, and
+//! a seccomp filter rejects system calls an agent has no business making.
+//! The restrictions are inherited by every tool the CLI runs, including Bash.
+
+#[cfg(not(target_os = "linux"))]
+use crate::error::ClaudeSDKError;
+use crate::error::Result;
+use crate::types::ClaudeCodeOptions;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Which restrictions to apply to the CLI process.
+///
+/// The workspace (`cwd`, or the current directory, plus `add_dirs`) and
+/// `writable_paths` stay writable; everything else can only be read and
+/// executed. By default `writable_paths` holds the temporary directory and
+/// the CLI's own state in `~/.claude` and `~/.claude.json`.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::sandbox::SandboxPolicy;
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new()
+///     .with_cwd("/srv/jobs/1234")
+///     .with_sandbox(SandboxPolicy::new().with_writable_path("/srv/cache"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxPolicy {
+    /// Restrict filesystem writes with Landlock.
+    pub filesystem: bool,
+    /// Block dangerous system calls with seccomp.
+    pub seccomp: bool,
+    pub writable_paths: Vec<PathBuf>,
+    /// Fail the spawn if the kernel cannot enforce the Landlock rules,
+    /// instead of running without them.
+    pub require_landlock: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        let mut writable_paths = vec![std::env::temp_dir()];
+        if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+            writable_paths.push(home.join(".claude"));
+            writable_paths.push(home.join(".claude.json"));
+        }
+        Self {
+            filesystem: true,
+            seccomp: true,
+            writable_paths,
+            require_landlock: true,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also allow writes below `path`.
+    pub fn with_writable_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.writable_paths.push(path.into());
+        self
+    }
+
+    pub fn without_filesystem(mut self) -> Self {
+        self.filesystem = false;
+        self
+    }
+
+    pub fn without_seccomp(mut self) -> Self {
+        self.seccomp = false;
+        self
+    }
+
+    /// Run without Landlock on kernels that do not support it.
+    pub fn best_effort(mut self) -> Self {
+        self.require_landlock = false;
+        self
+    }
+
+    /// The paths the CLI may write to when running with `options`.
+    pub fn writable_paths_for(&self, options: &ClaudeCodeOptions) -> Vec<PathBuf> {
+        let workspace = options.cwd.clone().or_else(|| std::env::current_dir().ok());
+        workspace
+            .into_iter()
+            .chain(options.add_dirs.iter().flatten().cloned())
+            .chain(self.writable_paths.iter().cloned())
+            .collect()
+    }
+
+    /// Install the restrictions on `cmd`, to be applied in the child
+    /// process between `fork` and `exec`.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn apply(&self, cmd: &mut Command, options: &ClaudeCodeOptions) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+        use std::sync::Mutex;
+
+        // Everything that allocates or opens files happens here, in the
+        // parent; the child only issues the final system calls.
+        let ruleset = match self.filesystem {
+            true => Some(linux::landlock_ruleset(&self.writable_paths_for(options))?),
+            false => None,
+        };
+        let filter = match self.seccomp {
+            true => Some(linux::seccomp_filter()?),
+            false => None,
+        };
+        let ruleset = Mutex::new(ruleset);
+        let require_landlock = self.require_landlock;
+
+        // SAFETY: the hook only takes an uncontended lock and makes the
+        // prctl, landlock and seccomp system calls, which are safe to use
+        // between fork and exec.
+        unsafe {
+            cmd.pre_exec(move || {
+                let ruleset = ruleset.lock().ok().and_then(|mut ruleset| ruleset.take());
+                if let Some(ruleset) = ruleset {
+                    linux::restrict_self(ruleset, require_landlock)?;
+                }
+                if let Some(filter) = &filter {
+                    seccompiler::apply_filter(filter).map_err(linux::io_error)?;
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn apply(&self, _cmd: &mut Command, _options: &ClaudeCodeOptions) -> Result<()> {
+        Err(ClaudeSDKError::sandbox(
+            "sandboxing is only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::error::{ClaudeSDKError, Result};
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreated,
+        RulesetCreatedAttr, RulesetStatus, ABI,
+    };
+    use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::path::PathBuf;
+
+    const LANDLOCK_ABI: ABI = ABI::V3;
+
+    /// System calls the CLI and its tools never need, rejected with `EPERM`.
+    const BLOCKED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_setns,
+        libc::SYS_reboot,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+    ];
+
+    /// Device files commands commonly write to, always writable.
+    const WRITABLE_DEVICES: &[&str] = &["/dev/null", "/dev/tty"];
+
+    /// Read and execute access everywhere, full access below `writable`.
+    pub(super) fn landlock_ruleset(writable: &[PathBuf]) -> Result<RulesetCreated> {
+        let build = || -> std::result::Result<RulesetCreated, landlock::RulesetError> {
+            Ruleset::default()
+                .handle_access(AccessFs::from_all(LANDLOCK_ABI))?
+                .create()?
+                .add_rules(path_beneath_rules(["/"], AccessFs::from_read(LANDLOCK_ABI)))?
+                .add_rules(path_beneath_rules(
+                    writable,
+                    AccessFs::from_all(LANDLOCK_ABI),
+                ))?
+                .add_rules(path_beneath_rules(
+                    WRITABLE_DEVICES,
+                    AccessFs::from_file(LANDLOCK_ABI),
+                ))
+        };
+        build().map_err(|e| ClaudeSDKError::sandbox(format!("Landlock ruleset: {}", e)))
+    }
+
+    pub(super) fn seccomp_filter() -> Result<BpfProgram> {
+        let arch = std::env::consts::ARCH
+            .try_into()
+            .map_err(|e| ClaudeSDKError::sandbox(format!("seccomp: {}", e)))?;
+        let rules = BLOCKED_SYSCALLS
+            .iter()
+            .map(|&syscall| (syscall, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+        SeccompFilter::new(
+            rules,
+            SeccompAction::Allow,
+            SeccompAction::Errno(libc::EPERM as u32),
+            arch,
+        )
+        .and_then(BpfProgram::try_from)
+        .map_err(|e| ClaudeSDKError::sandbox(format!("seccomp: {}", e)))
+    }
+
+    pub(super) fn restrict_self(ruleset: RulesetCreated, require: bool) -> io::Result<()> {
+        let status = ruleset.restrict_self().map_err(io_error)?;
+        if require && status.ruleset == RulesetStatus::NotEnforced {
+            return Err(io_error("Landlock is not supported by this kernel"));
+        }
+        Ok(())
+    }
+
+    pub(super) fn io_error(error: impl std::fmt::Display) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, error.to_string())
+    }
+}
@@ -0,0 +1,332 @@
+//! Declarative multi-step conversations against one session.
+
+use crate::error::Result;
+use crate::types::{ClaudeCodeOptions, ContentBlock, Message, Shared};
+use futures::future::BoxFuture;
+use std::fmt;
+use std::sync::Arc;
+use tokio_stream::StreamExt;
+
+/// A check against the output of the most recent prompt.
+#[derive(Clone)]
+pub enum Condition {
+    /// The assistant text or the result contains the string.
+    TextContains(String),
+    /// A tool with this name was called.
+    ToolUsed(String),
+    /// The run did not end with an error result.
+    Succeeded,
+    Custom {
+        description: String,
+        check: Shared<dyn Fn(&StepOutcome) -> bool + Send + Sync>,
+    },
+}
+
+impl Condition {
+    pub fn text_contains<S: Into<String>>(text: S) -> Self {
+        Self::TextContains(text.into())
+    }
+
+    pub fn tool_used<S: Into<String>>(tool: S) -> Self {
+        Self::ToolUsed(tool.into())
+    }
+
+    pub fn custom<S, F>(description: S, check: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&StepOutcome) -> bool + Send + Sync + 'static,
+    {
+        Self::Custom {
+            description: description.into(),
+            check: Shared(Arc::new(check)),
+        }
+    }
+
+    pub fn matches(&self, outcome: &StepOutcome) -> bool {
+        match self {
+            Self::TextContains(text) => outcome.text().contains(text.as_str()),
+            Self::ToolUsed(tool) => outcome.tools_used().any(|name| name == tool),
+            Self::Succeeded => outcome.succeeded(),
+            Self::Custom { check, .. } => check(outcome),
+        }
+    }
+}
+
+impl fmt::Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TextContains(text) => write!(f, "text contains {:?}", text),
+            Self::ToolUsed(tool) => write!(f, "tool {} used", tool),
+            Self::Succeeded => f.write_str("succeeded"),
+            Self::Custom { description, .. } => f.write_str(description),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Prompt(String),
+    Expect(Condition),
+    BranchOnToolUse {
+        tool: String,
+        then: Script,
+        otherwise: Script,
+    },
+}
+
+/// The messages produced by one prompt of a script.
+#[derive(Debug, Clone)]
+pub struct StepOutcome {
+    pub prompt: String,
+    pub messages: Vec<Message>,
+}
+
+impl StepOutcome {
+    /// The assistant text and result content, joined by newlines.
+    pub fn text(&self) -> String {
+        let mut parts = Vec::new();
+        for message in &self.messages {
+            match message {
+                Message::Assistant(msg) => {
+                    parts.extend(msg.content.iter().filter_map(|block| match block {
+                        ContentBlock::Text(text) => Some(text.text.as_str()),
+                        _ => None,
+                    }))
+                }
+                Message::Result(result) => parts.extend(result.content.as_deref()),
+                _ => {}
+            }
+        }
+        parts.join("\n")
+    }
+
+    /// Names of the tools called, in order.
+    pub fn tools_used(&self) -> impl Iterator<Item = &str> {
+        self.messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::User(msg) => Some(&msg.content),
+                Message::Assistant(msg) => Some(&msg.content),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse(tool_use) => Some(tool_use.name.as_str()),
+                _ => None,
+            })
+    }
+
+    pub fn succeeded(&self) -> bool {
+        !self.messages.iter().any(|message| match message {
+            Message::Result(result) => {
+                result.exit_code.is_some_and(|code| code != 0) || result.canceled == Some(true)
+            }
+            _ => false,
+        })
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Result(result) => result.session_id.as_deref(),
+                _ => None,
+            })
+    }
+}
+
+/// How a script run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptStatus {
+    Completed,
+    /// An exit condition matched after the prompt with this index.
+    ExitedEarly {
+        step: usize,
+    },
+    /// An expectation did not hold after the prompt with this index.
+    ExpectationFailed {
+        step: usize,
+        condition: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptRun {
+    pub status: ScriptStatus,
+    /// The outcome of every prompt that ran, in order.
+    pub steps: Vec<StepOutcome>,
+    pub session_id: Option<String>,
+}
+
+impl ScriptRun {
+    pub fn is_completed(&self) -> bool {
+        self.status == ScriptStatus::Completed
+    }
+}
+
+/// An ordered list of prompts, expectations and branches run against one
+/// persistent session.
+///
+/// Each prompt resumes the session of the previous one. After every prompt
+/// the exit conditions are checked and the run stops early if one matches.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::script::{Condition, Script};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let run = Script::new()
+///     .prompt("Add a failing test reproducing issue #12")
+///     .expect(Condition::tool_used("Write"))
+///     .prompt("Fix the bug")
+///     .prompt("Run the test suite")
+///     .on_tool_use(
+///         "Bash",
+///         Script::new().expect(Condition::text_contains("passed")),
+///         Script::new().prompt("Run the tests with cargo test"),
+///     )
+///     .exit_when(Condition::text_contains("CANNOT PROCEED"))
+///     .run(ClaudeCodeOptions::new())
+///     .await?;
+///
+/// println!("{:?} after {} prompts", run.status, run.steps.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    steps: Vec<Step>,
+    exit_conditions: Vec<Condition>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a prompt to the session.
+    pub fn prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.steps.push(Step::Prompt(prompt.into()));
+        self
+    }
+
+    /// Stop with [`ScriptStatus::ExpectationFailed`] unless the previous
+    /// prompt's outcome matches `condition`.
+    pub fn expect(mut self, condition: Condition) -> Self {
+        self.steps.push(Step::Expect(condition));
+        self
+    }
+
+    /// Continue with `then` if the previous prompt called `tool`, and with
+    /// `otherwise` if it did not.
+    pub fn on_tool_use<S: Into<String>>(
+        mut self,
+        tool: S,
+        then: Script,
+        otherwise: Script,
+    ) -> Self {
+        self.steps.push(Step::BranchOnToolUse {
+            tool: tool.into(),
+            then,
+            otherwise,
+        });
+        self
+    }
+
+    /// Stop the run early when a prompt's outcome matches `condition`.
+    pub fn exit_when(mut self, condition: Condition) -> Self {
+        self.exit_conditions.push(condition);
+        self
+    }
+
+    /// Run the script with `options`, starting a new session unless the
+    /// options resume one.
+    pub async fn run(&self, options: ClaudeCodeOptions) -> Result<ScriptRun> {
+        let mut run = ScriptRun {
+            status: ScriptStatus::Completed,
+            steps: Vec::new(),
+            session_id: options.resume.clone(),
+        };
+        run.status = self
+            .run_steps(&options, &self.exit_conditions, &mut run)
+            .await?;
+        Ok(run)
+    }
+
+    fn run_steps<'a>(
+        &'a self,
+        options: &'a ClaudeCodeOptions,
+        exit_conditions: &'a [Condition],
+        run: &'a mut ScriptRun,
+    ) -> BoxFuture<'a, Result<ScriptStatus>> {
+        Box::pin(async move {
+            for step in &self.steps {
+                match step {
+                    Step::Prompt(prompt) => {
+                        let outcome =
+                            run_prompt(prompt, options, run.session_id.as_deref()).await?;
+                        if let Some(session_id) = outcome.session_id() {
+                            run.session_id = Some(session_id.to_string());
+                        }
+                        let exit = exit_conditions.iter().any(|c| c.matches(&outcome));
+                        run.steps.push(outcome);
+                        if exit {
+                            return Ok(ScriptStatus::ExitedEarly {
+                                step: run.steps.len() - 1,
+                            });
+                        }
+                    }
+                    Step::Expect(condition) => {
+                        let holds = run.steps.last().is_some_and(|last| condition.matches(last));
+                        if !holds {
+                            return Ok(ScriptStatus::ExpectationFailed {
+                                step: run.steps.len().saturating_sub(1),
+                                condition: format!("{:?}", condition),
+                            });
+                        }
+                    }
+                    Step::BranchOnToolUse {
+                        tool,
+                        then,
+                        otherwise,
+                    } => {
+                        let used = run
+                            .steps
+                            .last()
+                            .is_some_and(|last| last.tools_used().any(|name| name == tool));
+                        let branch = if used { then } else { otherwise };
+                        let mut conditions = exit_conditions.to_vec();
+                        conditions.extend(branch.exit_conditions.iter().cloned());
+                        let status = branch.run_steps(options, &conditions, run).await?;
+                        if status != ScriptStatus::Completed {
+                            return Ok(status);
+                        }
+                    }
+                }
+            }
+            Ok(ScriptStatus::Completed)
+        })
+    }
+}
+
+async fn run_prompt(
+    prompt: &str,
+    options: &ClaudeCodeOptions,
+    session_id: Option<&str>,
+) -> Result<StepOutcome> {
+    let mut options = options.clone();
+    if let Some(session_id) = session_id {
+        options = options.with_resume(session_id);
+    }
+
+    let mut handle = crate::query_with_handle(prompt, Some(options)).await?;
+    let mut messages = Vec::new();
+    while let Some(message) = handle.next().await {
+        messages.push(message?);
+    }
+    Ok(StepOutcome {
+        prompt: prompt.to_string(),
+        messages,
+    })
+}
@@ -0,0 +1,145 @@
+use crate::run_id::RunId;
+use crate::types::{ClaudeCodeOptions, PermissionMode};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::collections::HashMap;
+#[cfg(feature = "subprocess")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "subprocess")]
+use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "subprocess")]
+use std::time::Duration;
+#[cfg(feature = "subprocess")]
+use tokio::process::Command;
+
+#[cfg(feature = "subprocess")]
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A synthetic message emitted first on each stream, describing the
+/// environment the query ran in so transcripts and bug reports are
+/// self-describing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SdkInfo {
+    pub sdk_version: String,
+    pub cli_path: Option<PathBuf>,
+    /// The output of `claude-code --version`, if it could be determined.
+    pub cli_version: Option<String>,
+    /// Operating system and architecture, e.g. `linux-x86_64`.
+    pub platform: String,
+    pub options: OptionsSummary,
+}
+
+/// The options a query ran with, without secrets.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OptionsSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<PermissionMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_dirs: Vec<PathBuf>,
+    /// The provider type, e.g. `bedrock`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continue_conversation: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+    /// Names of the extra environment variables; values are omitted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_keys: Vec<String>,
+}
+
+impl OptionsSummary {
+    pub fn new(options: &ClaudeCodeOptions) -> Self {
+        let mut env_keys: Vec<String> = options
+            .env
+            .iter()
+            .flat_map(|env| env.keys().cloned())
+            .collect();
+        env_keys.sort();
+
+        Self {
+            cwd: options.cwd.clone().or_else(|| std::env::current_dir().ok()),
+            model: options.claude_model.clone(),
+            permission_mode: options.permission_mode.clone(),
+            max_turns: options.max_turns,
+            allowed_tools: options.allowed_tools.clone().unwrap_or_default(),
+            add_dirs: options.add_dirs.clone().unwrap_or_default(),
+            provider: options.provider.as_ref().and_then(|provider| {
+                serde_json::to_value(provider)
+                    .ok()
+                    .and_then(|v| v.get("type")?.as_str().map(String::from))
+            }),
+            resume: options.resume.clone(),
+            continue_conversation: options.continue_conversation,
+            run_id: options.run_id,
+            env_keys,
+        }
+    }
+}
+
+impl SdkInfo {
+    pub fn new(
+        cli_path: Option<PathBuf>,
+        cli_version: Option<String>,
+        options: &ClaudeCodeOptions,
+    ) -> Self {
+        Self {
+            sdk_version: env!("CARGO_PKG_VERSION").to_string(),
+            cli_path,
+            cli_version,
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            options: OptionsSummary::new(options),
+        }
+    }
+
+    /// Collect the info for a query run with the CLI at `cli_path`.
+    #[cfg(feature = "subprocess")]
+    pub(crate) async fn collect(cli_path: &Path, options: &ClaudeCodeOptions) -> Self {
+        let cli_version = cli_version(cli_path).await;
+        Self::new(Some(cli_path.to_path_buf()), cli_version, options)
+    }
+}
+
+/// Run `--version` on the CLI, caching the result per binary.
+#[cfg(feature = "subprocess")]
+pub(crate) async fn cli_version(cli_path: &Path) -> Option<String> {
+    static VERSIONS: OnceLock<Mutex<HashMap<PathBuf, Option<String>>>> = OnceLock::new();
+    let versions = VERSIONS.get_or_init(Default::default);
+    if let Some(version) = versions.lock().unwrap().get(cli_path) {
+        return version.clone();
+    }
+
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        Command::new(cli_path)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    let version = match output {
+        Ok(Ok(output)) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty()),
+        _ => None,
+    };
+
+    versions
+        .lock()
+        .unwrap()
+        .insert(cli_path.to_path_buf(), version.clone());
+    version
+}
@@ -0,0 +1,216 @@
+//! MCP servers implemented in Rust, running inside the application.
+//!
+//! An [`SdkMcpServer`] offers Claude tools whose handlers are Rust closures.
+//! The CLI is told about it with `--mcp-config` as a server of type `sdk`
+//! and sends it MCP's JSON-RPC messages through the control protocol (see
+//! [`crate::control`]), so no separate server binary is needed. Its tools
+//! are named `mcp__<server>__<tool>` like those of any MCP server, which is
+//! also how they are named in `allowed_tools`.
+//!
+//! ```rust
+//! use claude_code_sdk::sdk_mcp::{tool, SdkMcpServer, ToolOutput};
+//! use claude_code_sdk::ClaudeCodeOptions;
+//! use serde_json::json;
+//!
+//! let add = tool(
+//!     "add",
+//!     "Add two numbers",
+//!     json!({
+//!         "type": "object",
+//!         "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+//!         "required": ["a", "b"],
+//!     }),
+//!     |args| async move {
+//!         let sum = args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0);
+//!         ToolOutput::text(sum.to_string())
+//!     },
+//! );
+//! let options = ClaudeCodeOptions::new()
+//!     .with_sdk_mcp_server(SdkMcpServer::new("calculator").with_tool(add))
+//!     .with_allowed_tools(vec!["mcp__calculator__add".to_string()]);
+//! ```
+
+use crate::types::Shared;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+
+/// The MCP protocol version the servers speak.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC's error code for an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC's error code for invalid parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC's error code for a failure of the server itself.
+const INTERNAL_ERROR: i64 = -32603;
+
+/// One item of a tool's output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolContent {
+    Text {
+        text: String,
+    },
+    Image {
+        /// Base64 encoded.
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// What a tool call returns to Claude.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolOutput {
+    pub content: Vec<ToolContent>,
+    /// The call failed; the content says why.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self {
+            content: vec![ToolContent::Text { text: text.into() }],
+            is_error: false,
+        }
+    }
+
+    /// A failed call, with `message` telling Claude what went wrong.
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Self {
+            is_error: true,
+            ..Self::text(message)
+        }
+    }
+
+    /// `value` as pretty-printed JSON text.
+    pub fn json(value: &Value) -> Self {
+        Self::text(serde_json::to_string_pretty(value).unwrap_or_default())
+    }
+}
+
+/// Runs a tool with the arguments Claude passed.
+pub type ToolHandler = Shared<dyn Fn(Value) -> BoxFuture<'static, ToolOutput> + Send + Sync>;
+
+/// A tool of an [`SdkMcpServer`].
+#[derive(Debug, Clone)]
+pub struct SdkMcpTool {
+    pub name: String,
+    pub description: String,
+    /// The JSON schema of the tool's arguments.
+    pub input_schema: Value,
+    pub handler: ToolHandler,
+}
+
+/// A tool named `name` whose calls run `handler`, see [`crate::sdk_mcp`].
+pub fn tool<N, D, F, Fut>(name: N, description: D, input_schema: Value, handler: F) -> SdkMcpTool
+where
+    N: Into<String>,
+    D: Into<String>,
+    F: Fn(Value) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ToolOutput> + Send + 'static,
+{
+    SdkMcpTool {
+        name: name.into(),
+        description: description.into(),
+        input_schema,
+        handler: Shared(Arc::new(move |args| Box::pin(handler(args)))),
+    }
+}
+
+/// An MCP server whose tools run in the application.
+#[derive(Debug, Clone)]
+pub struct SdkMcpServer {
+    pub name: String,
+    pub version: String,
+    pub tools: Vec<SdkMcpTool>,
+}
+
+impl SdkMcpServer {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            tools: Vec::new(),
+        }
+    }
+
+    pub fn with_version<S: Into<String>>(mut self, version: S) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn with_tool(mut self, tool: SdkMcpTool) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// The server's entry in the CLI's `--mcp-config`.
+    pub fn config(&self) -> Value {
+        json!({ "type": "sdk", "name": self.name })
+    }
+
+    /// Answer one JSON-RPC message from the CLI. Notifications, which
+    /// expect no answer, get an empty result.
+    pub async fn handle(&self, message: &Value) -> Value {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": self.name, "version": self.version },
+            })),
+            "tools/list" => Ok(json!({
+                "tools": self
+                    .tools
+                    .iter()
+                    .map(|tool| json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "inputSchema": tool.input_schema,
+                    }))
+                    .collect::<Vec<_>>(),
+            })),
+            "tools/call" => self.call(&params).await,
+            _ if method.starts_with("notifications/") => Ok(json!({})),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        }
+    }
+
+    async fn call(&self, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {}", name)))?;
+        let args = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let output = (tool.handler)(args).await;
+        tracing::debug!(server = %self.name, tool = %name, is_error = output.is_error, "ran SDK MCP tool");
+        serde_json::to_value(output).map_err(|e| (INTERNAL_ERROR, e.to_string()))
+    }
+}
@@ -0,0 +1,256 @@
+//! Driving the SDK over NDJSON, for clients written in other languages.
+//!
+//! Each line read is a request and each line written a response, much like
+//! a language server. Requests carry an `id` chosen by the client, which is
+//! echoed on every response they produce:
+//!
+//! ```text
+//! -> {"id":1,"method":"query","params":{"prompt":"What does main.rs do?","options":{"max_turns":3}}}
+//! <- {"type":"message","id":1,"message":{"type":"assistant","message":{"role":"assistant","content":[...]}}}
+//! <- {"type":"message","id":1,"message":{"type":"result","id":"...","exit_code":0,...}}
+//! <- {"type":"done","id":1}
+//! -> {"id":2,"method":"cancel","params":{"id":1}}
+//! -> {"id":3,"method":"shutdown"}
+//! ```
+//!
+//! Queries run concurrently. `options` takes the JSON written by
+//! [`ClaudeCodeOptions::to_json_compact`]. `cancel` stops a running query,
+//! which then reports `cancelled`; `shutdown`, like the end of the input,
+//! waits for running queries before returning.
+
+use crate::error::{ClaudeSDKError, ErrorCode, Result};
+use crate::prompt::PromptInput;
+use crate::types::{ClaudeCodeOptions, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// A request line.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServeRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    prompt: PromptInput,
+    #[serde(default)]
+    options: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct CancelParams {
+    id: Value,
+}
+
+/// A response line.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServeResponse {
+    /// A message of the query `id`.
+    Message { id: Value, message: Box<Message> },
+    /// The query `id` finished.
+    Done { id: Value },
+    /// The query `id` was cancelled.
+    Cancelled { id: Value },
+    /// The request `id` failed; `id` is null if the line could not be parsed.
+    /// Failures of the SDK carry their [`ErrorCode`] and details.
+    Error {
+        id: Value,
+        message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<ErrorCode>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        details: Option<serde_json::Map<String, Value>>,
+    },
+}
+
+impl ServeResponse {
+    fn error(id: Value, error: impl std::fmt::Display) -> Self {
+        Self::Error {
+            id,
+            message: error.to_string(),
+            code: None,
+            details: None,
+        }
+    }
+
+    fn sdk_error(id: Value, error: &ClaudeSDKError) -> Self {
+        let error = error.to_error_json();
+        Self::Error {
+            id,
+            message: error.message,
+            code: Some(error.code),
+            details: Some(error.details).filter(|details| !details.is_empty()),
+        }
+    }
+}
+
+/// Serve requests read from `reader`, writing responses to `writer`, until
+/// the input ends or a `shutdown` request arrives.
+///
+/// The `claude-sdk-serve` binary runs this on stdin and stdout.
+///
+/// ```rust,no_run
+/// use tokio::io::BufReader;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// claude_code_sdk::serve::serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn serve<R, W>(reader: R, writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    let writer_task = tokio::spawn(write_responses(rx, writer));
+    let mut running: HashMap<String, (Value, JoinHandle<()>)> = HashMap::new();
+
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        running.retain(|_, (_, task)| !task.is_finished());
+
+        let request: ServeRequest = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = tx.send(ServeResponse::error(
+                    Value::Null,
+                    format!("Invalid request: {}", e),
+                ));
+                continue;
+            }
+        };
+        let id = request.id.clone();
+        match request.method.as_str() {
+            "query" => {
+                let key = id.to_string();
+                if running.contains_key(&key) {
+                    let _ = tx.send(ServeResponse::error(
+                        id,
+                        format!("A query with id {} is already running", key),
+                    ));
+                    continue;
+                }
+                match query_request(request.params) {
+                    Ok((prompt, options)) => {
+                        let task = tokio::spawn(run_query(id.clone(), prompt, options, tx.clone()));
+                        running.insert(key, (id, task));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(ServeResponse::sdk_error(id, &e));
+                    }
+                }
+            }
+            "cancel" => {
+                let target = serde_json::from_value::<CancelParams>(request.params)
+                    .map(|params| params.id.to_string());
+                match target.map(|key| running.remove(&key)) {
+                    Ok(Some((query_id, task))) => {
+                        task.abort();
+                        // A query that finished before the abort already sent
+                        // its last response
+                        match task.await {
+                            Err(e) if e.is_cancelled() => {
+                                let _ = tx.send(ServeResponse::Cancelled { id: query_id });
+                            }
+                            _ => {
+                                let _ = tx.send(ServeResponse::error(
+                                    id,
+                                    "No running query with that id",
+                                ));
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        let _ = tx.send(ServeResponse::error(id, "No running query with that id"));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(ServeResponse::error(id, format!("Invalid params: {}", e)));
+                    }
+                }
+            }
+            "shutdown" => break,
+            method => {
+                let _ = tx.send(ServeResponse::error(
+                    id,
+                    format!("Unknown method: {}", method),
+                ));
+            }
+        }
+    }
+
+    for (_, (_, task)) in running {
+        let _ = task.await;
+    }
+    drop(tx);
+    writer_task
+        .await
+        .map_err(|e| ClaudeSDKError::cli_connection(format!("Response writer failed: {}", e)))?
+}
+
+fn query_request(params: Value) -> Result<(PromptInput, ClaudeCodeOptions)> {
+    let params: QueryParams = serde_json::from_value(params)
+        .map_err(|e| ClaudeSDKError::invalid_options(format!("Invalid params: {}", e)))?;
+    let options = match params.options {
+        Some(options) => ClaudeCodeOptions::from_json(&options.to_string())?,
+        None => ClaudeCodeOptions::default(),
+    };
+    Ok((params.prompt, options))
+}
+
+async fn run_query(
+    id: Value,
+    prompt: PromptInput,
+    options: ClaudeCodeOptions,
+    tx: mpsc::UnboundedSender<ServeResponse>,
+) {
+    let mut handle = match crate::query_with_handle(prompt, Some(options)).await {
+        Ok(handle) => handle,
+        Err(e) => {
+            let _ = tx.send(ServeResponse::sdk_error(id, &e));
+            return;
+        }
+    };
+    while let Some(item) = handle.next().await {
+        let response = match item {
+            Ok(message) => ServeResponse::Message {
+                id: id.clone(),
+                message: Box::new(message),
+            },
+            Err(e) => ServeResponse::sdk_error(id.clone(), &e),
+        };
+        if tx.send(response).is_err() {
+            return;
+        }
+    }
+    let _ = tx.send(ServeResponse::Done { id });
+}
+
+async fn write_responses<W>(
+    mut rx: mpsc::UnboundedReceiver<ServeResponse>,
+    mut writer: W,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(response) = rx.recv().await {
+        let mut line = serde_json::to_vec(&response)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,297 @@
+//! Assistant text in sentences, for text-to-speech.
+//!
+//! A voice agent speaks Claude's reply as it arrives rather than waiting
+//! for the result. [`on_text_chunk`](crate::ClaudeCodeOptions::on_text_chunk)
+//! receives the text of each turn as [`TextChunk`]s: a
+//! [`TurnStart`](TextChunk::TurnStart), one
+//! [`Sentence`](TextChunk::Sentence) at a time, and then either
+//! [`TurnEnd`](TextChunk::TurnEnd) once everything was said or
+//! [`Interrupted`](TextChunk::Interrupted) if the turn was cut short, at
+//! which point speech should stop. A turn is interrupted when the CLI
+//! cancels it, when the stream fails or ends without a result, and when
+//! the stream is dropped, e.g. because the query was closed.
+//!
+//! Only Claude's own text is spoken; tool calls and the text of subagents
+//! are left out.
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::speech::{text_chunk_channel, TextChunk};
+//! use claude_code_sdk::{query, ClaudeCodeOptions};
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn speak(_sentence: &str) {}
+//! # async fn stop_speaking() {}
+//! # async fn run() -> claude_code_sdk::Result<()> {
+//! let (callback, mut chunks) = text_chunk_channel();
+//! let options = ClaudeCodeOptions::new().with_text_chunk_callback(callback);
+//!
+//! tokio::spawn(async move {
+//!     while let Some(chunk) = chunks.next().await {
+//!         match chunk {
+//!             TextChunk::Sentence(sentence) => speak(&sentence).await,
+//!             TextChunk::Interrupted => stop_speaking().await,
+//!             TextChunk::TurnStart | TextChunk::TurnEnd => {}
+//!         }
+//!     }
+//! });
+//!
+//! let mut stream = query("What's the weather like on Mars?", Some(options)).await?;
+//! while let Some(message) = stream.next().await {
+//!     message?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "subprocess")]
+use crate::error::Result;
+use crate::types::Shared;
+#[cfg(feature = "subprocess")]
+use crate::types::{ContentBlock, Message};
+#[cfg(feature = "subprocess")]
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::pin::Pin;
+#[cfg(feature = "subprocess")]
+use std::sync::Arc;
+#[cfg(feature = "subprocess")]
+use tokio::sync::mpsc;
+#[cfg(feature = "subprocess")]
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// Characters that end a sentence when followed by whitespace.
+const TERMINATORS: &[char] = &['.', '!', '?', '…'];
+/// Full-width characters that end a sentence on their own.
+const FULL_WIDTH_TERMINATORS: &[char] = &['。', '！', '？'];
+/// Characters that may follow a terminator and still belong to the sentence.
+const CLOSERS: &[char] = &['"', '\'', ')', ']', '”', '’', '」', '』'];
+/// Words whose trailing period does not end a sentence.
+const ABBREVIATIONS: &[&str] = &["e.g", "i.e", "mr", "mrs", "ms", "dr", "prof", "vs", "cf"];
+
+/// One piece of a turn's text, see [`crate::speech`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "text", rename_all = "snake_case")]
+pub enum TextChunk {
+    /// Claude started answering.
+    TurnStart,
+    /// A complete sentence, without surrounding whitespace.
+    Sentence(String),
+    /// The turn is over and all of its text was delivered.
+    TurnEnd,
+    /// The turn was cut short; stop speaking.
+    Interrupted,
+}
+
+pub type TextChunkCallback = Shared<dyn Fn(&TextChunk) + Send + Sync>;
+
+/// Create a text chunk callback paired with a stream of the chunks it
+/// receives, see [`crate::speech`].
+#[cfg(feature = "subprocess")]
+pub fn text_chunk_channel() -> (
+    TextChunkCallback,
+    impl Stream<Item = TextChunk> + Send + Unpin,
+) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let callback: TextChunkCallback = Shared(Arc::new(move |chunk: &TextChunk| {
+        let _ = tx.send(chunk.clone());
+    }));
+    (callback, UnboundedReceiverStream::new(rx))
+}
+
+/// Splits text into sentences as it arrives.
+///
+/// A sentence ends at a line break, or at `.`, `!`, `?` or `…` followed by
+/// whitespace, so text is held back until the next piece shows where it
+/// ends. Periods after common abbreviations and list numbers do not end a
+/// sentence.
+///
+/// ```rust
+/// use claude_code_sdk::speech::SentenceSplitter;
+///
+/// let mut splitter = SentenceSplitter::new();
+/// assert_eq!(splitter.push("Hello there. How"), vec!["Hello there."]);
+/// assert!(splitter.push(" are").is_empty());
+/// assert_eq!(splitter.push(" you? Fine."), vec!["How are you?"]);
+/// assert_eq!(splitter.finish().as_deref(), Some("Fine."));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SentenceSplitter {
+    buffer: String,
+}
+
+impl SentenceSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `text`, returning the sentences it completes.
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        self.buffer.push_str(text);
+        let mut sentences = Vec::new();
+        while let Some(end) = sentence_end(&self.buffer) {
+            let rest = self.buffer.split_off(end);
+            let sentence = std::mem::replace(&mut self.buffer, rest);
+            let sentence = sentence.trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+        }
+        sentences
+    }
+
+    /// The text held back, as the last sentence.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+
+    /// Drop the text held back.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+/// The byte offset after the first complete sentence of `text`.
+fn sentence_end(text: &str) -> Option<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        if c == '\n' {
+            return Some(at + 1);
+        }
+        let full_width = FULL_WIDTH_TERMINATORS.contains(&c);
+        if !full_width && !TERMINATORS.contains(&c) {
+            i += 1;
+            continue;
+        }
+        let mut next = i + 1;
+        while next < chars.len()
+            && (TERMINATORS.contains(&chars[next].1)
+                || FULL_WIDTH_TERMINATORS.contains(&chars[next].1)
+                || CLOSERS.contains(&chars[next].1))
+        {
+            next += 1;
+        }
+        match chars.get(next) {
+            None if full_width => return Some(text.len()),
+            Some(&(end, following))
+                if (full_width || following.is_whitespace())
+                    && !(c == '.' && continues_after_period(&text[..at])) =>
+            {
+                return Some(end)
+            }
+            _ => {}
+        }
+        i = next;
+    }
+    None
+}
+
+/// Whether a period after `before` belongs to an abbreviation or a list
+/// number rather than ending a sentence.
+fn continues_after_period(before: &str) -> bool {
+    let last = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default();
+    let word = last.trim_start_matches(|c: char| !c.is_alphanumeric());
+    let list_number = before[..before.len() - last.len()].trim().is_empty()
+        && !word.is_empty()
+        && word.chars().all(|c| c.is_ascii_digit());
+    list_number || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Where the wrapped stream is within a turn.
+#[cfg(feature = "subprocess")]
+struct Speaker {
+    callback: TextChunkCallback,
+    splitter: SentenceSplitter,
+    in_turn: bool,
+}
+
+#[cfg(feature = "subprocess")]
+impl Speaker {
+    fn emit(&self, chunk: TextChunk) {
+        (self.callback)(&chunk);
+    }
+
+    fn start_turn(&mut self) {
+        if !self.in_turn {
+            self.in_turn = true;
+            self.emit(TextChunk::TurnStart);
+        }
+    }
+
+    fn speak(&mut self, text: &str) {
+        for sentence in self.splitter.push(text) {
+            self.emit(TextChunk::Sentence(sentence));
+        }
+        // Text blocks are complete, so nothing more is coming for this one
+        if let Some(sentence) = self.splitter.finish() {
+            self.emit(TextChunk::Sentence(sentence));
+        }
+    }
+
+    fn end_turn(&mut self, interrupted: bool) {
+        if std::mem::take(&mut self.in_turn) {
+            self.splitter.clear();
+            self.emit(if interrupted {
+                TextChunk::Interrupted
+            } else {
+                TextChunk::TurnEnd
+            });
+        }
+    }
+
+    fn observe(&mut self, item: &Result<Message>) {
+        match item {
+            Ok(Message::Assistant(message)) if message.parent_tool_use_id.is_none() => {
+                self.start_turn();
+                for block in &message.content {
+                    if let ContentBlock::Text(text) = block {
+                        self.speak(&text.text);
+                    }
+                }
+            }
+            Ok(Message::Result(result)) => {
+                // A turn without any text still has a start and an end
+                self.start_turn();
+                self.end_turn(result.canceled == Some(true));
+            }
+            Ok(_) => {}
+            Err(_) => self.end_turn(true),
+        }
+    }
+}
+
+#[cfg(feature = "subprocess")]
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        self.end_turn(true);
+    }
+}
+
+/// Report the stream's assistant text to `callback` as [`TextChunk`]s.
+#[cfg(feature = "subprocess")]
+pub(crate) fn speak_text(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    callback: TextChunkCallback,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let speaker = Speaker {
+        callback,
+        splitter: SentenceSplitter::new(),
+        in_turn: false,
+    };
+    Box::pin(stream::unfold(
+        (stream, speaker),
+        |(mut stream, mut speaker)| async move {
+            // A stream ending mid-turn interrupts it when the speaker drops
+            let item = stream.next().await?;
+            speaker.observe(&item);
+            Some((item, (stream, speaker)))
+        },
+    ))
+}
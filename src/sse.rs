@@ -0,0 +1,157 @@
+//! Conversion of query output to the Anthropic Messages API server-sent
+//! events vocabulary.
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::types::{AssistantMessage, ContentBlock, Message};
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::{json, Value};
+use std::fmt;
+
+/// A server-sent event: the event name and its JSON data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: &'static str,
+    pub data: Value,
+}
+
+impl SseEvent {
+    fn new(event: &'static str, data: Value) -> Self {
+        Self { event, data }
+    }
+
+    pub fn into_pair(self) -> (&'static str, Value) {
+        (self.event, self.data)
+    }
+}
+
+/// Formats the event for the wire, including the terminating blank line.
+impl fmt::Display for SseEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event: {}\ndata: {}\n\n", self.event, self.data)
+    }
+}
+
+/// Convert a query stream into Messages API streaming events.
+///
+/// Each assistant message becomes a `message_start`, a
+/// `content_block_start`/`content_block_delta`/`content_block_stop` triple
+/// per content block, a `message_delta` with the stop reason and a
+/// `message_stop`. Errors become `error` events. Messages with no equivalent
+/// in the API vocabulary, such as tool results and system messages, are
+/// skipped.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::{query, sse::to_sse_events};
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let stream = query("Explain this repository", None).await?;
+/// let mut events = to_sse_events(stream);
+/// while let Some(event) = events.next().await {
+///     print!("{}", event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_sse_events<S>(messages: S) -> impl Stream<Item = SseEvent> + Send
+where
+    S: Stream<Item = Result<Message>> + Send,
+{
+    messages.flat_map(|item| stream::iter(message_events(item)))
+}
+
+/// The events for a single stream item.
+pub fn message_events(item: Result<Message>) -> Vec<SseEvent> {
+    match item {
+        Ok(Message::Assistant(message)) => assistant_events(&message),
+        Ok(_) => Vec::new(),
+        Err(error) => vec![error_event(&error)],
+    }
+}
+
+fn assistant_events(message: &AssistantMessage) -> Vec<SseEvent> {
+    let id = format!("msg_{}", uuid::Uuid::new_v4().simple());
+    let mut events = vec![SseEvent::new(
+        "message_start",
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": id,
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "stop_reason": null,
+                "stop_sequence": null,
+            }
+        }),
+    )];
+
+    // Tool results and images are sent by the user side and never streamed
+    let blocks = message.content.iter().filter_map(|block| match block {
+        ContentBlock::Text(text) => Some((
+            json!({"type": "text", "text": ""}),
+            vec![json!({"type": "text_delta", "text": text.text})],
+        )),
+        ContentBlock::ToolUse(tool_use) => Some((
+            json!({"type": "tool_use", "id": tool_use.id, "name": tool_use.name, "input": {}}),
+            vec![json!({"type": "input_json_delta", "partial_json": tool_use.input.to_string()})],
+        )),
+        ContentBlock::Thinking(thinking) => Some((
+            json!({"type": "thinking", "thinking": ""}),
+            vec![
+                json!({"type": "thinking_delta", "thinking": thinking.thinking}),
+                json!({"type": "signature_delta", "signature": thinking.signature}),
+            ],
+        )),
+        // Redacted thinking arrives whole, without deltas
+        ContentBlock::RedactedThinking(redacted) => Some((
+            json!({"type": "redacted_thinking", "data": redacted.data}),
+            Vec::new(),
+        )),
+        ContentBlock::ToolResult(_) | ContentBlock::Image(_) => None,
+    });
+    for (index, (start, deltas)) in blocks.enumerate() {
+        events.push(SseEvent::new(
+            "content_block_start",
+            json!({"type": "content_block_start", "index": index, "content_block": start}),
+        ));
+        for delta in deltas {
+            events.push(SseEvent::new(
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": index, "delta": delta}),
+            ));
+        }
+        events.push(SseEvent::new(
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": index}),
+        ));
+    }
+
+    let stop_reason = match message.content.last() {
+        Some(ContentBlock::ToolUse(_)) => "tool_use",
+        _ => "end_turn",
+    };
+    events.push(SseEvent::new(
+        "message_delta",
+        json!({
+            "type": "message_delta",
+            "delta": {"stop_reason": stop_reason, "stop_sequence": null}
+        }),
+    ));
+    events.push(SseEvent::new(
+        "message_stop",
+        json!({"type": "message_stop"}),
+    ));
+    events
+}
+
+fn error_event(error: &ClaudeSDKError) -> SseEvent {
+    let (error_type, message) = match error.root() {
+        ClaudeSDKError::Api { kind, message, .. } => (kind.error_type(), message.clone()),
+        other => ("api_error", other.to_string()),
+    };
+    SseEvent::new(
+        "error",
+        json!({"type": "error", "error": {"type": error_type, "message": message}}),
+    )
+}
@@ -0,0 +1,208 @@
+//! Latency and cost percentiles, kept in process.
+//!
+//! Every query records how long the CLI took to start, the time to its
+//! first token, the duration of its turn and its cost into a
+//! [`StatsRegistry`]: the [global](StatsRegistry::global) one unless the
+//! options name another with
+//! [`with_stats_registry`](crate::ClaudeCodeOptions::with_stats_registry).
+//! [`stats`](StatsRegistry::stats) reports p50, p95 and p99 of each, so SLOs
+//! can be watched without a metrics stack.
+//!
+//! Values are kept in exponential histograms: memory does not grow with
+//! the number of queries, and percentiles are accurate to within about 5%.
+//!
+//! ```rust
+//! use claude_code_sdk::stats::StatsRegistry;
+//!
+//! let stats = StatsRegistry::global().stats();
+//! if stats.first_token_ms.p95 > 5_000.0 {
+//!     eprintln!("p95 time to first token is {:.0} ms", stats.first_token_ms.p95);
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Each bucket is this much wider than the one before. Values are reported
+/// as the middle of their bucket, at most about 4.4% off.
+const GROWTH: f64 = 1.090_507_732_665_257_7; // 2^(1/8)
+
+/// A histogram of positive values in exponentially growing buckets.
+#[derive(Debug, Clone, Default)]
+pub struct ExpHistogram {
+    /// Bucket `i` counts values in `(GROWTH^(i-1), GROWTH^i]`.
+    buckets: BTreeMap<i32, u64>,
+    /// Values of zero or less.
+    zeros: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl ExpHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        if value <= 0.0 {
+            self.zeros += 1;
+        } else {
+            let index = value.log(GROWTH).ceil() as i32;
+            *self.buckets.entry(index).or_default() += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+        if self.count == 1 {
+            (self.min, self.max) = (value, value);
+        } else {
+            (self.min, self.max) = (self.min.min(value), self.max.max(value));
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The value below which a share `q` of the recorded values fall, or
+    /// `None` if nothing was recorded.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        if rank <= self.zeros {
+            return Some(0.0);
+        }
+        let mut seen = self.zeros;
+        for (index, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let middle = GROWTH.powf(f64::from(*index) - 0.5);
+                return Some(middle.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    pub fn percentiles(&self) -> Percentiles {
+        if self.count == 0 {
+            return Percentiles::default();
+        }
+        Percentiles {
+            count: self.count,
+            mean: self.sum / self.count as f64,
+            p50: self.quantile(0.50).unwrap_or_default(),
+            p95: self.quantile(0.95).unwrap_or_default(),
+            p99: self.quantile(0.99).unwrap_or_default(),
+            max: self.max,
+        }
+    }
+}
+
+/// A summary of one histogram; all zero when it is empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// The percentiles of a [`StatsRegistry`] at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// From the call to the CLI having started and accepted the prompt.
+    pub spawn_ms: Percentiles,
+    /// From the start of a turn to its first assistant message.
+    pub first_token_ms: Percentiles,
+    /// From the start of a turn to its result.
+    pub turn_duration_ms: Percentiles,
+    /// The cost of each query, in USD, as reported by the CLI.
+    pub cost_usd: Percentiles,
+}
+
+#[derive(Debug, Default)]
+struct Histograms {
+    spawn_ms: ExpHistogram,
+    first_token_ms: ExpHistogram,
+    turn_duration_ms: ExpHistogram,
+    cost_usd: ExpHistogram,
+}
+
+/// Latency and cost histograms of the queries run with it.
+///
+/// Cheap to clone; clones record into the same histograms.
+#[derive(Debug, Clone, Default)]
+pub struct StatsRegistry {
+    histograms: Arc<Mutex<Histograms>>,
+}
+
+impl StatsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The registry queries record into unless their options name another.
+    pub fn global() -> &'static StatsRegistry {
+        static GLOBAL: OnceLock<StatsRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(StatsRegistry::new)
+    }
+
+    pub fn record_spawn(&self, elapsed: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .spawn_ms
+            .record(millis(elapsed));
+    }
+
+    pub fn record_first_token(&self, elapsed: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .first_token_ms
+            .record(millis(elapsed));
+    }
+
+    pub fn record_turn(&self, duration: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .turn_duration_ms
+            .record(millis(duration));
+    }
+
+    pub fn record_cost(&self, cost_usd: f64) {
+        self.histograms.lock().unwrap().cost_usd.record(cost_usd);
+    }
+
+    /// The percentiles of everything recorded since the last reset.
+    pub fn stats(&self) -> StatsSnapshot {
+        let histograms = self.histograms.lock().unwrap();
+        StatsSnapshot {
+            spawn_ms: histograms.spawn_ms.percentiles(),
+            first_token_ms: histograms.first_token_ms.percentiles(),
+            turn_duration_ms: histograms.turn_duration_ms.percentiles(),
+            cost_usd: histograms.cost_usd.percentiles(),
+        }
+    }
+
+    /// Forget everything recorded, e.g. at the start of each reporting
+    /// window.
+    pub fn reset(&self) {
+        *self.histograms.lock().unwrap() = Histograms::default();
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
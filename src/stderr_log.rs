@@ -0,0 +1,183 @@
+#[cfg(feature = "subprocess")]
+use crate::interaction::Lines;
+use crate::run_id::RunId;
+use crate::types::Shared;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "subprocess")]
+use std::fs;
+#[cfg(feature = "subprocess")]
+use std::io;
+use std::path::PathBuf;
+#[cfg(feature = "subprocess")]
+use tokio::fs::{File, OpenOptions};
+#[cfg(feature = "subprocess")]
+use tokio::io::AsyncWriteExt;
+#[cfg(feature = "subprocess")]
+use tokio::process::ChildStderr;
+#[cfg(feature = "subprocess")]
+use tokio::task::JoinHandle;
+
+/// How much of the stderr output is kept in memory for error reporting.
+#[cfg(feature = "subprocess")]
+const STDERR_TAIL_BYTES: usize = 64 * 1024;
+
+/// The start of the name of every log file, so rotation leaves other files
+/// in the directory alone.
+const FILE_PREFIX: &str = "claude-stderr-";
+
+/// Writes the CLI's stderr to one log file per run in a directory.
+///
+/// The file for a run is `<dir>/claude-stderr-<run id>.log`; retries of the
+/// run append to it. Before a new file is created the oldest of these logs
+/// are removed so that at most `max_files` remain, at least one. Other files
+/// in the directory are never touched. Each line is also emitted as a
+/// `DEBUG` tracing event with target `claude_code_sdk::cli_stderr`, so a
+/// `tracing-subscriber` file appender picks it up as well.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::stderr_log::StderrLog;
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new()
+///     .with_stderr_log(StderrLog::new("logs/claude").with_max_files(50));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StderrLog {
+    pub dir: PathBuf,
+    pub max_files: usize,
+}
+
+impl StderrLog {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            max_files: 20,
+        }
+    }
+
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    /// The log file of the run with id `run_id`.
+    pub fn path_for(&self, run_id: RunId) -> PathBuf {
+        self.dir.join(format!("{}{}.log", FILE_PREFIX, run_id))
+    }
+
+    /// Remove the oldest logs so that a new one fits within `max_files`.
+    #[cfg(feature = "subprocess")]
+    fn rotate(&self) -> io::Result<()> {
+        let mut logs: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(FILE_PREFIX) && name.ends_with(".log")
+            })
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+        // Deserialized options skip `with_max_files`
+        let keep = self.max_files.max(1) - 1;
+        logs.sort();
+        for (_, path) in &logs[..logs.len().saturating_sub(keep)] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Create the directory and rotate, returning the path for `run_id`.
+    #[cfg(feature = "subprocess")]
+    fn prepare(&self, run_id: RunId) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        self.rotate()?;
+        Ok(self.path_for(run_id))
+    }
+
+    /// Open the run's log file for appending, or `None` if that fails.
+    #[cfg(feature = "subprocess")]
+    async fn open(&self, run_id: RunId) -> Option<File> {
+        let path = match self.prepare(run_id) {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to prepare stderr log directory");
+                return None;
+            }
+        };
+        match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => Some(file),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "failed to create stderr log");
+                None
+            }
+        }
+    }
+}
+
+/// Called with each line the CLI writes to stderr, as it is written.
+pub type StderrCallback = Shared<dyn Fn(&str) + Send + Sync>;
+
+/// Read `stderr` on a background task, copying each line into the run's
+/// log file if there is one and passing it to `on_line`. A line left
+/// unfinished for a while is passed to `on_stall` once, before it ends.
+///
+/// The task returns the tail of the output once the stream closes.
+#[cfg(feature = "subprocess")]
+pub(crate) fn read_stderr(
+    stderr: ChildStderr,
+    log: Option<(StderrLog, RunId)>,
+    on_line: Option<StderrCallback>,
+    on_stall: Option<StderrCallback>,
+) -> JoinHandle<String> {
+    tokio::spawn(async move {
+        let mut file = match &log {
+            Some((log, run_id)) => log.open(*run_id).await,
+            None => None,
+        };
+
+        let mut tail = String::new();
+        let mut lines = Lines::new(stderr);
+        let on_stall = on_stall
+            .as_ref()
+            .map(|callback| &**callback as &(dyn Fn(&str) + Sync));
+        while let Ok(Some(line)) = lines.next_line(on_stall).await {
+            let line = String::from_utf8_lossy(&line);
+            tracing::debug!(target: "claude_code_sdk::cli_stderr", "{}", line);
+            if let Some(callback) = &on_line {
+                callback(&line);
+            }
+            if let Some(f) = &mut file {
+                let written = async {
+                    f.write_all(line.as_bytes()).await?;
+                    f.write_all(b"\n").await?;
+                    f.flush().await
+                };
+                if let Err(e) = written.await {
+                    tracing::warn!(error = %e, "failed to write stderr log");
+                    file = None;
+                }
+            }
+            push_tail(&mut tail, &line);
+        }
+        tail
+    })
+}
+
+/// Append `line` to `tail`, dropping the oldest output beyond the limit.
+#[cfg(feature = "subprocess")]
+fn push_tail(tail: &mut String, line: &str) {
+    tail.push_str(line);
+    tail.push('\n');
+    if tail.len() > STDERR_TAIL_BYTES {
+        let mut cut = tail.len() - STDERR_TAIL_BYTES;
+        while !tail.is_char_boundary(cut) {
+            cut += 1;
+        }
+        tail.drain(..cut);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::types::Shared;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// A writer receiving a verbatim copy of the NDJSON wire traffic with the CLI.
+///
+/// Each line is written as received, newline terminated, and flushed
+/// immediately so external tools can follow it live. Write errors are logged
+/// and otherwise ignored; a broken tap never fails the query.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let log = tokio::fs::File::create("wire.ndjson").await?;
+/// let options = ClaudeCodeOptions::new().with_raw_tap(log);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RawTap {
+    writer: Shared<Mutex<Pin<Box<dyn AsyncWrite + Send>>>>,
+    compressed: bool,
+}
+
+impl RawTap {
+    pub fn new<W: AsyncWrite + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Shared(Arc::new(Mutex::new(Box::pin(writer)))),
+            compressed: false,
+        }
+    }
+
+    /// A tap compressing the traffic as it is written.
+    ///
+    /// The compressed stream is finished when the query's output ends, so a
+    /// compressed tap records a single query. Streams cut off by a killed
+    /// run still load with [`Debugger::load`](crate::debugger::Debugger::load).
+    ///
+    /// ```rust,no_run
+    /// use claude_code_sdk::compression::Compression;
+    /// use claude_code_sdk::tap::RawTap;
+    /// use claude_code_sdk::ClaudeCodeOptions;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let log = tokio::fs::File::create("wire.ndjson.zst").await?;
+    /// let mut options = ClaudeCodeOptions::new();
+    /// options.raw_tap = Some(RawTap::compressed(log, Compression::Zstd));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn compressed<W: AsyncWrite + Send + 'static>(
+        writer: W,
+        compression: crate::compression::Compression,
+    ) -> Self {
+        Self {
+            writer: Shared(Arc::new(Mutex::new(compression.encoder(writer)))),
+            compressed: compression != crate::compression::Compression::None,
+        }
+    }
+
+    /// Finish a compressed stream once the query's output has ended.
+    pub(crate) async fn finish(&self) {
+        if !self.compressed {
+            return;
+        }
+        if let Err(e) = self.writer.lock().await.shutdown().await {
+            tracing::debug!(error = %e, "failed to finish raw tap");
+        }
+    }
+
+    pub(crate) async fn write_line(&self, line: &[u8]) {
+        let mut writer = self.writer.lock().await;
+        let result = async {
+            writer.write_all(line).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::debug!(error = %e, "failed to write to raw tap");
+        }
+    }
+}
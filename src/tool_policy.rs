@@ -0,0 +1,224 @@
+#[cfg(feature = "analysis-only")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(all(feature = "analysis-only", feature = "subprocess"))]
+use crate::transport::DisposeGuard;
+use crate::types::ClaudeCodeOptions;
+#[cfg(feature = "analysis-only")]
+use crate::types::PermissionMode;
+#[cfg(all(feature = "analysis-only", feature = "subprocess"))]
+use crate::types::{ContentBlock, Message};
+#[cfg(all(feature = "analysis-only", feature = "subprocess"))]
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "analysis-only", feature = "subprocess"))]
+use std::pin::Pin;
+
+/// Whether the crate was built with the `analysis-only` feature, which
+/// disables the [`ANALYSIS_ONLY_DISABLED`] tools whatever the options say.
+pub const ANALYSIS_ONLY: bool = cfg!(feature = "analysis-only");
+
+/// The tool categories `analysis-only` builds never let the CLI use.
+pub const ANALYSIS_ONLY_DISABLED: &[ToolCategory] =
+    &[ToolCategory::FileWrite, ToolCategory::Execute];
+
+/// A group of built-in Claude Code tools with a similar security impact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCategory {
+    /// Reading and searching files.
+    FileRead,
+    /// Creating and modifying files.
+    FileWrite,
+    /// Running shell commands.
+    Execute,
+    /// Fetching URLs and searching the web.
+    Network,
+    /// Launching sub-agents.
+    Agent,
+}
+
+impl ToolCategory {
+    pub const ALL: &'static [ToolCategory] = &[
+        Self::FileRead,
+        Self::FileWrite,
+        Self::Execute,
+        Self::Network,
+        Self::Agent,
+    ];
+
+    /// The built-in tools in this category.
+    pub fn tools(&self) -> &'static [&'static str] {
+        match self {
+            Self::FileRead => &["Read", "Glob", "Grep", "LS", "NotebookRead"],
+            Self::FileWrite => &["Edit", "MultiEdit", "Write", "NotebookEdit"],
+            Self::Execute => &["Bash", "BashOutput", "KillShell"],
+            Self::Network => &["WebFetch", "WebSearch"],
+            Self::Agent => &["Task"],
+        }
+    }
+
+    /// The category of a tool name or tool pattern such as `Bash(git:*)`.
+    pub fn of(tool: &str) -> Option<Self> {
+        let name = tool.split('(').next().unwrap_or(tool).trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|category| category.tools().contains(&name))
+    }
+}
+
+/// Disables tools by category instead of by name.
+///
+/// Applying the policy adds the tools of the disabled categories to the
+/// disallowed tools and removes them from the allowed tools.
+///
+/// ```rust
+/// use claude_code_sdk::tool_policy::{ToolCategory, ToolPolicy};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new().with_tool_policy(ToolPolicy::disable_categories(&[
+///     ToolCategory::Network,
+///     ToolCategory::FileWrite,
+/// ]));
+/// assert!(options.disallowed_tools.unwrap().contains(&"WebFetch".to_string()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_categories: Vec<ToolCategory>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_tools: Vec<String>,
+}
+
+impl ToolPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The policy `analysis-only` builds enforce: no file writes and no
+    /// commands.
+    pub fn analysis_only() -> Self {
+        Self::disable_categories(ANALYSIS_ONLY_DISABLED)
+    }
+
+    pub fn disable_categories(categories: &[ToolCategory]) -> Self {
+        Self::new().with_disabled_categories(categories)
+    }
+
+    pub fn with_disabled_categories(mut self, categories: &[ToolCategory]) -> Self {
+        for category in categories {
+            if !self.disabled_categories.contains(category) {
+                self.disabled_categories.push(*category);
+            }
+        }
+        self
+    }
+
+    /// Disable a single tool in addition to the disabled categories.
+    pub fn with_disabled_tool<S: Into<String>>(mut self, tool: S) -> Self {
+        self.disabled_tools.push(tool.into());
+        self
+    }
+
+    /// Whether a tool name or tool pattern is allowed by this policy.
+    pub fn is_allowed(&self, tool: &str) -> bool {
+        let name = tool.split('(').next().unwrap_or(tool).trim();
+        if self.disabled_tools.iter().any(|disabled| disabled == name) {
+            return false;
+        }
+        ToolCategory::of(name).map_or(true, |category| {
+            !self.disabled_categories.contains(&category)
+        })
+    }
+
+    /// The tool names the policy disables.
+    pub fn disallowed_tools(&self) -> Vec<String> {
+        let mut tools: Vec<String> = self
+            .disabled_categories
+            .iter()
+            .flat_map(|category| category.tools().iter().map(|tool| tool.to_string()))
+            .collect();
+        for tool in &self.disabled_tools {
+            if !tools.contains(tool) {
+                tools.push(tool.clone());
+            }
+        }
+        tools
+    }
+
+    pub fn apply(&self, mut options: ClaudeCodeOptions) -> ClaudeCodeOptions {
+        if let Some(allowed) = &mut options.allowed_tools {
+            allowed.retain(|tool| self.is_allowed(tool));
+        }
+        let disallowed = options.disallowed_tools.get_or_insert_with(Vec::new);
+        for tool in self.disallowed_tools() {
+            if !disallowed.contains(&tool) {
+                disallowed.push(tool);
+            }
+        }
+        options
+    }
+}
+
+/// Reject options that would let the CLI write files or run commands in an
+/// `analysis-only` build.
+#[cfg(feature = "analysis-only")]
+pub(crate) fn check_analysis_only(options: &ClaudeCodeOptions) -> Result<()> {
+    let policy = ToolPolicy::analysis_only();
+    if let Some(tool) = options
+        .allowed_tools
+        .iter()
+        .flatten()
+        .find(|tool| !policy.is_allowed(tool))
+    {
+        return Err(ClaudeSDKError::tool_disabled(tool.as_str()));
+    }
+    if matches!(
+        options.permission_mode,
+        Some(PermissionMode::AcceptEdits | PermissionMode::BypassPermissions)
+    ) {
+        return Err(ClaudeSDKError::unsupported_option(
+            "permission_mode",
+            "analysis-only builds only run in the default permission mode",
+        ));
+    }
+    Ok(())
+}
+
+/// Stop the stream, and the process behind `guard`, if the CLI calls a tool
+/// `analysis-only` builds disable anyway.
+#[cfg(all(feature = "analysis-only", feature = "subprocess"))]
+pub(crate) fn interrupt_on_disabled_tool(
+    stream: Pin<Box<dyn Stream<Item = Result<Message>> + Send>>,
+    guard: Option<DisposeGuard>,
+) -> Pin<Box<dyn Stream<Item = Result<Message>> + Send>> {
+    let policy = ToolPolicy::analysis_only();
+    Box::pin(stream::unfold(Some((stream, guard)), move |state| {
+        let policy = policy.clone();
+        async move {
+            let (mut stream, guard) = state?;
+            let item = stream.next().await?;
+            let content = match &item {
+                Ok(Message::Assistant(msg)) => msg.content.as_slice(),
+                Ok(Message::User(msg)) => msg.content.as_slice(),
+                _ => &[],
+            };
+            let disabled = content.iter().find_map(|block| match block {
+                ContentBlock::ToolUse(tool_use) if !policy.is_allowed(&tool_use.name) => {
+                    Some(tool_use.name.clone())
+                }
+                _ => None,
+            });
+            match disabled {
+                Some(tool) => {
+                    tracing::warn!(%tool, "interrupting run on a tool disabled in analysis-only builds");
+                    if let Some(guard) = &guard {
+                        let _ = guard.dispose().await;
+                    }
+                    Some((Err(ClaudeSDKError::tool_disabled(tool)), None))
+                }
+                None => Some((item, Some((stream, guard)))),
+            }
+        }
+    }))
+}
@@ -0,0 +1,198 @@
+use super::{ProcessExit, Transport};
+use crate::error::{ClaudeSDKError, Result};
+use crate::prompt::PromptInput;
+use crate::types::{ClaudeCodeOptions, Message, Shared};
+use async_trait::async_trait;
+use futures::stream::{self, Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "subprocess")]
+use std::time::Duration;
+
+/// One step of a [`MockTransport`]'s script.
+#[derive(Debug, Clone)]
+enum Step {
+    Message(Box<Message>),
+    /// Errors cannot be cloned, so each replay makes a new one.
+    Error(Shared<dyn Fn() -> ClaudeSDKError + Send + Sync>),
+    #[cfg(feature = "subprocess")]
+    Delay(Duration),
+}
+
+/// What the application did with a [`MockTransport`] and its clones.
+#[derive(Debug, Default)]
+struct Recorded {
+    prompts: Vec<PromptInput>,
+    controls: Vec<serde_json::Value>,
+    connections: usize,
+}
+
+/// A transport replaying a scripted sequence of messages, for testing
+/// applications built on the SDK without the CLI.
+///
+/// Every transport made by its [`factory`](Self::factory) replays the whole
+/// script; clones share a record of the prompts they were given and the
+/// messages sent to them. The options' message filter applies to the
+/// script as it would to the CLI's output.
+///
+/// ```rust
+/// use claude_code_sdk::transport::MockTransport;
+/// use claude_code_sdk::{
+///     query, AssistantMessage, ClaudeCodeOptions, Message, PromptInput, ResultMessage, TextBlock,
+/// };
+/// use tokio_stream::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mock = MockTransport::new()
+///     .with_message(AssistantMessage::new(vec![TextBlock::new("4").into()]))
+///     .with_message(ResultMessage::new("run-1"));
+/// let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+///
+/// let messages: Vec<Message> = query("What is 2 + 2?", Some(options))
+///     .await?
+///     .collect::<claude_code_sdk::Result<_>>()
+///     .await?;
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(mock.prompts(), vec![PromptInput::from("What is 2 + 2?")]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    script: Vec<Step>,
+    connected: bool,
+    recorded: Arc<Mutex<Recorded>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replay `message` next.
+    pub fn with_message<M: Into<Message>>(mut self, message: M) -> Self {
+        self.script.push(Step::Message(Box::new(message.into())));
+        self
+    }
+
+    pub fn with_messages<I>(mut self, messages: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Message>,
+    {
+        self.script.extend(
+            messages
+                .into_iter()
+                .map(|message| Step::Message(Box::new(message.into()))),
+        );
+        self
+    }
+
+    /// Fail with the error made by `error` next; the stream goes on with
+    /// the rest of the script.
+    pub fn with_error<F>(mut self, error: F) -> Self
+    where
+        F: Fn() -> ClaudeSDKError + Send + Sync + 'static,
+    {
+        self.script.push(Step::Error(Shared(Arc::new(error))));
+        self
+    }
+
+    /// Wait `delay` before replaying the rest of the script, e.g. to let
+    /// another query finish first.
+    #[cfg(feature = "subprocess")]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.script.push(Step::Delay(delay));
+        self
+    }
+
+    /// A factory for
+    /// [`with_transport_factory`](ClaudeCodeOptions::with_transport_factory)
+    /// making transports that replay this script.
+    pub fn factory(
+        &self,
+    ) -> impl Fn(PromptInput, ClaudeCodeOptions) -> Box<dyn Transport> + Send + Sync + 'static {
+        let mock = self.clone();
+        move |prompt, _options| {
+            if !prompt.is_empty() {
+                mock.recorded.lock().unwrap().prompts.push(prompt);
+            }
+            Box::new(MockTransport {
+                connected: false,
+                ..mock.clone()
+            })
+        }
+    }
+
+    /// The prompts the transports were made with and the messages sent to
+    /// them, in order.
+    pub fn prompts(&self) -> Vec<PromptInput> {
+        self.recorded.lock().unwrap().prompts.clone()
+    }
+
+    /// The control requests sent to the transports, in order.
+    pub fn control_requests(&self) -> Vec<serde_json::Value> {
+        self.recorded.lock().unwrap().controls.clone()
+    }
+
+    /// How often a transport sharing this record was connected.
+    pub fn connections(&self) -> usize {
+        self.recorded.lock().unwrap().connections
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn connect(&mut self) -> Result<()> {
+        if !self.connected {
+            self.connected = true;
+            self.recorded.lock().unwrap().connections += 1;
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<Option<ProcessExit>> {
+        self.connected = false;
+        Ok(None)
+    }
+
+    async fn receive_messages(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        if !self.connected {
+            return Err(ClaudeSDKError::cli_connection("Not connected"));
+        }
+        let items = stream::iter(self.script.clone()).filter_map(|step| async move {
+            match step {
+                Step::Message(message) => Some(Ok(*message)),
+                Step::Error(error) => Some(Err(error())),
+                #[cfg(feature = "subprocess")]
+                Step::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    None
+                }
+            }
+        });
+        Ok(Box::pin(items))
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    async fn send_message(&self, prompt: PromptInput) -> Result<()> {
+        if !self.connected {
+            return Err(ClaudeSDKError::cli_connection("Not connected"));
+        }
+        self.recorded.lock().unwrap().prompts.push(prompt);
+        Ok(())
+    }
+
+    async fn send_control(&self, request: serde_json::Value) -> Result<()> {
+        if !self.connected {
+            return Err(ClaudeSDKError::cli_connection("Not connected"));
+        }
+        self.recorded.lock().unwrap().controls.push(request);
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! Transports carrying a conversation between the SDK and Claude Code.
+//!
+//! The [`Transport`] trait itself needs no runtime. The transports are
+//! behind features: [`SubprocessCLITransport`] (`subprocess`) runs the CLI
+//! as a child process, and [`WebSocketTransport`] (`websocket`) talks to a
+//! CLI running elsewhere. [`MockTransport`] replays a script, for tests.
+//!
+//! Queries and [`ClaudeSDKClient`](crate::ClaudeSDKClient) conversations
+//! spawn the CLI unless their options name a [`TransportFactory`], see
+//! [`with_transport_factory`](crate::ClaudeCodeOptions::with_transport_factory).
+
+use crate::compat::OptionWarning;
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::external_tools::ToolResultSender;
+use crate::prompt::PromptInput;
+use crate::types::{ClaudeCodeOptions, Message, Shared};
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::pin::Pin;
+use std::process::ExitStatus;
+
+mod mock;
+#[cfg(feature = "subprocess")]
+mod subprocess;
+#[cfg(feature = "websocket")]
+mod websocket;
+
+pub use mock::MockTransport;
+#[cfg(feature = "subprocess")]
+pub use subprocess::{CliInput, DisposeGuard, SubprocessCLITransport, DEFAULT_SHUTDOWN_TIMEOUT};
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketTransport;
+
+/// Makes the transport of a query from its prompt and options. The prompt
+/// of a [`ClaudeSDKClient`](crate::ClaudeSDKClient) conversation is empty;
+/// its messages are sent with [`Transport::send_message`].
+pub type TransportFactory =
+    Shared<dyn Fn(PromptInput, ClaudeCodeOptions) -> Box<dyn Transport> + Send + Sync>;
+
+/// How a transport's process ended, as returned by
+/// [`Transport::disconnect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessExit {
+    pub status: ExitStatus,
+    /// How the process was stopped, if it did not exit by itself within
+    /// the [shutdown timeout](crate::ClaudeCodeOptions::with_shutdown_timeout).
+    pub forced: Option<Termination>,
+}
+
+impl ProcessExit {
+    /// Whether the process exited by itself and successfully.
+    pub fn is_clean(&self) -> bool {
+        self.forced.is_none() && self.status.success()
+    }
+}
+
+/// How a process that did not exit in time was stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// Exited after being asked to, with `SIGTERM`.
+    Terminated,
+    /// Killed, with `SIGKILL` on Unix.
+    Killed,
+}
+
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn connect(&mut self) -> Result<()>;
+    /// Close the connection, returning how the transport's process ended
+    /// if it has one.
+    async fn disconnect(&mut self) -> Result<Option<ProcessExit>>;
+    async fn receive_messages(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>>;
+    fn is_connected(&self) -> bool;
+
+    /// Send `prompt` as the next user message, if the transport keeps the
+    /// conversation's input open.
+    async fn send_message(&self, _prompt: PromptInput) -> Result<()> {
+        Err(ClaudeSDKError::cli_connection(
+            "This transport does not accept further messages",
+        ))
+    }
+
+    /// Tell the other side that no more messages follow.
+    async fn end_input(&self) {}
+
+    /// Send a [control request](crate::control) such as
+    /// `{"subtype": "set_model", "model": ...}` to the running CLI, if the
+    /// transport speaks the control protocol.
+    async fn send_control(&self, request: serde_json::Value) -> Result<()> {
+        let subtype = request["subtype"].as_str().unwrap_or("control request");
+        Err(ClaudeSDKError::unsupported_option(
+            subtype,
+            "this transport does not accept control requests",
+        ))
+    }
+
+    /// A guard that can reap the transport's process independently of the
+    /// transport itself, if it has one.
+    #[cfg(feature = "subprocess")]
+    fn dispose_guard(&self) -> Option<DisposeGuard> {
+        None
+    }
+
+    /// Options dropped because the CLI does not support them.
+    fn option_warnings(&self) -> Vec<OptionWarning> {
+        Vec::new()
+    }
+
+    /// Where the results of external tool calls go, if the options have
+    /// any external tools.
+    #[cfg(feature = "subprocess")]
+    fn tool_results(&self) -> Option<ToolResultSender> {
+        None
+    }
+
+    /// Whether the CLI asks the SDK before running a tool call, so
+    /// [file locks](crate::file_lock) and the
+    /// [danger detector](crate::danger) refuse it before it runs instead of
+    /// only stopping the run once it is reported.
+    #[cfg(feature = "subprocess")]
+    fn guards_tool_calls(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,999 @@
+use super::{ProcessExit, Termination, Transport};
+use crate::capabilities;
+use crate::compat::{self, OptionWarning};
+use crate::control::{self, ControlChannel, ControlMessage};
+use crate::danger;
+use crate::error::{ClaudeSDKError, ErrorContext, Result};
+use crate::external_tools::ToolResultSender;
+use crate::file_lock;
+use crate::hooks::{self, HookRegistration, ToolSlots};
+use crate::interaction::{self, InteractionKind, Lines};
+use crate::isolation::HomeDir;
+use crate::output::{self, OutputFormat};
+use crate::prompt::PromptInput;
+use crate::protocol;
+use crate::run_id::RunId;
+use crate::sdk_info::SdkInfo;
+use crate::stderr_log::{self, StderrCallback};
+use crate::types::{
+    AssistantMessage, ClaudeCodeOptions, Message, ResultMessage, Shared, SystemMessage, TextBlock,
+};
+use async_trait::async_trait;
+use futures::stream::{self, Stream};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// How long [`disconnect`](Transport::disconnect) waits for the CLI to exit
+/// after closing its input, and again after `SIGTERM`, unless the options
+/// set a [shutdown timeout](ClaudeCodeOptions::with_shutdown_timeout).
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Awaitable cleanup for a CLI process.
+///
+/// `Drop` cannot wait for the process to exit, so dropping a transport only
+/// signals the process and leaves reaping to a background task. Awaiting
+/// [`DisposeGuard::dispose`] or [`DisposeGuard::shutdown`] instead returns
+/// once the process is fully reaped.
+#[derive(Debug, Clone)]
+pub struct DisposeGuard {
+    child: Arc<Mutex<Option<Child>>>,
+    exit: Arc<Mutex<Option<ProcessExit>>>,
+}
+
+impl DisposeGuard {
+    fn new(child: Child) -> Self {
+        Self {
+            child: Arc::new(Mutex::new(Some(child))),
+            exit: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn take(&self) -> Option<Child> {
+        self.child.lock().unwrap().take()
+    }
+
+    fn with_child<T>(&self, f: impl FnOnce(&mut Child) -> T) -> Option<T> {
+        self.child.lock().unwrap().as_mut().map(f)
+    }
+
+    fn record(&self, exit: ProcessExit) -> ProcessExit {
+        *self.exit.lock().unwrap() = Some(exit);
+        exit
+    }
+
+    /// Kill the process if it is still running and wait until it is reaped.
+    pub async fn dispose(&self) -> Result<()> {
+        if let Some(mut child) = self.take() {
+            let forced = match child.try_wait()? {
+                Some(_) => None,
+                None => {
+                    // The process may exit in the meantime, in which case there is nothing to kill
+                    let _ = child.start_kill();
+                    Some(Termination::Killed)
+                }
+            };
+            let status = child.wait().await?;
+            self.record(ProcessExit { status, forced });
+        }
+        Ok(())
+    }
+
+    /// Stop the process gracefully: close `input`, wait up to `timeout` for
+    /// the process to exit, then send it `SIGTERM` and wait as long again
+    /// before killing it.
+    ///
+    /// Returns how the process ended, also when it was reaped before.
+    pub async fn shutdown(
+        &self,
+        input: Option<&CliInput>,
+        timeout: Duration,
+    ) -> Result<Option<ProcessExit>> {
+        let Some(mut child) = self.take() else {
+            return Ok(self.exit());
+        };
+        if let Some(input) = input {
+            input.close().await;
+        }
+        let mut forced = None;
+        let status = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                forced = Some(terminate(&mut child));
+                match tokio::time::timeout(timeout, child.wait()).await {
+                    Ok(status) => status?,
+                    Err(_) => {
+                        tracing::warn!(?timeout, "Claude Code CLI ignored SIGTERM, killing it");
+                        forced = Some(Termination::Killed);
+                        let _ = child.start_kill();
+                        child.wait().await?
+                    }
+                }
+            }
+        };
+        Ok(Some(self.record(ProcessExit { status, forced })))
+    }
+
+    /// How the process ended, once it has been reaped.
+    pub fn exit(&self) -> Option<ProcessExit> {
+        *self.exit.lock().unwrap()
+    }
+
+    /// Whether the process has already been disposed.
+    pub fn is_disposed(&self) -> bool {
+        self.child.lock().unwrap().is_none()
+    }
+}
+
+/// Ask `child` to exit, returning how it is being stopped.
+#[cfg(unix)]
+fn terminate(child: &mut Child) -> Termination {
+    if let Some(pid) = child.id() {
+        // SAFETY: `kill` only sends a signal. The child has not been reaped
+        // yet, so `pid` cannot belong to another process.
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+    Termination::Terminated
+}
+
+/// Ask `child` to exit, returning how it is being stopped. Without signals
+/// there is no gentler way than killing it.
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) -> Termination {
+    let _ = child.start_kill();
+    Termination::Killed
+}
+
+/// The stdin of a CLI process, kept open to send it further stream-json
+/// messages.
+///
+/// Clones write to the same process. Closing the input tells the CLI that
+/// no more messages follow.
+#[derive(Debug, Clone)]
+pub struct CliInput {
+    stdin: Arc<tokio::sync::Mutex<Option<ChildStdin>>>,
+}
+
+impl CliInput {
+    fn new(stdin: ChildStdin) -> Self {
+        Self {
+            stdin: Arc::new(tokio::sync::Mutex::new(Some(stdin))),
+        }
+    }
+
+    /// Write one line of stream-json input.
+    pub async fn send_line(&self, line: &str) -> Result<()> {
+        let mut stdin = self.stdin.lock().await;
+        let Some(writer) = stdin.as_mut() else {
+            return Err(ClaudeSDKError::cli_connection("The CLI's input is closed"));
+        };
+        let written = async {
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await
+        };
+        written.await.map_err(|e| {
+            ClaudeSDKError::cli_connection(format!("Failed to write to the CLI: {}", e))
+        })
+    }
+
+    /// Send `prompt` as the next user message.
+    pub async fn write_message<P: Into<PromptInput>>(&self, prompt: P) -> Result<()> {
+        self.send_line(&prompt.into().to_stream_json()?).await
+    }
+
+    /// Close the input. Closing it again does nothing.
+    pub async fn close(&self) {
+        if let Some(mut stdin) = self.stdin.lock().await.take() {
+            let _ = stdin.shutdown().await;
+        }
+    }
+
+    pub async fn is_closed(&self) -> bool {
+        self.stdin.lock().await.is_none()
+    }
+}
+
+/// Kill `child` and reap it on the current runtime without blocking,
+/// removing its isolated home afterwards.
+fn reap_in_background(mut child: Child, home: Option<Arc<HomeDir>>) {
+    let _ = child.start_kill();
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        runtime.spawn(async move {
+            let _ = child.wait().await;
+            drop(home);
+        });
+    }
+    // Without a runtime the child is dropped here and Tokio's orphan queue reaps it
+}
+
+pub struct SubprocessCLITransport {
+    child: Option<DisposeGuard>,
+    connected: bool,
+    options: ClaudeCodeOptions,
+    prompt: PromptInput,
+    context: ErrorContext,
+    sdk_info: Option<SdkInfo>,
+    option_warnings: Vec<OptionWarning>,
+    stderr_task: Option<JoinHandle<String>>,
+    home: Option<Arc<HomeDir>>,
+    /// Reads its messages from stdin, which stays open for more.
+    interactive: bool,
+    input: Option<CliInput>,
+    tool_results: Option<ToolResultSender>,
+    control: Option<ControlChannel>,
+    /// Tool calls are checked by the SDK's own `PreToolUse` hooks.
+    guarded: bool,
+    tool_slots: Option<ToolSlots>,
+    interaction: Option<InteractionWatch>,
+}
+
+impl SubprocessCLITransport {
+    pub fn new<P: Into<PromptInput>>(prompt: P, options: ClaudeCodeOptions) -> Self {
+        Self {
+            child: None,
+            connected: false,
+            options,
+            prompt: prompt.into(),
+            context: ErrorContext::default(),
+            sdk_info: None,
+            option_warnings: Vec::new(),
+            stderr_task: None,
+            home: None,
+            interactive: false,
+            input: None,
+            tool_results: None,
+            control: None,
+            guarded: false,
+            tool_slots: None,
+            interaction: None,
+        }
+    }
+
+    /// A transport in streaming input mode: the CLI is started with
+    /// `--input-format stream-json`, `prompt` is its first message, and
+    /// stdin stays open for follow-ups sent with
+    /// [`write_message`](Self::write_message).
+    ///
+    /// ```rust,no_run
+    /// use claude_code_sdk::transport::{SubprocessCLITransport, Transport};
+    /// use claude_code_sdk::ClaudeCodeOptions;
+    ///
+    /// # async fn run() -> claude_code_sdk::Result<()> {
+    /// let mut transport =
+    ///     SubprocessCLITransport::streaming("List the crates in this workspace", ClaudeCodeOptions::new());
+    /// transport.connect().await?;
+    /// let messages = transport.receive_messages().await?;
+    /// transport.write_message("Now the binaries").await?;
+    /// transport.close_input().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn streaming<P: Into<PromptInput>>(prompt: P, options: ClaudeCodeOptions) -> Self {
+        let mut transport = Self::new(prompt, options);
+        transport.interactive = true;
+        transport
+    }
+
+    /// A transport in streaming input mode whose messages are all sent
+    /// after connecting, see [`streaming`](Self::streaming).
+    pub fn interactive(options: ClaudeCodeOptions) -> Self {
+        Self::streaming(PromptInput::Blocks(Vec::new()), options)
+    }
+
+    /// The CLI's stdin, while it is kept open for further messages.
+    pub fn input(&self) -> Option<CliInput> {
+        self.input.clone()
+    }
+
+    /// Send `prompt` to the CLI as the next user message. Only transports
+    /// in streaming input mode keep stdin open for this.
+    pub async fn write_message<P: Into<PromptInput>>(&self, prompt: P) -> Result<()> {
+        match &self.input {
+            Some(input) => input.write_message(prompt).await,
+            None => Err(ClaudeSDKError::cli_connection(
+                "The CLI's input is not open; use a streaming transport and connect it first",
+            )),
+        }
+    }
+
+    /// Close the CLI's stdin, telling it no more messages follow. It exits
+    /// once it has answered the ones sent so far.
+    pub async fn close_input(&self) {
+        if let Some(input) = &self.input {
+            input.close().await;
+        }
+    }
+
+    /// Mark the working directory as trusted for the CLI about to start.
+    /// Failing that, the CLI's prompt is still caught and reported.
+    fn trust_working_dir(&self) {
+        let home = self.home.as_ref().map(|home| home.path());
+        let Some(config) = interaction::config_path(&self.options, home) else {
+            tracing::warn!("no home directory to mark the working directory as trusted in");
+            return;
+        };
+        let dir = self
+            .options
+            .cwd
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        if let Err(e) = interaction::trust_folder(&config, &dir) {
+            tracing::warn!(config = %config.display(), error = %e, "failed to mark the working directory as trusted");
+        }
+    }
+
+    fn build_command(&self) -> Result<Command> {
+        self.build_command_with_format(true)
+    }
+
+    fn build_command_with_format(&self, json_output: bool) -> Result<Command> {
+        // In streaming mode even a text prompt goes to stdin
+        let streamed = PromptInput::Blocks(Vec::new());
+        let prompt = if self.interactive {
+            &streamed
+        } else {
+            &self.prompt
+        };
+        let mut cmd = protocol::cli_command_for_input(&self.options, prompt, json_output)?;
+        if let Some(home) = &self.home {
+            home.apply(&mut cmd);
+        }
+        Ok(Command::from(cmd))
+    }
+}
+
+#[async_trait]
+impl Transport for SubprocessCLITransport {
+    async fn connect(&mut self) -> Result<()> {
+        if self.connected {
+            return Ok(());
+        }
+
+        let binary = protocol::cli_binary(&self.options)?;
+        let capabilities = capabilities::probe(&binary).await;
+        self.option_warnings = compat::check_capabilities(&mut self.options, &capabilities)?;
+        let probed = capabilities.version.is_some() && !capabilities.flags.is_empty();
+        let slots = self.options.max_parallel_tools.map(ToolSlots::new);
+        let mut guards = guard_hooks(&mut self.options);
+        guards.extend(slots.iter().flat_map(ToolSlots::hooks));
+        if !guards.is_empty() {
+            if probed && !capabilities.stream_json_input {
+                if slots.is_some() {
+                    return Err(no_callbacks("max_parallel_tools", &[]));
+                }
+                tracing::warn!(
+                    "{}; tool calls are only checked once reported",
+                    NO_CALLBACKS
+                );
+            } else {
+                self.options.hooks.extend(guards);
+                self.guarded = true;
+                self.tool_slots = slots;
+            }
+        }
+        if !self.options.tool_result_hooks.is_empty() {
+            if probed && !capabilities.stream_json_input {
+                return Err(no_callbacks("tool_result_hooks", &[]));
+            }
+            let rewrite = hooks::rewrite_tool_output(self.options.tool_result_hooks.clone());
+            self.options.hooks.push(rewrite);
+        }
+        let mut servers = self.options.sdk_mcp_servers.clone();
+        if let Some(tools) = &self.options.external_tools {
+            if probed
+                && !(capabilities.stream_json_input && capabilities.supports_flag("--mcp-config"))
+            {
+                return Err(no_callbacks("external_tools", &["--mcp-config"]));
+            }
+            let sender = ToolResultSender::default();
+            servers.push(sender.server(tools));
+            self.options.hooks.push(sender.hook());
+            self.tool_results = Some(sender);
+        }
+        let stdin_prompt = if self.interactive {
+            if self.prompt.is_empty() {
+                None
+            } else {
+                Some(self.prompt.to_stream_json()?)
+            }
+        } else if protocol::prompt_on_stdin(&self.options, &self.prompt) {
+            Some(self.prompt.to_stream_json()?)
+        } else {
+            None
+        };
+        let control = self.options.uses_control_protocol();
+        if self.interactive && probed && !capabilities.stream_json_input {
+            return Err(ClaudeSDKError::unsupported_option(
+                "input_format",
+                "the installed CLI cannot read messages from stdin \
+                 (`--input-format stream-json`)",
+            ));
+        }
+        if control && probed && !capabilities.stream_json_input {
+            let option = if self.options.hooks.is_empty() {
+                "sdk_mcp_servers"
+            } else {
+                "hooks"
+            };
+            return Err(no_callbacks(option, &[]));
+        }
+        if !self.options.sdk_mcp_servers.is_empty()
+            && probed
+            && !capabilities.supports_flag("--mcp-config")
+        {
+            return Err(ClaudeSDKError::unsupported_option(
+                "sdk_mcp_servers",
+                "the installed CLI cannot load MCP servers (`--mcp-config`)",
+            ));
+        }
+        if stdin_prompt.is_some() && probed && !capabilities.stream_json_input {
+            return Err(ClaudeSDKError::invalid_options(
+                "Prompts with images or files require a Claude Code CLI that supports \
+                 `--input-format stream-json`",
+            ));
+        }
+
+        // Hooks are registered before the first prompt, SDK MCP servers on
+        // the command line
+        let (initialize, callbacks) = control::initialize(&self.options.hooks);
+        let initialize = if control {
+            Some(serde_json::to_string(&ControlMessage::ControlRequest(
+                initialize,
+            ))?)
+        } else {
+            None
+        };
+
+        if let Some(isolated) = &self.options.isolated_home {
+            self.home = Some(Arc::new(isolated.create(&self.options)?));
+        }
+        if self.options.trust_folder == Some(true) {
+            self.trust_working_dir();
+        }
+        let mut cmd = self.build_command()?;
+        self.context = ErrorContext::from_command(cmd.as_std());
+        let mut child = cmd.spawn().map_err(|e| {
+            ClaudeSDKError::cli_connection(format!("Failed to spawn CLI process: {}", e))
+                .with_context(self.context.clone())
+        })?;
+
+        tracing::debug!(pid = child.id(), "spawned Claude Code CLI");
+        if let Some(mut stdin) = child.stdin.take() {
+            // Closing stdin afterwards tells the CLI the input is complete,
+            // unless more messages or control answers are still to follow
+            let keep_open = self.interactive || control;
+            let written = async {
+                for line in initialize.iter().chain(&stdin_prompt) {
+                    stdin.write_all(line.as_bytes()).await?;
+                    stdin.write_all(b"\n").await?;
+                }
+                if keep_open {
+                    stdin.flush().await?;
+                    Ok(Some(stdin))
+                } else {
+                    stdin.shutdown().await.map(|()| None)
+                }
+            };
+            match written.await {
+                Ok(stdin) => self.input = stdin.map(CliInput::new),
+                Err(e) => {
+                    let _ = child.start_kill();
+                    return Err(ClaudeSDKError::cli_connection(format!(
+                        "Failed to write the prompt to the CLI: {}",
+                        e
+                    ))
+                    .with_context(self.context.clone()));
+                }
+            }
+        }
+        // Conversations can also change their mode or model while they run
+        if let (true, Some(input)) = (control || self.interactive, &self.input) {
+            self.control = Some(ControlChannel::new(input.clone(), callbacks, servers));
+        }
+        let stderr = child.stderr.take();
+        let guard = DisposeGuard::new(child);
+        let watch = InteractionWatch::new(guard.clone());
+        if let Some(stderr) = stderr {
+            let log = self.options.stderr_log.clone().map(|log| {
+                let run_id = *self.options.run_id.get_or_insert_with(RunId::new);
+                (log, run_id)
+            });
+            self.stderr_task = Some(stderr_log::read_stderr(
+                stderr,
+                log,
+                Some(watch.stderr_callback(self.options.stderr_callback.clone())),
+                Some(watch.stall_callback()),
+            ));
+        }
+        self.child = Some(guard);
+        self.interaction = Some(watch);
+        if let Some(binary) = &self.context.binary {
+            self.sdk_info = Some(SdkInfo::collect(binary, &self.options).await);
+        }
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<Option<ProcessExit>> {
+        self.connected = false;
+        let mut exit = None;
+        if let Some(child) = self.child.take() {
+            let timeout = self
+                .options
+                .shutdown_timeout
+                .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+            exit = child.shutdown(self.input.as_ref(), timeout).await?;
+        }
+        self.home = None;
+        self.input = None;
+        if let Some(tool_results) = self.tool_results.take() {
+            tool_results.close();
+        }
+        self.control = None;
+        self.interaction = None;
+        Ok(exit)
+    }
+
+    async fn receive_messages(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        if !self.connected {
+            return Err(ClaudeSDKError::cli_connection("Not connected"));
+        }
+
+        let guard = self
+            .child
+            .clone()
+            .ok_or_else(|| ClaudeSDKError::cli_connection("No child process available"))?;
+        let stdout = guard
+            .with_child(|child| child.stdout.take())
+            .flatten()
+            .ok_or_else(|| {
+                ClaudeSDKError::cli_connection("Failed to get stdout from child process")
+            })?;
+
+        // Other formats may have Claude's own text on lines of their own
+        let watch = self
+            .interaction
+            .clone()
+            .filter(|_| self.options.output_format.unwrap_or_default() == OutputFormat::StreamJson);
+        // Processes the CLI started may keep stdout open after it exited, so
+        // stop once no more output arrives for a while. Output already
+        // written is always read first, however slowly it is consumed. A
+        // line left unfinished for a while is checked for a prompt.
+        let lines_stream = stream::unfold(
+            (Lines::new(stdout), guard, watch.clone()),
+            |(mut lines, guard, watch)| async move {
+                let idle = async {
+                    exited(guard.clone()).await;
+                    tokio::time::sleep(OUTPUT_GRACE).await;
+                };
+                let on_stall = watch.as_ref().map(|watch| {
+                    let watch = watch.clone();
+                    move |line: &str| {
+                        watch.check(line);
+                    }
+                });
+                let line = tokio::select! {
+                    biased;
+                    line = lines.next_line(on_stall.as_ref().map(|f| f as _)) => line.transpose()?,
+                    () = idle => return None,
+                };
+                Some((line, (lines, guard, watch)))
+            },
+        );
+        let raw_tap = self.options.raw_tap.clone();
+        let finishing_tap = raw_tap.clone();
+        let lines_stream = futures::StreamExt::then(lines_stream, move |line_result| {
+            let raw_tap = raw_tap.clone();
+            async move {
+                if let (Some(tap), Ok(line)) = (&raw_tap, &line_result) {
+                    tap.write_line(line).await;
+                }
+                line_result
+            }
+        });
+        let tap_finished = futures::StreamExt::filter_map(
+            stream::once(async move {
+                if let Some(tap) = finishing_tap {
+                    tap.finish().await;
+                }
+            }),
+            |()| async { None },
+        );
+        let lines_stream = lines_stream.chain(tap_finished);
+        // Control requests are answered here and never reach the parser
+        let control = self.control.clone();
+        let lines_stream = futures::StreamExt::filter_map(lines_stream, move |line_result| {
+            let control = control.clone();
+            async move {
+                match (&control, &line_result) {
+                    (Some(control), Ok(line)) if control.handle_line(line).await => None,
+                    _ => Some(line_result),
+                }
+            }
+        });
+
+        let context = self.context.clone();
+        let saw_output = Arc::new(AtomicBool::new(false));
+        let decoded = saw_output.clone();
+        let saw_result = Arc::new(AtomicBool::new(false));
+        let finished_turn = saw_result.clone();
+        let parser = Arc::new(Mutex::new(output::parser(&self.options)));
+        let finishing = parser.clone();
+
+        let sdk_info = self.sdk_info.take().map(Message::SdkInfo);
+
+        let message_stream = futures::StreamExt::flat_map(lines_stream, move |line_result| {
+            let bytes = match line_result {
+                Ok(bytes) => bytes,
+                Err(e) => return stream::iter(vec![Err(ClaudeSDKError::Io(e))]),
+            };
+            let (line, notice) = protocol::decode_utf8_lossy(&bytes);
+            if watch.as_ref().is_some_and(|watch| watch.check(&line)) {
+                return stream::iter(Vec::new());
+            }
+            let parsed = parser.lock().unwrap().parse_line(&line);
+            if parsed.is_ok() {
+                decoded.store(true, Ordering::SeqCst);
+            }
+            stream::iter(protocol::batch(notice, parsed))
+        });
+        let finished = futures::StreamExt::flat_map(
+            stream::once(async move { protocol::batch(None, finishing.lock().unwrap().finish()) }),
+            stream::iter,
+        );
+        let message_stream = message_stream
+            .chain(finished)
+            .filter_map(move |item| match item {
+                Ok(message) => {
+                    // A conversation's process may still fail in a later turn
+                    match &message {
+                        Message::Result(_) => finished_turn.store(true, Ordering::SeqCst),
+                        Message::System(_) => {}
+                        _ => finished_turn.store(false, Ordering::SeqCst),
+                    }
+                    Some(Ok(message))
+                }
+                Err(e) => Some(Err(e.with_context(context.clone()))),
+            });
+
+        let exited = futures::StreamExt::flat_map(
+            stream::once(finish(
+                Exit {
+                    // Streaming input has no plain-text equivalent
+                    prompt: Some(self.prompt.clone()).filter(|_| !self.interactive),
+                    options: self.options.clone(),
+                    child: self.child.clone(),
+                    stderr_task: self.stderr_task.take(),
+                    home: self.home.clone(),
+                    context: self.context.clone(),
+                    interaction: self.interaction.clone(),
+                },
+                saw_output,
+                saw_result,
+            )),
+            stream::iter,
+        );
+
+        let mut messages: Pin<Box<dyn Stream<Item = Result<Message>> + Send>> = Box::pin(
+            stream::iter(sdk_info.map(Ok))
+                .chain(message_stream)
+                .chain(exited),
+        );
+        if let Some(slots) = &self.tool_slots {
+            messages = slots.release_on_results(messages);
+        }
+        if let (Some(_), Some(input), false) = (&self.control, &self.input, self.interactive) {
+            messages = control::close_input_on_result(messages, input.clone());
+        }
+        Ok(messages)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    async fn send_message(&self, prompt: PromptInput) -> Result<()> {
+        self.write_message(prompt).await
+    }
+
+    async fn end_input(&self) {
+        self.close_input().await;
+    }
+
+    async fn send_control(&self, request: serde_json::Value) -> Result<()> {
+        match &self.control {
+            Some(control) => control.request(request).await,
+            None => Err(ClaudeSDKError::unsupported_option(
+                request["subtype"].as_str().unwrap_or("control request"),
+                "the CLI was not started in streaming input mode",
+            )),
+        }
+    }
+
+    fn dispose_guard(&self) -> Option<DisposeGuard> {
+        self.child.clone()
+    }
+
+    fn option_warnings(&self) -> Vec<OptionWarning> {
+        self.option_warnings.clone()
+    }
+
+    fn tool_results(&self) -> Option<ToolResultSender> {
+        self.tool_results.clone()
+    }
+
+    fn guards_tool_calls(&self) -> bool {
+        self.guarded
+    }
+}
+
+/// Why options relying on control requests cannot be used with the
+/// installed CLI.
+const NO_CALLBACKS: &str = "the installed CLI cannot call back into the SDK";
+
+/// The error for `option` when the CLI lacks `--input-format stream-json`,
+/// or one of the further `flags` the option needs.
+fn no_callbacks(option: &str, flags: &[&str]) -> ClaudeSDKError {
+    let flags: Vec<String> = std::iter::once("--input-format stream-json")
+        .chain(flags.iter().copied())
+        .map(|flag| format!("`{}`", flag))
+        .collect();
+    ClaudeSDKError::unsupported_option(option, format!("{} ({})", NO_CALLBACKS, flags.join(", ")))
+}
+
+/// The SDK's own `PreToolUse` hooks the options ask for, refusing tool
+/// calls before the CLI runs them. The slots of
+/// [`max_parallel_tools`](ClaudeCodeOptions::with_max_parallel_tools) are
+/// added by the caller, which keeps them to release on results.
+fn guard_hooks(options: &mut ClaudeCodeOptions) -> Vec<HookRegistration> {
+    let mut hooks: Vec<HookRegistration> = options
+        .danger_detector
+        .clone()
+        .and_then(danger::deny_dangerous_calls)
+        .into_iter()
+        .collect();
+    if let Some(locks) = options.file_locks.clone() {
+        let run_id = *options.run_id.get_or_insert_with(RunId::new);
+        let cwd = options
+            .cwd
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        hooks.push(file_lock::deny_locked_edits(locks, run_id, cwd));
+    }
+    hooks
+}
+
+/// Whether the CLI's stderr says it does not know the `--format` option.
+fn is_unsupported_format_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    let rejected = [
+        "unknown option",
+        "unrecognized option",
+        "unknown argument",
+        "unexpected argument",
+        "invalid option",
+        "unknown flag",
+    ];
+    stderr.contains("format") && rejected.iter().any(|pattern| stderr.contains(pattern))
+}
+
+/// Stops the CLI once it waits for interactive input, keeping what it
+/// asked for the error the stream ends with.
+#[derive(Clone)]
+struct InteractionWatch {
+    guard: DisposeGuard,
+    found: Arc<Mutex<Option<(InteractionKind, String)>>>,
+}
+
+impl InteractionWatch {
+    fn new(guard: DisposeGuard) -> Self {
+        Self {
+            guard,
+            found: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Whether `line` is an interactive prompt, stopping the CLI if so.
+    fn check(&self, line: &str) -> bool {
+        let Some(kind) = InteractionKind::from_line(line) else {
+            return false;
+        };
+        tracing::warn!(%kind, line, "Claude Code CLI is waiting for interactive input");
+        self.found
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| (kind, line.trim().to_string()));
+        self.guard.with_child(|child| child.start_kill());
+        true
+    }
+
+    /// Check each line of stderr before passing it on to `callback`.
+    fn stderr_callback(&self, callback: Option<StderrCallback>) -> StderrCallback {
+        let watch = self.clone();
+        Shared(Arc::new(move |line: &str| {
+            watch.check(line);
+            if let Some(callback) = &callback {
+                callback(line);
+            }
+        }))
+    }
+
+    /// Check an unfinished line of stderr, which the stderr callback only
+    /// gets once it ends.
+    fn stall_callback(&self) -> StderrCallback {
+        let watch = self.clone();
+        Shared(Arc::new(move |line: &str| {
+            watch.check(line);
+        }))
+    }
+
+    fn take(&self) -> Option<ClaudeSDKError> {
+        let (kind, prompt) = self.found.lock().unwrap().take()?;
+        Some(ClaudeSDKError::interaction_required(kind, prompt))
+    }
+}
+
+/// How long to wait for more of stdout and stderr once the CLI has exited
+/// and nothing is left to read. Processes the CLI started may still hold
+/// the pipes open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// How often [`exited`] checks on the CLI.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Resolve once the CLI has exited or been reaped, whether or not its
+/// stdout has closed.
+///
+/// The guard shares the process with disposal, so it is polled rather than
+/// waited on.
+async fn exited(guard: DisposeGuard) {
+    while let Some(Ok(None)) = guard.with_child(|child| child.try_wait()) {
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+/// What is needed to reap the CLI once it exits.
+struct Exit {
+    prompt: Option<PromptInput>,
+    options: ClaudeCodeOptions,
+    child: Option<DisposeGuard>,
+    stderr_task: Option<JoinHandle<String>>,
+    home: Option<Arc<HomeDir>>,
+    context: ErrorContext,
+    interaction: Option<InteractionWatch>,
+}
+
+/// Reap the CLI once its stdout closes or it exits.
+///
+/// A CLI that fails before its result message, or before the result of the
+/// current turn in a conversation, becomes a [`ClaudeSDKError::Process`]
+/// with the tail of its stderr rather than a silent end. One that
+/// rejected `--format json` without any output is re-run in plain-text
+/// mode instead. Nothing is reported if the transport already disposed of
+/// the process.
+async fn finish(
+    exit: Exit,
+    saw_output: Arc<AtomicBool>,
+    saw_result: Arc<AtomicBool>,
+) -> Vec<Result<Message>> {
+    let Some(guard) = exit.child else {
+        return Vec::new();
+    };
+    let Some(mut child) = guard.take() else {
+        return Vec::new();
+    };
+    let status = match child.wait().await {
+        Ok(status) => status,
+        Err(e) => return vec![Err(e.into())],
+    };
+    // Wait for stderr even after a success, so the run's log is complete
+    // once the stream ends
+    let stderr = match exit.stderr_task {
+        Some(task) => tokio::time::timeout(OUTPUT_GRACE, task)
+            .await
+            .ok()
+            .and_then(|tail| tail.ok())
+            .unwrap_or_default(),
+        None => String::new(),
+    };
+    let interaction = exit.interaction.and_then(|watch| watch.take());
+    // Kept for `disconnect`, which finds the process already reaped
+    guard.record(ProcessExit {
+        status,
+        forced: interaction.as_ref().map(|_| Termination::Killed),
+    });
+    if let Some(error) = interaction {
+        return vec![Err(error.with_context(exit.context))];
+    }
+    if status.success() || saw_result.load(Ordering::SeqCst) {
+        return Vec::new();
+    }
+    // If the CLI produced no JSON at all it may not support `--format json`.
+    // A CLI without JSON output cannot read stream-json input either.
+    let fallback = exit
+        .prompt
+        .filter(|prompt| !protocol::prompt_on_stdin(&exit.options, prompt))
+        .filter(|_| !saw_output.load(Ordering::SeqCst) && is_unsupported_format_error(&stderr));
+    if let Some(prompt) = fallback {
+        return text_fallback(prompt, exit.options, exit.home).await;
+    }
+    tracing::debug!(exit_code = ?status.code(), "Claude Code CLI failed before its result");
+    vec![Err(ClaudeSDKError::process(
+        status.code().unwrap_or(-1),
+        stderr.trim_end(),
+    )
+    .with_context(exit.context))]
+}
+
+/// Re-run the query in plain-text mode after the CLI rejected
+/// `--format json`.
+///
+/// The raw text output is wrapped into a synthetic assistant message,
+/// preceded by a system message warning about the missing capability.
+async fn text_fallback(
+    prompt: PromptInput,
+    options: ClaudeCodeOptions,
+    home: Option<Arc<HomeDir>>,
+) -> Vec<Result<Message>> {
+    tracing::warn!("Claude Code CLI does not support --format json, falling back to text mode");
+    let run_id = options.run_id;
+    let mut transport = SubprocessCLITransport::new(prompt, options);
+    transport.home = home;
+    let output = match transport.build_command_with_format(false) {
+        Ok(mut cmd) => cmd.kill_on_drop(true).output().await,
+        Err(e) => return vec![Err(e)],
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return vec![Err(e.into())],
+    };
+
+    let mut messages = vec![Ok(SystemMessage::new(
+        "The installed Claude Code CLI does not support JSON output (--format json); \
+         falling back to plain-text mode. Tool use, usage and cost details are unavailable.",
+    )
+    .into())];
+
+    let text = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    if !text.is_empty() {
+        messages.push(Ok(
+            AssistantMessage::new(vec![TextBlock::new(text).into()]).into()
+        ));
+    }
+
+    if output.status.success() {
+        let id = run_id.map(|id| id.to_string()).unwrap_or_default();
+        let mut result = ResultMessage::new(id);
+        result.exit_code = output.status.code();
+        messages.push(Ok(result.into()));
+    } else {
+        messages.push(Err(ClaudeSDKError::process(
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr),
+        )));
+    }
+    messages
+}
+
+impl Drop for SubprocessCLITransport {
+    fn drop(&mut self) {
+        let home = self.home.take();
+        if let Some(child) = self.child.take().and_then(|guard| guard.take()) {
+            reap_in_background(child, home);
+        }
+    }
+}
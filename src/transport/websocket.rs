@@ -0,0 +1,167 @@
+use super::{ProcessExit, Transport};
+use crate::error::{ClaudeSDKError, Result};
+use crate::output;
+use crate::prompt::PromptInput;
+use crate::protocol;
+use crate::types::{ClaudeCodeOptions, Message};
+use async_trait::async_trait;
+use futures::stream::{self, SplitSink, SplitStream, Stream, StreamExt};
+use futures::SinkExt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message as Frame;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A transport talking to a Claude Code CLI elsewhere over a WebSocket,
+/// e.g. through a remote runner that pipes the frames to the CLI's stdin
+/// and its stdout back.
+///
+/// Frames carry stream-json, the same lines the CLI reads and writes with
+/// `--input-format stream-json`: the prompt and follow-ups are sent as user
+/// messages, and every text frame received holds one or more lines of
+/// output. Options that start or configure the CLI process do not apply;
+/// the output format and message filter of the options do.
+///
+/// ```rust,no_run
+/// use claude_code_sdk::transport::{Transport, WebSocketTransport};
+/// use claude_code_sdk::ClaudeCodeOptions;
+/// use futures::StreamExt;
+///
+/// # async fn run() -> claude_code_sdk::Result<()> {
+/// let mut transport = WebSocketTransport::new(
+///     "wss://runner.example.com/claude",
+///     "Summarize the open pull requests",
+///     ClaudeCodeOptions::new(),
+/// );
+/// transport.connect().await?;
+/// let mut messages = transport.receive_messages().await?;
+/// while let Some(message) = messages.next().await {
+///     println!("{:?}", message?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct WebSocketTransport {
+    url: String,
+    prompt: PromptInput,
+    options: ClaudeCodeOptions,
+    sink: Option<Arc<tokio::sync::Mutex<SplitSink<Socket, Frame>>>>,
+    frames: Option<SplitStream<Socket>>,
+}
+
+impl WebSocketTransport {
+    pub fn new<U, P>(url: U, prompt: P, options: ClaudeCodeOptions) -> Self
+    where
+        U: Into<String>,
+        P: Into<PromptInput>,
+    {
+        Self {
+            url: url.into(),
+            prompt: prompt.into(),
+            options,
+            sink: None,
+            frames: None,
+        }
+    }
+
+    /// Send `prompt` as the next user message.
+    pub async fn write_message<P: Into<PromptInput>>(&self, prompt: P) -> Result<()> {
+        let Some(sink) = &self.sink else {
+            return Err(ClaudeSDKError::cli_connection("Not connected"));
+        };
+        send(sink, prompt.into().to_stream_json()?).await
+    }
+}
+
+async fn send(sink: &tokio::sync::Mutex<SplitSink<Socket, Frame>>, line: String) -> Result<()> {
+    sink.lock()
+        .await
+        .send(Frame::Text(line))
+        .await
+        .map_err(|e| ClaudeSDKError::cli_connection(format!("Failed to send to the CLI: {}", e)))
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn connect(&mut self) -> Result<()> {
+        if self.sink.is_some() {
+            return Ok(());
+        }
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .map_err(|e| {
+                ClaudeSDKError::cli_connection(format!("Failed to connect to {}: {}", self.url, e))
+            })?;
+        tracing::debug!(url = %self.url, "connected to Claude Code over a WebSocket");
+        let (sink, frames) = socket.split();
+        let sink = Arc::new(tokio::sync::Mutex::new(sink));
+        if !self.prompt.is_empty() {
+            send(&sink, self.prompt.to_stream_json()?).await?;
+        }
+        self.sink = Some(sink);
+        self.frames = Some(frames);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<Option<ProcessExit>> {
+        self.frames = None;
+        if let Some(sink) = self.sink.take() {
+            // The peer may already have closed the connection
+            let _ = sink.lock().await.close().await;
+        }
+        Ok(None)
+    }
+
+    async fn receive_messages(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        let frames = self
+            .frames
+            .take()
+            .ok_or_else(|| ClaudeSDKError::cli_connection("Not connected"))?;
+        let parser = Arc::new(Mutex::new(output::parser(&self.options)));
+        let finishing = parser.clone();
+
+        let messages = frames
+            .take_while(|frame| futures::future::ready(!matches!(frame, Ok(Frame::Close(_)))))
+            .flat_map(move |frame| {
+                let bytes = match frame {
+                    Ok(Frame::Text(text)) => text.into_bytes(),
+                    Ok(Frame::Binary(bytes)) => bytes,
+                    Ok(_) => return stream::iter(Vec::new()),
+                    Err(e) => {
+                        return stream::iter(vec![Err(ClaudeSDKError::cli_connection(format!(
+                            "WebSocket error: {}",
+                            e
+                        )))])
+                    }
+                };
+                let mut parser = parser.lock().unwrap();
+                let items: Vec<Result<Message>> = bytes
+                    .split(|byte| *byte == b'\n')
+                    .filter(|line| !line.is_empty())
+                    .flat_map(|line| {
+                        let (line, notice) = protocol::decode_utf8_lossy(line);
+                        protocol::batch(notice, parser.parse_line(&line))
+                    })
+                    .collect();
+                stream::iter(items)
+            });
+        let finished =
+            stream::once(async move { protocol::batch(None, finishing.lock().unwrap().finish()) })
+                .flat_map(stream::iter);
+
+        Ok(Box::pin(messages.chain(finished)))
+    }
+
+    fn is_connected(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    async fn send_message(&self, prompt: PromptInput) -> Result<()> {
+        self.write_message(prompt).await
+    }
+}
@@ -0,0 +1,462 @@
+//! Ratatui widgets for building agent dashboards on the message stream.
+//!
+//! [`Dashboard`] accumulates the state shown by the widgets. Feed it every
+//! item of a query's stream (and optionally its tool progress events), then
+//! render [`MessageList`], [`ToolActivityPanel`] and [`CostFooter`] wherever
+//! they fit, or the default layout with [`Dashboard::render`].
+//!
+//! ```rust,no_run
+//! use claude_code_sdk::tui::{Dashboard, TerminalGuard};
+//! use claude_code_sdk::query;
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut terminal = TerminalGuard::new();
+//! let mut dashboard = Dashboard::new("List the files in src/");
+//! let mut stream = query("List the files in src/", None).await?;
+//! while let Some(item) = stream.next().await {
+//!     dashboard.push_result(&item);
+//!     terminal.draw(|frame| dashboard.render(frame))?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ClaudeSDKError, Result};
+use crate::progress::ToolProgressEvent;
+use crate::types::{ContentBlock, Message, Usage};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Widget};
+use ratatui::{DefaultTerminal, Frame};
+use std::ops::{Deref, DerefMut};
+
+/// Who an entry of the message list comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    User,
+    Assistant,
+    System,
+    Error,
+}
+
+impl EntryKind {
+    fn label(self) -> &'static str {
+        match self {
+            EntryKind::User => "you",
+            EntryKind::Assistant => "claude",
+            EntryKind::System => "system",
+            EntryKind::Error => "error",
+        }
+    }
+
+    fn style(self) -> Style {
+        let color = match self {
+            EntryKind::User => Color::Cyan,
+            EntryKind::Assistant => Color::Green,
+            EntryKind::System => Color::DarkGray,
+            EntryKind::Error => Color::Red,
+        };
+        Style::default().fg(color).add_modifier(Modifier::BOLD)
+    }
+}
+
+/// One entry of the message list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: EntryKind,
+    pub text: String,
+}
+
+/// State of a tool call in the activity panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A tool call and its latest progress.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolActivity {
+    pub id: String,
+    pub name: String,
+    pub status: ToolStatus,
+    /// The last line of progress output or status text, if any.
+    pub detail: Option<String>,
+}
+
+/// The state rendered by the dashboard widgets.
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    entries: Vec<Entry>,
+    tools: Vec<ToolActivity>,
+    usage: Usage,
+    turns: i32,
+    finished: bool,
+    /// The text of the assistant message being streamed, until it arrives
+    /// whole.
+    partial: String,
+}
+
+impl Dashboard {
+    /// Create a dashboard showing `prompt` as the first user entry.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        let mut dashboard = Self::default();
+        dashboard.push_entry(EntryKind::User, prompt.into());
+        dashboard
+    }
+
+    /// Record an item of the message stream.
+    pub fn push_result(&mut self, item: &Result<Message>) {
+        match item {
+            Ok(message) => self.push(message),
+            Err(error) => self.push_error(error),
+        }
+    }
+
+    /// Record a message.
+    pub fn push(&mut self, message: &Message) {
+        match message {
+            Message::User(msg) => self.push_blocks(EntryKind::User, &msg.content),
+            Message::Assistant(msg) => {
+                if msg.parent_tool_use_id.is_none() {
+                    self.partial.clear();
+                }
+                self.push_blocks(EntryKind::Assistant, &msg.content)
+            }
+            Message::System(msg) => self.push_entry(EntryKind::System, msg.content.clone()),
+            Message::Result(result) => {
+                self.usage.add_result(result);
+                self.turns += result.num_turns.unwrap_or(1);
+                self.finished = true;
+            }
+            Message::StreamEvent(event) if event.parent_tool_use_id.is_none() => {
+                if let Some(text) = event.text_delta() {
+                    self.partial.push_str(text);
+                } else if event.event_type() == Some("message_start") {
+                    self.partial.clear();
+                }
+            }
+            Message::SdkInfo(_) | Message::StreamEvent(_) => {}
+        }
+    }
+
+    /// Record a tool progress event, as delivered to a progress callback.
+    pub fn push_progress(&mut self, event: &ToolProgressEvent) {
+        let detail = event
+            .output
+            .as_deref()
+            .and_then(|output| output.lines().rev().find(|line| !line.trim().is_empty()))
+            .or(event.message.as_deref())
+            .map(str::to_string);
+        match self
+            .tools
+            .iter_mut()
+            .find(|tool| tool.id == event.tool_use_id)
+        {
+            Some(tool) => {
+                if detail.is_some() {
+                    tool.detail = detail;
+                }
+            }
+            None => self.tools.push(ToolActivity {
+                id: event.tool_use_id.clone(),
+                name: event.tool_name.clone().unwrap_or_default(),
+                status: ToolStatus::Running,
+                detail,
+            }),
+        }
+    }
+
+    /// Record an error from the message stream.
+    pub fn push_error(&mut self, error: &ClaudeSDKError) {
+        self.push_entry(EntryKind::Error, error.to_string());
+        self.finished = true;
+    }
+
+    fn push_blocks(&mut self, kind: EntryKind, blocks: &[ContentBlock]) {
+        for block in blocks {
+            match block {
+                ContentBlock::Text(text) => self.push_entry(kind, text.text.clone()),
+                ContentBlock::ToolUse(tool_use) => self.tools.push(ToolActivity {
+                    id: tool_use.id.clone(),
+                    name: tool_use.name.clone(),
+                    status: ToolStatus::Running,
+                    detail: None,
+                }),
+                ContentBlock::ToolResult(result) => {
+                    if let Some(tool) = self
+                        .tools
+                        .iter_mut()
+                        .find(|tool| tool.id == result.tool_use_id)
+                    {
+                        tool.status = if result.is_error.unwrap_or(false) {
+                            ToolStatus::Failed
+                        } else {
+                            ToolStatus::Succeeded
+                        };
+                    }
+                }
+                ContentBlock::Image(_) => self.push_entry(kind, "[image]".to_string()),
+                ContentBlock::Thinking(_) | ContentBlock::RedactedThinking(_) => {}
+            }
+        }
+    }
+
+    fn push_entry(&mut self, kind: EntryKind, text: String) {
+        if !text.trim().is_empty() {
+            self.entries.push(Entry { kind, text });
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The text streamed so far of the assistant message being written,
+    /// shown after the entries. Only set with
+    /// [`with_include_partial_messages`](crate::ClaudeCodeOptions::with_include_partial_messages).
+    pub fn partial_text(&self) -> Option<&str> {
+        (!self.partial.is_empty()).then_some(self.partial.as_str())
+    }
+
+    pub fn tools(&self) -> &[ToolActivity] {
+        &self.tools
+    }
+
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Whether the stream has delivered its result or failed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn message_list(&self) -> MessageList<'_> {
+        MessageList::new(self)
+    }
+
+    pub fn tool_panel(&self) -> ToolActivityPanel<'_> {
+        ToolActivityPanel::new(self)
+    }
+
+    pub fn cost_footer(&self) -> CostFooter<'_> {
+        CostFooter::new(self)
+    }
+
+    /// Render the default layout: messages and tool activity side by side
+    /// above a one-line cost footer.
+    pub fn render(&self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [messages, tools] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .areas(main);
+        frame.render_widget(self.message_list(), messages);
+        frame.render_widget(self.tool_panel(), tools);
+        frame.render_widget(self.cost_footer(), footer);
+    }
+}
+
+/// The conversation so far, scrolled to keep the latest lines visible.
+#[derive(Debug, Clone)]
+pub struct MessageList<'a> {
+    dashboard: &'a Dashboard,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> MessageList<'a> {
+    pub fn new(dashboard: &'a Dashboard) -> Self {
+        Self {
+            dashboard,
+            block: Some(Block::default().borders(Borders::ALL).title(" Messages ")),
+        }
+    }
+
+    /// Replace the surrounding block, or remove it with `None`.
+    pub fn block(mut self, block: Option<Block<'a>>) -> Self {
+        self.block = block;
+        self
+    }
+}
+
+impl Widget for MessageList<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines = Vec::new();
+        let partial = self.dashboard.partial_text().map(|text| Entry {
+            kind: EntryKind::Assistant,
+            text: text.to_string(),
+        });
+        for entry in self.dashboard.entries.iter().chain(&partial) {
+            for (i, text) in entry.text.lines().enumerate() {
+                let label = if i == 0 { entry.kind.label() } else { "" };
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{:>7} ", label), entry.kind.style()),
+                    Span::raw(text.to_string()),
+                ]));
+            }
+        }
+
+        let inner_height = match &self.block {
+            Some(block) => block.inner(area).height,
+            None => area.height,
+        };
+        let scroll = lines.len().saturating_sub(inner_height as usize);
+        let lines = lines.split_off(scroll);
+
+        let mut paragraph = Paragraph::new(lines);
+        if let Some(block) = self.block {
+            paragraph = paragraph.block(block);
+        }
+        paragraph.render(area, buf);
+    }
+}
+
+/// Tool calls with their status and latest progress, newest at the bottom.
+#[derive(Debug, Clone)]
+pub struct ToolActivityPanel<'a> {
+    dashboard: &'a Dashboard,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> ToolActivityPanel<'a> {
+    pub fn new(dashboard: &'a Dashboard) -> Self {
+        Self {
+            dashboard,
+            block: Some(Block::default().borders(Borders::ALL).title(" Tools ")),
+        }
+    }
+
+    /// Replace the surrounding block, or remove it with `None`.
+    pub fn block(mut self, block: Option<Block<'a>>) -> Self {
+        self.block = block;
+        self
+    }
+}
+
+impl Widget for ToolActivityPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines = Vec::new();
+        for tool in &self.dashboard.tools {
+            let (symbol, color) = match tool.status {
+                ToolStatus::Running => ("…", Color::Yellow),
+                ToolStatus::Succeeded => ("✓", Color::Green),
+                ToolStatus::Failed => ("✗", Color::Red),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", symbol), Style::default().fg(color)),
+                Span::styled(
+                    tool.name.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ]));
+            if let (ToolStatus::Running, Some(detail)) = (tool.status, &tool.detail) {
+                lines.push(Line::styled(
+                    format!("  {}", detail),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+        }
+
+        let inner_height = match &self.block {
+            Some(block) => block.inner(area).height,
+            None => area.height,
+        };
+        let scroll = lines.len().saturating_sub(inner_height as usize);
+        let lines = lines.split_off(scroll);
+
+        let mut paragraph = Paragraph::new(lines);
+        if let Some(block) = self.block {
+            paragraph = paragraph.block(block);
+        }
+        paragraph.render(area, buf);
+    }
+}
+
+/// A single line with token counts, cost and turn count.
+#[derive(Debug, Clone)]
+pub struct CostFooter<'a> {
+    dashboard: &'a Dashboard,
+}
+
+impl<'a> CostFooter<'a> {
+    pub fn new(dashboard: &'a Dashboard) -> Self {
+        Self { dashboard }
+    }
+}
+
+impl Widget for CostFooter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let usage = &self.dashboard.usage;
+        let status = if self.dashboard.finished {
+            "done"
+        } else {
+            "running"
+        };
+        let line = Line::from(vec![
+            Span::styled(
+                format!(" {} ", status),
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(if self.dashboard.finished {
+                        Color::Green
+                    } else {
+                        Color::Yellow
+                    }),
+            ),
+            Span::raw(format!(
+                " in {} · out {} · ${:.4} · {} turn(s)",
+                usage.tokens_input, usage.tokens_output, usage.cost_usd, self.dashboard.turns
+            )),
+        ]);
+        Paragraph::new(line).render(area, buf);
+    }
+}
+
+/// A terminal in raw mode on the alternate screen, restored when dropped.
+///
+/// The terminal is also restored if the program panics, so an interrupted
+/// or crashing dashboard never leaves the user's shell in raw mode. In raw
+/// mode Ctrl-C arrives as a key event instead of a signal; applications
+/// should handle it and let the guard drop.
+pub struct TerminalGuard {
+    terminal: DefaultTerminal,
+}
+
+impl TerminalGuard {
+    pub fn new() -> Self {
+        Self {
+            terminal: ratatui::init(),
+        }
+    }
+}
+
+impl Default for TerminalGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TerminalGuard {
+    type Target = DefaultTerminal;
+
+    fn deref(&self) -> &Self::Target {
+        &self.terminal
+    }
+}
+
+impl DerefMut for TerminalGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.terminal
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
@@ -0,0 +1,206 @@
+//! Retrying failed, refused or empty turns with an adapted prompt.
+
+use crate::error::ClaudeSDKError;
+use crate::prompt::PromptInput;
+use crate::refusal::Refusal;
+use crate::types::{ContentBlock, Message, Shared};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// Why a turn is considered unsuccessful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnFailureKind {
+    /// The query failed with an error or a non-zero exit code.
+    Error,
+    /// Claude declined the request, see [`Refusal`].
+    Refused,
+    /// Claude produced no text.
+    Empty,
+}
+
+impl fmt::Display for TurnFailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "failed",
+            Self::Refused => "was refused",
+            Self::Empty => "produced no text",
+        })
+    }
+}
+
+/// An unsuccessful turn, handed to the prompt adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnFailure {
+    pub kind: TurnFailureKind,
+    /// The attempt that failed, starting at 1 for the original prompt.
+    pub attempt: u32,
+    /// The error message or the refusal text.
+    pub detail: Option<String>,
+}
+
+/// Which unsuccessful turns are retried, and how often.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnRetryPolicy {
+    pub max_retries: u32,
+    pub retry_errors: bool,
+    pub retry_refusals: bool,
+    pub retry_empty: bool,
+}
+
+impl Default for TurnRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            retry_errors: true,
+            retry_refusals: true,
+            retry_empty: true,
+        }
+    }
+}
+
+impl TurnRetryPolicy {
+    /// Retry every kind of failure up to `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    pub fn retries(&self, kind: TurnFailureKind) -> bool {
+        match kind {
+            TurnFailureKind::Error => self.retry_errors,
+            TurnFailureKind::Refused => self.retry_refusals,
+            TurnFailureKind::Empty => self.retry_empty,
+        }
+    }
+}
+
+/// Rewrites the prompt of an unsuccessful turn for the next attempt;
+/// `None` gives up.
+pub type PromptAdapter =
+    Shared<dyn Fn(&TurnFailure, &PromptInput) -> Option<PromptInput> + Send + Sync>;
+
+/// The turn retry set with
+/// [`with_turn_retry`](crate::ClaudeCodeOptions::with_turn_retry).
+///
+/// When a query fails, is refused or produces no text, the query is run
+/// again, in a new session, with the prompt returned by the adapter. Each
+/// retry is announced with a system message. Errors of a turn that will be
+/// retried are not delivered; when the retries run out they are delivered
+/// at the end of the last turn. Retries run without the idempotency key, so
+/// a stored result is that of the first attempt.
+///
+/// ```rust
+/// use claude_code_sdk::turn_retry::{TurnFailureKind, TurnRetryPolicy};
+/// use claude_code_sdk::{ClaudeCodeOptions, PromptInput};
+///
+/// let options = ClaudeCodeOptions::new().with_turn_retry(TurnRetryPolicy::new(2), |failure, prompt| {
+///     let hint = match failure.kind {
+///         TurnFailureKind::Refused => "Only describe the code; do not change anything.",
+///         _ => "Be concise and reduce the scope if needed.",
+///     };
+///     Some(PromptInput::Text(format!("{}\n\n{}", prompt.text_content(), hint)))
+/// });
+/// ```
+#[derive(Clone)]
+pub struct TurnRetry {
+    pub policy: TurnRetryPolicy,
+    pub adapter: PromptAdapter,
+}
+
+impl TurnRetry {
+    pub fn new<F>(policy: TurnRetryPolicy, adapter: F) -> Self
+    where
+        F: Fn(&TurnFailure, &PromptInput) -> Option<PromptInput> + Send + Sync + 'static,
+    {
+        Self {
+            policy,
+            adapter: Shared(Arc::new(adapter)),
+        }
+    }
+
+    /// The prompt for the retry of `failure`, or `None` if it is not
+    /// retried.
+    pub fn adapt(&self, failure: &TurnFailure, prompt: &PromptInput) -> Option<PromptInput> {
+        if failure.attempt > self.policy.max_retries || !self.policy.retries(failure.kind) {
+            return None;
+        }
+        (self.adapter)(failure, prompt)
+    }
+}
+
+impl fmt::Debug for TurnRetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TurnRetry")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a turn produced so far, to classify it once it ends.
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+#[derive(Debug, Default)]
+pub(crate) struct TurnOutcome {
+    produced_text: bool,
+    refusal: Option<String>,
+    failure: Option<String>,
+}
+
+#[cfg_attr(not(feature = "subprocess"), allow(dead_code))]
+impl TurnOutcome {
+    pub(crate) fn observe(&mut self, message: &Message) {
+        let content = match message {
+            Message::Assistant(msg) => &msg.content,
+            Message::Result(result) => {
+                if let Some(code) = result.exit_code.filter(|code| *code != 0) {
+                    self.failure
+                        .get_or_insert_with(|| format!("exit code {}", code));
+                }
+                return;
+            }
+            _ => return,
+        };
+        let text: Vec<&str> = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(block) => Some(block.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        let text = text.join("\n");
+        if text.trim().is_empty() {
+            return;
+        }
+        if !self.produced_text {
+            if let Some(refusal) = Refusal::from_text(&text) {
+                self.refusal = Some(refusal.text);
+            }
+        }
+        self.produced_text = true;
+    }
+
+    pub(crate) fn fail(&mut self, error: &ClaudeSDKError) {
+        self.failure.get_or_insert_with(|| error.root().to_string());
+    }
+
+    /// The failure of the finished turn, if it was unsuccessful.
+    pub(crate) fn failure(&self, attempt: u32) -> Option<TurnFailure> {
+        let (kind, detail) = if let Some(error) = &self.failure {
+            (TurnFailureKind::Error, Some(error.clone()))
+        } else if let Some(refusal) = &self.refusal {
+            (TurnFailureKind::Refused, Some(refusal.clone()))
+        } else if !self.produced_text {
+            (TurnFailureKind::Empty, None)
+        } else {
+            return None;
+        };
+        Some(TurnFailure {
+            kind,
+            attempt,
+            detail,
+        })
+    }
+}
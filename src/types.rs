@@ -1,6 +1,61 @@
-use serde::{Deserialize, Serialize};
+use crate::analytics::UsageLog;
+#[cfg(feature = "subprocess")]
+use crate::archive::SessionArchive;
+use crate::compat::CompatMode;
+use crate::danger::DangerousCommandDetector;
+use crate::diagnostics::DecodeDiagnostics;
+use crate::error::{ClaudeSDKError, Result};
+use crate::external_tools::{self, ExternalTool};
+use crate::file_lock::WorkspaceLocks;
+#[cfg(feature = "file-patch")]
+use crate::file_patch::{FilePatch, FilePatchCallback};
+use crate::filter::MessageFilter;
+use crate::hooks::{
+    HookEvent, HookInput, HookOutput, HookRegistration, ToolResultContext, ToolResultHook,
+};
+use crate::idempotency::{IdempotencyStore, IdempotencyStoreRef};
+use crate::isolation::IsolatedHome;
+use crate::key_rotation::KeyRotation;
+use crate::key_router::KeyRouter;
+use crate::language::LanguageTag;
+use crate::middleware::{Middleware, MiddlewareRef};
+use crate::output::OutputFormat;
+use crate::overlay::{OverlayProvider, OverlayProviderRef};
+use crate::progress::{ProgressCallback, ToolProgressEvent};
+use crate::prompt::{image_media_type, MediaSource, PromptInput};
+use crate::provider::Provider;
+use crate::proxy::ProxyConfig;
+use crate::refusal::{Refusal, RefusalCallback};
+use crate::retry::RetryPolicy;
+use crate::run_id::RunId;
+#[cfg(feature = "sandbox-linux")]
+use crate::sandbox::SandboxPolicy;
+use crate::sdk_info::SdkInfo;
+use crate::sdk_mcp::SdkMcpServer;
+use crate::speech::{TextChunk, TextChunkCallback};
+use crate::stats::StatsRegistry;
+use crate::stderr_log::{StderrCallback, StderrLog};
+#[cfg(feature = "subprocess")]
+use crate::tap::RawTap;
+use crate::tool_policy::ToolPolicy;
+use crate::transport::{Transport, TransportFactory};
+use crate::turn_retry::{TurnFailure, TurnRetry, TurnRetryPolicy};
+use crate::update::{UpdateCallback, UpdateEvent};
+use crate::verify::{Verifier, VerifierRef};
+#[cfg(feature = "webhooks")]
+use crate::webhook::WebhookNotifier;
+use crate::workspace_guard::WorkspaceGuard;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "subprocess")]
+use tokio::io::AsyncWrite;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -10,39 +65,59 @@ pub enum PermissionMode {
     BypassPermissions,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PermissionMode {
     fn default() -> Self {
         Self::Default
     }
 }
 
+/// A shared runtime value (callback, store, ...) carried in the options.
+///
+/// These values are skipped when the options are serialized.
+pub struct Shared<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Shared(..)")
+    }
+}
+
+impl<T: ?Sized> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpServerConfig {
     pub command: String,
     pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextBlock {
-    #[serde(rename = "type")]
-    pub block_type: String,
     pub text: String,
 }
 
 impl TextBlock {
     pub fn new<S: Into<String>>(text: S) -> Self {
-        Self {
-            block_type: "text".to_string(),
-            text: text.into(),
-        }
+        Self { text: text.into() }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolUseBlock {
-    #[serde(rename = "type")]
-    pub block_type: String,
     pub id: String,
     pub name: String,
     pub input: serde_json::Value,
@@ -51,7 +126,6 @@ pub struct ToolUseBlock {
 impl ToolUseBlock {
     pub fn new<S: Into<String>>(id: S, name: S, input: serde_json::Value) -> Self {
         Self {
-            block_type: "tool_use".to_string(),
             id: id.into(),
             name: name.into(),
             input,
@@ -59,12 +133,75 @@ impl ToolUseBlock {
     }
 }
 
+/// Claude's reasoning before its answer, sent when extended thinking is
+/// enabled, see [`ClaudeCodeOptions::with_max_thinking_tokens`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingBlock {
+    pub thinking: String,
+    /// The API's signature of the thinking, needed to send it back in a
+    /// later request.
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl ThinkingBlock {
+    pub fn new<S: Into<String>>(thinking: S) -> Self {
+        Self {
+            thinking: thinking.into(),
+            signature: String::new(),
+        }
+    }
+}
+
+/// Thinking the API flagged and encrypted, opaque to the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactedThinkingBlock {
+    pub data: String,
+}
+
+/// An image in a message, e.g. a screenshot attached to the prompt or
+/// taken by a tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageBlock {
+    pub source: MediaSource,
+}
+
+impl ImageBlock {
+    /// An image from base64-encoded `data`.
+    pub fn base64<M: Into<String>, D: Into<String>>(media_type: M, data: D) -> Self {
+        Self {
+            source: MediaSource::Base64 {
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+
+    /// The PNG, JPEG, GIF or WebP image at `path`, base64 encoded.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let media_type = image_media_type(path).ok_or_else(|| {
+            ClaudeSDKError::invalid_options(format!(
+                "{} is not a PNG, JPEG, GIF or WebP image",
+                path.display()
+            ))
+        })?;
+        let bytes = std::fs::read(path).map_err(|e| {
+            ClaudeSDKError::invalid_options(format!("Cannot read image {}: {}", path.display(), e))
+        })?;
+        Ok(Self::base64(media_type, STANDARD.encode(bytes)))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ToolResultWire", into = "ToolResultWire")]
 pub struct ToolResultBlock {
-    #[serde(rename = "type")]
-    pub block_type: String,
     pub tool_use_id: String,
+    /// The tool's output. The CLI sends it either as a string or as a list
+    /// of content parts, whose text parts are joined with newlines.
     pub content: Option<String>,
+    /// The image parts of the tool's output, e.g. screenshots.
+    pub images: Vec<ImageBlock>,
     pub is_error: Option<bool>,
 }
 
@@ -75,20 +212,101 @@ impl ToolResultBlock {
         is_error: Option<bool>,
     ) -> Self {
         Self {
-            block_type: "tool_result".to_string(),
             tool_use_id: tool_use_id.into(),
             content: content.map(|c| c.into()),
+            images: Vec::new(),
             is_error,
         }
     }
+
+    pub fn with_image(mut self, image: ImageBlock) -> Self {
+        self.images.push(image);
+        self
+    }
+}
+
+/// The wire form of tool results, whose content is a string, or a list of
+/// content parts when it holds images.
+#[derive(Serialize, Deserialize)]
+struct ToolResultWire {
+    tool_use_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    is_error: Option<bool>,
 }
 
+impl TryFrom<ToolResultWire> for ToolResultBlock {
+    type Error = String;
+
+    fn try_from(wire: ToolResultWire) -> std::result::Result<Self, String> {
+        let (content, images) = match wire.content {
+            None | Some(serde_json::Value::Null) => (None, Vec::new()),
+            Some(serde_json::Value::String(text)) => (Some(text), Vec::new()),
+            Some(serde_json::Value::Array(parts)) => {
+                let text = parts
+                    .iter()
+                    .filter_map(|part| part.get("text")?.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let images = parts
+                    .iter()
+                    .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("image"))
+                    .filter_map(|part| serde_json::from_value(part.clone()).ok())
+                    .collect();
+                (Some(text), images)
+            }
+            Some(other) => {
+                return Err(format!(
+                    "invalid type: {}, expected a string or a list of content parts",
+                    other
+                ))
+            }
+        };
+        Ok(Self {
+            tool_use_id: wire.tool_use_id,
+            content,
+            images,
+            is_error: wire.is_error,
+        })
+    }
+}
+
+impl From<ToolResultBlock> for ToolResultWire {
+    fn from(block: ToolResultBlock) -> Self {
+        let content = if block.images.is_empty() {
+            block.content.map(serde_json::Value::String)
+        } else {
+            let text = block
+                .content
+                .filter(|text| !text.is_empty())
+                .map(|text| serde_json::json!({ "type": "text", "text": text }));
+            let images = block
+                .images
+                .into_iter()
+                .map(|image| serde_json::json!({ "type": "image", "source": image.source }));
+            Some(serde_json::Value::Array(
+                text.into_iter().chain(images).collect(),
+            ))
+        };
+        Self {
+            tool_use_id: block.tool_use_id,
+            content,
+            is_error: block.is_error,
+        }
+    }
+}
+
+/// A part of a user or assistant message, tagged by its `type`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentBlock {
     Text(TextBlock),
     ToolUse(ToolUseBlock),
     ToolResult(ToolResultBlock),
+    Image(ImageBlock),
+    Thinking(ThinkingBlock),
+    RedactedThinking(RedactedThinkingBlock),
 }
 
 impl From<TextBlock> for ContentBlock {
@@ -109,91 +327,670 @@ impl From<ToolResultBlock> for ContentBlock {
     }
 }
 
+impl From<ImageBlock> for ContentBlock {
+    fn from(block: ImageBlock) -> Self {
+        Self::Image(block)
+    }
+}
+
+impl From<ThinkingBlock> for ContentBlock {
+    fn from(block: ThinkingBlock) -> Self {
+        Self::Thinking(block)
+    }
+}
+
+impl From<RedactedThinkingBlock> for ContentBlock {
+    fn from(block: RedactedThinkingBlock) -> Self {
+        Self::RedactedThinking(block)
+    }
+}
+
+/// Token counts as the API reports them, on assistant messages and in the
+/// `usage` of result messages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MessageUsage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+}
+
+/// A message from the user's side of the conversation: the prompt, or the
+/// results of tool calls.
+///
+/// On the wire the content is nested under `message`, as the CLI writes it
+/// with `--output-format stream-json`. The flat form of earlier SDK
+/// versions, `content` next to `type`, is still read.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ChatWire", into = "ChatWire")]
 pub struct UserMessage {
-    #[serde(rename = "type")]
-    pub message_type: String,
     pub content: Vec<ContentBlock>,
+    /// The subagent's tool call this message belongs to, if any.
+    pub parent_tool_use_id: Option<String>,
+    pub session_id: Option<String>,
 }
 
 impl UserMessage {
     pub fn new(content: Vec<ContentBlock>) -> Self {
         Self {
-            message_type: "user".to_string(),
             content,
+            parent_tool_use_id: None,
+            session_id: None,
         }
     }
+
+    /// Add the image at `path`, see [`ImageBlock::from_file`].
+    pub fn with_image<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.content.push(ImageBlock::from_file(path)?.into());
+        Ok(self)
+    }
 }
 
+/// A response of the model, laid out on the wire like [`UserMessage`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ChatWire", into = "ChatWire")]
 pub struct AssistantMessage {
-    #[serde(rename = "type")]
-    pub message_type: String,
     pub content: Vec<ContentBlock>,
+    /// The API's id of the message, e.g. `msg_01...`.
+    pub id: Option<String>,
+    pub model: Option<String>,
+    /// Why the model stopped, e.g. `end_turn` or `tool_use`.
+    pub stop_reason: Option<String>,
+    pub usage: Option<MessageUsage>,
+    /// The subagent's tool call this message belongs to, if any.
+    pub parent_tool_use_id: Option<String>,
+    pub session_id: Option<String>,
 }
 
 impl AssistantMessage {
     pub fn new(content: Vec<ContentBlock>) -> Self {
         Self {
-            message_type: "assistant".to_string(),
             content,
+            id: None,
+            model: None,
+            stop_reason: None,
+            usage: None,
+            parent_tool_use_id: None,
+            session_id: None,
+        }
+    }
+}
+
+/// The wire form of user and assistant messages.
+#[derive(Serialize, Deserialize)]
+struct ChatWire {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<ChatBody>,
+    #[serde(default, deserialize_with = "flat_content", skip_serializing)]
+    content: Option<Vec<ContentBlock>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_tool_use_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ChatBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default)]
+    role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(deserialize_with = "message_content")]
+    content: Vec<ContentBlock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stop_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<MessageUsage>,
+}
+
+impl ChatWire {
+    fn new(body: ChatBody, parent_tool_use_id: Option<String>, session_id: Option<String>) -> Self {
+        Self {
+            message: Some(body),
+            content: None,
+            parent_tool_use_id,
+            session_id,
+        }
+    }
+
+    /// The message body, built from the flat content if the message has
+    /// none.
+    fn into_parts(self) -> std::result::Result<(ChatBody, Option<String>, Option<String>), String> {
+        let body = match (self.message, self.content) {
+            (Some(body), _) => body,
+            (None, Some(content)) => ChatBody {
+                content,
+                ..ChatBody::default()
+            },
+            (None, None) => return Err("missing field `message`".to_string()),
+        };
+        Ok((body, self.parent_tool_use_id, self.session_id))
+    }
+}
+
+/// Content given as a plain string, as the CLI does for prompts, or as a
+/// list of blocks.
+fn message_content<'de, D>(deserializer: D) -> std::result::Result<Vec<ContentBlock>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ContentVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for ContentVisitor {
+        type Value = Vec<ContentBlock>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a string or a list of content blocks")
         }
+
+        fn visit_str<E: serde::de::Error>(self, text: &str) -> std::result::Result<Self::Value, E> {
+            Ok(vec![TextBlock::new(text).into()])
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut blocks = Vec::new();
+            while let Some(block) = seq.next_element()? {
+                blocks.push(block);
+            }
+            Ok(blocks)
+        }
+    }
+
+    deserializer.deserialize_any(ContentVisitor)
+}
+
+fn flat_content<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<ContentBlock>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    message_content(deserializer).map(Some)
+}
+
+impl TryFrom<ChatWire> for UserMessage {
+    type Error = String;
+
+    fn try_from(wire: ChatWire) -> std::result::Result<Self, String> {
+        let (body, parent_tool_use_id, session_id) = wire.into_parts()?;
+        Ok(Self {
+            content: body.content,
+            parent_tool_use_id,
+            session_id,
+        })
     }
 }
 
+impl From<UserMessage> for ChatWire {
+    fn from(msg: UserMessage) -> Self {
+        let body = ChatBody {
+            role: "user".to_string(),
+            content: msg.content,
+            ..ChatBody::default()
+        };
+        Self::new(body, msg.parent_tool_use_id, msg.session_id)
+    }
+}
+
+impl TryFrom<ChatWire> for AssistantMessage {
+    type Error = String;
+
+    fn try_from(wire: ChatWire) -> std::result::Result<Self, String> {
+        let (body, parent_tool_use_id, session_id) = wire.into_parts()?;
+        Ok(Self {
+            content: body.content,
+            id: body.id,
+            model: body.model,
+            stop_reason: body.stop_reason,
+            usage: body.usage,
+            parent_tool_use_id,
+            session_id,
+        })
+    }
+}
+
+impl From<AssistantMessage> for ChatWire {
+    fn from(msg: AssistantMessage) -> Self {
+        let body = ChatBody {
+            id: msg.id,
+            role: "assistant".to_string(),
+            model: msg.model,
+            content: msg.content,
+            stop_reason: msg.stop_reason,
+            usage: msg.usage,
+        };
+        Self::new(body, msg.parent_tool_use_id, msg.session_id)
+    }
+}
+
+/// The status of an MCP server, as listed on the `init` message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    /// E.g. `connected` or `failed`.
+    pub status: String,
+}
+
+/// A notice from the CLI or the SDK.
+///
+/// The CLI's `init` message has no text of its own; its `content` is its
+/// subtype, and is not written back out.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SystemWire", into = "SystemWire")]
 pub struct SystemMessage {
-    #[serde(rename = "type")]
-    pub message_type: String,
     pub content: String,
+    /// How many identical notices in a row this message stands for, set
+    /// when system messages are deduplicated, see
+    /// [`SystemDedupe`](crate::filter::SystemDedupe).
+    pub repeat_count: Option<u32>,
+    /// The CLI's protocol version, on its answer to the SDK's announcement,
+    /// see [`crate::negotiation`].
+    pub protocol_version: Option<u32>,
+    /// The oldest SDK protocol the CLI accepts, on the same answer.
+    pub min_sdk_protocol_version: Option<u32>,
+    /// The kind of notice, e.g. `init` for the first message of a run.
+    pub subtype: Option<String>,
+    /// The session the run belongs to, on the `init` message. Persist it to
+    /// pick the session up later with
+    /// [`with_resume`](ClaudeCodeOptions::with_resume).
+    pub session_id: Option<String>,
+    /// The working directory of the run, on the `init` message.
+    pub cwd: Option<PathBuf>,
+    /// The tools available to the model, on the `init` message.
+    pub tools: Option<Vec<String>>,
+    /// The configured MCP servers, on the `init` message.
+    pub mcp_servers: Option<Vec<McpServerStatus>>,
+    pub model: Option<String>,
+    /// The CLI's name for the permission mode, e.g. `acceptEdits`.
+    pub permission_mode: Option<String>,
+    /// Where the CLI found its API key, e.g. `ANTHROPIC_API_KEY`.
+    pub api_key_source: Option<String>,
 }
 
 impl SystemMessage {
     pub fn new<S: Into<String>>(content: S) -> Self {
         Self {
-            message_type: "system".to_string(),
             content: content.into(),
+            repeat_count: None,
+            protocol_version: None,
+            min_sdk_protocol_version: None,
+            subtype: None,
+            session_id: None,
+            cwd: None,
+            tools: None,
+            mcp_servers: None,
+            model: None,
+            permission_mode: None,
+            api_key_source: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SystemWire {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subtype: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repeat_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_sdk_protocol_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mcp_servers: Option<Vec<McpServerStatus>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(
+        default,
+        rename = "permissionMode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    permission_mode: Option<String>,
+    #[serde(
+        default,
+        rename = "apiKeySource",
+        skip_serializing_if = "Option::is_none"
+    )]
+    api_key_source: Option<String>,
+}
+
+impl From<SystemWire> for SystemMessage {
+    fn from(wire: SystemWire) -> Self {
+        Self {
+            content: wire
+                .content
+                .or_else(|| wire.subtype.clone())
+                .unwrap_or_default(),
+            repeat_count: wire.repeat_count,
+            protocol_version: wire.protocol_version,
+            min_sdk_protocol_version: wire.min_sdk_protocol_version,
+            subtype: wire.subtype,
+            session_id: wire.session_id,
+            cwd: wire.cwd,
+            tools: wire.tools,
+            mcp_servers: wire.mcp_servers,
+            model: wire.model,
+            permission_mode: wire.permission_mode,
+            api_key_source: wire.api_key_source,
         }
     }
 }
 
+impl From<SystemMessage> for SystemWire {
+    fn from(msg: SystemMessage) -> Self {
+        let from_subtype = msg.subtype.as_deref() == Some(msg.content.as_str());
+        Self {
+            subtype: msg.subtype,
+            content: (!from_subtype).then_some(msg.content),
+            repeat_count: msg.repeat_count,
+            protocol_version: msg.protocol_version,
+            min_sdk_protocol_version: msg.min_sdk_protocol_version,
+            cwd: msg.cwd,
+            session_id: msg.session_id,
+            tools: msg.tools,
+            mcp_servers: msg.mcp_servers,
+            model: msg.model,
+            permission_mode: msg.permission_mode,
+            api_key_source: msg.api_key_source,
+        }
+    }
+}
+
+/// The last message of a run.
+///
+/// Read from the CLI's fields (`result`, `total_cost_usd`, `is_error` and
+/// the token counts under `usage`) as well as from the flat form of earlier
+/// SDK versions (`content`, `cost_usd`, `exit_code`, `tokens_input`, ...).
+/// Written in the CLI's form.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ResultWire", into = "ResultWire")]
 pub struct ResultMessage {
-    #[serde(rename = "type")]
-    pub message_type: String,
+    /// The SDK's id of the result; empty for results read from the CLI.
     pub id: String,
+    /// How the run ended, e.g. `success` or `error_max_turns`.
+    pub subtype: Option<String>,
+    pub is_error: Option<bool>,
+    /// 1 for an error and 0 otherwise when only `is_error` is known.
     pub exit_code: Option<i32>,
+    /// The final response.
     pub content: Option<String>,
     pub cost_usd: Option<f64>,
     pub tokens_input: Option<i32>,
     pub tokens_output: Option<i32>,
     pub reasoning_tokens: Option<i32>,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_tokens: Option<i32>,
+    /// Input tokens written to the prompt cache.
+    pub cache_creation_tokens: Option<i32>,
     pub canceled: Option<bool>,
+    pub session_id: Option<String>,
+    pub num_turns: Option<i32>,
+    pub duration_ms: Option<u64>,
+    /// The time spent waiting for the API.
+    pub duration_api_ms: Option<u64>,
 }
 
 impl ResultMessage {
     pub fn new<S: Into<String>>(id: S) -> Self {
         Self {
-            message_type: "result".to_string(),
             id: id.into(),
+            subtype: None,
+            is_error: None,
             exit_code: None,
             content: None,
             cost_usd: None,
             tokens_input: None,
             tokens_output: None,
             reasoning_tokens: None,
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
             canceled: None,
+            session_id: None,
+            num_turns: None,
+            duration_ms: None,
+            duration_api_ms: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ResultWire {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subtype: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    is_error: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_api_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    num_turns: Option<i32>,
+    #[serde(default, alias = "content", skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(default, alias = "cost_usd", skip_serializing_if = "Option::is_none")]
+    total_cost_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<MessageUsage>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    canceled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reasoning_tokens: Option<i32>,
+    #[serde(default, skip_serializing)]
+    tokens_input: Option<i32>,
+    #[serde(default, skip_serializing)]
+    tokens_output: Option<i32>,
+    #[serde(default, skip_serializing)]
+    cache_read_tokens: Option<i32>,
+    #[serde(default, skip_serializing)]
+    cache_creation_tokens: Option<i32>,
+}
+
+impl From<ResultWire> for ResultMessage {
+    fn from(wire: ResultWire) -> Self {
+        let usage = wire.usage.unwrap_or_default();
+        Self {
+            id: wire.id,
+            subtype: wire.subtype,
+            is_error: wire.is_error,
+            exit_code: wire.exit_code.or(wire.is_error.map(i32::from)),
+            content: wire.result,
+            cost_usd: wire.total_cost_usd,
+            tokens_input: usage.input_tokens.or(wire.tokens_input),
+            tokens_output: usage.output_tokens.or(wire.tokens_output),
+            reasoning_tokens: wire.reasoning_tokens,
+            cache_read_tokens: usage.cache_read_input_tokens.or(wire.cache_read_tokens),
+            cache_creation_tokens: usage
+                .cache_creation_input_tokens
+                .or(wire.cache_creation_tokens),
+            canceled: wire.canceled,
+            session_id: wire.session_id,
+            num_turns: wire.num_turns,
+            duration_ms: wire.duration_ms,
+            duration_api_ms: wire.duration_api_ms,
+        }
+    }
+}
+
+impl From<ResultMessage> for ResultWire {
+    fn from(msg: ResultMessage) -> Self {
+        let usage = MessageUsage {
+            input_tokens: msg.tokens_input,
+            output_tokens: msg.tokens_output,
+            cache_creation_input_tokens: msg.cache_creation_tokens,
+            cache_read_input_tokens: msg.cache_read_tokens,
+        };
+        Self {
+            subtype: msg.subtype,
+            is_error: msg.is_error,
+            duration_ms: msg.duration_ms,
+            duration_api_ms: msg.duration_api_ms,
+            num_turns: msg.num_turns,
+            result: msg.content,
+            session_id: msg.session_id,
+            total_cost_usd: msg.cost_usd,
+            usage: (usage != MessageUsage::default()).then_some(usage),
+            id: msg.id,
+            // Implied by `is_error` when it agrees
+            exit_code: msg
+                .exit_code
+                .filter(|_| msg.exit_code != msg.is_error.map(i32::from)),
+            canceled: msg.canceled,
+            reasoning_tokens: msg.reasoning_tokens,
+            tokens_input: None,
+            tokens_output: None,
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
         }
     }
 }
 
+/// Token and cost totals accumulated over one or more queries.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub tokens_input: i32,
+    pub tokens_output: i32,
+    pub reasoning_tokens: i32,
+    #[serde(default)]
+    pub cache_read_tokens: i32,
+    #[serde(default)]
+    pub cache_creation_tokens: i32,
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    /// Add the usage reported by a `ResultMessage` to these totals.
+    pub fn add_result(&mut self, result: &ResultMessage) {
+        self.tokens_input += result.tokens_input.unwrap_or(0);
+        self.tokens_output += result.tokens_output.unwrap_or(0);
+        self.reasoning_tokens += result.reasoning_tokens.unwrap_or(0);
+        self.cache_read_tokens += result.cache_read_tokens.unwrap_or(0);
+        self.cache_creation_tokens += result.cache_creation_tokens.unwrap_or(0);
+        self.cost_usd += result.cost_usd.unwrap_or(0.0);
+    }
+
+    /// Add the totals of `other` to these.
+    pub fn add(&mut self, other: &Usage) {
+        self.tokens_input += other.tokens_input;
+        self.tokens_output += other.tokens_output;
+        self.reasoning_tokens += other.reasoning_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+
+    /// The share of the prompt read from the cache, or `None` before any
+    /// input tokens were counted.
+    ///
+    /// `tokens_input` only counts the uncached part of the prompt, so the
+    /// whole prompt is its sum with both cache counts.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let total = i64::from(self.tokens_input)
+            + i64::from(self.cache_read_tokens)
+            + i64::from(self.cache_creation_tokens);
+        (total > 0).then(|| f64::from(self.cache_read_tokens) / total as f64)
+    }
+}
+
+/// A raw event of the Messages API's streaming format, sent while Claude
+/// writes a message when partial messages are enabled, see
+/// [`ClaudeCodeOptions::with_include_partial_messages`].
+///
+/// The events of a message (`message_start`, `content_block_start`,
+/// `content_block_delta`, ...) arrive before the complete
+/// [`AssistantMessage`], which still follows them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamEvent {
+    /// The event as the API sent it, with its own `type`.
+    pub event: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// The `Task` tool call a sub-agent's event belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
+}
+
+impl StreamEvent {
+    pub fn new(event: serde_json::Value) -> Self {
+        Self {
+            event,
+            uuid: None,
+            session_id: None,
+            parent_tool_use_id: None,
+        }
+    }
+
+    /// The event's type, e.g. `content_block_delta`.
+    pub fn event_type(&self) -> Option<&str> {
+        self.event.get("type")?.as_str()
+    }
+
+    /// The index of the content block a `content_block_*` event is about.
+    pub fn index(&self) -> Option<usize> {
+        self.event
+            .get("index")?
+            .as_u64()
+            .map(|index| index as usize)
+    }
+
+    /// The text added by a `content_block_delta` event of a text block.
+    pub fn text_delta(&self) -> Option<&str> {
+        self.delta("text_delta", "text")
+    }
+
+    /// The reasoning added by a `content_block_delta` event of a thinking
+    /// block.
+    pub fn thinking_delta(&self) -> Option<&str> {
+        self.delta("thinking_delta", "thinking")
+    }
+
+    fn delta(&self, kind: &str, field: &str) -> Option<&str> {
+        if self.event_type() != Some("content_block_delta") {
+            return None;
+        }
+        let delta = self.event.get("delta")?;
+        if delta.get("type")?.as_str() != Some(kind) {
+            return None;
+        }
+        delta.get(field)?.as_str()
+    }
+}
+
+/// A message of the stream, tagged by its `type`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Message {
     User(UserMessage),
     Assistant(AssistantMessage),
     System(SystemMessage),
     Result(ResultMessage),
+    /// Synthetic message emitted by the SDK before the CLI's output.
+    SdkInfo(SdkInfo),
+    /// Part of a message still being written; only sent with
+    /// [`ClaudeCodeOptions::with_include_partial_messages`].
+    StreamEvent(StreamEvent),
 }
 
 impl From<UserMessage> for Message {
@@ -220,65 +1017,356 @@ impl From<ResultMessage> for Message {
     }
 }
 
+impl From<SdkInfo> for Message {
+    fn from(msg: SdkInfo) -> Self {
+        Self::SdkInfo(msg)
+    }
+}
+
+impl From<StreamEvent> for Message {
+    fn from(msg: StreamEvent) -> Self {
+        Self::StreamEvent(msg)
+    }
+}
+
+/// Complete messages for tests and examples.
+///
+/// ```rust
+/// use claude_code_sdk::Message;
+/// use serde_json::json;
+///
+/// let transcript = vec![
+///     Message::user_text("List the files"),
+///     Message::tool_use_with_id("toolu_1", "Bash", json!({ "command": "ls" })),
+///     Message::tool_result("toolu_1", "Cargo.toml\nsrc"),
+///     Message::assistant_text("There is a manifest and a src directory."),
+///     Message::result(),
+/// ];
+/// # assert_eq!(transcript.len(), 5);
+/// ```
+impl Message {
+    pub fn user_text<S: Into<String>>(text: S) -> Self {
+        UserMessage::new(vec![TextBlock::new(text).into()]).into()
+    }
+
+    pub fn assistant_text<S: Into<String>>(text: S) -> Self {
+        AssistantMessage::new(vec![TextBlock::new(text).into()]).into()
+    }
+
+    pub fn system<S: Into<String>>(content: S) -> Self {
+        SystemMessage::new(content).into()
+    }
+
+    /// An assistant message calling `name`, with an id derived from the name
+    /// and input so repeated calls produce the same message.
+    pub fn tool_use<S: Into<String>>(name: S, input: serde_json::Value) -> Self {
+        let name = name.into();
+        // FNV-1a, stable across runs unlike the std hasher
+        let hash = name
+            .bytes()
+            .chain([0])
+            .chain(input.to_string().into_bytes())
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        Self::tool_use_with_id(format!("toolu_{:016x}", hash), name, input)
+    }
+
+    pub fn tool_use_with_id<I: Into<String>, S: Into<String>>(
+        id: I,
+        name: S,
+        input: serde_json::Value,
+    ) -> Self {
+        AssistantMessage::new(vec![ToolUseBlock::new(id.into(), name.into(), input).into()]).into()
+    }
+
+    /// A user message reporting the successful result of a tool call.
+    pub fn tool_result<I: Into<String>, S: Into<String>>(tool_use_id: I, content: S) -> Self {
+        UserMessage::new(vec![ToolResultBlock::new(
+            tool_use_id.into(),
+            Some(content.into()),
+            Some(false),
+        )
+        .into()])
+        .into()
+    }
+
+    /// A user message reporting a failed tool call.
+    pub fn tool_error<I: Into<String>, S: Into<String>>(tool_use_id: I, content: S) -> Self {
+        UserMessage::new(vec![ToolResultBlock::new(
+            tool_use_id.into(),
+            Some(content.into()),
+            Some(true),
+        )
+        .into()])
+        .into()
+    }
+
+    /// A successful result with every field populated.
+    pub fn result() -> Self {
+        ResultMessage {
+            exit_code: Some(0),
+            cost_usd: Some(0.0042),
+            tokens_input: Some(120),
+            tokens_output: Some(48),
+            reasoning_tokens: Some(0),
+            cache_read_tokens: Some(2048),
+            cache_creation_tokens: Some(0),
+            canceled: Some(false),
+            session_id: Some("session_fixture".to_string()),
+            num_turns: Some(1),
+            ..ResultMessage::new("result_fixture")
+        }
+        .into()
+    }
+}
+
+/// Version of the persisted options format written by
+/// [`ClaudeCodeOptions::to_json_compact`].
+pub const OPTIONS_FORMAT_VERSION: u32 = 1;
+
+/// The smallest thinking budget the API accepts.
+pub const MIN_THINKING_TOKENS: i32 = 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeCodeOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cli_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub allowed_tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disallowed_tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_mode: Option<PermissionMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_turns: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_thinking_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_partial_messages: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_safety_suggestions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_telemetry: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_vision: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub disable_search: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_anthropic_version: Option<String>,
+    /// Passed to the CLI in
+    /// [`MAX_OUTPUT_TOKENS_ENV`](crate::protocol::MAX_OUTPUT_TOKENS_ENV).
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_max_tokens: Option<i32>,
+    /// Not supported by the CLI; setting it fails validation with
+    /// [`UnsupportedOption`](ClaudeSDKError::UnsupportedOption), as do
+    /// `claude_top_k`, `claude_top_p` and `claude_stop_sequences`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_timeout: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_extra_headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub claude_default_headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_servers: Option<Vec<McpServerConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_timeout: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_disable_tools: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_disable_resources: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_disable_prompts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_disable_sampling: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_disable_roots: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_extra_logging: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_batch_requests: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mcp_batch_delay: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_tools: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_tools: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_prompt_validation: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_prompt_cache: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_model_timeout: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_output_timeout: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub no_input_timeout: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub input_timeout: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub output_timeout: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub model_timeout: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prompt_cache_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub log_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub config_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continue_conversation: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_session: Option<bool>,
+    #[serde(skip)]
+    pub progress_callback: Option<ProgressCallback>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    #[serde(skip)]
+    pub idempotency_store: Option<IdempotencyStoreRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_filter: Option<MessageFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append_system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_language: Option<LanguageTag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_dirs: Option<Vec<PathBuf>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<RunId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_token_deadline: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens_per_turn: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_models: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shutdown_timeout: Option<Duration>,
+    #[serde(skip)]
+    pub tool_result_hooks: Vec<ToolResultHook>,
+    #[serde(skip)]
+    pub hooks: Vec<HookRegistration>,
+    #[serde(skip)]
+    pub sdk_mcp_servers: Vec<SdkMcpServer>,
+    #[serde(skip)]
+    pub middleware: Vec<MiddlewareRef>,
+    #[serde(skip)]
+    pub transport_factory: Option<TransportFactory>,
+    #[serde(skip)]
+    pub refusal_callback: Option<RefusalCallback>,
+    #[serde(skip)]
+    pub text_chunk_callback: Option<TextChunkCallback>,
+    #[cfg(feature = "file-patch")]
+    #[serde(skip)]
+    pub file_patch_callback: Option<FilePatchCallback>,
+    #[cfg(feature = "subprocess")]
+    #[serde(skip)]
+    pub raw_tap: Option<RawTap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compat_mode: Option<CompatMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_guard: Option<WorkspaceGuard>,
+    #[serde(skip)]
+    pub usage_log: Option<UsageLog>,
+    #[serde(skip)]
+    pub stats_registry: Option<StatsRegistry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr_log: Option<StderrLog>,
+    #[serde(skip)]
+    pub stderr_callback: Option<StderrCallback>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parallel_tools: Option<u32>,
+    #[serde(skip)]
+    pub danger_detector: Option<DangerousCommandDetector>,
+    #[serde(skip)]
+    pub verifier: Option<VerifierRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fix_attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    #[serde(skip)]
+    pub key_rotation: Option<KeyRotation>,
+    #[serde(skip)]
+    pub key_router: Option<KeyRouter>,
+    #[serde(skip)]
+    pub turn_retry: Option<TurnRetry>,
+    #[cfg(feature = "subprocess")]
+    #[serde(skip)]
+    pub session_archive: Option<SessionArchive>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_files: Option<Vec<PathBuf>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedupe_system_messages: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_diagnostics: Option<DecodeDiagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isolated_home: Option<IsolatedHome>,
+    #[serde(skip)]
+    pub update_callback: Option<UpdateCallback>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_auto_update: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_folder: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip)]
+    pub overlay_provider: Option<OverlayProviderRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_tools: Option<Vec<ExternalTool>>,
+    #[serde(skip)]
+    pub file_locks: Option<WorkspaceLocks>,
+    #[cfg(feature = "sandbox-linux")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxPolicy>,
+    #[cfg(feature = "webhooks")]
+    #[serde(skip)]
+    pub webhook_notifier: Option<WebhookNotifier>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for ClaudeCodeOptions {
     fn default() -> Self {
         Self {
             cwd: None,
+            cli_path: None,
             allowed_tools: None,
+            disallowed_tools: None,
             permission_mode: None,
             system_prompt: None,
             max_turns: None,
+            max_thinking_tokens: None,
+            include_partial_messages: None,
             disable_safety_suggestions: None,
             disable_telemetry: None,
             disable_stream: None,
@@ -321,6 +1409,66 @@ impl Default for ClaudeCodeOptions {
             log_level: None,
             config_file: None,
             env: None,
+            resume: None,
+            continue_conversation: None,
+            fork_session: None,
+            progress_callback: None,
+            idempotency_key: None,
+            idempotency_store: None,
+            message_filter: None,
+            provider: None,
+            proxy: None,
+            append_system_prompt: None,
+            response_language: None,
+            add_dirs: None,
+            run_id: None,
+            retry_policy: None,
+            first_token_deadline: None,
+            max_output_tokens_per_turn: None,
+            fallback_models: None,
+            shutdown_timeout: None,
+            tool_result_hooks: Vec::new(),
+            hooks: Vec::new(),
+            sdk_mcp_servers: Vec::new(),
+            middleware: Vec::new(),
+            transport_factory: None,
+            refusal_callback: None,
+            text_chunk_callback: None,
+            #[cfg(feature = "file-patch")]
+            file_patch_callback: None,
+            #[cfg(feature = "subprocess")]
+            raw_tap: None,
+            compat_mode: None,
+            workspace_guard: None,
+            usage_log: None,
+            stats_registry: None,
+            stderr_log: None,
+            stderr_callback: None,
+            max_parallel_tools: None,
+            danger_detector: None,
+            verifier: None,
+            max_fix_attempts: None,
+            output_format: None,
+            key_rotation: None,
+            key_router: None,
+            turn_retry: None,
+            #[cfg(feature = "subprocess")]
+            session_archive: None,
+            context_files: None,
+            dedupe_system_messages: None,
+            decode_diagnostics: None,
+            isolated_home: None,
+            update_callback: None,
+            disable_auto_update: None,
+            trust_folder: None,
+            tenant: None,
+            overlay_provider: None,
+            external_tools: None,
+            file_locks: None,
+            #[cfg(feature = "sandbox-linux")]
+            sandbox: None,
+            #[cfg(feature = "webhooks")]
+            webhook_notifier: None,
         }
     }
 }
@@ -330,16 +1478,226 @@ impl ClaudeCodeOptions {
         Self::default()
     }
 
+    /// Serialize the options for storage, omitting unset fields and tagging
+    /// the output with [`OPTIONS_FORMAT_VERSION`]. Keys are sorted so stored
+    /// configs diff cleanly.
+    ///
+    /// Callbacks, hooks and stores are not serialized.
+    pub fn to_json_compact(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(map) = &mut value {
+            map.insert("version".to_string(), OPTIONS_FORMAT_VERSION.into());
+        }
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    /// Load options saved by [`to_json_compact`](Self::to_json_compact).
+    ///
+    /// JSON without a version is read as version 1. Fields this version does
+    /// not know are ignored, so configs written by newer versions still load.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let version = match &mut value {
+            serde_json::Value::Object(map) => match map.remove("version") {
+                Some(version) => version.as_u64().ok_or_else(|| {
+                    ClaudeSDKError::invalid_options(format!(
+                        "Invalid options format version: {}",
+                        version
+                    ))
+                })?,
+                None => 1,
+            },
+            _ => {
+                return Err(ClaudeSDKError::invalid_options(
+                    "Options JSON must be an object",
+                ))
+            }
+        };
+        if version == 0 {
+            return Err(ClaudeSDKError::invalid_options(
+                "Invalid options format version: 0",
+            ));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Check the options for values the CLI would reject or misinterpret.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(provider) = &self.provider {
+            provider.validate()?;
+        }
+        if let Some(proxy) = &self.proxy {
+            proxy.validate()?;
+        }
+        if let Some(home) = &self.isolated_home {
+            home.validate()?;
+        }
+        if self.tenant.is_some() && self.overlay_provider.is_none() {
+            return Err(ClaudeSDKError::invalid_options(
+                "a tenant is set but no overlay provider",
+            ));
+        }
+        if self.first_token_deadline == Some(Duration::ZERO) {
+            return Err(ClaudeSDKError::invalid_options(
+                "first_token_deadline must be greater than zero",
+            ));
+        }
+        if self
+            .max_thinking_tokens
+            .is_some_and(|tokens| tokens < MIN_THINKING_TOKENS)
+        {
+            return Err(ClaudeSDKError::invalid_options(format!(
+                "max_thinking_tokens must be at least {}",
+                MIN_THINKING_TOKENS
+            )));
+        }
+        if self
+            .max_output_tokens_per_turn
+            .is_some_and(|tokens| tokens <= 0)
+        {
+            return Err(ClaudeSDKError::invalid_options(
+                "max_output_tokens_per_turn must be greater than zero",
+            ));
+        }
+        if self.fallback_models.is_some() && self.first_token_deadline.is_none() {
+            return Err(ClaudeSDKError::invalid_options(
+                "fallback_models are only used with a first_token_deadline",
+            ));
+        }
+        if let Some(tools) = &self.external_tools {
+            if tools.iter().any(|tool| tool.name.trim().is_empty()) {
+                return Err(ClaudeSDKError::invalid_options(
+                    "external tool names must not be empty",
+                ));
+            }
+            if self
+                .sdk_mcp_servers
+                .iter()
+                .any(|server| server.name == external_tools::SERVER_NAME)
+            {
+                return Err(ClaudeSDKError::invalid_options(format!(
+                    "the SDK MCP server name {:?} is reserved for external tools",
+                    external_tools::SERVER_NAME
+                )));
+            }
+        }
+        let mut server_names = std::collections::HashSet::new();
+        for server in &self.sdk_mcp_servers {
+            if server.name.trim().is_empty() || !server_names.insert(server.name.as_str()) {
+                return Err(ClaudeSDKError::invalid_options(format!(
+                    "SDK MCP server names must be unique and not empty, got {:?}",
+                    server.name
+                )));
+            }
+        }
+        #[cfg(feature = "analysis-only")]
+        crate::tool_policy::check_analysis_only(self)?;
+        if self.resume.is_some() && self.continue_conversation == Some(true) {
+            return Err(ClaudeSDKError::invalid_options(
+                "resume and continue_conversation both pick the session; set only one",
+            ));
+        }
+        if self.fork_session == Some(true)
+            && self.resume.is_none()
+            && self.continue_conversation != Some(true)
+        {
+            return Err(ClaudeSDKError::invalid_options(
+                "fork_session requires a session to resume",
+            ));
+        }
+        if self.key_rotation.is_some() && self.key_router.is_some() {
+            return Err(ClaudeSDKError::invalid_options(
+                "key_rotation and key_router both supply the credential; set only one",
+            ));
+        }
+        if self.max_parallel_tools == Some(0) {
+            return Err(ClaudeSDKError::invalid_options(
+                "max_parallel_tools must be at least 1",
+            ));
+        }
+        if matches!(self.claude_max_tokens, Some(max) if max < 1) {
+            return Err(ClaudeSDKError::invalid_options(
+                "claude_max_tokens must be at least 1",
+            ));
+        }
+        // The CLI picks its own sampling parameters and offers no way to
+        // override them, so refuse these rather than dropping them silently
+        let unsupported = [
+            ("claude_temperature", self.claude_temperature.is_some()),
+            ("claude_top_k", self.claude_top_k.is_some()),
+            ("claude_top_p", self.claude_top_p.is_some()),
+            (
+                "claude_stop_sequences",
+                self.claude_stop_sequences.is_some(),
+            ),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(ClaudeSDKError::unsupported_option(
+                *option,
+                "the Claude Code CLI does not accept sampling parameters",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The text passed to `--append-system-prompt`, combining the explicit
+    /// option with instructions generated from other options.
+    pub(crate) fn effective_append_system_prompt(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .append_system_prompt
+            .iter()
+            .cloned()
+            .chain(self.response_language.iter().map(|tag| tag.instruction()))
+            .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\n\n"))
+        }
+    }
+
+    /// Add `text` to the explicit append-system-prompt option.
+    pub fn push_append_system_prompt<S: Into<String>>(&mut self, text: S) {
+        let text = text.into();
+        self.append_system_prompt = Some(match self.append_system_prompt.take() {
+            Some(existing) => format!("{}\n\n{}", existing, text),
+            None => text,
+        });
+    }
+
+    /// Run the CLI at `path` instead of looking for `claude-code` on the
+    /// `PATH`.
+    pub fn with_cli_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cli_path = Some(path.into());
+        self
+    }
+
     pub fn with_cwd<P: Into<PathBuf>>(mut self, cwd: P) -> Self {
         self.cwd = Some(cwd.into());
         self
     }
 
+    pub fn with_add_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.add_dirs = Some(dirs);
+        self
+    }
+
     pub fn with_allowed_tools(mut self, tools: Vec<String>) -> Self {
         self.allowed_tools = Some(tools);
         self
     }
 
+    pub fn with_disallowed_tools(mut self, tools: Vec<String>) -> Self {
+        self.disallowed_tools = Some(tools);
+        self
+    }
+
+    /// Disable tools by category, see [`ToolPolicy`].
+    pub fn with_tool_policy(self, policy: ToolPolicy) -> Self {
+        policy.apply(self)
+    }
+
     pub fn with_permission_mode(mut self, mode: PermissionMode) -> Self {
         self.permission_mode = Some(mode);
         self
@@ -354,4 +1712,503 @@ impl ClaudeCodeOptions {
         self.max_turns = Some(turns);
         self
     }
+
+    /// Enable extended thinking, letting Claude reason for up to `tokens`
+    /// tokens before answering. The reasoning arrives as
+    /// [`ContentBlock::Thinking`] blocks.
+    pub fn with_max_thinking_tokens(mut self, tokens: i32) -> Self {
+        self.max_thinking_tokens = Some(tokens);
+        self
+    }
+
+    /// Also stream the messages Claude is still writing, as
+    /// [`Message::StreamEvent`]s, so answers can be shown token by token.
+    pub fn with_include_partial_messages(mut self, include: bool) -> Self {
+        self.include_partial_messages = Some(include);
+        self
+    }
+
+    pub fn with_append_system_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.append_system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Ask Claude to respond in the given language.
+    pub fn with_response_language(mut self, language: LanguageTag) -> Self {
+        self.response_language = Some(language);
+        self
+    }
+
+    pub fn with_resume<S: Into<String>>(mut self, session_id: S) -> Self {
+        self.resume = Some(session_id.into());
+        self
+    }
+
+    /// Pick up the most recent session in the working directory, passed to
+    /// the CLI as `--continue`.
+    pub fn with_continue_conversation(mut self) -> Self {
+        self.continue_conversation = Some(true);
+        self
+    }
+
+    /// Resume into a new session id instead of continuing the original
+    /// session, leaving it unchanged. Requires [`resume`](Self::with_resume)
+    /// or [`continue_conversation`](Self::with_continue_conversation).
+    pub fn with_fork_session(mut self) -> Self {
+        self.fork_session = Some(true);
+        self
+    }
+
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    pub fn on_tool_progress<F>(self, callback: F) -> Self
+    where
+        F: Fn(&ToolProgressEvent) + Send + Sync + 'static,
+    {
+        self.with_progress_callback(Shared(Arc::new(callback)))
+    }
+
+    /// Return the stored result instead of re-running the query when the
+    /// same key was already completed.
+    pub fn with_idempotency_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    pub fn with_idempotency_store<S: IdempotencyStore + 'static>(mut self, store: Arc<S>) -> Self {
+        self.idempotency_store = Some(Shared(store));
+        self
+    }
+
+    pub fn with_message_filter(mut self, filter: MessageFilter) -> Self {
+        self.message_filter = Some(filter);
+        self
+    }
+
+    pub fn with_provider(mut self, provider: Provider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn with_http_proxy<S: Into<String>>(mut self, url: S) -> Self {
+        self.proxy.get_or_insert_with(Default::default).http_proxy = Some(url.into());
+        self
+    }
+
+    pub fn with_https_proxy<S: Into<String>>(mut self, url: S) -> Self {
+        self.proxy.get_or_insert_with(Default::default).https_proxy = Some(url.into());
+        self
+    }
+
+    pub fn with_no_proxy<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.proxy.get_or_insert_with(Default::default).no_proxy =
+            hosts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Use a specific run id instead of generating one per query.
+    pub fn with_run_id(mut self, run_id: RunId) -> Self {
+        self.run_id = Some(run_id);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Abort the query with [`SlowStart`](ClaudeSDKError::SlowStart) if
+    /// Claude has not produced any output `deadline` after the CLI was
+    /// started, or first try the [fallback models](Self::with_fallback_models).
+    pub fn with_first_token_deadline(mut self, deadline: Duration) -> Self {
+        self.first_token_deadline = Some(deadline);
+        self
+    }
+
+    /// Interrupt a turn once Claude's output in it exceeds `tokens`, see
+    /// [`crate::output_budget`].
+    pub fn with_max_output_tokens_per_turn(mut self, tokens: i32) -> Self {
+        self.max_output_tokens_per_turn = Some(tokens);
+        self
+    }
+
+    /// Models to switch to, in order, when a query misses its
+    /// [first-token deadline](Self::with_first_token_deadline).
+    pub fn with_fallback_models<I, S>(mut self, models: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fallback_models = Some(models.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// How long disconnecting waits for the CLI to exit after closing its
+    /// input, and again after `SIGTERM`, before killing it. Defaults to
+    /// [`DEFAULT_SHUTDOWN_TIMEOUT`](crate::transport::DEFAULT_SHUTDOWN_TIMEOUT).
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Register a hook that post-processes the output of MCP tools before
+    /// Claude sees it, e.g. to truncate noisy logs, see [`ToolResultHook`].
+    /// Hooks run in registration order.
+    ///
+    /// The hooks run over the control protocol, so this needs a CLI that
+    /// reads `--input-format stream-json`; connecting to one that does not
+    /// fails with [`UnsupportedOption`](ClaudeSDKError::UnsupportedOption).
+    pub fn with_tool_result_hook(mut self, hook: ToolResultHook) -> Self {
+        self.tool_result_hooks.push(hook);
+        self
+    }
+
+    pub fn on_tool_result<F>(self, hook: F) -> Self
+    where
+        F: Fn(&ToolResultContext<'_>, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.with_tool_result_hook(Shared(Arc::new(hook)))
+    }
+
+    /// Register a lifecycle hook the CLI runs through its control protocol,
+    /// see [`crate::hooks`]. Hooks of the same event run in registration
+    /// order.
+    pub fn with_hook(mut self, hook: HookRegistration) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Run `callback` at every `event`, for every tool.
+    pub fn on_hook<F, Fut>(self, event: HookEvent, callback: F) -> Self
+    where
+        F: Fn(HookInput) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = HookOutput> + Send + 'static,
+    {
+        self.with_hook(HookRegistration::new(event, callback))
+    }
+
+    /// Offer Claude the tools of an MCP server running in the application,
+    /// see [`crate::sdk_mcp`].
+    pub fn with_sdk_mcp_server(mut self, server: SdkMcpServer) -> Self {
+        self.sdk_mcp_servers.push(server);
+        self
+    }
+
+    /// Whether the CLI needs the control protocol for these options: to
+    /// run hooks, SDK MCP servers or external tools.
+    pub(crate) fn uses_control_protocol(&self) -> bool {
+        !self.hooks.is_empty() || !self.sdk_mcp_servers.is_empty() || self.external_tools.is_some()
+    }
+
+    /// Wrap queries in `layer`, after any layers registered before, see
+    /// [`crate::middleware`].
+    pub fn with_middleware<M: Middleware + 'static>(mut self, layer: Arc<M>) -> Self {
+        self.middleware.push(Shared(layer));
+        self
+    }
+
+    /// Run queries and conversations over the transports `factory` makes
+    /// instead of spawning the CLI, e.g. a
+    /// [`MockTransport`](crate::transport::MockTransport) in tests.
+    pub fn with_transport_factory<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(PromptInput, ClaudeCodeOptions) -> Box<dyn Transport> + Send + Sync + 'static,
+    {
+        self.transport_factory = Some(Shared(Arc::new(factory)));
+        self
+    }
+
+    pub fn with_refusal_callback(mut self, callback: RefusalCallback) -> Self {
+        self.refusal_callback = Some(callback);
+        self
+    }
+
+    /// Call `callback` whenever an assistant message is detected as a refusal.
+    pub fn on_refusal<F>(self, callback: F) -> Self
+    where
+        F: Fn(&Refusal) + Send + Sync + 'static,
+    {
+        self.with_refusal_callback(Shared(Arc::new(callback)))
+    }
+
+    pub fn with_text_chunk_callback(mut self, callback: TextChunkCallback) -> Self {
+        self.text_chunk_callback = Some(callback);
+        self
+    }
+
+    /// Call `callback` with the assistant text of each turn in sentences,
+    /// between start and end of turn markers, see [`crate::speech`].
+    pub fn on_text_chunk<F>(self, callback: F) -> Self
+    where
+        F: Fn(&TextChunk) + Send + Sync + 'static,
+    {
+        self.with_text_chunk_callback(Shared(Arc::new(callback)))
+    }
+
+    #[cfg(feature = "file-patch")]
+    pub fn with_file_patch_callback(mut self, callback: FilePatchCallback) -> Self {
+        self.file_patch_callback = Some(callback);
+        self
+    }
+
+    /// Call `callback` with each successful file edit as a patch, see
+    /// [`crate::file_patch`].
+    #[cfg(feature = "file-patch")]
+    pub fn on_file_patch<F>(self, callback: F) -> Self
+    where
+        F: Fn(&FilePatch) + Send + Sync + 'static,
+    {
+        self.with_file_patch_callback(Shared(Arc::new(callback)))
+    }
+
+    /// Call `callback` with the CLI's update notifications and prompts, see
+    /// [`crate::update`].
+    pub fn on_update_notice<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&UpdateEvent) + Send + Sync + 'static,
+    {
+        self.update_callback = Some(Shared(Arc::new(callback)));
+        self
+    }
+
+    /// Turn off the CLI's auto-updater, so headless runs never stop at an
+    /// update prompt.
+    pub fn without_auto_update(mut self) -> Self {
+        self.disable_auto_update = Some(true);
+        self
+    }
+
+    /// Mark the working directory as trusted in the CLI's configuration
+    /// before each run, so headless runs never stop at the CLI's prompt to
+    /// trust it, see [`crate::interaction`]. Without an isolated home this
+    /// changes the user's global `~/.claude.json`.
+    pub fn with_trusted_folder(mut self) -> Self {
+        self.trust_folder = Some(true);
+        self
+    }
+
+    /// Run the query for `tenant`, applying the overlay the
+    /// [overlay provider](Self::with_overlay_provider) has for it.
+    pub fn with_tenant<S: Into<String>>(mut self, tenant: S) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Look up tenant overlays in `provider` when a query starts, see
+    /// [`ConfigOverlay`](crate::overlay::ConfigOverlay).
+    pub fn with_overlay_provider<P: OverlayProvider + 'static>(mut self, provider: Arc<P>) -> Self {
+        self.overlay_provider = Some(Shared(provider));
+        self
+    }
+
+    /// Let the application run `tools` instead of the CLI, answering each
+    /// call with [`QueryHandle::submit_tool_result`](crate::QueryHandle::submit_tool_result),
+    /// see [`crate::external_tools`]. Tool names alone offer tools taking
+    /// any object as arguments.
+    pub fn with_external_tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<ExternalTool>,
+    {
+        self.external_tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// How to handle options the installed CLI version does not support.
+    pub fn with_compat_mode(mut self, mode: CompatMode) -> Self {
+        self.compat_mode = Some(mode);
+        self
+    }
+
+    /// Configure the check for unsafe workspaces in edit modes.
+    pub fn with_workspace_guard(mut self, guard: WorkspaceGuard) -> Self {
+        self.workspace_guard = Some(guard);
+        self
+    }
+
+    /// Allow edit modes in any workspace.
+    pub fn without_workspace_guard(self) -> Self {
+        self.with_workspace_guard(WorkspaceGuard::disabled())
+    }
+
+    /// Append a [`UsageRecord`](crate::analytics::UsageRecord) to `log` for
+    /// each completed query.
+    pub fn with_usage_log(mut self, log: UsageLog) -> Self {
+        self.usage_log = Some(log);
+        self
+    }
+
+    /// Record latency and cost percentiles into `registry` instead of the
+    /// global one, see [`crate::stats`].
+    pub fn with_stats_registry(mut self, registry: StatsRegistry) -> Self {
+        self.stats_registry = Some(registry);
+        self
+    }
+
+    /// The registry this query records its latency and cost into.
+    pub fn stats(&self) -> &StatsRegistry {
+        self.stats_registry
+            .as_ref()
+            .unwrap_or_else(|| StatsRegistry::global())
+    }
+
+    /// Deny tool calls with dangerous input and interrupt the run, see
+    /// [`DangerousCommandDetector`].
+    pub fn with_danger_detector(mut self, detector: DangerousCommandDetector) -> Self {
+        self.danger_detector = Some(detector);
+        self
+    }
+
+    /// Lock the files this run edits against the other runs sharing
+    /// `locks`, see [`crate::file_lock`].
+    pub fn with_file_locks(mut self, locks: WorkspaceLocks) -> Self {
+        self.file_locks = Some(locks);
+        self
+    }
+
+    /// Check the workspace with `verifier` after the run, sending fix-it
+    /// turns while the check fails, see [`crate::verify`].
+    pub fn with_verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifier = Some(Shared(Arc::new(verifier)));
+        self
+    }
+
+    /// The number of fix-it turns sent before giving up on verification,
+    /// [`DEFAULT_MAX_FIX_ATTEMPTS`](crate::verify::DEFAULT_MAX_FIX_ATTEMPTS)
+    /// by default.
+    pub fn with_max_fix_attempts(mut self, attempts: u32) -> Self {
+        self.max_fix_attempts = Some(attempts);
+        self
+    }
+
+    /// Request `format` with `--output-format` instead of the default
+    /// `--format json`, for CLIs pinned to one of the other formats.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Embed the contents of `files` ahead of the prompt, see
+    /// [`ContextFiles`](crate::context_files::ContextFiles). Relative paths
+    /// are resolved against [`cwd`](Self::cwd).
+    pub fn with_context_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.context_files = Some(files);
+        self
+    }
+
+    /// Collapse runs of identical system messages, such as repeated
+    /// compaction or MCP reconnect notices, into one message with a
+    /// [`repeat_count`](SystemMessage::repeat_count), see
+    /// [`SystemDedupe`](crate::filter::SystemDedupe).
+    pub fn with_system_message_dedupe(mut self) -> Self {
+        self.dedupe_system_messages = Some(true);
+        self
+    }
+
+    /// Run the CLI with a temporary home directory instead of the user's,
+    /// see [`IsolatedHome`].
+    pub fn with_isolated_home(mut self, home: IsolatedHome) -> Self {
+        self.isolated_home = Some(home);
+        self
+    }
+
+    /// Redact or save the lines of CLI output that fail to decode, see
+    /// [`DecodeDiagnostics`].
+    pub fn with_decode_diagnostics(mut self, diagnostics: DecodeDiagnostics) -> Self {
+        self.decode_diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Fetch the API credential from `rotation` before each spawn, see
+    /// [`KeyRotation`].
+    pub fn with_key_rotation(mut self, rotation: KeyRotation) -> Self {
+        self.key_rotation = Some(rotation);
+        self
+    }
+
+    /// Take the API credential for each spawn from `router`, see
+    /// [`KeyRouter`]. Cannot be combined with a key rotation.
+    pub fn with_key_router(mut self, router: KeyRouter) -> Self {
+        self.key_router = Some(router);
+        self
+    }
+
+    /// Run a query that fails, is refused or produces no text again with
+    /// the prompt returned by `adapt`, see [`TurnRetry`].
+    pub fn with_turn_retry<F>(mut self, policy: TurnRetryPolicy, adapt: F) -> Self
+    where
+        F: Fn(&TurnFailure, &PromptInput) -> Option<PromptInput> + Send + Sync + 'static,
+    {
+        self.turn_retry = Some(TurnRetry::new(policy, adapt));
+        self
+    }
+
+    /// Archive the transcript when the session ends, and end sessions that
+    /// exceed a maximum lifetime, see [`SessionArchive`].
+    #[cfg(feature = "subprocess")]
+    pub fn with_session_archive(mut self, archive: SessionArchive) -> Self {
+        self.session_archive = Some(archive);
+        self
+    }
+
+    /// Run at most `max` tool calls concurrently.
+    ///
+    /// Tool uses beyond the limit wait until a running one finishes, which
+    /// keeps parallel Bash invocations from overwhelming small CI machines.
+    /// The SDK holds them back in a `PreToolUse` hook, so this needs a CLI
+    /// that reads `--input-format stream-json`; connecting to one that does
+    /// not fails with [`UnsupportedOption`](ClaudeSDKError::UnsupportedOption).
+    pub fn with_max_parallel_tools(mut self, max: u32) -> Self {
+        self.max_parallel_tools = Some(max);
+        self
+    }
+
+    /// Run the CLI process under Landlock and seccomp restrictions.
+    #[cfg(feature = "sandbox-linux")]
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Write the CLI's stderr to a log file per run, see [`StderrLog`].
+    pub fn with_stderr_log(mut self, log: StderrLog) -> Self {
+        self.stderr_log = Some(log);
+        self
+    }
+
+    pub fn with_stderr_callback(mut self, callback: StderrCallback) -> Self {
+        self.stderr_callback = Some(callback);
+        self
+    }
+
+    /// Call `callback` with each line the CLI writes to stderr, as it is
+    /// written. Useful for live debugging; the tail of the output is also
+    /// kept for [`ClaudeSDKError::Process`](crate::ClaudeSDKError::Process).
+    pub fn on_stderr_line<F>(self, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.with_stderr_callback(Shared(Arc::new(callback)))
+    }
+
+    /// Report the run's lifecycle events to `notifier`.
+    #[cfg(feature = "webhooks")]
+    pub fn with_webhook_notifier(mut self, notifier: WebhookNotifier) -> Self {
+        self.webhook_notifier = Some(notifier);
+        self
+    }
+
+    /// Copy the raw NDJSON traffic with the CLI into `writer`.
+    #[cfg(feature = "subprocess")]
+    pub fn with_raw_tap<W: AsyncWrite + Send + 'static>(mut self, writer: W) -> Self {
+        self.raw_tap = Some(RawTap::new(writer));
+        self
+    }
 }
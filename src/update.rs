@@ -0,0 +1,128 @@
+//! The CLI's auto-update notifications and prompts.
+//!
+//! The CLI announces available updates, and may ask whether to install one,
+//! in plain text mixed into its output. In the JSON output formats such
+//! lines are turned into [`UpdateEvent`]s for the callback set with
+//! [`on_update_notice`](crate::ClaudeCodeOptions::on_update_notice) instead
+//! of failing to decode. Headless runs should also disable the updater with
+//! [`without_auto_update`](crate::ClaudeCodeOptions::without_auto_update),
+//! so the CLI never waits for an answer to an update prompt.
+
+use crate::types::Shared;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Environment variable turning off the CLI's auto-updater.
+pub const DISABLE_AUTOUPDATER_ENV: &str = "DISABLE_AUTOUPDATER";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateEventKind {
+    /// A newer CLI version is available.
+    Available,
+    /// The CLI asks whether to install an update and waits for an answer.
+    Prompt,
+    /// The CLI is installing an update.
+    Installing,
+    /// Installing an update failed.
+    Failed,
+}
+
+/// An update notification or prompt from the CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateEvent {
+    pub kind: UpdateEventKind,
+    pub current_version: Option<String>,
+    pub latest_version: Option<String>,
+    /// The line the CLI wrote.
+    pub text: String,
+}
+
+impl UpdateEvent {
+    /// Parse an update notification from a raw CLI output line, either a
+    /// JSON `update_available` notice or the CLI's plain-text messages.
+    ///
+    /// Returns `None` for any other line.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.starts_with('{') {
+            return Self::from_json(line);
+        }
+
+        let lower = line.to_lowercase();
+        if !lower.contains("update") && !lower.contains("new version") {
+            return None;
+        }
+        let kind = if lower.ends_with('?') || lower.contains("(y/n)") || lower.contains("[y/n]") {
+            UpdateEventKind::Prompt
+        } else if lower.contains("update failed") {
+            UpdateEventKind::Failed
+        } else if lower.starts_with("updating") || lower.starts_with("auto-updating") {
+            UpdateEventKind::Installing
+        } else if lower.contains("available") {
+            UpdateEventKind::Available
+        } else {
+            return None;
+        };
+
+        static VERSION: OnceLock<Regex> = OnceLock::new();
+        let versions = VERSION.get_or_init(|| {
+            Regex::new(r"v?(\d+\.\d+\.\d+(?:-[0-9A-Za-z.]+)?)").expect("version pattern is valid")
+        });
+        let found: Vec<String> = versions
+            .captures_iter(line)
+            .map(|captures| captures[1].to_string())
+            .collect();
+        let (current_version, latest_version) = match found.as_slice() {
+            [] => (None, None),
+            [latest] => (None, Some(latest.clone())),
+            [current, latest, ..] => (Some(current.clone()), Some(latest.clone())),
+        };
+        Some(Self {
+            kind,
+            current_version,
+            latest_version,
+            text: line.to_string(),
+        })
+    }
+
+    fn from_json(line: &str) -> Option<Self> {
+        if !line.contains("\"update_") {
+            return None;
+        }
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let kind = match value.get("type")?.as_str()? {
+            "update_available" => UpdateEventKind::Available,
+            "update_prompt" => UpdateEventKind::Prompt,
+            _ => return None,
+        };
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(String::from);
+        Some(Self {
+            kind,
+            current_version: field("current_version"),
+            latest_version: field("latest_version"),
+            text: field("message").unwrap_or_else(|| line.to_string()),
+        })
+    }
+}
+
+pub type UpdateCallback = Shared<dyn Fn(&UpdateEvent) + Send + Sync>;
+
+/// Report `event` to `callback` and the log.
+pub(crate) fn notify(event: &UpdateEvent, callback: Option<&UpdateCallback>) {
+    match event.kind {
+        UpdateEventKind::Prompt | UpdateEventKind::Failed => tracing::warn!(
+            text = %event.text,
+            "Claude Code CLI auto-update needs attention; disable the auto-updater for headless runs"
+        ),
+        _ => tracing::info!(
+            latest_version = event.latest_version.as_deref(),
+            text = %event.text,
+            "Claude Code CLI update notice"
+        ),
+    }
+    if let Some(callback) = callback {
+        callback(event);
+    }
+}
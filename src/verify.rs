@@ -0,0 +1,205 @@
+//! Verification of a run's result, with automatic fix-it turns.
+//!
+//! With a [`Verifier`] configured, a query does not end when Claude reports
+//! its result: the verifier checks the workspace (runs the tests, compiles,
+//! or anything a closure can decide) and, if the check fails, the session is
+//! resumed with the failure output as the next prompt. This repeats until the
+//! check passes or `max_fix_attempts` follow-up turns have been sent.
+
+#[cfg(feature = "subprocess")]
+use crate::cargo::tail;
+use crate::error::Result;
+use crate::types::Shared;
+use async_trait::async_trait;
+use std::fmt;
+use std::path::Path;
+#[cfg(feature = "subprocess")]
+use std::path::PathBuf;
+
+/// The number of fix-it turns sent when no limit is configured.
+pub const DEFAULT_MAX_FIX_ATTEMPTS: u32 = 3;
+
+/// How much of a failing command's output is passed back to Claude.
+#[cfg(feature = "subprocess")]
+const FEEDBACK_TAIL_BYTES: usize = 16 * 1024;
+
+/// The outcome of a verification pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Passed,
+    /// The check failed; `feedback` is sent to Claude in the fix-it turn.
+    Failed {
+        feedback: String,
+    },
+}
+
+impl Verification {
+    pub fn failed<S: Into<String>>(feedback: S) -> Self {
+        Self::Failed {
+            feedback: feedback.into(),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        matches!(self, Self::Passed)
+    }
+}
+
+/// Checks the workspace after a run.
+///
+/// Returning an error ends the query with that error instead of sending a
+/// fix-it turn; use it for problems Claude cannot fix, like a missing
+/// toolchain.
+#[async_trait]
+pub trait Verifier: Send + Sync {
+    async fn verify(&self, workspace: &Path) -> Result<Verification>;
+}
+
+pub type VerifierRef = Shared<dyn Verifier>;
+
+/// A [`Verifier`] backed by a closure, see [`verify_fn`].
+pub struct FnVerifier<F>(F);
+
+/// Verify the workspace with a closure.
+///
+/// ```rust
+/// use claude_code_sdk::verify::{verify_fn, Verification};
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new().with_verifier(verify_fn(|workspace| {
+///     match workspace.join("CHANGELOG.md").exists() {
+///         true => Verification::Passed,
+///         false => Verification::failed("CHANGELOG.md is missing"),
+///     }
+/// }));
+/// ```
+pub fn verify_fn<F>(check: F) -> FnVerifier<F>
+where
+    F: Fn(&Path) -> Verification + Send + Sync,
+{
+    FnVerifier(check)
+}
+
+#[async_trait]
+impl<F> Verifier for FnVerifier<F>
+where
+    F: Fn(&Path) -> Verification + Send + Sync,
+{
+    async fn verify(&self, workspace: &Path) -> Result<Verification> {
+        Ok((self.0)(workspace))
+    }
+}
+
+impl<F> fmt::Debug for FnVerifier<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FnVerifier(..)")
+    }
+}
+
+/// Runs a command in the workspace; the check passes if it exits with
+/// status 0.
+///
+/// On failure the tail of its stdout and stderr becomes the feedback.
+///
+/// ```rust
+/// use claude_code_sdk::verify::CommandVerifier;
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let options = ClaudeCodeOptions::new()
+///     .with_verifier(CommandVerifier::cargo_test())
+///     .with_max_fix_attempts(2);
+/// ```
+#[cfg(feature = "subprocess")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandVerifier {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+#[cfg(feature = "subprocess")]
+impl CommandVerifier {
+    pub fn new<P: Into<PathBuf>>(program: P) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// `cargo build`
+    pub fn cargo_build() -> Self {
+        Self::new("cargo").arg("build")
+    }
+
+    /// `cargo test`
+    pub fn cargo_test() -> Self {
+        Self::new("cargo").arg("test")
+    }
+
+    fn command_line(&self) -> String {
+        std::iter::once(self.program.display().to_string())
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(feature = "subprocess")]
+#[async_trait]
+impl Verifier for CommandVerifier {
+    async fn verify(&self, workspace: &Path) -> Result<Verification> {
+        let output = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .current_dir(workspace)
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if output.status.success() {
+            return Ok(Verification::Passed);
+        }
+
+        let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+        combined.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok(Verification::failed(format!(
+            "`{}` failed with {}:\n\n{}",
+            self.command_line(),
+            output.status,
+            tail(combined.trim_end(), FEEDBACK_TAIL_BYTES)
+        )))
+    }
+}
+
+/// Where verification of a query stands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The last verification pass succeeded.
+    pub verified: bool,
+    /// How many verification passes ran.
+    pub passes: u32,
+    /// How many fix-it turns were sent.
+    pub fix_turns: u32,
+    /// The feedback of the last failed pass.
+    pub last_feedback: Option<String>,
+}
+
+/// The prompt of the fix-it turn sent after a failed pass.
+#[cfg(feature = "subprocess")]
+pub(crate) fn fix_prompt(feedback: &str) -> String {
+    format!(
+        "Verification of your changes failed:\n\n{}\n\nFix the problem so that verification passes.",
+        feedback
+    )
+}
@@ -0,0 +1,188 @@
+use crate::error::{ClaudeSDKError, Result};
+use crate::retry::RetryPolicy;
+use crate::run_id::RunId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header carrying the HMAC-SHA256 signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-Claude-SDK-Signature";
+
+/// A run lifecycle event delivered by a [`WebhookNotifier`].
+///
+/// Serialized with an `event` tag, e.g. `{"event":"run_started",...}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    RunStarted {
+        run_id: RunId,
+        prompt: String,
+    },
+    ToolUse {
+        run_id: RunId,
+        tool_use_id: String,
+        tool_name: String,
+        input: serde_json::Value,
+    },
+    RunCompleted {
+        run_id: RunId,
+        session_id: Option<String>,
+        exit_code: Option<i32>,
+        num_turns: Option<i32>,
+        cost_usd: Option<f64>,
+        duration_ms: u64,
+    },
+    RunFailed {
+        run_id: RunId,
+        error: String,
+    },
+}
+
+/// POSTs [`WebhookEvent`]s as JSON to one or more URLs.
+///
+/// Each payload is the event plus a `timestamp` in Unix seconds. Failed
+/// deliveries (connection errors, 429 and 5xx responses) are retried with
+/// the notifier's [`RetryPolicy`]. With a secret set, every request carries
+/// a `sha256=<hex>` HMAC of the body in [`SIGNATURE_HEADER`], which
+/// receivers can check with [`sign`].
+///
+/// ```rust,no_run
+/// use claude_code_sdk::webhook::WebhookNotifier;
+/// use claude_code_sdk::ClaudeCodeOptions;
+///
+/// let notifier = WebhookNotifier::new("https://hooks.example.com/claude")
+///     .with_secret("s3cret");
+/// let options = ClaudeCodeOptions::new().with_webhook_notifier(notifier);
+/// ```
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    urls: Vec<String>,
+    secret: Option<Vec<u8>>,
+    retry_policy: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl fmt::Debug for WebhookNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookNotifier")
+            .field("urls", &self.urls)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("retry_policy", &self.retry_policy)
+            .finish()
+    }
+}
+
+impl WebhookNotifier {
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            urls: vec![url.into()],
+            secret: None,
+            retry_policy: RetryPolicy::default(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Also deliver events to `url`.
+    pub fn with_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.urls.push(url.into());
+        self
+    }
+
+    /// Sign each request body with HMAC-SHA256 using `secret`.
+    pub fn with_secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.secret = Some(secret.as_ref().to_vec());
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// The JSON body sent for `event`.
+    pub fn payload(event: &WebhookEvent) -> Result<Vec<u8>> {
+        let mut value = serde_json::to_value(event)?;
+        if let serde_json::Value::Object(map) = &mut value {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            map.insert("timestamp".to_string(), timestamp.into());
+        }
+        Ok(serde_json::to_vec(&value)?)
+    }
+
+    /// Deliver `event` to every URL, retrying failed deliveries.
+    ///
+    /// All URLs are attempted; the first delivery error is returned.
+    pub async fn notify(&self, event: &WebhookEvent) -> Result<()> {
+        let body = Self::payload(event)?;
+        let signature = self.secret.as_ref().map(|secret| sign(secret, &body));
+
+        let mut first_error = None;
+        for url in &self.urls {
+            if let Err(e) = self.deliver(url, &body, signature.as_deref()).await {
+                tracing::warn!(url = %url, error = %e, "webhook delivery failed");
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Deliver `event` from a background task, logging failures.
+    ///
+    /// Does nothing outside a Tokio runtime.
+    pub(crate) fn notify_in_background(&self, event: WebhookEvent) {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let notifier = self.clone();
+            runtime.spawn(async move {
+                let _ = notifier.notify(&event).await;
+            });
+        }
+    }
+
+    async fn deliver(&self, url: &str, body: &[u8], signature: Option<&str>) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .body(body.to_vec());
+            if let Some(signature) = signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let (error, retryable) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let retryable = status.is_server_error() || status.as_u16() == 429;
+                    (format!("HTTP {}", status), retryable)
+                }
+                Err(e) => (e.to_string(), true),
+            };
+
+            if !retryable || attempt >= self.retry_policy.max_retries {
+                return Err(ClaudeSDKError::webhook(url, error));
+            }
+            tokio::time::sleep(self.retry_policy.delay(attempt, None)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// The signature header value for `body`: `sha256=` followed by the hex
+/// encoded HMAC-SHA256 of the body keyed with `secret`.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mac = hmac_sha256::HMAC::mac(body, secret);
+    let hex: String = mac.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
@@ -0,0 +1,129 @@
+#[cfg(feature = "subprocess")]
+use crate::error::{ClaudeSDKError, Result};
+#[cfg(feature = "subprocess")]
+use crate::types::{ClaudeCodeOptions, PermissionMode};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "subprocess")]
+use std::path::Path;
+#[cfg(feature = "subprocess")]
+use tokio::process::Command;
+
+/// Why a workspace is unsafe to edit without confirmation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsafeWorkspaceReason {
+    /// The directory is not inside a git work tree, so edits cannot be undone.
+    NotVersionControlled,
+    /// More files have uncommitted changes than the guard allows.
+    UncommittedChanges { changed: usize, threshold: usize },
+}
+
+impl fmt::Display for UnsafeWorkspaceReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotVersionControlled => f.write_str("not under version control"),
+            Self::UncommittedChanges { changed, threshold } => write!(
+                f,
+                "{} files with uncommitted changes (threshold {})",
+                changed, threshold
+            ),
+        }
+    }
+}
+
+/// Refuses to let the CLI edit files without confirmation in a workspace
+/// where the edits could not be reviewed or reverted.
+///
+/// The guard applies to [`PermissionMode::AcceptEdits`] and
+/// [`PermissionMode::BypassPermissions`] runs and is enabled by default. The
+/// run fails with [`ClaudeSDKError::UnsafeWorkspace`] if the working
+/// directory is not in a git repository or has more uncommitted changes than
+/// the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceGuard {
+    pub enabled: bool,
+    /// The maximum number of files with uncommitted changes.
+    pub max_uncommitted_changes: usize,
+}
+
+impl Default for WorkspaceGuard {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_uncommitted_changes: 20,
+        }
+    }
+}
+
+impl WorkspaceGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_uncommitted_changes(mut self, max: usize) -> Self {
+        self.max_uncommitted_changes = max;
+        self
+    }
+
+    /// Check the workspace the options would run in.
+    #[cfg(feature = "subprocess")]
+    pub async fn check(&self, options: &ClaudeCodeOptions) -> Result<()> {
+        let edits_unconfirmed = matches!(
+            options.permission_mode,
+            Some(PermissionMode::AcceptEdits) | Some(PermissionMode::BypassPermissions)
+        );
+        if !self.enabled || !edits_unconfirmed {
+            return Ok(());
+        }
+
+        let cwd = match &options.cwd {
+            Some(cwd) => cwd.clone(),
+            None => std::env::current_dir()?,
+        };
+        let unsafe_workspace = |reason| ClaudeSDKError::UnsafeWorkspace {
+            path: cwd.clone(),
+            reason,
+        };
+
+        let changed = uncommitted_changes(&cwd)
+            .await
+            .ok_or_else(|| unsafe_workspace(UnsafeWorkspaceReason::NotVersionControlled))?;
+        if changed > self.max_uncommitted_changes {
+            return Err(unsafe_workspace(
+                UnsafeWorkspaceReason::UncommittedChanges {
+                    changed,
+                    threshold: self.max_uncommitted_changes,
+                },
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The number of files with uncommitted changes, or `None` if `dir` is not
+/// in a git work tree.
+#[cfg(feature = "subprocess")]
+async fn uncommitted_changes(dir: &Path) -> Option<usize> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain"])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count(),
+    )
+}
@@ -0,0 +1,89 @@
+//! Shell scripts standing in for the CLI, shared by the test files that
+//! run a process.
+#![allow(dead_code)]
+
+use claude_code_sdk::{Message, ResultMessage};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Write a CLI to `dir` that runs `body`, after answering `--version`.
+#[cfg(unix)]
+pub fn fake_cli(dir: &Path, body: &str) -> PathBuf {
+    fake_cli_with_help(dir, None, body)
+}
+
+/// Like [`fake_cli`], but `--help` prints `help` instead of failing.
+#[cfg(unix)]
+pub fn fake_cli_with_help(dir: &Path, help: Option<&str>, body: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let help = match help {
+        Some(help) => format!("echo '{}'; exit 0", help),
+        None => "exit 1".to_string(),
+    };
+    let path = dir.join("claude-code");
+    let script = format!(
+        "#!/bin/sh\ncase \"$1\" in\n  --version) echo '1.0.90 (Claude Code)'; exit 0;;\n  --help) {};;\nesac\n{}\n",
+        help, body
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// A shell command printing `messages` as stream-json lines.
+pub fn print(messages: &[Message]) -> String {
+    let lines: Vec<String> = messages
+        .iter()
+        .map(|message| serde_json::to_string(message).unwrap())
+        .collect();
+    format!("cat <<'EOF'\n{}\nEOF", lines.join("\n"))
+}
+
+/// The CLI asking the `PreToolUse` hook about a call, under the call's id.
+pub fn pre_tool_use(id: &str, tool_name: &str, tool_input: Value) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": id,
+        "request": {
+            "subtype": "hook_callback",
+            "callback_id": "hook_0",
+            "input": {
+                "hook_event_name": "PreToolUse",
+                "tool_use_id": id,
+                "tool_name": tool_name,
+                "tool_input": tool_input,
+            },
+        },
+    })
+}
+
+/// Write a CLI to `dir` that reads the initialize request and the prompt,
+/// sends `requests` one at a time, and ends with a result. The answers are
+/// kept for [`hook_answers`].
+#[cfg(unix)]
+pub fn answering_cli(dir: &Path, requests: &[Value]) -> PathBuf {
+    let mut body = String::from("read -r init\nread -r prompt\n: > answers\n");
+    for request in requests {
+        body.push_str(&format!(
+            "echo '{}'\nread -r answer\necho \"$answer\" >> answers\n",
+            request
+        ));
+    }
+    body.push_str(&print(&[Message::from(ResultMessage::new("run-1"))]));
+    fake_cli(dir, &body)
+}
+
+/// The answers an [`answering_cli`] in `dir` received, in order.
+pub fn hook_answers(dir: &Path) -> Vec<Value> {
+    std::fs::read_to_string(dir.join("answers"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// The `permissionDecision` a hook answered with, or null.
+pub fn permission_decision(answer: &Value) -> Value {
+    answer["response"]["response"]["hookSpecificOutput"]["permissionDecision"].clone()
+}
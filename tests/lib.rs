@@ -1,4 +1,79 @@
-mod test_errors;
-mod test_types;
+// The test files are also built on their own, so each declares the shared
+// `common` fixtures itself
+#![allow(clippy::duplicate_mod)]
+
 // Integration tests would go here, but they require the actual CLI to be installed
 // mod test_integration;
+mod test_analytics;
+mod test_annotations;
+mod test_anonymize;
+mod test_archive;
+mod test_async_iter;
+mod test_buffered;
+mod test_capabilities;
+mod test_cargo;
+mod test_checkpoint;
+mod test_client;
+mod test_compat;
+mod test_compression;
+mod test_context_files;
+mod test_control;
+mod test_conversation_tree;
+mod test_danger;
+mod test_dataset;
+mod test_debugger;
+mod test_diagnostics;
+mod test_errors;
+mod test_external_tools;
+mod test_file_lock;
+mod test_file_patch;
+mod test_filter;
+mod test_github;
+mod test_hooks;
+mod test_idempotency;
+mod test_interaction;
+mod test_interop;
+mod test_isolation;
+mod test_join;
+mod test_key_rotation;
+mod test_key_router;
+mod test_language;
+mod test_lsp;
+mod test_memory;
+mod test_middleware;
+mod test_monorepo;
+mod test_negotiation;
+mod test_output;
+mod test_output_budget;
+mod test_overlay;
+mod test_pool;
+mod test_process;
+mod test_progress;
+mod test_prompt;
+mod test_protocol;
+mod test_provider;
+mod test_proxy;
+mod test_quota;
+mod test_refusal;
+mod test_repro;
+mod test_retry;
+mod test_sandbox;
+mod test_script;
+mod test_sdk_info;
+mod test_sdk_mcp;
+mod test_send_sync;
+mod test_serve;
+mod test_speech;
+mod test_sse;
+mod test_stats;
+mod test_stderr_log;
+mod test_tool_policy;
+mod test_transport;
+mod test_tui;
+mod test_turn_retry;
+mod test_types;
+mod test_update;
+mod test_verify;
+mod test_webhook;
+mod test_websocket;
+mod test_workspace_guard;
@@ -0,0 +1,114 @@
+use claude_code_sdk::analytics::{self, StreamStats, UsageLog, UsageRecord};
+use claude_code_sdk::ResultMessage;
+use std::time::Duration;
+
+fn record(timestamp: u64, model: &str, cost_usd: f64, exit_code: i32) -> UsageRecord {
+    let mut result = ResultMessage::new("r1");
+    result.cost_usd = Some(cost_usd);
+    result.tokens_input = Some(100);
+    result.tokens_output = Some(20);
+    result.exit_code = Some(exit_code);
+    UsageRecord {
+        timestamp,
+        ..UsageRecord::from_result(
+            &result,
+            None,
+            Some(model.to_string()),
+            Duration::from_millis(1500),
+        )
+    }
+}
+
+#[test]
+fn test_usage_record() {
+    let record = record(1_700_000_000, "sonnet", 0.25, 1);
+    assert_eq!(record.date(), "2023-11-14");
+    assert_eq!(record.duration_ms, 1500);
+    assert!(!record.success);
+    assert_eq!(record.tokens_input, 100);
+}
+
+#[test]
+fn test_usage_log_aggregates() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = UsageLog::new(dir.path().join("logs/usage.jsonl"));
+    log.append(&record(1_700_000_000, "sonnet", 0.25, 0))
+        .unwrap();
+    log.append(&record(1_700_003_600, "haiku", 0.01, 1))
+        .unwrap();
+    log.append(&record(1_700_090_000, "sonnet", 0.5, 0))
+        .unwrap();
+
+    let records = log.records().unwrap();
+    assert_eq!(records.len(), 3);
+
+    let daily = analytics::daily(&records);
+    assert_eq!(daily.len(), 2);
+    assert_eq!(daily["2023-11-14"].queries, 2);
+    assert_eq!(daily["2023-11-14"].failures, 1);
+    assert_eq!(daily["2023-11-15"].tokens_output, 20);
+
+    let by_model = analytics::by_model(&records);
+    assert_eq!(by_model["sonnet"].queries, 2);
+    assert!((by_model["sonnet"].cost_usd - 0.75).abs() < 1e-9);
+}
+
+#[test]
+fn test_usage_log_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("usage.jsonl");
+    let log = UsageLog::new(&path).with_rotation(1, 2);
+    for i in 0..4 {
+        log.append(&record(1_700_000_000 + i, "sonnet", 0.1, 0))
+            .unwrap();
+    }
+
+    assert!(dir.path().join("usage.jsonl.1").exists());
+    assert!(dir.path().join("usage.jsonl.2").exists());
+    assert!(!dir.path().join("usage.jsonl.3").exists());
+
+    // The oldest record was rotated out; the rest are read oldest first
+    let timestamps: Vec<_> = log.records().unwrap().iter().map(|r| r.timestamp).collect();
+    assert_eq!(
+        timestamps,
+        vec![1_700_000_001, 1_700_000_002, 1_700_000_003]
+    );
+}
+
+#[test]
+fn test_stream_stats() {
+    let stats = StreamStats {
+        time_to_first_token: Some(Duration::from_millis(500)),
+        duration: Duration::from_millis(2500),
+        tokens_output: 100,
+    };
+    assert_eq!(stats.tokens_per_second(), Some(50.0));
+    assert_eq!(StreamStats::default().tokens_per_second(), None);
+
+    let timed = record(1_700_000_000, "sonnet", 0.1, 0).with_stats(&stats);
+    assert_eq!(timed.time_to_first_token_ms, Some(500));
+    let slow = record(1_700_000_000, "sonnet", 0.1, 0).with_stats(&StreamStats {
+        time_to_first_token: Some(Duration::from_millis(1500)),
+        duration: Duration::from_millis(3500),
+        tokens_output: 20,
+    });
+    let untimed = record(1_700_000_000, "sonnet", 0.1, 0);
+
+    let usage = &analytics::by_model(&[timed, slow, untimed])["sonnet"];
+    assert_eq!(usage.queries, 3);
+    assert_eq!(
+        usage.mean_time_to_first_token(),
+        Some(Duration::from_millis(1000))
+    );
+    assert_eq!(usage.mean_tokens_per_second(), Some(30.0));
+}
+
+#[test]
+fn test_usage_record_without_stats_loads() {
+    let json = r#"{"timestamp":1,"run_id":null,"session_id":null,"model":null,"cost_usd":0.0,"tokens_input":1,"tokens_output":2,"duration_ms":3,"exit_code":0,"success":true}"#;
+    let record: UsageRecord = serde_json::from_str(json).unwrap();
+    assert_eq!(record.time_to_first_token_ms, None);
+    assert!(!serde_json::to_string(&record)
+        .unwrap()
+        .contains("tokens_per_second"));
+}
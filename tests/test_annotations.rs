@@ -0,0 +1,40 @@
+use claude_code_sdk::annotations::{self, Annotation};
+use claude_code_sdk::dataset::{Conversation, DatasetFormat};
+use claude_code_sdk::Message;
+use serde_json::json;
+
+fn annotations() -> Vec<Annotation> {
+    vec![
+        Annotation::new(0, "grade", "pass"),
+        Annotation::new(
+            1,
+            "reviewer_notes",
+            json!({ "score": 4, "flags": ["verbose"] }),
+        ),
+    ]
+}
+
+#[test]
+fn test_ndjson_round_trip() {
+    let ndjson = annotations::to_ndjson(&annotations()).unwrap();
+    assert_eq!(ndjson.lines().count(), 2);
+    assert_eq!(annotations::from_ndjson(&ndjson).unwrap(), annotations());
+
+    let error = annotations::from_ndjson("\n{\"key\":\"grade\"}\n").unwrap_err();
+    assert!(error.to_string().contains("Line 2"), "{}", error);
+}
+
+#[test]
+fn test_dataset_examples_carry_annotations() {
+    let conversation = Conversation::new(vec![Message::assistant_text("Done."), Message::result()])
+        .with_prompt("Fix the build");
+    let example = DatasetFormat::Anthropic.example(&conversation);
+    assert!(example.get("annotations").is_none());
+
+    let example = DatasetFormat::OpenAi.example(&conversation.with_annotations(annotations()));
+    assert_eq!(
+        example["annotations"][0],
+        json!({ "message_seq": 0, "key": "grade", "value": "pass" })
+    );
+    assert_eq!(example["annotations"][1]["value"]["score"], 4);
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::archive::{
+    FileTranscriptStore, InMemoryTranscriptStore, SessionArchive, TranscriptStore,
+};
+use claude_code_sdk::debugger::Debugger;
+use claude_code_sdk::{ClaudeCodeOptions, Message, RunId};
+use std::time::Duration;
+
+fn transcript() -> Vec<Message> {
+    vec![
+        Message::assistant_text("Looking at the tests."),
+        Message::result(),
+    ]
+}
+
+#[tokio::test]
+async fn test_file_store_writes_ndjson() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileTranscriptStore::new(dir.path().join("archive"));
+    let run_id = RunId::new();
+
+    let location = store
+        .store(run_id, Some("session_fixture"), &transcript())
+        .await
+        .unwrap();
+    assert!(location.ends_with(&format!("{}.ndjson", run_id)));
+    let debugger = Debugger::load(&location).unwrap();
+    assert_eq!(debugger.messages().len(), 2);
+}
+
+#[tokio::test]
+async fn test_in_memory_store_and_settings() {
+    let store = InMemoryTranscriptStore::new();
+    let run_id = RunId::new();
+    let location = store.store(run_id, None, &transcript()).await.unwrap();
+    assert_eq!(location, format!("memory:{}", run_id));
+    assert_eq!(store.get(&location).unwrap().len(), 2);
+    assert!(store.get("memory:other").is_none());
+
+    let archive = SessionArchive::new(InMemoryTranscriptStore::new())
+        .with_max_lifetime(Duration::from_secs(60));
+    assert_eq!(archive.max_lifetime(), Some(Duration::from_secs(60)));
+    let options = ClaudeCodeOptions::new().with_session_archive(archive);
+    assert!(options.session_archive.is_some());
+    // Runtime values are not persisted
+    assert!(!options
+        .to_json_compact()
+        .unwrap()
+        .contains("session_archive"));
+}
+
+#[tokio::test]
+async fn test_stores_keep_annotations() {
+    use claude_code_sdk::annotations::{self, Annotation};
+
+    let run_id = RunId::new();
+    let notes = vec![Annotation::new(1, "grade", "fail")];
+
+    let store = InMemoryTranscriptStore::new();
+    let location = store.store(run_id, None, &transcript()).await.unwrap();
+    assert!(store.annotations(&location).is_empty());
+    store
+        .store_annotations(run_id, &location, &notes)
+        .await
+        .unwrap();
+    assert_eq!(store.annotations(&location), notes);
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileTranscriptStore::new(dir.path());
+    let location = store.store(run_id, None, &transcript()).await.unwrap();
+    store
+        .store_annotations(run_id, &location, &notes)
+        .await
+        .unwrap();
+    let path = dir.path().join(format!("{}.annotations.ndjson", run_id));
+    assert_eq!(annotations::load(path).unwrap(), notes);
+}
+
+#[tokio::test]
+async fn test_handle_stores_annotations_with_the_transcript() {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query_with_handle, ClaudeSDKError};
+    use std::sync::Arc;
+    use tokio_stream::StreamExt;
+
+    let store = Arc::new(InMemoryTranscriptStore::new());
+    let mock = MockTransport::new().with_messages(transcript());
+    let options = ClaudeCodeOptions::new()
+        .with_session_archive(SessionArchive::from_shared(store.clone()))
+        .with_transport_factory(mock.factory());
+    let mut handle = query_with_handle("Run the tests", Some(options))
+        .await
+        .unwrap();
+
+    handle.next().await.unwrap().unwrap();
+    handle.annotate(0, "grade", "pass").unwrap();
+    assert!(matches!(
+        handle.annotate(5, "grade", "fail"),
+        Err(ClaudeSDKError::UnknownMessage { message_seq: 5 })
+    ));
+    // Nothing to add to before the transcript is archived
+    assert!(!handle.store_annotations().await.unwrap());
+    while let Some(message) = handle.next().await {
+        message.unwrap();
+    }
+    let archived = handle.archived().unwrap().clone();
+    assert_eq!(archived.annotations, 1);
+    assert_eq!(store.annotations(&archived.location).len(), 1);
+
+    // Grading after the run adds to what was stored
+    handle.annotate(1, "reviewed", true).unwrap();
+    assert!(handle.store_annotations().await.unwrap());
+    assert_eq!(store.annotations(&archived.location), handle.annotations());
+    assert_eq!(handle.archived().unwrap().annotations, 2);
+}
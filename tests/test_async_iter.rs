@@ -0,0 +1,58 @@
+#[cfg(feature = "subprocess")]
+use claude_code_sdk::async_iter::into_channel;
+use claude_code_sdk::async_iter::MessageIter;
+use claude_code_sdk::{Message, Result};
+use futures::stream;
+#[cfg(feature = "subprocess")]
+use futures::stream::StreamExt;
+#[cfg(feature = "subprocess")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "subprocess")]
+use std::sync::Arc;
+
+fn messages() -> Vec<Result<Message>> {
+    vec![Ok(Message::assistant_text("Hello")), Ok(Message::result())]
+}
+
+#[tokio::test]
+async fn test_message_iter() {
+    let mut iter = MessageIter::new(stream::iter(messages()));
+    assert!(matches!(iter.next().await, Some(Ok(Message::Assistant(_)))));
+    assert!(matches!(iter.next().await, Some(Ok(Message::Result(_)))));
+    assert!(iter.next().await.is_none());
+}
+
+#[cfg(feature = "subprocess")]
+struct DropFlag(Arc<AtomicBool>);
+
+#[cfg(feature = "subprocess")]
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_into_channel() {
+    let mut rx = into_channel(stream::iter(messages()));
+    assert!(matches!(rx.recv().await, Some(Ok(Message::Assistant(_)))));
+    assert!(matches!(rx.recv().await, Some(Ok(Message::Result(_)))));
+    assert!(rx.recv().await.is_none());
+
+    // A stream that never ends is dropped along with the receiver
+    let dropped = Arc::new(AtomicBool::new(false));
+    let flag = DropFlag(dropped.clone());
+    let pending = stream::pending::<Result<Message>>().map(move |item| {
+        let _ = &flag;
+        item
+    });
+    drop(into_channel(Box::pin(pending)));
+    for _ in 0..100 {
+        if dropped.load(Ordering::SeqCst) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    assert!(dropped.load(Ordering::SeqCst));
+}
@@ -0,0 +1,53 @@
+use claude_code_sdk::buffered::BufferedConversation;
+use claude_code_sdk::{ClaudeSDKError, Message, Result};
+use futures::stream::{self, StreamExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+#[tokio::test]
+async fn test_readers_replay_from_the_start() {
+    let items: Vec<Result<Message>> = vec![
+        Ok(Message::assistant_text("Hello")),
+        Err(ClaudeSDKError::cli_connection("stdout closed")),
+        Ok(Message::result()),
+    ];
+    let conversation = BufferedConversation::new(stream::iter(items));
+
+    let first: Vec<_> = conversation.reader().collect().await;
+    assert_eq!(first.len(), 3);
+    assert!(conversation.is_complete());
+    assert_eq!(conversation.messages().len(), 2);
+
+    // A reader created afterwards sees the same items, errors included
+    let second: Vec<_> = conversation.reader().collect().await;
+    assert!(matches!(second[0], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        second[1].as_ref().unwrap_err().as_ref(),
+        ClaudeSDKError::CLIConnection { .. }
+    ));
+    assert!(matches!(second[2], Ok(Message::Result(_))));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_readers_follow_a_live_stream() {
+    let (tx, rx) = mpsc::channel(4);
+    let conversation = BufferedConversation::new(ReceiverStream::new(rx));
+
+    let readers: Vec<_> = (0..3)
+        .map(|_| {
+            let reader = conversation.reader();
+            tokio::spawn(async move { reader.collect::<Vec<_>>().await.len() })
+        })
+        .collect();
+
+    for _ in 0..5 {
+        tx.send(Ok(Message::assistant_text("chunk"))).await.unwrap();
+        tokio::task::yield_now().await;
+    }
+    drop(tx);
+
+    for reader in readers {
+        assert_eq!(reader.await.unwrap(), 5);
+    }
+    assert_eq!(conversation.len(), 5);
+}
@@ -0,0 +1,69 @@
+use claude_code_sdk::capabilities::Capabilities;
+use claude_code_sdk::compat::{self, CliVersion};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+
+const HELP: &str = "\
+Usage: claude [options] [command] [prompt]
+
+Options:
+  -d, --debug                  Enable debug mode
+  --input-format <format>      Input format: \"text\" or \"stream-json\"
+  --agents <json>              JSON object defining custom agents
+  --settings <file-or-json>    Path to a settings JSON file or a JSON string
+  --add-dir <directories...>   Additional directories to allow tool access to
+  -r, --resume [sessionId]     Resume a conversation
+  --disallowedTools <tools...> Comma separated list of tool names to deny
+";
+
+#[test]
+fn test_from_help() {
+    let capabilities = Capabilities::from_help(Some(CliVersion::new(1, 0, 60)), HELP);
+    assert!(capabilities.stream_json_input);
+    assert!(capabilities.agents);
+    assert!(capabilities.settings_json);
+    assert!(capabilities.supports_flag("--add-dir"));
+    assert!(capabilities.supports_flag("--debug"));
+    assert!(!capabilities.supports_flag("--append-system-prompt"));
+}
+
+#[test]
+fn test_unknown_help_supports_everything() {
+    let capabilities = Capabilities::from_help(None, "");
+    assert!(!capabilities.stream_json_input);
+    assert!(capabilities.flags.is_empty());
+    assert!(capabilities.supports_flag("--append-system-prompt"));
+}
+
+#[test]
+fn test_check_capabilities_drops_flags_missing_from_help() {
+    let capabilities = Capabilities::from_help(Some(CliVersion::new(1, 0, 60)), HELP);
+    let mut options = ClaudeCodeOptions::new()
+        .with_append_system_prompt("Be brief")
+        .with_add_dirs(vec!["../shared".into()]);
+
+    let warnings = compat::check_capabilities(&mut options, &capabilities).unwrap();
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].flag, "--append-system-prompt");
+    // The version is new enough, only the help output lacks the flag
+    assert_eq!(
+        warnings[0].to_string(),
+        "option `append_system_prompt` (--append-system-prompt) is not listed by `--help` of Claude Code CLI 1.0.60"
+    );
+    assert!(options.append_system_prompt.is_none());
+    assert!(options.add_dirs.is_some());
+}
+
+#[test]
+fn test_check_capabilities_refuses_to_drop_disallowed_tools() {
+    let help = "  --input-format <format>      Input format";
+    let capabilities = Capabilities::from_help(Some(CliVersion::new(1, 0, 60)), help);
+    let mut options = ClaudeCodeOptions::new().with_disallowed_tools(vec!["Bash".to_string()]);
+
+    let error = compat::check_capabilities(&mut options, &capabilities).unwrap_err();
+    let ClaudeSDKError::UnsupportedOption { option, message } = error else {
+        panic!("expected an unsupported option, got {:?}", error);
+    };
+    assert_eq!(option, "disallowed_tools");
+    assert!(message.contains("not listed by `--help`"), "{}", message);
+}
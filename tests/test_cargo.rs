@@ -0,0 +1,80 @@
+use claude_code_sdk::cargo::CargoContext;
+use claude_code_sdk::ClaudeCodeOptions;
+#[cfg(feature = "subprocess")]
+use claude_code_sdk::ClaudeSDKError;
+#[cfg(feature = "subprocess")]
+use std::fs;
+
+const METADATA: &str = r#"{
+    "packages": [{
+        "name": "billing",
+        "version": "0.3.1",
+        "edition": "2021",
+        "manifest_path": "/work/app/billing/Cargo.toml",
+        "dependencies": []
+    }],
+    "workspace_root": "/work/app",
+    "target_directory": "/work/app/target",
+    "version": 1
+}"#;
+
+#[test]
+fn test_prompt_packs_metadata_and_errors() {
+    let output = "src/lib.rs:1:14: error[E0308]: mismatched types".to_string();
+    let context = CargoContext::from_metadata(METADATA, Some(output)).unwrap();
+    assert!(!context.check_passed());
+
+    let prompt = context.prompt("Fix the build.");
+    assert!(prompt.contains("- billing 0.3.1 (edition 2021, billing/Cargo.toml)"));
+    assert!(prompt.contains("error[E0308]: mismatched types"));
+    assert!(prompt.ends_with("\nFix the build."));
+}
+
+#[test]
+fn test_apply_configures_options() {
+    let context = CargoContext::from_metadata(METADATA, None).unwrap();
+    let options = context.apply(ClaudeCodeOptions::new().with_allowed_tools(vec!["Read".into()]));
+
+    assert_eq!(options.cwd, Some("/work/app".into()));
+    let allowed = options.allowed_tools.unwrap();
+    assert_eq!(allowed.iter().filter(|tool| *tool == "Read").count(), 1);
+    assert!(allowed.contains(&"Edit".to_string()));
+    assert!(allowed.contains(&"Bash(cargo test:*)".to_string()));
+    assert!(options
+        .append_system_prompt
+        .unwrap()
+        .contains("cargo check"));
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_detect_reports_check_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"broken\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("src/lib.rs"),
+        "pub fn answer() -> u32 { \"42\" }\n",
+    )
+    .unwrap();
+
+    let context = CargoContext::detect(dir.path().join("src")).await.unwrap();
+    assert_eq!(
+        context.workspace_root.canonicalize().unwrap(),
+        dir.path().canonicalize().unwrap()
+    );
+    assert_eq!(context.packages[0].name, "broken");
+    assert!(context.check_output.unwrap().contains("mismatched types"));
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_detect_outside_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = CargoContext::detect(dir.path()).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::Cargo { .. }));
+}
@@ -0,0 +1,114 @@
+use claude_code_sdk::checkpoint::*;
+use claude_code_sdk::types::*;
+use claude_code_sdk::ClaudeSDKError;
+
+#[test]
+fn test_usage_add_result() {
+    let mut result = ResultMessage::new("result-1");
+    result.tokens_input = Some(100);
+    result.tokens_output = Some(20);
+    result.cost_usd = Some(0.5);
+
+    let mut usage = Usage::default();
+    usage.add_result(&result);
+    usage.add_result(&result);
+
+    assert_eq!(usage.tokens_input, 200);
+    assert_eq!(usage.tokens_output, 40);
+    assert_eq!(usage.reasoning_tokens, 0);
+    assert_eq!(usage.cost_usd, 1.0);
+}
+
+#[test]
+fn test_checkpoint_json_roundtrip() {
+    let checkpoint = Checkpoint {
+        session_id: "session-123".to_string(),
+        turn_count: 3,
+        usage: Usage {
+            tokens_input: 10,
+            tokens_output: 5,
+            reasoning_tokens: 0,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: 0.01,
+        },
+        workspace: Some(WorkspaceSnapshot {
+            root: "/work/repo".into(),
+            revision: Some("abc123".to_string()),
+        }),
+    };
+
+    let json = checkpoint.to_json().unwrap();
+    let restored = Checkpoint::from_json(&json).unwrap();
+
+    assert_eq!(restored, checkpoint);
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_workspace_snapshot_outside_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).await;
+
+    assert_eq!(snapshot.root, dir.path());
+    assert!(snapshot.revision.is_none());
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_resume_refuses_a_different_revision() {
+    use claude_code_sdk::resume_from_checkpoint;
+    use std::process::Command;
+
+    let dir = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir.path())
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).await;
+    snapshot.verify(dir.path()).await.unwrap();
+
+    git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+    assert!(snapshot.verify(dir.path()).await.is_err());
+
+    let checkpoint = Checkpoint {
+        session_id: "session-123".to_string(),
+        turn_count: 1,
+        usage: Usage::default(),
+        workspace: Some(snapshot),
+    };
+    let options = ClaudeCodeOptions::new().with_cli_path(dir.path().join("no-such-cli"));
+    match resume_from_checkpoint(&checkpoint, "Continue", Some(options)).await {
+        Err(ClaudeSDKError::Checkpoint { message }) => {
+            assert!(message.contains("checkpoint was taken at"), "{}", message)
+        }
+        Err(e) => panic!("expected a checkpoint error, got {:?}", e),
+        Ok(_) => panic!("resumed on a different revision"),
+    }
+}
+
+#[test]
+fn test_options_with_resume() {
+    let options = ClaudeCodeOptions::new().with_resume("session-123");
+
+    assert_eq!(options.resume, Some("session-123".to_string()));
+}
+
+#[test]
+fn test_options_with_continue_conversation() {
+    let options = ClaudeCodeOptions::new().with_continue_conversation();
+    assert_eq!(options.continue_conversation, Some(true));
+    options.validate().unwrap();
+    options.clone().with_fork_session().validate().unwrap();
+
+    let error = options.with_resume("session-123").validate().unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+}
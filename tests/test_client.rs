@@ -0,0 +1,336 @@
+#![cfg(feature = "subprocess")]
+
+#[cfg(unix)]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::capabilities::Capabilities;
+use claude_code_sdk::compat::CliVersion;
+use claude_code_sdk::sdk_info::SdkInfo;
+use claude_code_sdk::{
+    ClaudeCodeOptions, ClaudeSDKClient, ClaudeSDKError, PermissionMode, Readiness,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_connect_fails_fast_on_invalid_options() {
+    // Validation runs before the CLI is looked up
+    let options = ClaudeCodeOptions::new().with_fork_session();
+    let error = ClaudeSDKClient::connect(options).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+
+    let mut options = ClaudeCodeOptions::new();
+    options.max_parallel_tools = Some(0);
+    let error = ClaudeSDKClient::connect(options).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+}
+
+fn client_with_help(help: &str) -> ClaudeSDKClient {
+    let options = ClaudeCodeOptions::new();
+    // Analysis-only builds need `--disallowedTools` to connect
+    let help = format!("{}  --disallowedTools <tools...>", help);
+    let readiness = Readiness {
+        cli_path: PathBuf::from("claude-code"),
+        capabilities: Capabilities::from_help(CliVersion::parse("1.0.0"), &help),
+        sdk_info: SdkInfo::new(None, None, &options),
+        option_warnings: Vec::new(),
+        warmup: Duration::ZERO,
+    };
+    ClaudeSDKClient::from_readiness(options, readiness).unwrap()
+}
+
+#[tokio::test]
+async fn test_set_model_and_permission_mode_apply_to_next_turn() {
+    let mut client = client_with_help("--model <model>  --accept-edits  --max-turns <n>");
+    client.set_model("claude-sonnet-4").await.unwrap();
+    assert_eq!(
+        client.options().claude_model.as_deref(),
+        Some("claude-sonnet-4")
+    );
+    #[cfg(not(feature = "analysis-only"))]
+    {
+        client
+            .set_permission_mode(PermissionMode::AcceptEdits)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.options().permission_mode,
+            Some(PermissionMode::AcceptEdits)
+        );
+    }
+    let summary = &client.readiness().sdk_info.options;
+    assert_eq!(summary.model.as_deref(), Some("claude-sonnet-4"));
+
+    assert!(matches!(
+        client.set_model(" ").await,
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_unsupported_runtime_change_is_rejected() {
+    let mut client = client_with_help("--model <model>  --max-turns <n>");
+    let error = client
+        .set_permission_mode(PermissionMode::BypassPermissions)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, ClaudeSDKError::UnsupportedOption { ref option, .. } if option == "permission_mode")
+    );
+    assert_eq!(client.options().permission_mode, None);
+
+    // The default mode needs no flag
+    client
+        .set_permission_mode(PermissionMode::Default)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_first_token_deadline_options_are_validated() {
+    let options = ClaudeCodeOptions::new().with_fallback_models(["claude-haiku-4"]);
+    let error = ClaudeSDKClient::connect(options).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+
+    let options = ClaudeCodeOptions::new().with_first_token_deadline(Duration::ZERO);
+    let error = ClaudeSDKClient::connect(options).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+
+    let error = ClaudeSDKError::SlowStart {
+        deadline: Duration::from_secs(2),
+        model: Some("claude-opus-4".to_string()),
+    };
+    assert_eq!(
+        error.to_string(),
+        "No output from claude-opus-4 within the first-token deadline of 2s"
+    );
+}
+
+#[tokio::test]
+async fn test_conversation_starts_with_first_message() {
+    use tokio_stream::StreamExt;
+
+    let mut client = client_with_help("--input-format <format>  --model <model>");
+    assert!(!client.is_conversing());
+    // Nothing has been sent, so there is no response to read
+    assert!(client.receive_response().next().await.is_none());
+    client.disconnect().await.unwrap();
+
+    let options = ClaudeCodeOptions::new().with_external_tools(["deploy"]);
+    let mut client = ClaudeSDKClient::from_readiness(options, client.readiness().clone()).unwrap();
+    let error = client.send_message("Deploy staging").await.unwrap_err();
+    assert!(
+        matches!(error, ClaudeSDKError::UnsupportedOption { ref option, .. } if option == "external_tools")
+    );
+    assert!(!client.is_conversing());
+}
+
+#[tokio::test]
+async fn test_conversation_applies_query_setup_and_adapters() {
+    use claude_code_sdk::danger::DangerousCommandDetector;
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{AssistantMessage, Message, ToolUseBlock};
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("NOTES.md"), "Deploys run on Fridays").unwrap();
+    let readiness = client_with_help("--input-format <format>")
+        .readiness()
+        .clone();
+
+    let mock = MockTransport::new().with_messages([
+        Message::assistant_text("Cleaning up"),
+        AssistantMessage::new(vec![ToolUseBlock::new(
+            "toolu_1",
+            "Bash",
+            json!({ "command": "rm -rf /" }),
+        )
+        .into()])
+        .into(),
+        Message::assistant_text("Done"),
+    ]);
+    let options = ClaudeCodeOptions::new()
+        .with_cwd(dir.path())
+        .with_context_files(vec!["NOTES.md".into()])
+        .with_danger_detector(DangerousCommandDetector::new())
+        .with_transport_factory(mock.factory());
+    let mut client = ClaudeSDKClient::from_readiness(options, readiness.clone()).unwrap();
+
+    client.send_message("When do deploys run?").await.unwrap();
+    let prompt = format!("{:?}", mock.prompts()[0]);
+    assert!(prompt.contains("Deploys run on Fridays"), "{}", prompt);
+
+    let turn: Vec<_> = client.receive_response().collect().await;
+    // The context files' summary comes first, and the dangerous call ends
+    // the turn
+    assert!(matches!(turn[0], Ok(Message::System(_))));
+    assert!(matches!(turn[1], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        turn.last(),
+        Some(Err(ClaudeSDKError::DangerousToolUse { .. }))
+    ));
+    assert_eq!(turn.len(), 3);
+
+    // Idempotency keys need a stored result to stand in for the run
+    let options = ClaudeCodeOptions::new().with_idempotency_key("deploy-1");
+    let mut client = ClaudeSDKClient::from_readiness(options, readiness.clone()).unwrap();
+    let error = client.send_message("Deploy").await.unwrap_err();
+    assert!(
+        matches!(error, ClaudeSDKError::UnsupportedOption { ref option, .. } if option == "idempotency_key")
+    );
+
+    #[cfg(feature = "analysis-only")]
+    {
+        let mock = MockTransport::new().with_messages([
+            AssistantMessage::new(vec![ToolUseBlock::new(
+                "toolu_2",
+                "Write",
+                json!({ "file_path": "a.txt", "content": "" }),
+            )
+            .into()]),
+            AssistantMessage::new(vec![]),
+        ]);
+        let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+        let mut client = ClaudeSDKClient::from_readiness(options, readiness).unwrap();
+        client.send_message("Write a file").await.unwrap();
+        let turn: Vec<_> = client.receive_response().collect().await;
+        assert_eq!(turn.len(), 1);
+        assert!(matches!(turn[0], Err(ClaudeSDKError::ToolDisabled { .. })));
+    }
+}
+
+#[tokio::test]
+async fn test_runtime_changes_reach_a_live_conversation() {
+    use claude_code_sdk::transport::MockTransport;
+    use serde_json::json;
+
+    let readiness = client_with_help("--input-format <format>  --model <model>")
+        .readiness()
+        .clone();
+    let mock = MockTransport::new();
+    let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+    let mut client = ClaudeSDKClient::from_readiness(options, readiness).unwrap();
+
+    // Without a conversation only the options change
+    client.set_model("claude-haiku-4").await.unwrap();
+    assert!(mock.control_requests().is_empty());
+
+    client.send_message("Hello").await.unwrap();
+    client.set_model("claude-opus-4").await.unwrap();
+    client
+        .set_permission_mode(PermissionMode::Default)
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.control_requests(),
+        vec![
+            json!({"subtype": "set_model", "model": "claude-opus-4"}),
+            json!({"subtype": "set_permission_mode", "mode": "default"}),
+        ]
+    );
+    assert_eq!(
+        client.options().claude_model.as_deref(),
+        Some("claude-opus-4")
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_runtime_change_is_sent_as_control_request() {
+    use claude_code_sdk::{Message, ResultMessage};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let assistant = serde_json::to_string(&Message::assistant_text("Still here")).unwrap();
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("turn-1"))).unwrap();
+    // Accept the first model, but refuse the second after a message of the
+    // running turn
+    let answer = |subtype: &str| {
+        format!(
+            "echo \"{{\\\"type\\\":\\\"control_response\\\",\\\"response\\\":{{\\\"subtype\\\":\\\"{}\\\",\\\"request_id\\\":\\\"$id\\\"{}}}}}\"",
+            subtype,
+            if subtype == "error" { ",\\\"error\\\":\\\"Unknown model\\\"" } else { "" }
+        )
+    };
+    let cli = common::fake_cli(
+        dir.path(),
+        &format!(
+            "while read -r line; do\n\
+             echo \"$line\" >> input\n\
+             id=$(echo \"$line\" | sed -n 's/.*\"request_id\":\"\\([^\"]*\\)\".*/\\1/p')\n\
+             case \"$line\" in\n\
+             *claude-missing*) echo '{}'; {}; echo '{}';;\n\
+             *control_request*) {};;\n\
+             esac\n\
+             done\n",
+            assistant,
+            answer("error"),
+            result,
+            answer("success"),
+        ),
+    );
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path());
+    let readiness = Readiness {
+        cli_path: cli,
+        capabilities: Capabilities::from_help(
+            CliVersion::parse("1.0.90"),
+            "--input-format <format>  --model <model>  --disallowedTools <tools...>",
+        ),
+        sdk_info: SdkInfo::new(None, None, &options),
+        option_warnings: Vec::new(),
+        warmup: Duration::ZERO,
+    };
+    let mut client = ClaudeSDKClient::from_readiness(options, readiness).unwrap();
+
+    client.send_message("Hello").await.unwrap();
+    client.set_model("claude-opus-4").await.unwrap();
+    assert_eq!(
+        client.options().claude_model.as_deref(),
+        Some("claude-opus-4")
+    );
+
+    // A refused change is an error and leaves the options alone
+    let error = client.set_model("claude-missing").await.unwrap_err();
+    assert!(
+        matches!(&error, ClaudeSDKError::UnsupportedOption { option, message } if option == "claude_model" && message == "Unknown model"),
+        "{:?}",
+        error
+    );
+    assert_eq!(
+        client.options().claude_model.as_deref(),
+        Some("claude-opus-4")
+    );
+
+    // Messages read while waiting for the answer are not lost
+    let turn: Vec<Message> = client
+        .receive_response()
+        .filter(|message| !matches!(message, Ok(Message::SdkInfo(_))))
+        .collect::<claude_code_sdk::Result<_>>()
+        .await
+        .unwrap();
+    assert_eq!(turn.len(), 2, "{:?}", turn);
+    assert!(matches!(turn[0], Message::Assistant(_)));
+    assert!(matches!(turn[1], Message::Result(_)));
+    client.disconnect().await.unwrap();
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let lines: Vec<serde_json::Value> = input
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["type"], "user");
+    assert_eq!(
+        lines[1],
+        serde_json::json!({
+            "type": "control_request",
+            "request_id": "req_1",
+            "request": {"subtype": "set_model", "model": "claude-opus-4"},
+        })
+    );
+    assert_eq!(lines[2]["request"]["model"], "claude-missing");
+}
@@ -0,0 +1,71 @@
+use claude_code_sdk::compat::{check, CliVersion, CompatMode};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+
+#[test]
+fn test_cli_version_parse() {
+    assert_eq!(
+        CliVersion::parse("1.0.18 (Claude Code)"),
+        Some(CliVersion::new(1, 0, 18))
+    );
+    assert_eq!(CliVersion::parse("v0.2"), Some(CliVersion::new(0, 2, 0)));
+    assert_eq!(
+        CliVersion::parse("2.1.0-beta.1"),
+        Some(CliVersion::new(2, 1, 0))
+    );
+    assert_eq!(CliVersion::parse("unknown"), None);
+    assert!(CliVersion::new(1, 0, 9) < CliVersion::new(1, 0, 18));
+}
+
+#[test]
+fn test_lax_mode_drops_unsupported_options() {
+    let mut options = ClaudeCodeOptions::new()
+        .with_add_dirs(vec!["../shared".into()])
+        .with_resume("session-1");
+
+    let warnings = check(&mut options, CliVersion::new(1, 0, 3)).unwrap();
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].option, "add_dirs");
+    assert_eq!(
+        warnings[0].to_string(),
+        "option `add_dirs` (--add-dir) requires Claude Code CLI 1.0.18 or newer, found 1.0.3"
+    );
+    assert!(options.add_dirs.is_none());
+    assert_eq!(options.resume.as_deref(), Some("session-1"));
+}
+
+#[test]
+fn test_strict_mode_fails_fast() {
+    let mut options = ClaudeCodeOptions::new()
+        .with_append_system_prompt("Be terse")
+        .with_compat_mode(CompatMode::Strict);
+
+    assert!(check(&mut options, CliVersion::new(0, 2, 80)).is_err());
+    assert!(check(&mut options, CliVersion::new(1, 0, 0))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_lax_mode_keeps_fork_session() {
+    let mut options = ClaudeCodeOptions::new()
+        .with_resume("session-1")
+        .with_fork_session();
+
+    assert!(check(&mut options, CliVersion::new(1, 0, 3)).is_err());
+    assert_eq!(options.fork_session, Some(true));
+}
+
+#[test]
+fn test_lax_mode_keeps_disallowed_tools() {
+    let mut options = ClaudeCodeOptions::new().with_disallowed_tools(vec!["Bash".to_string()]);
+
+    // A tool restriction never fails open
+    let error = check(&mut options, CliVersion::new(0, 2, 80)).unwrap_err();
+    assert!(
+        matches!(&error, ClaudeSDKError::UnsupportedOption { option, .. } if option == "disallowed_tools"),
+        "{:?}",
+        error
+    );
+    assert_eq!(options.disallowed_tools, Some(vec!["Bash".to_string()]));
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "compression")]
+
+use claude_code_sdk::archive::{FileTranscriptStore, TranscriptStore};
+use claude_code_sdk::compression::{decompress, Compression};
+use claude_code_sdk::debugger::Debugger;
+use claude_code_sdk::{Message, RunId};
+
+const NDJSON: &str = concat!(
+    r#"{"type":"system","content":"init"}"#,
+    "\n",
+    r#"{"type":"result","id":"r1","exit_code":0}"#,
+    "\n",
+);
+
+#[test]
+fn test_round_trip_and_truncated_streams() {
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let compressed = compression.compress(NDJSON.as_bytes()).unwrap();
+        assert_eq!(Compression::detect(&compressed), compression);
+        assert_eq!(decompress(compressed.clone()).unwrap(), NDJSON.as_bytes());
+
+        // A killed run leaves the stream unfinished
+        let cut = compressed[..compressed.len() - 4].to_vec();
+        let partial = decompress(cut).unwrap();
+        assert!(NDJSON.as_bytes().starts_with(&partial));
+    }
+    assert_eq!(decompress(NDJSON.into()).unwrap(), NDJSON.as_bytes());
+}
+
+#[tokio::test]
+async fn test_compressed_archive_loads() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileTranscriptStore::new(dir.path()).with_compression(Compression::Zstd);
+    let messages = vec![Message::assistant_text("Done."), Message::result()];
+
+    let location = store.store(RunId::new(), None, &messages).await.unwrap();
+    assert!(location.ends_with(".ndjson.zst"));
+    assert_eq!(Debugger::load(&location).unwrap().messages().len(), 2);
+}
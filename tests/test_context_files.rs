@@ -0,0 +1,76 @@
+use claude_code_sdk::context_files::{ContextFile, ContextFiles};
+use claude_code_sdk::prompt::InputBlock;
+use claude_code_sdk::{ClaudeSDKError, PromptInput};
+use std::fs;
+use std::path::PathBuf;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("claude-sdk-context-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("README.md"), "# Adder\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_context_files_render_with_headers() {
+    let dir = workspace("render");
+    let files = ContextFiles::read(["src/lib.rs", "README.md"], Some(&dir)).unwrap();
+    assert_eq!(files.files.len(), 2);
+    assert_eq!(files.files[0].path, PathBuf::from("src/lib.rs"));
+
+    let rendered = files.render();
+    assert!(rendered.contains("<file path=\"src/lib.rs\">\npub fn add"));
+    assert!(rendered.contains("<file path=\"README.md\">\n# Adder\n</file>"));
+    assert_eq!(
+        files.estimated_tokens(),
+        files.files[0].estimated_tokens() + files.files[1].estimated_tokens()
+    );
+    assert_eq!(files.files[1].estimated_tokens(), 2);
+    assert!(files.summary().content.contains("2 context file(s)"));
+}
+
+#[test]
+fn test_context_files_precede_the_prompt() {
+    let dir = workspace("apply");
+    let files = ContextFiles::read(["README.md"], Some(&dir)).unwrap();
+
+    let PromptInput::Text(text) = files.apply("Review this.".into()) else {
+        panic!("expected a text prompt");
+    };
+    assert!(text.starts_with("The following files are provided as context:"));
+    assert!(text.ends_with("</file>\n\nReview this."));
+
+    let image = InputBlock::image_base64("image/png", "AAAA");
+    let PromptInput::Blocks(blocks) = files.apply(image.clone().into()) else {
+        panic!("expected blocks");
+    };
+    assert_eq!(blocks.len(), 2);
+    assert!(matches!(&blocks[0], InputBlock::Text { text } if text.contains("# Adder")));
+    assert_eq!(blocks[1], image);
+
+    assert_eq!(
+        ContextFiles::default().apply("Unchanged".into()),
+        PromptInput::text("Unchanged")
+    );
+}
+
+#[test]
+fn test_missing_or_binary_context_file() {
+    let dir = workspace("errors");
+    assert!(matches!(
+        ContextFile::read("missing.rs", Some(&dir)),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+
+    fs::write(dir.join("blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+    assert!(matches!(
+        ContextFile::read(dir.join("blob.bin"), None),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
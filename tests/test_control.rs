@@ -0,0 +1,513 @@
+#![cfg(feature = "subprocess")]
+
+#[cfg(unix)]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::control::*;
+
+#[test]
+fn test_parse_control_messages() {
+    let line = br#"{"type":"control_request","request_id":"cli_1","request":{"subtype":"hook_callback","callback_id":"hook_0","input":{}}}"#;
+    match ControlMessage::parse(line) {
+        Some(ControlMessage::ControlRequest(request)) => {
+            assert_eq!(request.request_id, "cli_1");
+            assert_eq!(request.subtype(), Some("hook_callback"));
+        }
+        other => panic!("expected a control request, got {:?}", other),
+    }
+
+    let line = br#"{"type":"control_response","response":{"subtype":"error","request_id":"req_0","error":"unknown hook"}}"#;
+    assert_eq!(
+        ControlMessage::parse(line),
+        Some(ControlMessage::ControlResponse {
+            response: ControlResponse::Error {
+                request_id: "req_0".to_string(),
+                error: "unknown hook".to_string(),
+            }
+        })
+    );
+}
+
+#[test]
+fn test_conversation_messages_are_not_control_messages() {
+    let line = br#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"see control_request"}]}}"#;
+    assert_eq!(ControlMessage::parse(line), None);
+    assert_eq!(ControlMessage::parse(b"not json"), None);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_control_requests_are_answered_on_stdin() {
+    use claude_code_sdk::hooks::{HookEvent, HookOutput};
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message, ResultMessage};
+    use serde_json::{json, Value};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let request = |id: &str, request: Value| json!({ "type": "control_request", "request_id": id, "request": request });
+    let hook_call = |id: &str, callback_id: &str| {
+        request(
+            id,
+            json!({
+                "subtype": "hook_callback",
+                "callback_id": callback_id,
+                "input": {
+                    "hook_event_name": "PreToolUse",
+                    "tool_name": "Bash",
+                    "tool_input": { "command": "ls" },
+                },
+                "tool_use_id": "toolu_1",
+            }),
+        )
+    };
+    let lines = [
+        hook_call("cli_1", "hook_0"),
+        request("cli_2", json!({ "subtype": "can_use_tool" })),
+        hook_call("cli_3", "hook_9"),
+    ];
+    // The CLI's own answer to `initialize` is a control message too
+    let initialized = json!({
+        "type": "control_response",
+        "response": { "subtype": "success", "request_id": "req_0" },
+    });
+    let assistant = serde_json::to_string(&Message::assistant_text("Listed")).unwrap();
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("run-1"))).unwrap();
+    // Record stdin: the initialize request, the prompt, the answer to each
+    // request, and whether stdin is closed after the result
+    let mut script = String::from(
+        "read -r line\necho \"$line\" > input\nread -r line\necho \"$line\" >> input\n",
+    );
+    for line in &lines {
+        script.push_str(&format!(
+            "echo '{}'\nread -r line\necho \"$line\" >> input\n",
+            line
+        ));
+    }
+    script.push_str(&format!(
+        "echo '{}'\necho '{}'\necho '{}'\n\
+         if read -r line; then echo open >> input; else echo closed >> input; fi\n",
+        initialized, assistant, result
+    ));
+    let cli = common::fake_cli(dir.path(), &script);
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .on_hook(HookEvent::PreToolUse, |input| async move {
+            assert_eq!(input.tool_use_id.as_deref(), Some("toolu_1"));
+            HookOutput::deny("not today")
+        });
+    let messages: Vec<Message> = query("List the files", Some(options))
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|message| !matches!(message, Message::SdkInfo(_)))
+        .collect()
+        .await;
+    // Control messages never reach the stream
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(matches!(messages[0], Message::Assistant(_)));
+    assert!(matches!(messages[1], Message::Result(_)));
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let written: Vec<&str> = input.lines().collect();
+    assert_eq!(written.len(), 6, "{}", input);
+    let written_json: Vec<Value> = written[..5]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let initialize = &written_json[0];
+    assert_eq!(initialize["request_id"], "req_0");
+    assert_eq!(initialize["request"]["subtype"], "initialize");
+    assert_eq!(
+        initialize["request"]["hooks"]["PreToolUse"][0]["hookCallbackIds"],
+        json!(["hook_0"])
+    );
+    assert_eq!(written_json[1]["type"], "user");
+
+    let answer = &written_json[2]["response"];
+    assert_eq!(answer["subtype"], "success");
+    assert_eq!(answer["request_id"], "cli_1");
+    assert_eq!(
+        answer["response"]["hookSpecificOutput"]["permissionDecision"],
+        "deny"
+    );
+    assert_eq!(
+        written_json[3]["response"],
+        json!({
+            "subtype": "error",
+            "request_id": "cli_2",
+            "error": "Unsupported control request: can_use_tool",
+        })
+    );
+    assert_eq!(written_json[4]["response"]["subtype"], "error");
+    assert_eq!(written_json[4]["response"]["request_id"], "cli_3");
+    // The result closes stdin
+    assert_eq!(written[5], "closed");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_panicking_hook_is_answered_with_an_error() {
+    use claude_code_sdk::hooks::HookEvent;
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message};
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cli = common::answering_cli(
+        dir.path(),
+        &[common::pre_tool_use(
+            "cli_1",
+            "Bash",
+            json!({ "command": "ls" }),
+        )],
+    );
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .on_hook(
+            HookEvent::PreToolUse,
+            |_| async move { panic!("hook failed") },
+        );
+    let items: Vec<_> = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        query("List the files", Some(options))
+            .await
+            .unwrap()
+            .collect(),
+    )
+    .await
+    .expect("the CLI got an answer");
+    assert!(matches!(items.last(), Some(Ok(Message::Result(_)))));
+
+    let answers = common::hook_answers(dir.path());
+    assert_eq!(answers[0]["response"]["subtype"], "error");
+    assert_eq!(answers[0]["response"]["request_id"], "cli_1");
+    assert!(answers[0]["response"]["error"]
+        .as_str()
+        .unwrap()
+        .contains("hook failed"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_max_parallel_tools_holds_back_extra_calls() {
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message, ResultMessage};
+    use serde_json::{json, Value};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let hook_call = |id: &str, callback_id: &str, event: &str, tool_use_id: &str| {
+        json!({
+            "type": "control_request",
+            "request_id": id,
+            "request": {
+                "subtype": "hook_callback",
+                "callback_id": callback_id,
+                "input": { "hook_event_name": event, "tool_name": "Bash" },
+                "tool_use_id": tool_use_id,
+            },
+        })
+    };
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("run-1"))).unwrap();
+    // Three calls start at once with room for two. The third is answered
+    // once the first finishes, not within half a second before
+    let script = format!(
+        "read -r line\necho \"$line\" > input\nread -r line\n\
+         echo '{}'\nread -r line\necho \"$line\" >> input\n\
+         echo '{}'\nread -r line\necho \"$line\" >> input\n\
+         echo '{}'\n\
+         if timeout 0.5 head -n 1 >> input; then echo early >> input; else echo waited >> input; fi\n\
+         echo '{}'\nread -r line\necho \"$line\" >> input\n\
+         if grep -q waited input; then read -r line; echo \"$line\" >> input; fi\n\
+         echo '{}'\n",
+        hook_call("cli_1", "hook_0", "PreToolUse", "toolu_1"),
+        hook_call("cli_2", "hook_0", "PreToolUse", "toolu_2"),
+        hook_call("cli_3", "hook_0", "PreToolUse", "toolu_3"),
+        hook_call("cli_4", "hook_1", "PostToolUse", "toolu_1"),
+        result,
+    );
+    let cli = common::fake_cli(dir.path(), &script);
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .with_max_parallel_tools(2);
+    let messages: Vec<Message> = query("Run the checks", Some(options))
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let written: Vec<&str> = input.lines().collect();
+    assert_eq!(written.len(), 6, "{}", input);
+    let initialize: Value = serde_json::from_str(written[0]).unwrap();
+    let hooks = &initialize["request"]["hooks"];
+    assert_eq!(hooks["PreToolUse"][0]["hookCallbackIds"], json!(["hook_0"]));
+    assert_eq!(
+        hooks["PostToolUse"][0]["hookCallbackIds"],
+        json!(["hook_1"])
+    );
+
+    let answered = |line: &str| -> String {
+        let answer: Value = serde_json::from_str(line).unwrap();
+        assert_eq!(answer["response"]["subtype"], "success");
+        answer["response"]["request_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(answered(written[1]), "cli_1");
+    assert_eq!(answered(written[2]), "cli_2");
+    assert_eq!(written[3], "waited");
+    let mut rest = vec![answered(written[4]), answered(written[5])];
+    rest.sort();
+    assert_eq!(rest, ["cli_3", "cli_4"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_max_parallel_tools_without_tool_use_ids() {
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message, ResultMessage};
+    use serde_json::{json, Value};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let hook_call = |id: &str, callback_id: &str, event: &str, tool_use_id: Option<&str>| {
+        let mut request = json!({
+            "subtype": "hook_callback",
+            "callback_id": callback_id,
+            "input": { "hook_event_name": event, "tool_name": "Bash" },
+        });
+        if let Some(tool_use_id) = tool_use_id {
+            request["tool_use_id"] = tool_use_id.into();
+        }
+        json!({ "type": "control_request", "request_id": id, "request": request })
+    };
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("run-1"))).unwrap();
+    // Two calls without ids start at once with room for one: neither takes
+    // the slot from the other, nor from the call with an id after them,
+    // which still holds back the next one until it finishes
+    let script = format!(
+        "answer() {{ timeout 2 head -n \"$1\" >> input || echo stuck >> input; }}\n\
+         read -r line\nread -r line\n\
+         echo '{}'\necho '{}'\nanswer 2\n\
+         echo '{}'\nanswer 1\n\
+         echo '{}'\n\
+         if timeout 0.5 head -n 1 >> input; then echo early >> input; else echo waited >> input; fi\n\
+         echo '{}'\n\
+         if grep -q waited input; then answer 2; else answer 1; fi\n\
+         echo '{}'\n",
+        hook_call("cli_1", "hook_0", "PreToolUse", None),
+        hook_call("cli_2", "hook_0", "PreToolUse", None),
+        hook_call("cli_3", "hook_0", "PreToolUse", Some("toolu_1")),
+        hook_call("cli_4", "hook_0", "PreToolUse", Some("toolu_2")),
+        hook_call("cli_5", "hook_1", "PostToolUse", Some("toolu_1")),
+        result,
+    );
+    let cli = common::fake_cli(dir.path(), &script);
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .with_max_parallel_tools(1);
+    let messages: Vec<Message> = query("Run the checks", Some(options))
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let written: Vec<&str> = input.lines().collect();
+    assert_eq!(written.len(), 6, "{}", input);
+    let answered = |line: &str| -> String {
+        let answer: Value = serde_json::from_str(line).unwrap();
+        answer["response"]["request_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let mut unnamed = vec![answered(written[0]), answered(written[1])];
+    unnamed.sort();
+    assert_eq!(unnamed, ["cli_1", "cli_2"]);
+    assert_eq!(answered(written[2]), "cli_3");
+    assert_eq!(written[3], "waited");
+    let mut rest = vec![answered(written[4]), answered(written[5])];
+    rest.sort();
+    assert_eq!(rest, ["cli_4", "cli_5"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_tool_result_hooks_rewrite_mcp_output() {
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message, ResultMessage};
+    use serde_json::{json, Value};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let hook_call = json!({
+        "type": "control_request",
+        "request_id": "cli_1",
+        "request": {
+            "subtype": "hook_callback",
+            "callback_id": "hook_0",
+            "input": {
+                "hook_event_name": "PostToolUse",
+                "tool_name": "mcp__build__log",
+                "tool_input": {},
+                "tool_response": [{ "type": "text", "text": "warning: unused" }],
+            },
+            "tool_use_id": "toolu_1",
+        },
+    });
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("run-1"))).unwrap();
+    let script = format!(
+        "read -r line\necho \"$line\" > input\nread -r line\n\
+         echo '{}'\nread -r line\necho \"$line\" >> input\n\
+         echo '{}'\n",
+        hook_call, result,
+    );
+    let cli = common::fake_cli(dir.path(), &script);
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .on_tool_result(|context, text| {
+            assert_eq!(context.tool_use_id, "toolu_1");
+            Some(text.to_uppercase())
+        });
+    let messages: Vec<Message> = query("Build it", Some(options))
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let written: Vec<Value> = input
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(written.len(), 2, "{}", input);
+    // Only MCP tools can have their output replaced
+    assert_eq!(
+        written[0]["request"]["hooks"]["PostToolUse"][0]["matcher"],
+        "mcp__.*"
+    );
+    let answer = &written[1]["response"];
+    assert_eq!(answer["request_id"], "cli_1");
+    assert_eq!(
+        answer["response"]["hookSpecificOutput"],
+        json!({
+            "hookEventName": "PostToolUse",
+            "updatedMCPToolOutput": [{ "type": "text", "text": "WARNING: UNUSED" }],
+        })
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_tool_result_hooks_leave_built_in_tools_alone() {
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message, ResultMessage};
+    use serde_json::{json, Value};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let hook_call = |id: &str, tool_name: &str, tool_response: Value| {
+        json!({
+            "type": "control_request",
+            "request_id": id,
+            "request": {
+                "subtype": "hook_callback",
+                "callback_id": "hook_0",
+                "input": {
+                    "hook_event_name": "PostToolUse",
+                    "tool_name": tool_name,
+                    "tool_input": {},
+                    "tool_response": tool_response,
+                },
+                "tool_use_id": "toolu_1",
+            },
+        })
+    };
+    let bash = hook_call("cli_1", "Bash", json!("warning: unused"));
+    let failed = hook_call(
+        "cli_2",
+        "mcp__build__log",
+        json!({ "content": [{ "type": "text", "text": "error: failed" }], "isError": true }),
+    );
+    let result = serde_json::to_string(&Message::from(ResultMessage::new("run-1"))).unwrap();
+    let script = format!(
+        "read -r line\nread -r line\n\
+         echo '{}'\nread -r line\necho \"$line\" > input\n\
+         echo '{}'\nread -r line\necho \"$line\" >> input\n\
+         echo '{}'\n",
+        bash, failed, result,
+    );
+    let cli = common::fake_cli(dir.path(), &script);
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path())
+        .on_tool_result(|context, text| {
+            assert_eq!(context.tool_name, Some("mcp__build__log"));
+            assert!(context.is_error);
+            Some(text.to_uppercase())
+        });
+    let messages: Vec<Message> = query("Build it", Some(options))
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+
+    let input = std::fs::read_to_string(dir.path().join("input")).unwrap();
+    let answers: Vec<Value> = input
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["response"].clone())
+        .collect();
+    assert_eq!(answers.len(), 2, "{}", input);
+    // The Bash output passes through unchanged
+    assert_eq!(answers[0]["request_id"], "cli_1");
+    assert!(answers[0]["response"].get("hookSpecificOutput").is_none());
+    // The rewritten MCP output is still an error
+    assert_eq!(answers[1]["request_id"], "cli_2");
+    assert_eq!(
+        answers[1]["response"]["hookSpecificOutput"]["updatedMCPToolOutput"],
+        json!({ "content": [{ "type": "text", "text": "ERROR: FAILED" }], "isError": true })
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_max_parallel_tools_needs_callbacks() {
+    use claude_code_sdk::{query, ClaudeCodeOptions, ClaudeSDKError};
+
+    let dir = tempfile::tempdir().unwrap();
+    // A CLI whose help lists no `--input-format`
+    let cli = common::fake_cli_with_help(
+        dir.path(),
+        Some("  --model <model>  --disallowedTools <tools...>"),
+        "exit 1",
+    );
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_max_parallel_tools(2);
+    let error = query("Run the checks", Some(options)).await.err().unwrap();
+    assert!(
+        matches!(&error, ClaudeSDKError::UnsupportedOption { option, .. } if option == "max_parallel_tools"),
+        "{:?}",
+        error
+    );
+}
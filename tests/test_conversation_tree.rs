@@ -0,0 +1,110 @@
+use claude_code_sdk::conversation_tree::ConversationTree;
+use claude_code_sdk::{Checkpoint, ClaudeCodeOptions, ClaudeSDKError, Usage};
+
+fn checkpoint(session_id: &str) -> Checkpoint {
+    Checkpoint {
+        session_id: session_id.to_string(),
+        turn_count: 2,
+        usage: Usage::default(),
+        workspace: None,
+    }
+}
+
+/// root
+/// ├── a
+/// │   └── a1
+/// └── b
+fn tree() -> ConversationTree {
+    let mut tree = ConversationTree::new();
+    tree.record(None, &checkpoint("root"), "Sketch a cache")
+        .unwrap();
+    tree.record(Some("root"), &checkpoint("a"), "Use an LRU")
+        .unwrap();
+    tree.record(Some("a"), &checkpoint("a1"), "Make it sharded")
+        .unwrap();
+    tree.record(Some("root"), &checkpoint("b"), "Use a TTL cache")
+        .unwrap();
+    tree
+}
+
+fn ids<'a>(
+    nodes: impl IntoIterator<Item = &'a claude_code_sdk::conversation_tree::ConversationNode>,
+) -> Vec<&'a str> {
+    nodes
+        .into_iter()
+        .map(|node| node.session_id.as_str())
+        .collect()
+}
+
+#[test]
+fn test_traversal() {
+    let tree = tree();
+
+    assert_eq!(ids(tree.roots()), ["root"]);
+    assert_eq!(ids(tree.children("root")), ["a", "b"]);
+    assert_eq!(ids(tree.descendants("root")), ["a", "a1", "b"]);
+    assert_eq!(ids(tree.path("a1")), ["root", "a", "a1"]);
+    assert_eq!(ids(tree.leaves()), ["a1", "b"]);
+    assert_eq!(
+        tree.get("a1").unwrap().prompt.as_deref(),
+        Some("Make it sharded")
+    );
+
+    let error = ConversationTree::new()
+        .record(Some("missing"), &checkpoint("x"), "")
+        .unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::Checkpoint { .. }));
+}
+
+#[test]
+fn test_traversal_of_cyclic_json() {
+    // Edited by hand so that root and a are each other's parent
+    let mut json: serde_json::Value = serde_json::from_str(&tree().to_json().unwrap()).unwrap();
+    json["nodes"]["root"]["parent"] = "a".into();
+    json["nodes"]["a"]["children"] = serde_json::json!(["a1", "root"]);
+    let tree = ConversationTree::from_json(&json.to_string()).unwrap();
+
+    assert_eq!(ids(tree.path("a1")), ["root", "a", "a1"]);
+    assert_eq!(ids(tree.descendants("root")), ["a", "a1", "b"]);
+    assert_eq!(ids(tree.descendants("a")), ["a1", "root", "b"]);
+}
+
+#[test]
+fn test_prune() {
+    let mut tree = tree();
+
+    let removed = tree.prune("a").unwrap();
+    assert_eq!(ids(&removed), ["a", "a1"]);
+    assert_eq!(ids(tree.children("root")), ["b"]);
+    assert_eq!(tree.len(), 2);
+    assert!(tree.prune("a").is_err());
+}
+
+#[test]
+fn test_fork_options() {
+    let tree = tree();
+
+    let options = tree
+        .fork_options("a", Some(ClaudeCodeOptions::new().with_max_turns(3)))
+        .unwrap();
+    assert_eq!(options.resume.as_deref(), Some("a"));
+    assert_eq!(options.fork_session, Some(true));
+    assert_eq!(options.max_turns, Some(3));
+    assert!(tree.fork_options("missing", None).is_err());
+
+    assert!(ClaudeCodeOptions::new()
+        .with_fork_session()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn test_save_and_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trees/conversations.json");
+    let mut tree = tree();
+    tree.set_label("b", "ttl").unwrap();
+
+    tree.save(&path).unwrap();
+    assert_eq!(ConversationTree::load(&path).unwrap(), tree);
+}
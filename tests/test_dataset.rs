@@ -0,0 +1,92 @@
+use claude_code_sdk::dataset::{Conversation, DatasetFormat};
+use claude_code_sdk::Message;
+use serde_json::json;
+
+fn conversation() -> Conversation {
+    Conversation::new(vec![
+        Message::system("init"),
+        Message::assistant_text("Let me look."),
+        Message::tool_use_with_id("toolu_abc", "Bash", json!({ "command": "ls" })),
+        Message::tool_result("toolu_abc", "Cargo.toml\nsrc"),
+        // Never answered, so dropped
+        Message::tool_use_with_id("toolu_def", "Read", json!("src/lib.rs")),
+        Message::assistant_text("There is a manifest and a src directory."),
+        Message::result(),
+    ])
+    .with_system("You are terse.")
+    .with_prompt("What is here?")
+}
+
+#[test]
+fn test_anthropic_example() {
+    let example = DatasetFormat::Anthropic.example(&conversation());
+    assert_eq!(
+        example,
+        json!({
+            "system": "You are terse.",
+            "messages": [
+                { "role": "user", "content": [{ "type": "text", "text": "What is here?" }] },
+                { "role": "assistant", "content": [
+                    { "type": "text", "text": "Let me look." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "Bash", "input": { "command": "ls" } },
+                ] },
+                { "role": "user", "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": "toolu_1",
+                    "content": "Cargo.toml\nsrc",
+                    "is_error": false,
+                }] },
+                { "role": "assistant", "content": [
+                    { "type": "text", "text": "There is a manifest and a src directory." },
+                ] },
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_openai_example() {
+    let example = DatasetFormat::OpenAi.example(&conversation());
+    assert_eq!(
+        example,
+        json!({
+            "messages": [
+                { "role": "system", "content": "You are terse." },
+                { "role": "user", "content": "What is here?" },
+                { "role": "assistant", "content": "Let me look.", "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "Bash", "arguments": "{\"command\":\"ls\"}" },
+                }] },
+                { "role": "tool", "tool_call_id": "call_1", "content": "Cargo.toml\nsrc" },
+                { "role": "assistant", "content": "There is a manifest and a src directory." },
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_append_jsonl() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("evals").join("dataset.jsonl");
+    let conversations = [
+        conversation(),
+        Conversation::new(vec![Message::user_text("Hi")]),
+    ];
+
+    DatasetFormat::Anthropic
+        .append_jsonl(&path, &conversations)
+        .unwrap();
+    DatasetFormat::Anthropic
+        .append_jsonl(&path, &conversations[..1])
+        .unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<serde_json::Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], lines[2]);
+    assert_eq!(lines[1]["messages"][0]["content"][0]["text"], "Hi");
+}
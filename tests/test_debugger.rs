@@ -0,0 +1,92 @@
+use claude_code_sdk::debugger::{Breakpoint, Debugger};
+use claude_code_sdk::ClaudeCodeOptions;
+
+const TRANSCRIPT: &str = r#"{"type":"user","content":[{"type":"text","text":"Fix the tests"}]}
+{"type":"assistant","content":[{"type":"text","text":"Running the tests."},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test"}}]}
+{"type":"tool_progress","tool_use_id":"t1","tool_name":"Bash","elapsed_time_seconds":1.0}
+{"type":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"1 failed","is_error":true}]}
+{"type":"assistant","content":[{"type":"tool_use","id":"t2","name":"Edit","input":{"file_path":"src/lib.rs"}}]}
+
+{"type":"user","content":[{"type":"tool_result","tool_use_id":"t2","content":"ok"}]}
+{"type":"result","id":"r1","exit_code":0,"session_id":"s1","num_turns":2,"cost_usd":0.5}
+"#;
+
+#[test]
+fn test_step_and_state() {
+    let mut debugger = Debugger::from_ndjson(TRANSCRIPT).unwrap();
+    assert_eq!(debugger.messages().len(), 6);
+    assert_eq!(debugger.position(), None);
+    assert_eq!(debugger.state().tool_calls.len(), 0);
+
+    debugger.step();
+    debugger.step();
+    let state = debugger.state();
+    assert_eq!(state.index, 1);
+    assert_eq!(state.turn_count, 1);
+    assert_eq!(state.last_text.as_deref(), Some("Running the tests."));
+    assert_eq!(state.pending_tools().count(), 1);
+
+    debugger.step();
+    let state = debugger.state();
+    assert_eq!(state.pending_tools().count(), 0);
+    assert_eq!(
+        state.tool_calls[0].result.as_ref().unwrap().is_error,
+        Some(true)
+    );
+
+    debugger.seek(5);
+    assert!(debugger.is_at_end());
+    assert!(debugger.step().is_none());
+    let state = debugger.state();
+    assert_eq!(state.session_id.as_deref(), Some("s1"));
+    assert_eq!(state.usage.cost_usd, 0.5);
+
+    debugger.step_back();
+    assert_eq!(debugger.position(), Some(4));
+}
+
+#[test]
+fn test_navigation_to_tools_and_breakpoints() {
+    let mut debugger = Debugger::from_ndjson(TRANSCRIPT).unwrap();
+    assert_eq!(debugger.next_tool().unwrap().name, "Bash");
+    assert_eq!(debugger.next_tool().unwrap().name, "Edit");
+    assert!(debugger.next_tool().is_none());
+    assert_eq!(debugger.position(), Some(3));
+
+    debugger.reset();
+    debugger.add_breakpoint(Breakpoint::tool("Edit"));
+    debugger.add_breakpoint(Breakpoint::ToolError);
+    assert_eq!(debugger.continue_to_breakpoint(), Some(2));
+    assert_eq!(debugger.continue_to_breakpoint(), Some(3));
+    assert_eq!(debugger.continue_to_breakpoint(), None);
+    assert_eq!(debugger.position(), Some(3));
+}
+
+#[test]
+fn test_rerun_options() {
+    let mut debugger = Debugger::from_ndjson(TRANSCRIPT).unwrap();
+
+    debugger.seek(2);
+    let (prompt, options) = debugger.rerun_options("Only fix the failing test", None);
+    assert!(options.resume.is_none());
+    assert!(prompt.contains("[tool call Bash] {\"command\":\"cargo test\"}"));
+    assert!(prompt.contains("[tool error] 1 failed"));
+    assert!(!prompt.contains("src/lib.rs"));
+    assert!(prompt.ends_with("Continue from this point: Only fix the failing test"));
+
+    debugger.seek(5);
+    let (prompt, options) = debugger.rerun_options(
+        "Now add a test",
+        Some(ClaudeCodeOptions::new().with_max_turns(2)),
+    );
+    assert_eq!(prompt, "Now add a test");
+    assert_eq!(options.resume.as_deref(), Some("s1"));
+    assert_eq!(options.fork_session, Some(true));
+    assert_eq!(options.max_turns, Some(2));
+}
+
+#[test]
+fn test_invalid_transcript() {
+    let error = Debugger::from_ndjson("{\"type\":\"user\",\"content\":[]}\nnot json").unwrap_err();
+    assert!(error.to_string().contains("Line 2"));
+}
@@ -0,0 +1,56 @@
+use claude_code_sdk::diagnostics::DecodeDiagnostics;
+use claude_code_sdk::output::{OutputParser, StreamJsonParser};
+use claude_code_sdk::protocol::decode_line;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+
+#[test]
+fn test_decode_error_locates_failure() {
+    let line = r#"{"type":"assistant","content":[{"type":"text","text":42}]}"#;
+    let error = decode_line(line, None, None).unwrap_err();
+    match &error {
+        ClaudeSDKError::CLIJSONDecode { path, payload, .. } => {
+            assert_eq!(path.as_deref(), Some("content[0]"));
+            assert_eq!(payload.as_deref(), Some(line));
+        }
+        other => panic!("Expected CLIJSONDecode, got {:?}", other),
+    }
+    assert!(error.to_string().contains("at `content[0]` in line: {"));
+
+    let error = decode_line(r#"{"type":"handoff","target":"reviewer"}"#, None, None).unwrap_err();
+    assert!(error.to_string().contains("unknown message type `handoff`"));
+}
+
+#[test]
+fn test_redacts_truncates_and_dumps_payloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ClaudeCodeOptions::new().with_decode_diagnostics(
+        DecodeDiagnostics::new()
+            .redacted()
+            .with_dump_dir(dir.path())
+            .with_max_payload_len(40),
+    );
+    let mut parser = StreamJsonParser::new(&options);
+    let line = r#"{"type":"result","id":"sk-ant-REDACTED","exit_code":"zero"}"#;
+
+    let error = parser.parse_line(line).unwrap_err();
+    let ClaudeSDKError::CLIJSONDecode {
+        path,
+        payload,
+        dump,
+        ..
+    } = error
+    else {
+        panic!("Expected CLIJSONDecode");
+    };
+    assert_eq!(path.as_deref(), Some("exit_code"));
+    let payload = payload.unwrap();
+    assert!(payload.starts_with(r#"{"type":"result","id":"<redacted"#));
+    assert!(payload.ends_with(" chars)"));
+
+    let dumped: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dump.unwrap()).unwrap()).unwrap();
+    assert_eq!(dumped["path"], "exit_code");
+    let saved = dumped["payload"].as_str().unwrap();
+    assert!(saved.contains("<redacted:anthropic-key>"));
+    assert!(!saved.contains("sk-ant-"));
+}
@@ -48,7 +48,7 @@ fn test_cli_json_decode_error() {
     let error = ClaudeSDKError::cli_json_decode("Invalid JSON");
 
     match &error {
-        ClaudeSDKError::CLIJSONDecode { message } => {
+        ClaudeSDKError::CLIJSONDecode { message, .. } => {
             assert_eq!(message, "Invalid JSON");
         }
         _ => panic!("Expected CLIJSONDecode variant"),
@@ -106,6 +106,7 @@ fn test_error_display() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_literal_unwrap)]
 fn test_result_type() {
     let success: Result<i32> = Ok(42);
     let failure: Result<i32> = Err(ClaudeSDKError::CLINotFound);
@@ -116,3 +117,89 @@ fn test_result_type() {
     assert_eq!(success.unwrap(), 42);
     assert!(matches!(failure.unwrap_err(), ClaudeSDKError::CLINotFound));
 }
+
+#[test]
+fn test_error_context_from_command() {
+    let long_prompt = "x".repeat(200);
+    let mut cmd = std::process::Command::new("/usr/local/bin/claude-code");
+    cmd.arg("--format")
+        .arg("json")
+        .arg(&long_prompt)
+        .current_dir("/tmp")
+        .env("ANTHROPIC_API_KEY", "secret");
+
+    let context = ErrorContext::from_command(&cmd);
+
+    assert_eq!(context.binary, Some("/usr/local/bin/claude-code".into()));
+    assert_eq!(context.args[..2], ["--format", "json"]);
+    assert!(context.args[2].ends_with("... (200 chars)"));
+    assert_eq!(context.cwd, Some("/tmp".into()));
+    assert_eq!(context.env_keys, vec!["ANTHROPIC_API_KEY".to_string()]);
+    assert!(!context.to_string().contains("secret"));
+}
+
+#[test]
+fn test_error_with_context() {
+    let context = ErrorContext {
+        binary: Some("/usr/bin/claude-code".into()),
+        ..Default::default()
+    };
+    let error = ClaudeSDKError::cli_connection("spawn failed").with_context(context.clone());
+
+    assert_eq!(error.context(), Some(&context));
+    assert!(matches!(error.root(), ClaudeSDKError::CLIConnection { .. }));
+    assert_eq!(
+        error.to_string(),
+        "CLI connection error: spawn failed [binary: /usr/bin/claude-code, args: []]"
+    );
+}
+
+#[test]
+fn test_error_to_json() {
+    let error = ClaudeSDKError::process(2, "boom").with_context(ErrorContext {
+        args: vec!["--print".to_string(), "secret prompt".to_string()],
+        ..Default::default()
+    });
+    assert_eq!(error.code(), ErrorCode::ProcessFailed);
+    assert_eq!(
+        error.to_json(),
+        serde_json::json!({
+            "code": "process_failed",
+            "message": "Process failed with exit code 2: boom",
+            "retryable": false,
+            "details": { "exit_code": 2 },
+        })
+    );
+
+    let json = ClaudeSDKError::SlowStart {
+        deadline: std::time::Duration::from_millis(1500),
+        model: None,
+    }
+    .to_json();
+    assert_eq!(json["code"], "slow_start");
+    assert_eq!(json["details"], serde_json::json!({ "deadline_ms": 1500 }));
+
+    let parsed: ErrorJson = serde_json::from_value(ClaudeSDKError::CLINotFound.to_json()).unwrap();
+    assert_eq!(parsed.code, ErrorCode::CliNotFound);
+    assert!(parsed.details.is_empty());
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    for code in [
+        ErrorCode::CliConnection,
+        ErrorCode::ApiRateLimited,
+        ErrorCode::UnknownToolUse,
+        ErrorCode::BinaryDiscovery,
+    ] {
+        assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+    }
+    assert_eq!(ErrorCode::ApiRateLimited.http_status(), 429);
+    assert_eq!(ErrorCode::InvalidOptions.http_status(), 400);
+    assert_eq!(ErrorCode::ProcessFailed.http_status(), 500);
+
+    // Codes added by later versions still parse
+    let code: ErrorCode = serde_json::from_value(serde_json::json!("quota_exhausted")).unwrap();
+    assert_eq!(code, ErrorCode::Unknown);
+    assert_eq!(code.http_status(), 500);
+}
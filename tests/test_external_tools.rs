@@ -0,0 +1,242 @@
+#[cfg(all(feature = "subprocess", unix))]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::external_tools::{qualified_name, ExternalTool};
+use claude_code_sdk::sdk_mcp::SdkMcpServer;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+
+#[test]
+fn test_external_tools_option() {
+    let deploy = ExternalTool::new("deploy")
+        .with_description("Deploy a branch")
+        .with_input_schema(serde_json::json!({
+            "type": "object",
+            "properties": {"branch": {"type": "string"}},
+        }));
+    let options = ClaudeCodeOptions::new().with_external_tools([deploy.clone(), "db_query".into()]);
+    options.validate().unwrap();
+
+    let json = serde_json::to_value(&options).unwrap();
+    assert_eq!(json["external_tools"][0]["name"], "deploy");
+    assert_eq!(
+        json["external_tools"][1]["input_schema"],
+        serde_json::json!({"type": "object"})
+    );
+    let restored: ClaudeCodeOptions = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        restored.external_tools,
+        Some(vec![deploy, ExternalTool::new("db_query")])
+    );
+
+    // Tool names alone are enough
+    let restored: ClaudeCodeOptions =
+        serde_json::from_value(serde_json::json!({"external_tools": [{"name": "deploy"}]}))
+            .unwrap();
+    assert_eq!(
+        restored.external_tools,
+        Some(vec![ExternalTool::new("deploy")])
+    );
+    assert_eq!(qualified_name("deploy"), "mcp__external__deploy");
+}
+
+#[test]
+fn test_external_tool_names_are_validated() {
+    let options = ClaudeCodeOptions::new().with_external_tools(["deploy", " "]);
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+
+    // The server external tools are offered through cannot be taken
+    let options = ClaudeCodeOptions::new()
+        .with_external_tools(["deploy"])
+        .with_sdk_mcp_server(SdkMcpServer::new("external"));
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+
+    let error = ClaudeSDKError::UnknownToolUse {
+        tool_use_id: "toolu_1".to_string(),
+    };
+    assert!(error.to_string().contains("toolu_1"));
+}
+
+#[cfg(all(feature = "subprocess", unix))]
+mod cli {
+    use super::common::{fake_cli, print};
+    use claude_code_sdk::{
+        query_with_handle, ClaudeCodeOptions, ClaudeSDKError, Message, ResultMessage,
+    };
+    use serde_json::{json, Value};
+    use std::path::Path;
+    use tokio_stream::StreamExt;
+
+    /// A shell command sending the control request `request`, then
+    /// recording the SDK's answer in `input`.
+    fn ask(id: &str, request: Value) -> String {
+        let line = json!({ "type": "control_request", "request_id": id, "request": request });
+        format!(
+            "echo '{}'\ntimeout 5 head -n 1 >> input || echo stuck >> input",
+            line
+        )
+    }
+
+    /// What the CLI does for a call of `mcp__external__deploy`: ask the
+    /// `PreToolUse` hook, then call the tool on the SDK MCP server.
+    fn call_deploy() -> String {
+        let input = json!({"branch": "staging"});
+        let hook = ask(
+            "cli_1",
+            json!({
+                "subtype": "hook_callback",
+                "callback_id": "hook_0",
+                "input": {
+                    "hook_event_name": "PreToolUse",
+                    "tool_name": "mcp__external__deploy",
+                    "tool_input": input,
+                },
+                "tool_use_id": "toolu_1",
+            }),
+        );
+        let call = ask(
+            "cli_2",
+            json!({
+                "subtype": "mcp_message",
+                "server_name": "external",
+                "message": {
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "tools/call",
+                    "params": {"name": "deploy", "arguments": input},
+                },
+            }),
+        );
+        format!("{}\n{}", hook, call)
+    }
+
+    fn tool_uses() -> Vec<Message> {
+        vec![
+            Message::tool_use_with_id(
+                "toolu_1",
+                "mcp__external__deploy",
+                json!({"branch": "staging"}),
+            ),
+            // Not an external tool, so the CLI answers it
+            Message::tool_use_with_id("toolu_2", "Read", json!({"file_path": "README.md"})),
+        ]
+    }
+
+    /// The lines the CLI recorded in `input`.
+    fn recorded(dir: &Path) -> Vec<Value> {
+        std::fs::read_to_string(dir.join("input"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_external_tools_are_answered_through_the_sdk_mcp_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = format!(
+            "echo \"$@\" > args\nhead -n 2 > input\n{}\n{}\n{}",
+            print(&tool_uses()),
+            call_deploy(),
+            print(&[ResultMessage::new("run-1").into()]),
+        );
+        let options = ClaudeCodeOptions::new()
+            .with_cli_path(fake_cli(dir.path(), &body))
+            .with_cwd(dir.path())
+            .with_external_tools(["deploy"]);
+        let mut handle = query_with_handle("Deploy", Some(options)).await.unwrap();
+        let sender = handle.tool_results().unwrap();
+
+        let mut submitted = false;
+        while let Some(message) = handle.next().await {
+            if matches!(message.unwrap(), Message::Assistant(_)) && !submitted {
+                // Only the external tool is the application's to answer
+                let pending = sender.pending();
+                assert_eq!(pending.len(), 1);
+                assert_eq!(pending[0].id, "toolu_1");
+                assert_eq!(pending[0].name, "deploy");
+                assert_eq!(pending[0].input["branch"], "staging");
+
+                let error = handle
+                    .submit_tool_result("toolu_2", "done")
+                    .await
+                    .unwrap_err();
+                assert!(matches!(error, ClaudeSDKError::UnknownToolUse { .. }));
+                handle
+                    .submit_tool_result("toolu_1", "deployed")
+                    .await
+                    .unwrap();
+                assert!(sender.pending().is_empty());
+                // Answered calls cannot be answered again
+                let error = handle
+                    .submit_tool_error("toolu_1", "failed")
+                    .await
+                    .unwrap_err();
+                assert!(matches!(error, ClaudeSDKError::UnknownToolUse { .. }));
+                submitted = true;
+            }
+        }
+        assert!(submitted);
+
+        let args = std::fs::read_to_string(dir.path().join("args")).unwrap();
+        assert!(!args.contains("--external-tool"), "{}", args);
+        assert!(
+            args.contains(
+                r#"--mcp-config {"mcpServers":{"external":{"name":"external","type":"sdk"}}}"#
+            ),
+            "{}",
+            args
+        );
+
+        let written = recorded(dir.path());
+        assert_eq!(written.len(), 4, "{:?}", written);
+        let hooks = &written[0]["request"]["hooks"]["PreToolUse"];
+        assert_eq!(hooks[0]["matcher"], "mcp__external__.*");
+        assert_eq!(hooks[0]["hookCallbackIds"], json!(["hook_0"]));
+        assert_eq!(written[1]["type"], "user");
+
+        let approved = &written[2]["response"];
+        assert_eq!(approved["request_id"], "cli_1");
+        assert_eq!(
+            approved["response"]["hookSpecificOutput"]["permissionDecision"],
+            "allow"
+        );
+        let answered = &written[3]["response"];
+        assert_eq!(answered["request_id"], "cli_2");
+        let result = &answered["response"]["mcp_response"]["result"];
+        assert_eq!(result["content"][0]["text"], "deployed");
+        assert_ne!(result["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_calls_are_dropped_with_the_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = format!(
+            "head -n 2 > input\n{}\n{}",
+            print(&tool_uses()),
+            print(&[ResultMessage::new("run-1").into()]),
+        );
+        let options = ClaudeCodeOptions::new()
+            .with_cli_path(fake_cli(dir.path(), &body))
+            .with_cwd(dir.path())
+            .with_external_tools(["deploy"]);
+        let mut handle = query_with_handle("Deploy", Some(options)).await.unwrap();
+        let sender = handle.tool_results().unwrap();
+
+        while let Some(message) = handle.next().await {
+            message.unwrap();
+        }
+        assert!(sender.pending().is_empty());
+        let error = handle
+            .submit_tool_result("toolu_1", "deployed")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ClaudeSDKError::UnknownToolUse { .. }));
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(all(feature = "subprocess", unix))]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::file_lock::{LockEvent, LockScope, WorkspaceLocks};
+use claude_code_sdk::{ClaudeSDKError, ErrorCode, Message, RunId, ToolUseBlock};
+use serde_json::json;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+fn write(path: &str) -> ToolUseBlock {
+    ToolUseBlock::new(
+        "toolu_1",
+        "Write",
+        json!({ "file_path": path, "content": "" }),
+    )
+}
+
+#[test]
+fn test_second_run_conflicts_until_release() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let locks = WorkspaceLocks::new().on_event({
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event.clone())
+    });
+    let (first, second) = (RunId::new(), RunId::new());
+    let cwd = Path::new("/work/app");
+
+    assert!(locks.check(first, cwd, &write("src/main.rs")).is_none());
+    // The same run may edit its file again
+    assert!(locks.check(first, cwd, &write("./src/main.rs")).is_none());
+    let conflict = locks
+        .check(second, cwd, &write("/work/app/src/../src/main.rs"))
+        .unwrap();
+    assert_eq!(conflict.path, Path::new("/work/app/src/main.rs"));
+    assert_eq!(conflict.holder, first);
+    assert_eq!(locks.holder("/work/app/src/main.rs"), Some(first));
+
+    assert_eq!(
+        locks.release(first),
+        vec![Path::new("/work/app/src/main.rs")]
+    );
+    assert!(locks.check(second, cwd, &write("src/main.rs")).is_none());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 4);
+    assert!(matches!(&events[1], LockEvent::Conflict(c) if c.run_id == second));
+    assert!(matches!(&events[2], LockEvent::Released { run_id, .. } if *run_id == first));
+
+    let error = ClaudeSDKError::FileLockConflict { conflict };
+    assert_eq!(error.code(), ErrorCode::FileLockConflict);
+    assert_eq!(error.code().http_status(), 409);
+    assert_eq!(error.to_json()["details"]["tool_name"], "Write");
+}
+
+#[test]
+fn test_directory_scope_and_other_tools() {
+    let locks = WorkspaceLocks::new().with_scope(LockScope::Directory);
+    let (first, second) = (RunId::new(), RunId::new());
+    let cwd = Path::new("/work/app");
+
+    let edit = Message::tool_use_with_id(
+        "toolu_2",
+        "Edit",
+        json!({ "file_path": "src/lib.rs", "old_string": "a", "new_string": "b" }),
+    );
+    assert!(locks.scan(first, cwd, &edit).is_none());
+    assert_eq!(
+        locks.held(),
+        vec![(Path::new("/work/app/src").to_path_buf(), first)]
+    );
+
+    // Another file of the same directory is covered, reading it is not
+    assert!(locks.check(second, cwd, &write("src/other.rs")).is_some());
+    let read = ToolUseBlock::new("toolu_3", "Read", json!({ "file_path": "src/lib.rs" }));
+    assert!(locks.check(second, cwd, &read).is_none());
+    assert!(locks.check(second, cwd, &write("tests/lib.rs")).is_none());
+}
+
+// Analysis-only builds stop at the first Write instead
+#[cfg(all(feature = "subprocess", not(feature = "analysis-only")))]
+#[tokio::test]
+async fn test_conflicting_run_is_interrupted_without_hooks() {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, ClaudeCodeOptions, ResultMessage};
+    use tokio_stream::StreamExt;
+
+    let locks = WorkspaceLocks::new();
+    let (holder, run_id) = (RunId::new(), RunId::new());
+    let cwd = Path::new("/work/app");
+    assert!(locks.check(holder, cwd, &write("src/main.rs")).is_none());
+
+    // The mock transport reports tool calls without asking first
+    let mock = MockTransport::new()
+        .with_message(Message::tool_use_with_id(
+            "toolu_1",
+            "Write",
+            json!({ "file_path": "src/lib.rs", "content": "" }),
+        ))
+        .with_message(Message::tool_use_with_id(
+            "toolu_2",
+            "Write",
+            json!({ "file_path": "src/main.rs", "content": "" }),
+        ))
+        .with_message(ResultMessage::new("run-1"));
+    let options = ClaudeCodeOptions::new()
+        .with_cwd(cwd)
+        .with_run_id(run_id)
+        .with_file_locks(locks.clone())
+        .with_transport_factory(mock.factory());
+    let items: Vec<_> = query("Refactor", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        &items[1],
+        Err(ClaudeSDKError::FileLockConflict { conflict }) if conflict.holder == holder
+    ));
+    // The interrupted run's locks are released with its stream
+    assert_eq!(locks.holder("/work/app/src/lib.rs"), None);
+    assert_eq!(locks.holder("/work/app/src/main.rs"), Some(holder));
+}
+
+#[cfg(all(feature = "subprocess", unix))]
+#[tokio::test]
+async fn test_hook_denies_conflicting_edit() {
+    use claude_code_sdk::{query, ClaudeCodeOptions};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cwd = dir.path().canonicalize().unwrap();
+    let edit = |id: &str, path: &str| {
+        common::pre_tool_use(
+            id,
+            "Edit",
+            json!({ "file_path": path, "old_string": "a", "new_string": "b" }),
+        )
+    };
+    let cli = common::answering_cli(
+        &cwd,
+        &[edit("cli_1", "src/main.rs"), edit("cli_2", "src/lib.rs")],
+    );
+
+    let locks = WorkspaceLocks::new();
+    let (holder, run_id) = (RunId::new(), RunId::new());
+    assert!(locks.check(holder, &cwd, &write("src/main.rs")).is_none());
+    let acquired = Arc::new(Mutex::new(Vec::new()));
+    let locks = locks.on_event({
+        let acquired = acquired.clone();
+        move |event| {
+            if let LockEvent::Acquired { run_id, path } = event {
+                acquired.lock().unwrap().push((*run_id, path.clone()));
+            }
+        }
+    });
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(&cwd)
+        .with_run_id(run_id)
+        .with_file_locks(locks.clone());
+    let items: Vec<_> = query("Refactor", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    // Denied before it ran, so the run goes on
+    assert!(items.iter().all(|item| item.is_ok()));
+    assert!(matches!(items.last(), Some(Ok(Message::Result(_)))));
+
+    let answers = common::hook_answers(&cwd);
+    assert_eq!(answers[0]["response"]["request_id"], "cli_1");
+    assert_eq!(common::permission_decision(&answers[0]), "deny");
+    assert_eq!(
+        common::permission_decision(&answers[1]),
+        serde_json::Value::Null
+    );
+    assert_eq!(
+        *acquired.lock().unwrap(),
+        vec![(run_id, cwd.join("src/lib.rs"))]
+    );
+    // Released with the result
+    assert_eq!(locks.held(), vec![(cwd.join("src/main.rs"), holder)]);
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "file-patch")]
+
+use claude_code_sdk::file_patch::{content_hash, PatchChange, TextEdit};
+use claude_code_sdk::ToolUseBlock;
+use serde_json::json;
+
+#[test]
+fn test_content_hash() {
+    assert_eq!(
+        content_hash(b"abc"),
+        "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_patch_change_from_tool_use() {
+    let multi_edit = ToolUseBlock::new(
+        "toolu_1",
+        "MultiEdit",
+        json!({
+            "file_path": "src/lib.rs",
+            "edits": [
+                { "old_string": "foo", "new_string": "bar" },
+                { "old_string": "a", "new_string": "b", "replace_all": true },
+            ],
+        }),
+    );
+    assert_eq!(
+        PatchChange::from_tool_use(&multi_edit),
+        Some(PatchChange::Edits {
+            edits: vec![
+                TextEdit {
+                    old: "foo".into(),
+                    new: "bar".into(),
+                    replace_all: false,
+                },
+                TextEdit {
+                    old: "a".into(),
+                    new: "b".into(),
+                    replace_all: true,
+                },
+            ],
+        })
+    );
+    let write = ToolUseBlock::new(
+        "toolu_2",
+        "Write",
+        json!({ "file_path": "a.txt", "content": "hi" }),
+    );
+    assert_eq!(
+        serde_json::to_value(PatchChange::from_tool_use(&write).unwrap()).unwrap(),
+        json!({ "kind": "content", "content": "hi" })
+    );
+    let read = ToolUseBlock::new("toolu_3", "Read", json!({ "file_path": "a.txt" }));
+    assert_eq!(PatchChange::from_tool_use(&read), None);
+}
+
+// `analysis-only` interrupts runs calling editing tools
+#[cfg(all(feature = "subprocess", not(feature = "analysis-only")))]
+#[tokio::test]
+async fn test_successful_edits_are_reported_as_patches() {
+    use claude_code_sdk::file_patch::{FilePatch, PatchOp};
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message};
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.txt");
+    std::fs::write(&file, "old").unwrap();
+
+    let mock = MockTransport::new().with_messages([
+        Message::tool_use_with_id(
+            "toolu_1",
+            "Edit",
+            json!({ "file_path": "notes.txt", "old_string": "old", "new_string": "new" }),
+        ),
+        Message::tool_result("toolu_1", "edited"),
+        Message::tool_use_with_id(
+            "toolu_2",
+            "Write",
+            json!({ "file_path": "other.txt", "content": "x" }),
+        ),
+        Message::tool_error("toolu_2", "permission denied"),
+        Message::result(),
+    ]);
+    let patches: Arc<Mutex<Vec<FilePatch>>> = Arc::default();
+    let seen = patches.clone();
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_cwd(dir.path())
+        .on_file_patch(move |patch| seen.lock().unwrap().push(patch.clone()));
+
+    let mut stream = query("Update the notes", Some(options)).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    // The mock does not run tools, so make the edit it reports
+    std::fs::write(&file, "new").unwrap();
+    while let Some(message) = stream.next().await {
+        message.unwrap();
+    }
+
+    let patches = patches.lock().unwrap();
+    // The failed write is not reported
+    assert_eq!(patches.len(), 1);
+    assert_eq!(patches[0].op, PatchOp::Replace);
+    assert_eq!(patches[0].tool_use_id, "toolu_1");
+    assert!(patches[0].path.ends_with("notes.txt"));
+    assert_eq!(patches[0].before_hash, Some(content_hash(b"old")));
+    assert_eq!(patches[0].after_hash, Some(content_hash(b"new")));
+}
+
+// A file changed behind the CLI's back before the call is seen: the patch
+// carries the hash of the content the edit actually applied to
+#[cfg(all(feature = "subprocess", not(feature = "analysis-only")))]
+#[tokio::test]
+async fn test_before_hash_reflects_changes_by_others() {
+    use claude_code_sdk::file_patch::FilePatch;
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, ClaudeCodeOptions, Message};
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.txt");
+    // What the mirror last saw
+    std::fs::write(&file, "old").unwrap();
+
+    let mock = MockTransport::new().with_messages([
+        Message::tool_use_with_id(
+            "toolu_1",
+            "Edit",
+            json!({ "file_path": "notes.txt", "old_string": "theirs", "new_string": "new" }),
+        ),
+        Message::tool_result("toolu_1", "edited"),
+        Message::result(),
+    ]);
+    let patches: Arc<Mutex<Vec<FilePatch>>> = Arc::default();
+    let seen = patches.clone();
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_cwd(dir.path())
+        .on_file_patch(move |patch| seen.lock().unwrap().push(patch.clone()));
+
+    let mut stream = query("Update the notes", Some(options)).await.unwrap();
+    // Someone else writes the file before the call comes in
+    std::fs::write(&file, "theirs").unwrap();
+    stream.next().await.unwrap().unwrap();
+    std::fs::write(&file, "new").unwrap();
+    while let Some(message) = stream.next().await {
+        message.unwrap();
+    }
+
+    let patches = patches.lock().unwrap();
+    assert_eq!(patches.len(), 1);
+    assert_eq!(patches[0].before_hash, Some(content_hash(b"theirs")));
+    // The mirror sees it cannot apply the patch to what it has
+    assert_ne!(patches[0].before_hash, Some(content_hash(b"old")));
+    assert_eq!(patches[0].after_hash, Some(content_hash(b"new")));
+}
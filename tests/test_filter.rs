@@ -0,0 +1,181 @@
+use claude_code_sdk::filter::*;
+use claude_code_sdk::types::*;
+
+#[test]
+fn test_default_filter_keeps_everything() {
+    let filter = MessageFilter::new();
+    let message: Message = SystemMessage::new("init").into();
+
+    assert!(filter.apply(message).is_some());
+}
+
+#[test]
+fn test_assistant_and_result_filter() {
+    let filter = MessageFilter::assistant_and_result();
+
+    let user: Message = UserMessage::new(vec![]).into();
+    let system: Message = SystemMessage::new("init").into();
+    let assistant: Message = AssistantMessage::new(vec![]).into();
+    let result: Message = ResultMessage::new("result-1").into();
+
+    assert!(filter.apply(user).is_none());
+    assert!(filter.apply(system).is_none());
+    assert!(filter.apply(assistant).is_some());
+    assert!(filter.apply(result).is_some());
+}
+
+#[test]
+fn test_strip_tool_results() {
+    let filter = MessageFilter::new().strip_tool_results();
+    let block = ToolResultBlock::new("tool-1", Some("very long build log"), Some(false));
+    let message: Message = UserMessage::new(vec![block.into()]).into();
+
+    match filter.apply(message) {
+        Some(Message::User(msg)) => match &msg.content[0] {
+            ContentBlock::ToolResult(result) => {
+                assert_eq!(result.tool_use_id, "tool-1");
+                assert!(result.content.is_none());
+            }
+            _ => panic!("Expected ToolResult block"),
+        },
+        _ => panic!("Expected User message"),
+    }
+}
+
+#[test]
+fn test_system_dedupe_collapses_runs() {
+    let messages = SystemDedupe::dedupe(vec![
+        Message::system("Compacted conversation"),
+        Message::system("Compacted conversation"),
+        Message::system("Compacted conversation"),
+        Message::system("Reconnected to MCP server"),
+        Message::assistant_text("Done."),
+        Message::system("Compacted conversation"),
+    ]);
+
+    let systems: Vec<(&str, Option<u32>)> = messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::System(msg) => Some((msg.content.as_str(), msg.repeat_count)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(messages.len(), 4);
+    assert!(matches!(messages[2], Message::Assistant(_)));
+    assert_eq!(
+        systems,
+        vec![
+            ("Compacted conversation", Some(3)),
+            ("Reconnected to MCP server", None),
+            ("Compacted conversation", None),
+        ]
+    );
+
+    let json = serde_json::to_value(&messages[0]).unwrap();
+    assert_eq!(json["repeat_count"], 3);
+    assert!(serde_json::to_value(&messages[1])
+        .unwrap()
+        .get("repeat_count")
+        .is_none());
+}
+
+#[test]
+fn test_system_dedupe_holds_back_system_messages() {
+    let mut dedupe = SystemDedupe::new();
+
+    assert!(dedupe.push(Message::system("Compacted")).is_empty());
+    assert!(dedupe.push(Message::system("Compacted")).is_empty());
+    let ready = dedupe.push(Message::user_text("Continue"));
+    assert_eq!(ready.len(), 2);
+    assert!(matches!(&ready[0], Message::System(msg) if msg.repeat_count == Some(2)));
+    assert!(dedupe.finish().is_none());
+}
+
+#[cfg(feature = "subprocess")]
+mod query {
+    use claude_code_sdk::idempotency::{IdempotencyStore, InMemoryIdempotencyStore};
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::types::*;
+    use claude_code_sdk::{query_with_handle, ClaudeSDKClient, MessageFilter};
+    use std::sync::Arc;
+    use tokio_stream::StreamExt;
+
+    fn init(session_id: &str) -> SystemMessage {
+        let mut init = SystemMessage::new("init");
+        init.subtype = Some("init".to_string());
+        init.session_id = Some(session_id.to_string());
+        init
+    }
+
+    #[tokio::test]
+    async fn test_filtered_init_still_sets_the_session() {
+        let mock = MockTransport::new()
+            .with_message(init("session-1"))
+            .with_message(Message::assistant_text("Done"))
+            .with_message(ResultMessage::new("run-1"));
+        let options = ClaudeCodeOptions::new()
+            .with_transport_factory(mock.factory())
+            .with_message_filter(MessageFilter::assistant_and_result());
+
+        let mut handle = query_with_handle("Hello", Some(options)).await.unwrap();
+        let mut messages = Vec::new();
+        while let Some(message) = handle.next().await {
+            messages.push(message.unwrap());
+        }
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(matches!(messages[0], Message::Assistant(_)));
+        assert!(matches!(messages[1], Message::Result(_)));
+        assert_eq!(handle.session_id(), Some("session-1"));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_result_is_still_recorded() {
+        let store = Arc::new(InMemoryIdempotencyStore::new());
+        let mock = MockTransport::new()
+            .with_message(Message::assistant_text("Done"))
+            .with_message(ResultMessage::new("run-1"));
+        let filter = MessageFilter {
+            include_result: false,
+            ..MessageFilter::new()
+        };
+        let options = ClaudeCodeOptions::new()
+            .with_transport_factory(mock.factory())
+            .with_message_filter(filter)
+            .with_idempotency_key("webhook-1")
+            .with_idempotency_store(store.clone());
+
+        let messages: Vec<_> = query_with_handle("Hello", Some(options))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(messages.len(), 1);
+        assert!(store.get("webhook-1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_filtered_result_still_ends_the_turn() {
+        let mock = MockTransport::new()
+            .with_messages(vec![
+                Message::assistant_text("Hi"),
+                ResultMessage::new("turn-1").into(),
+            ])
+            .with_messages(vec![
+                Message::assistant_text("Bye"),
+                ResultMessage::new("turn-2").into(),
+            ]);
+        let filter = MessageFilter {
+            include_result: false,
+            ..MessageFilter::new()
+        };
+        let options = ClaudeCodeOptions::new()
+            .with_transport_factory(mock.factory())
+            .with_message_filter(filter);
+        let mut client = ClaudeSDKClient::connect(options).await.unwrap();
+
+        client.send_message("Hi").await.unwrap();
+        let turn: Vec<_> = client.receive_response().collect().await;
+        assert_eq!(turn.len(), 1, "{:?}", turn);
+        assert!(matches!(turn[0], Ok(Message::Assistant(_))));
+    }
+}
@@ -0,0 +1,163 @@
+#![cfg(feature = "github")]
+
+use claude_code_sdk::github::{
+    parse_diff, parse_findings, review_options, review_payload, review_pull_request, GitHubClient,
+    PullRequest, ReviewEvent, Severity,
+};
+use claude_code_sdk::transport::MockTransport;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError, ResultMessage};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,4 +10,5 @@ pub fn add(a: i32, b: i32) -> i32 {
+ fn one() {}
+-fn two() {}
++fn two() { unsafe { ptr::null::<u8>().read() }; }
++++counter;
+ fn three() {}
+ fn four() {}
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+/// Serve one HTTP response with `status` and `body`, returning the raw request.
+async fn serve(status: u16, body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/v3", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+            let n = socket.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request).into_owned()
+    });
+    (url, server)
+}
+
+#[test]
+fn test_pull_request_parse() {
+    let pr = PullRequest::new("octocat", "hello-world", 42);
+    assert_eq!(PullRequest::parse("octocat/hello-world#42").unwrap(), pr);
+    assert_eq!(
+        PullRequest::parse("https://github.com/octocat/hello-world/pull/42/").unwrap(),
+        pr
+    );
+    assert_eq!(pr.to_string(), "octocat/hello-world#42");
+    assert!(PullRequest::parse("octocat/hello-world").is_err());
+    assert!(PullRequest::parse("https://github.com/octocat/hello-world/issues/42").is_err());
+}
+
+#[test]
+fn test_findings_map_to_review_comments() {
+    let files = parse_diff(DIFF);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "src/lib.rs");
+    assert_eq!(files[0].hunks, vec![10..15]);
+
+    let findings = parse_findings(
+        "Here is my review:\n```json\n{\"findings\": [\
+         {\"path\": \"src/lib.rs\", \"line\": 11, \"severity\": \"error\", \"message\": \"Null read\"},\
+         {\"path\": \"src/lib.rs\", \"line\": 12, \"end_line\": 30, \"severity\": \"suggestion\", \"message\": \"Split\"},\
+         {\"path\": \"src/main.rs\", \"line\": 3, \"message\": \"Unused\"}\
+         ]}\n```",
+    )
+    .unwrap();
+    assert_eq!(findings[2].severity, Severity::Warning);
+
+    let review = review_payload(&findings, DIFF);
+    assert_eq!(review.event, ReviewEvent::RequestChanges);
+    assert_eq!(review.comments.len(), 2);
+    assert_eq!(
+        serde_json::to_value(&review.comments[0]).unwrap(),
+        serde_json::json!({
+            "path": "src/lib.rs",
+            "line": 11,
+            "side": "RIGHT",
+            "body": "**error**: Null read",
+        })
+    );
+    // Clamped to the end of the hunk
+    assert_eq!(review.comments[1].start_line, Some(12));
+    assert_eq!(review.comments[1].line, 14);
+    assert_eq!(
+        review.body,
+        "3 findings.\n\nOutside the diff:\n\n- `src/main.rs:3` **warning**: Unused"
+    );
+}
+
+#[tokio::test]
+async fn test_review_pull_request() {
+    let (url, server) = serve(200, DIFF).await;
+    let github = GitHubClient::new().with_token("t0ken").with_api_url(url);
+    let mock = MockTransport::new().with_message(ResultMessage {
+        content: Some(r#"{"findings": []}"#.to_string()),
+        ..ResultMessage::new("run-1")
+    });
+    let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+
+    let pr = PullRequest::new("octocat", "hello-world", 42);
+    let review = review_pull_request(&github, &pr, options).await.unwrap();
+    assert_eq!(review.event, ReviewEvent::Comment);
+    assert_eq!(review.body, "No issues found.");
+    assert!(review.comments.is_empty());
+
+    let request = server.await.unwrap().to_lowercase();
+    assert!(request.starts_with("get /api/v3/repos/octocat/hello-world/pulls/42 "));
+    assert!(request.contains("accept: application/vnd.github.diff"));
+    assert!(request.contains("authorization: bearer t0ken"));
+
+    let prompt = mock.prompts()[0].as_text().unwrap().to_string();
+    assert!(prompt.contains("octocat/hello-world#42"));
+    assert!(prompt.contains("+++counter;"));
+    // Reviews only read
+    let disallowed = review_options(ClaudeCodeOptions::new())
+        .disallowed_tools
+        .unwrap();
+    assert!(disallowed.contains(&"Bash".to_string()));
+    assert!(disallowed.contains(&"Edit".to_string()));
+}
+
+#[tokio::test]
+async fn test_github_errors() {
+    let (url, server) = serve(404, r#"{"message": "Not Found"}"#).await;
+    let github = GitHubClient::new().with_api_url(url);
+    let error = github
+        .pull_request_diff(&PullRequest::new("octocat", "missing", 1))
+        .await
+        .unwrap_err();
+    server.await.unwrap();
+
+    assert!(matches!(
+        error,
+        ClaudeSDKError::GitHub {
+            status: Some(404),
+            ..
+        }
+    ));
+    let json = error.to_json();
+    assert_eq!(json["code"], "github");
+    assert_eq!(json["details"]["status"], 404);
+    assert!(json["message"].as_str().unwrap().ends_with("Not Found"));
+}
@@ -0,0 +1,201 @@
+use claude_code_sdk::hooks::*;
+use claude_code_sdk::ClaudeCodeOptions;
+
+fn context(tool_name: Option<&str>) -> ToolResultContext<'_> {
+    ToolResultContext {
+        tool_use_id: "tool-1",
+        tool_name,
+        input: None,
+        is_error: false,
+    }
+}
+
+#[test]
+fn test_truncate_lines() {
+    let hook = truncate_lines(2, 1);
+    let log = (1..=10)
+        .map(|i| format!("line {}", i))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert_eq!(
+        hook(&context(Some("Bash")), &log),
+        Some("line 1\nline 2\n... [7 lines omitted] ...\nline 10".to_string())
+    );
+    assert_eq!(hook(&context(Some("Bash")), "short\noutput"), None);
+}
+
+#[test]
+fn test_number_lines() {
+    let hook = number_lines(&["mcp__files__read"]);
+    let content = (1..=10)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let numbered = hook(&context(Some("mcp__files__read")), &content).unwrap();
+    assert!(numbered.starts_with(" 1\t1\n 2\t2"));
+    assert!(numbered.ends_with("10\t10"));
+    assert_eq!(hook(&context(Some("Bash")), &content), None);
+    assert_eq!(hook(&context(None), &content), None);
+}
+
+#[test]
+fn test_register_tool_result_hooks() {
+    let options = ClaudeCodeOptions::new()
+        .with_tool_result_hook(truncate_lines(50, 50))
+        .on_tool_result(|_, content| Some(content.trim().to_string()));
+
+    assert_eq!(options.tool_result_hooks.len(), 2);
+}
+
+#[test]
+fn test_hook_input_from_cli() {
+    let input: HookInput = serde_json::from_value(serde_json::json!({
+        "hook_event_name": "PreToolUse",
+        "session_id": "s1",
+        "transcript_path": "/tmp/s1.jsonl",
+        "cwd": "/work",
+        "tool_name": "Bash",
+        "tool_input": {"command": "cargo test"},
+    }))
+    .unwrap();
+
+    assert_eq!(input.hook_event_name, HookEvent::PreToolUse);
+    assert_eq!(input.tool_name.as_deref(), Some("Bash"));
+    assert_eq!(input.tool_input.unwrap()["command"], "cargo test");
+    assert_eq!(input.prompt, None);
+}
+
+#[test]
+fn test_hook_output_json() {
+    assert_eq!(
+        serde_json::to_value(HookOutput::allow()).unwrap(),
+        serde_json::json!({})
+    );
+    assert_eq!(
+        serde_json::to_value(HookOutput::deny("no deletes")).unwrap(),
+        serde_json::json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": "deny",
+                "permissionDecisionReason": "no deletes",
+            }
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(HookOutput::block("tests fail").with_system_message("blocked"))
+            .unwrap(),
+        serde_json::json!({"decision": "block", "reason": "tests fail", "systemMessage": "blocked"})
+    );
+    assert_eq!(
+        serde_json::to_value(HookOutput::stop("budget spent")).unwrap(),
+        serde_json::json!({"continue": false, "stopReason": "budget spent"})
+    );
+}
+
+#[test]
+fn test_register_lifecycle_hooks() {
+    let options = ClaudeCodeOptions::new()
+        .with_hook(
+            HookRegistration::new(HookEvent::PreToolUse, |input| async move {
+                match input.tool_name.as_deref() {
+                    Some("Bash") => HookOutput::deny("no shell"),
+                    _ => HookOutput::allow(),
+                }
+            })
+            .with_matcher("Bash"),
+        )
+        .on_hook(HookEvent::Stop, |_| async { HookOutput::allow() });
+
+    assert_eq!(options.hooks.len(), 2);
+    assert_eq!(options.hooks[0].matcher.as_deref(), Some("Bash"));
+    assert_eq!(options.hooks[1].event, HookEvent::Stop);
+
+    let mut input = HookInput::new(HookEvent::PreToolUse);
+    input.tool_name = Some("Bash".to_string());
+    let output = futures::executor::block_on((options.hooks[0].callback)(input));
+    assert_eq!(output, HookOutput::deny("no shell"));
+}
+
+fn tool_call(tool_name: &str, tool_input: serde_json::Value) -> HookInput {
+    let mut input = HookInput::new(HookEvent::PreToolUse);
+    input.cwd = Some("/work/app".to_string());
+    input.tool_name = Some(tool_name.to_string());
+    input.tool_input = Some(tool_input);
+    input
+}
+
+#[test]
+fn test_hook_matcher_tools_and_paths() {
+    let matcher = HookMatcher::new()
+        .tool("Edit")
+        .tool("mcp__github__*")
+        .path("src/**/*.rs")
+        .path("Cargo.toml");
+
+    assert!(matcher.matches(&tool_call(
+        "Edit",
+        serde_json::json!({"file_path": "/work/app/src/lib.rs"})
+    )));
+    assert!(matcher.matches(&tool_call(
+        "Edit",
+        serde_json::json!({"file_path": "src/client/mod.rs"})
+    )));
+    assert!(matcher.matches(&tool_call(
+        "Edit",
+        serde_json::json!({"file_path": "/work/app/crates/core/Cargo.toml"})
+    )));
+    assert!(matcher.matches(&tool_call(
+        "mcp__github__create_file",
+        serde_json::json!({"path": "src/main.rs"})
+    )));
+    assert!(!matcher.matches(&tool_call(
+        "Write",
+        serde_json::json!({"file_path": "/work/app/src/lib.rs"})
+    )));
+    assert!(!matcher.matches(&tool_call(
+        "Edit",
+        serde_json::json!({"file_path": "/work/app/README.md"})
+    )));
+    assert!(!matcher.matches(&tool_call(
+        "Edit",
+        serde_json::json!({"content": "no path"})
+    )));
+
+    assert_eq!(
+        matcher.cli_matcher().as_deref(),
+        Some("Edit|mcp__github__.*")
+    );
+    assert_eq!(HookMatcher::new().path("*.rs").cli_matcher(), None);
+}
+
+#[test]
+fn test_hook_matcher_content_predicates() {
+    let matcher = HookMatcher::new()
+        .tool("Bash")
+        .content(|text| text.contains("rm -rf"))
+        .when(|input| input.session_id.is_none());
+
+    assert!(matcher.matches(&tool_call(
+        "Bash",
+        serde_json::json!({"command": "rm -rf target"})
+    )));
+    assert!(!matcher.matches(&tool_call(
+        "Bash",
+        serde_json::json!({"command": "cargo build"})
+    )));
+
+    let mut resumed = tool_call("Bash", serde_json::json!({"command": "rm -rf target"}));
+    resumed.session_id = Some("s1".to_string());
+    assert!(!matcher.matches(&resumed));
+
+    let hook = HookRegistration::new(HookEvent::PreToolUse, |_| async { HookOutput::deny("no") })
+        .with_filter(matcher);
+    assert_eq!(hook.matcher.as_deref(), Some("Bash"));
+    assert!(hook.applies_to(&tool_call(
+        "Bash",
+        serde_json::json!({"command": "rm -rf /"})
+    )));
+    assert!(!hook.applies_to(&tool_call("Bash", serde_json::json!({"command": "ls"}))));
+}
@@ -0,0 +1,236 @@
+#![cfg(feature = "subprocess")]
+
+#[cfg(unix)]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::idempotency::*;
+use claude_code_sdk::types::*;
+use claude_code_sdk::{query, Message};
+use std::sync::Arc;
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn test_in_memory_store() {
+    let store = InMemoryIdempotencyStore::new();
+    assert!(store.get("key-1").await.unwrap().is_none());
+
+    store
+        .put("key-1", ResultMessage::new("result-1"))
+        .await
+        .unwrap();
+
+    let stored = store.get("key-1").await.unwrap().unwrap();
+    assert_eq!(stored.id, "result-1");
+    assert!(store.get("key-2").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_in_memory_store_claims_keys() {
+    let store = InMemoryIdempotencyStore::new();
+    assert!(matches!(
+        store.begin("key-1").await.unwrap(),
+        Begin::Started
+    ));
+    // A second run under the same key must not start while the first runs
+    assert!(matches!(
+        store.begin("key-1").await.unwrap(),
+        Begin::InFlight
+    ));
+
+    // A failed run leaves the key free for a retry
+    store.abandon("key-1").await.unwrap();
+    assert!(matches!(
+        store.begin("key-1").await.unwrap(),
+        Begin::Started
+    ));
+
+    store
+        .put("key-1", ResultMessage::new("result-1"))
+        .await
+        .unwrap();
+    match store.begin("key-1").await.unwrap() {
+        Begin::Done(result) => assert_eq!(result.id, "result-1"),
+        other => panic!("Expected a stored result, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_in_memory_store_capacity() {
+    let store = InMemoryIdempotencyStore::with_capacity(2);
+    for key in ["key-1", "key-2", "key-3"] {
+        store.put(key, ResultMessage::new(key)).await.unwrap();
+    }
+    // The oldest result goes first
+    assert!(store.get("key-1").await.unwrap().is_none());
+    assert!(store.get("key-2").await.unwrap().is_some());
+    assert!(store.get("key-3").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_query_replays_stored_result() {
+    let store = Arc::new(InMemoryIdempotencyStore::new());
+    let mut result = ResultMessage::new("result-1");
+    result.session_id = Some("session-1".to_string());
+    store.put("webhook-42", result).await.unwrap();
+
+    let options = ClaudeCodeOptions::new()
+        .with_idempotency_key("webhook-42")
+        .with_idempotency_store(store);
+
+    // The CLI is never spawned for a key that already completed
+    let messages: Vec<_> = query("Deploy", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 1);
+    match &messages[0] {
+        Ok(Message::Result(result)) => {
+            assert_eq!(result.id, "result-1");
+            assert_eq!(result.session_id, Some("session-1".to_string()));
+        }
+        other => panic!("Expected Result message, got {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+mod cli {
+    use super::common::{fake_cli, print};
+    use super::*;
+    use claude_code_sdk::ClaudeSDKError;
+    use std::path::Path;
+
+    /// A CLI that notes each run in `runs` and ends with `result`.
+    fn counting_cli(dir: &Path, result: ResultMessage) -> ClaudeCodeOptions {
+        let body = format!(
+            "echo run >> '{}'\n{}",
+            dir.join("runs").display(),
+            print(&[Message::assistant_text("Deployed"), result.into()])
+        );
+        ClaudeCodeOptions::new().with_cli_path(fake_cli(dir, &body))
+    }
+
+    fn runs(dir: &Path) -> usize {
+        std::fs::read_to_string(dir.join("runs"))
+            .map(|runs| runs.lines().count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_is_refused_while_the_first_run_is_in_flight() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(InMemoryIdempotencyStore::new());
+        let options = counting_cli(dir.path(), ResultMessage::new("result-1"))
+            .with_idempotency_key("webhook-7")
+            .with_idempotency_store(store);
+
+        let first = query("Deploy", Some(options.clone())).await.unwrap();
+        let error = query("Deploy", Some(options.clone())).await.err().unwrap();
+        assert!(
+            matches!(&error, ClaudeSDKError::RunInProgress { idempotency_key } if idempotency_key == "webhook-7"),
+            "{:?}",
+            error
+        );
+
+        let messages: Vec<Message> = first.map(Result::unwrap).collect().await;
+        assert!(matches!(messages.last(), Some(Message::Result(_))));
+
+        // Once the first run succeeded, its result is replayed
+        let replayed: Vec<_> = query("Deploy", Some(options))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(replayed.len(), 1);
+        assert!(matches!(&replayed[0], Ok(Message::Result(result)) if result.id == "result-1"));
+        assert_eq!(runs(dir.path()), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_runs_can_be_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(InMemoryIdempotencyStore::new());
+        let mut failed = ResultMessage::new("result-1");
+        failed.is_error = Some(true);
+        let options = counting_cli(dir.path(), failed)
+            .with_idempotency_key("webhook-8")
+            .with_idempotency_store(store.clone());
+
+        for _ in 0..2 {
+            let messages: Vec<_> = query("Deploy", Some(options.clone()))
+                .await
+                .unwrap()
+                .collect()
+                .await;
+            assert!(matches!(messages.last(), Some(Ok(Message::Result(_)))));
+        }
+        assert_eq!(runs(dir.path()), 2);
+        assert!(store.get("webhook-8").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_run_releases_its_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(InMemoryIdempotencyStore::new());
+        let options = counting_cli(dir.path(), ResultMessage::new("result-1"))
+            .with_idempotency_key("webhook-9")
+            .with_idempotency_store(store.clone());
+
+        drop(query("Deploy", Some(options)).await.unwrap());
+        // The key is released in the background
+        for _ in 0..50 {
+            if matches!(store.begin("webhook-9").await.unwrap(), Begin::Started) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the key of the dropped run was never released");
+    }
+
+    /// Claims keys, but cannot save results.
+    struct ReadOnlyStore(InMemoryIdempotencyStore);
+
+    #[async_trait::async_trait]
+    impl IdempotencyStore for ReadOnlyStore {
+        async fn begin(&self, key: &str) -> claude_code_sdk::Result<Begin> {
+            self.0.begin(key).await
+        }
+
+        async fn get(&self, key: &str) -> claude_code_sdk::Result<Option<ResultMessage>> {
+            self.0.get(key).await
+        }
+
+        async fn put(&self, _key: &str, _result: ResultMessage) -> claude_code_sdk::Result<()> {
+            Err(ClaudeSDKError::invalid_options("read-only store"))
+        }
+
+        async fn abandon(&self, key: &str) -> claude_code_sdk::Result<()> {
+            self.0.abandon(key).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_put_still_yields_the_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ReadOnlyStore(InMemoryIdempotencyStore::new()));
+        let options = counting_cli(dir.path(), ResultMessage::new("result-1"))
+            .with_idempotency_key("webhook-10")
+            .with_idempotency_store(store.clone());
+
+        let messages: Vec<_> = query("Deploy", Some(options))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(
+            matches!(messages.last(), Some(Ok(Message::Result(result))) if result.id == "result-1")
+        );
+        // The unsaved run does not block a retry
+        assert!(matches!(
+            store.begin("webhook-10").await.unwrap(),
+            Begin::Started
+        ));
+    }
+}
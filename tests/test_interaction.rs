@@ -0,0 +1,166 @@
+#[cfg(all(feature = "subprocess", unix))]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::interaction::{trust_folder, InteractionKind};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError, ErrorCode};
+
+#[test]
+fn test_interaction_kind_from_line() {
+    assert_eq!(
+        InteractionKind::from_line("\x1b[1mDo you trust the files in this folder?\x1b[0m"),
+        Some(InteractionKind::TrustFolder)
+    );
+    assert_eq!(
+        InteractionKind::from_line("Invalid API key · Please run /login"),
+        Some(InteractionKind::Login)
+    );
+    assert_eq!(
+        InteractionKind::from_line("Error: Raw mode is not supported on the current process.stdin"),
+        Some(InteractionKind::Terminal)
+    );
+    // Claude's own words in a message are not a prompt
+    assert_eq!(
+        InteractionKind::from_line(r#"{"type":"assistant","content":"Please run /login"}"#),
+        None
+    );
+    assert_eq!(InteractionKind::from_line("Compiling claude v1.0.0"), None);
+    // Framed prompts count, output that mentions logging in does not
+    assert_eq!(
+        InteractionKind::from_line("│ Select login method: │"),
+        Some(InteractionKind::Login)
+    );
+    assert_eq!(
+        InteractionKind::from_line("warning: gh is not logged in; please run /login later"),
+        None
+    );
+    assert_eq!(
+        InteractionKind::from_line("test auth::requires_login ... press enter to continue"),
+        None
+    );
+}
+
+#[test]
+fn test_trust_folder_updates_config() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let config = home.path().join(".claude.json");
+    std::fs::write(&config, r#"{"numStartups": 3, "projects": {}}"#).unwrap();
+
+    assert!(trust_folder(&config, project.path()).unwrap());
+    assert!(!trust_folder(&config, project.path()).unwrap());
+
+    let written: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&config).unwrap()).unwrap();
+    let key = std::fs::canonicalize(project.path()).unwrap();
+    assert_eq!(written["numStartups"], 3);
+    assert_eq!(
+        written["projects"][key.to_string_lossy().as_ref()]["hasTrustDialogAccepted"],
+        true
+    );
+
+    std::fs::write(&config, "[]").unwrap();
+    assert!(trust_folder(&config, project.path()).is_err());
+}
+
+#[test]
+fn test_trust_folder_keeps_concurrent_changes() {
+    let home = tempfile::tempdir().unwrap();
+    let config = home.path().join(".claude.json");
+    let projects: Vec<_> = (0..8).map(|_| tempfile::tempdir().unwrap()).collect();
+    std::thread::scope(|scope| {
+        for project in &projects {
+            let config = &config;
+            scope.spawn(move || trust_folder(config, project.path()).unwrap());
+        }
+    });
+
+    let written: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&config).unwrap()).unwrap();
+    for project in &projects {
+        let key = std::fs::canonicalize(project.path()).unwrap();
+        assert_eq!(
+            written["projects"][key.to_string_lossy().as_ref()]["hasTrustDialogAccepted"],
+            true
+        );
+    }
+}
+
+#[test]
+fn test_interaction_required_error() {
+    let error =
+        ClaudeSDKError::interaction_required(InteractionKind::Login, "Select login method:");
+    assert_eq!(error.code(), ErrorCode::InteractionRequired);
+    assert_eq!(error.code().http_status(), 503);
+    assert!(error.to_string().contains("pass an API key"));
+    assert_eq!(
+        error.to_json()["details"],
+        serde_json::json!({ "kind": "login" })
+    );
+
+    let options = ClaudeCodeOptions::new().with_trusted_folder();
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert_eq!(restored.trust_folder, Some(true));
+}
+
+#[cfg(all(feature = "subprocess", unix))]
+async fn run_fake_cli(body: &str) -> Vec<claude_code_sdk::Result<claude_code_sdk::Message>> {
+    use claude_code_sdk::query;
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cli = common::fake_cli(dir.path(), body);
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path(&cli)
+        .with_cwd(dir.path());
+    let stream = query("Hello", Some(options)).await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(20), stream.collect())
+        .await
+        .expect("the watch stops a CLI waiting for input")
+}
+
+#[cfg(all(feature = "subprocess", unix))]
+#[tokio::test]
+async fn test_cli_waiting_for_input_is_stopped() {
+    // An unfinished prompt on stderr, and a whole one on stdout
+    for (body, kind) in [
+        (
+            "printf 'Do you trust the files in this folder? (y/n) ' >&2\nexec sleep 30",
+            InteractionKind::TrustFolder,
+        ),
+        (
+            "echo 'Invalid API key · Please run /login'\nexec sleep 30",
+            InteractionKind::Login,
+        ),
+        (
+            "printf 'Press Enter to continue'\nexec sleep 30",
+            InteractionKind::Terminal,
+        ),
+    ] {
+        let items = run_fake_cli(body).await;
+        match items.last() {
+            Some(Err(error)) => match error.root() {
+                ClaudeSDKError::InteractionRequired { kind: found, .. } => {
+                    assert_eq!(*found, kind)
+                }
+                other => panic!("expected an interaction error, got {:?}", other),
+            },
+            other => panic!("expected an interaction error, got {:?}", other),
+        }
+    }
+
+    // Output that only mentions logging in goes on
+    let result = serde_json::to_string(&claude_code_sdk::Message::from(
+        claude_code_sdk::ResultMessage::new("run-1"),
+    ))
+    .unwrap();
+    let items = run_fake_cli(&format!(
+        "echo 'warning: gh is not logged in, please run /login later' >&2\necho '{}'",
+        result
+    ))
+    .await;
+    assert!(matches!(
+        items.last(),
+        Some(Ok(claude_code_sdk::Message::Result(_)))
+    ));
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "anthropic-interop")]
+
+use claude_code_sdk::interop::*;
+use claude_code_sdk::{
+    AssistantMessage, ContentBlock, Message, SystemMessage, TextBlock, ToolUseBlock, UserMessage,
+};
+use serde_json::json;
+
+#[test]
+fn test_message_to_api_wire_format() {
+    let message = Message::Assistant(AssistantMessage::new(vec![
+        ContentBlock::Text(TextBlock::new("Listing files")),
+        ContentBlock::ToolUse(ToolUseBlock::new(
+            "toolu_1",
+            "Bash",
+            json!({"command": "ls"}),
+        )),
+    ]));
+
+    let api = ApiMessage::try_from(message).unwrap();
+    assert_eq!(
+        serde_json::to_value(&api).unwrap(),
+        json!({
+            "role": "assistant",
+            "content": [
+                {"type": "text", "text": "Listing files"},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}}
+            ]
+        })
+    );
+
+    let system = Message::System(SystemMessage::new("init"));
+    assert!(ApiMessage::try_from(system).is_err());
+}
+
+#[test]
+fn test_api_message_to_message() {
+    let api: ApiMessage = serde_json::from_value(json!({
+        "role": "user",
+        "content": [{
+            "type": "tool_result",
+            "tool_use_id": "toolu_1",
+            "content": [{"type": "text", "text": "a.rs"}, {"type": "text", "text": "b.rs"}]
+        }]
+    }))
+    .unwrap();
+
+    match Message::from(api) {
+        Message::User(UserMessage { content, .. }) => match &content[0] {
+            ContentBlock::ToolResult(result) => {
+                assert_eq!(result.tool_use_id, "toolu_1");
+                assert_eq!(result.content.as_deref(), Some("a.rs\nb.rs"));
+            }
+            other => panic!("unexpected block: {:?}", other),
+        },
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[test]
+fn test_wire_round_trip() {
+    let api = ApiMessage {
+        role: ApiRole::User,
+        content: vec![ApiContentBlock::Text {
+            text: "hello".to_string(),
+        }],
+    };
+
+    let value: serde_json::Value = api.to_wire().unwrap();
+    assert_eq!(ApiMessage::from_wire(&value).unwrap(), api);
+}
@@ -0,0 +1,45 @@
+use claude_code_sdk::isolation::IsolatedHome;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+use serde_json::json;
+
+#[test]
+fn test_generated_settings() {
+    let options = ClaudeCodeOptions::new()
+        .with_allowed_tools(vec!["Read".to_string()])
+        .with_disallowed_tools(vec!["Bash".to_string()]);
+    let home = IsolatedHome::new().with_settings(json!({ "cleanupPeriodDays": 1 }));
+    assert_eq!(
+        home.settings_for(&options),
+        json!({
+            "permissions": { "allow": ["Read"], "deny": ["Bash"] },
+            "cleanupPeriodDays": 1,
+        })
+    );
+
+    let invalid = options.with_isolated_home(IsolatedHome::new().with_settings(json!([1])));
+    assert!(matches!(
+        invalid.validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_home_is_seeded_and_removed() {
+    let home = IsolatedHome::new()
+        .with_settings(json!({ "model": "claude-sonnet-4-5" }))
+        .create(&ClaudeCodeOptions::new())
+        .unwrap();
+    let path = home.path().to_path_buf();
+    let settings: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(path.join(".claude/settings.json")).unwrap())
+            .unwrap();
+    assert_eq!(settings, json!({ "model": "claude-sonnet-4-5" }));
+    assert!(path.join(".config").is_dir());
+    assert!(home
+        .env_vars()
+        .iter()
+        .any(|(key, value)| *key == "HOME" && *value == path));
+
+    drop(home);
+    assert!(!path.exists());
+}
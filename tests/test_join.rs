@@ -0,0 +1,139 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::{join_all_conversations, select_first_success, QueryHandle, Usage};
+
+#[tokio::test]
+async fn test_join_and_select_over_no_queries() {
+    let joined = join_all_conversations(Vec::<QueryHandle>::new()).await;
+    assert!(joined.conversations.is_empty());
+    assert!(joined.all_succeeded());
+    assert_eq!(joined.usage, Usage::default());
+
+    let race = select_first_success(Vec::<QueryHandle>::new()).await;
+    assert!(race.winner.is_none());
+    assert!(race.failed.is_empty());
+    assert!(race.cancelled.is_empty());
+    assert_eq!(race.usage, Usage::default());
+}
+
+#[test]
+fn test_usage_add_sums_every_count() {
+    let mut total = Usage {
+        tokens_input: 100,
+        tokens_output: 20,
+        cache_read_tokens: 64,
+        cost_usd: 0.5,
+        ..Usage::default()
+    };
+    total.add(&Usage {
+        tokens_input: 10,
+        tokens_output: 5,
+        reasoning_tokens: 3,
+        cache_creation_tokens: 32,
+        cost_usd: 0.25,
+        ..Usage::default()
+    });
+    assert_eq!(total.tokens_input, 110);
+    assert_eq!(total.tokens_output, 25);
+    assert_eq!(total.reasoning_tokens, 3);
+    assert_eq!(total.cache_read_tokens, 64);
+    assert_eq!(total.cache_creation_tokens, 32);
+    assert!((total.cost_usd - 0.75).abs() < f64::EPSILON);
+}
+
+mod mocked {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{
+        join_all_conversations, query_with_handle, select_first_success, AssistantMessage,
+        ClaudeCodeOptions, ClaudeSDKError, Message, MessageUsage, QueryHandle, ResultMessage,
+    };
+    use std::time::Duration;
+
+    fn working(id: &str, input_tokens: i32) -> Message {
+        let mut message = AssistantMessage::new(Vec::new());
+        message.id = Some(id.to_string());
+        message.usage = Some(MessageUsage {
+            input_tokens: Some(input_tokens),
+            output_tokens: Some(10),
+            ..MessageUsage::default()
+        });
+        message.into()
+    }
+
+    fn result(cost_usd: f64) -> Message {
+        ResultMessage {
+            tokens_input: Some(100),
+            tokens_output: Some(20),
+            cost_usd: Some(cost_usd),
+            ..ResultMessage::new("run")
+        }
+        .into()
+    }
+
+    async fn handle(mock: &MockTransport) -> QueryHandle {
+        let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+        query_with_handle("Fix the build", Some(options))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_select_keeps_winner_and_counts_every_query() {
+        // Fails at once, after one message
+        let failing = MockTransport::new()
+            .with_message(working("msg_1", 40))
+            .with_error(|| ClaudeSDKError::cli_connection("CLI crashed"));
+        let winning = MockTransport::new()
+            .with_delay(Duration::from_millis(50))
+            .with_message(result(0.5));
+        // Still working when the winner is found; the message repeats
+        // its usage for a second content block
+        let slow = MockTransport::new()
+            .with_messages([working("msg_2", 30), working("msg_2", 30)])
+            .with_delay(Duration::from_secs(30))
+            .with_message(result(2.0));
+
+        let race = select_first_success(vec![
+            handle(&failing).await,
+            handle(&winning).await,
+            handle(&slow).await,
+        ])
+        .await;
+
+        let winner = race.winner.unwrap();
+        assert_eq!(winner.index, 1);
+        assert!(winner.is_success());
+        assert_eq!(race.cancelled, vec![2]);
+        assert_eq!(race.failed.len(), 1);
+        assert_eq!(race.failed[0].index, 0);
+        assert!(race.failed[0].error.is_some());
+        assert_eq!(race.failed[0].usage.tokens_input, 40);
+
+        assert_eq!(race.usage.tokens_input, 100 + 40 + 30);
+        assert_eq!(race.usage.tokens_output, 20 + 10 + 10);
+        assert!((race.usage.cost_usd - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_join_waits_for_every_query() {
+        let slow = MockTransport::new()
+            .with_delay(Duration::from_millis(20))
+            .with_message(result(0.25));
+        let fast = MockTransport::new().with_message(result(0.5));
+        let failing = MockTransport::new().with_message(working("msg_1", 40));
+
+        let joined = join_all_conversations(vec![
+            handle(&slow).await,
+            handle(&fast).await,
+            handle(&failing).await,
+        ])
+        .await;
+        let indexes: Vec<usize> = joined.conversations.iter().map(|c| c.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2]);
+        assert!(!joined.all_succeeded());
+        assert!(joined.conversations[0].is_success());
+        assert!(!joined.conversations[2].is_success());
+        assert_eq!(joined.usage.tokens_input, 100 + 100 + 40);
+        assert!((joined.usage.cost_usd - 0.75).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,64 @@
+use claude_code_sdk::key_rotation::{Credential, KeyRotation};
+use claude_code_sdk::ClaudeCodeOptions;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn counting_rotation(fetches: Arc<AtomicUsize>) -> KeyRotation {
+    KeyRotation::from_fn(move || {
+        let fetches = fetches.clone();
+        async move {
+            let n = fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Credential::ApiKey(format!("key-{}", n)))
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_credential_is_cached_for_ttl() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let rotation = counting_rotation(fetches.clone());
+    let shared = rotation.clone();
+
+    assert_eq!(
+        rotation.current().await.unwrap(),
+        Credential::ApiKey("key-1".to_string())
+    );
+    assert_eq!(
+        shared.current().await.unwrap(),
+        Credential::ApiKey("key-1".to_string())
+    );
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    shared.invalidate();
+    assert_eq!(
+        rotation.current().await.unwrap(),
+        Credential::ApiKey("key-2".to_string())
+    );
+    assert_eq!(
+        rotation.refresh().await.unwrap(),
+        Credential::ApiKey("key-3".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_expired_credential_is_refetched() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let rotation = counting_rotation(fetches.clone()).with_ttl(Duration::ZERO);
+
+    rotation.current().await.unwrap();
+    rotation.current().await.unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_credential_apply_and_debug() {
+    let mut options = ClaudeCodeOptions::new();
+    Credential::AuthToken("secret-token".to_string()).apply(&mut options);
+    let env = options.env.unwrap();
+    assert_eq!(env["ANTHROPIC_AUTH_TOKEN"], "secret-token");
+
+    let credential = Credential::ApiKey("sk-secret".to_string());
+    assert_eq!(credential.env_var(), "ANTHROPIC_API_KEY");
+    assert!(!format!("{:?}", credential).contains("sk-secret"));
+}
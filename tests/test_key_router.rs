@@ -0,0 +1,97 @@
+use claude_code_sdk::key_rotation::{Credential, KeyRotation};
+use claude_code_sdk::key_router::{KeyRouter, RoutingStrategy};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError, ResultMessage};
+use std::time::Duration;
+
+fn router(strategy: RoutingStrategy) -> KeyRouter {
+    KeyRouter::new(strategy)
+        .with_key("a", Credential::ApiKey("key-a".to_string()), 3)
+        .with_key("b", Credential::ApiKey("key-b".to_string()), 1)
+}
+
+fn picks(router: &KeyRouter, count: usize) -> Vec<String> {
+    (0..count).map(|_| router.select().unwrap().name).collect()
+}
+
+#[test]
+fn test_weighted_and_least_recently_used_routing() {
+    let weighted = router(RoutingStrategy::Weighted);
+    assert_eq!(
+        picks(&weighted, 8),
+        ["a", "a", "b", "a", "a", "a", "b", "a"]
+    );
+    assert_eq!(weighted.usage("a").unwrap().queries, 6);
+    assert_eq!(weighted.usage("b").unwrap().queries, 2);
+
+    let lru = router(RoutingStrategy::LeastRecentlyUsed);
+    assert_eq!(picks(&lru, 4), ["a", "b", "a", "b"]);
+
+    let unweighted = KeyRouter::new(RoutingStrategy::Weighted).with_key(
+        "a",
+        Credential::ApiKey("key-a".to_string()),
+        0,
+    );
+    assert!(matches!(
+        unweighted.select(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_quarantine_skips_rate_limited_keys() {
+    let router = router(RoutingStrategy::Weighted);
+    router.quarantine("a", Some(Duration::from_secs(30)));
+
+    assert!(router.is_quarantined("a"));
+    assert_eq!(picks(&router, 3), ["b", "b", "b"]);
+
+    router.quarantine("b", None);
+    assert!(!router.has_available_key());
+    let error = router.select().unwrap_err();
+    assert!(error.is_retryable());
+    assert!(error.retry_after().unwrap() <= Duration::from_secs(30));
+    assert_eq!(router.usage("b").unwrap().rate_limits, 1);
+
+    let short = KeyRouter::new(RoutingStrategy::Weighted)
+        .with_key("a", Credential::ApiKey("key-a".to_string()), 1)
+        .with_quarantine(Duration::ZERO);
+    short.quarantine("a", None);
+    assert_eq!(short.select().unwrap().name, "a");
+}
+
+#[test]
+fn test_quarantine_beyond_the_clock() {
+    let router = router(RoutingStrategy::Weighted);
+    // A Retry-After far past what an Instant can hold
+    router.quarantine("a", Some(Duration::MAX));
+    assert!(router.is_quarantined("a"));
+    assert_eq!(picks(&router, 2), ["b", "b"]);
+}
+
+#[test]
+fn test_usage_and_options() {
+    let router = router(RoutingStrategy::Weighted);
+    let result = ResultMessage {
+        tokens_input: Some(100),
+        tokens_output: Some(20),
+        cost_usd: Some(0.5),
+        ..ResultMessage::new("r1")
+    };
+    router.record_usage("b", &result);
+    router.record_usage("b", &result);
+
+    let usage = router.usage("b").unwrap();
+    assert_eq!(usage.tokens_input, 200);
+    assert_eq!(usage.tokens_output, 40);
+    assert_eq!(usage.cost_usd, 1.0);
+    assert!(router.usage("missing").is_none());
+
+    let rotation = KeyRotation::from_fn(|| async { Ok(Credential::ApiKey("k".to_string())) });
+    let options = ClaudeCodeOptions::new()
+        .with_key_router(router)
+        .with_key_rotation(rotation);
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
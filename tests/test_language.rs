@@ -0,0 +1,66 @@
+use claude_code_sdk::language::*;
+use claude_code_sdk::ClaudeCodeOptions;
+
+#[test]
+fn test_valid_language_tags() {
+    let cases = [
+        ("en", "en"),
+        ("fr-ca", "fr-CA"),
+        ("ZH-hant-tw", "zh-Hant-TW"),
+        ("es-419", "es-419"),
+        ("sl-rozaj-biske", "sl-rozaj-biske"),
+        ("de-CH-1996", "de-CH-1996"),
+        ("en-US-u-ca-gregory", "en-US-u-ca-gregory"),
+        ("en-x-custom", "en-x-custom"),
+        ("X-Klingon", "x-klingon"),
+        ("x-a-b", "x-a-b"),
+        ("i-default", "i-default"),
+        ("EN-gb-OED", "en-GB-oed"),
+        ("sgn-be-fr", "sgn-BE-FR"),
+    ];
+
+    for (input, expected) in cases {
+        let tag = LanguageTag::parse(input).unwrap();
+        assert_eq!(tag.as_str(), expected);
+    }
+}
+
+#[test]
+fn test_invalid_language_tags() {
+    for input in [
+        "",
+        "e",
+        "english!",
+        "en--US",
+        "en-US-",
+        "toolongtag",
+        "en-u",
+        "12",
+        "x",
+        "x-",
+        "i-unknown",
+    ] {
+        assert!(
+            LanguageTag::parse(input).is_err(),
+            "{:?} should be invalid",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_language_tag_serde() {
+    let tag: LanguageTag = serde_json::from_str("\"pt-br\"").unwrap();
+
+    assert_eq!(tag.to_string(), "pt-BR");
+    assert_eq!(serde_json::to_string(&tag).unwrap(), "\"pt-BR\"");
+    assert!(serde_json::from_str::<LanguageTag>("\"not a tag\"").is_err());
+}
+
+#[test]
+fn test_options_with_response_language() {
+    let tag: LanguageTag = "ja".parse().unwrap();
+    let options = ClaudeCodeOptions::new().with_response_language(tag.clone());
+
+    assert_eq!(options.response_language, Some(tag));
+}
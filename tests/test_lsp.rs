@@ -0,0 +1,170 @@
+#![cfg(feature = "lsp")]
+
+use claude_code_sdk::lsp::{read_message, serve, write_message, ASK_COMMAND, FIX_COMMAND};
+use claude_code_sdk::transport::MockTransport;
+use claude_code_sdk::{ClaudeCodeOptions, Message, ResultMessage};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, BufReader};
+
+const DOCUMENT: &str = "fn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n";
+
+fn reply(text: &str) -> Message {
+    ResultMessage {
+        content: Some(text.to_string()),
+        ..ResultMessage::new("run-1")
+    }
+    .into()
+}
+
+/// Serve `messages` with queries answered by `mock`, returning what the
+/// server wrote.
+async fn run(mock: &MockTransport, messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).await.unwrap();
+    }
+    let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+    let (writer, mut output) = tokio::io::duplex(64 * 1024);
+    serve(options, input.as_slice(), writer).await.unwrap();
+
+    let mut bytes = Vec::new();
+    output.read_to_end(&mut bytes).await.unwrap();
+    let mut reader = BufReader::new(bytes.as_slice());
+    let mut written = Vec::new();
+    while let Some(body) = read_message(&mut reader).await.unwrap() {
+        written.push(serde_json::from_slice(&body).unwrap());
+    }
+    written
+}
+
+fn opened() -> Vec<Value> {
+    vec![
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"rootUri": "file:///work/my%20app"}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": "file:///work/my%20app/src/lib.rs", "languageId": "rust", "version": 1, "text": DOCUMENT}
+        }}),
+    ]
+}
+
+#[tokio::test]
+async fn test_lsp_lifecycle_and_code_actions() {
+    let mock = MockTransport::new();
+    let mut messages = vec![json!({"jsonrpc": "2.0", "id": 0, "method": "shutdown"})];
+    messages.extend(opened());
+    messages.push(json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/codeAction", "params": {
+        "textDocument": {"uri": "file:///work/my%20app/src/lib.rs"},
+        "range": {"start": {"line": 1, "character": 4}, "end": {"line": 1, "character": 9}},
+        "context": {"diagnostics": [{"message": "`add` subtracts", "range": {"start": {"line": 1, "character": 4}, "end": {"line": 1, "character": 9}}}]}
+    }}));
+    messages.push(json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/hover", "params": {}}));
+    messages.push(json!({"jsonrpc": "2.0", "id": 4, "method": "shutdown"}));
+    messages.push(json!({"jsonrpc": "2.0", "method": "exit"}));
+    messages.push(json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}));
+    let written = run(&mock, &messages).await;
+
+    assert_eq!(written.len(), 5);
+    assert_eq!(written[0]["error"]["code"], -32002);
+    let commands = &written[1]["result"]["capabilities"]["executeCommandProvider"]["commands"];
+    assert_eq!(commands, &json!([ASK_COMMAND, FIX_COMMAND]));
+
+    let actions = written[2]["result"].as_array().unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0]["command"]["command"], ASK_COMMAND);
+    let fix = &actions[1]["command"];
+    assert_eq!(fix["command"], FIX_COMMAND);
+    // Fixes replace whole lines
+    assert_eq!(
+        fix["arguments"][0]["range"],
+        json!({"start": {"line": 1, "character": 0}, "end": {"line": 2, "character": 0}})
+    );
+    assert_eq!(
+        fix["arguments"][0]["diagnostics"],
+        json!(["`add` subtracts"])
+    );
+
+    assert_eq!(written[3]["error"]["code"], -32601);
+    assert_eq!(
+        written[4],
+        json!({"jsonrpc": "2.0", "id": 4, "result": null})
+    );
+}
+
+#[tokio::test]
+async fn test_lsp_ask_answers_about_the_selection() {
+    let mock = MockTransport::new().with_message(reply("It subtracts b from a."));
+    let mut messages = opened();
+    messages.push(
+        json!({"jsonrpc": "2.0", "id": 2, "method": "workspace/executeCommand", "params": {
+            "command": ASK_COMMAND,
+            "arguments": [{
+                "uri": "file:///work/my%20app/src/lib.rs",
+                "range": {"start": {"line": 1, "character": 4}, "end": {"line": 1, "character": 9}},
+                "instruction": "What does this do?"
+            }]
+        }}),
+    );
+    let written = run(&mock, &messages).await;
+
+    assert_eq!(
+        written.last().unwrap(),
+        &json!({"jsonrpc": "2.0", "id": 2, "result": {"answer": "It subtracts b from a."}})
+    );
+    let prompt = mock.prompts()[0].as_text().unwrap().to_string();
+    assert!(prompt.starts_with("What does this do?"));
+    assert!(prompt.contains("/work/my app/src/lib.rs, lines 2-2"));
+    assert!(prompt.contains("```\na - b\n```"));
+}
+
+#[tokio::test]
+async fn test_lsp_fix_applies_an_edit() {
+    let mock = MockTransport::new().with_message(reply("```rust\n    a + b\n```"));
+    let mut messages = opened();
+    messages.push(
+        json!({"jsonrpc": "2.0", "id": 2, "method": "workspace/executeCommand", "params": {
+            "command": FIX_COMMAND,
+            "arguments": [{
+                "uri": "file:///work/my%20app/src/lib.rs",
+                "range": {"start": {"line": 1, "character": 0}, "end": {"line": 2, "character": 0}},
+                "diagnostics": ["`add` subtracts"]
+            }]
+        }}),
+    );
+    let written = run(&mock, &messages).await;
+
+    let edit = json!({
+        "range": {"start": {"line": 1, "character": 0}, "end": {"line": 2, "character": 0}},
+        "newText": "    a + b\n"
+    });
+    let apply = &written[written.len() - 2];
+    assert_eq!(apply["method"], "workspace/applyEdit");
+    assert_eq!(
+        apply["params"]["edit"]["changes"]["file:///work/my%20app/src/lib.rs"],
+        json!([edit])
+    );
+    assert_eq!(written.last().unwrap()["result"], json!({ "edit": edit }));
+    let prompt = mock.prompts()[0].as_text().unwrap().to_string();
+    assert!(prompt.contains("- `add` subtracts"));
+}
+
+#[tokio::test]
+async fn test_lsp_bounds_ranges_and_message_sizes() {
+    let mock = MockTransport::new();
+    let mut messages = opened();
+    messages.push(json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/codeAction", "params": {
+        "textDocument": {"uri": "file:///work/my%20app/src/lib.rs"},
+        "range": {"start": {"line": 1, "character": 0}, "end": {"line": u32::MAX, "character": 5}},
+        "context": {"diagnostics": []}
+    }}));
+    let written = run(&mock, &messages).await;
+    let fix = &written[1]["result"][1]["command"];
+    assert_eq!(
+        fix["arguments"][0]["range"]["end"],
+        json!({"line": u32::MAX, "character": 0})
+    );
+
+    let header = format!("Content-Length: {}\r\n\r\n", usize::MAX);
+    let mut reader = BufReader::new(header.as_bytes());
+    let error = read_message(&mut reader).await.unwrap_err();
+    assert!(error.to_string().contains("exceeds the limit"), "{}", error);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::memory::*;
+use claude_code_sdk::ClaudeCodeOptions;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_file_memory_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileMemoryStore::new(dir.path().join("memory"));
+
+    assert_eq!(store.load("org/project").await.unwrap(), None);
+    store.save("org/project", "- uses tokio").await.unwrap();
+
+    assert_eq!(
+        store.load("org/project").await.unwrap(),
+        Some("- uses tokio".to_string())
+    );
+    assert!(dir.path().join("memory/org_project.md").exists());
+}
+
+#[tokio::test]
+async fn test_prepare_injects_memory() {
+    let store = Arc::new(InMemoryMemoryStore::new());
+    store
+        .save("billing", "- tests run with `make test`")
+        .await
+        .unwrap();
+
+    let memory = MemoryManager::new("billing", store);
+    let options = memory
+        .prepare(ClaudeCodeOptions::new().with_append_system_prompt("Be concise."))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        options.append_system_prompt,
+        Some(
+            "Be concise.\n\nMemory from previous sessions on this project:\n\
+             - tests run with `make test`"
+                .to_string()
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_prepare_without_memory() {
+    let memory = MemoryManager::new("new-project", Arc::new(InMemoryMemoryStore::new()));
+    let options = memory.prepare(ClaudeCodeOptions::new()).await.unwrap();
+
+    assert!(options.append_system_prompt.is_none());
+}
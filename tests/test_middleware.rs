@@ -0,0 +1,76 @@
+#![cfg(feature = "subprocess")]
+
+use async_trait::async_trait;
+use claude_code_sdk::middleware::Middleware;
+use claude_code_sdk::prompt::PromptInput;
+use claude_code_sdk::{query_with_handle, ClaudeCodeOptions, ClaudeSDKError, Message, Result};
+use std::sync::{Arc, Mutex};
+
+struct PinModel;
+
+#[async_trait]
+impl Middleware for PinModel {
+    async fn before_query(
+        &self,
+        prompt: &mut PromptInput,
+        options: &mut ClaudeCodeOptions,
+    ) -> Result<()> {
+        options.claude_model = Some("claude-sonnet-4".to_string());
+        *prompt = PromptInput::text(format!("[tenant a] {}", prompt.text_content()));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Gate {
+    seen: Mutex<Option<(String, Option<String>)>>,
+}
+
+#[async_trait]
+impl Middleware for Gate {
+    async fn before_query(
+        &self,
+        prompt: &mut PromptInput,
+        options: &mut ClaudeCodeOptions,
+    ) -> Result<()> {
+        *self.seen.lock().unwrap() = Some((prompt.text_content(), options.claude_model.clone()));
+        Err(ClaudeSDKError::Cancelled)
+    }
+}
+
+#[tokio::test]
+async fn test_before_query_runs_in_order_and_can_abort() {
+    let gate = Arc::new(Gate::default());
+    let options = ClaudeCodeOptions::new()
+        .with_middleware(Arc::new(PinModel))
+        .with_middleware(gate.clone());
+
+    // The gate aborts before the CLI is looked up
+    let error = query_with_handle("Hello", Some(options))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, ClaudeSDKError::Cancelled));
+    assert_eq!(
+        *gate.seen.lock().unwrap(),
+        Some((
+            "[tenant a] Hello".to_string(),
+            Some("claude-sonnet-4".to_string())
+        ))
+    );
+}
+
+#[test]
+fn test_default_methods_pass_messages_through() {
+    struct Noop;
+    impl Middleware for Noop {}
+
+    let message: Message = claude_code_sdk::SystemMessage::new("init").into();
+    let passed = Noop.on_message(message.clone()).unwrap();
+    assert_eq!(
+        serde_json::to_value(passed).unwrap(),
+        serde_json::to_value(message).unwrap()
+    );
+    let options = ClaudeCodeOptions::new().with_middleware(Arc::new(Noop));
+    assert_eq!(options.middleware.len(), 1);
+}
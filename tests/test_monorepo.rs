@@ -0,0 +1,56 @@
+use claude_code_sdk::monorepo::*;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+use std::fs;
+
+fn monorepo() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("services/billing")).unwrap();
+    fs::create_dir_all(dir.path().join("libs/proto")).unwrap();
+    dir
+}
+
+#[test]
+fn test_apply_scopes_options() {
+    let dir = monorepo();
+    let options = ClaudeCodeOptions::new()
+        .with_allowed_tools(vec!["Read".to_string()])
+        .with_append_system_prompt("Be concise.");
+
+    let options = MonorepoContext::new(dir.path())
+        .with_package("services/billing")
+        .with_read_only("libs/proto")
+        .with_external_dir("/opt/shared-configs")
+        .apply(options)
+        .unwrap();
+
+    assert_eq!(options.cwd, Some(dir.path().to_path_buf()));
+    assert_eq!(options.add_dirs, Some(vec!["/opt/shared-configs".into()]));
+
+    let allowed = options.allowed_tools.unwrap();
+    assert_eq!(allowed[0], "Read");
+    assert!(allowed.contains(&"Edit(services/billing/**)".to_string()));
+    assert!(allowed.contains(&"Write(services/billing/**)".to_string()));
+    assert!(!allowed.iter().any(|rule| rule.contains("libs/proto")));
+
+    let prompt = options.append_system_prompt.unwrap();
+    assert!(prompt.starts_with("Be concise.\n\n"));
+    assert!(prompt.contains("- services/billing"));
+    assert!(prompt.contains("- libs/proto"));
+}
+
+#[test]
+fn test_validate_rejects_bad_packages() {
+    let dir = monorepo();
+
+    for package in ["../outside", "/absolute", "services/missing"] {
+        let result = MonorepoContext::new(dir.path())
+            .with_package(package)
+            .validate();
+        assert!(
+            matches!(result, Err(ClaudeSDKError::InvalidOptions { .. })),
+            "{} should be rejected",
+            package
+        );
+    }
+    assert!(MonorepoContext::new(dir.path()).validate().is_err());
+}
@@ -0,0 +1,64 @@
+use claude_code_sdk::negotiation::{
+    env_vars, NegotiatedProtocol, PROTOCOL_VERSION, SDK_NAME, SDK_NAME_ENV, SDK_PROTOCOL_ENV,
+    SDK_VERSION_ENV,
+};
+use claude_code_sdk::protocol::decode_line;
+use claude_code_sdk::{ClaudeSDKError, Message, SystemMessage};
+
+fn ack(protocol_version: u32, min_sdk_protocol_version: Option<u32>) -> SystemMessage {
+    SystemMessage {
+        protocol_version: Some(protocol_version),
+        min_sdk_protocol_version,
+        ..SystemMessage::new("protocol")
+    }
+}
+
+#[test]
+fn test_negotiated_protocol_from_ack() {
+    assert!(NegotiatedProtocol::from_ack(&SystemMessage::new("init")).is_none());
+    assert_eq!(NegotiatedProtocol::default().cli_version, None);
+
+    let protocol = NegotiatedProtocol::from_ack(&ack(PROTOCOL_VERSION + 3, Some(1)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(protocol.version, PROTOCOL_VERSION);
+    assert_eq!(protocol.cli_version, Some(PROTOCOL_VERSION + 3));
+
+    let error = NegotiatedProtocol::from_ack(&ack(9, Some(PROTOCOL_VERSION + 1)))
+        .unwrap()
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ClaudeSDKError::IncompatibleProtocol { required, supported, .. }
+            if required == PROTOCOL_VERSION + 1 && supported == PROTOCOL_VERSION
+    ));
+    assert!(error
+        .to_string()
+        .contains("cargo update -p claude-code-sdk"));
+}
+
+#[test]
+fn test_decode_line_refuses_newer_protocol() {
+    let line = format!(
+        r#"{{"type":"system","content":"protocol","protocol_version":9,"min_sdk_protocol_version":{}}}"#,
+        PROTOCOL_VERSION + 1
+    );
+    assert!(matches!(
+        decode_line(&line, None, None),
+        Err(ClaudeSDKError::IncompatibleProtocol { .. })
+    ));
+
+    let line = r#"{"type":"system","content":"protocol","protocol_version":1}"#;
+    let Ok(Some(Message::System(message))) = decode_line(line, None, None) else {
+        panic!("expected a system message");
+    };
+    assert_eq!(message.protocol_version, Some(1));
+}
+
+#[test]
+fn test_env_vars_announce_sdk() {
+    let env = env_vars();
+    assert!(env.contains(&(SDK_NAME_ENV, SDK_NAME.to_string())));
+    assert!(env.contains(&(SDK_VERSION_ENV, env!("CARGO_PKG_VERSION").to_string())));
+    assert!(env.contains(&(SDK_PROTOCOL_ENV, PROTOCOL_VERSION.to_string())));
+}
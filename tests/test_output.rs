@@ -0,0 +1,130 @@
+use claude_code_sdk::output::{JsonParser, OutputFormat, OutputParser, TextParser};
+use claude_code_sdk::protocol::decode_lines;
+use claude_code_sdk::{ClaudeCodeOptions, ContentBlock, Message};
+use futures::io::Cursor;
+use futures::stream::StreamExt;
+
+fn assistant_text(message: &Message) -> &str {
+    match message {
+        Message::Assistant(msg) => match &msg.content[0] {
+            ContentBlock::Text(block) => &block.text,
+            other => panic!("expected text, got {:?}", other),
+        },
+        other => panic!("expected assistant message, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_output_format_args() {
+    assert_eq!(OutputFormat::default(), OutputFormat::StreamJson);
+    assert_eq!(OutputFormat::StreamJson.as_arg(), "stream-json");
+    assert_eq!(OutputFormat::Json.as_arg(), "json");
+    assert_eq!(OutputFormat::Text.as_arg(), "text");
+
+    let options = ClaudeCodeOptions::new().with_output_format(OutputFormat::Json);
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert_eq!(restored.output_format, Some(OutputFormat::Json));
+}
+
+#[test]
+fn test_json_parser_summary() {
+    let mut parser = JsonParser::new(&ClaudeCodeOptions::new(), "run-1");
+    let output = concat!(
+        r#"{"type":"result","subtype":"success","is_error":false,"num_turns":2,"#,
+        r#""result":"Done.","session_id":"s1","total_cost_usd":0.25,"#,
+        r#""usage":{"input_tokens":120,"output_tokens":30,"cache_read_input_tokens":900}}"#
+    );
+    assert!(parser.parse_line(output).unwrap().is_empty());
+
+    let messages = parser.finish().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(assistant_text(&messages[0]), "Done.");
+    let Message::Result(result) = &messages[1] else {
+        panic!("expected result, got {:?}", messages[1]);
+    };
+    assert_eq!(result.id, "run-1");
+    assert_eq!(result.exit_code, Some(0));
+    assert_eq!(result.session_id.as_deref(), Some("s1"));
+    assert_eq!(result.num_turns, Some(2));
+    assert_eq!(result.cost_usd, Some(0.25));
+    assert_eq!(result.tokens_input, Some(120));
+    assert_eq!(result.tokens_output, Some(30));
+    assert_eq!(result.cache_read_tokens, Some(900));
+    assert_eq!(result.cache_creation_tokens, None);
+}
+
+#[test]
+fn test_json_parser_message_array() {
+    let mut parser = JsonParser::new(&ClaudeCodeOptions::new(), "");
+    // Pretty-printed output spans several lines
+    for line in [
+        "[",
+        r#"  {"type":"system","content":"init"},"#,
+        r#"  {"type":"result","id":"r1","exit_code":0}"#,
+        "]",
+    ] {
+        parser.parse_line(line).unwrap();
+    }
+    let messages = parser.finish().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], Message::System(_)));
+    assert!(matches!(messages[1], Message::Result(_)));
+
+    let mut parser = JsonParser::new(&ClaudeCodeOptions::new(), "");
+    parser.parse_line("{not json").unwrap();
+    assert!(parser.finish().is_err());
+}
+
+#[test]
+fn test_json_parser_update_notices_outside_the_document() {
+    use std::sync::{Arc, Mutex};
+
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let seen = notices.clone();
+    let options = ClaudeCodeOptions::new()
+        .on_update_notice(move |event| seen.lock().unwrap().push(event.text.clone()));
+    let mut parser = JsonParser::new(&options, "run-1");
+    for line in [
+        "Update available: 1.0.91",
+        "{",
+        r#"  "type": "result","#,
+        r#"  "result": "Update available: it is {","#,
+        "  \"session_id\": \"s1\"",
+        "}",
+        "Update available: 1.0.92",
+    ] {
+        assert!(parser.parse_line(line).unwrap().is_empty());
+    }
+    let messages = parser.finish().unwrap();
+    assert_eq!(assistant_text(&messages[0]), "Update available: it is {");
+    assert_eq!(notices.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_text_parser() {
+    let mut parser = TextParser::new("run-1");
+    assert!(parser.finish().unwrap().is_empty());
+
+    parser.parse_line("First line").unwrap();
+    parser.parse_line("Second line").unwrap();
+    parser.parse_line("").unwrap();
+    let messages = parser.finish().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(assistant_text(&messages[0]), "First line\nSecond line");
+    assert!(matches!(&messages[1], Message::Result(result) if result.id == "run-1"));
+}
+
+#[tokio::test]
+async fn test_decode_lines_with_output_format() {
+    let options = ClaudeCodeOptions::new().with_output_format(OutputFormat::Text);
+    let messages: Vec<_> = decode_lines(Cursor::new("Hello\nworld\n".as_bytes()), &options)
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        assistant_text(messages[0].as_ref().unwrap()),
+        "Hello\nworld"
+    );
+    assert!(matches!(messages[1], Ok(Message::Result(_))));
+}
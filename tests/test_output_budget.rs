@@ -0,0 +1,183 @@
+use claude_code_sdk::output_budget::output_tokens;
+use claude_code_sdk::{
+    AssistantMessage, ClaudeCodeOptions, ClaudeSDKError, ErrorCode, MessageUsage, TextBlock,
+};
+
+fn reply(text: &str, tokens: Option<i32>) -> AssistantMessage {
+    let mut message = AssistantMessage::new(vec![TextBlock::new(text).into()]);
+    message.usage = tokens.map(|tokens| MessageUsage {
+        output_tokens: Some(tokens),
+        ..Default::default()
+    });
+    message
+}
+
+#[test]
+fn test_output_tokens_prefers_reported_usage() {
+    assert_eq!(output_tokens(&reply("Hello", Some(120))), 120);
+    // Without usage, the text is estimated at four characters a token
+    assert_eq!(output_tokens(&reply("Hello, world", None)), 3);
+    assert_eq!(output_tokens(&reply("", None)), 0);
+}
+
+#[test]
+fn test_max_output_tokens_per_turn_option() {
+    let options = ClaudeCodeOptions::new().with_max_output_tokens_per_turn(2_000);
+    assert_eq!(options.max_output_tokens_per_turn, Some(2_000));
+    assert!(options.validate().is_ok());
+
+    let err = ClaudeCodeOptions::new()
+        .with_max_output_tokens_per_turn(0)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("max_output_tokens_per_turn"));
+}
+
+#[test]
+fn test_output_budget_exceeded_error() {
+    let err = ClaudeSDKError::output_budget_exceeded(1_000, 1_250);
+    assert_eq!(err.code(), ErrorCode::OutputBudgetExceeded);
+    assert_eq!(
+        err.to_string(),
+        "Run interrupted: 1250 output tokens in one turn exceed the budget of 1000"
+    );
+    let json = err.to_json();
+    assert_eq!(json["code"], "output_budget_exceeded");
+    assert_eq!(json["details"]["budget"], 1_000);
+    assert_eq!(json["details"]["output_tokens"], 1_250);
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_turn_over_budget_is_interrupted() {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, ResultMessage};
+    use tokio_stream::StreamExt;
+
+    let mock = MockTransport::new()
+        .with_message(reply("Short answer.", Some(40)))
+        .with_message(ResultMessage::new("run-1"))
+        // The result resets the count, so this turn is measured on its own
+        .with_message(reply("A long, rambling answer...", Some(80)))
+        .with_message(reply("...that goes on and on.", Some(80)))
+        .with_message(ResultMessage::new("run-1"));
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_max_output_tokens_per_turn(100);
+
+    let items: Vec<_> = query("Explain", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 4);
+    assert!(items[..3].iter().all(|item| item.is_ok()));
+    assert!(matches!(
+        items[3],
+        Err(ClaudeSDKError::OutputBudgetExceeded {
+            budget: 100,
+            output_tokens: 160
+        })
+    ));
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_repeated_usage_is_counted_once() {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, ResultMessage, ToolUseBlock};
+    use tokio_stream::StreamExt;
+
+    // One API message sent as a message per content block, each with the
+    // usage of the whole message
+    let with_id = |mut message: AssistantMessage, id: &str| {
+        message.id = Some(id.to_string());
+        message
+    };
+    let mut tool_call = AssistantMessage::new(vec![ToolUseBlock::new(
+        "toolu_1",
+        "Read",
+        serde_json::json!({ "file_path": "src/lib.rs" }),
+    )
+    .into()]);
+    tool_call.usage = Some(MessageUsage {
+        output_tokens: Some(80),
+        ..Default::default()
+    });
+    let mock = MockTransport::new()
+        .with_message(with_id(reply("Let me look.", Some(80)), "msg_1"))
+        .with_message(with_id(tool_call, "msg_1"))
+        .with_message(with_id(reply("Found it.", Some(30)), "msg_2"))
+        .with_message(ResultMessage::new("run-1"));
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_max_output_tokens_per_turn(100);
+
+    let items: Vec<_> = query("Explain", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 3);
+    assert!(matches!(
+        items[2],
+        Err(ClaudeSDKError::OutputBudgetExceeded {
+            budget: 100,
+            output_tokens: 110
+        })
+    ));
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_streamed_deltas_count_against_budget() {
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{query, Message, ResultMessage, StreamEvent};
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    let start =
+        |id: &str| StreamEvent::new(json!({ "type": "message_start", "message": { "id": id } }));
+    let delta = |delta: serde_json::Value| {
+        StreamEvent::new(json!({ "type": "content_block_delta", "index": 0, "delta": delta }))
+    };
+    let text = |chars: usize| delta(json!({ "type": "text_delta", "text": "a".repeat(chars) }));
+
+    // The reported usage replaces the estimate of the deltas
+    let mut reported = reply("", Some(10));
+    reported.id = Some("msg_1".to_string());
+    let mut messages: Vec<Message> = vec![
+        start("msg_1").into(),
+        text(200).into(),
+        delta(json!({ "type": "thinking_delta", "thinking": "b".repeat(100) })).into(),
+        StreamEvent::new(json!({ "type": "message_delta", "usage": { "output_tokens": 10 } }))
+            .into(),
+        reported.into(),
+        start("msg_2").into(),
+        delta(json!({ "type": "input_json_delta", "partial_json": "c".repeat(200) })).into(),
+    ];
+    // A runaway message, stopped before it is complete
+    messages.extend((0..100).map(|_| text(40).into()));
+    messages.push(reply("never sent", Some(2_000)).into());
+    messages.push(ResultMessage::new("run-1").into());
+    let mock = MockTransport::new().with_messages(messages);
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_max_output_tokens_per_turn(100);
+
+    let items: Vec<_> = query("Explain", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    // 10 reported, 50 for the tool input, then 10 per text delta
+    let error = items.last().unwrap().as_ref().unwrap_err();
+    assert!(matches!(
+        error,
+        ClaudeSDKError::OutputBudgetExceeded {
+            budget: 100,
+            output_tokens: 110
+        }
+    ));
+    assert_eq!(items.len(), 7 + 5);
+}
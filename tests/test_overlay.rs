@@ -0,0 +1,59 @@
+use claude_code_sdk::overlay::{ConfigOverlay, InMemoryOverlayProvider, OverlayProvider};
+use claude_code_sdk::tool_policy::{ToolCategory, ToolPolicy};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+use std::sync::Arc;
+
+fn base() -> ClaudeCodeOptions {
+    ClaudeCodeOptions::new()
+        .with_max_turns(10)
+        .with_append_system_prompt("Answer in English.")
+        .with_allowed_tools(vec!["Read".to_string(), "WebFetch".to_string()])
+}
+
+#[test]
+fn test_apply_overlay() {
+    let overlay = ConfigOverlay::new()
+        .with_model("claude-haiku-4-5")
+        .with_max_turns(3)
+        .with_max_output_tokens(2048)
+        .with_append_system_prompt("You work for Acme.")
+        .with_env("ACME_PLAN", "free")
+        .with_tool_policy(ToolPolicy::disable_categories(&[ToolCategory::Network]));
+
+    let options = overlay.apply(base());
+    assert_eq!(options.claude_model.as_deref(), Some("claude-haiku-4-5"));
+    assert_eq!(options.max_turns, Some(3));
+    assert_eq!(options.claude_max_tokens, Some(2048));
+    assert_eq!(
+        options.append_system_prompt.as_deref(),
+        Some("Answer in English.\n\nYou work for Acme.")
+    );
+    assert_eq!(options.env.unwrap()["ACME_PLAN"], "free");
+    assert_eq!(options.allowed_tools, Some(vec!["Read".to_string()]));
+
+    // An empty overlay leaves the options alone
+    let options = ConfigOverlay::new().apply(base());
+    assert_eq!(options.max_turns, Some(10));
+    assert_eq!(options.claude_model, None);
+}
+
+#[tokio::test]
+async fn test_in_memory_provider() {
+    let provider = Arc::new(
+        InMemoryOverlayProvider::new().with_overlay("acme", ConfigOverlay::new().with_max_turns(3)),
+    );
+    assert_eq!(
+        provider.overlay("acme").await.unwrap(),
+        Some(ConfigOverlay::new().with_max_turns(3))
+    );
+    assert_eq!(provider.overlay("globex").await.unwrap(), None);
+    provider.set("globex", ConfigOverlay::new().with_model("claude-opus-4-1"));
+    assert!(provider.overlay("globex").await.unwrap().is_some());
+
+    let options = base().with_tenant("acme").with_overlay_provider(provider);
+    assert!(options.validate().is_ok());
+    assert!(matches!(
+        base().with_tenant("acme").validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+}
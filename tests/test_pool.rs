@@ -0,0 +1,124 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::pool::*;
+use claude_code_sdk::ClaudeSDKError;
+
+#[tokio::test]
+async fn test_high_priority_starts_first() {
+    let pool = SessionPool::new(1);
+    let first = pool.acquire(Priority::Normal).wait().await.unwrap();
+
+    let background = pool.acquire(Priority::Background);
+    let high = pool.acquire(Priority::High);
+    assert_eq!(pool.queued(Priority::Background), 1);
+    assert_eq!(pool.queued(Priority::High), 1);
+
+    drop(first);
+    let high = high.wait().await.unwrap();
+    assert_eq!(high.priority(), Priority::High);
+    assert_eq!(pool.running(Priority::High), 1);
+    assert_eq!(pool.queued(Priority::Background), 1);
+
+    drop(high);
+    let background = background.wait().await.unwrap();
+    assert_eq!(pool.running(Priority::Background), 1);
+    drop(background);
+    assert_eq!(pool.running(Priority::Background), 0);
+}
+
+#[tokio::test]
+async fn test_priority_share_limits_running() {
+    let pool = SessionPool::new(3).with_share(Priority::Background, 1);
+
+    let _batch = pool.acquire(Priority::Background).wait().await.unwrap();
+    let waiting = pool.acquire(Priority::Background);
+    let _normal = pool.acquire(Priority::Normal).wait().await.unwrap();
+
+    assert_eq!(pool.running(Priority::Background), 1);
+    assert_eq!(pool.queued(Priority::Background), 1);
+    assert!(waiting.cancel());
+}
+
+#[tokio::test]
+async fn test_cancel_queued_query() {
+    let pool = SessionPool::new(1);
+    let _running = pool.acquire(Priority::High).wait().await.unwrap();
+
+    let queued = pool.submit("Summarize", None, Priority::Background);
+    assert!(queued.cancel());
+    assert!(!queued.cancel());
+    assert_eq!(pool.queued(Priority::Background), 0);
+
+    assert!(matches!(
+        queued.start().await,
+        Err(ClaudeSDKError::Cancelled)
+    ));
+}
+
+#[tokio::test]
+async fn test_dropped_request_frees_slot() {
+    let pool = SessionPool::new(1);
+    let running = pool.acquire(Priority::Normal).wait().await.unwrap();
+    let abandoned = pool.acquire(Priority::Normal);
+    let next = pool.acquire(Priority::Normal);
+
+    drop(abandoned);
+    drop(running);
+
+    let _next = next.wait().await.unwrap();
+    assert_eq!(pool.running(Priority::Normal), 1);
+}
+
+#[test]
+fn test_affinity_sessions() {
+    let pool = SessionPool::new(1);
+    assert_eq!(pool.affinity_session("alice"), None);
+
+    pool.set_affinity_session("alice", "session-a");
+    pool.set_affinity_session("bob", "session-b");
+    assert_eq!(pool.affinity_session("alice").as_deref(), Some("session-a"));
+    assert_eq!(pool.affinity_keys(), 2);
+
+    assert!(pool.forget_affinity("alice"));
+    assert!(!pool.forget_affinity("alice"));
+    assert_eq!(pool.affinity_session("alice"), None);
+    assert_eq!(pool.affinity_keys(), 1);
+}
+
+#[test]
+fn test_affinity_lru_eviction() {
+    let pool = SessionPool::new(1).with_affinity_capacity(2);
+    pool.set_affinity_session("alice", "session-a");
+    pool.set_affinity_session("bob", "session-b");
+    // Using alice again makes bob the least recently used key
+    pool.set_affinity_session("alice", "session-a2");
+    pool.set_affinity_session("carol", "session-c");
+
+    assert_eq!(pool.affinity_keys(), 2);
+    assert_eq!(pool.affinity_session("bob"), None);
+    assert_eq!(
+        pool.affinity_session("alice").as_deref(),
+        Some("session-a2")
+    );
+    assert_eq!(pool.affinity_session("carol").as_deref(), Some("session-c"));
+
+    let pool = pool.with_affinity_capacity(1);
+    assert_eq!(pool.affinity_keys(), 1);
+    assert_eq!(pool.affinity_session("carol").as_deref(), Some("session-c"));
+}
+
+#[tokio::test]
+async fn test_affinity_key_on_cancelled_query() {
+    let pool = SessionPool::new(1);
+    let _running = pool.acquire(Priority::High).wait().await.unwrap();
+
+    let queued = pool
+        .submit("Hello again", None, Priority::Normal)
+        .with_affinity_key("alice");
+    assert!(queued.cancel());
+    assert!(matches!(
+        queued.start().await,
+        Err(ClaudeSDKError::Cancelled)
+    ));
+    assert_eq!(pool.affinity_keys(), 0);
+}
@@ -0,0 +1,181 @@
+//! The CLI process lifecycle, against shell scripts standing in for the CLI.
+#![cfg(all(feature = "subprocess", unix))]
+
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::{
+    query, ClaudeCodeOptions, ClaudeSDKClient, ClaudeSDKError, Message, ResultMessage,
+};
+use common::{fake_cli, print};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+fn assistant_count(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .filter(|message| matches!(message, Message::Assistant(_)))
+        .count()
+}
+
+#[tokio::test]
+async fn test_slow_consumer_reads_all_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut messages: Vec<Message> = (0..30)
+        .map(|i| Message::assistant_text(format!("line {}", i)))
+        .collect();
+    messages.push(ResultMessage::new("run-1").into());
+    let cli = fake_cli(dir.path(), &format!("{}\nexit 0", print(&messages)));
+
+    let options = ClaudeCodeOptions::new().with_cli_path(cli);
+    let mut stream = query("Count", Some(options)).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(item) = stream.next().await {
+        received.push(item.unwrap());
+        // Well past the CLI's exit and the grace period
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(assistant_count(&received), 30);
+    assert!(matches!(received.last(), Some(Message::Result(_))));
+}
+
+#[tokio::test]
+async fn test_crash_mid_turn_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let body = format!(
+        "read line\n{}\nread line\n{}\necho 'Segmentation fault' >&2\nexit 139",
+        print(&[
+            Message::assistant_text("First"),
+            ResultMessage::new("turn-1").into()
+        ]),
+        print(&[Message::assistant_text("Second")]),
+    );
+    let cli = fake_cli(dir.path(), &body);
+    let mut client = ClaudeSDKClient::connect(ClaudeCodeOptions::new().with_cli_path(cli))
+        .await
+        .unwrap();
+
+    client.send_message("One").await.unwrap();
+    let turn: Vec<Message> = client
+        .receive_response()
+        .collect::<claude_code_sdk::Result<_>>()
+        .await
+        .unwrap();
+    assert!(matches!(turn.last(), Some(Message::Result(_))));
+
+    // The first turn's result does not cover the second
+    client.send_message("Two").await.unwrap();
+    let turn: Vec<_> = client.receive_response().collect().await;
+    assert!(matches!(turn.first(), Some(Ok(Message::Assistant(_)))));
+    let error = turn.last().unwrap().as_ref().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ClaudeSDKError::Process { exit_code: 139, stderr } if stderr.contains("Segmentation fault")
+    ));
+}
+
+#[tokio::test]
+async fn test_stdout_held_open_after_exit() {
+    let dir = tempfile::tempdir().unwrap();
+    let body = format!(
+        "{}\nsleep 5 &\nexit 0",
+        print(&[ResultMessage::new("run-1").into()])
+    );
+    let cli = fake_cli(dir.path(), &body);
+
+    let options = ClaudeCodeOptions::new().with_cli_path(cli);
+    let read = async {
+        query("Hello", Some(options))
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+    };
+    // The background process keeps the pipe open for five seconds
+    let items = tokio::time::timeout(Duration::from_secs(4), read)
+        .await
+        .unwrap();
+    assert!(matches!(items.last(), Some(Ok(Message::Result(_)))));
+}
+
+/// A CLI without `--format json`, which runs `text_mode` when started
+/// without it. Each invocation's arguments go to `args` in `dir`.
+fn text_only_cli(dir: &Path, text_mode: &str) -> PathBuf {
+    let body = format!(
+        "echo \"$@\" >> '{}'\n\
+         case \" $* \" in *' --format json '*) echo \"error: unknown option '--format'\" >&2; exit 1;; esac\n{}",
+        dir.join("args").display(),
+        text_mode
+    );
+    fake_cli(dir, &body)
+}
+
+async fn text_fallback_run(dir: &Path, text_mode: &str) -> Vec<claude_code_sdk::Result<Message>> {
+    let options = ClaudeCodeOptions::new().with_cli_path(text_only_cli(dir, text_mode));
+    query("Hello", Some(options))
+        .await
+        .unwrap()
+        .filter(|item| !matches!(item, Ok(Message::SdkInfo(_))))
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn test_rejected_json_format_is_retried_as_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let received = text_fallback_run(dir.path(), "echo 'Hi there'").await;
+
+    let args = std::fs::read_to_string(dir.path().join("args")).unwrap();
+    let runs: Vec<&str> = args.lines().collect();
+    assert_eq!(runs.len(), 2, "{}", args);
+    assert!(runs[0].contains("--format json"));
+    assert!(!runs[1].contains("--format"));
+    assert!(runs[1].contains("Hello"));
+
+    // The missing capability is reported first
+    let Some(Ok(Message::System(warning))) = received.first() else {
+        panic!("{:?}", received);
+    };
+    assert!(warning.content.contains("--format json"), "{:?}", warning);
+}
+
+#[tokio::test]
+async fn test_text_fallback_wraps_the_output_in_messages() {
+    let dir = tempfile::tempdir().unwrap();
+    let received = text_fallback_run(dir.path(), "echo 'Hi there'").await;
+
+    let messages: Vec<Message> = received.into_iter().map(Result::unwrap).collect();
+    assert_eq!(messages.len(), 3, "{:?}", messages);
+    assert_eq!(assistant_count(&messages), 1);
+    let Message::Assistant(assistant) = &messages[1] else {
+        panic!("{:?}", messages);
+    };
+    assert!(matches!(
+        &assistant.content[..],
+        [claude_code_sdk::ContentBlock::Text(text)] if text.text == "Hi there"
+    ));
+    let Message::Result(result) = &messages[2] else {
+        panic!("{:?}", messages);
+    };
+    assert_eq!(result.exit_code, Some(0));
+}
+
+#[tokio::test]
+async fn test_failed_text_fallback_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let received = text_fallback_run(
+        dir.path(),
+        "echo 'Partial'\necho 'out of credits' >&2\nexit 3",
+    )
+    .await;
+
+    assert_eq!(received.len(), 3, "{:?}", received);
+    assert!(matches!(received[0], Ok(Message::System(_))));
+    assert!(matches!(received[1], Ok(Message::Assistant(_))));
+    let error = received[2].as_ref().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ClaudeSDKError::Process { exit_code: 3, stderr } if stderr.contains("out of credits")
+    ));
+}
@@ -0,0 +1,49 @@
+use claude_code_sdk::progress::*;
+#[cfg(feature = "subprocess")]
+use claude_code_sdk::ClaudeCodeOptions;
+#[cfg(feature = "subprocess")]
+use tokio_stream::StreamExt;
+
+#[test]
+fn test_parse_tool_progress() {
+    let value = serde_json::json!({
+        "type": "tool_progress",
+        "tool_use_id": "tool-1",
+        "tool_name": "Bash",
+        "output": "Compiling foo\n"
+    });
+
+    let event = ToolProgressEvent::parse(&value).unwrap();
+
+    assert_eq!(event.tool_use_id, "tool-1");
+    assert_eq!(event.tool_name, Some("Bash".to_string()));
+    assert_eq!(event.output, Some("Compiling foo\n".to_string()));
+    assert_eq!(event.progress, None);
+}
+
+#[test]
+fn test_parse_ignores_other_messages() {
+    let value = serde_json::json!({"type": "system", "content": "hello"});
+
+    assert!(ToolProgressEvent::parse(&value).is_none());
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_progress_channel() {
+    let (callback, mut events) = progress_channel();
+    let options = ClaudeCodeOptions::new().with_progress_callback(callback);
+
+    let event = ToolProgressEvent {
+        tool_use_id: "tool-1".to_string(),
+        tool_name: None,
+        output: None,
+        message: Some("50% done".to_string()),
+        progress: Some(0.5),
+    };
+    (options.progress_callback.as_ref().unwrap())(&event);
+    drop(options);
+
+    assert_eq!(events.next().await, Some(event));
+    assert_eq!(events.next().await, None);
+}
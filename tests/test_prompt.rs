@@ -0,0 +1,71 @@
+use claude_code_sdk::prompt::{InputBlock, MediaSource, PromptInput};
+use claude_code_sdk::ClaudeSDKError;
+use std::fs;
+
+#[test]
+fn test_from_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("shot.PNG"), [0x89, b'P', b'N', b'G']).unwrap();
+    fs::write(dir.path().join("notes.md"), "# Notes").unwrap();
+    fs::write(dir.path().join("blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+    assert_eq!(
+        InputBlock::from_file(dir.path().join("shot.PNG")).unwrap(),
+        InputBlock::image_base64("image/png", "iVBORw==")
+    );
+    let InputBlock::Document { source, title } =
+        InputBlock::from_file(dir.path().join("notes.md")).unwrap()
+    else {
+        panic!("expected a document");
+    };
+    assert_eq!(
+        source,
+        MediaSource::Text {
+            media_type: "text/plain".to_string(),
+            data: "# Notes".to_string()
+        }
+    );
+    assert!(title.unwrap().ends_with("notes.md"));
+
+    for name in ["blob.bin", "missing.txt"] {
+        let error = InputBlock::from_file(dir.path().join(name)).unwrap_err();
+        assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+    }
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(PromptInput::from("hi"), PromptInput::Text("hi".to_string()));
+    assert_eq!(PromptInput::from(&"hi".to_string()).as_text(), Some("hi"));
+
+    let prompt = PromptInput::text("Describe this")
+        .with_block(InputBlock::image_base64("image/gif", "R0lG"));
+    assert_eq!(prompt.as_text(), None);
+    assert_eq!(prompt.text_content(), "Describe this");
+    assert_eq!(prompt.clone().into_blocks().len(), 2);
+    assert!(PromptInput::text("").into_blocks().is_empty());
+    assert!(PromptInput::text("").is_empty());
+    assert!(PromptInput::Blocks(Vec::new()).is_empty());
+    assert!(!prompt.is_empty());
+}
+
+#[test]
+fn test_stream_json() {
+    let prompt = PromptInput::text("What is in the image?")
+        .with_block(InputBlock::image_base64("image/png", "AAAA"));
+    let line: serde_json::Value = serde_json::from_str(&prompt.to_stream_json().unwrap()).unwrap();
+
+    assert_eq!(
+        line,
+        serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What is in the image?"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}},
+                ],
+            },
+        })
+    );
+}
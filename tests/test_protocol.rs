@@ -0,0 +1,219 @@
+use claude_code_sdk::protocol::{cli_command, decode_line, decode_lines, decode_utf8_lossy};
+use claude_code_sdk::{
+    ClaudeCodeOptions, ClaudeSDKError, ContentBlock, Message, MessageFilter, RedactedThinkingBlock,
+    TextBlock, ThinkingBlock, ToolResultBlock, ToolUseBlock,
+};
+use futures::io::Cursor;
+use futures::stream::StreamExt;
+use proptest::prelude::*;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_decode_line() {
+    let message = decode_line(r#"{"type":"system","content":"init"}"#, None, None).unwrap();
+    assert!(matches!(message, Some(Message::System(_))));
+
+    // The init message names the session and has no content of its own
+    let message = decode_line(
+        r#"{"type":"system","subtype":"init","session_id":"session-42"}"#,
+        None,
+        None,
+    )
+    .unwrap();
+    let Some(Message::System(init)) = message else {
+        panic!("expected system message, got {:?}", message);
+    };
+    assert_eq!(init.content, "init");
+    assert_eq!(init.session_id.as_deref(), Some("session-42"));
+
+    assert!(matches!(
+        decode_line("not json", None, None),
+        Err(ClaudeSDKError::CLIJSONDecode { .. })
+    ));
+    assert!(matches!(
+        decode_line(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            None,
+            None
+        ),
+        Err(ClaudeSDKError::Api { .. })
+    ));
+    // Out-of-range retry delays are dropped instead of overflowing
+    let error = decode_line(
+        r#"{"type":"error","retry_after":1e300,"error":{"type":"rate_limit_error","message":"retry after 99999999999999999999999"}}"#,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(error.retry_after(), None);
+}
+
+#[tokio::test]
+async fn test_decode_lines_from_any_reader() {
+    let output = concat!(
+        r#"{"type":"system","content":"init"}"#,
+        "\n",
+        r#"{"type":"tool_progress","tool_use_id":"toolu_1","output":"Compiling"}"#,
+        "\n",
+        r#"{"type":"result","id":"r1","exit_code":0}"#,
+        "\n",
+    );
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let options = ClaudeCodeOptions::new()
+        .with_message_filter(MessageFilter::new().without_system_messages())
+        .on_tool_progress(move |event| seen.lock().unwrap().push(event.tool_use_id.clone()));
+
+    let messages: Vec<_> = decode_lines(Cursor::new(output.as_bytes()), &options)
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(messages[0], Ok(Message::Result(_))));
+    assert_eq!(*progress.lock().unwrap(), vec!["toolu_1".to_string()]);
+}
+
+#[test]
+fn test_decode_utf8_lossy() {
+    let (line, notice) = decode_utf8_lossy(b"{\"type\":\"system\"}\r");
+    assert_eq!(line, r#"{"type":"system"}"#);
+    assert!(notice.is_none());
+
+    let (line, notice) = decode_utf8_lossy(b"bin\xff\xfeary \xe2\x82");
+    assert_eq!(line, "bin\u{FFFD}\u{FFFD}ary \u{FFFD}");
+    match notice {
+        Some(Message::System(msg)) => assert!(msg.content.contains("Replaced 3 invalid")),
+        other => panic!("expected a system message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_decode_lines_tolerates_invalid_utf8() {
+    let mut output = br#"{"type":"result","id":"r1","exit_code":0,"content":"bad "#.to_vec();
+    output.extend_from_slice(b"\xc3\x28\"}\n");
+
+    let messages: Vec<_> = decode_lines(Cursor::new(output), &ClaudeCodeOptions::new())
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], Ok(Message::System(_))));
+    assert!(messages[1].is_ok());
+}
+
+#[test]
+fn test_cli_command_disallowed_tools() {
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path("claude-code")
+        .with_disallowed_tools(vec!["WebSearch".to_string(), "Bash(rm:*)".to_string()]);
+    let command = cli_command(&options, "Hello", true).unwrap();
+    let args: Vec<_> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    // One comma-separated list, as the CLI expects
+    let flags: Vec<_> = args
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| *arg == "--disallowedTools")
+        .collect();
+    assert_eq!(flags.len(), 1);
+    assert!(args[flags[0].0 + 1].starts_with("WebSearch,Bash(rm:*)"));
+}
+
+fn content_block() -> impl Strategy<Value = ContentBlock> {
+    let text = "[a-z0-9 .]{0,40}";
+    prop_oneof![
+        text.prop_map(|text| TextBlock::new(text).into()),
+        (text, "[a-z_]{1,12}", any::<i64>()).prop_map(|(id, name, n)| {
+            ToolUseBlock::new(id, name, serde_json::json!({ "n": n })).into()
+        }),
+        (
+            text,
+            proptest::option::of(text),
+            proptest::option::of(any::<bool>())
+        )
+            .prop_map(|(id, content, is_error)| {
+                ToolResultBlock::new(id, content, is_error).into()
+            }),
+        text.prop_map(|thinking| ThinkingBlock::new(thinking).into()),
+        text.prop_map(|data| RedactedThinkingBlock {
+            data: data.to_string()
+        }
+        .into()),
+    ]
+}
+
+/// A user or assistant message line, ASCII only.
+fn message_line() -> impl Strategy<Value = String> {
+    (
+        prop_oneof![Just("user"), Just("assistant")],
+        proptest::collection::vec(content_block(), 0..4),
+    )
+        .prop_map(|(message_type, content)| {
+            serde_json::json!({
+                "type": message_type,
+                "message": { "role": message_type, "content": content },
+            })
+            .to_string()
+        })
+}
+
+/// Lines that look like CLI output: mostly messages, some cut short, some
+/// arbitrary text.
+fn cli_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => message_line(),
+        1 => (message_line(), any::<prop::sample::Index>())
+            .prop_map(|(line, cut)| line[..cut.index(line.len())].to_string()),
+        1 => any::<String>(),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_generated_messages_decode(line in message_line()) {
+        let decoded = match decode_line(&line, None, None) {
+            Ok(Some(message @ (Message::User(_) | Message::Assistant(_)))) => {
+                serde_json::to_value(message).unwrap()
+            }
+            other => panic!("unexpected decode of {}: {:?}", line, other),
+        };
+        let original: serde_json::Value = serde_json::from_str(&line).unwrap();
+        prop_assert_eq!(&decoded, &original);
+
+        // The flat form of earlier SDK versions decodes to the same message
+        let flat = serde_json::json!({
+            "type": original["type"],
+            "content": original["message"]["content"],
+        });
+        let decoded_flat = serde_json::to_value(decode_line(&flat.to_string(), None, None).unwrap()).unwrap();
+        prop_assert_eq!(decoded_flat, original);
+    }
+
+    #[test]
+    fn test_decode_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+        let (line, _) = decode_utf8_lossy(&bytes);
+        let _ = decode_line(&line, None, None);
+        let _ = serde_json::from_slice::<Message>(&bytes);
+        let _ = serde_json::from_slice::<ContentBlock>(&bytes);
+    }
+
+    #[test]
+    fn test_decode_lines_frames_every_line(
+        lines in proptest::collection::vec(cli_line(), 0..8),
+        crlf in any::<bool>(),
+    ) {
+        let separator = if crlf { "\r\n" } else { "\n" };
+        let output = lines
+            .iter()
+            .map(|line| format!("{}{}", line.replace(['\r', '\n'], " "), separator))
+            .collect::<String>();
+        let items: Vec<_> = futures::executor::block_on(
+            decode_lines(Cursor::new(output.into_bytes()), &ClaudeCodeOptions::new()).collect(),
+        );
+        // One message or error per line, nothing dropped or merged
+        prop_assert_eq!(items.len(), lines.len());
+    }
+}
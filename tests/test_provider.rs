@@ -0,0 +1,67 @@
+use claude_code_sdk::provider::*;
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError};
+
+#[test]
+fn test_bedrock_env_vars() {
+    let provider = Provider::Bedrock {
+        region: "us-east-1".to_string(),
+        profile: Some("prod".to_string()),
+        base_url: None,
+    };
+
+    assert!(provider.validate().is_ok());
+    assert_eq!(
+        provider.env_vars(),
+        vec![
+            ("CLAUDE_CODE_USE_BEDROCK", "1".to_string()),
+            ("AWS_REGION", "us-east-1".to_string()),
+            ("AWS_PROFILE", "prod".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_vertex_env_vars() {
+    let provider = Provider::vertex("my-project", "us-east5");
+
+    assert!(provider.validate().is_ok());
+    assert_eq!(
+        provider.env_vars(),
+        vec![
+            ("CLAUDE_CODE_USE_VERTEX", "1".to_string()),
+            ("ANTHROPIC_VERTEX_PROJECT_ID", "my-project".to_string()),
+            ("CLOUD_ML_REGION", "us-east5".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_provider_validation() {
+    let error = Provider::vertex("", "us-east5").validate().unwrap_err();
+
+    assert!(matches!(error, ClaudeSDKError::InvalidOptions { .. }));
+    assert_eq!(
+        error.to_string(),
+        "Invalid options: project is required for the Vertex provider"
+    );
+    assert!(Provider::bedrock(" ").validate().is_err());
+    assert!(Provider::Anthropic.env_vars().is_empty());
+}
+
+#[test]
+fn test_options_with_provider() {
+    let options = ClaudeCodeOptions::new().with_provider(Provider::bedrock("eu-west-1"));
+
+    assert_eq!(options.provider, Some(Provider::bedrock("eu-west-1")));
+}
+
+#[test]
+fn test_options_validate_provider() {
+    let options = ClaudeCodeOptions::new().with_provider(Provider::bedrock(""));
+
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::InvalidOptions { .. })
+    ));
+    assert!(ClaudeCodeOptions::new().validate().is_ok());
+}
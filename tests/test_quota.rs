@@ -0,0 +1,113 @@
+use claude_code_sdk::api_error::ApiErrorKind;
+use claude_code_sdk::quota::{QuotaState, RateLimits};
+use claude_code_sdk::ClaudeSDKError;
+use std::time::Duration;
+
+fn rate_limited(message: &str, retry_after: Option<Duration>) -> ClaudeSDKError {
+    ClaudeSDKError::Api {
+        kind: ApiErrorKind::RateLimit,
+        message: message.to_string(),
+        retry_after,
+    }
+}
+
+#[test]
+fn test_parse_rate_limits() {
+    let headers = "anthropic-ratelimit-requests-limit: 50\n\
+                   anthropic-ratelimit-requests-remaining: 12\n\
+                   anthropic-ratelimit-input-tokens-remaining: 8000\n\
+                   anthropic-ratelimit-output-tokens-remaining: 300\n\
+                   retry-after: 4";
+    assert_eq!(
+        RateLimits::parse(headers),
+        Some(RateLimits {
+            requests_limit: Some(50),
+            requests_remaining: Some(12),
+            tokens_limit: None,
+            // The tightest token limit
+            tokens_remaining: Some(300),
+            retry_after: Some(Duration::from_secs(4)),
+        })
+    );
+    let json = r#"{"headers": {"Anthropic-RateLimit-Tokens-Limit": "40000"}}"#;
+    assert_eq!(RateLimits::parse(json).unwrap().tokens_limit, Some(40000));
+    assert_eq!(RateLimits::parse("Overloaded"), None);
+
+    // A bare 429 still means nothing remains
+    let limits = RateLimits::from_error(&rate_limited("Too many requests", None)).unwrap();
+    assert!(limits.is_exhausted());
+    let overloaded = ClaudeSDKError::Api {
+        kind: ApiErrorKind::Overloaded,
+        message: "Overloaded".to_string(),
+        retry_after: None,
+    };
+    assert_eq!(RateLimits::from_error(&overloaded), None);
+}
+
+#[test]
+fn test_quota_paces_to_observed_limits() {
+    let quota = QuotaState::new();
+    // Nothing is known, so nothing is paced
+    for _ in 0..10 {
+        assert_eq!(quota.try_acquire(), Ok(()));
+    }
+
+    quota.observe_limits(&RateLimits {
+        requests_limit: Some(2),
+        requests_remaining: Some(1),
+        ..RateLimits::default()
+    });
+    assert_eq!(quota.try_acquire(), Ok(()));
+    // Two requests a minute refill one every 30 seconds
+    let delay = quota.try_acquire().unwrap_err();
+    assert!(delay > Duration::from_secs(29) && delay <= Duration::from_secs(30));
+
+    let quota = QuotaState::new()
+        .with_requests_per_minute(100)
+        .with_backoff(Duration::from_secs(5));
+    quota.observe_error(&rate_limited(
+        "anthropic-ratelimit-tokens-remaining: 0",
+        None,
+    ));
+    assert_eq!(quota.rate_limits(), 1);
+    assert_eq!(quota.limits().tokens_remaining, Some(0));
+    let delay = quota.delay();
+    assert!(delay > Duration::from_secs(4) && delay <= Duration::from_secs(5));
+    // The API's retry-after wins over the backoff
+    quota.observe_error(&rate_limited("Slow down", Some(Duration::from_secs(20))));
+    assert!(quota.delay() > Duration::from_secs(19));
+    assert_eq!(quota.rate_limits(), 2);
+
+    // An absurd retry-after is capped instead of overflowing
+    let quota = QuotaState::new();
+    quota.observe_error(&rate_limited("retry-after: 10000000000000000000", None));
+    let delay = quota.delay();
+    assert!(
+        delay > Duration::from_secs(23 * 60 * 60) && delay <= Duration::from_secs(24 * 60 * 60)
+    );
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_pool_feeds_its_quota() {
+    use claude_code_sdk::pool::{Priority, SessionPool};
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{ClaudeCodeOptions, ResultMessage};
+    use tokio_stream::StreamExt;
+
+    let mock = MockTransport::new()
+        .with_error(|| rate_limited("retry-after: 15", None))
+        .with_message(ResultMessage::new("run-1"));
+    let options = ClaudeCodeOptions::new().with_transport_factory(mock.factory());
+    let pool = SessionPool::new(2).with_quota(QuotaState::new());
+
+    let query = pool.submit("Summarize", Some(options), Priority::Background);
+    let items: Vec<_> = query.start().await.unwrap().collect().await;
+    assert!(items[0].is_err());
+
+    let quota = pool.quota().unwrap();
+    assert_eq!(quota.rate_limits(), 1);
+    assert_eq!(quota.limits().retry_after, Some(Duration::from_secs(15)));
+    // The next query waits
+    assert!(quota.delay() > Duration::from_secs(14));
+}
@@ -0,0 +1,49 @@
+use claude_code_sdk::{
+    AssistantMessage, ContentBlock, Message, Refusal, RefusalCategory, TextBlock,
+};
+use serde_json::json;
+
+#[test]
+fn test_refusal_stop_reason() {
+    let value = json!({
+        "type": "assistant",
+        "message": {
+            "content": [{"type": "text", "text": "That would violate the usage policy."}],
+            "stop_reason": "refusal"
+        }
+    });
+
+    let refusal = Refusal::from_value(&value).unwrap();
+    assert_eq!(refusal.category, RefusalCategory::Policy);
+    assert_eq!(refusal.stop_reason.as_deref(), Some("refusal"));
+    assert_eq!(refusal.text, "That would violate the usage policy.");
+}
+
+#[test]
+fn test_refusal_text_patterns() {
+    let refusal = Refusal::from_text("I'm sorry, but I can't help with writing malware.").unwrap();
+    assert_eq!(refusal.category, RefusalCategory::Safety);
+    assert_eq!(refusal.stop_reason, None);
+
+    let refusal = Refusal::from_text("I won't provide that person's home address.").unwrap();
+    assert_eq!(refusal.category, RefusalCategory::Other);
+
+    assert!(Refusal::from_text("Done! The tests pass now.").is_none());
+    assert!(Refusal::from_text("The parser can't handle tabs, so I fixed it.").is_none());
+}
+
+#[test]
+fn test_refusal_from_message() {
+    let message = Message::Assistant(AssistantMessage::new(vec![ContentBlock::Text(
+        TextBlock::new("I cannot assist with collecting personal data without consent."),
+    )]));
+
+    let refusal = Refusal::from_message(&message).unwrap();
+    assert_eq!(refusal.category, RefusalCategory::Privacy);
+
+    let value = json!({
+        "type": "assistant",
+        "content": [{"type": "text", "text": "Here is the summary."}]
+    });
+    assert!(Refusal::from_value(&value).is_none());
+}
@@ -0,0 +1,91 @@
+use claude_code_sdk::api_error::*;
+use claude_code_sdk::retry::*;
+use claude_code_sdk::types::*;
+use claude_code_sdk::ClaudeSDKError;
+use std::time::Duration;
+
+#[test]
+fn test_parse_overloaded_error_text() {
+    let error = from_text(
+        r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+    )
+    .unwrap();
+
+    match &error {
+        ClaudeSDKError::Api { kind, message, .. } => {
+            assert_eq!(*kind, ApiErrorKind::Overloaded);
+            assert_eq!(message, "Overloaded");
+        }
+        _ => panic!("Expected Api variant"),
+    }
+    assert!(error.is_retryable());
+    assert_eq!(error.retry_after(), None);
+}
+
+#[test]
+fn test_parse_rate_limit_with_retry_after() {
+    let value = serde_json::json!({
+        "type": "error",
+        "error": {"type": "rate_limit_error", "message": "Rate limited"},
+        "retry_after": 30
+    });
+
+    let error = from_error_value(&value).unwrap();
+
+    assert!(error.is_retryable());
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(30)));
+    assert_eq!(
+        error.to_string(),
+        "Claude API error (rate limited): Rate limited"
+    );
+}
+
+#[test]
+fn test_parse_status_only_error_text() {
+    let error = from_text("API Error: 429 Too Many Requests (retry-after: 12)").unwrap();
+
+    assert!(matches!(
+        &error,
+        ClaudeSDKError::Api {
+            kind: ApiErrorKind::RateLimit,
+            ..
+        }
+    ));
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(12)));
+
+    let error = from_text("API Error: 401 Invalid API key").unwrap();
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_detect_api_error_message() {
+    let message: Message = AssistantMessage::new(vec![TextBlock::new(
+        r#"API Error: 500 {"type":"error","error":{"type":"api_error","message":"Internal"}}"#,
+    )
+    .into()])
+    .into();
+    assert!(from_message(&message).unwrap().is_retryable());
+
+    let normal: Message = AssistantMessage::new(vec![TextBlock::new("All good").into()]).into();
+    assert!(from_message(&normal).is_none());
+    assert!(from_text("Everything compiled").is_none());
+}
+
+#[test]
+fn test_retry_policy_delay() {
+    let policy = RetryPolicy::new(5)
+        .with_initial_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_millis(500));
+
+    assert_eq!(policy.delay(0, None), Duration::from_millis(100));
+    assert_eq!(policy.delay(1, None), Duration::from_millis(200));
+    assert_eq!(policy.delay(2, None), Duration::from_millis(400));
+    assert_eq!(policy.delay(3, None), Duration::from_millis(500));
+    // Far past the point where the backoff overflows a duration
+    assert_eq!(policy.delay(1000, None), Duration::from_millis(500));
+    assert_eq!(policy.delay(u32::MAX, None), Duration::from_millis(500));
+    assert_eq!(
+        policy.delay(0, Some(Duration::from_secs(7))),
+        Duration::from_secs(7)
+    );
+}
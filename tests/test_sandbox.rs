@@ -0,0 +1,32 @@
+#![cfg(feature = "sandbox-linux")]
+
+use claude_code_sdk::sandbox::SandboxPolicy;
+use claude_code_sdk::ClaudeCodeOptions;
+use std::path::PathBuf;
+
+#[test]
+fn test_writable_paths_cover_workspace() {
+    let policy = SandboxPolicy::new().with_writable_path("/srv/cache");
+    let options = ClaudeCodeOptions::new()
+        .with_cwd("/srv/jobs/1")
+        .with_add_dirs(vec![PathBuf::from("/srv/shared")]);
+
+    let writable = policy.writable_paths_for(&options);
+
+    assert_eq!(writable[0], PathBuf::from("/srv/jobs/1"));
+    assert_eq!(writable[1], PathBuf::from("/srv/shared"));
+    assert!(writable.contains(&std::env::temp_dir()));
+    assert!(writable.contains(&PathBuf::from("/srv/cache")));
+}
+
+#[test]
+fn test_policy_builders_and_roundtrip() {
+    let policy = SandboxPolicy::new().without_seccomp().best_effort();
+    assert!(policy.filesystem);
+    assert!(!policy.seccomp);
+    assert!(!policy.require_landlock);
+
+    let options = ClaudeCodeOptions::new().with_sandbox(policy.clone());
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert_eq!(restored.sandbox, Some(policy));
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::script::{Condition, Script, ScriptStatus, StepOutcome};
+use claude_code_sdk::{
+    AssistantMessage, ClaudeCodeOptions, ContentBlock, Message, ResultMessage, TextBlock,
+    ToolUseBlock,
+};
+use serde_json::json;
+
+fn outcome() -> StepOutcome {
+    let mut result = ResultMessage::new("r1");
+    result.exit_code = Some(0);
+    result.session_id = Some("session-1".to_string());
+    StepOutcome {
+        prompt: "Run the tests".to_string(),
+        messages: vec![
+            Message::Assistant(AssistantMessage::new(vec![
+                ContentBlock::Text(TextBlock::new("Running cargo test")),
+                ContentBlock::ToolUse(ToolUseBlock::new(
+                    "toolu_1",
+                    "Bash",
+                    json!({"command": "cargo test"}),
+                )),
+            ])),
+            Message::Result(result),
+        ],
+    }
+}
+
+#[test]
+fn test_conditions() {
+    let outcome = outcome();
+    assert_eq!(outcome.session_id(), Some("session-1"));
+    assert!(Condition::text_contains("cargo test").matches(&outcome));
+    assert!(Condition::tool_used("Bash").matches(&outcome));
+    assert!(!Condition::tool_used("Write").matches(&outcome));
+    assert!(Condition::Succeeded.matches(&outcome));
+    assert!(!Condition::custom("one message", |o| o.messages.len() == 1).matches(&outcome));
+}
+
+#[tokio::test]
+async fn test_script_without_prompts() {
+    let run = Script::new().run(ClaudeCodeOptions::new()).await.unwrap();
+    assert!(run.is_completed());
+    assert!(run.steps.is_empty());
+
+    let run = Script::new()
+        .on_tool_use(
+            "Bash",
+            Script::new(),
+            Script::new().expect(Condition::Succeeded),
+        )
+        .run(ClaudeCodeOptions::new().with_resume("session-0"))
+        .await
+        .unwrap();
+    assert_eq!(
+        run.status,
+        ScriptStatus::ExpectationFailed {
+            step: 0,
+            condition: "succeeded".to_string()
+        }
+    );
+    assert_eq!(run.session_id.as_deref(), Some("session-0"));
+}
@@ -0,0 +1,54 @@
+use claude_code_sdk::sdk_info::OptionsSummary;
+use claude_code_sdk::{ClaudeCodeOptions, Message, MessageFilter, Provider, SdkInfo};
+use std::collections::HashMap;
+
+fn options() -> ClaudeCodeOptions {
+    let mut env = HashMap::new();
+    env.insert("SECRET_TOKEN".to_string(), "hunter2".to_string());
+    ClaudeCodeOptions {
+        claude_api_key: Some("sk-ant-secret".to_string()),
+        env: Some(env),
+        ..ClaudeCodeOptions::new()
+    }
+    .with_cwd("/work/project")
+    .with_max_turns(5)
+    .with_provider(Provider::bedrock("us-east-1"))
+}
+
+#[test]
+fn test_options_summary_omits_secrets() {
+    let summary = OptionsSummary::new(&options());
+    assert_eq!(summary.max_turns, Some(5));
+    assert_eq!(summary.provider.as_deref(), Some("bedrock"));
+    assert_eq!(summary.env_keys, vec!["SECRET_TOKEN".to_string()]);
+
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(!json.contains("hunter2"));
+    assert!(!json.contains("sk-ant-secret"));
+}
+
+#[test]
+fn test_sdk_info_message_round_trip() {
+    let info = SdkInfo::new(
+        Some("/usr/local/bin/claude-code".into()),
+        Some("1.0.0 (Claude Code)".to_string()),
+        &options(),
+    );
+    assert_eq!(info.sdk_version, env!("CARGO_PKG_VERSION"));
+
+    let json = serde_json::to_string(&Message::SdkInfo(info.clone())).unwrap();
+    match serde_json::from_str::<Message>(&json).unwrap() {
+        Message::SdkInfo(parsed) => assert_eq!(parsed, info),
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[test]
+fn test_sdk_info_filtered_with_system_messages() {
+    let message = Message::SdkInfo(SdkInfo::new(None, None, &ClaudeCodeOptions::new()));
+    assert!(MessageFilter::new()
+        .without_system_messages()
+        .apply(message.clone())
+        .is_none());
+    assert!(MessageFilter::new().apply(message).is_some());
+}
@@ -0,0 +1,93 @@
+use claude_code_sdk::sdk_mcp::{tool, SdkMcpServer, ToolOutput};
+use claude_code_sdk::ClaudeCodeOptions;
+use futures::executor::block_on;
+use serde_json::json;
+
+fn calculator() -> SdkMcpServer {
+    let divide = tool(
+        "divide",
+        "Divide a by b",
+        json!({"type": "object", "properties": {"a": {"type": "number"}, "b": {"type": "number"}}}),
+        |args| async move {
+            let (a, b) = (
+                args["a"].as_f64().unwrap_or(0.0),
+                args["b"].as_f64().unwrap_or(0.0),
+            );
+            if b == 0.0 {
+                ToolOutput::error("division by zero")
+            } else {
+                ToolOutput::text((a / b).to_string())
+            }
+        },
+    );
+    SdkMcpServer::new("calculator").with_tool(divide)
+}
+
+#[test]
+fn test_list_and_call_tools() {
+    let server = calculator();
+
+    let initialized =
+        block_on(server.handle(&json!({"jsonrpc": "2.0", "id": 0, "method": "initialize"})));
+    assert_eq!(initialized["result"]["serverInfo"]["name"], "calculator");
+
+    let listed =
+        block_on(server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"})));
+    assert_eq!(listed["id"], 1);
+    assert_eq!(listed["result"]["tools"][0]["name"], "divide");
+    assert_eq!(
+        listed["result"]["tools"][0]["inputSchema"]["type"],
+        "object"
+    );
+
+    let called = block_on(server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {"name": "divide", "arguments": {"a": 6, "b": 4}},
+    })));
+    assert_eq!(
+        called["result"],
+        json!({"content": [{"type": "text", "text": "1.5"}]})
+    );
+
+    let failed = block_on(server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {"name": "divide", "arguments": {"a": 1, "b": 0}},
+    })));
+    assert_eq!(failed["result"]["isError"], true);
+}
+
+#[test]
+fn test_unknown_methods_and_tools() {
+    let server = calculator();
+
+    let response =
+        block_on(server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "resources/list"})));
+    assert_eq!(response["error"]["code"], -32601);
+
+    let response = block_on(server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {"name": "multiply"},
+    })));
+    assert_eq!(response["error"]["code"], -32602);
+
+    let response =
+        block_on(server.handle(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"})));
+    assert_eq!(response["result"], json!({}));
+}
+
+#[test]
+fn test_server_names_must_be_unique() {
+    let options = ClaudeCodeOptions::new()
+        .with_sdk_mcp_server(calculator())
+        .with_sdk_mcp_server(SdkMcpServer::new("calculator"));
+    assert!(options.validate().is_err());
+
+    let options = ClaudeCodeOptions::new().with_sdk_mcp_server(calculator());
+    assert!(options.validate().is_ok());
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::pool::{Priority, SessionPool};
+use claude_code_sdk::{query, query_local, query_with_handle, ClaudeCodeOptions, QueryHandle};
+
+fn assert_send<T: Send>(_: &T) {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_handles_are_send_sync() {
+    assert_send_sync::<QueryHandle>();
+    assert_send_sync::<claude_code_sdk::ClaudeSDKClient>();
+    assert_send_sync::<ClaudeCodeOptions>();
+    assert_send_sync::<SessionPool>();
+    assert_send_sync::<claude_code_sdk::pool::PooledQuery>();
+    assert_send_sync::<claude_code_sdk::ClaudeSDKError>();
+}
+
+#[test]
+fn test_query_futures_are_send() {
+    assert_send(&query("prompt", None));
+    assert_send(&query_with_handle("prompt", None));
+    let pool = SessionPool::new(1);
+    assert_send(&pool.submit("prompt", None, Priority::Normal).start());
+}
+
+#[tokio::test]
+async fn test_query_local_runs_on_local_set() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            // The stream can live in non-Send state such as an Rc
+            let result = tokio::task::spawn_local(async {
+                std::rc::Rc::new(query_local("prompt", None).await)
+            })
+            .await;
+            assert!(result.is_ok());
+        })
+        .await;
+}
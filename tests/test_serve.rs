@@ -0,0 +1,116 @@
+#![cfg(feature = "serve")]
+
+use claude_code_sdk::serve::serve;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines};
+
+async fn run(input: &str) -> Vec<Value> {
+    let (writer, mut output) = tokio::io::duplex(64 * 1024);
+    serve(input.as_bytes(), writer).await.unwrap();
+    let mut text = String::new();
+    output.read_to_string(&mut text).await.unwrap();
+    text.lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+async fn send(input: &mut DuplexStream, request: Value) {
+    input
+        .write_all(format!("{}\n", request).as_bytes())
+        .await
+        .unwrap();
+}
+
+async fn next_response(output: &mut Lines<BufReader<DuplexStream>>) -> Value {
+    serde_json::from_str(&output.next_line().await.unwrap().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_serve_rejects_bad_requests() {
+    let responses = run(concat!(
+        "not json\n",
+        "\n",
+        r#"{"id":1,"method":"explode"}"#,
+        "\n",
+        r#"{"id":2,"method":"cancel","params":{"id":99}}"#,
+        "\n",
+        r#"{"id":3,"method":"query","params":{}}"#,
+        "\n",
+    ))
+    .await;
+
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0]["type"], "error");
+    assert_eq!(responses[0]["id"], Value::Null);
+    assert_eq!(responses[1]["id"], 1);
+    assert_eq!(responses[1]["message"], "Unknown method: explode");
+    assert_eq!(responses[2]["id"], 2);
+    assert_eq!(responses[3]["id"], 3);
+    assert!(responses[3]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Invalid params"));
+}
+
+#[tokio::test]
+async fn test_serve_reports_query_errors_and_stops_on_shutdown() {
+    let query = json!({
+        "id": "q1",
+        "method": "query",
+        "params": {
+            "prompt": "Review",
+            "options": { "context_files": ["/nonexistent/claude-sdk-serve-test.rs"] }
+        }
+    });
+    let input = format!(
+        "{}\n{}\n{}\n",
+        query,
+        json!({"id": 2, "method": "shutdown"}),
+        json!({"id": 3, "method": "explode"})
+    );
+    let responses = run(&input).await;
+
+    // The query fails before the CLI is spawned; nothing after shutdown is read
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0]["type"], "error");
+    assert_eq!(responses[0]["id"], "q1");
+    assert_eq!(responses[0]["code"], "invalid_options");
+}
+
+#[tokio::test]
+async fn test_serve_cancel_after_query_finished() {
+    let (mut input, reader) = tokio::io::duplex(64 * 1024);
+    let (writer, output) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(serve(BufReader::new(reader), writer));
+    let mut output = BufReader::new(output).lines();
+
+    send(
+        &mut input,
+        json!({
+            "id": "q1",
+            "method": "query",
+            "params": {
+                "prompt": "Review",
+                "options": { "context_files": ["/nonexistent/claude-sdk-serve-test.rs"] }
+            }
+        }),
+    )
+    .await;
+    let finished = next_response(&mut output).await;
+    assert_eq!(finished["id"], "q1");
+
+    send(
+        &mut input,
+        json!({"id": 2, "method": "cancel", "params": {"id": "q1"}}),
+    )
+    .await;
+    let cancel = next_response(&mut output).await;
+    // The query already sent its last response, so it is not cancelled too
+    assert_eq!(cancel["type"], "error");
+    assert_eq!(cancel["id"], 2);
+    assert_eq!(cancel["message"], "No running query with that id");
+
+    send(&mut input, json!({"id": 3, "method": "shutdown"})).await;
+    server.await.unwrap().unwrap();
+    assert_eq!(output.next_line().await.unwrap(), None);
+}
@@ -0,0 +1,147 @@
+use claude_code_sdk::speech::{SentenceSplitter, TextChunk};
+use claude_code_sdk::ClaudeCodeOptions;
+
+#[test]
+fn test_splitter_holds_back_incomplete_sentences() {
+    let mut splitter = SentenceSplitter::new();
+    assert_eq!(
+        splitter.push("It's sunny (mostly.) Want more? Sure"),
+        vec!["It's sunny (mostly.)", "Want more?"]
+    );
+    assert_eq!(splitter.push("!"), Vec::<String>::new());
+    assert_eq!(splitter.push("\nNext line"), vec!["Sure!".to_string()]);
+    assert_eq!(splitter.finish().as_deref(), Some("Next line"));
+    assert_eq!(splitter.finish(), None);
+}
+
+#[test]
+fn test_splitter_keeps_abbreviations_and_numbers() {
+    let mut splitter = SentenceSplitter::new();
+    let sentences =
+        splitter.push("1. Ask Dr. Smith, e.g. by mail. Pi is 3.14 today. 天気は晴れです。明日も");
+    assert_eq!(
+        sentences,
+        vec![
+            "1. Ask Dr. Smith, e.g. by mail.",
+            "Pi is 3.14 today.",
+            "天気は晴れです。",
+        ]
+    );
+    assert_eq!(splitter.finish().as_deref(), Some("明日も"));
+}
+
+#[test]
+fn test_text_chunk_json() {
+    assert_eq!(
+        serde_json::to_value(TextChunk::Sentence("Hello.".to_string())).unwrap(),
+        serde_json::json!({"type": "sentence", "text": "Hello."})
+    );
+    let options = ClaudeCodeOptions::new().on_text_chunk(|_| {});
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert!(restored.text_chunk_callback.is_none());
+}
+
+#[cfg(feature = "subprocess")]
+mod spoken {
+    use super::*;
+    use claude_code_sdk::transport::MockTransport;
+    use claude_code_sdk::{
+        query, AssistantMessage, ClaudeSDKError, Message, ResultMessage, TextBlock,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    fn sentence(text: &str) -> TextChunk {
+        TextChunk::Sentence(text.to_string())
+    }
+
+    /// The chunks spoken while reading `mock`'s script, and the options
+    /// recording them.
+    fn recorded(mock: &MockTransport) -> (Arc<Mutex<Vec<TextChunk>>>, ClaudeCodeOptions) {
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        let seen = chunks.clone();
+        let options = ClaudeCodeOptions::new()
+            .on_text_chunk(move |chunk| seen.lock().unwrap().push(chunk.clone()))
+            .with_transport_factory(mock.factory());
+        (chunks, options)
+    }
+
+    async fn read_all(options: ClaudeCodeOptions) {
+        let _: Vec<_> = query("Say hi", Some(options))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_turn_is_spoken_in_sentences_without_subagents() {
+        let mut subagent = AssistantMessage::new(vec![TextBlock::new("Searching files.").into()]);
+        subagent.parent_tool_use_id = Some("toolu_1".to_string());
+        let mock = MockTransport::new().with_messages([
+            Message::assistant_text("Hello there. How are"),
+            subagent.into(),
+            Message::assistant_text("you?"),
+            ResultMessage::new("run-1").into(),
+        ]);
+        let (chunks, options) = recorded(&mock);
+        read_all(options).await;
+        assert_eq!(
+            *chunks.lock().unwrap(),
+            vec![
+                TextChunk::TurnStart,
+                sentence("Hello there."),
+                sentence("How are"),
+                sentence("you?"),
+                TextChunk::TurnEnd,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_turn_is_interrupted() {
+        // Cancelled by the CLI
+        let mock = MockTransport::new().with_messages([
+            Message::assistant_text("Let me check."),
+            ResultMessage {
+                canceled: Some(true),
+                ..ResultMessage::new("run-1")
+            }
+            .into(),
+        ]);
+        let (chunks, options) = recorded(&mock);
+        read_all(options).await;
+        assert_eq!(
+            *chunks.lock().unwrap(),
+            vec![
+                TextChunk::TurnStart,
+                sentence("Let me check."),
+                TextChunk::Interrupted
+            ]
+        );
+
+        // The stream fails
+        let mock = MockTransport::new()
+            .with_message(Message::assistant_text("Let me check."))
+            .with_error(|| ClaudeSDKError::cli_connection("CLI crashed"));
+        let (chunks, options) = recorded(&mock);
+        read_all(options).await;
+        assert_eq!(chunks.lock().unwrap().last(), Some(&TextChunk::Interrupted));
+
+        // The stream is dropped mid-turn
+        let mock = MockTransport::new()
+            .with_message(Message::assistant_text("Let me check."))
+            .with_delay(Duration::from_secs(30))
+            .with_message(ResultMessage::new("run-1"));
+        let (chunks, options) = recorded(&mock);
+        let mut stream = query("Say hi", Some(options)).await.unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            chunks.lock().unwrap().last(),
+            Some(&sentence("Let me check."))
+        );
+        drop(stream);
+        assert_eq!(chunks.lock().unwrap().last(), Some(&TextChunk::Interrupted));
+    }
+}
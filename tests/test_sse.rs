@@ -0,0 +1,65 @@
+use claude_code_sdk::api_error::ApiErrorKind;
+use claude_code_sdk::sse::{message_events, to_sse_events};
+use claude_code_sdk::{
+    AssistantMessage, ClaudeSDKError, ContentBlock, Message, SystemMessage, TextBlock, ToolUseBlock,
+};
+use futures::stream::{self, StreamExt};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_assistant_message_events() {
+    let message = Message::Assistant(AssistantMessage::new(vec![
+        ContentBlock::Text(TextBlock::new("Checking")),
+        ContentBlock::ToolUse(ToolUseBlock::new(
+            "toolu_1",
+            "Bash",
+            json!({"command": "ls"}),
+        )),
+    ]));
+    let messages = stream::iter(vec![
+        Ok(Message::System(SystemMessage::new("init"))),
+        Ok(message),
+    ]);
+
+    let events: Vec<_> = to_sse_events(messages).collect().await;
+    let names: Vec<_> = events.iter().map(|e| e.event).collect();
+    assert_eq!(
+        names,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop",
+        ]
+    );
+    assert_eq!(
+        events[2].data,
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Checking"}})
+    );
+    assert_eq!(
+        events[5].data["delta"],
+        json!({"type": "input_json_delta", "partial_json": "{\"command\":\"ls\"}"})
+    );
+    assert_eq!(events[7].data["delta"]["stop_reason"], "tool_use");
+}
+
+#[test]
+fn test_error_event() {
+    let error = ClaudeSDKError::Api {
+        kind: ApiErrorKind::Overloaded,
+        message: "Overloaded".to_string(),
+        retry_after: None,
+    };
+
+    let events = message_events(Err(error));
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].to_string(),
+        "event: error\ndata: {\"error\":{\"message\":\"Overloaded\",\"type\":\"overloaded_error\"},\"type\":\"error\"}\n\n"
+    );
+}
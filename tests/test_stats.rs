@@ -0,0 +1,66 @@
+use claude_code_sdk::stats::{ExpHistogram, StatsRegistry};
+use claude_code_sdk::ClaudeCodeOptions;
+use std::time::Duration;
+
+#[test]
+fn test_histogram_percentiles_within_bucket_error() {
+    let mut histogram = ExpHistogram::new();
+    assert_eq!(histogram.quantile(0.5), None);
+    for value in 1..=1000 {
+        histogram.record(f64::from(value));
+    }
+    histogram.record(f64::NAN);
+
+    let percentiles = histogram.percentiles();
+    assert_eq!(percentiles.count, 1000);
+    assert_eq!(percentiles.max, 1000.0);
+    assert!((percentiles.mean - 500.5).abs() < 1e-9);
+    for (actual, expected) in [
+        (percentiles.p50, 500.0),
+        (percentiles.p95, 950.0),
+        (percentiles.p99, 990.0),
+    ] {
+        assert!(
+            (actual - expected).abs() / expected < 0.05,
+            "{} vs {}",
+            actual,
+            expected
+        );
+    }
+
+    // Costs are far below one and zero costs are kept apart
+    let mut costs = ExpHistogram::new();
+    for cost in [0.0, 0.0042, 0.0042, 0.0125] {
+        costs.record(cost);
+    }
+    assert_eq!(costs.quantile(0.25), Some(0.0));
+    assert!((costs.quantile(0.5).unwrap() - 0.0042).abs() < 0.0042 * 0.05);
+    assert_eq!(costs.quantile(1.0), Some(0.0125));
+}
+
+#[test]
+fn test_registry_records_and_resets() {
+    let registry = StatsRegistry::new();
+    registry.record_spawn(Duration::from_millis(40));
+    registry.record_first_token(Duration::from_millis(800));
+    registry.record_turn(Duration::from_secs(3));
+    registry.record_cost(0.02);
+
+    // Clones share the histograms
+    let stats = registry.clone().stats();
+    assert_eq!(stats.spawn_ms.count, 1);
+    assert_eq!(stats.first_token_ms.max, 800.0);
+    assert_eq!(stats.turn_duration_ms.p99, 3000.0);
+    assert_eq!(stats.cost_usd.p50, 0.02);
+
+    registry.reset();
+    assert_eq!(registry.stats(), Default::default());
+
+    let options = ClaudeCodeOptions::new().with_stats_registry(registry.clone());
+    options.stats().record_cost(0.5);
+    assert_eq!(registry.stats().cost_usd.count, 1);
+    assert!(std::ptr::eq(
+        ClaudeCodeOptions::new().stats(),
+        StatsRegistry::global()
+    ));
+}
@@ -0,0 +1,105 @@
+#[cfg(all(feature = "subprocess", unix))]
+#[path = "common/mod.rs"]
+mod common;
+
+use claude_code_sdk::stderr_log::StderrLog;
+use claude_code_sdk::{ClaudeCodeOptions, RunId};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_path_for_run() {
+    let run_id = RunId::new();
+    let log = StderrLog::new("logs").with_max_files(0);
+    assert_eq!(log.max_files, 1);
+    assert_eq!(
+        log.path_for(run_id),
+        PathBuf::from("logs").join(format!("claude-stderr-{}.log", run_id))
+    );
+}
+
+#[test]
+fn test_stderr_log_option_roundtrip() {
+    let options = ClaudeCodeOptions::new().with_stderr_log(StderrLog::new("/var/log/claude"));
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert_eq!(restored.stderr_log, Some(StderrLog::new("/var/log/claude")));
+}
+
+#[test]
+fn test_stderr_callback_is_not_serialized() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let seen = lines.clone();
+    let options = ClaudeCodeOptions::new()
+        .on_stderr_line(move |line| seen.lock().unwrap().push(line.to_string()));
+    let callback = options.stderr_callback.clone().unwrap();
+    callback("Error: API key invalid");
+    assert_eq!(*lines.lock().unwrap(), vec!["Error: API key invalid"]);
+
+    let restored = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert!(restored.stderr_callback.is_none());
+}
+
+#[cfg(all(feature = "subprocess", unix))]
+#[tokio::test]
+async fn test_rotation_keeps_max_files_and_foreign_logs() {
+    use claude_code_sdk::{query, Message, ResultMessage};
+    use tokio_stream::StreamExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cli = common::fake_cli(
+        dir.path(),
+        &format!(
+            "echo 'Starting' >&2\n{}",
+            common::print(&[Message::from(ResultMessage::new("run-1"))])
+        ),
+    );
+
+    let logs = dir.path().join("logs");
+    std::fs::create_dir(&logs).unwrap();
+    std::fs::write(logs.join("server.log"), "not ours").unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::write(logs.join(format!("claude-stderr-{}.log", name)), name).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let sdk_logs = || {
+        let mut names: Vec<String> = std::fs::read_dir(&logs)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("claude-stderr-"))
+            .collect();
+        names.sort();
+        names
+    };
+
+    let run = |log: StderrLog| {
+        let run_id = RunId::new();
+        let options = ClaudeCodeOptions::new()
+            .with_cli_path(&cli)
+            .with_run_id(run_id)
+            .with_stderr_log(log);
+        async move {
+            let items: Vec<_> = query("Hello", Some(options)).await.unwrap().collect().await;
+            assert!(items.iter().all(|item| item.is_ok()));
+            run_id
+        }
+    };
+
+    let run_id = run(StderrLog::new(&logs).with_max_files(2)).await;
+    // The newest old log and the new one remain
+    let path = StderrLog::new(&logs).path_for(run_id);
+    let names = sdk_logs();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"claude-stderr-c.log".to_string()));
+    assert!(path.exists());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Starting\n");
+
+    // A limit of zero from deserialized options still keeps the new log
+    let run_id = run(StderrLog {
+        dir: logs.clone(),
+        max_files: 0,
+    })
+    .await;
+    assert_eq!(sdk_logs().len(), 1);
+    assert!(StderrLog::new(&logs).path_for(run_id).exists());
+    assert!(logs.join("server.log").exists());
+}
@@ -0,0 +1,135 @@
+use claude_code_sdk::tool_policy::{ToolCategory, ToolPolicy};
+use claude_code_sdk::ClaudeCodeOptions;
+
+#[test]
+fn test_tool_category_of() {
+    assert_eq!(ToolCategory::of("Bash"), Some(ToolCategory::Execute));
+    assert_eq!(
+        ToolCategory::of("Edit(src/**)"),
+        Some(ToolCategory::FileWrite)
+    );
+    assert_eq!(ToolCategory::of("WebFetch"), Some(ToolCategory::Network));
+    assert_eq!(ToolCategory::of("mcp__github__create_issue"), None);
+}
+
+#[test]
+fn test_disable_categories() {
+    let policy = ToolPolicy::disable_categories(&[ToolCategory::Network, ToolCategory::Execute])
+        .with_disabled_tool("Task");
+
+    assert_eq!(
+        policy.disallowed_tools(),
+        vec![
+            "WebFetch",
+            "WebSearch",
+            "Bash",
+            "BashOutput",
+            "KillShell",
+            "Task"
+        ]
+    );
+    assert!(!policy.is_allowed("Bash(git status)"));
+    assert!(policy.is_allowed("Read"));
+    assert!(policy.is_allowed("mcp__github__create_issue"));
+}
+
+#[test]
+fn test_tool_policy_applies_to_options() {
+    let options = ClaudeCodeOptions::new()
+        .with_allowed_tools(vec![
+            "Read".to_string(),
+            "Write(docs/**)".to_string(),
+            "WebSearch".to_string(),
+        ])
+        .with_disallowed_tools(vec!["WebSearch".to_string()])
+        .with_tool_policy(ToolPolicy::disable_categories(&[
+            ToolCategory::Network,
+            ToolCategory::FileWrite,
+        ]));
+
+    assert_eq!(options.allowed_tools, Some(vec!["Read".to_string()]));
+    assert_eq!(
+        options.disallowed_tools,
+        Some(vec![
+            "WebSearch".to_string(),
+            "WebFetch".to_string(),
+            "Edit".to_string(),
+            "MultiEdit".to_string(),
+            "Write".to_string(),
+            "NotebookEdit".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn test_analysis_only_policy() {
+    let policy = ToolPolicy::analysis_only();
+    assert!(policy.is_allowed("Read"));
+    assert!(policy.is_allowed("WebFetch"));
+    assert!(!policy.is_allowed("Write"));
+    assert!(!policy.is_allowed("Bash(git status:*)"));
+    assert_eq!(
+        claude_code_sdk::tool_policy::ANALYSIS_ONLY,
+        cfg!(feature = "analysis-only")
+    );
+}
+
+#[cfg(feature = "analysis-only")]
+#[test]
+fn test_analysis_only_build_rejects_write_tools() {
+    use claude_code_sdk::{ClaudeSDKError, PermissionMode};
+
+    let options = ClaudeCodeOptions::new().with_allowed_tools(vec!["Read".to_string()]);
+    assert!(options.validate().is_ok());
+
+    let options = ClaudeCodeOptions::new()
+        .with_allowed_tools(vec!["Read".to_string(), "Bash(ls:*)".to_string()]);
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::ToolDisabled { tool }) if tool == "Bash(ls:*)"
+    ));
+
+    let options = ClaudeCodeOptions::new().with_permission_mode(PermissionMode::AcceptEdits);
+    assert!(matches!(
+        options.validate(),
+        Err(ClaudeSDKError::UnsupportedOption { option, .. }) if option == "permission_mode"
+    ));
+}
+
+#[cfg(feature = "analysis-only")]
+#[test]
+fn test_analysis_only_build_disallows_write_tools() {
+    use claude_code_sdk::compat::{check, CliVersion};
+    use claude_code_sdk::protocol::cli_command;
+    use claude_code_sdk::ClaudeSDKError;
+
+    let options = ClaudeCodeOptions::new()
+        .with_cli_path("claude-code")
+        .with_disallowed_tools(vec!["WebSearch".to_string()]);
+    let command = cli_command(&options, "Hello", true).unwrap();
+    let args: Vec<_> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let flag = args
+        .iter()
+        .position(|arg| arg == "--disallowedTools")
+        .unwrap();
+    let tools: Vec<_> = args[flag + 1].split(',').collect();
+    assert_eq!(tools[0], "WebSearch");
+    assert!(tools.contains(&"Bash"));
+    assert!(tools.contains(&"Write"));
+    assert_eq!(
+        args.iter()
+            .filter(|arg| *arg == "--disallowedTools")
+            .count(),
+        1
+    );
+
+    // A CLI without the flag is refused rather than run unrestricted
+    let mut options = ClaudeCodeOptions::new();
+    assert!(matches!(
+        check(&mut options, CliVersion::new(0, 2, 80)),
+        Err(ClaudeSDKError::UnsupportedOption { option, .. }) if option == "disallowed_tools"
+    ));
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::transport::{MockTransport, SubprocessCLITransport, Transport};
+use claude_code_sdk::{
+    query, AssistantMessage, ClaudeCodeOptions, ClaudeSDKClient, ClaudeSDKError, Message,
+    MessageFilter, PromptInput, ResultMessage, SystemMessage, TextBlock,
+};
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn test_streaming_input_needs_a_connection() {
+    let transport = SubprocessCLITransport::streaming("Hello", ClaudeCodeOptions::new());
+    assert!(!transport.is_connected());
+    assert!(transport.input().is_none());
+
+    let error = transport.write_message("And then?").await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::CLIConnection { .. }));
+    // Closing an input that was never opened does nothing
+    transport.close_input().await;
+
+    let transport = SubprocessCLITransport::new("Hello", ClaudeCodeOptions::new());
+    assert!(transport.write_message("And then?").await.is_err());
+}
+
+fn answer(text: &str) -> Message {
+    AssistantMessage::new(vec![TextBlock::new(text).into()]).into()
+}
+
+#[tokio::test]
+async fn test_query_replays_mock_transport() {
+    let mock = MockTransport::new()
+        .with_message(SystemMessage::new("init"))
+        .with_message(answer("4"))
+        .with_error(|| ClaudeSDKError::process(1, "boom"))
+        .with_message(ResultMessage::new("run-1"));
+    let options = ClaudeCodeOptions::new()
+        .with_transport_factory(mock.factory())
+        .with_message_filter(MessageFilter::assistant_and_result());
+
+    let items: Vec<_> = query("What is 2 + 2?", Some(options))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 3);
+    assert!(matches!(&items[0], Ok(Message::Assistant(_))));
+    assert!(matches!(
+        items[1].as_ref().unwrap_err(),
+        ClaudeSDKError::Process { exit_code: 1, .. }
+    ));
+    assert!(matches!(&items[2], Ok(Message::Result(_))));
+    assert_eq!(mock.prompts(), vec![PromptInput::from("What is 2 + 2?")]);
+    assert_eq!(mock.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_client_conversation_over_mock_transport() {
+    let mock = MockTransport::new()
+        .with_messages(vec![answer("Hi"), ResultMessage::new("turn-1").into()])
+        .with_messages(vec![answer("Bye"), ResultMessage::new("turn-2").into()]);
+    // No CLI is needed to connect with a custom transport
+    let mut client =
+        ClaudeSDKClient::connect(ClaudeCodeOptions::new().with_transport_factory(mock.factory()))
+            .await
+            .unwrap();
+
+    for expected in ["Hi", "Bye"] {
+        client.send_message(expected).await.unwrap();
+        let turn: Vec<Message> = client
+            .receive_response()
+            .collect::<claude_code_sdk::Result<_>>()
+            .await
+            .unwrap();
+        assert_eq!(turn.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&turn[0]).unwrap(),
+            serde_json::to_value(answer(expected)).unwrap()
+        );
+    }
+    // The mock has no process to report on
+    assert_eq!(client.disconnect().await.unwrap(), None);
+    assert_eq!(
+        mock.prompts(),
+        vec![PromptInput::from("Hi"), PromptInput::from("Bye")]
+    );
+}
+
+#[tokio::test]
+async fn test_disconnect_without_a_process() {
+    // Nothing was started, so nothing ended
+    let mut transport = SubprocessCLITransport::streaming("Hello", ClaudeCodeOptions::new());
+    assert_eq!(transport.disconnect().await.unwrap(), None);
+}
+
+#[cfg(unix)]
+#[path = "common/mod.rs"]
+mod common;
+
+#[cfg(unix)]
+mod process {
+    use super::common::fake_cli;
+    use super::*;
+    use claude_code_sdk::Termination;
+    use std::time::{Duration, Instant};
+
+    /// Connect a streaming transport to a CLI running `body`, and disconnect
+    /// it again.
+    async fn connect_and_disconnect(
+        body: &str,
+        timeout: Duration,
+    ) -> (claude_code_sdk::ProcessExit, Duration) {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ClaudeCodeOptions::new()
+            .with_cli_path(fake_cli(dir.path(), body))
+            .with_shutdown_timeout(timeout);
+        let mut transport = SubprocessCLITransport::streaming("Hello", options);
+        transport.connect().await.unwrap();
+
+        let started = Instant::now();
+        let exit = transport.disconnect().await.unwrap().unwrap();
+        // The exit is only reported once
+        assert_eq!(transport.disconnect().await.unwrap(), None);
+        (exit, started.elapsed())
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_after_exit_on_eof() {
+        let (exit, elapsed) = connect_and_disconnect(
+            "while read -r line; do :; done\nexit 0",
+            Duration::from_secs(5),
+        )
+        .await;
+        assert_eq!(exit.forced, None);
+        assert!(exit.is_clean());
+        assert!(elapsed < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_terminates_a_process_ignoring_eof() {
+        let (exit, _) = connect_and_disconnect(
+            "trap 'exit 0' TERM\nwhile :; do sleep 0.1; done",
+            Duration::from_millis(300),
+        )
+        .await;
+        assert_eq!(exit.forced, Some(Termination::Terminated));
+        // Exiting successfully after SIGTERM still took force
+        assert!(exit.status.success());
+        assert!(!exit.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_kills_a_process_ignoring_sigterm() {
+        let (exit, elapsed) = connect_and_disconnect(
+            "trap '' TERM\nwhile :; do sleep 0.1; done",
+            Duration::from_millis(300),
+        )
+        .await;
+        assert_eq!(exit.forced, Some(Termination::Killed));
+        assert_eq!(exit.status.code(), None);
+        assert!(!exit.is_clean());
+        // Once for the input to close, once more after SIGTERM
+        assert!(elapsed >= Duration::from_millis(600));
+    }
+}
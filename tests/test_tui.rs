@@ -0,0 +1,125 @@
+#![cfg(feature = "tui")]
+
+use claude_code_sdk::progress::ToolProgressEvent;
+use claude_code_sdk::tui::{Dashboard, EntryKind, ToolStatus};
+use claude_code_sdk::{
+    AssistantMessage, ClaudeSDKError, ResultMessage, StreamEvent, TextBlock, ToolResultBlock,
+    ToolUseBlock, UserMessage,
+};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+
+fn sample_dashboard() -> Dashboard {
+    let mut dashboard = Dashboard::new("Run the tests");
+    dashboard.push(
+        &AssistantMessage::new(vec![
+            TextBlock::new("Running cargo test").into(),
+            ToolUseBlock::new(
+                "toolu_1",
+                "Bash",
+                serde_json::json!({"command": "cargo test"}),
+            )
+            .into(),
+        ])
+        .into(),
+    );
+    dashboard.push_progress(&ToolProgressEvent {
+        tool_use_id: "toolu_1".to_string(),
+        tool_name: Some("Bash".to_string()),
+        output: Some("Compiling foo\nRunning 3 tests\n".to_string()),
+        message: None,
+        progress: None,
+    });
+    dashboard
+}
+
+#[test]
+fn test_dashboard_tracks_messages_and_tools() {
+    let mut dashboard = sample_dashboard();
+    assert_eq!(dashboard.entries().len(), 2);
+    assert_eq!(dashboard.entries()[0].kind, EntryKind::User);
+    assert_eq!(dashboard.tools()[0].status, ToolStatus::Running);
+    assert_eq!(
+        dashboard.tools()[0].detail.as_deref(),
+        Some("Running 3 tests")
+    );
+
+    dashboard.push(
+        &UserMessage::new(vec![ToolResultBlock::new(
+            "toolu_1",
+            Some("ok"),
+            Some(false),
+        )
+        .into()])
+        .into(),
+    );
+    assert_eq!(dashboard.tools()[0].status, ToolStatus::Succeeded);
+
+    let mut result = ResultMessage::new("r1");
+    result.tokens_input = Some(120);
+    result.cost_usd = Some(0.01);
+    dashboard.push(&result.into());
+    assert!(dashboard.is_finished());
+    assert_eq!(dashboard.usage().tokens_input, 120);
+}
+
+#[test]
+fn test_dashboard_records_errors() {
+    let mut dashboard = Dashboard::new("prompt");
+    dashboard.push_result(&Err(ClaudeSDKError::cli_connection("broken pipe")));
+    assert!(dashboard.is_finished());
+    assert_eq!(dashboard.entries()[1].kind, EntryKind::Error);
+}
+
+#[test]
+fn test_dashboard_shows_partial_text() {
+    let delta = |text: &str| {
+        StreamEvent::new(serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": text },
+        }))
+        .into()
+    };
+    let mut dashboard = Dashboard::new("prompt");
+    dashboard.push(&StreamEvent::new(serde_json::json!({ "type": "message_start" })).into());
+    dashboard.push(&delta("All tests "));
+    dashboard.push(&delta("pass"));
+    assert_eq!(dashboard.partial_text(), Some("All tests pass"));
+    assert_eq!(dashboard.entries().len(), 1);
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| dashboard.render(frame)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("All tests pass"));
+
+    // The whole message replaces the streamed text
+    dashboard.push(&AssistantMessage::new(vec![TextBlock::new("All tests pass.").into()]).into());
+    assert_eq!(dashboard.partial_text(), None);
+    assert_eq!(dashboard.entries()[1].text, "All tests pass.");
+}
+
+#[test]
+fn test_dashboard_renders() {
+    let dashboard = sample_dashboard();
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| dashboard.render(frame)).unwrap();
+
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Run the tests"));
+    assert!(screen.contains("Running cargo test"));
+    assert!(screen.contains("Bash"));
+    assert!(screen.contains("running"));
+}
@@ -0,0 +1,56 @@
+use claude_code_sdk::turn_retry::{TurnFailure, TurnFailureKind, TurnRetry, TurnRetryPolicy};
+use claude_code_sdk::{ClaudeCodeOptions, PromptInput};
+
+fn failure(kind: TurnFailureKind, attempt: u32) -> TurnFailure {
+    TurnFailure {
+        kind,
+        attempt,
+        detail: None,
+    }
+}
+
+fn concise(_: &TurnFailure, prompt: &PromptInput) -> Option<PromptInput> {
+    Some(PromptInput::Text(format!(
+        "{} Be concise.",
+        prompt.text_content()
+    )))
+}
+
+#[test]
+fn test_adapt_is_bounded_by_attempts() {
+    let retry = TurnRetry::new(TurnRetryPolicy::new(2), concise);
+    let prompt = PromptInput::Text("Explain lib.rs.".to_string());
+
+    let adapted = retry.adapt(&failure(TurnFailureKind::Empty, 1), &prompt);
+    assert_eq!(
+        adapted.unwrap().text_content(),
+        "Explain lib.rs. Be concise."
+    );
+    assert!(retry
+        .adapt(&failure(TurnFailureKind::Error, 2), &prompt)
+        .is_some());
+    assert!(retry
+        .adapt(&failure(TurnFailureKind::Error, 3), &prompt)
+        .is_none());
+}
+
+#[test]
+fn test_adapt_respects_policy_and_adapter() {
+    let policy = TurnRetryPolicy {
+        retry_refusals: false,
+        ..TurnRetryPolicy::default()
+    };
+    let retry = TurnRetry::new(policy, concise);
+    let prompt = PromptInput::Text("Delete everything.".to_string());
+    assert!(retry
+        .adapt(&failure(TurnFailureKind::Refused, 1), &prompt)
+        .is_none());
+
+    // The adapter can give up too
+    let options = ClaudeCodeOptions::new().with_turn_retry(TurnRetryPolicy::default(), |_, _| None);
+    let retry = options.turn_retry.unwrap();
+    assert!(retry
+        .adapt(&failure(TurnFailureKind::Empty, 1), &prompt)
+        .is_none());
+    assert_eq!(TurnFailureKind::Refused.to_string(), "was refused");
+}
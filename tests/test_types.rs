@@ -1,6 +1,16 @@
+use claude_code_sdk::prompt::MediaSource;
 use claude_code_sdk::types::*;
+#[allow(clippy::single_component_path_imports)]
 use serde_json;
 
+/// The `type` tag a value is serialized with.
+fn tag<T: serde::Serialize>(value: T) -> String {
+    serde_json::to_value(value).unwrap()["type"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
 #[test]
 fn test_permission_mode_serialization() {
     let default_mode = PermissionMode::Default;
@@ -33,13 +43,13 @@ fn test_permission_mode_deserialization() {
 fn test_text_block_creation() {
     let block = TextBlock::new("Hello, world!");
 
-    assert_eq!(block.block_type, "text");
+    assert_eq!(tag(ContentBlock::from(block.clone())), "text");
     assert_eq!(block.text, "Hello, world!");
 }
 
 #[test]
 fn test_text_block_serialization() {
-    let block = TextBlock::new("Test text");
+    let block = ContentBlock::from(TextBlock::new("Test text"));
     let json = serde_json::to_string(&block).unwrap();
 
     assert!(json.contains("\"type\":\"text\""));
@@ -51,7 +61,7 @@ fn test_tool_use_block_creation() {
     let input = serde_json::json!({"param": "value"});
     let block = ToolUseBlock::new("tool-123", "read_file", input.clone());
 
-    assert_eq!(block.block_type, "tool_use");
+    assert_eq!(tag(ContentBlock::from(block.clone())), "tool_use");
     assert_eq!(block.id, "tool-123");
     assert_eq!(block.name, "read_file");
     assert_eq!(block.input, input);
@@ -61,7 +71,7 @@ fn test_tool_use_block_creation() {
 fn test_tool_result_block_creation() {
     let block = ToolResultBlock::new("tool-123", Some("File contents"), Some(false));
 
-    assert_eq!(block.block_type, "tool_result");
+    assert_eq!(tag(ContentBlock::from(block.clone())), "tool_result");
     assert_eq!(block.tool_use_id, "tool-123");
     assert_eq!(block.content, Some("File contents".to_string()));
     assert_eq!(block.is_error, Some(false));
@@ -73,7 +83,7 @@ fn test_user_message_creation() {
     let content = vec![ContentBlock::Text(text_block)];
     let message = UserMessage::new(content);
 
-    assert_eq!(message.message_type, "user");
+    assert_eq!(tag(Message::from(message.clone())), "user");
     assert_eq!(message.content.len(), 1);
 }
 
@@ -83,7 +93,7 @@ fn test_assistant_message_creation() {
     let content = vec![ContentBlock::Text(text_block)];
     let message = AssistantMessage::new(content);
 
-    assert_eq!(message.message_type, "assistant");
+    assert_eq!(tag(Message::from(message.clone())), "assistant");
     assert_eq!(message.content.len(), 1);
 }
 
@@ -91,7 +101,7 @@ fn test_assistant_message_creation() {
 fn test_system_message_creation() {
     let message = SystemMessage::new("You are a helpful assistant");
 
-    assert_eq!(message.message_type, "system");
+    assert_eq!(tag(Message::from(message.clone())), "system");
     assert_eq!(message.content, "You are a helpful assistant");
 }
 
@@ -99,7 +109,7 @@ fn test_system_message_creation() {
 fn test_result_message_creation() {
     let message = ResultMessage::new("result-123");
 
-    assert_eq!(message.message_type, "result");
+    assert_eq!(tag(Message::from(message.clone())), "result");
     assert_eq!(message.id, "result-123");
     assert_eq!(message.exit_code, None);
     assert_eq!(message.content, None);
@@ -153,10 +163,422 @@ fn test_message_from_user() {
     let user_message = UserMessage::new(content);
     let message: Message = user_message.into();
 
+    assert_eq!(tag(&message), "user");
     match message {
         Message::User(msg) => {
-            assert_eq!(msg.message_type, "user");
+            assert_eq!(msg.content.len(), 1);
         }
         _ => panic!("Expected User variant"),
     }
 }
+
+#[test]
+fn test_run_id() {
+    let first = claude_code_sdk::RunId::new();
+    let second = claude_code_sdk::RunId::new();
+    assert_ne!(first, second);
+
+    let json = serde_json::to_string(&first).unwrap();
+    assert_eq!(json, format!("\"{}\"", first));
+
+    let options = ClaudeCodeOptions::new().with_run_id(first);
+    assert_eq!(options.run_id, Some(first));
+}
+
+#[test]
+fn test_options_json_compact() {
+    assert_eq!(
+        serde_json::to_string(&ClaudeCodeOptions::new()).unwrap(),
+        "{}"
+    );
+
+    let options = ClaudeCodeOptions::new()
+        .with_system_prompt("Be terse")
+        .with_max_turns(3);
+    assert_eq!(
+        options.to_json_compact().unwrap(),
+        r#"{"max_turns":3,"system_prompt":"Be terse","version":1}"#
+    );
+
+    let loaded = ClaudeCodeOptions::from_json(&options.to_json_compact().unwrap()).unwrap();
+    assert_eq!(loaded.system_prompt, options.system_prompt);
+    assert_eq!(loaded.max_turns, Some(3));
+}
+
+#[test]
+fn test_options_from_json_versions() {
+    // Unversioned configs and fields from newer versions still load
+    let legacy = ClaudeCodeOptions::from_json(r#"{"max_turns":2,"log_level":null}"#).unwrap();
+    assert_eq!(legacy.max_turns, Some(2));
+
+    let newer = ClaudeCodeOptions::from_json(r#"{"version":7,"max_turns":4,"future_option":true}"#)
+        .unwrap();
+    assert_eq!(newer.max_turns, Some(4));
+
+    assert!(ClaudeCodeOptions::from_json(r#"{"version":"one"}"#).is_err());
+    assert!(ClaudeCodeOptions::from_json("[]").is_err());
+}
+
+#[cfg(feature = "subprocess")]
+#[test]
+fn test_raw_tap_option() {
+    let (writer, _reader) = tokio::io::duplex(64);
+    let options = ClaudeCodeOptions::new().with_raw_tap(writer);
+
+    assert!(options.raw_tap.is_some());
+    assert_eq!(serde_json::to_string(&options).unwrap(), "{}");
+}
+
+#[test]
+fn test_max_parallel_tools() {
+    let options = ClaudeCodeOptions::new().with_max_parallel_tools(2);
+    assert_eq!(options.max_parallel_tools, Some(2));
+    assert!(options.validate().is_ok());
+    assert!(options
+        .to_json_compact()
+        .unwrap()
+        .contains(r#""max_parallel_tools":2"#));
+
+    let error = ClaudeCodeOptions::new()
+        .with_max_parallel_tools(0)
+        .validate()
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        claude_code_sdk::ClaudeSDKError::InvalidOptions { .. }
+    ));
+}
+
+#[test]
+fn test_generation_parameters() {
+    let options = ClaudeCodeOptions {
+        claude_max_tokens: Some(4096),
+        ..Default::default()
+    };
+    assert!(options.validate().is_ok());
+
+    let options = ClaudeCodeOptions {
+        claude_max_tokens: Some(0),
+        ..Default::default()
+    };
+    assert!(matches!(
+        options.validate(),
+        Err(claude_code_sdk::ClaudeSDKError::InvalidOptions { .. })
+    ));
+
+    let options = ClaudeCodeOptions {
+        claude_temperature: Some(0.2),
+        ..Default::default()
+    };
+    let error = options.validate().unwrap_err();
+    assert!(matches!(
+        &error,
+        claude_code_sdk::ClaudeSDKError::UnsupportedOption { option, .. }
+            if option == "claude_temperature"
+    ));
+
+    let options = ClaudeCodeOptions {
+        claude_stop_sequences: Some(vec!["END".to_string()]),
+        ..Default::default()
+    };
+    assert!(matches!(
+        options.validate(),
+        Err(claude_code_sdk::ClaudeSDKError::UnsupportedOption { option, .. })
+            if option == "claude_stop_sequences"
+    ));
+}
+
+#[test]
+fn test_message_fixtures() {
+    let call = Message::tool_use("Bash", serde_json::json!({ "command": "ls" }));
+    let Message::Assistant(msg) = &call else {
+        panic!("expected assistant message, got {:?}", call);
+    };
+    let ContentBlock::ToolUse(tool_use) = &msg.content[0] else {
+        panic!("expected tool use, got {:?}", msg.content[0]);
+    };
+    assert_eq!(tool_use.name, "Bash");
+    assert!(tool_use.id.starts_with("toolu_"));
+    let again = Message::tool_use("Bash", serde_json::json!({ "command": "ls" }));
+    let other = Message::tool_use("Bash", serde_json::json!({ "command": "pwd" }));
+    assert_eq!(
+        serde_json::to_string(&call).unwrap(),
+        serde_json::to_string(&again).unwrap()
+    );
+    assert_ne!(
+        serde_json::to_string(&call).unwrap(),
+        serde_json::to_string(&other).unwrap()
+    );
+
+    let Message::User(msg) = Message::tool_error(&tool_use.id, "not found") else {
+        panic!("expected user message");
+    };
+    assert!(matches!(
+        &msg.content[0],
+        ContentBlock::ToolResult(result) if result.is_error == Some(true) && result.tool_use_id == tool_use.id
+    ));
+}
+
+#[test]
+fn test_result_fixture_round_trips() {
+    let json = serde_json::to_string(&Message::result()).unwrap();
+    let Message::Result(result) = serde_json::from_str(&json).unwrap() else {
+        panic!("expected result message");
+    };
+    assert_eq!(result.exit_code, Some(0));
+    assert_eq!(result.session_id.as_deref(), Some("session_fixture"));
+    assert!(result.tokens_input.is_some() && result.tokens_output.is_some());
+
+    let json = serde_json::to_string(&Message::system("init")).unwrap();
+    assert!(matches!(
+        serde_json::from_str(&json).unwrap(),
+        Message::System(msg) if msg.content == "init"
+    ));
+}
+
+#[test]
+fn test_usage_cache_tokens() {
+    let mut usage = Usage::default();
+    assert_eq!(usage.cache_hit_ratio(), None);
+
+    let result: ResultMessage = serde_json::from_str(
+        r#"{"type":"result","id":"r1","tokens_input":100,"tokens_output":20,"cache_read_tokens":300,"cache_creation_tokens":100}"#,
+    )
+    .unwrap();
+    usage.add_result(&result);
+    assert_eq!(usage.cache_read_tokens, 300);
+    assert_eq!(usage.cache_creation_tokens, 100);
+    assert_eq!(usage.cache_hit_ratio(), Some(0.6));
+
+    // Totals saved before the cache counts existed still load
+    let usage: Usage = serde_json::from_str(
+        r#"{"tokens_input":1,"tokens_output":2,"reasoning_tokens":0,"cost_usd":0.1}"#,
+    )
+    .unwrap();
+    assert_eq!(usage.cache_read_tokens, 0);
+}
+
+/// Output of `claude -p --output-format stream-json --verbose`, as recorded
+/// from the CLI.
+const RECORDED_STREAM: &str = r#"{"type":"system","subtype":"init","cwd":"/home/dev/app","session_id":"9b3c1f0e-5d2a-4f8e-a1c7-2e6d8f4b0a13","tools":["Task","Bash","Glob","Grep","Read","Edit","Write"],"mcp_servers":[{"name":"github","status":"connected"}],"model":"claude-sonnet-4-20250514","permissionMode":"default","apiKeySource":"ANTHROPIC_API_KEY"}
+{"type":"assistant","message":{"id":"msg_01XkVw7bN3","role":"assistant","model":"claude-sonnet-4-20250514","content":[{"type":"tool_use","id":"toolu_01Ab","name":"Bash","input":{"command":"ls"}}],"stop_reason":null,"usage":{"input_tokens":4,"cache_creation_input_tokens":1620,"cache_read_input_tokens":13410,"output_tokens":57}},"parent_tool_use_id":null,"session_id":"9b3c1f0e-5d2a-4f8e-a1c7-2e6d8f4b0a13"}
+{"type":"user","message":{"role":"user","content":[{"tool_use_id":"toolu_01Ab","type":"tool_result","content":"Cargo.toml\nsrc","is_error":false}]},"parent_tool_use_id":null,"session_id":"9b3c1f0e-5d2a-4f8e-a1c7-2e6d8f4b0a13"}
+{"type":"assistant","message":{"id":"msg_01Yq2c","role":"assistant","model":"claude-sonnet-4-20250514","content":[{"type":"text","text":"There is a manifest and a src directory."}],"stop_reason":"end_turn","usage":{"input_tokens":8,"output_tokens":12}},"parent_tool_use_id":null,"session_id":"9b3c1f0e-5d2a-4f8e-a1c7-2e6d8f4b0a13"}
+{"type":"result","subtype":"success","is_error":false,"duration_ms":5123,"duration_api_ms":4800,"num_turns":2,"result":"There is a manifest and a src directory.","session_id":"9b3c1f0e-5d2a-4f8e-a1c7-2e6d8f4b0a13","total_cost_usd":0.0213,"usage":{"input_tokens":12,"cache_creation_input_tokens":1620,"cache_read_input_tokens":13410,"output_tokens":69}}"#;
+
+/// `value` without its null fields, which are not written back out.
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, without_nulls(value)))
+            .collect(),
+        serde_json::Value::Array(items) => items.into_iter().map(without_nulls).collect(),
+        value => value,
+    }
+}
+
+#[test]
+fn test_recorded_stream_json_round_trips() {
+    for line in RECORDED_STREAM.lines() {
+        let message: Message = serde_json::from_str(line).unwrap();
+        let original: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            without_nulls(original),
+            "{}",
+            line
+        );
+    }
+}
+
+#[test]
+fn test_recorded_stream_json_fields() {
+    let messages: Vec<Message> = RECORDED_STREAM
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let Message::System(init) = &messages[0] else {
+        panic!("Expected the init message, got {:?}", messages[0]);
+    };
+    assert_eq!(init.subtype.as_deref(), Some("init"));
+    assert_eq!(init.content, "init");
+    assert_eq!(init.tools.as_ref().map(Vec::len), Some(7));
+    assert_eq!(init.mcp_servers.as_ref().unwrap()[0].status, "connected");
+    assert_eq!(init.permission_mode.as_deref(), Some("default"));
+
+    let Message::Assistant(tool_call) = &messages[1] else {
+        panic!("Expected an assistant message, got {:?}", messages[1]);
+    };
+    assert!(matches!(&tool_call.content[0], ContentBlock::ToolUse(block) if block.name == "Bash"));
+    assert_eq!(tool_call.model.as_deref(), Some("claude-sonnet-4-20250514"));
+    assert_eq!(
+        tool_call.usage.as_ref().unwrap().cache_read_input_tokens,
+        Some(13410)
+    );
+    assert!(matches!(&messages[2], Message::User(user) if user.session_id == init.session_id));
+
+    let Message::Result(result) = &messages[4] else {
+        panic!("Expected the result, got {:?}", messages[4]);
+    };
+    assert_eq!(result.exit_code, Some(0));
+    assert_eq!(result.cost_usd, Some(0.0213));
+    assert_eq!(result.tokens_output, Some(69));
+    assert_eq!(result.cache_creation_tokens, Some(1620));
+    assert_eq!(
+        result.content.as_deref(),
+        Some("There is a manifest and a src directory.")
+    );
+}
+
+#[test]
+fn test_earlier_message_forms_still_read() {
+    // Prompts are echoed with plain string content, tool output as parts
+    let message: Message = serde_json::from_str(
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"a"},{"type":"text","text":"b"}]}]}}"#,
+    )
+    .unwrap();
+    let Message::User(user) = message else {
+        panic!("Expected a user message");
+    };
+    assert!(
+        matches!(&user.content[0], ContentBlock::ToolResult(block) if block.content.as_deref() == Some("a\nb"))
+    );
+
+    // The flat form written by earlier SDK versions
+    let message: Message = serde_json::from_str(
+        r#"{"type":"result","id":"r1","exit_code":2,"content":"failed","cost_usd":0.5,"tokens_input":10,"tokens_output":3}"#,
+    )
+    .unwrap();
+    let Message::Result(result) = &message else {
+        panic!("Expected a result message");
+    };
+    assert_eq!(result.exit_code, Some(2));
+    assert_eq!(result.tokens_input, Some(10));
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        serde_json::json!({
+            "type": "result",
+            "id": "r1",
+            "exit_code": 2,
+            "result": "failed",
+            "total_cost_usd": 0.5,
+            "usage": { "input_tokens": 10, "output_tokens": 3 },
+        })
+    );
+    assert!(serde_json::from_str::<Message>(r#"{"type":"assistant"}"#).is_err());
+}
+
+#[test]
+fn test_thinking_blocks() {
+    let message: Message = serde_json::from_str(
+        r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"thinking","thinking":"The user wants a sum.","signature":"EqQB"},{"type":"redacted_thinking","data":"EmwKAhgB"},{"type":"text","text":"4"}]}}"#,
+    )
+    .unwrap();
+    let Message::Assistant(assistant) = &message else {
+        panic!("Expected an assistant message");
+    };
+    assert!(matches!(
+        &assistant.content[0],
+        ContentBlock::Thinking(block)
+            if block.thinking == "The user wants a sum." && block.signature == "EqQB"
+    ));
+    assert!(
+        matches!(&assistant.content[1], ContentBlock::RedactedThinking(block) if block.data == "EmwKAhgB")
+    );
+    assert_eq!(tag(&assistant.content[1]), "redacted_thinking");
+    assert_eq!(
+        tag(ContentBlock::from(ThinkingBlock::new("hmm"))),
+        "thinking"
+    );
+}
+
+#[test]
+fn test_max_thinking_tokens_option() {
+    let options = ClaudeCodeOptions::new().with_max_thinking_tokens(8_000);
+    assert_eq!(options.max_thinking_tokens, Some(8_000));
+    assert!(options.validate().is_ok());
+    assert!(ClaudeCodeOptions::new()
+        .with_max_thinking_tokens(MIN_THINKING_TOKENS - 1)
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn test_image_blocks() {
+    let message: Message = serde_json::from_str(
+        r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"What is this?"},{"type":"image","source":{"type":"base64","media_type":"image/png","data":"iVBORw0KGgo="}}]}}"#,
+    )
+    .unwrap();
+    let Message::User(user) = &message else {
+        panic!("Expected a user message");
+    };
+    assert!(matches!(
+        &user.content[1],
+        ContentBlock::Image(ImageBlock { source: MediaSource::Base64 { media_type, .. } })
+            if media_type == "image/png"
+    ));
+
+    // Screenshots taken by tools come as image parts of the result
+    let line = r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"Captured"},{"type":"image","source":{"type":"base64","media_type":"image/jpeg","data":"/9j/"}}]}]}}"#;
+    let message: Message = serde_json::from_str(line).unwrap();
+    let Message::User(user) = &message else {
+        panic!("Expected a user message");
+    };
+    let ContentBlock::ToolResult(result) = &user.content[0] else {
+        panic!("Expected a tool result");
+    };
+    assert_eq!(result.content.as_deref(), Some("Captured"));
+    assert_eq!(
+        result.images,
+        vec![ImageBlock::base64("image/jpeg", "/9j/")]
+    );
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        serde_json::from_str::<serde_json::Value>(line).unwrap()
+    );
+}
+
+#[test]
+fn test_user_message_with_image() {
+    let dir = tempfile::tempdir().unwrap();
+    let png = dir.path().join("screen.PNG");
+    std::fs::write(&png, [0x89, b'P', b'N', b'G']).unwrap();
+
+    let message = UserMessage::new(vec![TextBlock::new("Why is it blank?").into()])
+        .with_image(&png)
+        .unwrap();
+    assert!(matches!(
+        &message.content[1],
+        ContentBlock::Image(image) if *image == ImageBlock::base64("image/png", "iVBORw==")
+    ));
+
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "text").unwrap();
+    assert!(UserMessage::new(Vec::new()).with_image(&notes).is_err());
+}
+
+#[test]
+fn test_stream_event_messages() {
+    let message: Message = serde_json::from_str(
+        r#"{"type":"stream_event","uuid":"u1","session_id":"s1","parent_tool_use_id":null,"event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}}"#,
+    )
+    .unwrap();
+    let Message::StreamEvent(event) = &message else {
+        panic!("expected a stream event, got {:?}", message);
+    };
+    assert_eq!(event.event_type(), Some("content_block_delta"));
+    assert_eq!(event.index(), Some(0));
+    assert_eq!(event.text_delta(), Some("Hel"));
+    assert_eq!(event.thinking_delta(), None);
+    assert_eq!(event.session_id.as_deref(), Some("s1"));
+    assert_eq!(tag(&message), "stream_event");
+
+    let start = StreamEvent::new(serde_json::json!({ "type": "message_start" }));
+    assert_eq!(start.text_delta(), None);
+
+    let options = ClaudeCodeOptions::new().with_include_partial_messages(true);
+    assert_eq!(options.include_partial_messages, Some(true));
+}
@@ -0,0 +1,50 @@
+use claude_code_sdk::output::{OutputParser, StreamJsonParser};
+use claude_code_sdk::update::{UpdateEvent, UpdateEventKind};
+use claude_code_sdk::ClaudeCodeOptions;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_parse_update_lines() {
+    let available = UpdateEvent::from_line("Update available: 1.0.30 → 1.0.35").unwrap();
+    assert_eq!(available.kind, UpdateEventKind::Available);
+    assert_eq!(available.current_version.as_deref(), Some("1.0.30"));
+    assert_eq!(available.latest_version.as_deref(), Some("1.0.35"));
+
+    let prompt = UpdateEvent::from_line("Install update v1.0.35 now? (y/n)").unwrap();
+    assert_eq!(prompt.kind, UpdateEventKind::Prompt);
+    assert_eq!(prompt.current_version, None);
+    assert_eq!(prompt.latest_version.as_deref(), Some("1.0.35"));
+
+    let failed = UpdateEvent::from_line("✗ Auto-update failed · Try claude doctor").unwrap();
+    assert_eq!(failed.kind, UpdateEventKind::Failed);
+
+    let json = UpdateEvent::from_line(
+        r#"{"type":"update_available","current_version":"1.0.30","latest_version":"1.0.35"}"#,
+    )
+    .unwrap();
+    assert_eq!(json.kind, UpdateEventKind::Available);
+
+    assert!(UpdateEvent::from_line(r#"{"type":"system","content":"update available"}"#).is_none());
+    assert!(UpdateEvent::from_line("Compiling claude-code-sdk").is_none());
+}
+
+#[test]
+fn test_parser_reports_update_notices() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    let options = ClaudeCodeOptions::new()
+        .without_auto_update()
+        .on_update_notice(move |event| seen.lock().unwrap().push(event.kind));
+    let mut parser = StreamJsonParser::new(&options);
+
+    assert!(parser
+        .parse_line("A new version of Claude Code is available: 1.0.35")
+        .unwrap()
+        .is_empty());
+    let messages = parser
+        .parse_line(r#"{"type":"system","content":"init"}"#)
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(*events.lock().unwrap(), vec![UpdateEventKind::Available]);
+    assert_eq!(options.disable_auto_update, Some(true));
+}
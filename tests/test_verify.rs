@@ -0,0 +1,70 @@
+#[cfg(feature = "subprocess")]
+use claude_code_sdk::verify::CommandVerifier;
+use claude_code_sdk::verify::{verify_fn, Verification, Verifier};
+#[cfg(feature = "subprocess")]
+use claude_code_sdk::ClaudeCodeOptions;
+use std::path::Path;
+
+#[tokio::test]
+async fn test_fn_verifier() {
+    let verifier = verify_fn(|workspace: &Path| match workspace.join("done").exists() {
+        true => Verification::Passed,
+        false => Verification::failed("done is missing"),
+    });
+    let dir = tempfile::tempdir().unwrap();
+
+    assert_eq!(
+        verifier.verify(dir.path()).await.unwrap(),
+        Verification::failed("done is missing")
+    );
+    std::fs::write(dir.path().join("done"), "").unwrap();
+    assert!(verifier.verify(dir.path()).await.unwrap().passed());
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_command_verifier_runs_in_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("marker"), "").unwrap();
+
+    let verifier = CommandVerifier::new("sh").args(["-c", "test -f marker"]);
+    assert_eq!(
+        verifier.verify(dir.path()).await.unwrap(),
+        Verification::Passed
+    );
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_command_verifier_reports_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let verifier = CommandVerifier::new("sh").args(["-c", "echo 'test foo ... FAILED'; exit 3"]);
+
+    let Verification::Failed { feedback } = verifier.verify(dir.path()).await.unwrap() else {
+        panic!("expected a failure");
+    };
+    assert!(feedback.starts_with("`sh -c echo 'test foo ... FAILED'; exit 3` failed"));
+    assert!(feedback.contains("test foo ... FAILED"));
+}
+
+#[cfg(feature = "subprocess")]
+#[tokio::test]
+async fn test_command_verifier_missing_program() {
+    let dir = tempfile::tempdir().unwrap();
+    let verifier = CommandVerifier::new("definitely-not-a-real-program");
+    assert!(verifier.verify(dir.path()).await.is_err());
+}
+
+#[cfg(feature = "subprocess")]
+#[test]
+fn test_options_with_verifier() {
+    let options = ClaudeCodeOptions::new()
+        .with_verifier(CommandVerifier::cargo_test())
+        .with_max_fix_attempts(2);
+    assert!(options.verifier.is_some());
+    assert_eq!(options.max_fix_attempts, Some(2));
+
+    let json = options.to_json_compact().unwrap();
+    assert!(!json.contains("verifier"));
+    assert!(json.contains(r#""max_fix_attempts":2"#));
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "webhooks")]
+
+use claude_code_sdk::webhook::{sign, WebhookEvent, WebhookNotifier, SIGNATURE_HEADER};
+use claude_code_sdk::{ClaudeSDKError, RetryPolicy, RunId};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve one HTTP request per status in `statuses`, returning the raw requests.
+async fn serve(statuses: Vec<u16>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read until the headers and the full JSON body have arrived
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if n == 0 || (text.contains("\r\n\r\n") && text.trim_end().ends_with('}')) {
+                    break;
+                }
+            }
+            requests.push(String::from_utf8_lossy(&request).into_owned());
+            let response = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    });
+    (url, server)
+}
+
+#[test]
+fn test_sign() {
+    // RFC 4231, test case 2
+    assert_eq!(
+        sign(b"Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn test_payload() {
+    let event = WebhookEvent::RunFailed {
+        run_id: RunId::new(),
+        error: "boom".to_string(),
+    };
+    let payload: serde_json::Value =
+        serde_json::from_slice(&WebhookNotifier::payload(&event).unwrap()).unwrap();
+    assert_eq!(payload["event"], "run_failed");
+    assert_eq!(payload["error"], "boom");
+    assert!(payload["timestamp"].as_u64().is_some());
+}
+
+#[tokio::test]
+async fn test_notify_retries_and_signs() {
+    let (url, server) = serve(vec![503, 200]).await;
+    let notifier = WebhookNotifier::new(url)
+        .with_secret("s3cret")
+        .with_retry_policy(RetryPolicy::new(2).with_initial_delay(Duration::from_millis(10)));
+
+    let event = WebhookEvent::RunStarted {
+        run_id: RunId::new(),
+        prompt: "Fix the build".to_string(),
+    };
+    notifier.notify(&event).await.unwrap();
+
+    let requests = server.await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let request = &requests[1];
+    let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+    let header = format!(
+        "{}: {}",
+        SIGNATURE_HEADER.to_lowercase(),
+        sign(b"s3cret", body.as_bytes())
+    );
+    assert!(request.to_lowercase().contains(&header));
+    assert!(body.contains(r#""event":"run_started""#));
+}
+
+#[tokio::test]
+async fn test_notify_gives_up_on_client_errors() {
+    let (url, server) = serve(vec![404]).await;
+    let notifier = WebhookNotifier::new(url);
+
+    let event = WebhookEvent::RunFailed {
+        run_id: RunId::new(),
+        error: "boom".to_string(),
+    };
+    let error = notifier.notify(&event).await.unwrap_err();
+    assert!(matches!(error, ClaudeSDKError::Webhook { .. }));
+    assert_eq!(server.await.unwrap().len(), 1);
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "websocket")]
+
+use claude_code_sdk::transport::{Transport, WebSocketTransport};
+use claude_code_sdk::{ClaudeCodeOptions, Message};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message as Frame;
+
+#[tokio::test]
+async fn test_websocket_transport_round_trip() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let prompt = socket.next().await.unwrap().unwrap().into_text().unwrap();
+        socket
+            .send(Frame::Text(
+                r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"hi"}]}}
+{"type":"result","subtype":"success","session_id":"s1","result":"hi","is_error":false}"#
+                    .to_string(),
+            ))
+            .await
+            .unwrap();
+        socket.close(None).await.unwrap();
+        prompt
+    });
+
+    let mut transport = WebSocketTransport::new(url, "Say hi", ClaudeCodeOptions::new());
+    transport.connect().await.unwrap();
+    let messages: Vec<Message> = transport
+        .receive_messages()
+        .await
+        .unwrap()
+        .map(|message| message.unwrap())
+        .collect()
+        .await;
+    transport.disconnect().await.unwrap();
+
+    let prompt: serde_json::Value = serde_json::from_str(&server.await.unwrap()).unwrap();
+    assert_eq!(prompt["type"], "user");
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], Message::Assistant(_)));
+    match &messages[1] {
+        Message::Result(result) => assert_eq!(result.session_id.as_deref(), Some("s1")),
+        other => panic!("expected a result, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_websocket_connection_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let mut transport = WebSocketTransport::new(url, "Say hi", ClaudeCodeOptions::new());
+    assert!(transport.connect().await.is_err());
+    assert!(!transport.is_connected());
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "subprocess")]
+
+use claude_code_sdk::workspace_guard::{UnsafeWorkspaceReason, WorkspaceGuard};
+use claude_code_sdk::{ClaudeCodeOptions, ClaudeSDKError, PermissionMode};
+use std::process::Command;
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test]
+async fn test_guard_refuses_unversioned_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = ClaudeCodeOptions::new()
+        .with_cwd(dir.path())
+        .with_permission_mode(PermissionMode::AcceptEdits);
+
+    match WorkspaceGuard::new().check(&options).await {
+        Err(ClaudeSDKError::UnsafeWorkspace { path, reason }) => {
+            assert_eq!(path, dir.path());
+            assert_eq!(reason, UnsafeWorkspaceReason::NotVersionControlled);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Confirmed edits and disabled guards are not checked
+    let default_mode = options
+        .clone()
+        .with_permission_mode(PermissionMode::Default);
+    assert!(WorkspaceGuard::new().check(&default_mode).await.is_ok());
+    assert!(WorkspaceGuard::disabled().check(&options).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_guard_uncommitted_changes_threshold() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        std::fs::write(dir.path().join(name), "changed").unwrap();
+    }
+    let options = ClaudeCodeOptions::new()
+        .with_cwd(dir.path())
+        .with_permission_mode(PermissionMode::BypassPermissions);
+
+    assert!(WorkspaceGuard::new().check(&options).await.is_ok());
+
+    let strict = WorkspaceGuard::new().with_max_uncommitted_changes(2);
+    match strict.check(&options).await {
+        Err(ClaudeSDKError::UnsafeWorkspace { reason, .. }) => assert_eq!(
+            reason,
+            UnsafeWorkspaceReason::UncommittedChanges {
+                changed: 3,
+                threshold: 2
+            }
+        ),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}